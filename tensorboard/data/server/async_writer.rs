@@ -0,0 +1,315 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Summary writer for async code, backed by a dedicated blocking writer task.
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::proto::tensorboard as pb;
+use crate::types::{Step, Tag, WallTime};
+use crate::writer::{self, SummaryWriteExt};
+
+/// A summary writer that can be used from async code without blocking the executor.
+///
+/// Events are sent over a bounded channel to a task on Tokio's blocking thread pool, which owns
+/// the underlying writer. Each `write_*` method returns once its event has been enqueued, waiting
+/// only if the queue is full. Methods take `&self`, so a writer may be shared across tasks (e.g.,
+/// in an `Arc`); events from concurrent tasks are written in the order in which they're enqueued.
+///
+/// If the writer task fails to write an event, it stops writing and the error is returned from
+/// the next call to any method on this writer. Subsequent calls fail with
+/// [`io::ErrorKind::BrokenPipe`].
+///
+/// Call [`Self::close`] to drain the queue, flush the underlying writer, and wait for the writer
+/// task to finish. If the `AsyncSummaryWriter` is simply dropped, the writer task still drains the
+/// queue in the background, but any errors are lost.
+#[derive(Debug)]
+pub struct AsyncSummaryWriter {
+    /// Sender for the writer task's queue.
+    tx: mpsc::Sender<Command>,
+    /// Error from the writer task that has not yet been returned to a caller.
+    error: Arc<Mutex<Option<io::Error>>>,
+    /// Handle to the writer task, which completes once all senders have been dropped and the
+    /// queue has been drained.
+    task: JoinHandle<()>,
+}
+
+/// A request to the writer task.
+#[derive(Debug)]
+enum Command {
+    /// Write an event to the underlying writer.
+    Event(Box<pb::Event>),
+    /// Flush the underlying writer, then signal the sender. On failure, the error is stored for
+    /// the caller instead, and only then is the sender dropped.
+    Flush(oneshot::Sender<()>),
+}
+
+impl AsyncSummaryWriter {
+    /// Creates a new async writer that writes events to `writer`, with room for `capacity` events
+    /// in the queue before callers must wait.
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is zero, or if called outside of a Tokio runtime.
+    pub fn new<W: Write + Send + 'static>(writer: W, capacity: usize) -> Self {
+        let (tx, rx) = mpsc::channel(capacity);
+        let error = Arc::new(Mutex::new(None));
+        let task = tokio::task::spawn_blocking({
+            let error = Arc::clone(&error);
+            move || run_writer(writer, rx, &error)
+        });
+        AsyncSummaryWriter { tx, error, task }
+    }
+
+    /// Enqueues an `Event` proto to be written.
+    pub async fn write_event(&self, event: pb::Event) -> io::Result<()> {
+        self.send(Command::Event(Box::new(event))).await
+    }
+
+    /// Enqueues a TF 1.x scalar event (`simple_value`) to be written.
+    pub async fn write_scalar(
+        &self,
+        tag: &Tag,
+        step: Step,
+        wt: WallTime,
+        value: f32,
+    ) -> io::Result<()> {
+        self.write_event(writer::scalar_event(tag, step, wt, value))
+            .await
+    }
+
+    /// Enqueues a TF 1.x `graph_def` event to be written.
+    pub async fn write_graph(&self, step: Step, wt: WallTime, bytes: Vec<u8>) -> io::Result<()> {
+        self.write_event(writer::graph_event(step, wt, bytes)).await
+    }
+
+    /// Enqueues a TF 1.x `tagged_run_metadata` event to be written.
+    pub async fn write_tagged_run_metadata(
+        &self,
+        tag: &Tag,
+        step: Step,
+        wt: WallTime,
+        run_metadata: Vec<u8>,
+    ) -> io::Result<()> {
+        let event = writer::tagged_run_metadata_event(tag, step, wt, run_metadata);
+        self.write_event(event).await
+    }
+
+    /// Waits until all previously enqueued events have been written, then flushes the underlying
+    /// writer.
+    pub async fn flush(&self) -> io::Result<()> {
+        let (done_tx, done_rx) = oneshot::channel();
+        self.send(Command::Flush(done_tx)).await?;
+        done_rx.await.map_err(|_| self.take_error())
+    }
+
+    /// Drains the queue, flushes the underlying writer, and waits for the writer task to finish.
+    pub async fn close(self) -> io::Result<()> {
+        let flushed = self.flush().await;
+        let AsyncSummaryWriter { tx, error, task } = self;
+        drop(tx);
+        task.await?;
+        flushed?;
+        let pending = error.lock().expect("writer error lock poisoned").take();
+        match pending {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Sends a command to the writer task, first surfacing any pending error.
+    async fn send(&self, command: Command) -> io::Result<()> {
+        if let Some(e) = self
+            .error
+            .lock()
+            .expect("writer error lock poisoned")
+            .take()
+        {
+            return Err(e);
+        }
+        self.tx.send(command).await.map_err(|_| self.take_error())
+    }
+
+    /// Takes the pending error from the writer task, or creates a new error if there is none
+    /// (i.e., the error has already been reported).
+    fn take_error(&self) -> io::Error {
+        self.error
+            .lock()
+            .expect("writer error lock poisoned")
+            .take()
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "writer task has exited"))
+    }
+}
+
+/// Body of the writer task: handles commands until the queue is closed or a write fails.
+fn run_writer<W: Write>(
+    mut writer: W,
+    mut rx: mpsc::Receiver<Command>,
+    error: &Mutex<Option<io::Error>>,
+) {
+    // A failed flush keeps its signal, so that its caller isn't woken before the error is stored.
+    let (result, failed_flush) = loop {
+        let (result, failed_flush) = match rx.blocking_recv() {
+            None => break (writer.flush(), None),
+            Some(Command::Event(event)) => (writer.write_event(&event), None),
+            Some(Command::Flush(done)) => match writer.flush() {
+                Ok(()) => {
+                    // The caller may have stopped waiting, which is fine.
+                    let _ = done.send(());
+                    (Ok(()), None)
+                }
+                Err(e) => (Err(e), Some(done)),
+            },
+        };
+        if result.is_err() {
+            break (result, failed_flush);
+        }
+    };
+    if let Err(e) = result {
+        // Close the queue and publish the error atomically with respect to callers, so that any
+        // caller whose send fails also sees the error. Only then drop the queue and the failed
+        // flush's signal, which wakes callers waiting on flushes that will never be handled.
+        let mut slot = error.lock().expect("writer error lock poisoned");
+        rx.close();
+        *slot = Some(e);
+        drop(slot);
+        drop(failed_flush);
+        drop(rx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::fs::File;
+    use std::io::BufWriter;
+
+    use crate::event_file::EventFileReader;
+
+    #[tokio::test]
+    async fn test_concurrent_writes() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let path = logdir.path().join("tfevents.123");
+        let writer = Arc::new(AsyncSummaryWriter::new(
+            BufWriter::new(File::create(&path)?),
+            16,
+        ));
+
+        let mut handles = Vec::new();
+        for t in 0..4 {
+            let writer = Arc::clone(&writer);
+            handles.push(tokio::spawn(async move {
+                let tag = Tag(format!("task{}", t));
+                for i in 0..1000 {
+                    let wt = WallTime::new(1234.0 + i as f64).unwrap();
+                    writer.write_scalar(&tag, Step(i), wt, i as f32).await?;
+                }
+                io::Result::Ok(())
+            }));
+        }
+        for handle in handles {
+            handle.await??;
+        }
+        writer.flush().await?;
+
+        // Everything should be on disk after the flush, even before closing.
+        let mut reader = EventFileReader::new(File::open(&path)?);
+        let mut seen = HashSet::new();
+        loop {
+            match reader.read_event() {
+                Ok(event) => {
+                    let tag = match event.what {
+                        Some(pb::event::What::Summary(mut sum)) => sum.value.remove(0).tag,
+                        other => panic!("unexpected event: {:?}", other),
+                    };
+                    seen.insert((tag, event.step));
+                }
+                Err(e) if e.truncated() => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let expected = (0..4)
+            .flat_map(|t| (0..1000).map(move |i| (format!("task{}", t), i)))
+            .collect::<HashSet<_>>();
+        assert_eq!(seen, expected);
+
+        Arc::try_unwrap(writer)
+            .expect("all tasks should have dropped their writers")
+            .close()
+            .await?;
+        Ok(())
+    }
+
+    /// A writer that fails every write.
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "disk on fire",
+            ))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_error_surfaces_on_flush() {
+        let writer = AsyncSummaryWriter::new(FailingWriter, 4);
+        let tag = Tag("loss".to_string());
+        let wt = WallTime::new(1234.0).unwrap();
+        writer.write_scalar(&tag, Step(0), wt, 0.5).await.unwrap();
+        let err = writer.flush().await.unwrap_err();
+        assert_eq!(err.to_string(), "disk on fire");
+        // The error has been reported once; later calls see that the task is gone.
+        let err = writer
+            .write_scalar(&tag, Step(1), wt, 0.5)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    /// A writer that accepts every write but fails to flush.
+    struct UnflushableWriter;
+
+    impl Write for UnflushableWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+        #[allow(clippy::io_other_error)] // `io::Error::other` needs Rust 1.74
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flush_error_surfaces_on_flush() {
+        let writer = AsyncSummaryWriter::new(UnflushableWriter, 4);
+        let tag = Tag("loss".to_string());
+        let wt = WallTime::new(1234.0).unwrap();
+        writer.write_scalar(&tag, Step(0), wt, 0.5).await.unwrap();
+        let err = writer.flush().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert_eq!(err.to_string(), "disk full");
+        let err = writer.close().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
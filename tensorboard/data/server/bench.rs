@@ -23,6 +23,7 @@ use std::time::Instant;
 use rustboard_core::commit::Commit;
 use rustboard_core::disk_logdir::DiskLogdir;
 use rustboard_core::logdir::LogdirLoader;
+use rustboard_core::run::SampleSizes;
 
 #[derive(Clap)]
 struct Opts {
@@ -38,6 +39,9 @@ struct Opts {
     no_checksum: bool,
     #[clap(long, multiple_occurrences = true, overrides_with = "no_checksum")]
     checksum: bool,
+    /// Keep every point instead of sampling time series. Memory use grows with the logdir.
+    #[clap(long)]
+    no_sampling: bool,
 }
 
 fn main() {
@@ -51,6 +55,9 @@ fn main() {
         opts.reload_threads.unwrap_or(0),
     );
     loader.checksum(opts.checksum); // if neither `--[no-]checksum` given, defaults to false
+    if opts.no_sampling {
+        loader.sample_sizes(SampleSizes::unlimited());
+    }
 
     info!("Starting load cycle");
     let start = Instant::now();
@@ -0,0 +1,453 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Load throughput measurement, and synthetic log directories to measure it on.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::commit::Commit;
+use crate::disk_logdir::DiskLogdir;
+use crate::logdir::{Logdir, LogdirLoader};
+use crate::proto::tensorboard as pb;
+use crate::run::SampleSizes;
+use crate::types::{Tag, WallTime};
+use crate::writer::SummaryWriteExt;
+
+/// Shape of a synthetic log directory: every run has the same scalar tags, and every tag has the
+/// same number of points. Written on the command line as `<runs>x<tags>x<points>`, optionally
+/// followed by `/<values per event>`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GenerateSpec {
+    pub runs: usize,
+    pub tags: usize,
+    pub points: usize,
+    /// Maximum number of scalars written in a single event, as loggers that batch all scalars
+    /// for a step do. Defaults to 1.
+    pub values_per_event: usize,
+}
+
+impl FromStr for GenerateSpec {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut halves = s.splitn(2, '/');
+        let shape = halves.next().unwrap_or_default();
+        let values_per_event = match halves.next() {
+            None => Some(1),
+            Some(n) => n.parse().ok().filter(|&n| n > 0),
+        };
+        let parts: Vec<&str> = shape.split('x').collect();
+        let nums: Option<Vec<usize>> = parts.iter().map(|p| p.parse().ok()).collect();
+        match (nums.as_deref(), values_per_event) {
+            (Some(&[runs, tags, points]), Some(values_per_event)) => Ok(GenerateSpec {
+                runs,
+                tags,
+                points,
+                values_per_event,
+            }),
+            _ => Err(format!(
+                "invalid log directory shape {:?}; expected <runs>x<tags>x<points>[/<values per \
+                 event>], like \"4x10x1000\" or \"1x500x100/500\"",
+                s
+            )),
+        }
+    }
+}
+
+/// Writes a synthetic log directory with the given shape under `dir`, which must exist.
+///
+/// The output is a deterministic function of `spec`: file names, steps, wall times, and values
+/// (from a fixed-seed random number generator) are the same on every machine, so load times on
+/// generated log directories are comparable. Each run has one event file, with all tags written
+/// at each step, in events of up to `spec.values_per_event` scalars each.
+pub fn generate(dir: &Path, spec: GenerateSpec) -> io::Result<()> {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let tags: Vec<Tag> = (0..spec.tags)
+        .map(|i| Tag(format!("tag_{:04}", i)))
+        .collect();
+    for run in 0..spec.runs {
+        let run_dir = dir.join(format!("run_{:04}", run));
+        fs::create_dir(&run_dir)?;
+        let mut file = BufWriter::new(File::create(run_dir.join("events.out.tfevents.0.bench"))?);
+        for step in 0..spec.points {
+            let wall_time = WallTime::new(1e9 + step as f64).unwrap();
+            for chunk in tags.chunks(spec.values_per_event.max(1)) {
+                let value = chunk
+                    .iter()
+                    .map(|tag| pb::summary::Value {
+                        tag: tag.0.clone(),
+                        value: Some(pb::summary::value::Value::SimpleValue(rng.gen())),
+                        ..Default::default()
+                    })
+                    .collect();
+                let event = pb::Event {
+                    step: step as i64,
+                    wall_time: wall_time.into(),
+                    what: Some(pb::event::What::Summary(pb::Summary {
+                        value,
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                };
+                file.write_event(&event)?;
+            }
+        }
+        file.into_inner()?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// A synthetic log directory in a fresh temporary directory, deleted on drop.
+pub struct GeneratedLogdir {
+    path: PathBuf,
+}
+
+impl GeneratedLogdir {
+    /// Generates a log directory with the given shape under the system temporary directory.
+    pub fn new(spec: GenerateSpec) -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("rustboard-bench-{}", std::process::id()));
+        fs::create_dir(&path)?;
+        let result = GeneratedLogdir { path };
+        generate(&result.path, spec)?;
+        Ok(result)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for GeneratedLogdir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Loader settings to measure.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LoadConfig {
+    /// Whether to checksum every record (see [`LogdirLoader::checksum`]).
+    pub checksum: bool,
+    /// Read buffer size per event file (see [`DiskLogdir::buffer_size`]).
+    pub buffer_size: usize,
+    /// Number of loader threads, or `0` to choose automatically.
+    pub threads: usize,
+    /// Whether to sample time series as the server does, rather than keeping every point (see
+    /// [`SampleSizes::unlimited`]).
+    pub sampling: bool,
+    /// Whether to stage scalar-only summaries on the fast path (see
+    /// [`LogdirLoader::scalar_fast_path`]).
+    pub scalar_fast_path: bool,
+}
+
+/// Load time for one [`LoadConfig`], averaged over some number of iterations.
+#[derive(Debug)]
+pub struct Measurement {
+    pub config: LoadConfig,
+    /// Mean time per load.
+    pub elapsed: Duration,
+    /// Number of events read per load.
+    pub events: usize,
+    /// Total size of the event files, in bytes.
+    pub bytes: u64,
+}
+
+impl Measurement {
+    pub fn events_per_sec(&self) -> f64 {
+        self.events as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn megabytes_per_sec(&self) -> f64 {
+        self.bytes as f64 / 1e6 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Fully loads `logdir` `iterations` times with the given settings, each time into a new commit.
+///
+/// Only the load cycles themselves are timed, not setting up the loader or dropping the commit.
+pub fn measure(logdir: &Path, config: LoadConfig, iterations: usize) -> io::Result<Measurement> {
+    let bytes = event_file_bytes(logdir)?;
+    let mut elapsed = Duration::default();
+    let mut events = 0;
+    for _ in 0..iterations {
+        let commit = Commit::new();
+        let mut disk_logdir = DiskLogdir::new(logdir.to_path_buf());
+        disk_logdir.buffer_size(config.buffer_size);
+        let mut loader = LogdirLoader::new(&commit, disk_logdir, config.threads);
+        loader.checksum(config.checksum);
+        if !config.sampling {
+            loader.sample_sizes(SampleSizes::unlimited());
+        }
+        loader.scalar_fast_path(config.scalar_fast_path);
+        let start = Instant::now();
+        events = loader.reload();
+        elapsed += start.elapsed();
+    }
+    Ok(Measurement {
+        config,
+        elapsed: elapsed / iterations.max(1) as u32,
+        events,
+        bytes,
+    })
+}
+
+/// Total size of all event files in a log directory, in bytes.
+fn event_file_bytes(logdir: &Path) -> io::Result<u64> {
+    let discoveries = DiskLogdir::new(logdir.to_path_buf()).discover()?;
+    let mut total = 0;
+    for filename in discoveries.values().flatten() {
+        total += fs::metadata(&filename.0)?.len();
+    }
+    Ok(total)
+}
+
+/// Formats measurements as a plain-text table, one row per configuration.
+pub fn format_table(measurements: &[Measurement]) -> String {
+    let mut out = format!(
+        "{:<9} {:>8} {:>8} {:>10} {:>10} {:>12} {:>10}\n",
+        "checksum", "buffer", "threads", "fast path", "time (s)", "events/s", "MB/s"
+    );
+    for m in measurements {
+        let threads = match m.config.threads {
+            0 => "auto".to_string(),
+            n => n.to_string(),
+        };
+        writeln!(
+            out,
+            "{:<9} {:>8} {:>8} {:>10} {:>10.3} {:>12.0} {:>10.1}",
+            if m.config.checksum { "on" } else { "off" },
+            m.config.buffer_size,
+            threads,
+            if m.config.scalar_fast_path {
+                "on"
+            } else {
+                "off"
+            },
+            m.elapsed.as_secs_f64(),
+            m.events_per_sec(),
+            m.megabytes_per_sec(),
+        )
+        .unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_generate_spec() {
+        assert_eq!(
+            "2x3x100".parse::<GenerateSpec>(),
+            Ok(GenerateSpec {
+                runs: 2,
+                tags: 3,
+                points: 100,
+                values_per_event: 1,
+            })
+        );
+        assert_eq!(
+            "1x500x10/500".parse::<GenerateSpec>(),
+            Ok(GenerateSpec {
+                runs: 1,
+                tags: 500,
+                points: 10,
+                values_per_event: 500,
+            })
+        );
+        for bad in &[
+            "2x3", "2x3x4x5", "2x3xlots", "", "2x3x4/0", "2x3x4/", "2/3x4",
+        ] {
+            bad.parse::<GenerateSpec>()
+                .expect_err(&format!("should reject {:?}", bad));
+        }
+    }
+
+    /// Reads all files under `dir` into a sorted list of relative paths and contents.
+    fn read_tree(dir: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+        let mut files: Vec<(PathBuf, Vec<u8>)> = walkdir::WalkDir::new(dir)
+            .into_iter()
+            .map(|e| e.unwrap())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| {
+                let relpath = e.path().strip_prefix(dir).unwrap().to_path_buf();
+                (relpath, fs::read(e.path()).unwrap())
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_generate_deterministic() -> Result<(), Box<dyn std::error::Error>> {
+        let spec = GenerateSpec {
+            runs: 2,
+            tags: 3,
+            points: 10,
+            values_per_event: 1,
+        };
+        let (dir1, dir2) = (tempfile::tempdir()?, tempfile::tempdir()?);
+        generate(dir1.path(), spec)?;
+        generate(dir2.path(), spec)?;
+        let (tree1, tree2) = (read_tree(dir1.path()), read_tree(dir2.path()));
+        assert_eq!(tree1.len(), 2);
+        assert_eq!(tree1, tree2);
+        // Runs differ from each other, since they draw different values.
+        assert_ne!(tree1[0].1, tree1[1].1);
+
+        let other_spec = GenerateSpec { points: 11, ..spec };
+        let dir3 = tempfile::tempdir()?;
+        generate(dir3.path(), other_spec)?;
+        assert_ne!(read_tree(dir3.path()), tree1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_batched() -> Result<(), Box<dyn std::error::Error>> {
+        let spec = GenerateSpec {
+            runs: 1,
+            tags: 5,
+            points: 3,
+            values_per_event: 2,
+        };
+        let dir = tempfile::tempdir()?;
+        generate(dir.path(), spec)?;
+        let config = LoadConfig {
+            checksum: true,
+            buffer_size: 1024,
+            threads: 1,
+            sampling: false,
+            scalar_fast_path: true,
+        };
+        // Each step's 5 tags are written as events of 2, 2, and 1 values.
+        let m = measure(dir.path(), config, 1)?;
+        assert_eq!(m.events, 3 * 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_measure() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let spec = GenerateSpec {
+            runs: 2,
+            tags: 3,
+            points: 10,
+            values_per_event: 1,
+        };
+        generate(dir.path(), spec)?;
+        let config = LoadConfig {
+            checksum: true,
+            buffer_size: 1024,
+            threads: 1,
+            sampling: false,
+            scalar_fast_path: true,
+        };
+        let m = measure(dir.path(), config, 2)?;
+        assert_eq!(m.events, 2 * 3 * 10);
+        assert_eq!(m.bytes, event_file_bytes(dir.path())?);
+        assert!(m.bytes > 0);
+
+        let table = format_table(&[m]);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("checksum"), "{}", table);
+        assert!(lines[1].starts_with("on "), "{}", table);
+        Ok(())
+    }
+
+    /// Compares load throughput with and without the scalar fast path. Ignored by default, since
+    /// timing is noisy on shared machines; run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_scalar_fast_path_throughput() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let spec = GenerateSpec {
+            runs: 4,
+            tags: 50,
+            points: 5000,
+            values_per_event: 50,
+        };
+        generate(dir.path(), spec)?;
+        let config = LoadConfig {
+            checksum: false,
+            buffer_size: 1 << 16,
+            threads: 1,
+            sampling: true,
+            scalar_fast_path: true,
+        };
+        let fast = measure(dir.path(), config, 5)?;
+        let slow = measure(
+            dir.path(),
+            LoadConfig {
+                scalar_fast_path: false,
+                ..config
+            },
+            5,
+        )?;
+        assert_eq!(fast.events, slow.events);
+        assert!(
+            fast.events_per_sec() > slow.events_per_sec(),
+            "{}",
+            format_table(&[fast, slow])
+        );
+        Ok(())
+    }
+
+    /// Checks that the same scalars load no slower when batched 500 to an event than when
+    /// written one per event, so per-event work like the duplicate tag check stays linear in the
+    /// number of values. Ignored by default, like [`test_scalar_fast_path_throughput`].
+    #[test]
+    #[ignore]
+    fn test_many_values_per_event_throughput() -> Result<(), Box<dyn std::error::Error>> {
+        let spec = GenerateSpec {
+            runs: 1,
+            tags: 500,
+            points: 400,
+            values_per_event: 1,
+        };
+        let config = LoadConfig {
+            checksum: false,
+            buffer_size: 1 << 16,
+            threads: 1,
+            sampling: true,
+            scalar_fast_path: true,
+        };
+        let (single_dir, batched_dir) = (tempfile::tempdir()?, tempfile::tempdir()?);
+        generate(single_dir.path(), spec)?;
+        generate(
+            batched_dir.path(),
+            GenerateSpec {
+                values_per_event: 500,
+                ..spec
+            },
+        )?;
+        let single = measure(single_dir.path(), config, 3)?;
+        let batched = measure(batched_dir.path(), config, 3)?;
+        assert_eq!(single.events, 500 * batched.events);
+        assert!(
+            batched.elapsed <= single.elapsed,
+            "{}",
+            format_table(&[single, batched])
+        );
+        Ok(())
+    }
+}
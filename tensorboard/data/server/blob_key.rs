@@ -42,9 +42,10 @@ pub struct BlobKey<'a> {
 }
 
 /// Helper struct to encode `BlobKey`s as tuples (rather than objects with named keys) and to use
-/// portable integers over the wire.
+/// portable integers over the wire. Strings are owned when decoding, since names with characters
+/// that JSON escapes (like the NUL that starts reserved tags) can't be borrowed from the input.
 #[derive(Debug, Serialize, Deserialize)]
-struct WireBlobKey<'a>(&'a str, &'a str, &'a str, i64, u64);
+struct WireBlobKey<S>(S, S, S, i64, u64);
 
 /// An error returned when parsing a `BlobKey`.
 #[derive(Debug, thiserror::Error)]
@@ -62,13 +63,13 @@ impl<'a> FromStr for BlobKey<'a> {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let buf = base64::decode_config(s, BASE_64_CONFIG).map_err(ParseBlobKeyError::BadBase64)?;
-        let WireBlobKey(experiment_id, run, tag, step, index) =
+        let WireBlobKey::<String>(experiment_id, run, tag, step, index) =
             serde_json::from_slice(&buf).map_err(ParseBlobKeyError::BadJson)?;
         let index = usize::try_from(index).map_err(|_| ParseBlobKeyError::BadIndex(index))?;
         Ok(BlobKey {
-            experiment_id: Cow::Owned(experiment_id.into()),
-            run: Cow::Owned(run.into()),
-            tag: Cow::Owned(tag.into()),
+            experiment_id: Cow::Owned(experiment_id),
+            run: Cow::Owned(run),
+            tag: Cow::Owned(tag),
             step: Step(step),
             index,
         })
@@ -78,7 +79,7 @@ impl<'a> FromStr for BlobKey<'a> {
 impl<'a> Display for BlobKey<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use base64::display::Base64Display;
-        let wire = WireBlobKey(
+        let wire = WireBlobKey::<&str>(
             &self.experiment_id,
             &self.run,
             &self.tag,
@@ -107,6 +108,18 @@ mod tests {
         assert_eq!(key.to_string().parse::<BlobKey>().unwrap(), key);
     }
 
+    #[test]
+    fn test_roundtrip_escaped() {
+        let key = BlobKey {
+            experiment_id: Cow::Borrowed("123"),
+            run: Cow::Borrowed("runs\\\"quoted\""),
+            tag: Cow::Owned(crate::commit::reserved_key("__run_graph__").0),
+            step: Step(0),
+            index: 0,
+        };
+        assert_eq!(key.to_string().parse::<BlobKey>().unwrap(), key);
+    }
+
     #[test]
     fn test_no_padding() {
         for eid_length in 0..10 {
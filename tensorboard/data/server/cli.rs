@@ -15,26 +15,38 @@ limitations under the License.
 
 //! Command-line interface for the main entry point.
 
-use clap::Clap;
+use clap::{ArgMatches, Clap, FromArgMatches, IntoApp};
 use log::{debug, error, info, LevelFilter};
+use serde::de::{self, Deserialize, Deserializer};
+use std::fmt;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 use tokio::net::TcpListener;
 use tokio_stream::wrappers::TcpListenerStream;
 use tonic::transport::Server;
 
-use crate::commit::Commit;
+use crate::bench_load::{self, GenerateSpec};
+use crate::commit::{BlobSource, Commit, ScalarPrecision, Watermarks};
 use crate::disk_logdir::DiskLogdir;
-use crate::logdir::LogdirLoader;
+use crate::event_file::Framing;
+use crate::export::{export_logdir, ExportOptions};
+use crate::ingest::{IngestHandler, RunSources};
+use crate::logdir::{ColdStartOrder, LogdirLoader, PollBackoff, RetentionPolicy};
+use crate::logging::{self, LogFormat};
+use crate::metadata_override::MetadataOverrides;
 use crate::proto::tensorboard::data;
+use crate::run::{EventFileOrder, ExpiredFilePolicy, SampleSizes};
+use crate::run_filter::{RunFilter, TagFilter};
 use crate::server::DataProviderHandler;
 
 use data::tensor_board_data_provider_server::TensorBoardDataProviderServer;
+use data::tensor_board_ingest_server::TensorBoardIngestServer;
 
 #[derive(Clap, Debug)]
 #[clap(name = "rustboard", version = crate::VERSION)]
@@ -43,8 +55,18 @@ struct Opts {
     ///
     /// Directory to recursively scan for event files (files matching the `*tfevents*` glob). This
     /// directory, its descendants, and its event files will be periodically polled for new data.
+    /// Required, either here or in the `--config` file.
     #[clap(long)]
-    logdir: PathBuf,
+    logdir: Option<PathBuf>,
+
+    /// Read options from this TOML file
+    ///
+    /// Each key sets the option with the same long name, in snake_case: e.g., `port_file =
+    /// "/tmp/port"` or `log_format = "json"`, plus `verbose = 2` for `-vv`. Options given on the
+    /// command line take precedence over those in the file, which take precedence over defaults.
+    /// Unknown keys are an error.
+    #[clap(long, value_name = "path")]
+    config: Option<PathBuf>,
 
     /// Bind to this IP address
     ///
@@ -62,14 +84,36 @@ struct Opts {
     /// Seconds to sleep between reloads, or "once"
     ///
     /// Number of seconds to wait between finishing one load cycle and starting the next one. This
-    /// does not include the time for the reload itself. If "once", data will be loaded only once.
-    #[clap(long, default_value = "5", value_name = "secs")]
+    /// does not include the time for the reload itself. If `0`, reload continuously. If "once" or
+    /// negative, data will be loaded only once, before the server starts accepting requests (and
+    /// before the `--port-file` is written). Also accepted as `--reload_interval`.
+    #[clap(
+        long,
+        alias = "reload_interval",
+        default_value = "5",
+        value_name = "secs",
+        allow_hyphen_values = true
+    )]
     reload: ReloadStrategy,
 
     /// Use verbose output (-vv for very verbose output)
     #[clap(long = "verbose", short, parse(from_occurrences))]
     verbosity: u32,
 
+    /// Log output format: "text" or "json"
+    ///
+    /// With "json", each log record is written to stderr as a single-line JSON object with
+    /// "timestamp", "level", "target", and "message" keys. Structured fields (like "run") are
+    /// separate keys if the server is built with the `structured-logging` feature, and part of
+    /// the message otherwise. Also accepted as `--log_format`.
+    #[clap(
+        long,
+        alias = "log_format",
+        default_value = "text",
+        value_name = "format"
+    )]
+    log_format: LogFormat,
+
     /// Kill this server once stdin is closed
     ///
     /// While this server is running, read stdin to end of file and then kill the server. Used to
@@ -92,18 +136,74 @@ struct Opts {
     #[clap(long)]
     port_file: Option<PathBuf>,
 
-    /// Checksum all records (negate with `--no-checksum`)
+    /// Accept pushed events from clients holding these tokens
+    ///
+    /// Enable the `TensorBoardIngest` gRPC service on the same port as the data provider, so that
+    /// clients can push events to runs that have no event files. The file at this path lists the
+    /// accepted bearer tokens, one per line; blank lines are ignored. A run that is pushed can't
+    /// also be loaded from the log directory, and vice versa: whichever source reaches a run name
+    /// first keeps it. By default, the service is disabled.
+    #[clap(long, value_name = "path")]
+    ingest_token_file: Option<PathBuf>,
+
+    /// Approximate memory limit for loaded data, in bytes
     ///
-    /// With `--checksum`, every record will be checksummed before being parsed. With
-    /// `--no-checksum` (the default), records are only checksummed if parsing fails. Skipping
-    /// checksums for records that successfully parse can be significantly faster, but also means
-    /// that some bit flips may not be detected.
-    #[clap(long, multiple_occurrences = true, overrides_with = "no_checksum")]
-    checksum: bool,
+    /// After each load cycle, if the loaded data takes more than this much memory, the blob
+    /// sequences of the least recently read runs are dropped until it fits. Dropped data is
+    /// reloaded from disk after the run is next read. Scalars are never dropped. By default, there
+    /// is no limit.
+    #[clap(long, value_name = "bytes")]
+    memory_budget: Option<usize>,
+
+    /// Stop storing blob data while loaded data takes more than this many bytes
+    ///
+    /// Over this limit, new images, audio, graphs, and other blob data are kept without their
+    /// contents, so their steps still show up; scalars load as usual. Blob data is
+    /// stored again once usage falls below `--blob-low-water`, as when runs are dropped to fit
+    /// `--memory-budget`. By default, blob data is always stored.
+    #[clap(long, value_name = "bytes")]
+    blob_high_water: Option<usize>,
+
+    /// Resume storing blob data once loaded data takes less than this many bytes
+    ///
+    /// Applies only with `--blob-high-water`, and defaults to the same value. A larger value is
+    /// taken as equal to `--blob-high-water`.
+    #[clap(long, value_name = "bytes")]
+    blob_low_water: Option<usize>,
+
+    /// Number of threads for loading runs
+    ///
+    /// Runs are reloaded in parallel on a single pool of this many threads, so the number of runs
+    /// being read at once is bounded no matter how many runs there are. Defaults to the number of
+    /// CPU cores, but at most 16. `0` means one thread per core, without the cap. Also accepted as
+    /// `--load_threads`.
+    #[clap(long, alias = "load_threads", value_name = "n")]
+    load_threads: Option<usize>,
+
+    /// How to check record checksums: "verify", "skip", or "ignore"
+    ///
+    /// With "verify", every record is checksummed before being parsed, and an event file is
+    /// abandoned at its first bad record. With "skip", every record is checksummed, but records
+    /// with bad checksums are dropped and reading continues. With "ignore" (the default), records
+    /// are only checksummed if parsing fails. Skipping checksums for records that successfully
+    /// parse can be significantly faster, but also means that some bit flips may not be detected.
+    /// Pass the policy with an equals sign, as in `--checksum=skip`. A bare `--checksum` means
+    /// "verify", and `--no-checksum` means "ignore".
+    #[clap(
+        long,
+        default_value = "ignore",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "verify",
+        multiple_occurrences = true,
+        overrides_with = "no-checksum",
+        value_name = "policy"
+    )]
+    checksum: ChecksumPolicy,
 
     /// Only checksum records that fail to parse
     ///
-    /// Negates `--checksum`. This is the default.
+    /// Same as `--checksum=ignore`. This is the default.
     #[clap(
         long,
         multiple_occurrences = true,
@@ -112,6 +212,500 @@ struct Opts {
     )]
     #[allow(unused)]
     no_checksum: bool,
+
+    /// Maximum size of a single record, in MiB
+    ///
+    /// A record whose length header exceeds this is treated as corrupt, and its event file is
+    /// abandoned, instead of the loader trying to allocate that much memory. By default, there is
+    /// no limit. Also accepted as `--max_record_mb`.
+    #[clap(long, alias = "max_record_mb", value_name = "n")]
+    max_record_mb: Option<u64>,
+
+    /// How records are delimited in event files: "tfrecord" or "length-delimited"
+    ///
+    /// Event files are TFRecords by default, as TensorFlow and most summary writers produce. Some
+    /// lightweight exporters instead prefix each event with just its varint length, as protobuf's
+    /// `writeDelimitedTo` does; read those with "length-delimited". Such files have no checksums,
+    /// so `--checksum` has no effect on them. Applies to every event file in the log directory.
+    #[clap(long, default_value = "tfrecord", value_name = "framing")]
+    framing: Framing,
+
+    /// Maximum number of load cycles between polls of an idle run
+    ///
+    /// A run is idle once it has had no new events for 3 load cycles in a row. Idle runs are
+    /// polled after 2, then 4, 8, and so on load cycles, up to this many, and go back to being
+    /// polled on every cycle as soon as they have new data. By default, every run is polled on
+    /// every cycle.
+    #[clap(long, value_name = "n")]
+    max_idle_poll_interval: Option<u32>,
+
+    /// Order in which to load runs at startup
+    ///
+    /// On the first load cycle, runs are loaded in this order: "alphabetical" (the default),
+    /// "smallest-first" by total event file size, or "most-recent-first" by latest event file
+    /// modification time. With "smallest-first", a log directory with a few very large runs and
+    /// many small ones shows the small ones sooner. Later load cycles are unaffected.
+    #[clap(long, default_value = "alphabetical", value_name = "order")]
+    cold_start_order: ColdStartOrder,
+
+    /// Order in which to read each run's event files
+    ///
+    /// "name" (the default) reads them in order by path, which suits the usual timestamped
+    /// names. "rotation" is for writers that rotate files like `tfevents.log`,
+    /// `tfevents.log.1`, `tfevents.log.2`: it reads the highest-numbered (oldest) file first and
+    /// the unnumbered (current) file last.
+    #[clap(long, default_value = "name", value_name = "order")]
+    event_file_order: EventFileOrder,
+
+    /// Maximum number of event files to read in each run
+    ///
+    /// Guards against writers that start a new event file every few seconds. Beyond this many,
+    /// the oldest files by name that aren't still being written are expired and never read
+    /// again; points already loaded from them are kept. By default, there is no limit.
+    #[clap(long, value_name = "n")]
+    max_files_per_run: Option<usize>,
+
+    /// What to do with event files over `--max-files-per-run`
+    ///
+    /// "mark-dead" (the default) keeps tracking each expired file as dead. "forget" stops
+    /// tracking them, remembering only the newest expired name, so that new files named before
+    /// it are ignored too.
+    #[clap(long, default_value = "mark-dead", value_name = "policy")]
+    expired_files: ExpiredFilePolicy,
+
+    /// Load only runs whose names match this glob (may be repeated)
+    ///
+    /// A run is loaded if its name matches any `--include-runs` pattern and no `--exclude-runs`
+    /// pattern. Other runs' event files are never opened. In patterns, `*` and `?` match within
+    /// a path component, `**` matches across components, and `**/` matches any number of whole
+    /// components, as in `--include-runs 'teamA/**'`. By default, all runs are loaded. Also
+    /// accepted as `--include_runs`.
+    #[clap(
+        long,
+        alias = "include_runs",
+        multiple_occurrences = true,
+        number_of_values = 1,
+        value_name = "glob"
+    )]
+    include_runs: Vec<String>,
+
+    /// Don't load runs whose names match this glob (may be repeated)
+    ///
+    /// See `--include-runs`. Also accepted as `--exclude_runs`.
+    #[clap(
+        long,
+        alias = "exclude_runs",
+        multiple_occurrences = true,
+        number_of_values = 1,
+        value_name = "glob"
+    )]
+    exclude_runs: Vec<String>,
+
+    /// Load only time series whose tags match this glob (may be repeated)
+    ///
+    /// Like `--include-runs`, but for tags within each run, with `/` as the separator: e.g.,
+    /// `--include-tags 'eval/**'`. Values for other tags are counted but never stored. By
+    /// default, all tags are loaded. Also accepted as `--include_tags`.
+    #[clap(
+        long,
+        alias = "include_tags",
+        multiple_occurrences = true,
+        number_of_values = 1,
+        value_name = "glob"
+    )]
+    include_tags: Vec<String>,
+
+    /// Don't load time series whose tags match this glob (may be repeated)
+    ///
+    /// See `--include-tags`. Also accepted as `--exclude_tags`.
+    #[clap(
+        long,
+        alias = "exclude_tags",
+        multiple_occurrences = true,
+        number_of_values = 1,
+        value_name = "glob"
+    )]
+    exclude_tags: Vec<String>,
+
+    /// File of corrections to time series metadata, by tag
+    ///
+    /// For data whose writer stamped the wrong plugin name or data class. The file is TOML, or
+    /// JSON if its name ends in `.json`, with a list of `override` entries. Each has a `tag` glob
+    /// (as for `--include-tags`) and sets a `plugin_name`, a `data_class` ("scalar", "tensor", or
+    /// "blob-sequence"), or both. The first entry whose glob matches a tag applies.
+    #[clap(long, value_name = "path")]
+    metadata_overrides: Option<PathBuf>,
+
+    /// Drop points older than this many seconds
+    ///
+    /// Points whose wall time is more than this far in the past are not loaded, and already
+    /// loaded points are dropped as they age past it on later load cycles. Runs matching a
+    /// `--run-retention` rule use that rule's horizon instead. By default, points are kept
+    /// regardless of age.
+    #[clap(long, value_name = "seconds")]
+    retention: Option<u64>,
+
+    /// Retention horizon for runs whose names match a glob (may be repeated)
+    ///
+    /// Given as `<glob>=<seconds>`, as in `--run-retention 'scratch/**=86400'`. Globs are as for
+    /// `--include-runs`. If several rules match a run, the first one given wins.
+    #[clap(
+        long,
+        multiple_occurrences = true,
+        number_of_values = 1,
+        value_name = "glob=seconds"
+    )]
+    run_retention: Vec<RunRetention>,
+
+    /// Precision in which to store scalar values: "f64" or "f32"
+    ///
+    /// With "f64", values written in double precision keep it. With "f32", values are rounded to
+    /// single precision as they're loaded, as if they had been written that way. Most writers log
+    /// scalars in single precision anyway.
+    #[clap(long, default_value = "f64", value_name = "precision")]
+    scalar_precision: ScalarPrecision,
+
+    /// Read blobs from event files when they're requested instead of holding them in memory
+    ///
+    /// Images, graphs, and other blob sequences then take only a few bytes per point, and each
+    /// blob is read back from its event file when a client asks for it. Event files should not be
+    /// rewritten or deleted while the server runs, or those reads fail.
+    #[clap(long)]
+    blob_references: bool,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+/// Tools to run instead of the server.
+#[derive(Clap, Debug)]
+enum Command {
+    /// Load a log directory once and report problems with its data
+    ///
+    /// Reads every event file under the log directory, with checksums, and prints the runs and
+    /// event files found, corrupt records, the tags that would be loaded under each data class,
+    /// and any time series that would be ignored or whose metadata is inconsistent. Does not
+    /// start a server. Exits with a nonzero status if any event file could not be read.
+    Doctor(DoctorOpts),
+
+    /// Measure load throughput under different loader settings
+    ///
+    /// Loads a log directory (or a synthetic one, with `--generate`) several times for each
+    /// combination of the given `--checksum`, `--buffer-size`, and `--threads` values, starting
+    /// from an empty commit each time. Prints a table of mean wall time, events per second, and
+    /// megabytes per second for each combination. Does not start a server.
+    BenchLoad(BenchLoadOpts),
+
+    /// Load a log directory once and write it out as a new, smaller log directory
+    ///
+    /// Loads the log directory with the server's default sampling, so the export keeps the same
+    /// points that the server would serve, then writes each run to a single event file under
+    /// `--dest`. Use `--max-scalars` and `--max-blob-sequences` to downsample further. Any
+    /// TensorBoard can read the result. Does not start a server.
+    Export(ExportOpts),
+}
+
+#[derive(Clap, Debug)]
+struct DoctorOpts {
+    /// Log directory to check
+    #[clap(long)]
+    logdir: PathBuf,
+
+    /// File of corrections to time series metadata, as for the server's `--metadata-overrides`
+    #[clap(long, value_name = "path")]
+    metadata_overrides: Option<PathBuf>,
+}
+
+#[derive(Clap, Debug)]
+struct BenchLoadOpts {
+    /// Log directory to load
+    #[clap(long, required_unless_present = "generate")]
+    logdir: Option<PathBuf>,
+
+    /// Load a synthetic log directory with this shape instead
+    ///
+    /// Writes a temporary log directory with `<runs>` runs, each with `<tags>` scalar time series
+    /// of `<points>` points, like `4x10x1000`. Append `/<n>` to write up to `n` scalars per event,
+    /// like `1x500x100/500`. The contents are the same on every machine, so results are
+    /// comparable. The directory is deleted afterward.
+    #[clap(long, conflicts_with = "logdir", value_name = "runs>x<tags>x<points")]
+    generate: Option<GenerateSpec>,
+
+    /// Number of times to load each configuration
+    #[clap(long, default_value = "3")]
+    iterations: usize,
+
+    /// Comma-separated values for whether to checksum all records
+    #[clap(
+        long,
+        default_value = "false",
+        use_delimiter = true,
+        value_name = "bools"
+    )]
+    checksum: Vec<bool>,
+
+    /// Comma-separated read buffer sizes per event file, in bytes
+    #[clap(
+        long,
+        default_value = "8192",
+        use_delimiter = true,
+        value_name = "bytes"
+    )]
+    buffer_size: Vec<usize>,
+
+    /// Comma-separated numbers of loader threads (`0` to choose automatically)
+    #[clap(long, default_value = "0", use_delimiter = true, value_name = "counts")]
+    threads: Vec<usize>,
+
+    /// Comma-separated values for whether to stage scalar-only summaries on the fast path
+    #[clap(
+        long,
+        default_value = "true",
+        use_delimiter = true,
+        value_name = "bools"
+    )]
+    scalar_fast_path: Vec<bool>,
+
+    /// Keep every point instead of sampling time series
+    ///
+    /// Measures loading without reservoir sampling, so that every point is kept in memory. Memory
+    /// use grows with the size of the log directory.
+    #[clap(long)]
+    no_sampling: bool,
+}
+
+#[derive(Clap, Debug)]
+struct ExportOpts {
+    /// Log directory to export
+    #[clap(long)]
+    logdir: PathBuf,
+
+    /// Directory to write the export to, which must be empty or not yet exist
+    #[clap(long)]
+    dest: PathBuf,
+
+    /// Write at most this many points of each scalar time series
+    #[clap(long, value_name = "points")]
+    max_scalars: Option<usize>,
+
+    /// Write at most this many points of each blob sequence time series
+    #[clap(long, value_name = "points")]
+    max_blob_sequences: Option<usize>,
+
+    /// Fail without exporting if any event file can't be read in full
+    ///
+    /// Any unreadable file, corrupt record, or event dropped for an invalid wall time or missing
+    /// contents is an error naming the file and offset, instead of a warning.
+    #[clap(long)]
+    strict: bool,
+}
+
+/// Server options as read from a `--config` file. Each field overrides the default for the
+/// [`Opts`] field of the same name (except that `verbose` corresponds to `verbosity`).
+#[derive(Debug, Default, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    logdir: Option<PathBuf>,
+    host: Option<IpAddr>,
+    port: Option<u16>,
+    reload: Option<ReloadStrategy>,
+    verbose: Option<u32>,
+    log_format: Option<LogFormat>,
+    die_after_stdin: Option<bool>,
+    port_file: Option<PathBuf>,
+    ingest_token_file: Option<PathBuf>,
+    memory_budget: Option<usize>,
+    blob_high_water: Option<usize>,
+    blob_low_water: Option<usize>,
+    load_threads: Option<usize>,
+    checksum: Option<ChecksumPolicy>,
+    max_record_mb: Option<u64>,
+    framing: Option<Framing>,
+    max_idle_poll_interval: Option<u32>,
+    cold_start_order: Option<ColdStartOrder>,
+    event_file_order: Option<EventFileOrder>,
+    max_files_per_run: Option<usize>,
+    expired_files: Option<ExpiredFilePolicy>,
+    include_runs: Option<Vec<String>>,
+    exclude_runs: Option<Vec<String>>,
+    include_tags: Option<Vec<String>>,
+    exclude_tags: Option<Vec<String>>,
+    metadata_overrides: Option<PathBuf>,
+    retention: Option<u64>,
+    run_retention: Option<Vec<RunRetention>>,
+    scalar_precision: Option<ScalarPrecision>,
+    blob_references: Option<bool>,
+}
+
+/// Error reading options from the command line and config file.
+#[derive(Debug, thiserror::Error)]
+enum OptsError {
+    /// The config file could not be read.
+    #[error("failed to read config file {}: {}", .0.display(), .1)]
+    ReadConfig(PathBuf, #[source] io::Error),
+    /// The config file is not valid TOML, or has unknown keys or invalid values.
+    #[error("invalid config file {}: {}", .0.display(), .1)]
+    ParseConfig(PathBuf, #[source] toml::de::Error),
+    /// Neither the command line nor the config file gave a log directory.
+    #[error("no log directory given: pass `--logdir` or set `logdir` in the config file")]
+    MissingLogdir,
+}
+
+impl ConfigFile {
+    fn read(path: &Path) -> Result<Self, OptsError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| OptsError::ReadConfig(path.into(), e))?;
+        toml::from_str(&contents).map_err(|e| OptsError::ParseConfig(path.into(), e))
+    }
+}
+
+impl Opts {
+    /// Builds options from command-line arguments as parsed by `Opts::into_app()`, filling in
+    /// any options not given on the command line from the `--config` file, if any.
+    fn from_matches(matches: &ArgMatches) -> Result<Self, OptsError> {
+        let mut opts = Opts::from_arg_matches(matches);
+        if let Some(path) = &opts.config {
+            let file = ConfigFile::read(path)?;
+            opts.merge(file, matches);
+        }
+        if opts.logdir.is_none() && opts.command.is_none() {
+            return Err(OptsError::MissingLogdir);
+        }
+        Ok(opts)
+    }
+
+    /// Overwrites options with values from `file`, except for those given explicitly in
+    /// `matches`. (Clap identifies each argument by its field name in kebab case.)
+    fn merge(&mut self, file: ConfigFile, matches: &ArgMatches) {
+        fn fill<T>(dst: &mut T, src: Option<T>, on_command_line: bool) {
+            if let (Some(v), false) = (src, on_command_line) {
+                *dst = v;
+            }
+        }
+        let given = |id: &str| matches.occurrences_of(id) > 0;
+        fill(&mut self.logdir, file.logdir.map(Some), given("logdir"));
+        fill(&mut self.host, file.host, given("host"));
+        fill(&mut self.port, file.port, given("port"));
+        fill(&mut self.reload, file.reload, given("reload"));
+        fill(&mut self.verbosity, file.verbose, given("verbosity"));
+        fill(&mut self.log_format, file.log_format, given("log-format"));
+        fill(
+            &mut self.die_after_stdin,
+            file.die_after_stdin,
+            given("die-after-stdin"),
+        );
+        fill(
+            &mut self.port_file,
+            file.port_file.map(Some),
+            given("port-file"),
+        );
+        fill(
+            &mut self.ingest_token_file,
+            file.ingest_token_file.map(Some),
+            given("ingest-token-file"),
+        );
+        fill(
+            &mut self.memory_budget,
+            file.memory_budget.map(Some),
+            given("memory-budget"),
+        );
+        fill(
+            &mut self.blob_high_water,
+            file.blob_high_water.map(Some),
+            given("blob-high-water"),
+        );
+        fill(
+            &mut self.blob_low_water,
+            file.blob_low_water.map(Some),
+            given("blob-low-water"),
+        );
+        fill(
+            &mut self.load_threads,
+            file.load_threads.map(Some),
+            given("load-threads"),
+        );
+        fill(
+            &mut self.checksum,
+            file.checksum,
+            given("checksum") || given("no-checksum"),
+        );
+        fill(
+            &mut self.max_record_mb,
+            file.max_record_mb.map(Some),
+            given("max-record-mb"),
+        );
+        fill(&mut self.framing, file.framing, given("framing"));
+        fill(
+            &mut self.max_idle_poll_interval,
+            file.max_idle_poll_interval.map(Some),
+            given("max-idle-poll-interval"),
+        );
+        fill(
+            &mut self.cold_start_order,
+            file.cold_start_order,
+            given("cold-start-order"),
+        );
+        fill(
+            &mut self.event_file_order,
+            file.event_file_order,
+            given("event-file-order"),
+        );
+        fill(
+            &mut self.max_files_per_run,
+            file.max_files_per_run.map(Some),
+            given("max-files-per-run"),
+        );
+        fill(
+            &mut self.expired_files,
+            file.expired_files,
+            given("expired-files"),
+        );
+        fill(
+            &mut self.include_runs,
+            file.include_runs,
+            given("include-runs"),
+        );
+        fill(
+            &mut self.exclude_runs,
+            file.exclude_runs,
+            given("exclude-runs"),
+        );
+        fill(
+            &mut self.include_tags,
+            file.include_tags,
+            given("include-tags"),
+        );
+        fill(
+            &mut self.exclude_tags,
+            file.exclude_tags,
+            given("exclude-tags"),
+        );
+        fill(
+            &mut self.metadata_overrides,
+            file.metadata_overrides.map(Some),
+            given("metadata-overrides"),
+        );
+        fill(
+            &mut self.retention,
+            file.retention.map(Some),
+            given("retention"),
+        );
+        fill(
+            &mut self.run_retention,
+            file.run_retention,
+            given("run-retention"),
+        );
+        fill(
+            &mut self.scalar_precision,
+            file.scalar_precision,
+            given("scalar-precision"),
+        );
+        fill(
+            &mut self.blob_references,
+            file.blob_references,
+            given("blob-references"),
+        );
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -120,28 +714,169 @@ enum ReloadStrategy {
     Once,
 }
 impl FromStr for ReloadStrategy {
-    type Err = <u64 as FromStr>::Err;
+    type Err = <i64 as FromStr>::Err;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s == "once" {
+            return Ok(ReloadStrategy::Once);
+        }
+        let secs: i64 = s.parse()?;
+        if secs < 0 {
             Ok(ReloadStrategy::Once)
         } else {
             Ok(ReloadStrategy::Loop {
-                delay: Duration::from_secs(s.parse()?),
+                delay: Duration::from_secs(secs as u64),
             })
         }
     }
 }
 
+/// Accepts either a string, as on the command line, or an integer number of seconds.
+impl<'de> Deserialize<'de> for ReloadStrategy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = ReloadStrategy;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number of seconds or \"once\"")
+            }
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                self.visit_str(&v.to_string())
+            }
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                self.visit_str(&v.to_string())
+            }
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse()
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+            }
+        }
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// A `--run-retention` rule: a glob on run names and a retention horizon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RunRetention {
+    pattern: String,
+    horizon: Duration,
+}
+
+impl FromStr for RunRetention {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let eq = s
+            .rfind('=')
+            .ok_or_else(|| format!("expected <glob>=<seconds>, got {:?}", s))?;
+        let (pattern, secs) = (&s[..eq], &s[eq + 1..]);
+        if pattern.is_empty() {
+            return Err(format!("empty glob in {:?}", s));
+        }
+        let secs: u64 = secs
+            .parse()
+            .map_err(|e| format!("invalid seconds in {:?}: {}", s, e))?;
+        Ok(RunRetention {
+            pattern: pattern.to_string(),
+            horizon: Duration::from_secs(secs),
+        })
+    }
+}
+
+/// Accepts a `<glob>=<seconds>` string, as on the command line.
+impl<'de> Deserialize<'de> for RunRetention {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = RunRetention;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string of the form \"<glob>=<seconds>\"")
+            }
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse()
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+            }
+        }
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+/// How the loader checks record checksums.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ChecksumPolicy {
+    /// Checksum every record, and stop reading an event file at its first bad record.
+    Verify,
+    /// Checksum every record, and skip records with bad checksums.
+    Skip,
+    /// Only checksum records that fail to parse.
+    Ignore,
+}
+
+impl FromStr for ChecksumPolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "verify" => Ok(ChecksumPolicy::Verify),
+            "skip" => Ok(ChecksumPolicy::Skip),
+            "ignore" => Ok(ChecksumPolicy::Ignore),
+            _ => Err(format!(
+                "unknown checksum policy {:?}; expected \"verify\", \"skip\", or \"ignore\"",
+                s
+            )),
+        }
+    }
+}
+
+/// Accepts either a string, as on the command line, or a boolean, where `true` means "verify"
+/// and `false` means "ignore".
+impl<'de> Deserialize<'de> for ChecksumPolicy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = ChecksumPolicy;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("\"verify\", \"skip\", \"ignore\", or a boolean")
+            }
+            fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+                Ok(if v {
+                    ChecksumPolicy::Verify
+                } else {
+                    ChecksumPolicy::Ignore
+                })
+            }
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse()
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+            }
+        }
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
 #[tokio::main]
 pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let opts = Opts::parse();
-    init_logging(match opts.verbosity {
-        0 => LevelFilter::Warn,
-        1 => LevelFilter::Info,
-        _ => LevelFilter::max(),
+    let opts = Opts::from_matches(&Opts::into_app().get_matches()).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(2);
     });
+    logging::init(
+        match opts.verbosity {
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            _ => LevelFilter::max(),
+        },
+        opts.log_format,
+    );
     debug!("Parsed options: {:?}", opts);
 
+    if let Some(Command::Doctor(doctor_opts)) = &opts.command {
+        std::process::exit(doctor(doctor_opts));
+    }
+    if let Some(Command::BenchLoad(bench_opts)) = &opts.command {
+        return bench_load(bench_opts);
+    }
+    if let Some(Command::Export(export_opts)) = &opts.command {
+        return export(export_opts);
+    }
+
     if opts.die_after_stdin {
         thread::Builder::new()
             .name("StdinWatcher".to_string())
@@ -153,6 +888,82 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let listener = TcpListener::bind(addr).await?;
     let bound = listener.local_addr()?;
 
+    // Leak the commit object, since the Tonic server must have only 'static references. This only
+    // leaks the outer commit structure (of constant size), not the pointers to the actual data.
+    let mut commit = Commit::new();
+    commit.memory_budget(opts.memory_budget);
+    commit
+        .load_shedding()
+        .set_watermarks(blob_watermarks(opts.blob_high_water, opts.blob_low_water));
+    let commit: &'static Commit = Box::leak(Box::new(commit));
+
+    let ingest = match &opts.ingest_token_file {
+        Some(path) => {
+            let tokens = read_ingest_tokens(path).unwrap_or_else(|e| {
+                eprintln!("error: reading {}: {}", path.display(), e);
+                std::process::exit(2);
+            });
+            if tokens.is_empty() {
+                eprintln!("error: no tokens in {}", path.display());
+                std::process::exit(2);
+            }
+            let sources = Arc::new(RunSources::new());
+            Some((
+                IngestHandler::new(commit, Arc::clone(&sources), tokens),
+                sources,
+            ))
+        }
+        None => None,
+    };
+
+    let logdir = opts.logdir.expect("checked by Opts::from_matches");
+    let load_threads = opts.load_threads.unwrap_or_else(default_load_threads);
+    let blob_source: Option<Arc<dyn BlobSource>> = if opts.blob_references {
+        Some(Arc::new(DiskLogdir::new(logdir.clone())))
+    } else {
+        None
+    };
+    let mut loader = LogdirLoader::new(commit, DiskLogdir::new(logdir), load_threads);
+    configure_loader(&mut loader, opts.checksum, opts.max_record_mb);
+    loader.framing(opts.framing);
+    loader.poll_backoff(opts.max_idle_poll_interval.map(|max_interval| PollBackoff {
+        idle_reloads: IDLE_RELOADS,
+        max_interval,
+    }));
+    loader.cold_start_order(opts.cold_start_order);
+    loader.file_order(opts.event_file_order);
+    loader.max_files(opts.max_files_per_run, opts.expired_files);
+    loader.run_filter(run_filter(&opts.include_runs, &opts.exclude_runs));
+    loader.tag_filter(tag_filter(&opts.include_tags, &opts.exclude_tags));
+    if let Some(path) = &opts.metadata_overrides {
+        let overrides = MetadataOverrides::read(path).unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        });
+        info!(
+            "Loaded {} metadata overrides from {}",
+            overrides.len(),
+            path.display()
+        );
+        loader.metadata_overrides(overrides);
+    }
+    loader.retention(retention_policy(opts.retention, &opts.run_retention));
+    loader.scalar_precision(opts.scalar_precision);
+    loader.blob_references(opts.blob_references);
+    // After the setters above, so that pushed runs start with the same options.
+    loader.run_sources(ingest.as_ref().map(|(_, sources)| Arc::clone(sources)));
+    let reload_strategy = opts.reload;
+    if reload_strategy == ReloadStrategy::Once {
+        // Finish the only load before announcing the port, so that clients never see partial
+        // data from a server that will never load more.
+        reload(&mut loader, reload_strategy);
+    } else {
+        thread::Builder::new()
+            .name("Reloader".to_string())
+            .spawn(move || reload(&mut loader, reload_strategy))
+            .expect("failed to spawn reloader thread");
+    }
+
     if let Some(port_file) = opts.port_file {
         let port = bound.port();
         if let Err(e) = write_port_file(&port_file, port) {
@@ -169,45 +980,194 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
         eprintln!("listening on {:?}", bound);
     }
 
-    // Leak the commit object, since the Tonic server must have only 'static references. This only
-    // leaks the outer commit structure (of constant size), not the pointers to the actual data.
-    let commit: &'static Commit = Box::leak(Box::new(Commit::new()));
-
-    thread::Builder::new()
-        .name("Reloader".to_string())
-        .spawn({
-            let reload_strategy = opts.reload;
-            let mut loader = LogdirLoader::new(commit, DiskLogdir::new(opts.logdir), 0);
-            // Checksum only if `--checksum` given (i.e., off by default).
-            loader.checksum(opts.checksum);
-            move || loop {
-                info!("Starting load cycle");
-                let start = Instant::now();
-                loader.reload();
-                let end = Instant::now();
-                info!("Finished load cycle ({:?})", end - start);
-                match reload_strategy {
-                    ReloadStrategy::Loop { delay } => thread::sleep(delay),
-                    ReloadStrategy::Once => break,
-                };
-            }
-        })
-        .expect("failed to spawn reloader thread");
-
-    let handler = DataProviderHandler { commit };
+    let handler = DataProviderHandler {
+        commit,
+        blob_source,
+    };
     Server::builder()
         .add_service(TensorBoardDataProviderServer::new(handler))
+        .add_optional_service(ingest.map(|(handler, _)| TensorBoardIngestServer::new(handler)))
         .serve_with_incoming(TcpListenerStream::new(listener))
         .await?;
     Ok(())
 }
 
-/// Installs a logging handler whose behavior is determined by the `RUST_LOG` environment variable
-/// (per <https://docs.rs/env_logger> semantics), or by including all logs at `default_log_level`
-/// or above if `RUST_LOG_LEVEL` is not given.
-fn init_logging(default_log_level: LevelFilter) {
-    use env_logger::{Builder, Env};
-    Builder::from_env(Env::default().default_filter_or(default_log_level.to_string())).init();
+/// Number of consecutive load cycles without new events after which a run is polled less often,
+/// under `--max-idle-poll-interval`.
+const IDLE_RELOADS: u32 = 3;
+
+/// Maximum number of loader threads to use by default, even on machines with more cores.
+const MAX_DEFAULT_LOAD_THREADS: usize = 16;
+
+/// Number of loader threads to use if `--load-threads` is not given: one per core, up to
+/// [`MAX_DEFAULT_LOAD_THREADS`].
+fn default_load_threads() -> usize {
+    num_cpus::get().min(MAX_DEFAULT_LOAD_THREADS)
+}
+
+/// Applies record-reading options to a loader, before its first load.
+fn configure_loader(
+    loader: &mut LogdirLoader<DiskLogdir>,
+    checksum: ChecksumPolicy,
+    max_record_mb: Option<u64>,
+) {
+    loader.checksum(checksum != ChecksumPolicy::Ignore);
+    loader.skip_bad_checksums(checksum == ChecksumPolicy::Skip);
+    loader.max_record_len(max_record_mb.map(|mb| mb << 20));
+}
+
+/// Builds a run filter from `--include-runs` and `--exclude-runs` patterns.
+fn run_filter(include: &[String], exclude: &[String]) -> RunFilter {
+    let mut filter = RunFilter::new();
+    for pattern in include {
+        filter.include(pattern);
+    }
+    for pattern in exclude {
+        filter.exclude(pattern);
+    }
+    filter
+}
+
+/// Builds a tag filter from `--include-tags` and `--exclude-tags` patterns.
+fn tag_filter(include: &[String], exclude: &[String]) -> TagFilter {
+    let mut filter = TagFilter::new();
+    for pattern in include {
+        filter.include(pattern);
+    }
+    for pattern in exclude {
+        filter.exclude(pattern);
+    }
+    filter
+}
+
+/// Builds a retention policy from `--retention` and `--run-retention` rules.
+fn retention_policy(default_secs: Option<u64>, rules: &[RunRetention]) -> RetentionPolicy {
+    RetentionPolicy {
+        default: default_secs.map(Duration::from_secs),
+        rules: rules
+            .iter()
+            .map(|rule| {
+                let mut filter = RunFilter::new();
+                filter.include(&rule.pattern);
+                (filter, rule.horizon)
+            })
+            .collect(),
+    }
+}
+
+/// Runs load cycles on `loader` according to `strategy`. Returns after the first cycle if the
+/// strategy is [`ReloadStrategy::Once`]; otherwise, never returns.
+fn reload(loader: &mut LogdirLoader<DiskLogdir>, strategy: ReloadStrategy) {
+    loop {
+        info!("Starting load cycle");
+        let start = Instant::now();
+        loader.reload();
+        let end = Instant::now();
+        info!("Finished load cycle ({:?})", end - start);
+        match strategy {
+            ReloadStrategy::Loop { delay } => thread::sleep(delay),
+            ReloadStrategy::Once => break,
+        };
+    }
+}
+
+/// Runs the `doctor` subcommand, printing the report to stdout. Returns the exit status: nonzero
+/// if the metadata overrides, the log directory, or any event file in it could not be read.
+fn doctor(opts: &DoctorOpts) -> i32 {
+    let overrides = match &opts.metadata_overrides {
+        Some(path) => match MetadataOverrides::read(path) {
+            Ok(overrides) => overrides,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return 2;
+            }
+        },
+        None => MetadataOverrides::new(),
+    };
+    match crate::doctor::diagnose(&opts.logdir, &overrides) {
+        Ok(report) => {
+            print!("{}", report);
+            if report.has_unreadable_files() {
+                1
+            } else {
+                0
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "error: failed to read log directory {}: {}",
+                opts.logdir.display(),
+                e
+            );
+            1
+        }
+    }
+}
+
+/// Gets the watermarks for `--blob-high-water` and `--blob-low-water`, if set.
+fn blob_watermarks(high: Option<usize>, low: Option<usize>) -> Option<Watermarks> {
+    let high = high?;
+    Some(Watermarks {
+        high,
+        low: low.map_or(high, |low| low.min(high)),
+    })
+}
+
+/// Runs the `bench-load` subcommand, printing a table of results to stdout.
+fn bench_load(opts: &BenchLoadOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let generated = match opts.generate {
+        Some(spec) => {
+            info!("Generating {:?}", spec);
+            Some(bench_load::GeneratedLogdir::new(spec)?)
+        }
+        None => None,
+    };
+    let logdir = match (&generated, &opts.logdir) {
+        (Some(g), _) => g.path(),
+        (None, Some(logdir)) => logdir.as_path(),
+        (None, None) => unreachable!("clap requires --logdir unless --generate is given"),
+    };
+    let mut measurements = Vec::new();
+    for &checksum in &opts.checksum {
+        for &buffer_size in &opts.buffer_size {
+            for &threads in &opts.threads {
+                for &scalar_fast_path in &opts.scalar_fast_path {
+                    let config = bench_load::LoadConfig {
+                        checksum,
+                        buffer_size,
+                        threads,
+                        sampling: !opts.no_sampling,
+                        scalar_fast_path,
+                    };
+                    info!("Measuring {:?}", config);
+                    measurements.push(bench_load::measure(logdir, config, opts.iterations)?);
+                }
+            }
+        }
+    }
+    print!("{}", bench_load::format_table(&measurements));
+    Ok(())
+}
+
+/// Runs the `export` subcommand, loading the log directory once and then writing it to the
+/// destination.
+fn export(opts: &ExportOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let commit = Commit::new();
+    let logdir = DiskLogdir::new(opts.logdir.clone());
+    let mut loader = LogdirLoader::new(&commit, logdir, default_load_threads());
+    loader.strict(opts.strict);
+    loader.try_reload()?;
+    let unlimited = ExportOptions::default().max_points;
+    let options = ExportOptions {
+        max_points: SampleSizes {
+            scalars: opts.max_scalars.unwrap_or(unlimited.scalars),
+            blob_sequences: opts.max_blob_sequences.unwrap_or(unlimited.blob_sequences),
+        },
+        ..ExportOptions::default()
+    };
+    let runs = export_logdir(&commit, &opts.dest, &options)?;
+    println!("Exported {} runs to {}", runs, opts.dest.display());
+    Ok(())
 }
 
 /// Locks stdin and reads it to EOF, then exits the process.
@@ -227,6 +1187,18 @@ fn write_port_file(path: &Path, port: u16) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Reads bearer tokens for `--ingest-token-file`: one per line, ignoring surrounding whitespace
+/// and blank lines.
+fn read_ingest_tokens(path: &Path) -> std::io::Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -240,7 +1212,671 @@ mod tests {
                 delay: Duration::from_secs(5)
             })
         );
+        assert_eq!(
+            "0".parse::<ReloadStrategy>(),
+            Ok(ReloadStrategy::Loop {
+                delay: Duration::from_secs(0)
+            })
+        );
+        assert_eq!("-1".parse::<ReloadStrategy>(), Ok(ReloadStrategy::Once));
         "5s".parse::<ReloadStrategy>()
             .expect_err("explicit \"s\" trailer should be forbidden");
     }
+
+    #[test]
+    fn test_reload_interval_flag() {
+        let parse = |args: &[&str]| {
+            let argv = ["rustboard", "--logdir", "/tmp/logs"].iter().chain(args);
+            Opts::try_parse_from(argv)
+                .expect("failed to parse flags")
+                .reload
+        };
+        let every = |secs| ReloadStrategy::Loop {
+            delay: Duration::from_secs(secs),
+        };
+        assert_eq!(parse(&[]), every(5));
+        assert_eq!(parse(&["--reload", "once"]), ReloadStrategy::Once);
+        assert_eq!(parse(&["--reload_interval", "30"]), every(30));
+        assert_eq!(parse(&["--reload_interval", "0"]), every(0));
+        assert_eq!(parse(&["--reload_interval", "-1"]), ReloadStrategy::Once);
+        assert_eq!(parse(&["--reload_interval=-1"]), ReloadStrategy::Once);
+    }
+
+    #[test]
+    fn test_load_threads_flag() -> Result<(), Box<dyn std::error::Error>> {
+        let load_threads = |args: &[&str]| -> Result<Option<usize>, OptsError> {
+            let args = [&["--logdir", "/logs"], args].concat();
+            Ok(parse_opts(&args)?.load_threads)
+        };
+        assert_eq!(load_threads(&[])?, None);
+        assert_eq!(load_threads(&["--load-threads", "1"])?, Some(1));
+        assert_eq!(load_threads(&["--load_threads=4"])?, Some(4));
+        let default = default_load_threads();
+        assert!((1..=MAX_DEFAULT_LOAD_THREADS).contains(&default));
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_format_flag() {
+        let parse = |args: &[&str]| {
+            let argv = ["rustboard", "--logdir", "/tmp/logs"].iter().chain(args);
+            Opts::try_parse_from(argv)
+        };
+        assert_eq!(parse(&[]).unwrap().log_format, LogFormat::Text);
+        let opts = parse(&["--log_format", "json", "-vv"]).unwrap();
+        assert_eq!(opts.log_format, LogFormat::Json);
+        assert_eq!(opts.verbosity, 2);
+        let opts = parse(&["--log-format=text", "--verbose"]).unwrap();
+        assert_eq!(opts.log_format, LogFormat::Text);
+        assert_eq!(opts.verbosity, 1);
+        parse(&["--log_format", "xml"]).expect_err("should reject unknown formats");
+    }
+
+    #[test]
+    fn test_reload_once() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::{Run, Step, Tag, WallTime};
+        use crate::writer::SummaryWriteExt;
+
+        let logdir = tempfile::tempdir()?;
+        std::fs::create_dir(logdir.path().join("train"))?;
+        let mut f = File::create(logdir.path().join("train").join("tfevents.123"))?;
+        let tag = Tag("loss".to_string());
+        for i in 0..3 {
+            let wall_time = WallTime::new(1234.0 + i as f64).unwrap();
+            f.write_scalar(&tag, Step(i), wall_time, 0.5)?;
+        }
+        f.sync_all()?;
+
+        let commit = Commit::new();
+        let mut loader =
+            LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+        // Must return rather than looping forever, with all the data loaded.
+        reload(&mut loader, ReloadStrategy::Once);
+
+        let runs = commit.runs.read().unwrap();
+        let run_data = runs[&Run("train".to_string())].read().unwrap();
+        let steps: Vec<Step> = run_data.scalars[&tag]
+            .valid_values()
+            .map(|(step, _, _)| step)
+            .collect();
+        assert_eq!(steps, vec![Step(0), Step(1), Step(2)]);
+        Ok(())
+    }
+
+    /// Parses options from the given arguments (excluding the program name).
+    fn parse_opts(args: &[&str]) -> Result<Opts, OptsError> {
+        let argv = std::iter::once(&"rustboard").chain(args);
+        let matches = Opts::into_app()
+            .try_get_matches_from(argv)
+            .expect("failed to parse flags");
+        Opts::from_matches(&matches)
+    }
+
+    #[test]
+    fn test_config_precedence() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("rustboard.toml");
+        std::fs::write(
+            &config,
+            "logdir = \"/from/file\"\nport = 1234\nreload = 30\nchecksum = true\nverbose = 2\n\
+             port_file = \"/from/file.port\"\n",
+        )?;
+        let config = config.to_str().unwrap();
+
+        // File overrides defaults.
+        let opts = parse_opts(&["--config", config])?;
+        assert_eq!(opts.logdir, Some(PathBuf::from("/from/file")));
+        assert_eq!(opts.port, 1234);
+        assert_eq!(
+            opts.reload,
+            ReloadStrategy::Loop {
+                delay: Duration::from_secs(30)
+            }
+        );
+        assert_eq!(opts.checksum, ChecksumPolicy::Verify);
+        assert_eq!(opts.verbosity, 2);
+        assert_eq!(opts.port_file, Some(PathBuf::from("/from/file.port")));
+        // Defaults remain for keys not in the file.
+        assert_eq!(opts.host, "::1".parse::<IpAddr>()?);
+        assert_eq!(opts.log_format, LogFormat::Text);
+        assert_eq!(opts.memory_budget, None);
+        assert_eq!(opts.load_threads, None);
+
+        // Command line overrides file, even when equal to the default.
+        let opts = parse_opts(&[
+            "--config",
+            config,
+            "--logdir",
+            "/from/cli",
+            "--port",
+            "6806",
+            "--checksum=skip",
+            "-v",
+            "--port-file",
+            "/from/cli.port",
+        ])?;
+        assert_eq!(opts.logdir, Some(PathBuf::from("/from/cli")));
+        assert_eq!(opts.port, 6806);
+        assert_eq!(opts.checksum, ChecksumPolicy::Skip);
+        assert_eq!(opts.verbosity, 1);
+        assert_eq!(opts.port_file, Some(PathBuf::from("/from/cli.port")));
+        assert_eq!(
+            opts.reload,
+            ReloadStrategy::Loop {
+                delay: Duration::from_secs(30)
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_checksum_flags() -> Result<(), Box<dyn std::error::Error>> {
+        use ChecksumPolicy::*;
+        let checksum = |args: &[&str]| -> Result<ChecksumPolicy, OptsError> {
+            let args = [&["--logdir", "/logs"], args].concat();
+            Ok(parse_opts(&args)?.checksum)
+        };
+        assert_eq!(checksum(&[])?, Ignore);
+        assert_eq!(checksum(&["--checksum"])?, Verify);
+        assert_eq!(checksum(&["--checksum", "--port", "0"])?, Verify);
+        assert_eq!(checksum(&["--checksum=skip"])?, Skip);
+        assert_eq!(checksum(&["--no-checksum"])?, Ignore);
+        assert_eq!(checksum(&["--checksum", "--no-checksum"])?, Ignore);
+        assert_eq!(checksum(&["--no-checksum", "--checksum"])?, Verify);
+        assert_eq!(
+            checksum(&["--checksum", "--no-checksum", "--checksum"])?,
+            Verify
+        );
+
+        let argv = ["rustboard", "--logdir", "/logs", "--checksum=sometimes"];
+        Opts::into_app()
+            .try_get_matches_from(argv)
+            .expect_err("should reject unknown checksum policy");
+        let argv = ["rustboard", "--logdir", "/logs", "--checksum", "skip"];
+        Opts::into_app()
+            .try_get_matches_from(argv)
+            .expect_err("should require `=` before checksum policy");
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_record_mb_flag() -> Result<(), Box<dyn std::error::Error>> {
+        let opts = parse_opts(&["--logdir", "/logs"])?;
+        assert_eq!(opts.max_record_mb, None);
+        let opts = parse_opts(&["--logdir", "/logs", "--max-record-mb", "64"])?;
+        assert_eq!(opts.max_record_mb, Some(64));
+        let opts = parse_opts(&["--logdir", "/logs", "--max_record_mb=1"])?;
+        assert_eq!(opts.max_record_mb, Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_framing_flag() -> Result<(), Box<dyn std::error::Error>> {
+        let opts = parse_opts(&["--logdir", "/logs"])?;
+        assert_eq!(opts.framing, Framing::TfRecord);
+        let opts = parse_opts(&["--logdir", "/logs", "--framing", "length-delimited"])?;
+        assert_eq!(opts.framing, Framing::LengthDelimited);
+        let argv = ["rustboard", "--logdir", "/logs", "--framing", "protobuf"];
+        Opts::into_app()
+            .try_get_matches_from(argv)
+            .expect_err("should reject unknown framing");
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_idle_poll_interval_flag() -> Result<(), Box<dyn std::error::Error>> {
+        let opts = parse_opts(&["--logdir", "/logs"])?;
+        assert_eq!(opts.max_idle_poll_interval, None);
+        let opts = parse_opts(&["--logdir", "/logs", "--max-idle-poll-interval", "16"])?;
+        assert_eq!(opts.max_idle_poll_interval, Some(16));
+        Ok(())
+    }
+
+    #[test]
+    fn test_blob_watermark_flags() -> Result<(), Box<dyn std::error::Error>> {
+        let opts = parse_opts(&["--logdir", "/logs"])?;
+        assert_eq!(
+            blob_watermarks(opts.blob_high_water, opts.blob_low_water),
+            None
+        );
+        let opts = parse_opts(&["--logdir", "/logs", "--blob-high-water", "1000"])?;
+        assert_eq!(
+            blob_watermarks(opts.blob_high_water, opts.blob_low_water),
+            Some(Watermarks {
+                high: 1000,
+                low: 1000
+            })
+        );
+        let opts = parse_opts(&[
+            "--logdir",
+            "/logs",
+            "--blob-high-water",
+            "1000",
+            "--blob-low-water",
+            "800",
+        ])?;
+        assert_eq!(
+            blob_watermarks(opts.blob_high_water, opts.blob_low_water),
+            Some(Watermarks {
+                high: 1000,
+                low: 800
+            })
+        );
+        assert_eq!(
+            blob_watermarks(Some(1000), Some(2000)),
+            Some(Watermarks {
+                high: 1000,
+                low: 1000
+            })
+        );
+        assert_eq!(blob_watermarks(None, Some(800)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cold_start_order_flag() -> Result<(), Box<dyn std::error::Error>> {
+        let opts = parse_opts(&["--logdir", "/logs"])?;
+        assert_eq!(opts.cold_start_order, ColdStartOrder::Alphabetical);
+        let opts = parse_opts(&["--logdir", "/logs", "--cold-start-order", "smallest-first"])?;
+        assert_eq!(opts.cold_start_order, ColdStartOrder::SmallestFirst);
+        let argv = [
+            "rustboard",
+            "--logdir",
+            "/logs",
+            "--cold-start-order",
+            "biggest",
+        ];
+        Opts::into_app()
+            .try_get_matches_from(argv)
+            .expect_err("should reject unknown cold start order");
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_file_order_flag() -> Result<(), Box<dyn std::error::Error>> {
+        let opts = parse_opts(&["--logdir", "/logs"])?;
+        assert_eq!(opts.event_file_order, EventFileOrder::Name);
+        let opts = parse_opts(&["--logdir", "/logs", "--event-file-order", "rotation"])?;
+        assert_eq!(opts.event_file_order, EventFileOrder::Rotation);
+        let argv = [
+            "rustboard",
+            "--logdir",
+            "/logs",
+            "--event-file-order",
+            "mtime",
+        ];
+        Opts::into_app()
+            .try_get_matches_from(argv)
+            .expect_err("should reject unknown event file order");
+        Ok(())
+    }
+
+    #[test]
+    fn test_scalar_precision_flag() -> Result<(), Box<dyn std::error::Error>> {
+        let opts = parse_opts(&["--logdir", "/logs"])?;
+        assert_eq!(opts.scalar_precision, ScalarPrecision::F64);
+        let opts = parse_opts(&["--logdir", "/logs", "--scalar-precision", "f32"])?;
+        assert_eq!(opts.scalar_precision, ScalarPrecision::F32);
+        let argv = [
+            "rustboard",
+            "--logdir",
+            "/logs",
+            "--scalar-precision",
+            "f16",
+        ];
+        Opts::into_app()
+            .try_get_matches_from(argv)
+            .expect_err("should reject unknown scalar precision");
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_files_per_run_flags() -> Result<(), Box<dyn std::error::Error>> {
+        let opts = parse_opts(&["--logdir", "/logs"])?;
+        assert_eq!(opts.max_files_per_run, None);
+        assert_eq!(opts.expired_files, ExpiredFilePolicy::MarkDead);
+        let opts = parse_opts(&[
+            "--logdir",
+            "/logs",
+            "--max-files-per-run",
+            "100",
+            "--expired-files",
+            "forget",
+        ])?;
+        assert_eq!(opts.max_files_per_run, Some(100));
+        assert_eq!(opts.expired_files, ExpiredFilePolicy::Forget);
+        let argv = [
+            "rustboard",
+            "--logdir",
+            "/logs",
+            "--expired-files",
+            "delete",
+        ];
+        Opts::into_app()
+            .try_get_matches_from(argv)
+            .expect_err("should reject unknown expired file policy");
+        Ok(())
+    }
+
+    /// Writes an event file under `logdir` with five scalar points, where the records for steps 1
+    /// and 3 have their data corrupted (and so their data CRCs are wrong) but still parse.
+    fn write_bad_crc_event_file(logdir: &Path, tag: &crate::types::Tag) -> io::Result<()> {
+        use crate::types::{Step, WallTime};
+        use crate::writer::SummaryWriteExt;
+        let mut file = Vec::new();
+        for step in 0..5 {
+            let mut record = Vec::new();
+            let wall_time = WallTime::new(1234.0 + step as f64).unwrap();
+            record.write_scalar(tag, Step(step), wall_time, 1.0)?;
+            if step % 2 == 1 {
+                // The last 4 bytes of the scalar value precede the data CRC; change the value
+                // from 1.0 to 2.0 without updating the CRC.
+                let n = record.len();
+                assert_eq!(record[n - 8..n - 4], 1.0f32.to_le_bytes());
+                record[n - 8..n - 4].copy_from_slice(&2.0f32.to_le_bytes());
+            }
+            file.extend(record);
+        }
+        std::fs::write(logdir.join("tfevents.123"), file)
+    }
+
+    #[test]
+    fn test_checksum_policy_loading() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::{Run, Step, Tag};
+        let logdir = tempfile::tempdir()?;
+        let tag = Tag("accuracy".to_string());
+        write_bad_crc_event_file(logdir.path(), &tag)?;
+
+        let load = |policy: ChecksumPolicy| -> Vec<(Step, f64)> {
+            let commit = Commit::new();
+            let mut loader =
+                LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+            configure_loader(&mut loader, policy, Some(1));
+            loader.reload();
+            let runs = commit.runs.read().unwrap();
+            let run_data = runs.get(&Run(".".to_string())).unwrap().read().unwrap();
+            run_data.scalars.get(&tag).map_or_else(Vec::new, |ts| {
+                ts.valid_values().map(|(step, _, &v)| (step, v.0)).collect()
+            })
+        };
+
+        let ignore = load(ChecksumPolicy::Ignore);
+        let steps: Vec<Step> = ignore.iter().map(|&(step, _)| step).collect();
+        assert_eq!(steps, (0..5).map(Step).collect::<Vec<_>>());
+        assert_eq!(ignore[1], (Step(1), 2.0));
+        assert_eq!(load(ChecksumPolicy::Verify), vec![(Step(0), 1.0)]);
+        assert_eq!(
+            load(ChecksumPolicy::Skip),
+            vec![(Step(0), 1.0), (Step(2), 1.0), (Step(4), 1.0)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_errors() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("rustboard.toml");
+        std::fs::write(&config, "logdir = \"/logs\"\nprot = 1234\n")?;
+        let err = parse_opts(&["--config", config.to_str().unwrap()]).unwrap_err();
+        assert!(matches!(err, OptsError::ParseConfig(..)), "{:?}", err);
+        assert!(err.to_string().contains("unknown field `prot`"), "{}", err);
+
+        std::fs::write(&config, "reload = \"sometimes\"\n")?;
+        let err = parse_opts(&["--config", config.to_str().unwrap()]).unwrap_err();
+        assert!(matches!(err, OptsError::ParseConfig(..)), "{:?}", err);
+
+        let missing = dir.path().join("missing.toml");
+        let err = parse_opts(&["--config", missing.to_str().unwrap()]).unwrap_err();
+        assert!(matches!(err, OptsError::ReadConfig(..)), "{:?}", err);
+
+        let err = parse_opts(&[]).unwrap_err();
+        assert!(matches!(err, OptsError::MissingLogdir), "{:?}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_doctor_subcommand() -> Result<(), Box<dyn std::error::Error>> {
+        let opts = parse_opts(&["doctor", "--logdir", "/tmp/logs"])?;
+        match opts.command {
+            Some(Command::Doctor(DoctorOpts { logdir, .. })) => {
+                assert_eq!(logdir, PathBuf::from("/tmp/logs"))
+            }
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(opts.logdir, None);
+
+        let logdir = tempfile::tempdir()?;
+        let doctor_opts = DoctorOpts {
+            logdir: logdir.path().to_path_buf(),
+            metadata_overrides: None,
+        };
+        assert_eq!(doctor(&doctor_opts), 0);
+        std::fs::write(logdir.path().join("tfevents.123"), [0xff; 16])?;
+        assert_eq!(doctor(&doctor_opts), 1);
+        let missing = DoctorOpts {
+            logdir: logdir.path().join("missing"),
+            metadata_overrides: None,
+        };
+        assert_eq!(doctor(&missing), 1);
+        let bad_overrides = DoctorOpts {
+            logdir: logdir.path().to_path_buf(),
+            metadata_overrides: Some(logdir.path().join("missing.toml")),
+        };
+        assert_eq!(doctor(&bad_overrides), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_subcommand() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::{Run, Step, Tag, WallTime};
+        use crate::writer::SummaryWriteExt;
+        let logdir = tempfile::tempdir()?;
+        let dest = tempfile::tempdir()?;
+        let tag = Tag("loss".to_string());
+        let mut f = File::create(logdir.path().join("tfevents.123"))?;
+        for i in 0..5 {
+            let wall_time = WallTime::new(1234.0 + i as f64).unwrap();
+            f.write_scalar(&tag, Step(i), wall_time, i as f32)?;
+        }
+        f.sync_all()?;
+
+        let logdir_arg = logdir.path().to_str().unwrap();
+        let dest_arg = dest.path().join("out");
+        let dest_arg = dest_arg.to_str().unwrap();
+        let argv = ["export", "--logdir", logdir_arg, "--dest", dest_arg];
+        let opts = parse_opts(&[&argv[..], &["--max-scalars", "3"]].concat())?;
+        let export_opts = match opts.command {
+            Some(Command::Export(e)) => e,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(export_opts.max_scalars, Some(3));
+        assert!(!export_opts.strict);
+        export(&export_opts)?;
+
+        let commit = Commit::new();
+        let exported = DiskLogdir::new(dest.path().join("out"));
+        LogdirLoader::new(&commit, exported, 1).reload();
+        let root = Run(".".to_string());
+        let steps: Vec<i64> = (commit.read_scalars(&root, &tag).unwrap().iter())
+            .map(|(step, _, _)| step.0)
+            .collect();
+        assert_eq!(steps.len(), 3);
+        assert_eq!((steps[0], steps[2]), (0, 4));
+
+        // The destination is no longer empty, so a second export fails.
+        assert!(export(&export_opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bench_load_subcommand() {
+        let opts = parse_opts(&[
+            "bench-load",
+            "--generate",
+            "1x2x3",
+            "--checksum",
+            "true,false",
+            "--threads=1,4",
+        ])
+        .unwrap();
+        match opts.command {
+            Some(Command::BenchLoad(b)) => {
+                assert_eq!(b.logdir, None);
+                assert_eq!(
+                    b.generate,
+                    Some(GenerateSpec {
+                        runs: 1,
+                        tags: 2,
+                        points: 3,
+                        values_per_event: 1,
+                    })
+                );
+                assert_eq!(b.iterations, 3);
+                assert_eq!(b.checksum, vec![true, false]);
+                assert_eq!(b.buffer_size, vec![8192]);
+                assert_eq!(b.threads, vec![1, 4]);
+                assert_eq!(b.scalar_fast_path, vec![true]);
+                assert!(!b.no_sampling);
+            }
+            other => panic!("{:?}", other),
+        };
+
+        let argv = ["rustboard", "bench-load"];
+        Opts::into_app()
+            .try_get_matches_from(argv)
+            .expect_err("should require --logdir or --generate");
+        let argv = [
+            "rustboard",
+            "bench-load",
+            "--logdir",
+            "x",
+            "--generate",
+            "1x1x1",
+        ];
+        Opts::into_app()
+            .try_get_matches_from(argv)
+            .expect_err("should reject both --logdir and --generate");
+
+        let opts = parse_opts(&["bench-load", "--generate", "1x1x1", "--no-sampling"]).unwrap();
+        match opts.command {
+            Some(Command::BenchLoad(b)) => assert!(b.no_sampling),
+            other => panic!("{:?}", other),
+        };
+    }
+
+    #[test]
+    fn test_run_filter_flags() {
+        use crate::types::{Run, Tag};
+
+        let opts = parse_opts(&[
+            "--logdir",
+            "x",
+            "--include-runs",
+            "teamA/**",
+            "--include_runs",
+            "shared",
+            "--exclude-runs",
+            "**/scratch*",
+        ])
+        .unwrap();
+        assert_eq!(opts.include_runs, vec!["teamA/**", "shared"]);
+        assert_eq!(opts.exclude_runs, vec!["**/scratch*"]);
+        let filter = run_filter(&opts.include_runs, &opts.exclude_runs);
+        assert!(filter.matches(&Run("shared".to_string())));
+        assert!(!filter.matches(&Run("teamB/run2".to_string())));
+
+        let opts = parse_opts(&["--logdir", "x"]).unwrap();
+        assert!(run_filter(&opts.include_runs, &opts.exclude_runs).is_empty());
+        assert!(tag_filter(&opts.include_tags, &opts.exclude_tags).is_empty());
+
+        let opts = parse_opts(&["--logdir", "x", "--include-tags", "eval/**"]).unwrap();
+        let filter = tag_filter(&opts.include_tags, &opts.exclude_tags);
+        assert!(filter.matches(&Tag("eval/loss".to_string())));
+        assert!(!filter.matches(&Tag("train/loss".to_string())));
+    }
+
+    #[test]
+    fn test_retention_flags() {
+        use crate::types::Run;
+
+        let opts = parse_opts(&["--logdir", "x"]).unwrap();
+        assert_eq!(
+            retention_policy(opts.retention, &opts.run_retention),
+            RetentionPolicy::default()
+        );
+
+        let opts = parse_opts(&[
+            "--logdir",
+            "x",
+            "--retention",
+            "3600",
+            "--run-retention",
+            "scratch/**=60",
+            "--run-retention",
+            "a=b=120",
+        ])
+        .unwrap();
+        let policy = retention_policy(opts.retention, &opts.run_retention);
+        let horizon = |run: &str| policy.horizon(&Run(run.to_string()));
+        assert_eq!(horizon("scratch/tmp"), Some(Duration::from_secs(60)));
+        assert_eq!(horizon("a=b"), Some(Duration::from_secs(120)));
+        assert_eq!(horizon("train"), Some(Duration::from_secs(3600)));
+
+        for bad in &["scratch", "=60", "scratch=soon", "scratch=-1"] {
+            let argv = ["rustboard", "--logdir", "x", "--run-retention", bad];
+            Opts::into_app().try_get_matches_from(argv).expect_err(bad);
+        }
+    }
+
+    #[test]
+    fn test_example_config() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("example_config.toml");
+        std::fs::write(&config, include_str!("testdata/example_config.toml"))?;
+
+        let opts = parse_opts(&["--config", config.to_str().unwrap(), "--port", "0"])?;
+        assert_eq!(opts.logdir, Some(PathBuf::from("/data/experiments/logs")));
+        assert_eq!(opts.host, "0.0.0.0".parse::<IpAddr>()?);
+        assert_eq!(opts.port, 0);
+        assert_eq!(opts.reload, ReloadStrategy::Once);
+        assert_eq!(opts.verbosity, 1);
+        assert_eq!(opts.log_format, LogFormat::Json);
+        assert!(!opts.die_after_stdin);
+        assert_eq!(opts.port_file, Some(PathBuf::from("/tmp/rustboard.port")));
+        assert_eq!(
+            opts.ingest_token_file,
+            Some(PathBuf::from("/etc/rustboard/ingest_tokens"))
+        );
+        assert_eq!(opts.memory_budget, Some(4_000_000_000));
+        assert_eq!(opts.blob_high_water, Some(3_000_000_000));
+        assert_eq!(opts.blob_low_water, Some(2_000_000_000));
+        assert_eq!(opts.load_threads, Some(8));
+        assert_eq!(opts.checksum, ChecksumPolicy::Skip);
+        assert_eq!(opts.max_record_mb, Some(256));
+        assert_eq!(opts.framing, Framing::LengthDelimited);
+        assert_eq!(opts.max_idle_poll_interval, Some(32));
+        assert_eq!(opts.cold_start_order, ColdStartOrder::SmallestFirst);
+        assert_eq!(opts.event_file_order, EventFileOrder::Rotation);
+        assert_eq!(opts.max_files_per_run, Some(10_000));
+        assert_eq!(opts.expired_files, ExpiredFilePolicy::Forget);
+        assert_eq!(opts.include_runs, vec!["teamA/**".to_string()]);
+        assert_eq!(opts.exclude_runs, vec!["**/scratch*".to_string()]);
+        assert_eq!(opts.include_tags, Vec::<String>::new());
+        assert_eq!(opts.exclude_tags, vec!["debug/**".to_string()]);
+        assert_eq!(
+            opts.metadata_overrides,
+            Some(PathBuf::from("/etc/rustboard/metadata_overrides.toml"))
+        );
+        assert_eq!(opts.retention, Some(2_592_000));
+        assert_eq!(
+            opts.run_retention,
+            vec![RunRetention {
+                pattern: "scratch/**".to_string(),
+                horizon: Duration::from_secs(86400),
+            }]
+        );
+        assert_eq!(opts.scalar_precision, ScalarPrecision::F32);
+        assert!(opts.blob_references);
+        Ok(())
+    }
 }
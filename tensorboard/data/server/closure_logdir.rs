@@ -0,0 +1,147 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Log directories backed by caller-provided closures, for custom storage backends.
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+use crate::logdir::{EventFileBuf, Logdir};
+use crate::types::Run;
+
+/// A log directory whose discovery and file reads are delegated to closures.
+///
+/// This lets an embedder serve event data from any source that can produce a [`Read`] (an
+/// in-memory buffer, a database blob, a custom network client) without writing a full [`Logdir`]
+/// implementation. The `discover` closure plays the role of [`Logdir::discover`], and `open`
+/// plays the role of [`Logdir::open`]; the same contracts apply. In particular, `open` is called
+/// again whenever a run's files are reopened, so each call should return a fresh reader
+/// positioned at the start of the file.
+///
+/// To be used with a [`LogdirLoader`], both closures and the readers that `open` returns must be
+/// thread-safe, since runs are loaded in parallel.
+///
+/// [`LogdirLoader`]: crate::logdir::LogdirLoader
+pub struct ClosureLogdir<D, O> {
+    discover: D,
+    open: O,
+}
+
+impl<D, O, R> ClosureLogdir<D, O>
+where
+    D: Fn() -> io::Result<HashMap<Run, Vec<EventFileBuf>>>,
+    O: Fn(&EventFileBuf) -> io::Result<R>,
+    R: Read,
+{
+    /// Creates a log directory that finds event files with `discover` and reads them with `open`.
+    ///
+    /// Does not call either closure.
+    pub fn new(discover: D, open: O) -> Self {
+        ClosureLogdir { discover, open }
+    }
+}
+
+impl<D, O, R> Logdir for ClosureLogdir<D, O>
+where
+    D: Fn() -> io::Result<HashMap<Run, Vec<EventFileBuf>>>,
+    O: Fn(&EventFileBuf) -> io::Result<R>,
+    R: Read,
+{
+    type File = R;
+
+    fn discover(&self) -> io::Result<HashMap<Run, Vec<EventFileBuf>>> {
+        (self.discover)()
+    }
+
+    fn open(&self, path: &EventFileBuf) -> io::Result<Self::File> {
+        (self.open)(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+    use std::sync::{Arc, RwLock};
+
+    use crate::commit::Commit;
+    use crate::logdir::LogdirLoader;
+    use crate::types::{Step, Tag, WallTime};
+    use crate::writer::SummaryWriteExt;
+
+    #[test]
+    fn test_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+        let tag = Tag("loss".to_string());
+        let files: Arc<RwLock<HashMap<PathBuf, Vec<u8>>>> = Arc::default();
+        let mut train = Vec::new();
+        train.write_scalar(&tag, Step(0), WallTime::new(1235.0).unwrap(), 0.25)?;
+        train.write_scalar(&tag, Step(1), WallTime::new(1236.0).unwrap(), 0.5)?;
+        let mut test = Vec::new();
+        test.write_scalar(&tag, Step(0), WallTime::new(1235.0).unwrap(), 0.75)?;
+        {
+            let mut files = files.write().unwrap();
+            files.insert(PathBuf::from("train/tfevents.1"), train);
+            files.insert(PathBuf::from("test/tfevents.1"), test);
+        }
+
+        let discover_files = Arc::clone(&files);
+        let open_files = Arc::clone(&files);
+        let logdir = ClosureLogdir::new(
+            move || {
+                let mut runs: HashMap<Run, Vec<EventFileBuf>> = HashMap::new();
+                for path in discover_files.read().unwrap().keys() {
+                    let run = path.parent().unwrap().display().to_string();
+                    runs.entry(Run(run))
+                        .or_default()
+                        .push(EventFileBuf(path.clone()));
+                }
+                for files in runs.values_mut() {
+                    files.sort();
+                }
+                Ok(runs)
+            },
+            move |path: &EventFileBuf| match open_files.read().unwrap().get(&path.0) {
+                Some(buf) => Ok(Cursor::new(buf.clone())),
+                None => Err(io::Error::new(io::ErrorKind::NotFound, "no such file")),
+            },
+        );
+        let commit = Commit::new();
+        let mut loader = LogdirLoader::new(&commit, logdir, 1);
+        let values = |run: &str| -> Vec<f64> {
+            let runs = commit.runs.read().unwrap();
+            let run_data = runs.get(&Run(run.to_string())).expect("run missing");
+            let run_data = run_data.read().unwrap();
+            run_data.scalars.get(&tag).map_or_else(Vec::new, |ts| {
+                ts.valid_values().map(|(_, _, v)| v.0).collect()
+            })
+        };
+
+        loader.reload();
+        assert_eq!(values("train"), vec![0.25, 0.5]);
+        assert_eq!(values("test"), vec![0.75]);
+
+        // A newly discovered file is opened and read on the next reload.
+        let mut more = Vec::new();
+        more.write_scalar(&tag, Step(1), WallTime::new(1236.0).unwrap(), 1.0)?;
+        files
+            .write()
+            .unwrap()
+            .insert(PathBuf::from("test/tfevents.2"), more);
+        loader.reload();
+        assert_eq!(values("test"), vec![0.75, 1.0]);
+        Ok(())
+    }
+}
@@ -15,12 +15,23 @@ limitations under the License.
 
 //! Shared state for sampled data available to readers.
 
-use std::collections::HashMap;
-use std::sync::RwLock;
+use log::{info, warn};
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::Hasher;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
 
+use crate::data_compat::{EventValue, GraphDefValue, SummaryValue};
+use crate::event_file::{EventFileReader, Framing};
+use crate::logdir::{EventFileBuf, Logdir};
 use crate::proto::tensorboard as pb;
 use crate::reservoir::Basin;
-use crate::types::{Run, Step, Tag, WallTime};
+use crate::types::{Run, Step, StepRange, Tag, WallTime, WallTimeRange};
 
 /// Current state of in-memory sampled data.
 ///
@@ -33,6 +44,278 @@ use crate::types::{Run, Step, Tag, WallTime};
 #[derive(Debug, Default)]
 pub struct Commit {
     pub runs: RwLock<HashMap<Run, RwLock<RunData>>>,
+
+    /// Approximate limit on the memory used by all runs' data, in bytes. See
+    /// [`Self::memory_budget`].
+    memory_budget: Option<usize>,
+
+    /// Logical clock for recording run accesses. See [`Self::touch`].
+    clock: AtomicU64,
+
+    /// Channel for announcing new data to subscribers. See [`Self::subscribe`].
+    events: CommitEvents,
+
+    /// Records of recently removed runs. See [`Self::remove_run`].
+    tombstones: Mutex<Tombstones>,
+
+    /// Whether loaders should stop updating this commit. See [`Self::read_only`].
+    read_only: ReadOnlySwitch,
+
+    /// Running totals of the commit's contents. See [`Self::counts`].
+    counters: Arc<Counters>,
+
+    /// Whether loaders should stop storing blobs. See [`Self::load_shedding`].
+    load_shedding: Arc<LoadShedding>,
+}
+
+/// Notice that new points have been committed to a run. See [`Commit::subscribe`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitEvent {
+    /// Run whose data changed.
+    pub run: Run,
+    /// Tags of the time series that have new points, in sorted order.
+    pub changed_tags: Vec<Tag>,
+}
+
+/// Number of [`CommitEvent`]s buffered for each subscriber that hasn't yet received them.
+pub const COMMIT_EVENT_CAPACITY: usize = 1024;
+
+/// Default number of tags listed in a [`MemoryReport`].
+pub const DEFAULT_HEAVIEST_TAGS: usize = 20;
+
+/// Default time for which a removed run's tombstone is kept. See [`Commit::tombstone_ttl`].
+pub const DEFAULT_TOMBSTONE_TTL: Duration = Duration::from_secs(60);
+
+/// Recently removed runs, each with the generation of its removal.
+#[derive(Debug)]
+struct Tombstones {
+    /// Number of removals so far; the generation of the latest one.
+    generation: u64,
+    /// How long to keep each tombstone.
+    ttl: Duration,
+    /// Generation and time of each run's latest removal.
+    runs: HashMap<Run, (u64, Instant)>,
+}
+
+impl Default for Tombstones {
+    fn default() -> Self {
+        Tombstones {
+            generation: 0,
+            ttl: DEFAULT_TOMBSTONE_TTL,
+            runs: HashMap::new(),
+        }
+    }
+}
+
+/// A switch that can be flipped at runtime to stop loaders from reading new data.
+///
+/// Clones share state, so a single switch can be handed to several loaders to freeze them all at
+/// once: see [`LogdirLoader::global_read_only`]. Each commit also has its own switch, which
+/// freezes just the loaders that write to it: see [`Commit::read_only`].
+///
+/// [`LogdirLoader::global_read_only`]: crate::logdir::LogdirLoader::global_read_only
+#[derive(Debug, Clone, Default)]
+pub struct ReadOnlySwitch(Arc<AtomicBool>);
+
+impl ReadOnlySwitch {
+    /// Creates a new switch, initially off.
+    pub fn new() -> Self {
+        ReadOnlySwitch::default()
+    }
+
+    /// Turns read-only mode on or off. Takes effect at the start of each loader's next load
+    /// cycle; a cycle already in progress runs to completion.
+    pub fn set(&self, read_only: bool) {
+        self.0.store(read_only, Ordering::SeqCst);
+    }
+
+    /// Checks whether read-only mode is on.
+    pub fn get(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Sizes of a commit's contents, or of one run's. See [`Commit::counts`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Counts {
+    pub runs: usize,
+    pub scalar_series: usize,
+    pub tensor_series: usize,
+    pub blob_sequence_series: usize,
+    /// Committed points across all time series, including points that failed to convert.
+    pub points: usize,
+    /// Estimated memory used by committed points, in bytes. See [`RunData::memory_usage`].
+    pub memory: usize,
+}
+
+/// Running totals of a commit's contents, kept up to date by whoever changes them. See
+/// [`Commit::counts`].
+///
+/// Each run's [`RunData`] remembers what it last contributed, so every recount corrects the
+/// totals for that run. Totals can only be off for runs changed since their last recount.
+#[derive(Debug, Default)]
+pub struct Counters {
+    runs: AtomicUsize,
+    scalar_series: AtomicUsize,
+    tensor_series: AtomicUsize,
+    blob_sequence_series: AtomicUsize,
+    points: AtomicUsize,
+    memory: AtomicUsize,
+}
+
+impl Counters {
+    /// Reads the current totals. Each total is read separately, so they may reflect slightly
+    /// different moments if the commit is being updated.
+    pub fn snapshot(&self) -> Counts {
+        Counts {
+            runs: self.runs.load(Ordering::Relaxed),
+            scalar_series: self.scalar_series.load(Ordering::Relaxed),
+            tensor_series: self.tensor_series.load(Ordering::Relaxed),
+            blob_sequence_series: self.blob_sequence_series.load(Ordering::Relaxed),
+            points: self.points.load(Ordering::Relaxed),
+            memory: self.memory.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Updates the totals for a change to `run_data`, which must be in (or about to be added to)
+    /// the commit. Call this after every change to the run's time series, while still holding
+    /// its write lock.
+    pub fn recount(&self, run_data: &mut RunData) {
+        let counts = run_data.counts();
+        self.apply(&run_data.counted, &counts);
+        run_data.counted = counts;
+    }
+
+    /// Removes the contribution of `run_data`, which has just been removed from the commit.
+    pub fn uncount(&self, run_data: &RunData) {
+        self.apply(&run_data.counted, &Counts::default());
+    }
+
+    /// Adjusts each total from `old` to `new`.
+    fn apply(&self, old: &Counts, new: &Counts) {
+        fn adjust(total: &AtomicUsize, old: usize, new: usize) {
+            if new > old {
+                total.fetch_add(new - old, Ordering::Relaxed);
+            } else if old > new {
+                total.fetch_sub(old - new, Ordering::Relaxed);
+            }
+        }
+        adjust(&self.runs, old.runs, new.runs);
+        adjust(&self.scalar_series, old.scalar_series, new.scalar_series);
+        adjust(&self.tensor_series, old.tensor_series, new.tensor_series);
+        adjust(
+            &self.blob_sequence_series,
+            old.blob_sequence_series,
+            new.blob_sequence_series,
+        );
+        adjust(&self.points, old.points, new.points);
+        adjust(&self.memory, old.memory, new.memory);
+    }
+}
+
+/// High and low watermarks for [`LoadShedding`], in bytes of estimated commit memory (see
+/// [`Counts::memory`]).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Watermarks {
+    /// Start shedding once memory use exceeds this.
+    pub high: usize,
+    /// Stop shedding once memory use falls below this. Should be at most `high`.
+    pub low: usize,
+}
+
+/// A switch that loaders check before committing, to stop storing blobs while a commit uses too
+/// much memory. See [`Commit::load_shedding`].
+///
+/// While shedding, loaders commit new blob sequence points as [`DataLoss`] markers, which keep
+/// their steps and wall times but none of their blobs. Scalars are committed as usual. Shedding
+/// starts when the commit's memory use exceeds the high watermark, and stops once it falls below
+/// the low watermark, as when runs are evicted (see [`Commit::enforce_memory_budget`]) or
+/// removed. Points already shed aren't restored.
+#[derive(Debug, Default)]
+pub struct LoadShedding {
+    watermarks: Mutex<Option<Watermarks>>,
+    active: AtomicBool,
+    shed: AtomicUsize,
+}
+
+impl LoadShedding {
+    /// Sets the watermarks, or stops shedding for good if `None` (the default). Takes effect at
+    /// each loader's next commit.
+    pub fn set_watermarks(&self, watermarks: Option<Watermarks>) {
+        *self.watermarks.lock().expect("failed to lock watermarks") = watermarks;
+    }
+
+    /// Checks whether loaders are shedding blobs, as of the last check.
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Number of blob sequence points committed without their blobs so far, across all runs.
+    pub fn shed_points(&self) -> usize {
+        self.shed.load(Ordering::Relaxed)
+    }
+
+    /// Starts or stops shedding given the commit's current memory use, in bytes, and returns
+    /// whether to shed. Loaders call this before each commit.
+    pub fn check(&self, memory: usize) -> bool {
+        let watermarks = *self.watermarks.lock().expect("failed to lock watermarks");
+        let was_active = self.is_active();
+        let active = match watermarks {
+            None => false,
+            Some(w) if memory > w.high => true,
+            Some(w) if memory < w.low => false,
+            Some(_) => was_active,
+        };
+        if active != was_active
+            && self
+                .active
+                .compare_exchange(was_active, active, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            if active {
+                warn!(
+                    "Loaded data uses about {} bytes, over the high watermark; dropping new \
+                     images, audio, and other blob data until usage falls",
+                    memory
+                );
+            } else {
+                info!(
+                    "Loaded data uses about {} bytes; storing blob data again",
+                    memory
+                );
+            }
+        }
+        active
+    }
+
+    /// Records that `n` more blob sequence points were committed without their blobs.
+    pub fn record_shed(&self, n: usize) {
+        self.shed.fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+/// Options for reading a scalar time series with [`Commit::query_scalars`]. The default reads
+/// every point, with absolute wall times.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ScalarQuery {
+    /// Only read points whose steps lie in this range.
+    pub steps: StepRange,
+    /// Only read points whose wall times lie in this range. Bounds are absolute wall times, even
+    /// if `relative_time` is set.
+    pub wall_times: WallTimeRange,
+    /// Give each wall time in seconds since the run's start time, as with
+    /// [`Commit::read_scalars_relative`].
+    pub relative_time: bool,
+}
+
+/// Sending half of a commit's event channel.
+#[derive(Debug)]
+struct CommitEvents(broadcast::Sender<CommitEvent>);
+
+impl Default for CommitEvents {
+    fn default() -> Self {
+        CommitEvents(broadcast::channel(COMMIT_EVENT_CAPACITY).0)
+    }
 }
 
 impl Commit {
@@ -40,12 +323,429 @@ impl Commit {
     pub fn new() -> Self {
         Commit::default()
     }
+
+    /// Sets an approximate limit on the total memory used by all runs' data, in bytes.
+    ///
+    /// When [`Self::enforce_memory_budget`] finds the commit over budget, it evicts the blob
+    /// sequence data of the least recently accessed runs until the commit is back under budget.
+    /// Scalars are cheap and are never evicted, so the budget may still be exceeded if there are
+    /// enough of them. `None` (the default) means no limit.
+    pub fn memory_budget(&mut self, bytes: Option<usize>) {
+        self.memory_budget = bytes;
+    }
+
+    /// Sets how long [`Self::remove_run`] remembers a removed run. Defaults to
+    /// [`DEFAULT_TOMBSTONE_TTL`].
+    ///
+    /// This should comfortably exceed the time a loader takes to discover runs and synchronize
+    /// them with this commit, since a tombstone that expires sooner can't stop a loader that
+    /// discovered the run before its removal from adding it back.
+    pub fn tombstone_ttl(&mut self, ttl: Duration) {
+        self.tombstones
+            .get_mut()
+            .expect("failed to lock tombstones")
+            .ttl = ttl;
+    }
+
+    /// Gets the number of runs, time series, and points in this commit, without locking any of
+    /// them. Loaders update the totals as they commit data, so they lag the data slightly.
+    pub fn counts(&self) -> Counts {
+        self.counters.snapshot()
+    }
+
+    /// Gets the running totals behind [`Self::counts`]. Pass them to each loader that writes to
+    /// this commit, so that it can keep them up to date.
+    pub fn counters(&self) -> Arc<Counters> {
+        Arc::clone(&self.counters)
+    }
+
+    /// Gets the switch that tells loaders to stop storing blobs while this commit uses too much
+    /// memory, as measured by [`Counts::memory`]. Pass it to each loader that writes to this
+    /// commit, along with its [counters](Self::counters). Shedding is off until
+    /// [watermarks](LoadShedding::set_watermarks) are set.
+    pub fn load_shedding(&self) -> Arc<LoadShedding> {
+        Arc::clone(&self.load_shedding)
+    }
+
+    /// Removes a run and all its data, returning whether it was present.
+    ///
+    /// Leaves a tombstone so that a loader that discovered the run before its removal doesn't
+    /// add it back: see [`Self::generation`]. A loader may still add the run again in a later
+    /// load cycle if its event files are still there.
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn remove_run(&self, run: &Run) -> bool {
+        let mut runs = self.runs.write().expect("failed to write-lock runs map");
+        let removed = runs.remove(run);
+        if let Some(run_data) = &removed {
+            self.counters
+                .uncount(&run_data.read().expect("failed to read-lock run data"));
+        }
+        self.bury(run.clone());
+        removed.is_some()
+    }
+
+    /// Records a tombstone for a run just removed from `self.runs`. Callers must hold the write
+    /// lock on `self.runs`, so that removals and their generations happen in the same order.
+    pub(crate) fn bury(&self, run: Run) {
+        let mut tombstones = self.tombstones.lock().expect("failed to lock tombstones");
+        let now = Instant::now();
+        let ttl = tombstones.ttl;
+        tombstones
+            .runs
+            .retain(|_, (_, removed_at)| now.duration_since(*removed_at) < ttl);
+        tombstones.generation += 1;
+        let generation = tombstones.generation;
+        tombstones.runs.insert(run, (generation, now));
+    }
+
+    /// Gets the generation of the latest run removal, which increases with every removal.
+    ///
+    /// A loader should read this before it looks for runs, and then not add any run that
+    /// [was removed since](Self::removed_since) then, since its view of that run is stale.
+    ///
+    /// # Panics
+    ///
+    /// If the tombstones lock is poisoned.
+    pub fn generation(&self) -> u64 {
+        self.tombstones
+            .lock()
+            .expect("failed to lock tombstones")
+            .generation
+    }
+
+    /// Checks whether `run` was removed after the given [generation](Self::generation), and
+    /// its tombstone hasn't yet expired.
+    ///
+    /// # Panics
+    ///
+    /// If the tombstones lock is poisoned.
+    pub fn removed_since(&self, run: &Run, generation: u64) -> bool {
+        let tombstones = self.tombstones.lock().expect("failed to lock tombstones");
+        match tombstones.runs.get(run) {
+            Some(&(g, removed_at)) => g > generation && removed_at.elapsed() < tombstones.ttl,
+            None => false,
+        }
+    }
+
+    /// Gets this commit's read-only switch. While it's on, loaders skip their load cycles, so
+    /// readers keep seeing the data already committed. Loaders keep their read positions, so when
+    /// it's turned off, the next load cycle picks up where the last one left off.
+    pub fn read_only(&self) -> &ReadOnlySwitch {
+        &self.read_only
+    }
+
+    /// Records that `run_data` has just been accessed by a reader.
+    ///
+    /// Readers should call this when serving a run's blob sequences, so that
+    /// [`Self::enforce_memory_budget`] evicts runs that have gone unused the longest, and so
+    /// that data already evicted from this run is restored on the next load.
+    pub fn touch(&self, run_data: &RunData) {
+        let now = self.clock.fetch_add(1, Ordering::Relaxed) + 1;
+        run_data.last_access.store(now, Ordering::Relaxed);
+    }
+
+    /// Subscribes to notices of new data. Each time a loader commits new points to a run, every
+    /// subscriber receives a [`CommitEvent`] naming the run and the changed tags, and can then
+    /// read just those time series. Events are sent after the run's lock is released, so the new
+    /// data is visible by the time an event is received.
+    ///
+    /// A subscriber that falls more than [`COMMIT_EVENT_CAPACITY`] events behind misses the
+    /// oldest ones, and its next receive reports how many were lost; it should then re-read
+    /// everything it cares about.
+    pub fn subscribe(&self) -> broadcast::Receiver<CommitEvent> {
+        self.events.0.subscribe()
+    }
+
+    /// Gets a handle for announcing new data to [subscribers](Self::subscribe). Writers should
+    /// send a [`CommitEvent`] after committing new points and releasing the run's lock. Sending
+    /// fails harmlessly if there are no subscribers.
+    pub fn notifier(&self) -> broadcast::Sender<CommitEvent> {
+        self.events.0.clone()
+    }
+
+    /// Finds the data class of the time series for `tag` in `run`, without touching its data.
+    ///
+    /// Returns `None` if there is no such run, or no time series with that tag in the run. (A tag
+    /// appears under at most one data class in a run, since the loader fixes each time series'
+    /// data class when it first sees the tag.)
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn data_class_of(&self, run: &Run, tag: &Tag) -> Option<pb::DataClass> {
+        let runs = self.runs.read().expect("failed to read-lock runs map");
+        let run_data = runs.get(run)?.read().expect("failed to read-lock run data");
+        if run_data.scalars.contains_key(tag) {
+            Some(pb::DataClass::Scalar)
+        } else if run_data.tensor_metadata.contains_key(tag) {
+            Some(pb::DataClass::Tensor)
+        } else if run_data.blob_sequences.contains_key(tag)
+            || run_data.blob_references.contains_key(tag)
+            || run_data.blob_sequences.contains_key(&reserved_key(&tag.0))
+            || run_data.blob_references.contains_key(&reserved_key(&tag.0))
+        {
+            Some(pb::DataClass::BlobSequence)
+        } else {
+            None
+        }
+    }
+
+    /// Lists the names of all runs, in sorted order.
+    ///
+    /// Holds the runs lock only long enough to clone the names, so callers that just need the
+    /// list should use this rather than holding the lock while they work through it.
+    ///
+    /// # Panics
+    ///
+    /// If the runs lock is poisoned.
+    pub fn run_names(&self) -> Vec<Run> {
+        let mut names: Vec<Run> = {
+            let runs = self.runs.read().expect("failed to read-lock runs map");
+            runs.keys().cloned().collect()
+        };
+        names.sort();
+        names
+    }
+
+    /// Lists the tags of all scalar time series in `run`, in sorted order. Returns `None` if
+    /// there is no such run.
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn scalar_tags(&self, run: &Run) -> Option<Vec<Tag>> {
+        let runs = self.runs.read().expect("failed to read-lock runs map");
+        let run_data = runs.get(run)?.read().expect("failed to read-lock run data");
+        let mut tags: Vec<Tag> = run_data.scalars.keys().cloned().collect();
+        tags.sort();
+        Some(tags)
+    }
+
+    /// Lists the [groups](tag_group) of tags in `run`, across all data classes, as pairs of
+    /// prefix and number of tags, sorted by prefix. Returns `None` if there is no such run.
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn tag_groups(&self, run: &Run) -> Option<Vec<(String, usize)>> {
+        let runs = self.runs.read().expect("failed to read-lock runs map");
+        let run_data = runs.get(run)?.read().expect("failed to read-lock run data");
+        let tags: BTreeSet<&str> = run_data.tags().map(public_tag).collect();
+        let mut groups: BTreeMap<&str, usize> = BTreeMap::new();
+        for tag in tags {
+            *groups.entry(tag_group(tag)).or_default() += 1;
+        }
+        Some(
+            groups
+                .into_iter()
+                .map(|(prefix, count)| (prefix.to_string(), count))
+                .collect(),
+        )
+    }
+
+    /// Lists the tags in `run` whose [group](tag_group) is `prefix`, across all data classes, in
+    /// sorted order. Returns `None` if there is no such run.
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn tags_in_group(&self, run: &Run, prefix: &str) -> Option<Vec<Tag>> {
+        let runs = self.runs.read().expect("failed to read-lock runs map");
+        let run_data = runs.get(run)?.read().expect("failed to read-lock run data");
+        let tags: BTreeSet<&str> = run_data
+            .tags()
+            .map(public_tag)
+            .filter(|tag| tag_group(tag) == prefix)
+            .collect();
+        Some(tags.into_iter().map(|tag| Tag(tag.to_string())).collect())
+    }
+
+    /// Reads the valid points of the scalar time series for `tag` in `run`, in step order.
+    /// Returns `None` if there is no such run, or no scalar time series with that tag in the run.
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn read_scalars(&self, run: &Run, tag: &Tag) -> Option<Vec<(Step, WallTime, f64)>> {
+        let runs = self.runs.read().expect("failed to read-lock runs map");
+        let run_data = runs.get(run)?.read().expect("failed to read-lock run data");
+        let ts = run_data.scalars.get(tag)?;
+        Some(
+            ts.valid_values()
+                .map(|(step, wall_time, &ScalarValue(value))| (step, wall_time, value))
+                .collect(),
+        )
+    }
+
+    /// Reads the points of a scalar time series like [`Self::read_scalars`], but with each wall
+    /// time given in seconds since the run's start time. See [`RunData::relative_time`].
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn read_scalars_relative(&self, run: &Run, tag: &Tag) -> Option<Vec<(Step, f64, f64)>> {
+        let runs = self.runs.read().expect("failed to read-lock runs map");
+        let run_data = runs.get(run)?.read().expect("failed to read-lock run data");
+        let ts = run_data.scalars.get(tag)?;
+        Some(
+            ts.valid_values()
+                .map(|(step, wall_time, &ScalarValue(value))| {
+                    (step, run_data.relative_time(wall_time), value)
+                })
+                .collect(),
+        )
+    }
+
+    /// Reads the valid points of the scalar time series for `tag` in `run` that match `query`, in
+    /// step order, for charts with a wall-time x-axis. Each point is `(step, time, value)`, where
+    /// `time` is a wall time in seconds since epoch, or since the run's start time if
+    /// `query.relative_time` is set. Returns `None` if there is no such run, or no scalar time
+    /// series with that tag in the run.
+    ///
+    /// Points stay in step order even where a restarted job's wall times go backward, so `time`
+    /// need not be monotonic.
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn query_scalars(
+        &self,
+        run: &Run,
+        tag: &Tag,
+        query: &ScalarQuery,
+    ) -> Option<Vec<(Step, f64, f64)>> {
+        let runs = self.runs.read().expect("failed to read-lock runs map");
+        let run_data = runs.get(run)?.read().expect("failed to read-lock run data");
+        let ts = run_data.scalars.get(tag)?;
+        let time = |wall_time: WallTime| {
+            if query.relative_time {
+                run_data.relative_time(wall_time)
+            } else {
+                f64::from(wall_time)
+            }
+        };
+        Some(
+            ts.valid_values_in_wall_time(&query.wall_times)
+                .filter(|(step, _, _)| query.steps.contains(*step))
+                .map(|(step, wall_time, &ScalarValue(value))| (step, time(wall_time), value))
+                .collect(),
+        )
+    }
+
+    /// Calls `f` on the data of every run, in parallel on the global [`rayon`] thread pool.
+    ///
+    /// Each run is visited exactly once, under its own read lock, so calls never contend with
+    /// each other for a lock. The runs map stays read-locked until all calls return, so no runs
+    /// are added or removed in the meantime, though loaders wait to commit to any run that's
+    /// being visited. Runs are visited in no particular order.
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned, or if `f` panics.
+    pub fn for_each_run_parallel<F>(&self, f: F)
+    where
+        F: Fn(&Run, &RunData) + Sync,
+    {
+        let runs = self.runs.read().expect("failed to read-lock runs map");
+        runs.par_iter().for_each(|(run, run_data)| {
+            let run_data = run_data.read().expect("failed to read-lock run data");
+            f(run, &run_data);
+        });
+    }
+
+    /// Gets a copy of the run-level graph of `run`, if any. See [`RunData::run_graph`].
+    ///
+    /// This counts as an access to the run for [`Self::touch`].
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn run_graph(&self, run: &Run) -> Option<Vec<u8>> {
+        let runs = self.runs.read().expect("failed to read-lock runs map");
+        let run_data = runs.get(run)?.read().expect("failed to read-lock run data");
+        self.touch(&run_data);
+        run_data.run_graph().map(<[u8]>::to_vec)
+    }
+
+    /// Finds the event file that contributed the point at `step` in the time series for `tag` in
+    /// `run`, of any data class, and the byte offset of the point's event record in that file.
+    ///
+    /// Returns `None` if there is no such point, or if its provenance wasn't recorded: the run
+    /// must have been loaded in provenance mode (see [`RunLoader::provenance`]), and points
+    /// restored from saved loader state have no provenance.
+    ///
+    /// [`RunLoader::provenance`]: crate::run::RunLoader::provenance
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn provenance(&self, run: &Run, tag: &Tag, step: Step) -> Option<(PathBuf, u64)> {
+        let runs = self.runs.read().expect("failed to read-lock runs map");
+        let run_data = runs.get(run)?.read().expect("failed to read-lock run data");
+        let source = if let Some(ts) = run_data.scalars.get(tag) {
+            ts.provenance_of(step)
+        } else if let Some(ts) = run_data.blob_sequences.get(tag) {
+            ts.provenance_of(step)
+        } else {
+            let key = reserved_key(&tag.0);
+            run_data.blob_sequences.get(&key)?.provenance_of(step)
+        }?;
+        let path = run_data.event_files.get(source.file as usize)?;
+        Some((path.clone(), source.offset))
+    }
+
+    /// Evicts tensor and blob sequence data from the least recently accessed runs until the
+    /// commit's estimated memory usage is within budget. Returns the names of the evicted runs.
+    ///
+    /// Evicted runs keep their scalars and the metadata for all their time series, but have no
+    /// tensor or blob sequence points until they've been accessed (per [`Self::touch`]) and then
+    /// reloaded; see [`RunData::evicted`]. Does nothing if there is no memory budget.
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn enforce_memory_budget(&self) -> Vec<Run> {
+        let budget = match self.memory_budget {
+            None => return Vec::new(),
+            Some(b) => b,
+        };
+        let runs = self.runs.read().expect("failed to read-lock runs map");
+        let mut total = 0;
+        let mut candidates = Vec::new();
+        for (run, run_data) in runs.iter() {
+            let run_data = run_data.read().expect("failed to read-lock run data");
+            total += run_data.memory_usage();
+            let evictable = run_data.evictable_memory_usage();
+            if evictable > 0 {
+                let last_access = run_data.last_access.load(Ordering::Relaxed);
+                candidates.push((last_access, run, evictable));
+            }
+        }
+        // Least recently accessed first, breaking ties by run name for determinism.
+        candidates.sort_unstable_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+        let mut evicted = Vec::new();
+        for (_, run, evictable) in candidates {
+            if total <= budget {
+                break;
+            }
+            let mut run_data = runs[run].write().expect("failed to write-lock run data");
+            run_data.evict(self.clock.load(Ordering::Relaxed));
+            self.counters.recount(&mut run_data);
+            total -= evictable;
+            evicted.push(run.clone());
+        }
+        evicted
+    }
 }
 
 /// Data for a single run.
 ///
-/// This contains all data and metadata for a run. For now, that data includes only scalars;
-/// tensors and blob sequences will come soon.
+/// This contains all data and metadata for a run: scalars and blob sequences, plus the metadata of
+/// tensor time series.
 #[derive(Debug, Default)]
 pub struct RunData {
     /// The time of the first event recorded for this run.
@@ -54,14 +754,434 @@ pub struct RunData {
     /// runs aren't constantly changing color.
     pub start_time: Option<WallTime>,
 
+    /// Whether `start_time` is the modification time of the run's earliest event file, because
+    /// no event in the run has a usable wall time. Such a start time still orders runs, but may
+    /// be shown differently. Always `false` once any event has set the start time.
+    pub start_time_synthesized: bool,
+
+    /// Distinct `file_version` strings (like `"brain.Event:2"`) reported by this run's event
+    /// files, in the order first seen. Useful for diagnosing which writer produced the data.
+    pub file_versions: Vec<String>,
+
+    /// Event files that have contributed points to this run, indexed by [`FileId`]. Empty unless
+    /// the run is loaded in provenance mode; see [`TimeSeries::provenance`].
+    pub event_files: Vec<PathBuf>,
+
     /// Scalar time series for this run.
     pub scalars: TagStore<ScalarValue>,
 
+    /// Summary metadata of the tensor time series in this run. Tensor points aren't committed, so
+    /// these time series have no data, but readers like the hparams index need only the metadata.
+    pub tensor_metadata: HashMap<Tag, Box<pb::SummaryMetadata>>,
+
     /// Blob sequence time series for this run.
     pub blob_sequences: TagStore<BlobSequenceValue>,
+
+    /// Blob sequence time series whose blobs were left in their event files, to be read on demand
+    /// with [`BlobReference::read_blob`]. Empty unless the run is loaded in blob reference mode;
+    /// see [`RunLoader::blob_references`]. A tag is in at most one of this and
+    /// [`Self::blob_sequences`].
+    ///
+    /// [`RunLoader::blob_references`]: crate::run::RunLoader::blob_references
+    pub blob_references: TagStore<BlobReference>,
+
+    /// Logical time at which this run was last accessed. See [`Commit::touch`].
+    pub last_access: AtomicU64,
+
+    /// If this run's blob sequence points have been evicted to stay within the
+    /// commit's memory budget, the logical time at which that happened.
+    ///
+    /// Loaders should re-read the run from the beginning once it's been accessed after eviction
+    /// (see [`Self::needs_restore`]), and should reset this to `None` when they do.
+    pub evicted: Option<u64>,
+
+    /// What this run contributed to its commit's totals as of its last recount. See
+    /// [`Counters::recount`].
+    counted: Counts,
+}
+
+impl RunData {
+    /// Counts this run's time series and points.
+    pub fn counts(&self) -> Counts {
+        fn points<V>(store: &TagStore<V>) -> usize {
+            store.values().map(|ts| ts.basin.as_slice().len()).sum()
+        }
+        Counts {
+            runs: 1,
+            scalar_series: self.scalars.len(),
+            tensor_series: self.tensor_metadata.len(),
+            blob_sequence_series: self.blob_sequences.len() + self.blob_references.len(),
+            points: points(&self.scalars)
+                + points(&self.blob_sequences)
+                + points(&self.blob_references),
+            memory: self.memory_usage(),
+        }
+    }
+
+    /// Removes the time series for `tag` from whichever store holds it. Returns whether there was
+    /// one.
+    ///
+    /// This doesn't update the commit's [`Counters`], nor stop a loader from adding the tag
+    /// back; to drop a tag for good, use [`RunLoader::drop_tag`].
+    ///
+    /// [`RunLoader::drop_tag`]: crate::run::RunLoader::drop_tag
+    pub fn drop_tag(&mut self, tag: &Tag) -> bool {
+        let scalar = self.scalars.remove(tag).is_some();
+        let tensor = self.tensor_metadata.remove(tag).is_some();
+        let key = reserved_key(&tag.0);
+        let blob_sequence =
+            self.blob_sequences.remove(tag).is_some() | self.blob_sequences.remove(&key).is_some();
+        let blob_reference = self.blob_references.remove(tag).is_some()
+            | self.blob_references.remove(&key).is_some();
+        scalar || tensor || blob_sequence || blob_reference
+    }
+
+    /// Iterates over the keys of all time series in this run, in no particular order. Map them
+    /// through [`public_tag`] to show them to users.
+    pub fn tags(&self) -> impl Iterator<Item = &Tag> {
+        (self.scalars.keys())
+            .chain(self.tensor_metadata.keys())
+            .chain(self.blob_sequences.keys())
+            .chain(self.blob_references.keys())
+    }
+
+    /// Estimates the memory used by the points in this run's time series, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        store_memory_usage(&self.scalars, |_| 0)
+            + store_memory_usage(&self.blob_references, BlobReference::heap_size)
+            + self.evictable_memory_usage()
+    }
+
+    /// Breaks down the estimate of [`Self::memory_usage`] by data class, and finds the `k` time
+    /// series that use the most memory. Like that estimate, this counts the bytes of blob
+    /// sequence values plus a fixed size per point, and is computed from the committed
+    /// points when called.
+    pub fn memory_report(&self, k: usize) -> MemoryReport {
+        let mut tags = Vec::new();
+        let mut add = |data_class, tag: &Tag, bytes| {
+            tags.push((tag.clone(), data_class, bytes));
+            bytes
+        };
+        let scalars = self
+            .scalars
+            .iter()
+            .map(|(tag, ts)| add(pb::DataClass::Scalar, tag, series_memory_usage(ts, |_| 0)))
+            .sum();
+        let blob_sequences: usize = self
+            .blob_sequences
+            .iter()
+            .map(|(tag, ts)| {
+                let bytes = series_memory_usage(ts, |v| v.0.iter().map(Vec::len).sum());
+                add(pb::DataClass::BlobSequence, tag, bytes)
+            })
+            .sum();
+        let blob_references: usize = self
+            .blob_references
+            .iter()
+            .map(|(tag, ts)| {
+                let bytes = series_memory_usage(ts, BlobReference::heap_size);
+                add(pb::DataClass::BlobSequence, tag, bytes)
+            })
+            .sum();
+        // Heaviest first, breaking ties by tag for determinism.
+        tags.sort_unstable_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        tags.truncate(k);
+        MemoryReport {
+            scalars,
+            blob_sequences: blob_sequences + blob_references,
+            heaviest_tags: tags,
+        }
+    }
+
+    /// Estimates the memory used by this run's blob sequence points, which may be evicted, in
+    /// bytes.
+    fn evictable_memory_usage(&self) -> usize {
+        store_memory_usage(&self.blob_sequences, |v| v.0.iter().map(Vec::len).sum())
+    }
+
+    /// Drops all blob sequence points, keeping their time series metadata, and marks this run as
+    /// evicted at logical time `now`.
+    fn evict(&mut self, now: u64) {
+        for ts in self.blob_sequences.values_mut() {
+            ts.basin = Basin::new();
+            ts.provenance = None;
+        }
+        self.evicted = Some(now);
+    }
+
+    /// Whether this run's start time is known. It's unknown for a run with no events that had a
+    /// valid wall time, such as a run whose event files are empty or whose only events were
+    /// dropped, unless the loader [synthesized](Self::start_time_synthesized) one.
+    pub fn has_start_time(&self) -> bool {
+        self.start_time.is_some()
+    }
+
+    /// Gets the number of seconds from this run's start time to `wall_time`.
+    ///
+    /// If the start time is unknown (see [`Self::has_start_time`]), the start is taken to be the
+    /// epoch, so that the relative time is the same as the absolute wall time. This keeps relative
+    /// times well defined, if not meaningful, for degenerate runs.
+    pub fn relative_time(&self, wall_time: WallTime) -> f64 {
+        let start = self.start_time.map_or(0.0, f64::from);
+        f64::from(wall_time) - start
+    }
+
+    /// Whether this run's data has been evicted and then accessed, and so should be restored.
+    pub fn needs_restore(&self) -> bool {
+        match self.evicted {
+            Some(t) => self.last_access.load(Ordering::Relaxed) > t,
+            None => false,
+        }
+    }
+
+    /// Gets the serialized `GraphDef` of this run's run-level graph: the first blob of the
+    /// earliest committed point in the run graph time series, which is committed under
+    /// `reserved_key(GraphDefValue::TAG_NAME)`. Returns `None` if the run has no graph, or if its
+    /// graph has been evicted (see [`Self::evicted`]).
+    pub fn run_graph(&self) -> Option<&[u8]> {
+        let ts = self
+            .blob_sequences
+            .get(&reserved_key(GraphDefValue::TAG_NAME))?;
+        let (_, _, BlobSequenceValue(blobs)) = ts.valid_values().next()?;
+        blobs.first().map(Vec::as_slice)
+    }
+
+    /// Compacts every time series in this run (see [`TimeSeries::compact`]). Returns the number
+    /// of bytes reclaimed.
+    pub fn compact_all(&mut self) -> usize {
+        let scalars: usize = self.scalars.values_mut().map(TimeSeries::compact).sum();
+        let blobs: usize = self
+            .blob_sequences
+            .values_mut()
+            .map(TimeSeries::compact)
+            .sum();
+        let blob_references: usize = self
+            .blob_references
+            .values_mut()
+            .map(TimeSeries::compact)
+            .sum();
+        scalars + blobs + blob_references
+    }
+
+    /// Computes a fingerprint of all data points committed to this run.
+    ///
+    /// The fingerprint covers the tag, step, wall time, and value of every point in every time
+    /// series, visited in a deterministic order, so it's the same for any two loads of the same
+    /// data, even across server restarts, and changes whenever a point is added or changed.
+    /// Summary metadata and the run's start time are not included.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1aHasher::default();
+        // Values that fit in single precision hash as they did when scalars were stored that way.
+        hash_tag_store(&mut hasher, &self.scalars, |h, v| match v.to_f32_exact() {
+            Some(x) => h.write_u32(x.to_bits()),
+            None => h.write_u64(v.0.to_bits()),
+        });
+        hash_tag_store(&mut hasher, &self.blob_sequences, |h, v| {
+            h.write_u64(v.0.len() as u64);
+            for blob in &v.0 {
+                hash_bytes(h, blob);
+            }
+        });
+        // Skipped when empty, so that runs loaded without blob references keep their
+        // fingerprints.
+        if !self.blob_references.is_empty() {
+            hash_tag_store(&mut hasher, &self.blob_references, |h, v| {
+                hash_bytes(h, v.file.0.to_string_lossy().as_bytes());
+                h.write_u64(v.offset);
+                h.write_u64(v.length);
+                h.write_u32(v.value);
+                h.write_u64(v.count as u64);
+            });
+        }
+        hasher.finish()
+    }
+}
+
+/// Prefix of the keys under which the loader commits time series that it synthesizes, like the
+/// run graph, so that they never share a key with a user time series. The loader drops user tags
+/// with this prefix. Readers should show these series under their conventional tags; see
+/// [`public_tag`].
+pub const RESERVED_TAG_PREFIX: &str = "\u{0}reserved/";
+
+/// Gets the key under which the synthesized time series with the given conventional tag is
+/// committed and staged, like `reserved_key(GraphDefValue::TAG_NAME)` for the run graph.
+pub fn reserved_key(name: &str) -> Tag {
+    Tag(format!("{}{}", RESERVED_TAG_PREFIX, name))
+}
+
+/// Gets the tag under which readers should show the time series committed under `key`: its
+/// conventional tag if it's synthesized, or `key` itself otherwise. A user time series may have
+/// the same public tag as a synthesized one, though never the same key.
+pub fn public_tag(key: &Tag) -> &str {
+    key.0.strip_prefix(RESERVED_TAG_PREFIX).unwrap_or(&key.0)
+}
+
+/// Gets the group of a tag for grouped listings: the part of its name before the first `/`, or
+/// the whole name if it has no `/`. Only the first level is grouped, so `"a/b/c"` is in group
+/// `"a"`, and a name starting with `/` is in the group with the empty prefix.
+pub fn tag_group(tag: &str) -> &str {
+    match tag.find('/') {
+        Some(i) => &tag[..i],
+        None => tag,
+    }
+}
+
+/// Estimates the memory used by the points in `store`, given a function that estimates the heap
+/// memory owned by each value.
+fn store_memory_usage<V>(store: &TagStore<V>, heap_size: impl Fn(&V) -> usize) -> usize {
+    store
+        .values()
+        .map(|ts| series_memory_usage(ts, &heap_size))
+        .sum()
+}
+
+/// Estimates the memory used by the points in a single time series. See [`store_memory_usage`].
+fn series_memory_usage<V>(ts: &TimeSeries<V>, heap_size: impl Fn(&V) -> usize) -> usize {
+    let point_size = std::mem::size_of::<(Step, (WallTime, Result<V, DataLoss>))>();
+    ts.basin
+        .as_slice()
+        .iter()
+        .map(|(_, (_, v))| point_size + v.as_ref().map_or(0, &heap_size))
+        .sum()
+}
+
+/// Estimated memory used by a run's points, by data class and by time series. See
+/// [`RunData::memory_report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// Bytes used by scalar time series.
+    pub scalars: usize,
+    /// Bytes used by blob sequence time series.
+    pub blob_sequences: usize,
+    /// The time series that use the most memory, heaviest first, with their data classes and
+    /// bytes used.
+    pub heaviest_tags: Vec<(Tag, pb::DataClass, usize)>,
+}
+
+impl MemoryReport {
+    /// Total bytes used by the run, the same as [`RunData::memory_usage`].
+    pub fn total(&self) -> usize {
+        self.scalars + self.blob_sequences
+    }
+}
+
+/// Feeds every point of every time series in `store` into `hasher`, in order of tag and step.
+fn hash_tag_store<V>(
+    hasher: &mut Fnv1aHasher,
+    store: &TagStore<V>,
+    mut hash_value: impl FnMut(&mut Fnv1aHasher, &V),
+) {
+    hasher.write_u64(store.len() as u64);
+    for tag in store.sorted_tags() {
+        hash_bytes(hasher, tag.0.as_bytes());
+        let points = store[tag].basin.as_slice();
+        hasher.write_u64(points.len() as u64);
+        for (step, (wall_time, value)) in points {
+            hasher.write_i64(step.0);
+            hasher.write_u64(f64::from(*wall_time).to_bits());
+            match value {
+                Ok(v) => {
+                    hasher.write_u8(1);
+                    hash_value(hasher, v);
+                }
+                Err(DataLoss) => hasher.write_u8(0),
+            }
+        }
+    }
+}
+
+/// Finds the index of the first element of `slice` for which `pred` is false, given that `pred`
+/// holds for a prefix of the slice, like `slice::partition_point` (which needs Rust 1.52).
+fn partition_point<T>(slice: &[T], mut pred: impl FnMut(&T) -> bool) -> usize {
+    slice
+        .binary_search_by(|x| {
+            if pred(x) {
+                std::cmp::Ordering::Less
+            } else {
+                std::cmp::Ordering::Greater
+            }
+        })
+        .unwrap_err()
+}
+
+/// Feeds a length-prefixed byte string into `hasher`, so that adjacent strings can't run together.
+fn hash_bytes(hasher: &mut Fnv1aHasher, bytes: &[u8]) {
+    hasher.write_u64(bytes.len() as u64);
+    hasher.write(bytes);
+}
+
+/// A 64-bit FNV-1a hasher.
+///
+/// Unlike the standard library's default hasher, this has a fixed and documented algorithm, so its
+/// output is stable across Rust versions and can be persisted. Integers are hashed in little-endian
+/// order regardless of platform.
+struct Fnv1aHasher(u64);
+
+impl Default for Fnv1aHasher {
+    fn default() -> Self {
+        Fnv1aHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1aHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.write(&[i]);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+pub type TagStore<V> = HashMap<Tag, TimeSeries<V>>;
+
+/// Index of an event file in [`RunData::event_files`].
+pub type FileId = u32;
+
+/// Where a committed point came from. See [`TimeSeries::provenance`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// The event file that the point was read from.
+    pub file: FileId,
+    /// Byte offset of the start of the point's event record in that file.
+    pub offset: u64,
+}
+
+/// Extension methods for [`TagStore`]s.
+pub trait TagStoreExt {
+    /// Gets all tags in this store in lexicographic order.
+    ///
+    /// The store itself iterates in arbitrary order; use this wherever the output must be
+    /// reproducible, as in exports or API responses.
+    fn sorted_tags(&self) -> Vec<&Tag>;
 }
 
-pub type TagStore<V> = HashMap<Tag, TimeSeries<V>>;
+impl<V> TagStoreExt for TagStore<V> {
+    fn sorted_tags(&self) -> Vec<&Tag> {
+        let mut tags: Vec<&Tag> = self.keys().collect();
+        tags.sort_unstable();
+        tags
+    }
+}
 
 #[derive(Debug)]
 pub struct TimeSeries<V> {
@@ -72,7 +1192,39 @@ pub struct TimeSeries<V> {
     ///
     /// See [`TimeSeries::valid_values`] for a client-friendly view that omits `DataLoss` points
     /// and transposes `Step`s into the tuple.
+    ///
+    /// Code that changes the basin of a scalar time series other than through the loader should
+    /// call [`TimeSeries::refresh_value_range`] afterward.
+    ///
+    /// Points are sorted by step. Under [`DuplicateStepPolicy::KeepBoth`], several points may
+    /// share a step, in wall time order; a binary search by step may then land on any of them, so
+    /// readers that need them all should search for both ends of the run of equal steps.
+    ///
+    /// [`DuplicateStepPolicy::KeepBoth`]: crate::reservoir::DuplicateStepPolicy::KeepBoth
     pub basin: Basin<(WallTime, Result<V, DataLoss>)>,
+
+    /// Where each point in the basin came from, as `(step, source)` pairs sorted by step, if the
+    /// run is loaded in provenance mode. Points without provenance (e.g., those
+    /// restored from saved loader state) have no entry.
+    ///
+    /// This is `None`, and costs nothing per point, when provenance mode is off.
+    pub provenance: Option<Vec<(Step, Provenance)>>,
+
+    /// Number of points offered to the loader's reservoir for this time series as of the last
+    /// commit, of which the basin keeps only a sample. Points preempted by a later point with a
+    /// smaller step aren't counted. Zero for time series not built by a loader.
+    ///
+    /// Compare with [`TimeSeries::retained`] to tell how heavily the series was downsampled.
+    pub offered: usize,
+
+    /// Least and greatest finite values in the basin, for scalar time series. See
+    /// [`TimeSeries::value_range`].
+    value_range: Option<(f64, f64)>,
+
+    /// Whether the wall times of the points in the basin never decrease in step order, for
+    /// scalar time series. Kept up to date along with `value_range`. Preemption and clock skew
+    /// between writers can make the two orders differ.
+    wall_times_sorted: bool,
 }
 
 impl<V> TimeSeries<V> {
@@ -81,9 +1233,37 @@ impl<V> TimeSeries<V> {
         TimeSeries {
             metadata,
             basin: Basin::new(),
+            provenance: None,
+            offered: 0,
+            value_range: None,
+            wall_times_sorted: true,
         }
     }
 
+    /// Gets the number of points kept in the basin, including `DataLoss` points. See
+    /// [`TimeSeries::offered`] for how many were offered.
+    pub fn retained(&self) -> usize {
+        self.basin.as_slice().len()
+    }
+
+    /// Gets the display name for this time series, or `""` if none was given.
+    pub fn display_name(&self) -> &str {
+        &self.metadata.display_name
+    }
+
+    /// Gets the Markdown description for this time series, or `""` if none was given.
+    pub fn summary_description(&self) -> &str {
+        &self.metadata.summary_description
+    }
+
+    /// Gets the name of the plugin that owns this time series, if its metadata names one.
+    pub fn plugin_name(&self) -> Option<&str> {
+        self.metadata
+            .plugin_data
+            .as_ref()
+            .map(|pd| pd.plugin_name.as_str())
+    }
+
     /// Gets an iterator over `self.values` that omits `DataLoss` points.
     pub fn valid_values(&self) -> impl Iterator<Item = (Step, WallTime, &V)> {
         self.basin
@@ -91,6 +1271,169 @@ impl<V> TimeSeries<V> {
             .iter()
             .filter_map(|(step, (wall_time, v))| Some((*step, *wall_time, v.as_ref().ok()?)))
     }
+
+    /// Gets the steps of the points in [`Self::valid_values`], in order, without their values.
+    pub fn steps(&self) -> impl Iterator<Item = Step> + '_ {
+        self.basin
+            .as_slice()
+            .iter()
+            .filter(|(_, (_, v))| v.is_ok())
+            .map(|(step, _)| *step)
+    }
+
+    /// Releases memory held for points that are no longer in this time series, such as points
+    /// preempted by a restarted job. Returns the number of bytes reclaimed. Points are unchanged.
+    ///
+    /// The loader does this on its own when a commit leaves a basin mostly empty, so this is only
+    /// needed to reclaim smaller amounts.
+    pub fn compact(&mut self) -> usize {
+        let mut reclaimed = self.basin.compact();
+        if let Some(provenance) = &mut self.provenance {
+            let before = provenance.capacity();
+            provenance.shrink_to_fit();
+            reclaimed +=
+                (before - provenance.capacity()) * std::mem::size_of::<(Step, Provenance)>();
+        }
+        reclaimed
+    }
+
+    /// Gets where the point at `step` came from, if it's known. See [`Self::provenance`]. If
+    /// several points share the step, this may be the source of any of them.
+    pub fn provenance_of(&self, step: Step) -> Option<Provenance> {
+        let provenance = self.provenance.as_ref()?;
+        let i = provenance.binary_search_by_key(&step, |&(s, _)| s).ok()?;
+        Some(provenance[i].1)
+    }
+
+    /// Like [`TimeSeries::valid_values`], but only includes points whose steps lie in `range`.
+    pub fn valid_values_in(&self, range: &StepRange) -> impl Iterator<Item = (Step, WallTime, &V)> {
+        let points = self.basin.as_slice();
+        // Points are sorted by step, so the points in range are contiguous.
+        let (start, end) = if range.is_valid() {
+            let start = range
+                .min
+                .map_or(0, |min| partition_point(points, |(step, _)| *step < min));
+            let end = range.max.map_or(points.len(), |max| {
+                partition_point(points, |(step, _)| *step <= max)
+            });
+            (start, end)
+        } else {
+            (0, 0)
+        };
+        points[start..end]
+            .iter()
+            .filter_map(|(step, (wall_time, v))| Some((*step, *wall_time, v.as_ref().ok()?)))
+    }
+}
+
+impl TimeSeries<ScalarValue> {
+    /// Gets the least and greatest values of the points in this time series, for scaling chart
+    /// axes without scanning every point. Non-finite values (NaN and infinities) are skipped, as
+    /// are `DataLoss` points. Returns `None` if there are no finite values.
+    ///
+    /// This is kept up to date as the loader commits new points, so it takes constant time.
+    pub fn value_range(&self) -> Option<(f64, f64)> {
+        self.value_range
+    }
+
+    /// Estimates the given percentiles (each from 0 to 100) of the finite values in this time
+    /// series, interpolating linearly between the nearest values. Non-finite values are skipped,
+    /// as in [`Self::value_range`]. Gives NaN for every percentile if there are no finite values.
+    ///
+    /// This only sees the points kept in the commit. Long time series are sampled down by the
+    /// loader, so the results are approximate, though unbiased: the sample is uniform.
+    pub fn scalar_percentiles(&self, percentiles: &[f64]) -> Vec<f64> {
+        let mut values: Vec<f64> = self
+            .valid_values()
+            .map(|(_, _, &ScalarValue(x))| x)
+            .filter(|x| x.is_finite())
+            .collect();
+        values.sort_unstable_by(|a, b| a.partial_cmp(b).expect("finite values are ordered"));
+        percentiles
+            .iter()
+            .map(|&p| {
+                if values.is_empty() {
+                    return f64::NAN;
+                }
+                #[allow(clippy::manual_clamp)] // `f64::clamp` needs Rust 1.50
+                let rank = p.max(0.0).min(100.0) / 100.0 * (values.len() - 1) as f64;
+                let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+                values[lo] + (values[hi] - values[lo]) * (rank - lo as f64)
+            })
+            .collect()
+    }
+
+    /// Like [`TimeSeries::valid_values`], but only includes points whose wall times lie in
+    /// `range`. Points are still given in step order.
+    ///
+    /// Points are sorted by step, which usually sorts them by wall time too, and then the points
+    /// in range are found by binary search. Otherwise, as when a job restarted from a checkpoint
+    /// on a host whose clock is behind, every point is scanned.
+    pub fn valid_values_in_wall_time(
+        &self,
+        range: &WallTimeRange,
+    ) -> impl Iterator<Item = (Step, WallTime, &ScalarValue)> {
+        let points = self.basin.as_slice();
+        let (start, end) = if !range.is_valid() {
+            (0, 0)
+        } else if self.wall_times_sorted {
+            let start = range.min.map_or(0, |min| {
+                partition_point(points, |(_, (wall_time, _))| *wall_time < min)
+            });
+            let end = range.max.map_or(points.len(), |max| {
+                partition_point(points, |(_, (wall_time, _))| *wall_time <= max)
+            });
+            (start, end.max(start))
+        } else {
+            (0, points.len())
+        };
+        let range = *range;
+        points[start..end]
+            .iter()
+            .filter(move |(_, (wall_time, _))| range.contains(*wall_time))
+            .filter_map(|(step, (wall_time, v))| Some((*step, *wall_time, v.as_ref().ok()?)))
+    }
+
+    /// Recomputes [`Self::value_range`], and whether wall times are in step order, from all
+    /// points in the basin.
+    pub fn refresh_value_range(&mut self) {
+        self.value_range = None;
+        self.wall_times_sorted = true;
+        self.extend_value_range(0);
+    }
+
+    /// Updates [`Self::value_range`] and wall-time order after a commit that took the basin from `old_len` points to
+    /// its current contents by dropping some of the old points and then appending `added` new
+    /// ones. If no old points were dropped, only the new points need to be scanned.
+    pub(crate) fn note_committed(&mut self, old_len: usize, added: usize) {
+        let kept = self.basin.as_slice().len() - added;
+        if kept == old_len {
+            self.extend_value_range(kept);
+        } else {
+            self.refresh_value_range();
+        }
+    }
+
+    /// Widens [`Self::value_range`] to include the finite values of the points in the basin from
+    /// index `start` on, and checks that their wall times are in order.
+    fn extend_value_range(&mut self, start: usize) {
+        let points = self.basin.as_slice();
+        let mut last_wall_time = start.checked_sub(1).map(|i| points[i].1 .0);
+        for (_, (wall_time, value)) in &points[start..] {
+            if matches!(last_wall_time, Some(last) if *wall_time < last) {
+                self.wall_times_sorted = false;
+            }
+            last_wall_time = Some(*wall_time);
+            let x = match value {
+                Ok(ScalarValue(x)) if x.is_finite() => *x,
+                _ => continue,
+            };
+            self.value_range = Some(match self.value_range {
+                None => (x, x),
+                Some((lo, hi)) => (lo.min(x), hi.max(x)),
+            });
+        }
+    }
 }
 
 /// A value in a time series is corrupt and should be ignored.
@@ -105,8 +1448,70 @@ impl<V> TimeSeries<V> {
 pub struct DataLoss;
 
 /// The value of a scalar time series at a single point.
+///
+/// Scalars are stored in double precision, so that values written as `float64` tensors can keep
+/// their full precision through to readers of the commit and to exports. The data provider API can
+/// only serve single precision (`ScalarData.value` is a `float`), so values are narrowed there. A
+/// loader may round values to single precision as it commits them; see [`ScalarPrecision`].
 #[derive(Debug, Copy, Clone, PartialEq)]
-pub struct ScalarValue(pub f32);
+pub struct ScalarValue(pub f64);
+
+impl ScalarValue {
+    /// Gets this value in single precision, if that represents it exactly. NaN always narrows.
+    pub fn to_f32_exact(self) -> Option<f32> {
+        let narrow = self.0 as f32;
+        if f64::from(narrow) == self.0 || self.0.is_nan() {
+            Some(narrow)
+        } else {
+            None
+        }
+    }
+
+    /// Rounds this value to the given precision. Values too large for single precision become
+    /// infinite.
+    pub fn with_precision(self, precision: ScalarPrecision) -> Self {
+        match precision {
+            ScalarPrecision::F32 => ScalarValue(f64::from(self.0 as f32)),
+            ScalarPrecision::F64 => self,
+        }
+    }
+}
+
+/// Precision in which a loader commits scalar values. See [`ScalarValue::with_precision`].
+///
+/// The default is [`ScalarPrecision::F64`], which keeps values as written. Committed points take
+/// the same memory either way. Values rounded to single precision are saved in [loader
+/// state](crate::loader_state) as 4-byte floats rather than 8-byte doubles, and hash and export as
+/// they would have when written as `float32`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScalarPrecision {
+    /// Round values to single precision, as most writers log them.
+    F32,
+    /// Keep values as written.
+    F64,
+}
+
+#[allow(clippy::derivable_impls)] // deriving needs `#[default]` (Rust 1.62)
+impl Default for ScalarPrecision {
+    fn default() -> Self {
+        ScalarPrecision::F64
+    }
+}
+
+impl std::str::FromStr for ScalarPrecision {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f32" => Ok(ScalarPrecision::F32),
+            "f64" => Ok(ScalarPrecision::F64),
+            _ => Err(format!(
+                "unknown scalar precision {:?}; expected \"f32\" or \"f64\"",
+                s
+            )),
+        }
+    }
+}
 
 /// The value of a blob sequence time series at a single point.
 ///
@@ -114,10 +1519,132 @@ pub struct ScalarValue(pub f32);
 #[derive(Debug, Clone, PartialEq)]
 pub struct BlobSequenceValue(pub Vec<Vec<u8>>);
 
+/// Where to find the value of a blob sequence time series at a single point, in place of the
+/// blobs themselves. See [`RunData::blob_references`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobReference {
+    /// The event file that holds the blob sequence.
+    pub file: EventFileBuf,
+    /// Byte offset of the start of the blob sequence's event record in that file.
+    pub offset: u64,
+    /// Length of that event record, in bytes.
+    pub length: u64,
+    /// Index of the blob sequence among the event's summary values, as written. Zero for graphs
+    /// and run metadata, which are events of their own.
+    pub value: u32,
+    /// Number of blobs in the sequence.
+    pub count: usize,
+    /// How records are delimited in the event file.
+    pub framing: Framing,
+}
+
+impl BlobReference {
+    /// Reads this blob sequence back from its event file in `logdir`. The `metadata` should be
+    /// that of the time series holding this reference, which determines how the summary value is
+    /// converted, as when loading.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the event file no longer has the record that
+    /// this reference was taken from, as when the file has been rewritten.
+    pub fn read_blob<L: Logdir>(
+        &self,
+        logdir: &L,
+        metadata: &pb::SummaryMetadata,
+    ) -> io::Result<BlobSequenceValue> {
+        let invalid = |reason: String| {
+            let message = format!(
+                "blob reference to {} at offset {}: {}",
+                self.file.0.display(),
+                self.offset,
+                reason
+            );
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        };
+        let mut reader = EventFileReader::with_framing(logdir.open(&self.file)?, self.framing);
+        reader.resume_at(self.offset)?;
+        let event = reader.read_event().map_err(|e| invalid(e.to_string()))?;
+        if reader.offset() != self.offset + self.length {
+            return Err(invalid(format!(
+                "record is {} bytes, not {}",
+                reader.offset() - self.offset,
+                self.length
+            )));
+        }
+        use pb::event::What;
+        let value = match event.what {
+            Some(What::GraphDef(bytes)) => EventValue::GraphDef(GraphDefValue(bytes)),
+            Some(What::TaggedRunMetadata(trm)) => {
+                EventValue::GraphDef(GraphDefValue(trm.run_metadata))
+            }
+            Some(What::Summary(sum)) => {
+                match sum
+                    .value
+                    .into_iter()
+                    .nth(self.value as usize)
+                    .and_then(|v| v.value)
+                {
+                    Some(v) => EventValue::Summary(SummaryValue(Box::new(v))),
+                    None => return Err(invalid(format!("no summary value {}", self.value))),
+                }
+            }
+            _ => return Err(invalid("not a blob sequence event".to_string())),
+        };
+        match value.into_blob_sequence(metadata) {
+            Ok(blobs) if blobs.0.len() == self.count => Ok(blobs),
+            Ok(blobs) => Err(invalid(format!(
+                "{} blobs, not {}",
+                blobs.0.len(),
+                self.count
+            ))),
+            Err(DataLoss) => Err(invalid("not a blob sequence".to_string())),
+        }
+    }
+
+    /// Estimates the heap memory used by this reference, in bytes.
+    fn heap_size(&self) -> usize {
+        self.file.0.as_os_str().len()
+    }
+}
+
+/// A log directory from which to read [`BlobReference`]s back, for consumers of a commit (like a
+/// data server or [`export_logdir`]) that don't know the type of the loader's [`Logdir`]. Every
+/// logdir is one; pass the logdir that the runs were loaded from.
+///
+/// [`export_logdir`]: crate::export::export_logdir
+pub trait BlobSource: std::fmt::Debug + Send + Sync {
+    /// Reads a blob sequence back from this log directory. See [`BlobReference::read_blob`].
+    fn read_blob(
+        &self,
+        reference: &BlobReference,
+        metadata: &pb::SummaryMetadata,
+    ) -> io::Result<BlobSequenceValue>;
+}
+
+impl<L: Logdir + std::fmt::Debug + Send + Sync> BlobSource for L {
+    fn read_blob(
+        &self,
+        reference: &BlobReference,
+        metadata: &pb::SummaryMetadata,
+    ) -> io::Result<BlobSequenceValue> {
+        reference.read_blob(self, metadata)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_plugin_name() {
+        use crate::data_compat::{plugin_names, SummaryValue};
+        let value = pb::summary::value::Value::SimpleValue(0.5);
+        let metadata = SummaryValue(Box::new(value)).initial_metadata(None);
+        let ts = TimeSeries::<ScalarValue>::new(metadata);
+        assert_eq!(ts.plugin_name(), Some(plugin_names::SCALARS));
+
+        let ts = TimeSeries::<ScalarValue>::new(Box::default());
+        assert_eq!(ts.plugin_name(), None);
+    }
+
     #[test]
     fn test_valid_values() {
         let mut ts = TimeSeries::<&str>::new(Box::new(pb::SummaryMetadata::default()));
@@ -143,6 +1670,595 @@ mod tests {
                 (Step(5), wall_time, &"five")
             ]
         );
+        assert_eq!(
+            ts.steps().collect::<Vec<_>>(),
+            ts.valid_values()
+                .map(|(step, _, _)| step)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            ts.steps().collect::<Vec<_>>(),
+            vec![Step(0), Step(1), Step(2), Step(5)]
+        );
+    }
+
+    #[test]
+    fn test_valid_values_in() {
+        let mut ts = TimeSeries::<&str>::new(Box::default());
+        let mut rsv = crate::reservoir::StageReservoir::new(10);
+        let wall_time = WallTime::new(0.0).unwrap();
+        for (step, name) in &[
+            (0, "zero"),
+            (2, "two"),
+            (3, "three"),
+            (5, "five"),
+            (8, "eight"),
+        ] {
+            rsv.offer(Step(*step), *name);
+        }
+        rsv.commit_map(&mut ts.basin, |s| {
+            (wall_time, if s == "three" { Err(DataLoss) } else { Ok(s) })
+        });
+
+        let names = |min: Option<i64>, max: Option<i64>| {
+            let range = StepRange {
+                min: min.map(Step),
+                max: max.map(Step),
+            };
+            ts.valid_values_in(&range)
+                .map(|(_, _, &v)| v)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(None, None), vec!["zero", "two", "five", "eight"]);
+        assert_eq!(names(Some(1), Some(5)), vec!["two", "five"]);
+        assert_eq!(names(Some(2), None), vec!["two", "five", "eight"]);
+        assert_eq!(names(None, Some(2)), vec!["zero", "two"]);
+        assert_eq!(names(Some(3), Some(3)), Vec::<&str>::new());
+        assert_eq!(names(Some(9), None), Vec::<&str>::new());
+        assert_eq!(names(Some(5), Some(2)), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_valid_values_in_wall_time() {
+        let wall_times_range = |min: f64, max: f64| WallTimeRange {
+            min: WallTime::new(min),
+            max: WallTime::new(max),
+        };
+        let steps = |ts: &TimeSeries<ScalarValue>, range: WallTimeRange| {
+            ts.valid_values_in_wall_time(&range)
+                .map(|(Step(s), _, _)| s)
+                .collect::<Vec<_>>()
+        };
+
+        // A job writes steps 0 through 4, then restarts from a checkpoint at step 3 on a host
+        // whose clock is behind, preempting steps 3 and 4 and writing steps 3 through 6.
+        let mut ts = TimeSeries::<ScalarValue>::new(Box::default());
+        let mut rsv = crate::reservoir::StageReservoir::new(100);
+        for s in 0..5 {
+            rsv.offer(Step(s), 1000.0 + s as f64);
+        }
+        for s in 3..7 {
+            rsv.offer(Step(s), 500.0 + s as f64);
+        }
+        rsv.commit_map(&mut ts.basin, |t| {
+            (WallTime::new(t).unwrap(), Ok(ScalarValue(t)))
+        });
+        ts.refresh_value_range();
+        assert!(!ts.wall_times_sorted);
+        assert_eq!(steps(&ts, WallTimeRange::all()), vec![0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(steps(&ts, wall_times_range(999.0, 1001.5)), vec![0, 1]);
+        assert_eq!(steps(&ts, wall_times_range(500.0, 504.0)), vec![3, 4]);
+        assert_eq!(
+            steps(&ts, wall_times_range(504.0, 500.0)),
+            Vec::<i64>::new()
+        );
+
+        // With wall times in step order, the same queries give the same results.
+        let mut ts = TimeSeries::<ScalarValue>::new(Box::default());
+        let mut rsv = crate::reservoir::StageReservoir::new(100);
+        for s in 0..5 {
+            rsv.offer(Step(s), 1000.0 + s as f64);
+        }
+        rsv.commit_map(&mut ts.basin, |t| {
+            (WallTime::new(t).unwrap(), Ok(ScalarValue(t)))
+        });
+        ts.note_committed(0, 5);
+        assert!(ts.wall_times_sorted);
+        assert_eq!(steps(&ts, wall_times_range(999.0, 1001.5)), vec![0, 1]);
+        assert_eq!(steps(&ts, wall_times_range(1003.0, 2000.0)), vec![3, 4]);
+        assert_eq!(
+            steps(
+                &ts,
+                WallTimeRange {
+                    min: None,
+                    max: WallTime::new(1002.0)
+                }
+            ),
+            vec![0, 1, 2]
+        );
+        assert_eq!(steps(&ts, wall_times_range(0.0, 999.0)), Vec::<i64>::new());
+
+        // Appending a point whose wall time goes backward turns off the fast path.
+        for s in 5..7 {
+            rsv.offer(Step(s), 500.0 + s as f64);
+        }
+        rsv.commit_map(&mut ts.basin, |t| {
+            (WallTime::new(t).unwrap(), Ok(ScalarValue(t)))
+        });
+        ts.note_committed(5, 2);
+        assert!(!ts.wall_times_sorted);
+        assert_eq!(steps(&ts, wall_times_range(500.0, 510.0)), vec![5, 6]);
+    }
+
+    #[test]
+    fn test_scalar_percentiles() {
+        let wall_time = WallTime::new(0.0).unwrap();
+        let mut ts = TimeSeries::<ScalarValue>::new(Box::default());
+        assert!(ts.scalar_percentiles(&[50.0])[0].is_nan());
+
+        // Small enough to keep every point: exact, skipping non-finite values.
+        let mut rsv = crate::reservoir::StageReservoir::new(10);
+        for (i, x) in [4.0, f32::NAN, 1.0, 3.0, f32::INFINITY, 2.0]
+            .iter()
+            .enumerate()
+        {
+            rsv.offer(Step(i as i64), *x);
+        }
+        rsv.commit_map(&mut ts.basin, |x| {
+            (wall_time, Ok(ScalarValue(f64::from(x))))
+        });
+        assert_eq!(
+            ts.scalar_percentiles(&[0.0, 50.0, 100.0, -5.0, 200.0]),
+            vec![1.0, 2.5, 4.0, 1.0, 4.0]
+        );
+
+        // A uniform distribution on [0, 1), sampled down to 1000 points.
+        let mut ts = TimeSeries::<ScalarValue>::new(Box::default());
+        let mut rsv = crate::reservoir::StageReservoir::new(1000);
+        let n = 100_000;
+        for i in 0..n {
+            rsv.offer(Step(i), i as f32 / n as f32);
+        }
+        rsv.commit_map(&mut ts.basin, |x| {
+            (wall_time, Ok(ScalarValue(f64::from(x))))
+        });
+        assert_eq!(ts.basin.as_slice().len(), 1000);
+        let ps = [50.0, 90.0, 99.0];
+        for (p, actual) in ps.iter().zip(ts.scalar_percentiles(&ps)) {
+            let expected = p / 100.0;
+            assert!(
+                (actual - expected).abs() < 0.05,
+                "p{}: got {}, want about {}",
+                p,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_sorted_tags() {
+        let tags = ["zeta", "alpha", "mu", "beta/2", "beta/10", "Alpha"];
+        let mut forward: TagStore<()> = TagStore::new();
+        let mut backward: TagStore<()> = TagStore::new();
+        for t in &tags {
+            let md = Box::new(pb::SummaryMetadata::default());
+            forward.insert(Tag(t.to_string()), TimeSeries::new(md));
+        }
+        for t in tags.iter().rev() {
+            let md = Box::new(pb::SummaryMetadata::default());
+            backward.insert(Tag(t.to_string()), TimeSeries::new(md));
+        }
+        let expected = ["Alpha", "alpha", "beta/10", "beta/2", "mu", "zeta"]
+            .iter()
+            .map(|t| Tag(t.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(forward.sorted_tags(), expected.iter().collect::<Vec<_>>());
+        assert_eq!(backward.sorted_tags(), expected.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_data_class_of() {
+        use test_data::CommitBuilder;
+
+        let commit = CommitBuilder::new()
+            .scalars("train", "loss", |b| b.build())
+            .blob_sequences("train", "images", |b| b.build())
+            .run("test", None)
+            .build();
+        let data_class_of = |run: &str, tag: &str| {
+            commit.data_class_of(&Run(run.to_string()), &Tag(tag.to_string()))
+        };
+        assert_eq!(data_class_of("train", "loss"), Some(pb::DataClass::Scalar));
+        assert_eq!(
+            data_class_of("train", "images"),
+            Some(pb::DataClass::BlobSequence)
+        );
+        assert_eq!(data_class_of("train", "accuracy"), None);
+        assert_eq!(data_class_of("test", "loss"), None);
+        assert_eq!(data_class_of("nonexistent", "loss"), None);
+    }
+
+    #[test]
+    fn test_tag_groups() {
+        use test_data::CommitBuilder;
+
+        let commit = CommitBuilder::new()
+            .scalars("train", "losses/total", |b| b.build())
+            .scalars("train", "losses/regularization", |b| b.build())
+            .scalars("train", "accuracy", |b| b.build())
+            .blob_sequences("train", "images/input/0", |b| b.build())
+            .build();
+        let train = Run("train".to_string());
+        assert_eq!(
+            commit.tag_groups(&train),
+            Some(vec![
+                ("accuracy".to_string(), 1),
+                ("images".to_string(), 1),
+                ("losses".to_string(), 2),
+            ])
+        );
+        assert_eq!(
+            commit.tags_in_group(&train, "losses"),
+            Some(vec![
+                Tag("losses/regularization".to_string()),
+                Tag("losses/total".to_string()),
+            ])
+        );
+        assert_eq!(commit.tags_in_group(&train, "loss"), Some(vec![]));
+        assert_eq!(commit.tag_groups(&Run("test".to_string())), None);
+
+        assert_eq!(tag_group("a/b/c"), "a");
+        assert_eq!(tag_group("a//b"), "a");
+        assert_eq!(tag_group("/a"), "");
+        assert_eq!(tag_group("a"), "a");
+    }
+
+    #[test]
+    fn test_run_names() {
+        let commit = Commit::new();
+        assert_eq!(commit.run_names(), Vec::<Run>::new());
+        let names: Vec<Run> = ["train", "eval", "test/a", "test"]
+            .iter()
+            .map(|name| Run(name.to_string()))
+            .collect();
+        {
+            let mut runs = commit.runs.write().unwrap();
+            for run in &names {
+                runs.insert(run.clone(), RwLock::new(RunData::default()));
+            }
+        }
+        let listed = commit.run_names();
+        let mut expected = names;
+        expected.sort();
+        assert_eq!(listed, expected);
+        // The lock is released on return, so writers aren't blocked by the list.
+        assert!(commit.runs.try_write().is_ok());
+    }
+
+    #[test]
+    fn test_scalar_accessors() {
+        use test_data::CommitBuilder;
+
+        let commit = CommitBuilder::new()
+            .scalars("train", "loss", |mut b| {
+                b.len(3).eval(|Step(i)| i as f32 * 0.5).build()
+            })
+            .scalars("train", "accuracy", |b| b.build())
+            .blob_sequences("train", "images", |b| b.build())
+            .run("test", None)
+            .build();
+        let run = |name: &str| Run(name.to_string());
+        let tag = |name: &str| Tag(name.to_string());
+
+        assert_eq!(commit.run_names(), vec![run("test"), run("train")]);
+        assert_eq!(
+            commit.scalar_tags(&run("train")),
+            Some(vec![tag("accuracy"), tag("loss")])
+        );
+        assert_eq!(commit.scalar_tags(&run("test")), Some(vec![]));
+        assert_eq!(commit.scalar_tags(&run("nonexistent")), None);
+
+        let points = commit.read_scalars(&run("train"), &tag("loss")).unwrap();
+        assert_eq!(
+            points
+                .iter()
+                .map(|&(step, _, value)| (step, value))
+                .collect::<Vec<_>>(),
+            vec![(Step(0), 0.0), (Step(1), 0.5), (Step(2), 1.0)]
+        );
+        assert_eq!(commit.read_scalars(&run("train"), &tag("images")), None);
+        assert_eq!(commit.read_scalars(&run("test"), &tag("loss")), None);
+    }
+
+    #[test]
+    fn test_relative_time() {
+        use test_data::CommitBuilder;
+
+        let commit = CommitBuilder::new()
+            .scalars("train", "loss", |mut b| {
+                b.len(2).wall_time_start(1000.0).build()
+            })
+            .scalars("degenerate", "loss", |mut b| {
+                b.len(2).wall_time_start(1000.0).build()
+            })
+            // Clear the start time that the scalars set.
+            .run("degenerate", None)
+            .build();
+        let run = |name: &str| Run(name.to_string());
+        let tag = Tag("loss".to_string());
+
+        let runs = commit.runs.read().unwrap();
+        assert!(runs[&run("train")].read().unwrap().has_start_time());
+        assert!(!runs[&run("degenerate")].read().unwrap().has_start_time());
+        drop(runs);
+
+        let relative_times = |name: &str| -> Vec<f64> {
+            let points = commit.read_scalars_relative(&run(name), &tag).unwrap();
+            points.into_iter().map(|(_, t, _)| t).collect()
+        };
+        assert_eq!(relative_times("train"), vec![0.0, 1.0]);
+        // Without a start time, relative times are absolute.
+        assert_eq!(relative_times("degenerate"), vec![1000.0, 1001.0]);
+        assert_eq!(commit.read_scalars_relative(&run("nope"), &tag), None);
+    }
+
+    #[test]
+    fn test_query_scalars() {
+        use test_data::CommitBuilder;
+
+        let commit = CommitBuilder::new()
+            .scalars("train", "loss", |mut b| {
+                b.len(5).wall_time_start(1000.0).build()
+            })
+            .build();
+        let run = Run("train".to_string());
+        let tag = Tag("loss".to_string());
+        let times = |query: ScalarQuery| -> Vec<(i64, f64)> {
+            let points = commit.query_scalars(&run, &tag, &query).unwrap();
+            points.into_iter().map(|(Step(s), t, _)| (s, t)).collect()
+        };
+
+        assert_eq!(
+            commit.query_scalars(&run, &tag, &ScalarQuery::default()),
+            commit.read_scalars_relative(&run, &tag).map(|points| points
+                .into_iter()
+                .map(|(step, t, v)| (step, t + 1000.0, v))
+                .collect())
+        );
+        let wall_times = WallTimeRange {
+            min: WallTime::new(1001.0),
+            max: WallTime::new(1003.5),
+        };
+        assert_eq!(
+            times(ScalarQuery {
+                wall_times,
+                ..Default::default()
+            }),
+            vec![(1, 1001.0), (2, 1002.0), (3, 1003.0)]
+        );
+        // Wall-time bounds are absolute even when reading relative times.
+        assert_eq!(
+            times(ScalarQuery {
+                wall_times,
+                relative_time: true,
+                ..Default::default()
+            }),
+            vec![(1, 1.0), (2, 2.0), (3, 3.0)]
+        );
+        assert_eq!(
+            times(ScalarQuery {
+                steps: StepRange {
+                    min: Some(Step(2)),
+                    max: None,
+                },
+                wall_times,
+                relative_time: true,
+            }),
+            vec![(2, 2.0), (3, 3.0)]
+        );
+        assert_eq!(
+            commit.query_scalars(&Run("nope".to_string()), &tag, &ScalarQuery::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_for_each_run_parallel() {
+        use std::collections::HashSet;
+        use std::sync::Mutex;
+        use test_data::CommitBuilder;
+
+        let mut builder = CommitBuilder::new();
+        for i in 0..100 {
+            builder = builder.scalars(&format!("run{}", i), "loss", |mut b| {
+                b.len(10).eval(move |Step(s)| (i * 10 + s) as f32).build()
+            });
+        }
+        let commit = builder.build();
+
+        let seen = Mutex::new(HashSet::new());
+        let total = AtomicU64::new(0);
+        commit.for_each_run_parallel(|run, run_data| {
+            assert!(
+                seen.lock().unwrap().insert(run.clone()),
+                "{:?} seen twice",
+                run
+            );
+            let sum: f64 = run_data.scalars[&Tag("loss".to_string())]
+                .valid_values()
+                .map(|(_, _, v)| v.0)
+                .sum();
+            total.fetch_add(sum as u64, Ordering::Relaxed);
+        });
+        assert_eq!(seen.into_inner().unwrap().len(), 100);
+        // Every value from 0 to 999 exactly once.
+        assert_eq!(total.into_inner(), 999 * 1000 / 2);
+    }
+
+    #[test]
+    fn test_run_graph() {
+        use test_data::CommitBuilder;
+
+        let graph = |bytes: &[u8]| BlobSequenceValue(vec![bytes.to_vec()]);
+        let graph_key = reserved_key(GraphDefValue::TAG_NAME);
+        let commit = CommitBuilder::new()
+            .blob_sequences("train", &graph_key.0, |mut b| {
+                b.values(vec![graph(b"<first graph>"), graph(b"<second graph>")])
+                    .build()
+            })
+            .blob_sequences("eval", &graph_key.0, |mut b| {
+                b.values(vec![BlobSequenceValue(vec![])]).build()
+            })
+            .scalars("test", "loss", |b| b.build())
+            .build();
+        let run = |name: &str| Run(name.to_string());
+
+        assert_eq!(
+            commit.run_graph(&run("train")),
+            Some(b"<first graph>".to_vec())
+        );
+        // A graph point with no blobs isn't a graph.
+        assert_eq!(commit.run_graph(&run("eval")), None);
+        assert_eq!(commit.run_graph(&run("test")), None);
+        assert_eq!(commit.run_graph(&run("nonexistent")), None);
+    }
+
+    #[test]
+    fn test_load_shedding_watermarks() {
+        let shedding = LoadShedding::default();
+        assert!(!shedding.check(usize::MAX));
+        shedding.set_watermarks(Some(Watermarks {
+            high: 1000,
+            low: 500,
+        }));
+        // Starts only above the high watermark, and stops only below the low one.
+        assert!(!shedding.check(1000));
+        assert!(shedding.check(1001));
+        assert!(shedding.check(500));
+        assert!(shedding.is_active());
+        assert!(!shedding.check(499));
+        assert!(!shedding.check(1000));
+        // Turning it off stops shedding at the next check.
+        assert!(shedding.check(2000));
+        shedding.set_watermarks(None);
+        assert!(!shedding.check(2000));
+        assert_eq!(shedding.shed_points(), 0);
+        shedding.record_shed(3);
+        assert_eq!(shedding.shed_points(), 3);
+    }
+
+    #[test]
+    fn test_enforce_memory_budget() {
+        use test_data::CommitBuilder;
+
+        let blobs = || vec![BlobSequenceValue(vec![vec![0u8; 1000]; 2])];
+        let mut commit = CommitBuilder::new()
+            .scalars("old", "loss", |b| b.build())
+            .blob_sequences("old", "images", |mut b| b.values(blobs()).build())
+            .scalars("new", "loss", |b| b.build())
+            .blob_sequences("new", "images", |mut b| b.values(blobs()).build())
+            .build();
+        let usage = |commit: &Commit, run: &str| -> usize {
+            let runs = commit.runs.read().unwrap();
+            let run_data = runs[run].read().unwrap();
+            run_data.memory_usage()
+        };
+        let before = usage(&commit, "old");
+        assert!(before > 2000);
+
+        // No budget, no eviction.
+        assert_eq!(commit.enforce_memory_budget(), Vec::<Run>::new());
+
+        // Access both runs, "new" more recently.
+        for run in &["old", "new"] {
+            let runs = commit.runs.read().unwrap();
+            commit.touch(&runs[*run].read().unwrap());
+        }
+        // Room for one run's blobs, but not both.
+        commit.memory_budget(Some(before + 100));
+        assert_eq!(commit.enforce_memory_budget(), vec![Run("old".to_string())]);
+        // Now within budget, so nothing more to do.
+        assert_eq!(commit.enforce_memory_budget(), Vec::<Run>::new());
+
+        let runs = commit.runs.read().unwrap();
+        let old = runs["old"].read().unwrap();
+        let new = runs["new"].read().unwrap();
+        assert_eq!(old.blob_sequences["images"].valid_values().count(), 0);
+        assert_eq!(old.scalars["loss"].valid_values().count(), 1);
+        assert_eq!(new.blob_sequences["images"].valid_values().count(), 1);
+        assert!(old.evicted.is_some());
+        assert!(new.evicted.is_none());
+
+        // Accessing the evicted run marks it for restoration.
+        assert!(!old.needs_restore());
+        commit.touch(&old);
+        assert!(old.needs_restore());
+    }
+
+    #[test]
+    fn test_remove_run() {
+        let mut commit = Commit::new();
+        let (train, test) = (Run("train".to_string()), Run("test".to_string()));
+        for run in &[&train, &test] {
+            commit
+                .runs
+                .write()
+                .unwrap()
+                .insert((*run).clone(), Default::default());
+        }
+        let before = commit.generation();
+        assert!(commit.remove_run(&train));
+        assert!(!commit.remove_run(&train));
+        assert_eq!(commit.run_names(), vec![test.clone()]);
+        assert!(commit.removed_since(&train, before));
+        assert!(!commit.removed_since(&train, commit.generation()));
+        assert!(!commit.removed_since(&test, before));
+
+        // Tombstones expire.
+        commit.tombstone_ttl(Duration::from_secs(0));
+        assert!(!commit.removed_since(&train, before));
+    }
+
+    #[test]
+    fn test_memory_report() {
+        use test_data::CommitBuilder;
+
+        let mut builder = CommitBuilder::new().blob_sequences("train", "images", |mut b| {
+            b.values(vec![BlobSequenceValue(vec![vec![0u8; 1_000_000]])])
+                .build()
+        });
+        for i in 0..30 {
+            builder = builder.scalars("train", &format!("loss{:02}", i), |b| b.build());
+        }
+        let commit = builder.build();
+        let runs = commit.runs.read().unwrap();
+        let run_data = runs["train"].read().unwrap();
+
+        let report = run_data.memory_report(DEFAULT_HEAVIEST_TAGS);
+        assert_eq!(report.total(), run_data.memory_usage());
+        assert!(
+            (1_000_000..1_001_000).contains(&report.blob_sequences),
+            "{:?}",
+            report
+        );
+        assert!(
+            report.scalars > 0 && report.scalars < 10_000,
+            "{:?}",
+            report
+        );
+        assert_eq!(report.heaviest_tags.len(), DEFAULT_HEAVIEST_TAGS);
+        assert_eq!(
+            report.heaviest_tags[0],
+            (
+                Tag("images".to_string()),
+                pb::DataClass::BlobSequence,
+                report.blob_sequences
+            )
+        );
+        // Equally heavy scalar time series come in order of tag.
+        assert_eq!(report.heaviest_tags[1].0, Tag("loss00".to_string()));
+        assert_eq!(report.heaviest_tags[1].1, pb::DataClass::Scalar);
     }
 }
 
@@ -176,6 +2292,7 @@ pub mod test_data {
                 .write()
                 .expect("runs[run].write");
             update(&mut run_data);
+            self.0.counters.recount(&mut run_data);
         }
 
         /// Adds a scalar time series, creating the run if it doesn't exist, and setting its start
@@ -339,9 +2456,10 @@ pub mod test_data {
                 let wall_time =
                     WallTime::new(f64::from(self.wall_time_start) + (i as f64)).unwrap();
                 let value = (self.eval)(step);
-                rsv.offer(step, (wall_time, Ok(ScalarValue(value))));
+                rsv.offer(step, (wall_time, Ok(ScalarValue(f64::from(value)))));
             }
             rsv.commit(&mut time_series.basin);
+            time_series.refresh_value_range();
 
             time_series
         }
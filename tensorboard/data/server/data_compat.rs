@@ -37,6 +37,8 @@ pub(crate) mod plugin_names {
     pub const GRAPH_RUN_METADATA: &str = "graph_run_metadata";
     pub const GRAPH_RUN_METADATA_WITH_GRAPH: &str = "graph_run_metadata_graph";
     pub const GRAPH_KERAS_MODEL: &str = "graph_keras_model";
+    pub const HPARAMS: &str = "hparams";
+    pub const HISTOGRAMS: &str = "histograms";
 }
 
 /// The inner contents of a single value from an event.
@@ -61,22 +63,26 @@ pub enum EventValue {
     GraphDef(GraphDefValue),
     TaggedRunMetadata(TaggedRunMetadataValue),
     Summary(SummaryValue),
+    /// A TensorFlow 1.x `simple_value` summary, kept unboxed so that scalar-only runs can stage
+    /// points without allocating. Commits the same as the equivalent [`Summary`][Self::Summary].
+    SimpleValue(f32),
 }
 
 impl EventValue {
     /// Consumes this event value and enriches it into a scalar.
     ///
     /// This supports `simple_value` (TF 1.x) summaries as well as rank-0 tensors of type
-    /// `DT_FLOAT`. Returns `DataLoss` if the value is a `GraphDef`, a tagged run metadata proto,
+    /// `DT_FLOAT` or `DT_DOUBLE`, keeping double precision values exact. Returns `DataLoss` if the value is a `GraphDef`, a tagged run metadata proto,
     /// an unsupported summary, or a tensor of the wrong rank.
     pub fn into_scalar(self) -> Result<ScalarValue, DataLoss> {
         let value_box = match self {
             EventValue::GraphDef(_) => return Err(DataLoss),
             EventValue::TaggedRunMetadata(_) => return Err(DataLoss),
             EventValue::Summary(SummaryValue(v)) => v,
+            EventValue::SimpleValue(f) => return Ok(ScalarValue(f64::from(f))),
         };
         match *value_box {
-            pb::summary::value::Value::SimpleValue(f) => Ok(ScalarValue(f)),
+            pb::summary::value::Value::SimpleValue(f) => Ok(ScalarValue(f64::from(f))),
             pb::summary::value::Value::Tensor(tp) => match tensor_proto_to_scalar(&tp) {
                 Some(f) => Ok(ScalarValue(f)),
                 None => Err(DataLoss),
@@ -92,7 +98,9 @@ impl EventValue {
     ///   - `GraphDef`s;
     ///   - tagged run metadata protos;
     ///   - summaries with TensorFlow 1.x `image` or `audio`;
-    ///   - summaries with `tensor` set to a rank-1 tensor of type `DT_STRING`;
+    ///   - summaries with `tensor` set to a rank-1 tensor of type `DT_STRING`; for image
+    ///     metadata, the first two elements must be the width and height as decimal integers,
+    ///     which is also how TensorFlow 1.x `image`s are converted, so both forms commit alike;
     ///   - for audio metadata, summaries with `tensor` set to a shape-`[k, 2]` tensor of type
     ///     `DT_STRING`, in which case the second axis is assumed to represent string labels and is
     ///     dropped entirely;
@@ -122,6 +130,11 @@ impl EventValue {
                 {
                     let shape = tp.tensor_shape.unwrap_or_default();
                     if shape.dim.len() == 1 {
+                        if is_plugin(metadata, plugin_names::IMAGES)
+                            && !has_image_dimensions(&tp.string_val)
+                        {
+                            return Err(DataLoss);
+                        }
                         Ok(BlobSequenceValue(tp.string_val))
                     } else if shape.dim.len() == 2
                         && shape.dim[1].size == 2
@@ -148,11 +161,12 @@ impl EventValue {
                 }
                 _ => Err(DataLoss),
             },
+            EventValue::SimpleValue(_) => Err(DataLoss),
         }
     }
 }
 
-fn tensor_proto_to_scalar(tp: &pb::TensorProto) -> Option<f32> {
+fn tensor_proto_to_scalar(tp: &pb::TensorProto) -> Option<f64> {
     // Ensure that it's rank-0. Treat an absent `tensor_shape` as an empty message, which happens
     // to imply rank 0.
     match &tp.tensor_shape {
@@ -165,11 +179,25 @@ fn tensor_proto_to_scalar(tp: &pb::TensorProto) -> Option<f32> {
             // Could have data in either `float_val` or `tensor_content`.
             if let Some(f) = tp.float_val.first() {
                 if tp.float_val.len() == 1 {
-                    Some(*f)
+                    Some(f64::from(*f))
                 } else {
                     None
                 }
             } else if let Ok(f) = (&*tp.tensor_content).try_into().map(f32::from_le_bytes) {
+                Some(f64::from(f))
+            } else {
+                None
+            }
+        }
+        Some(DataType::DtDouble) => {
+            // Likewise, in either `double_val` or `tensor_content`.
+            if let Some(f) = tp.double_val.first() {
+                if tp.double_val.len() == 1 {
+                    Some(*f)
+                } else {
+                    None
+                }
+            } else if let Ok(f) = (&*tp.tensor_content).try_into().map(f64::from_le_bytes) {
                 Some(f)
             } else {
                 None
@@ -179,6 +207,14 @@ fn tensor_proto_to_scalar(tp: &pb::TensorProto) -> Option<f32> {
     }
 }
 
+/// Tests whether the blobs of an image summary start with its width and height, as decimal
+/// integers.
+fn has_image_dimensions(blobs: &[Vec<u8>]) -> bool {
+    let is_dimension =
+        |blob: &Vec<u8>| matches!(std::str::from_utf8(blob).map(str::parse::<u64>), Ok(Ok(_)));
+    blobs.len() >= 2 && blobs[..2].iter().all(is_dimension)
+}
+
 /// Tests whether `md` has plugin name `plugin_name`.
 fn is_plugin(md: &pb::SummaryMetadata, plugin_name: &str) -> bool {
     md.plugin_data
@@ -227,6 +263,12 @@ impl GraphDefValue {
 }
 
 impl TaggedRunMetadataValue {
+    /// Prefix for run metadata tags kept apart from summary tags. See
+    /// [`RunMetadataTags::Prefixed`].
+    ///
+    /// [`RunMetadataTags::Prefixed`]: crate::run::RunMetadataTags::Prefixed
+    pub const TAG_PREFIX: &'static str = "__run_metadata__/";
+
     /// Determines the metadata for a time series whose first event is a
     /// [`TaggedRunMetadata`][`EventValue::TaggedRunMetadata`].
     pub fn initial_metadata() -> Box<pb::SummaryMetadata> {
@@ -247,10 +289,11 @@ impl SummaryValue {
     /// Rules, in order of decreasing precedence:
     ///
     ///   - If the initial metadata has a data class, it is taken as authoritative and returned
-    ///     verbatim.
+    ///     verbatim, unless it [conflicts](Self::conflicts_with) with the type of the value.
     ///   - If the summary value is of primitive type, an appropriate plugin metadata value is
     ///     synthesized: e.g. a `simple_value` becomes metadata for the scalars plugin. Any
-    ///     existing metadata is ignored.
+    ///     existing metadata is ignored, except for its display name and description, and its
+    ///     plugin content if it names the same plugin (and the synthesized metadata has none).
     ///   - If the metadata has a known plugin name, the appropriate data class is added: e.g., a
     ///     `"scalars"` metadata gets `DataClass::Scalar`.
     ///   - Otherwise, the metadata is returned as is (or an empty metadata value synthesized if
@@ -261,10 +304,17 @@ impl SummaryValue {
         match (md, &*self.0) {
             // Any summary metadata that sets its own data class is expected to already be in the right
             // form.
-            (Some(md), _) if md.data_class != i32::from(pb::DataClass::Unknown) => Box::new(md),
-            (_, Value::SimpleValue(_)) => blank(plugin_names::SCALARS, pb::DataClass::Scalar),
-            (_, Value::Image(_)) => tf1x_image_metadata(),
-            (_, Value::Audio(_)) => tf1x_audio_metadata(),
+            (Some(md), _)
+                if md.data_class != i32::from(pb::DataClass::Unknown)
+                    && !self.conflicts_with(&md) =>
+            {
+                Box::new(md)
+            }
+            (md, Value::SimpleValue(_)) => {
+                with_descriptions(blank(plugin_names::SCALARS, pb::DataClass::Scalar), md)
+            }
+            (md, Value::Image(_)) => with_descriptions(tf1x_image_metadata(), md),
+            (md, Value::Audio(au)) => with_descriptions(tf1x_audio_metadata(&au.content_type), md),
             (Some(mut md), _) => {
                 // Use given metadata, but first set data class based on plugin name, if known.
                 match md.plugin_data.as_ref().map(|pd| pd.plugin_name.as_str()) {
@@ -278,6 +328,9 @@ impl SummaryValue {
                     | Some(plugin_names::GRAPH_KERAS_MODEL) => {
                         md.data_class = pb::DataClass::BlobSequence.into();
                     }
+                    Some(plugin_names::HPARAMS) => {
+                        md.data_class = pb::DataClass::Tensor.into();
+                    }
                     _ => {}
                 };
                 Box::new(md)
@@ -287,6 +340,47 @@ impl SummaryValue {
     }
 }
 
+impl SummaryValue {
+    /// Checks whether `md` names a built-in plugin that can't own a value of this type, like a
+    /// `simple_value` whose metadata claims the histograms plugin. That usually means a bug in the
+    /// code that wrote the summary.
+    ///
+    /// Only values of primitive type (`simple_value`, `image`, and `audio`) are checked, since
+    /// their plugin is implied by the type. Metadata for custom plugins never conflicts.
+    /// [`Self::initial_metadata`] infers metadata from the value when it conflicts.
+    pub fn conflicts_with(&self, md: &pb::SummaryMetadata) -> bool {
+        use pb::summary::value::Value;
+        let implied = match &*self.0 {
+            Value::SimpleValue(_) => plugin_names::SCALARS,
+            Value::Image(_) => plugin_names::IMAGES,
+            Value::Audio(_) => plugin_names::AUDIO,
+            _ => return false,
+        };
+        match md.plugin_data.as_ref().map(|pd| pd.plugin_name.as_str()) {
+            Some(declared) => is_builtin_plugin(declared) && declared != implied,
+            None => false,
+        }
+    }
+}
+
+/// Checks whether `plugin_name` is one of the [`plugin_names`] with known data.
+fn is_builtin_plugin(plugin_name: &str) -> bool {
+    use plugin_names::*;
+    matches!(
+        plugin_name,
+        SCALARS
+            | IMAGES
+            | AUDIO
+            | GRAPHS
+            | GRAPH_TAGGED_RUN_METADATA
+            | GRAPH_RUN_METADATA
+            | GRAPH_RUN_METADATA_WITH_GRAPH
+            | GRAPH_KERAS_MODEL
+            | HPARAMS
+            | HISTOGRAMS
+    )
+}
+
 impl Debug for GraphDefValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("GraphDefValue")
@@ -303,6 +397,25 @@ impl Debug for TaggedRunMetadataValue {
     }
 }
 
+/// Copies the user-facing display name and description from `original`, if any, onto `md`, along
+/// with its plugin content if `md` has none of its own and names the same plugin.
+fn with_descriptions(
+    mut md: Box<pb::SummaryMetadata>,
+    original: Option<pb::SummaryMetadata>,
+) -> Box<pb::SummaryMetadata> {
+    if let Some(original) = original {
+        md.display_name = original.display_name;
+        md.summary_description = original.summary_description;
+        // Plugin content is kept too, if it's for the same plugin and we didn't need our own.
+        if let (Some(pd), Some(original_pd)) = (md.plugin_data.as_mut(), original.plugin_data) {
+            if pd.content.is_empty() && pd.plugin_name == original_pd.plugin_name {
+                pd.content = original_pd.content;
+            }
+        }
+    }
+    md
+}
+
 /// Creates a summary metadata value with plugin name and data class, but no other contents.
 fn blank(plugin_name: &str, data_class: pb::DataClass) -> Box<pb::SummaryMetadata> {
     blank_with_plugin_content(plugin_name, data_class, Vec::new())
@@ -325,6 +438,16 @@ fn blank_with_plugin_content(
     })
 }
 
+/// Gets the audio plugin encoding for a TensorFlow 1.x audio content type. TensorFlow has only
+/// ever written WAV, and very old writers left the content type empty.
+fn audio_encoding(content_type: &str) -> pb::audio_plugin_data::Encoding {
+    use pb::audio_plugin_data::Encoding;
+    match content_type {
+        "" | "audio/wav" | "audio/x-wav" | "audio/wave" => Encoding::Wav,
+        _ => Encoding::Unknown,
+    }
+}
+
 fn tf1x_image_metadata() -> Box<pb::SummaryMetadata> {
     let plugin_content = pb::ImagePluginData {
         converted_to_tensor: true,
@@ -342,8 +465,11 @@ fn tf1x_image_metadata() -> Box<pb::SummaryMetadata> {
     )
 }
 
-fn tf1x_audio_metadata() -> Box<pb::SummaryMetadata> {
+/// Builds metadata for a TensorFlow 1.x audio summary, recording the encoding named by its
+/// content type.
+fn tf1x_audio_metadata(content_type: &str) -> Box<pb::SummaryMetadata> {
     let plugin_content = pb::AudioPluginData {
+        encoding: audio_encoding(content_type).into(),
         converted_to_tensor: true,
         ..Default::default()
     };
@@ -406,6 +532,86 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_metadata_simple_value_with_histogram_plugin() {
+            let md = pb::SummaryMetadata {
+                plugin_data: Some(PluginData {
+                    plugin_name: plugin_names::HISTOGRAMS.to_string(),
+                    ..Default::default()
+                }),
+                data_class: pb::DataClass::Tensor.into(),
+                ..Default::default()
+            };
+            let v = SummaryValue(Box::new(Value::SimpleValue(0.125)));
+            assert!(v.conflicts_with(&md));
+            let result = v.initial_metadata(Some(md));
+            assert_eq!(
+                *result,
+                *blank(plugin_names::SCALARS, pb::DataClass::Scalar)
+            );
+
+            // Unknown plugins and matching plugins are fine.
+            let md = |plugin_name: &str| pb::SummaryMetadata {
+                plugin_data: Some(PluginData {
+                    plugin_name: plugin_name.to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            assert!(!v.conflicts_with(&md("myplugin")));
+            assert!(!v.conflicts_with(&md(plugin_names::SCALARS)));
+            let tensor = SummaryValue(Box::new(Value::Tensor(pb::TensorProto::default())));
+            assert!(!tensor.conflicts_with(&md(plugin_names::HISTOGRAMS)));
+        }
+
+        #[test]
+        fn test_metadata_tf1x_simple_value_keeps_descriptions() {
+            let md = pb::SummaryMetadata {
+                display_name: "Loss".to_string(),
+                summary_description: "Cross-entropy *loss*.".to_string(),
+                ..Default::default()
+            };
+            let v = SummaryValue(Box::new(Value::SimpleValue(0.125)));
+            let result = v.initial_metadata(Some(md));
+
+            assert_eq!(
+                *result,
+                pb::SummaryMetadata {
+                    plugin_data: Some(PluginData {
+                        plugin_name: plugin_names::SCALARS.to_string(),
+                        ..Default::default()
+                    }),
+                    data_class: pb::DataClass::Scalar.into(),
+                    display_name: "Loss".to_string(),
+                    summary_description: "Cross-entropy *loss*.".to_string(),
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn test_metadata_tf1x_simple_value_keeps_scalars_content() {
+            let md = pb::SummaryMetadata {
+                plugin_data: Some(PluginData {
+                    plugin_name: plugin_names::SCALARS.to_string(),
+                    content: b"preserved!".to_vec(),
+                    ..Default::default()
+                }),
+                display_name: "Loss".to_string(),
+                ..Default::default()
+            };
+            let v = SummaryValue(Box::new(Value::SimpleValue(0.125)));
+            let result = v.initial_metadata(Some(md.clone()));
+
+            assert_eq!(
+                *result,
+                pb::SummaryMetadata {
+                    data_class: pb::DataClass::Scalar.into(),
+                    ..md
+                }
+            );
+        }
+
         #[test]
         fn test_metadata_tf2x_scalar_tensor_without_dataclass() {
             let md = pb::SummaryMetadata {
@@ -442,6 +648,8 @@ mod tests {
         fn test_enrich_simple_value() {
             let v = EventValue::Summary(SummaryValue(Box::new(Value::SimpleValue(0.125))));
             assert_eq!(v.into_scalar(), Ok(ScalarValue(0.125)));
+            let v = EventValue::SimpleValue(0.125);
+            assert_eq!(v.into_scalar(), Ok(ScalarValue(0.125)));
         }
 
         #[test]
@@ -478,6 +686,38 @@ mod tests {
             }
         }
 
+        #[test]
+        fn test_enrich_double_tensors() {
+            // Not representable in single precision.
+            let x = 1.0 + f64::EPSILON;
+            let tensors = vec![
+                pb::TensorProto {
+                    dtype: pb::DataType::DtDouble.into(),
+                    tensor_shape: Some(tensor_shape(&[])),
+                    double_val: vec![x],
+                    ..Default::default()
+                },
+                pb::TensorProto {
+                    dtype: pb::DataType::DtDouble.into(),
+                    tensor_shape: Some(tensor_shape(&[])),
+                    tensor_content: f64::to_le_bytes(x).to_vec(),
+                    ..Default::default()
+                },
+            ];
+            for tensor in tensors {
+                let v = EventValue::Summary(SummaryValue(Box::new(Value::Tensor(tensor.clone()))));
+                assert_eq!(v.into_scalar(), Ok(ScalarValue(x)), "{:?}", &tensor);
+            }
+            // A double-precision value packed as if it were single precision is malformed.
+            let v = EventValue::Summary(SummaryValue(Box::new(Value::Tensor(pb::TensorProto {
+                dtype: pb::DataType::DtDouble.into(),
+                tensor_shape: Some(tensor_shape(&[])),
+                tensor_content: f32::to_le_bytes(0.125).to_vec(),
+                ..Default::default()
+            }))));
+            assert_eq!(v.into_scalar(), Err(DataLoss));
+        }
+
         #[test]
         fn test_enrich_short_tensors() {
             let tensors = vec![
@@ -580,16 +820,6 @@ mod tests {
                     int_val: vec![123],
                     ..Default::default()
                 },
-                pb::TensorProto {
-                    dtype: pb::DataType::DtDouble.into(),
-                    double_val: vec![123.0],
-                    ..Default::default()
-                },
-                pb::TensorProto {
-                    dtype: pb::DataType::DtDouble.into(),
-                    tensor_content: f64::to_le_bytes(123.0).to_vec(),
-                    ..Default::default()
-                },
                 pb::TensorProto::default(),
             ];
             for tensor in tensors {
@@ -702,6 +932,29 @@ mod tests {
             assert_eq!(plugin_data.plugin_name, plugin_names::AUDIO);
             let plugin_content = pb::AudioPluginData::decode(&plugin_data.content[..]).unwrap();
             assert_eq!(plugin_content.converted_to_tensor, true);
+            assert_eq!(
+                plugin_content.encoding,
+                i32::from(pb::audio_plugin_data::Encoding::Wav)
+            );
+        }
+
+        #[test]
+        fn test_metadata_tf1x_audio_content_type() {
+            let encoding = |content_type: &str| {
+                let v = SummaryValue(Box::new(Value::Audio(pb::summary::Audio {
+                    encoded_audio_string: b"abcd".to_vec(),
+                    content_type: content_type.to_string(),
+                    ..Default::default()
+                })));
+                let plugin_data = v.initial_metadata(None).plugin_data.unwrap();
+                pb::AudioPluginData::decode(&plugin_data.content[..])
+                    .unwrap()
+                    .encoding
+            };
+            use pb::audio_plugin_data::Encoding;
+            assert_eq!(encoding("audio/wav"), i32::from(Encoding::Wav));
+            assert_eq!(encoding("audio/x-wav"), i32::from(Encoding::Wav));
+            assert_eq!(encoding("audio/ogg"), i32::from(Encoding::Unknown));
         }
 
         #[test]
@@ -817,6 +1070,59 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_enrich_tf2x_image() {
+            let md = pb::SummaryMetadata {
+                plugin_data: Some(PluginData {
+                    plugin_name: plugin_names::IMAGES.to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let image = |string_val: Vec<Vec<u8>>| {
+                SummaryValue(Box::new(Value::Tensor(pb::TensorProto {
+                    dtype: pb::DataType::DtString.into(),
+                    tensor_shape: Some(tensor_shape(&[string_val.len() as i64])),
+                    string_val,
+                    ..Default::default()
+                })))
+            };
+            let v = image(vec![
+                b"640".to_vec(),
+                b"480".to_vec(),
+                b"\x89PNGabc".to_vec(),
+            ]);
+            let md = v.initial_metadata(Some(md));
+            assert_eq!(md.data_class, i32::from(pb::DataClass::BlobSequence));
+            // Same as the TensorFlow 1.x image in `test_enrich_tf1x_image`.
+            let expected = BlobSequenceValue(vec![
+                b"640".to_vec(),
+                b"480".to_vec(),
+                b"\x89PNGabc".to_vec(),
+            ]);
+            assert_eq!(
+                EventValue::Summary(v).into_blob_sequence(md.as_ref()),
+                Ok(expected)
+            );
+
+            // Images must lead with their dimensions.
+            for bad in [
+                vec![],
+                vec![b"640".to_vec()],
+                vec![b"\x89PNGabc".to_vec(), b"\x89PNGdef".to_vec()],
+                vec![b"640".to_vec(), b"-480".to_vec(), b"\x89PNGabc".to_vec()],
+            ]
+            .iter()
+            {
+                assert_eq!(
+                    EventValue::Summary(image(bad.clone())).into_blob_sequence(md.as_ref()),
+                    Err(DataLoss),
+                    "{:?}",
+                    bad
+                );
+            }
+        }
+
         #[test]
         fn test_enrich_valid_tensor() {
             let v = EventValue::Summary(SummaryValue(Box::new(Value::Tensor(pb::TensorProto {
@@ -973,6 +1279,29 @@ mod tests {
         }
     }
 
+    mod hparams {
+        use super::*;
+
+        #[test]
+        fn test_metadata_hparams_without_dataclass() {
+            let md = pb::SummaryMetadata {
+                plugin_data: Some(PluginData {
+                    plugin_name: plugin_names::HPARAMS.to_string(),
+                    content: b"experiment".to_vec(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let v = SummaryValue(Box::new(Value::Tensor(pb::TensorProto::default())));
+            let result = v.initial_metadata(Some(md.clone()));
+            let expected = pb::SummaryMetadata {
+                data_class: pb::DataClass::Tensor.into(),
+                ..md
+            };
+            assert_eq!(*result, expected);
+        }
+    }
+
     mod unknown {
         use super::*;
 
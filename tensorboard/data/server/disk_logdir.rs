@@ -18,27 +18,43 @@ limitations under the License.
 use log::{error, warn};
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, BufReader};
+use std::io::{self, BufReader, Read};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-use crate::logdir::{EventFileBuf, Logdir, EVENT_FILE_BASENAME_INFIX};
+use crate::gzip::GzipReader;
+use crate::logdir::{is_event_file_name, EventFileBuf, Logdir, RunVersion, GZIP_EVENT_FILE_SUFFIX};
 use crate::types::Run;
 
+/// Default capacity of the read buffer for each event file, in bytes.
+const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
 /// A log directory on local disk.
+#[derive(Debug)]
 pub struct DiskLogdir {
     root: PathBuf,
+    /// Capacity of the read buffer for each event file, in bytes.
+    buffer_size: usize,
 }
 
 impl DiskLogdir {
     /// Creates a `DiskLogdir` with the given root directory.
     pub fn new(root: PathBuf) -> Self {
-        DiskLogdir { root }
+        DiskLogdir {
+            root,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+        }
+    }
+
+    /// Sets the capacity of the read buffer for each event file opened after this call, in bytes.
+    /// Defaults to 8 KiB.
+    pub fn buffer_size(&mut self, bytes: usize) {
+        self.buffer_size = bytes;
     }
 }
 
 impl Logdir for DiskLogdir {
-    type File = BufReader<File>;
+    type File = DiskFile;
 
     fn discover(&self) -> io::Result<HashMap<Run, Vec<EventFileBuf>>> {
         let mut run_map: HashMap<Run, Vec<EventFileBuf>> = HashMap::new();
@@ -57,7 +73,7 @@ impl Logdir for DiskLogdir {
                 continue;
             }
             let filename = dirent.file_name().to_string_lossy();
-            if !filename.contains(EVENT_FILE_BASENAME_INFIX) {
+            if !is_event_file_name(&filename) {
                 continue;
             }
             let run_dir = match dirent.path().parent() {
@@ -97,6 +113,63 @@ impl Logdir for DiskLogdir {
     }
 
     fn open(&self, path: &EventFileBuf) -> io::Result<Self::File> {
-        File::open(self.root.join(&path.0)).map(BufReader::new)
+        let path = self.root.join(&path.0);
+        let file = BufReader::with_capacity(self.buffer_size, File::open(&path)?);
+        let gzip = matches!(
+            path.file_name(),
+            Some(name) if name.to_string_lossy().ends_with(GZIP_EVENT_FILE_SUFFIX)
+        );
+        if gzip {
+            Ok(DiskFile::Gzip(GzipReader::new(file)))
+        } else {
+            Ok(DiskFile::Plain(file))
+        }
+    }
+
+    /// Lists the event files directly in the run's directory, with their sizes and modification
+    /// times, without walking the rest of the log directory.
+    fn run_version(&self, run: &Run) -> io::Result<RunVersion> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(self.root.join(&run.0))? {
+            let path = entry?.path();
+            let is_event_file = matches!(
+                path.file_name(),
+                Some(name) if is_event_file_name(&name.to_string_lossy())
+            );
+            if !is_event_file {
+                continue;
+            }
+            // Follows symlinks, as `discover` does.
+            let metadata = match std::fs::metadata(&path) {
+                Ok(m) if m.is_file() => m,
+                _ => continue,
+            };
+            files.push((
+                EventFileBuf(path),
+                Some(metadata.len()),
+                metadata.modified().ok(),
+            ));
+        }
+        files.sort();
+        Ok(RunVersion(files))
+    }
+}
+
+/// An event file opened from a [`DiskLogdir`].
+///
+/// Files whose names end in [`GZIP_EVENT_FILE_SUFFIX`] are decompressed as they are read. Tailing
+/// a compressed file that is still being written has weaker guarantees than tailing a plain file:
+/// only data that the writer has flushed (with a sync flush) can be read. See [`GzipReader`].
+pub enum DiskFile {
+    Plain(BufReader<File>),
+    Gzip(GzipReader<BufReader<File>>),
+}
+
+impl Read for DiskFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            DiskFile::Plain(f) => f.read(buf),
+            DiskFile::Gzip(f) => f.read(buf),
+        }
     }
 }
@@ -0,0 +1,673 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Diagnostics for log directories whose data doesn't show up as expected.
+//!
+//! [`diagnose`] reads every event file under a log directory once, much like a load cycle, but
+//! instead of storing the data it records what it found: which files were read and how far, which
+//! records were corrupt, and which time series would be loaded (or ignored) under which data
+//! class, after any [metadata overrides](MetadataOverrides). The resulting [`Report`] renders as a
+//! human-readable summary.
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::commit::DEFAULT_HEAVIEST_TAGS;
+use crate::data_compat::{GraphDefValue, SummaryValue, TaggedRunMetadataValue};
+use crate::disk_logdir::DiskLogdir;
+use crate::event_file::{EventFileReader, ReadEventError};
+use crate::logdir::{EventFileBuf, Logdir};
+use crate::metadata_override::MetadataOverrides;
+use crate::proto::tensorboard as pb;
+use crate::run::DeadReason;
+use crate::types::{Run, Tag};
+
+/// Findings about a whole log directory.
+#[derive(Debug)]
+pub struct Report {
+    /// The log directory that was read.
+    pub logdir: PathBuf,
+    /// Findings for each run, sorted by run name.
+    pub runs: Vec<RunReport>,
+    /// Number of metadata overrides in effect.
+    pub overrides: usize,
+    /// Total time taken to discover and read all event files.
+    pub elapsed: Duration,
+}
+
+/// Findings about a single run.
+#[derive(Debug)]
+pub struct RunReport {
+    pub run: Run,
+    /// Findings for each event file in this run, in load order.
+    pub files: Vec<FileReport>,
+    /// Tags of time series that would be loaded, by data class.
+    pub tags: BTreeMap<DataClassName, BTreeSet<Tag>>,
+    /// Tags of time series that will not be loaded because their plugin isn't supported, with the
+    /// name of that plugin (possibly empty).
+    pub unsupported: BTreeMap<Tag, String>,
+    /// Tags of time series whose metadata was changed by a metadata override.
+    pub overridden: BTreeSet<Tag>,
+    /// Time series whose summary metadata changed partway through. Only the first metadata for
+    /// each time series is used when loading.
+    pub conflicts: Vec<Conflict>,
+    /// Encoded size of all values written to time series that would be loaded, by data class.
+    /// Loading keeps only a sample of each time series, so this is an upper bound on their size
+    /// in memory.
+    pub bytes_written: BTreeMap<DataClassName, u64>,
+    /// The time series that would be loaded with the most bytes written, heaviest first.
+    pub heaviest_tags: Vec<(Tag, u64)>,
+}
+
+/// Findings about a single event file.
+#[derive(Debug, Default)]
+pub struct FileReport {
+    pub path: PathBuf,
+    /// Size of the file on disk, in bytes, or `None` if it couldn't be determined.
+    pub size: Option<u64>,
+    /// Value of the first `file_version` event in the file, like `"brain.Event:2"`.
+    pub file_version: Option<String>,
+    /// Number of events read successfully.
+    pub events: usize,
+    /// Number of records that were skipped because of a bad data checksum or an invalid proto.
+    pub corrupt_records: usize,
+    /// Offset of the first corrupt record, in bytes from the start of the (decompressed) stream.
+    pub first_corrupt_offset: Option<u64>,
+    /// If the file couldn't be opened or read to the end, the error that stopped it.
+    pub error: Option<String>,
+    /// If the file couldn't be opened or read to the end, why a loader would stop reading it.
+    /// Always set along with `error`.
+    pub dead: Option<DeadReason>,
+}
+
+/// A time series whose summary metadata differs between events.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub tag: Tag,
+    /// Plugin name and data class from the first event for this time series.
+    pub first: (String, DataClassName),
+    /// Plugin name and data class from the first event that disagreed with `first`.
+    pub later: (String, DataClassName),
+}
+
+/// A data class as described in the report.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DataClassName {
+    Scalars,
+    Tensors,
+    BlobSequences,
+    Unknown,
+}
+
+impl From<pb::DataClass> for DataClassName {
+    fn from(data_class: pb::DataClass) -> Self {
+        match data_class {
+            pb::DataClass::Scalar => DataClassName::Scalars,
+            pb::DataClass::Tensor => DataClassName::Tensors,
+            pb::DataClass::BlobSequence => DataClassName::BlobSequences,
+            pb::DataClass::Unknown => DataClassName::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for DataClassName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DataClassName::Scalars => "scalars",
+            DataClassName::Tensors => "tensors",
+            DataClassName::BlobSequences => "blob sequences",
+            DataClassName::Unknown => "unknown",
+        })
+    }
+}
+
+impl Report {
+    /// Whether any event file could not be opened or read to the end.
+    pub fn has_unreadable_files(&self) -> bool {
+        self.files().any(|f| f.error.is_some())
+    }
+
+    fn files(&self) -> impl Iterator<Item = &FileReport> {
+        self.runs.iter().flat_map(|r| r.files.iter())
+    }
+}
+
+/// Reads all event files under `logdir` and reports on their contents, applying `overrides` to
+/// summary metadata as a loader would.
+///
+/// Fails only if `logdir` is not a directory; problems with individual files are
+/// recorded in the report.
+pub fn diagnose(logdir: &Path, overrides: &MetadataOverrides) -> io::Result<Report> {
+    let start = Instant::now();
+    if !logdir.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "not a directory"));
+    }
+    let disk_logdir = DiskLogdir::new(logdir.to_path_buf());
+    let mut discoveries: Vec<(Run, Vec<EventFileBuf>)> =
+        disk_logdir.discover()?.into_iter().collect();
+    discoveries.sort();
+    let runs = discoveries
+        .into_iter()
+        .map(|(run, filenames)| {
+            let mut report = RunReport {
+                run,
+                files: Vec::new(),
+                tags: BTreeMap::new(),
+                unsupported: BTreeMap::new(),
+                overridden: BTreeSet::new(),
+                conflicts: Vec::new(),
+                bytes_written: BTreeMap::new(),
+                heaviest_tags: Vec::new(),
+            };
+            let mut metadata = HashMap::new();
+            let mut sizes = HashMap::new();
+            for filename in filenames {
+                let file = diagnose_file(&disk_logdir, filename, &mut |event| {
+                    report.observe(&mut metadata, &mut sizes, overrides, event)
+                });
+                report.files.push(file);
+            }
+            for (tag, (plugin_name, data_class)) in metadata {
+                if data_class == DataClassName::Unknown {
+                    report.unsupported.insert(tag, plugin_name);
+                } else {
+                    let size = sizes.get(&tag).copied().unwrap_or(0);
+                    *report.bytes_written.entry(data_class).or_default() += size;
+                    report.heaviest_tags.push((tag.clone(), size));
+                    report.tags.entry(data_class).or_default().insert(tag);
+                }
+            }
+            let heaviest = &mut report.heaviest_tags;
+            heaviest.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            heaviest.truncate(DEFAULT_HEAVIEST_TAGS);
+            report
+        })
+        .collect();
+    Ok(Report {
+        logdir: logdir.to_path_buf(),
+        runs,
+        overrides: overrides.len(),
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Reads a single event file with checksums enabled, calling `handle_event` for each valid event.
+fn diagnose_file(
+    logdir: &DiskLogdir,
+    filename: EventFileBuf,
+    handle_event: &mut dyn FnMut(pb::Event),
+) -> FileReport {
+    let mut report = FileReport {
+        size: std::fs::metadata(&filename.0).ok().map(|m| m.len()),
+        ..Default::default()
+    };
+    let file = match logdir.open(&filename) {
+        Ok(f) => f,
+        Err(e) => {
+            report.error = Some(e.to_string());
+            report.dead = Some(DeadReason::OpenFailed(e.kind()));
+            report.path = filename.0;
+            return report;
+        }
+    };
+    report.path = filename.0;
+    let offset = Rc::new(Cell::new(0));
+    let mut reader = EventFileReader::new(CountingReader {
+        inner: file,
+        count: Rc::clone(&offset),
+    });
+    reader.checksum(true);
+    loop {
+        let record_start = offset.get();
+        match reader.read_event() {
+            Ok(event) => {
+                report.events += 1;
+                if let Some(pb::event::What::FileVersion(v)) = &event.what {
+                    report.file_version.get_or_insert_with(|| v.clone());
+                }
+                handle_event(event);
+            }
+            Err(e) if e.truncated() => break,
+            Err(e @ ReadEventError::ReadRecordError(_)) => {
+                report.error = Some(e.to_string());
+                report.dead = Some(DeadReason::from_read_error(&e, record_start));
+                break;
+            }
+            Err(_) => {
+                report.corrupt_records += 1;
+                report.first_corrupt_offset.get_or_insert(record_start);
+            }
+        }
+    }
+    report
+}
+
+impl RunReport {
+    /// Records the metadata of each time series in `event`. The first metadata seen for each tag
+    /// is kept in `metadata`; any later metadata that disagrees with it is recorded as a conflict.
+    /// The encoded size of each value is added to its tag's total in `sizes`. Summary metadata is
+    /// corrected by `overrides` first.
+    fn observe(
+        &mut self,
+        metadata: &mut HashMap<Tag, (String, DataClassName)>,
+        sizes: &mut HashMap<Tag, u64>,
+        overrides: &MetadataOverrides,
+        event: pb::Event,
+    ) {
+        let mut add_size = |tag: &Tag, size: usize| {
+            *sizes.entry(tag.clone()).or_default() += size as u64;
+        };
+        match event.what {
+            Some(pb::event::What::GraphDef(graph)) => {
+                let tag = Tag(GraphDefValue::TAG_NAME.to_string());
+                add_size(&tag, graph.len());
+                self.observe_metadata(metadata, tag, *GraphDefValue::initial_metadata());
+            }
+            Some(pb::event::What::TaggedRunMetadata(trm)) => {
+                let tag = Tag(trm.tag);
+                add_size(&tag, trm.run_metadata.len());
+                self.observe_metadata(metadata, tag, *TaggedRunMetadataValue::initial_metadata());
+            }
+            Some(pb::event::What::Summary(summary)) => {
+                for value in summary.value {
+                    let tag = Tag(value.tag);
+                    add_size(&tag, value.value.as_ref().map_or(0, |v| v.encoded_len()));
+                    // Only the first event for each time series needs to carry metadata, so an
+                    // event without metadata is consistent with whatever came before it.
+                    if value.metadata.is_none() && metadata.contains_key(&tag) {
+                        continue;
+                    }
+                    if let Some(v) = value.value {
+                        let mut md = SummaryValue(Box::new(v)).initial_metadata(value.metadata);
+                        if overrides.apply(&tag, &mut md) {
+                            self.overridden.insert(tag.clone());
+                        }
+                        self.observe_metadata(metadata, tag, *md);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn observe_metadata(
+        &mut self,
+        metadata: &mut HashMap<Tag, (String, DataClassName)>,
+        tag: Tag,
+        md: pb::SummaryMetadata,
+    ) {
+        let plugin_name = md.plugin_data.map(|p| p.plugin_name).unwrap_or_default();
+        let data_class = pb::DataClass::from_i32(md.data_class)
+            .unwrap_or(pb::DataClass::Unknown)
+            .into();
+        let seen = (plugin_name, data_class);
+        use std::collections::hash_map::Entry;
+        match metadata.entry(tag) {
+            Entry::Vacant(v) => {
+                v.insert(seen);
+            }
+            Entry::Occupied(o) => {
+                let first = o.get();
+                if *first != seen && !self.conflicts.iter().any(|c| &c.tag == o.key()) {
+                    self.conflicts.push(Conflict {
+                        tag: o.key().clone(),
+                        first: first.clone(),
+                        later: seen,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// A reader that counts the bytes read through it, so that record offsets can be reported.
+struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Log directory: {}", self.logdir.display())?;
+        if self.overrides > 0 {
+            writeln!(f, "Metadata overrides in effect: {}", self.overrides)?;
+        }
+        for run in &self.runs {
+            writeln!(f)?;
+            writeln!(f, "Run {:?}", run.run.0)?;
+            for file in &run.files {
+                let path = file.path.strip_prefix(&self.logdir).unwrap_or(&file.path);
+                write!(f, "  {}", path.display())?;
+                match file.size {
+                    Some(size) => write!(f, " ({} bytes", size)?,
+                    None => write!(f, " (unknown size")?,
+                }
+                match &file.file_version {
+                    Some(v) => write!(f, ", version {:?})", v)?,
+                    None => write!(f, ", no file version)")?,
+                }
+                write!(f, ": {} events", file.events)?;
+                if let Some(offset) = file.first_corrupt_offset {
+                    write!(
+                        f,
+                        ", {} corrupt records (first at offset {})",
+                        file.corrupt_records, offset
+                    )?;
+                }
+                match (&file.error, &file.dead) {
+                    (Some(e), Some(dead)) => write!(f, "; UNREADABLE ({}): {}", dead, e)?,
+                    (Some(e), None) => write!(f, "; UNREADABLE: {}", e)?,
+                    (None, _) => (),
+                }
+                writeln!(f)?;
+            }
+            for (data_class, tags) in &run.tags {
+                let tags: Vec<&str> = tags.iter().map(|t| t.0.as_str()).collect();
+                writeln!(f, "  {}: {}", data_class, tags.join(", "))?;
+            }
+            for (tag, plugin_name) in &run.unsupported {
+                writeln!(
+                    f,
+                    "  not loaded: tag {:?} has unsupported plugin {:?}",
+                    tag.0, plugin_name
+                )?;
+            }
+            if !run.overridden.is_empty() {
+                let tags: Vec<String> = run
+                    .overridden
+                    .iter()
+                    .map(|t| format!("{:?}", t.0))
+                    .collect();
+                writeln!(f, "  metadata overridden: {}", tags.join(", "))?;
+            }
+            for c in &run.conflicts {
+                writeln!(
+                    f,
+                    "  metadata conflict: tag {:?} first written by plugin {:?} ({}), later by \
+                     plugin {:?} ({}); later metadata is ignored",
+                    c.tag.0, c.first.0, c.first.1, c.later.0, c.later.1
+                )?;
+            }
+            if !run.bytes_written.is_empty() {
+                let classes: Vec<String> = run
+                    .bytes_written
+                    .iter()
+                    .map(|(data_class, bytes)| format!("{} {}", data_class, bytes))
+                    .collect();
+                writeln!(f, "  bytes written: {}", classes.join(", "))?;
+                let tags: Vec<String> = run
+                    .heaviest_tags
+                    .iter()
+                    .map(|(tag, bytes)| format!("{:?} ({})", tag.0, bytes))
+                    .collect();
+                writeln!(f, "  heaviest tags: {}", tags.join(", "))?;
+            }
+        }
+        writeln!(f)?;
+        let files = self.files().count();
+        let unreadable = self.files().filter(|f| f.error.is_some()).count();
+        let corrupt: usize = self.files().map(|f| f.corrupt_records).sum();
+        writeln!(
+            f,
+            "Read {} runs, {} event files ({} unreadable), {} corrupt records in {:.3}s",
+            self.runs.len(),
+            files,
+            unreadable,
+            corrupt,
+            self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::{BufWriter, Write};
+
+    use crate::masked_crc::MaskedCrc;
+    use crate::tf_record::TfRecord;
+    use crate::types::{Step, WallTime};
+    use crate::writer::SummaryWriteExt;
+
+    #[test]
+    fn test_diagnose() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let train_dir = logdir.path().join("train");
+        fs::create_dir(&train_dir)?;
+        let wall_time = WallTime::new(1234.0).unwrap();
+        let file_version = pb::Event {
+            wall_time: wall_time.into(),
+            what: Some(pb::event::What::FileVersion("brain.Event:2".to_string())),
+            ..Default::default()
+        };
+
+        // A good file, with a scalar, an unsupported plugin, and a metadata conflict.
+        let mut good = BufWriter::new(File::create(train_dir.join("tfevents.1"))?);
+        good.write_event(&file_version)?;
+        good.write_scalar(&Tag("loss".to_string()), Step(0), wall_time, 0.5)?;
+        let custom = |step: i64, plugin_name: &str| pb::Event {
+            step,
+            wall_time: wall_time.into(),
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: vec![pb::summary::Value {
+                    tag: "custom".to_string(),
+                    metadata: Some(pb::SummaryMetadata {
+                        plugin_data: Some(pb::summary_metadata::PluginData {
+                            plugin_name: plugin_name.to_string(),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    value: Some(pb::summary::value::Value::Tensor(Default::default())),
+                    ..Default::default()
+                }],
+            })),
+            ..Default::default()
+        };
+        good.write_event(&custom(0, "my_plugin"))?;
+        good.write_event(&custom(1, "other_plugin"))?;
+        good.write_event(&custom(2, "other_plugin"))?;
+        good.into_inner()?.sync_all()?;
+
+        // A file with two records with bad data checksums, then a good scalar.
+        let mut corrupt = BufWriter::new(File::create(train_dir.join("tfevents.2"))?);
+        corrupt.write_event(&file_version)?;
+        let offset = corrupt.get_ref().metadata()?.len() + corrupt.buffer().len() as u64;
+        for _ in 0..2 {
+            TfRecord {
+                data: b"not an event".to_vec(),
+                data_crc: MaskedCrc(0x12345678),
+            }
+            .write(&mut corrupt)?;
+        }
+        corrupt.write_scalar(&Tag("accuracy".to_string()), Step(0), wall_time, 0.9)?;
+        corrupt.into_inner()?.sync_all()?;
+
+        // A file with a bad length checksum, which can't be read past.
+        let mut unreadable = File::create(logdir.path().join("tfevents.3"))?;
+        unreadable.write_all(&[0xff; 16])?;
+        unreadable.sync_all()?;
+
+        let report = diagnose(logdir.path(), &MetadataOverrides::new())?;
+        assert!(report.has_unreadable_files());
+        let runs: Vec<&str> = report.runs.iter().map(|r| r.run.0.as_str()).collect();
+        assert_eq!(runs, vec![".", "train"]);
+
+        let root = &report.runs[0];
+        assert_eq!(root.files.len(), 1);
+        assert_eq!(root.files[0].size, Some(16));
+        assert_eq!(root.files[0].events, 0);
+        assert!(root.files[0].error.is_some());
+        assert_eq!(
+            root.files[0].dead,
+            Some(DeadReason::BadChecksum { offset: 0 })
+        );
+
+        let train = &report.runs[1];
+        let files: Vec<(usize, usize, Option<u64>, bool)> = train
+            .files
+            .iter()
+            .map(|f| {
+                (
+                    f.events,
+                    f.corrupt_records,
+                    f.first_corrupt_offset,
+                    f.error.is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            files,
+            vec![(5, 0, None, false), (2, 2, Some(offset), false)]
+        );
+        assert_eq!(
+            train.files[0].file_version.as_deref(),
+            Some("brain.Event:2")
+        );
+        let scalars: Vec<&str> = train.tags[&DataClassName::Scalars]
+            .iter()
+            .map(|t| t.0.as_str())
+            .collect();
+        assert_eq!(scalars, vec!["accuracy", "loss"]);
+        // Each `simple_value` encodes as a one-byte key and a 32-bit float. The unsupported
+        // time series isn't counted.
+        let bytes_written: Vec<(DataClassName, u64)> =
+            train.bytes_written.clone().into_iter().collect();
+        assert_eq!(bytes_written, vec![(DataClassName::Scalars, 10)]);
+        assert_eq!(
+            train.heaviest_tags,
+            vec![
+                (Tag("accuracy".to_string()), 5),
+                (Tag("loss".to_string()), 5)
+            ]
+        );
+        assert_eq!(
+            train.unsupported.get(&Tag("custom".to_string())),
+            Some(&"my_plugin".to_string())
+        );
+        assert_eq!(
+            train.conflicts,
+            vec![Conflict {
+                tag: Tag("custom".to_string()),
+                first: ("my_plugin".to_string(), DataClassName::Unknown),
+                later: ("other_plugin".to_string(), DataClassName::Unknown),
+            }]
+        );
+
+        let text = report.to_string();
+        assert!(text.contains("Run \"train\""), "{}", text);
+        assert!(
+            text.contains(&format!(
+                "2 events, 2 corrupt records (first at offset {})",
+                offset
+            )),
+            "{}",
+            text
+        );
+        assert!(text.contains("  scalars: accuracy, loss\n"), "{}", text);
+        assert!(text.contains("  bytes written: scalars 10\n"), "{}", text);
+        assert!(
+            text.contains("  heaviest tags: \"accuracy\" (5), \"loss\" (5)\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("UNREADABLE (checksum mismatch at offset 0)"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("Read 2 runs, 3 event files (1 unreadable), 2 corrupt records"),
+            "{}",
+            text
+        );
+        assert!(!text.contains("overrid"), "{}", text);
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnose_overrides() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::metadata_override::{MetadataOverride, OverrideDataClass};
+
+        let logdir = tempfile::tempdir()?;
+        let mut file = File::create(logdir.path().join("tfevents.1"))?;
+        let wall_time = WallTime::new(1234.0).unwrap();
+        file.write_scalar(&Tag("loss".to_string()), Step(0), wall_time, 0.5)?;
+        file.write_event(&pb::Event {
+            wall_time: wall_time.into(),
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: vec![pb::summary::Value {
+                    tag: "custom".to_string(),
+                    metadata: Some(pb::SummaryMetadata {
+                        plugin_data: Some(pb::summary_metadata::PluginData {
+                            plugin_name: "my_scalars".to_string(),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    value: Some(pb::summary::value::Value::Tensor(Default::default())),
+                    ..Default::default()
+                }],
+            })),
+            ..Default::default()
+        })?;
+        file.sync_all()?;
+
+        let mut overrides = MetadataOverrides::new();
+        overrides.push(MetadataOverride {
+            tag: "custom".to_string(),
+            plugin_name: None,
+            data_class: Some(OverrideDataClass::Scalar),
+        });
+        let report = diagnose(logdir.path(), &overrides)?;
+        let run = &report.runs[0];
+        let scalars: Vec<&str> = run.tags[&DataClassName::Scalars]
+            .iter()
+            .map(|t| t.0.as_str())
+            .collect();
+        assert_eq!(scalars, vec!["custom", "loss"]);
+        assert!(run.unsupported.is_empty());
+        assert_eq!(
+            run.overridden.iter().collect::<Vec<_>>(),
+            vec![&Tag("custom".to_string())]
+        );
+        let text = report.to_string();
+        assert!(
+            text.contains("Metadata overrides in effect: 1\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("  metadata overridden: \"custom\"\n"),
+            "{}",
+            text
+        );
+        Ok(())
+    }
+}
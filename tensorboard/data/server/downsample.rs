@@ -18,42 +18,53 @@ limitations under the License.
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
 
-/// Downsamples `xs` in place to contain at most `k` elements, always including the last element.
+/// Downsamples `xs` in place to contain at most `k` elements, always including the first and last
+/// elements.
 ///
-/// If `k == 0`, then `xs` is cleared. If `k >= xs.len()`, then `xs` is returned unchanged.
-/// Otherwise, a uniformly random sample of `k - 1` of the first `n - 1` elements of `xs` is chosen
-/// and retained, and the final element of `xs` is also retained. The relative order of elements of
-/// `xs` is unchanged.
+/// If `k == 0`, then `xs` is cleared. If `k >= xs.len()`, then `xs` is returned unchanged. If
+/// `k == 1`, only the last element is retained. Otherwise, the first and last elements of `xs` are
+/// retained, along with a uniformly random sample of `k - 2` of the elements between them. The
+/// relative order of elements of `xs` is unchanged.
 ///
 /// More declaratively: among all subsequences of `xs` of length `min(k, xs.len())` that include
-/// the last element, one is selected uniformly at random, and `xs` is updated in place to
-/// represent that subsequence.
+/// the last element and (if `k >= 2`) the first element, one is selected uniformly at random, and
+/// `xs` is updated in place to represent that subsequence.
 ///
 /// The random number generator is initialized with a fixed seed, so this function is
 /// deterministic.
 pub fn downsample<T>(xs: &mut Vec<T>, k: usize) {
     let n = xs.len();
-    if k == 0 {
-        xs.clear();
-        return;
-    }
     if k >= n {
         return;
     }
-
-    let mut rng = ChaCha20Rng::seed_from_u64(0);
-
-    // Choose `k - 1` of the `n - 1` indices to keep, and move their elements into place. Then,
-    // move the last element into place and drop extra elements.
-    let mut indices = rand::seq::index::sample(&mut rng, n - 1, k - 1).into_vec();
-    indices.sort_unstable();
+    let indices = indices(n, k);
     for (dst, src) in indices.into_iter().enumerate() {
         xs.swap(dst, src);
     }
-    xs.swap(k - 1, n - 1);
     xs.truncate(k);
 }
 
+/// Chooses the indices of the elements that [`downsample`] retains from a sequence of length `n`,
+/// in increasing order.
+pub fn indices(n: usize, k: usize) -> Vec<usize> {
+    match k {
+        0 => return Vec::new(),
+        1 => return (n.checked_sub(1)).into_iter().collect(),
+        _ if k >= n => return (0..n).collect(),
+        _ => (),
+    }
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    // Choose `k - 2` of the `n - 2` interior indices, then add the first and last.
+    let mut indices = rand::seq::index::sample(&mut rng, n - 2, k - 2).into_vec();
+    for i in indices.iter_mut() {
+        *i += 1;
+    }
+    indices.push(0);
+    indices.push(n - 1);
+    indices.sort_unstable();
+    indices
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,14 +94,45 @@ mod tests {
     }
 
     #[test]
-    fn test_inorder_plus_last() {
+    fn test_inorder_plus_first_and_last() {
         let xs: Vec<u32> = downsample_cloned(&(0..10000).collect::<Vec<_>>(), 100);
         let mut ys = xs.clone();
         ys.sort_unstable();
         assert_eq!(xs, ys);
+        assert_eq!(xs.first(), Some(&0));
         assert_eq!(xs.last(), Some(&9999));
     }
 
+    #[test]
+    fn test_k_one_keeps_last() {
+        assert_eq!(downsample_cloned(&[1, 2, 3], 1), vec![3]);
+    }
+
+    #[test]
+    fn test_indices_properties() {
+        use rand::Rng;
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        for _ in 0..1000 {
+            let n = rng.gen_range(0, 200);
+            let k = rng.gen_range(0, 220);
+            let is = indices(n, k);
+            assert_eq!(is.len(), k.min(n), "n={}, k={}", n, k);
+            assert!(is.windows(2).all(|w| w[0] < w[1]), "unsorted: {:?}", is);
+            assert!(is.iter().all(|&i| i < n), "out of bounds: {:?}", is);
+            if k >= 1 && n >= 1 {
+                assert_eq!(is.last(), Some(&(n - 1)), "n={}, k={}", n, k);
+            }
+            if k >= 2 && n >= 1 {
+                assert_eq!(is.first(), Some(&0), "n={}, k={}", n, k);
+            }
+            assert_eq!(indices(n, k), is, "not stable: n={}, k={}", n, k);
+
+            let xs: Vec<usize> = (0..n).map(|i| i * 10).collect();
+            let ys = downsample_cloned(&xs, k);
+            assert_eq!(ys, is.iter().map(|&i| xs[i]).collect::<Vec<_>>());
+        }
+    }
+
     #[test]
     fn test_zero_k() {
         for n in 0..3 {
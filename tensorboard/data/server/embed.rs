@@ -0,0 +1,304 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Loading a log directory in process, without the gRPC server.
+//!
+//! A [`Loader`] reads a log directory into a [`Commit`] that the caller can query directly:
+//!
+//! ```no_run
+//! use rustboard_core::embed::{Loader, LoaderOptions};
+//! use rustboard_core::types::{Run, Tag};
+//!
+//! let mut loader = Loader::open("/tmp/logs", LoaderOptions::default());
+//! loader.load_once();
+//! let commit = loader.commit();
+//! for run in commit.run_names() {
+//!     let points = commit.read_scalars(&run, &Tag("loss".to_string()));
+//!     println!("{}: {:?}", run.0, points);
+//! }
+//! ```
+//!
+//! To keep the commit up to date as the log directory grows, call [`Loader::spawn_polling`]
+//! instead, which reloads on a background thread until the returned handle is stopped or dropped.
+
+use log::info;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::commit::{Commit, ReadOnlySwitch, ScalarPrecision, Watermarks};
+use crate::disk_logdir::DiskLogdir;
+use crate::event_file::Framing;
+use crate::logdir::{ColdStartOrder, Logdir, LogdirLoader, PollBackoff, RetentionPolicy};
+use crate::metadata_override::MetadataOverrides;
+use crate::reservoir::DuplicateStepPolicy;
+use crate::run::{
+    DuplicateTagPolicy, EventFileOrder, ExpiredFilePolicy, NegativeStepPolicy, RunMetadataTags,
+    SampleSizes, ZeroWallTime, DEFAULT_MAX_PLUGIN_CONTENT_LEN, DEFAULT_MAX_TAGS,
+};
+use crate::run_filter::{RunFilter, TagFilter};
+
+/// Options for a [`Loader`]. The defaults match those of a [`LogdirLoader`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoaderOptions {
+    /// Number of threads for loading runs in parallel. `0` means one per core.
+    pub reload_threads: usize,
+    /// How many points to keep in each time series.
+    pub sample_sizes: SampleSizes,
+    /// Whether to checksum every record before parsing it, rather than only records that fail
+    /// to parse. See [`LogdirLoader::checksum`].
+    pub checksum: bool,
+    /// Whether to skip records with bad checksums rather than abandoning their event files. See
+    /// [`LogdirLoader::skip_bad_checksums`].
+    pub skip_bad_checksums: bool,
+    /// Maximum data length of a record, in bytes. See [`LogdirLoader::max_record_len`].
+    pub max_record_len: Option<u64>,
+    /// Approximate limit on memory used by loaded data, in bytes. See [`Commit::memory_budget`].
+    pub memory_budget: Option<usize>,
+    /// Memory use at which to stop and resume storing blobs. See [`Commit::load_shedding`].
+    pub blob_watermarks: Option<Watermarks>,
+    /// How to handle events whose wall time is at the epoch.
+    pub zero_wall_time: ZeroWallTime,
+    /// How to handle events whose step is negative.
+    pub negative_steps: NegativeStepPolicy,
+    /// Largest step to accept. See [`RunLoader::max_step`].
+    ///
+    /// [`RunLoader::max_step`]: crate::run::RunLoader::max_step
+    pub max_step: Option<i64>,
+    /// How to handle repeated tags within an event.
+    pub duplicate_tags: DuplicateTagPolicy,
+    /// How to resolve points at the same step.
+    pub duplicate_steps: DuplicateStepPolicy,
+    /// Precision in which to commit scalar values.
+    pub scalar_precision: ScalarPrecision,
+    /// Whether to keep references to blob sequences in their event files instead of the blobs.
+    /// Serve or export them with the log directory as a [`BlobSource`]. See
+    /// [`LogdirLoader::blob_references`].
+    ///
+    /// [`BlobSource`]: crate::commit::BlobSource
+    pub blob_references: bool,
+    /// How records are delimited in event files. See [`LogdirLoader::framing`].
+    pub framing: Framing,
+    /// Where to keep run metadata tags.
+    pub run_metadata_tags: RunMetadataTags,
+    /// How to poll runs that have stopped producing data. See [`LogdirLoader::poll_backoff`].
+    pub poll_backoff: Option<PollBackoff>,
+    /// Order in which to load runs on the first load cycle. See
+    /// [`LogdirLoader::cold_start_order`].
+    pub cold_start_order: ColdStartOrder,
+    /// Order in which to read each run's event files. See [`LogdirLoader::file_order`].
+    pub file_order: EventFileOrder,
+    /// Maximum number of event files to read in each run. See [`LogdirLoader::max_files`].
+    pub max_files: Option<usize>,
+    /// What to do with event files over that limit.
+    pub expired_files: ExpiredFilePolicy,
+    /// Maximum number of distinct tags per run. See [`RunLoader::max_tags`].
+    ///
+    /// [`RunLoader::max_tags`]: crate::run::RunLoader::max_tags
+    pub max_tags: usize,
+    /// Longest plugin content to keep, in bytes. See [`RunLoader::max_plugin_content_len`].
+    ///
+    /// [`RunLoader::max_plugin_content_len`]: crate::run::RunLoader::max_plugin_content_len
+    pub max_plugin_content_len: Option<usize>,
+    /// Maximum number of time series across all runs. See [`LogdirLoader::max_total_tags`].
+    pub max_total_tags: Option<usize>,
+    /// Whether to record which event file each point came from. See [`Commit::provenance`].
+    pub provenance: bool,
+    /// How long to keep points in each run, by wall time. See [`LogdirLoader::retention`].
+    pub retention: RetentionPolicy,
+    /// Which runs to load, by name. See [`LogdirLoader::run_filter`].
+    pub run_filter: RunFilter,
+    /// Corrections to time series metadata, by tag. See [`RunLoader::metadata_overrides`].
+    ///
+    /// [`RunLoader::metadata_overrides`]: crate::run::RunLoader::metadata_overrides
+    pub metadata_overrides: MetadataOverrides,
+    /// Which tags to load in each run. See [`RunLoader::tag_filter`].
+    ///
+    /// [`RunLoader::tag_filter`]: crate::run::RunLoader::tag_filter
+    pub tag_filter: TagFilter,
+}
+
+impl Default for LoaderOptions {
+    fn default() -> Self {
+        LoaderOptions {
+            reload_threads: 0,
+            sample_sizes: SampleSizes::default(),
+            checksum: true,
+            skip_bad_checksums: false,
+            max_record_len: None,
+            memory_budget: None,
+            blob_watermarks: None,
+            zero_wall_time: ZeroWallTime::default(),
+            negative_steps: NegativeStepPolicy::default(),
+            max_step: None,
+            duplicate_tags: DuplicateTagPolicy::default(),
+            duplicate_steps: DuplicateStepPolicy::default(),
+            scalar_precision: ScalarPrecision::default(),
+            blob_references: false,
+            framing: Framing::default(),
+            run_metadata_tags: RunMetadataTags::default(),
+            poll_backoff: None,
+            cold_start_order: ColdStartOrder::default(),
+            file_order: EventFileOrder::default(),
+            max_files: None,
+            expired_files: ExpiredFilePolicy::default(),
+            max_tags: DEFAULT_MAX_TAGS,
+            max_plugin_content_len: Some(DEFAULT_MAX_PLUGIN_CONTENT_LEN),
+            max_total_tags: None,
+            provenance: false,
+            retention: RetentionPolicy::default(),
+            run_filter: RunFilter::default(),
+            metadata_overrides: MetadataOverrides::default(),
+            tag_filter: TagFilter::default(),
+        }
+    }
+}
+
+/// Loads a log directory into a shared [`Commit`]. See the [module docs](self).
+pub struct Loader<L: Logdir> {
+    commit: Arc<Commit>,
+    loader: LogdirLoader<'static, L>,
+}
+
+impl Loader<DiskLogdir> {
+    /// Creates a loader for the log directory at `path` on disk. Does not load any data.
+    pub fn open(path: impl Into<PathBuf>, options: LoaderOptions) -> Self {
+        Loader::new(DiskLogdir::new(path.into()), options)
+    }
+}
+
+impl<L: Logdir> Loader<L>
+where
+    L: Sync + Send + 'static,
+    <L as Logdir>::File: Sync + Send + 'static,
+{
+    /// Creates a loader for the given log directory, with a new, empty commit. Does not load any
+    /// data.
+    ///
+    /// # Panics
+    ///
+    /// If the loader's thread pool cannot be created; see [`LogdirLoader::new`].
+    pub fn new(logdir: L, options: LoaderOptions) -> Self {
+        let mut commit = Commit::new();
+        commit.memory_budget(options.memory_budget);
+        commit
+            .load_shedding()
+            .set_watermarks(options.blob_watermarks);
+        let commit = Arc::new(commit);
+        let mut loader = LogdirLoader::shared(Arc::clone(&commit), logdir, options.reload_threads);
+        loader.sample_sizes(options.sample_sizes);
+        loader.checksum(options.checksum);
+        loader.skip_bad_checksums(options.skip_bad_checksums);
+        loader.max_record_len(options.max_record_len);
+        loader.zero_wall_time(options.zero_wall_time);
+        loader.negative_steps(options.negative_steps);
+        loader.max_step(options.max_step);
+        loader.duplicate_tags(options.duplicate_tags);
+        loader.duplicate_steps(options.duplicate_steps);
+        loader.scalar_precision(options.scalar_precision);
+        loader.blob_references(options.blob_references);
+        loader.framing(options.framing);
+        loader.run_metadata_tags(options.run_metadata_tags);
+        loader.poll_backoff(options.poll_backoff);
+        loader.cold_start_order(options.cold_start_order);
+        loader.file_order(options.file_order);
+        loader.max_files(options.max_files, options.expired_files);
+        loader.max_tags(options.max_tags);
+        loader.max_plugin_content_len(options.max_plugin_content_len);
+        loader.max_total_tags(options.max_total_tags);
+        loader.provenance(options.provenance);
+        loader.retention(options.retention);
+        loader.run_filter(options.run_filter);
+        loader.metadata_overrides(options.metadata_overrides);
+        loader.tag_filter(options.tag_filter);
+        Loader { commit, loader }
+    }
+
+    /// Gets the commit that this loader writes to. The commit may be read at any time, including
+    /// while a load is in progress on another thread.
+    pub fn commit(&self) -> Arc<Commit> {
+        Arc::clone(&self.commit)
+    }
+
+    /// Sets a read-only switch shared with other loaders. See [`LogdirLoader::global_read_only`].
+    ///
+    /// To freeze just this loader, use the commit's own [`Commit::read_only`] switch instead.
+    pub fn global_read_only(&mut self, switch: Option<ReadOnlySwitch>) {
+        self.loader.global_read_only(switch);
+    }
+
+    /// Performs a single load cycle, reading all new data in the log directory into the commit.
+    /// Returns the number of events read. See [`LogdirLoader::reload`].
+    pub fn load_once(&mut self) -> usize {
+        self.loader.reload()
+    }
+
+    /// Moves this loader to a new thread that performs a load cycle, then sleeps for `interval`,
+    /// and repeats, until the returned handle is stopped or dropped.
+    pub fn spawn_polling(self, interval: Duration) -> PollingHandle {
+        let Loader { commit, mut loader } = self;
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let thread = thread::Builder::new()
+            .name("Reloader".to_string())
+            .spawn(move || loop {
+                let start = Instant::now();
+                let events = loader.reload();
+                info!(
+                    "Finished load cycle ({:?}, {} events)",
+                    start.elapsed(),
+                    events
+                );
+                match stop_rx.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                }
+            })
+            .expect("failed to spawn reloader thread");
+        PollingHandle {
+            commit,
+            stop: stop_tx,
+            thread,
+        }
+    }
+}
+
+/// Handle to a [`Loader`] polling on a background thread. See [`Loader::spawn_polling`].
+///
+/// Dropping the handle stops polling after the current load cycle, without waiting for it.
+pub struct PollingHandle {
+    commit: Arc<Commit>,
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl PollingHandle {
+    /// Gets the commit that the polling thread writes to.
+    pub fn commit(&self) -> Arc<Commit> {
+        Arc::clone(&self.commit)
+    }
+
+    /// Stops polling, waiting for any load cycle in progress to finish.
+    ///
+    /// # Panics
+    ///
+    /// If the polling thread panicked.
+    pub fn stop(self) {
+        // The thread may already have exited if it panicked; that's reported by `join` below.
+        let _ = self.stop.send(());
+        self.thread.join().expect("reloader thread panicked");
+    }
+}
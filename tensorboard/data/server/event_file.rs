@@ -16,12 +16,48 @@ limitations under the License.
 //! Parsing for event files containing a stream of `Event` protos.
 
 use prost::{DecodeError, Message};
-use std::io::Read;
+use std::io::{self, Read};
 
+use crate::length_delimited::LengthDelimitedReader;
 use crate::proto::tensorboard::Event;
 use crate::tf_record::{ChecksumError, ReadRecordError, TfRecordReader};
 
-/// A reader for a stream of `Event` protos framed as TFRecords.
+/// How records are delimited within an event file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Framing {
+    /// TFRecords, with masked CRCs of each record's length and data. This is what TensorFlow and
+    /// other summary writers produce.
+    #[serde(rename = "tfrecord")]
+    TfRecord,
+    /// Varint length prefixes with no checksums, as written by protobuf's `writeDelimitedTo`. See
+    /// [`LengthDelimitedReader`].
+    LengthDelimited,
+}
+
+#[allow(clippy::derivable_impls)] // deriving needs `#[default]` (Rust 1.62)
+impl Default for Framing {
+    fn default() -> Self {
+        Framing::TfRecord
+    }
+}
+
+impl std::str::FromStr for Framing {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tfrecord" => Ok(Framing::TfRecord),
+            "length-delimited" => Ok(Framing::LengthDelimited),
+            _ => Err(format!(
+                "unknown framing {:?}; expected \"tfrecord\" or \"length-delimited\"",
+                s
+            )),
+        }
+    }
+}
+
+/// A reader for a stream of `Event` protos framed as TFRecords or, optionally, with plain length
+/// prefixes. See [`Framing`].
 ///
 /// As with [`TfRecordReader`], an event may be read over one or more underlying reads, to support
 /// growing, partially flushed files.
@@ -31,11 +67,19 @@ pub struct EventFileReader<R> {
     /// have been read. Used for determining when to consider this file dead and abandon it.
     last_wall_time: Option<f64>,
     /// Underlying record reader owned by this event file.
-    reader: TfRecordReader<R>,
-    /// Whether to compute CRCs for records before parsing as protos.
+    reader: RecordReader<R>,
+    /// Whether to compute CRCs for records before parsing as protos. Ignored for
+    /// length-delimited files, which have no CRCs.
     checksum: bool,
 }
 
+/// Record reader for an event file's [`Framing`].
+#[derive(Debug)]
+enum RecordReader<R> {
+    TfRecord(TfRecordReader<R>),
+    LengthDelimited(LengthDelimitedReader<R>),
+}
+
 /// Error returned by [`EventFileReader::read_event`].
 #[derive(Debug, thiserror::Error)]
 pub enum ReadEventError {
@@ -65,23 +109,77 @@ impl ReadEventError {
 }
 
 impl<R: Read> EventFileReader<R> {
-    /// Creates a new `EventFileReader` wrapping the given reader.
+    /// Creates a new `EventFileReader` wrapping the given reader, for records framed as
+    /// TFRecords.
     pub fn new(reader: R) -> Self {
+        Self::with_framing(reader, Framing::TfRecord)
+    }
+
+    /// Creates a new `EventFileReader` wrapping the given reader, for records with the given
+    /// framing.
+    pub fn with_framing(reader: R, framing: Framing) -> Self {
+        let reader = match framing {
+            Framing::TfRecord => RecordReader::TfRecord(TfRecordReader::new(reader)),
+            Framing::LengthDelimited => {
+                RecordReader::LengthDelimited(LengthDelimitedReader::new(reader))
+            }
+        };
         Self {
             last_wall_time: None,
-            reader: TfRecordReader::new(reader),
+            reader,
             checksum: true,
         }
     }
 
-    /// Sets whether to compute checksums for records before parsing them as protos.
+    /// Gets the framing of records read by this reader.
+    pub fn framing(&self) -> Framing {
+        match self.reader {
+            RecordReader::TfRecord(_) => Framing::TfRecord,
+            RecordReader::LengthDelimited(_) => Framing::LengthDelimited,
+        }
+    }
+
+    /// Sets whether to compute checksums for records before parsing them as protos. Has no effect
+    /// for [`Framing::LengthDelimited`].
     pub fn checksum(&mut self, yes: bool) {
         self.checksum = yes;
     }
 
+    /// Sets the maximum data length of a record, in bytes. See [`TfRecordReader::max_record_len`].
+    pub fn max_record_len(&mut self, bytes: Option<u64>) {
+        match &mut self.reader {
+            RecordReader::TfRecord(r) => r.max_record_len(bytes),
+            RecordReader::LengthDelimited(r) => r.max_record_len(bytes),
+        }
+    }
+
+    /// Gets the offset of the start of the next record in the file. See
+    /// [`TfRecordReader::offset`].
+    pub fn offset(&self) -> u64 {
+        match &self.reader {
+            RecordReader::TfRecord(r) => r.offset(),
+            RecordReader::LengthDelimited(r) => r.offset(),
+        }
+    }
+
+    /// Skips to the given record offset in the file. See [`TfRecordReader::resume_at`].
+    pub fn resume_at(&mut self, offset: u64) -> io::Result<()> {
+        match &mut self.reader {
+            RecordReader::TfRecord(r) => r.resume_at(offset),
+            RecordReader::LengthDelimited(r) => r.resume_at(offset),
+        }
+    }
+
     /// Reads the next event from the file.
     pub fn read_event(&mut self) -> Result<Event, ReadEventError> {
-        let record = self.reader.read_record()?;
+        let record = match &mut self.reader {
+            RecordReader::TfRecord(r) => r.read_record()?,
+            RecordReader::LengthDelimited(r) => {
+                let data = r.read_record()?;
+                let event = Event::decode(&data[..])?;
+                return self.accept(event);
+            }
+        };
         let event = if self.checksum {
             record.checksum()?;
             Event::decode(&record.data[..])?
@@ -94,6 +192,11 @@ impl<R: Read> EventFileReader<R> {
                 }
             }
         };
+        self.accept(event)
+    }
+
+    /// Checks an event's wall time and records it as the last one read.
+    fn accept(&mut self, event: Event) -> Result<Event, ReadEventError> {
         let wall_time = event.wall_time;
         if wall_time.is_nan() {
             return Err(ReadEventError::NanWallTime(event));
@@ -251,6 +354,58 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_length_delimited() {
+        let events: Vec<Event> = (0..3)
+            .map(|i| Event {
+                what: Some(pb::event::What::FileVersion(format!("event {}", i))),
+                wall_time: 1234.5 + i as f64,
+                ..Event::default()
+            })
+            .collect();
+        let mut file = Vec::new();
+        for e in &events {
+            e.encode_length_delimited(&mut file).unwrap();
+        }
+        file.extend_from_slice(b"\x05junk!");
+        let first_len = events[0].encoded_len();
+        let first_len = (first_len + prost::length_delimiter_len(first_len)) as u64;
+        // Split mid-record to exercise truncation.
+        let (beginning, end) = file.split_at(first_len as usize + 3);
+
+        let sr = ScriptedReader::new(vec![beginning.to_vec(), end.to_vec()]);
+        let mut reader = EventFileReader::with_framing(sr, Framing::LengthDelimited);
+        assert_eq!(reader.framing(), Framing::LengthDelimited);
+        // Irrelevant for this framing.
+        reader.checksum(true);
+
+        assert_eq!(reader.read_event().unwrap(), events[0]);
+        assert_eq!(reader.offset(), first_len);
+        let result = reader.read_event();
+        assert!(result.as_ref().unwrap_err().truncated(), "{:?}", result);
+        assert_eq!(reader.offset(), first_len);
+        assert_eq!(reader.read_event().unwrap(), events[1]);
+        assert_eq!(reader.read_event().unwrap(), events[2]);
+        assert_eq!(reader.last_wall_time(), &Some(1236.5));
+        match reader.read_event() {
+            Err(ReadEventError::InvalidProto(_)) => (),
+            other => panic!("{:?}", other),
+        };
+        let result = reader.read_event();
+        assert!(result.as_ref().unwrap_err().truncated(), "{:?}", result);
+
+        // Resuming skips the first event.
+        let mut reader =
+            EventFileReader::with_framing(Cursor::new(file.clone()), Framing::LengthDelimited);
+        reader.resume_at(first_len).unwrap();
+        assert_eq!(reader.read_event().unwrap(), events[1]);
+
+        // The same bytes don't parse as TFRecords.
+        let mut reader = EventFileReader::new(Cursor::new(file));
+        assert_eq!(reader.framing(), Framing::TfRecord);
+        assert!(reader.read_event().is_err());
+    }
+
     #[test]
     fn test_resume() {
         let event = Event {
@@ -0,0 +1,658 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Writing committed data back out as a log directory.
+//!
+//! [`export_logdir`] writes the points that a [`Commit`] holds to a new log directory with one
+//! event file per run, so that a dashboard can be archived in a small, self-contained form that
+//! any TensorBoard can read later.
+
+use log::warn;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::commit::{
+    reserved_key, BlobReference, BlobSource, Commit, RunData, ScalarValue, TagStore,
+};
+use crate::data_compat::{plugin_names, GraphDefValue};
+use crate::downsample::downsample;
+use crate::proto::tensorboard as pb;
+use crate::run::SampleSizes;
+use crate::types::{Run, Step, Tag, WallTime};
+use crate::writer::{EventFileWriter, SummaryWriteExt};
+
+/// Options for [`export_logdir`].
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Maximum number of points to write for each time series of each data class. Longer time
+    /// series are downsampled, always keeping their first and last points. Defaults to
+    /// [`SampleSizes::unlimited`], which writes every committed point.
+    pub max_points: SampleSizes,
+    /// Log directory from which to read the blobs of runs loaded in [blob reference
+    /// mode](crate::run::RunLoader::blob_references). Required to export such runs.
+    pub blob_source: Option<Arc<dyn BlobSource>>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            max_points: SampleSizes::unlimited(),
+            blob_source: None,
+        }
+    }
+}
+
+/// Writes the committed data of every run to a new log directory at `dest`, returning the number
+/// of runs written.
+///
+/// Each run gets one event file, in the subdirectory of `dest` named by the run (`.` for the root
+/// run). Scalars and blob sequences are written as tensor summaries, with each time series'
+/// summary metadata on its first point only, as TensorFlow writes them. Tensor time series, which
+/// have no committed points, are written as one empty tensor carrying their metadata, at the run's
+/// start time. Run graphs and tagged run metadata are written as `graph_def` and
+/// `tagged_run_metadata` events. Points that were lost to corruption are omitted. Loading the
+/// result gives the same points and metadata as the commit, up to
+/// [downsampling][ExportOptions::max_points].
+///
+/// Each run is copied out of the commit under a single read lock and written after the lock is
+/// released, so its event file reflects one consistent state of the run even if loaders are
+/// still committing. Different runs may be copied at different times.
+///
+/// Blob references are read back from [`ExportOptions::blob_source`] after the lock is released.
+/// Fails with [`io::ErrorKind::InvalidInput`] if a run has any but there's no blob source, or
+/// with the error of any read that fails.
+///
+/// Fails with [`io::ErrorKind::AlreadyExists`] if `dest` exists and is not an empty directory,
+/// so that an export never mixes with existing event files. Runs whose names don't form a
+/// relative path within `dest` (say, because they contain `..`) are skipped with a warning.
+///
+/// # Panics
+///
+/// If any of the commit locks is poisoned.
+pub fn export_logdir(commit: &Commit, dest: &Path, options: &ExportOptions) -> io::Result<usize> {
+    if dest.exists() && std::fs::read_dir(dest)?.next().is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("export destination {} is not empty", dest.display()),
+        ));
+    }
+    let mut exported = 0;
+    for run in commit.run_names() {
+        let dir = match run_dir(dest, &run) {
+            Some(dir) => dir,
+            None => {
+                warn!("Not exporting run {:?}: name is not a relative path", run.0);
+                continue;
+            }
+        };
+        let snapshot = {
+            let runs = commit.runs.read().expect("failed to read-lock runs map");
+            let run_data = match runs.get(&run) {
+                Some(run_data) => run_data,
+                None => continue, // removed since we listed the runs
+            };
+            let run_data = run_data.read().expect("failed to read-lock run data");
+            RunSnapshot::new(&run_data, &options.max_points)
+        };
+        let snapshot = snapshot.read_blob_references(&run, options.blob_source.as_deref())?;
+        snapshot.write(&dir)?;
+        exported += 1;
+    }
+    Ok(exported)
+}
+
+/// Gets the directory under `dest` for a run's event file, or `None` if the run name isn't a path
+/// made only of ordinary components (or `.`), since it might then lead outside of `dest`.
+fn run_dir(dest: &Path, run: &Run) -> Option<PathBuf> {
+    let mut dir = dest.to_path_buf();
+    for component in Path::new(&run.0).components() {
+        match component {
+            Component::Normal(name) => dir.push(name),
+            // Skip `.` rather than joining it, since `create_dir_all` can't create `a/b/.`.
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+    Some(dir)
+}
+
+/// Points of one time series, copied out of a commit.
+struct SeriesSnapshot<V> {
+    tag: Tag,
+    metadata: pb::SummaryMetadata,
+    points: Vec<(Step, WallTime, V)>,
+}
+
+/// Copies the valid points of every time series in `store`, downsampled to at most `max_points`
+/// each, in tag order.
+fn snapshot_store<V, W>(
+    store: &TagStore<V>,
+    max_points: usize,
+    copy: fn(&V) -> W,
+) -> Vec<SeriesSnapshot<W>> {
+    let mut result: Vec<SeriesSnapshot<W>> = store
+        .iter()
+        .map(|(tag, ts)| {
+            // Downsample before copying, so that points that won't be written are never cloned.
+            let mut points: Vec<(Step, WallTime, &V)> = ts.valid_values().collect();
+            downsample(&mut points, max_points);
+            SeriesSnapshot {
+                tag: tag.clone(),
+                metadata: (*ts.metadata).clone(),
+                points: points
+                    .into_iter()
+                    .map(|(step, wall_time, v)| (step, wall_time, copy(v)))
+                    .collect(),
+            }
+        })
+        .collect();
+    result.sort_by(|a, b| a.tag.cmp(&b.tag));
+    result
+}
+
+/// Everything that [`export_logdir`] writes for one run.
+struct RunSnapshot {
+    start_time: Option<WallTime>,
+    scalars: Vec<SeriesSnapshot<ScalarValue>>,
+    /// Tensor time series, which have metadata but no committed points.
+    tensors: Vec<(Tag, pb::SummaryMetadata)>,
+    blob_sequences: Vec<SeriesSnapshot<Vec<Vec<u8>>>>,
+    /// Blob sequences still to be read back; see [`Self::read_blob_references`].
+    blob_references: Vec<SeriesSnapshot<BlobReference>>,
+}
+
+impl RunSnapshot {
+    fn new(run_data: &RunData, max_points: &SampleSizes) -> Self {
+        RunSnapshot {
+            start_time: run_data.start_time,
+            scalars: snapshot_store(&run_data.scalars, max_points.scalars, |v| *v),
+            tensors: {
+                let mut tensors: Vec<_> = (run_data.tensor_metadata.iter())
+                    .map(|(tag, metadata)| (tag.clone(), (**metadata).clone()))
+                    .collect();
+                tensors.sort_by(|a, b| a.0.cmp(&b.0));
+                tensors
+            },
+            blob_sequences: snapshot_store(
+                &run_data.blob_sequences,
+                max_points.blob_sequences,
+                |v| v.0.clone(),
+            ),
+            blob_references: snapshot_store(
+                &run_data.blob_references,
+                max_points.blob_sequences,
+                BlobReference::clone,
+            ),
+        }
+    }
+
+    /// Reads the blobs of every blob reference from `source`, so that they're written like any
+    /// other blob sequence.
+    fn read_blob_references(
+        mut self,
+        run: &Run,
+        source: Option<&dyn BlobSource>,
+    ) -> io::Result<Self> {
+        if self.blob_references.is_empty() {
+            return Ok(self);
+        }
+        let source = source.ok_or_else(|| {
+            let message = format!(
+                "run {:?} holds blob references, but there's no blob source to read them from",
+                run.0
+            );
+            io::Error::new(io::ErrorKind::InvalidInput, message)
+        })?;
+        for series in std::mem::take(&mut self.blob_references) {
+            let SeriesSnapshot {
+                tag,
+                metadata,
+                points,
+            } = series;
+            let points = (points.into_iter())
+                .map(|(step, wall_time, reference)| {
+                    let blobs = source.read_blob(&reference, &metadata)?;
+                    Ok((step, wall_time, blobs.0))
+                })
+                .collect::<io::Result<_>>()?;
+            self.blob_sequences.push(SeriesSnapshot {
+                tag,
+                metadata,
+                points,
+            });
+        }
+        self.blob_sequences.sort_by(|a, b| a.tag.cmp(&b.tag));
+        Ok(self)
+    }
+
+    /// Writes this run as a single event file in `dir`.
+    fn write(&self, dir: &Path) -> io::Result<()> {
+        let mut writer = EventFileWriter::new(dir)?;
+        if let Some(start_time) = self.start_time {
+            // The loader takes a run's start time to be its earliest wall time, which may precede
+            // all of the points that were kept. An otherwise empty event preserves it.
+            writer.write_event(&pb::Event {
+                wall_time: start_time.into(),
+                ..Default::default()
+            })?;
+        }
+        for series in &self.scalars {
+            // Values that single precision can't hold exactly are written as `DT_DOUBLE`.
+            write_series(&mut writer, series, |&value| match value.to_f32_exact() {
+                Some(x) => pb::TensorProto {
+                    dtype: pb::DataType::DtFloat.into(),
+                    tensor_shape: Some(pb::TensorShapeProto::default()),
+                    float_val: vec![x],
+                    ..Default::default()
+                },
+                None => pb::TensorProto {
+                    dtype: pb::DataType::DtDouble.into(),
+                    tensor_shape: Some(pb::TensorShapeProto::default()),
+                    double_val: vec![value.0],
+                    ..Default::default()
+                },
+            })?;
+        }
+        // Tensor points aren't committed, but the metadata of a tensor time series (like an
+        // hparams config) is kept by writing it on a single empty tensor at the run's start time.
+        for (tag, metadata) in &self.tensors {
+            let series = SeriesSnapshot {
+                tag: tag.clone(),
+                metadata: metadata.clone(),
+                points: self
+                    .start_time
+                    .map(|t| (Step(0), t, ()))
+                    .into_iter()
+                    .collect(),
+            };
+            write_series(&mut writer, &series, |()| pb::TensorProto::default())?;
+        }
+        for series in &self.blob_sequences {
+            let plugin_name = series
+                .metadata
+                .plugin_data
+                .as_ref()
+                .map(|pd| &*pd.plugin_name);
+            if series.tag == reserved_key(GraphDefValue::TAG_NAME)
+                && plugin_name == Some(plugin_names::GRAPHS)
+            {
+                for (step, wall_time, blobs) in &series.points {
+                    for blob in blobs {
+                        writer.write_graph(*step, *wall_time, blob.clone())?;
+                    }
+                }
+            } else if plugin_name == Some(plugin_names::GRAPH_TAGGED_RUN_METADATA) {
+                for (step, wall_time, blobs) in &series.points {
+                    for blob in blobs {
+                        let tag = &series.tag;
+                        writer.write_tagged_run_metadata(tag, *step, *wall_time, blob.clone())?;
+                    }
+                }
+            } else {
+                write_series(&mut writer, series, |blobs| pb::TensorProto {
+                    dtype: pb::DataType::DtString.into(),
+                    tensor_shape: Some(pb::TensorShapeProto {
+                        dim: vec![pb::tensor_shape_proto::Dim {
+                            size: blobs.len() as i64,
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }),
+                    string_val: blobs.clone(),
+                    ..Default::default()
+                })?;
+            }
+        }
+        writer.flush()
+    }
+}
+
+/// Writes each point of a time series as a tensor summary event, with the time series' metadata
+/// on the first one.
+fn write_series<V, F>(
+    writer: &mut EventFileWriter,
+    series: &SeriesSnapshot<V>,
+    to_tensor: F,
+) -> io::Result<()>
+where
+    F: Fn(&V) -> pb::TensorProto,
+{
+    for (i, (step, wall_time, value)) in series.points.iter().enumerate() {
+        let event = pb::Event {
+            step: step.0,
+            wall_time: (*wall_time).into(),
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: vec![pb::summary::Value {
+                    tag: series.tag.0.clone(),
+                    metadata: if i == 0 {
+                        Some(series.metadata.clone())
+                    } else {
+                        None
+                    },
+                    value: Some(pb::summary::value::Value::Tensor(to_tensor(value))),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        writer.write_event(&event)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+
+    use crate::disk_logdir::DiskLogdir;
+    use crate::logdir::LogdirLoader;
+
+    /// Loads every point of the log directory at `path` into a new commit.
+    fn load(path: &Path) -> Commit {
+        let commit = Commit::new();
+        let mut loader = LogdirLoader::new(&commit, DiskLogdir::new(path.to_path_buf()), 1);
+        loader.sample_sizes(SampleSizes::unlimited());
+        loader.reload();
+        drop(loader);
+        commit
+    }
+
+    /// Describes each run's start time and each time series' metadata and points, in a canonical
+    /// order, for comparing commits.
+    fn dump(commit: &Commit) -> Vec<String> {
+        fn series<V: std::fmt::Debug>(run: &Run, store: &TagStore<V>) -> Vec<String> {
+            store
+                .iter()
+                .map(|(tag, ts)| {
+                    let points: Vec<_> = ts.valid_values().collect();
+                    format!("{:?} {:?} {:?} {:?}", run, tag, ts.metadata, points)
+                })
+                .collect()
+        }
+        let runs = commit.runs.read().unwrap();
+        let mut lines = Vec::new();
+        for (run, run_data) in runs.iter() {
+            let run_data = run_data.read().unwrap();
+            lines.push(format!("{:?} start {:?}", run, run_data.start_time));
+            lines.extend(series(run, &run_data.scalars));
+            lines.extend(
+                (run_data.tensor_metadata.iter())
+                    .map(|(tag, metadata)| format!("{:?} {:?} {:?}", run, tag, metadata)),
+            );
+            lines.extend(series(run, &run_data.blob_sequences));
+        }
+        lines.sort();
+        lines
+    }
+
+    /// Creates an event with a single summary value.
+    fn summary_event(
+        tag: &str,
+        step: i64,
+        wall_time: f64,
+        metadata: Option<pb::SummaryMetadata>,
+        tensor: pb::TensorProto,
+    ) -> pb::Event {
+        pb::Event {
+            step,
+            wall_time,
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: vec![pb::summary::Value {
+                    tag: tag.to_string(),
+                    metadata,
+                    value: Some(pb::summary::value::Value::Tensor(tensor)),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    /// Writes a log directory with scalars in two runs, plus a tensor, an image, and a run graph.
+    fn write_fixture(logdir: &Path) -> io::Result<()> {
+        let train_dir = logdir.join("mnist").join("train");
+        fs::create_dir_all(&train_dir)?;
+        let mut f = File::create(train_dir.join("tfevents.123"))?;
+        // An early event that contributes only to the run's start time.
+        f.write_event(&pb::Event {
+            wall_time: 1000.0,
+            what: Some(pb::event::What::FileVersion("brain.Event:2".to_string())),
+            ..Default::default()
+        })?;
+        f.write_graph(
+            Step(0),
+            WallTime::new(1001.0).unwrap(),
+            b"my graph".to_vec(),
+        )?;
+        for i in 0..10 {
+            let wall_time = WallTime::new(1234.0 + i as f64).unwrap();
+            f.write_scalar(
+                &Tag("loss".to_string()),
+                Step(i),
+                wall_time,
+                1.0 / (i + 1) as f32,
+            )?;
+            f.write_scalar(
+                &Tag("accuracy".to_string()),
+                Step(i),
+                wall_time,
+                i as f32 / 10.0,
+            )?;
+        }
+        let histogram_metadata = pb::SummaryMetadata {
+            plugin_data: Some(pb::summary_metadata::PluginData {
+                plugin_name: plugin_names::HISTOGRAMS.to_string(),
+                ..Default::default()
+            }),
+            data_class: pb::DataClass::Tensor.into(),
+            ..Default::default()
+        };
+        for i in 0..3 {
+            let tensor = pb::TensorProto {
+                dtype: pb::DataType::DtDouble.into(),
+                double_val: vec![i as f64, 1.0, 2.0],
+                ..Default::default()
+            };
+            let metadata = Some(histogram_metadata.clone()).filter(|_| i == 0);
+            f.write_event(&summary_event("weights", i, 1300.0, metadata, tensor))?;
+        }
+        let image_metadata = pb::SummaryMetadata {
+            plugin_data: Some(pb::summary_metadata::PluginData {
+                plugin_name: plugin_names::IMAGES.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let image = pb::TensorProto {
+            dtype: pb::DataType::DtString.into(),
+            tensor_shape: Some(pb::TensorShapeProto {
+                dim: vec![pb::tensor_shape_proto::Dim {
+                    size: 3,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            string_val: vec![b"28".to_vec(), b"28".to_vec(), b"\x89PNG".to_vec()],
+            ..Default::default()
+        };
+        f.write_event(&summary_event(
+            "input",
+            5,
+            1400.0,
+            Some(image_metadata),
+            image,
+        ))?;
+        f.sync_all()?;
+
+        let mut f = File::create(logdir.join("tfevents.456"))?;
+        f.write_scalar(
+            &Tag("loss".to_string()),
+            Step(3),
+            WallTime::new(2000.0).unwrap(),
+            0.5,
+        )?;
+        f.sync_all()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        write_fixture(logdir.path())?;
+        let commit = load(logdir.path());
+        let dest = tempfile::tempdir()?;
+        let dest = dest.path().join("export");
+
+        assert_eq!(export_logdir(&commit, &dest, &ExportOptions::default())?, 2);
+        let exported = load(&dest);
+        assert_eq!(exported.run_names(), commit.run_names());
+        let train = Run(format!("mnist{}train", std::path::MAIN_SEPARATOR));
+        for tag in &["loss", "accuracy"] {
+            let tag = Tag(tag.to_string());
+            let scalars = commit.read_scalars(&train, &tag).unwrap();
+            assert_eq!(scalars.len(), 10);
+            assert_eq!(exported.read_scalars(&train, &tag), Some(scalars));
+        }
+        assert_eq!(exported.run_graph(&train), Some(b"my graph".to_vec()));
+        assert_eq!(dump(&exported), dump(&commit));
+
+        // Each run is a single event file, with metadata only on the first point of each series.
+        let files: Vec<_> = fs::read_dir(dest.join(&train.0))?.collect::<Result<_, _>>()?;
+        assert_eq!(files.len(), 1);
+        let mut reader = crate::event_file::EventFileReader::new(File::open(files[0].path())?);
+        let mut loss_metadata = 0;
+        while let Ok(event) = reader.read_event() {
+            if let Some(pb::event::What::Summary(summary)) = event.what {
+                for value in summary.value {
+                    if value.tag == "loss" && value.metadata.is_some() {
+                        loss_metadata += 1;
+                    }
+                }
+            }
+        }
+        assert_eq!(loss_metadata, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blob_references() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        write_fixture(logdir.path())?;
+        let commit = Commit::new();
+        let mut loader = LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().into()), 1);
+        loader.sample_sizes(SampleSizes::unlimited());
+        loader.blob_references(true);
+        loader.reload();
+        drop(loader);
+
+        let dest = tempfile::tempdir()?;
+        match export_logdir(&commit, dest.path(), &ExportOptions::default()) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+            Ok(n) => panic!("exported {} runs without a blob source", n),
+        }
+
+        let dest = tempfile::tempdir()?;
+        let options = ExportOptions {
+            blob_source: Some(Arc::new(DiskLogdir::new(logdir.path().into()))),
+            ..ExportOptions::default()
+        };
+        assert_eq!(export_logdir(&commit, dest.path(), &options)?, 2);
+        let exported = load(dest.path());
+        let train = Run(format!("mnist{}train", std::path::MAIN_SEPARATOR));
+        assert_eq!(exported.run_graph(&train), Some(b"my graph".to_vec()));
+        assert_eq!(dump(&exported), dump(&load(logdir.path())));
+        Ok(())
+    }
+
+    #[test]
+    fn test_double_precision_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let scalars_metadata = pb::SummaryMetadata {
+            plugin_data: Some(pb::summary_metadata::PluginData {
+                plugin_name: plugin_names::SCALARS.to_string(),
+                ..Default::default()
+            }),
+            data_class: pb::DataClass::Scalar.into(),
+            ..Default::default()
+        };
+        // Below single precision's range, past its mantissa, and exactly representable in it.
+        let values = [1e-300, 9007199254740993.0, 0.5];
+        let mut f = File::create(logdir.path().join("tfevents.123"))?;
+        for (i, &x) in values.iter().enumerate() {
+            let tensor = pb::TensorProto {
+                dtype: pb::DataType::DtDouble.into(),
+                tensor_shape: Some(pb::TensorShapeProto::default()),
+                double_val: vec![x],
+                ..Default::default()
+            };
+            let metadata = Some(scalars_metadata.clone()).filter(|_| i == 0);
+            f.write_event(&summary_event("x", i as i64, 1000.0, metadata, tensor))?;
+        }
+        f.sync_all()?;
+        let commit = load(logdir.path());
+        let dest = tempfile::tempdir()?;
+        export_logdir(&commit, dest.path(), &ExportOptions::default())?;
+
+        let exported = load(dest.path());
+        let points = exported
+            .read_scalars(&Run(".".to_string()), &Tag("x".to_string()))
+            .unwrap();
+        let bits: Vec<u64> = points.iter().map(|&(_, _, x)| x.to_bits()).collect();
+        let expected: Vec<u64> = values.iter().map(|x| x.to_bits()).collect();
+        assert_eq!(bits, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_points() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        write_fixture(logdir.path())?;
+        let commit = load(logdir.path());
+        let dest = tempfile::tempdir()?;
+
+        let options = ExportOptions {
+            max_points: SampleSizes {
+                scalars: 2,
+                blob_sequences: 1,
+            },
+            ..ExportOptions::default()
+        };
+        export_logdir(&commit, dest.path(), &options)?;
+        let exported = load(dest.path());
+        let train = Run(format!("mnist{}train", std::path::MAIN_SEPARATOR));
+        let loss = exported.read_scalars(&train, &Tag("loss".to_string()));
+        let steps: Vec<i64> = loss.unwrap().iter().map(|(step, _, _)| step.0).collect();
+        assert_eq!(steps, vec![0, 9]);
+        let runs = exported.runs.read().unwrap();
+        let run_data = runs[&train].read().unwrap();
+        assert_eq!(run_data.tensor_metadata.len(), 1);
+        assert_eq!(run_data.blob_sequences.len(), 2);
+        // The start time survives even though no point has that wall time.
+        assert_eq!(run_data.start_time, Some(WallTime::new(1000.0).unwrap()));
+        drop(run_data);
+        drop(runs);
+
+        // Exporting again to the same, now non-empty, directory fails.
+        match export_logdir(&commit, dest.path(), &options) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::AlreadyExists),
+            Ok(n) => panic!("exported {} runs over an existing export", n),
+        }
+        Ok(())
+    }
+}
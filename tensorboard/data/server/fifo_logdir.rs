@@ -0,0 +1,256 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Log directories backed by a named pipe (FIFO), for real-time ingestion.
+
+use crossbeam::channel::{self, Receiver, RecvTimeoutError, Sender};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use crate::logdir::{EventFileBuf, Logdir};
+use crate::types::Run;
+
+/// Default time that a read waits for new data before reporting that none is available.
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Number of bytes to read from the pipe at a time.
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// Number of chunks that may be read ahead of the loader before the background reader waits.
+const CHANNEL_CAPACITY: usize = 16;
+
+/// A log directory with a single run, whose event stream is read from a named pipe (FIFO).
+///
+/// A training job writes TFRecord-framed events to the FIFO, and the loader drains them as they
+/// arrive. Unlike a regular file, a FIFO blocks on read when it's empty instead of returning end
+/// of file, and opening it blocks until there is a writer. So the pipe is read on a background
+/// thread, and each read from the resulting [`FifoFile`] waits at most a bounded time for new
+/// data before returning `Ok(0)`. To the event file reader, this looks just like reaching the end
+/// of a regular file that is still being written: "no data available right now". Thus, a load
+/// cycle reads all available records and then returns, instead of hanging.
+///
+/// When the writer closes the pipe, the background thread waits for the next writer, and the
+/// bytes that it writes continue the same record stream.
+pub struct FifoLogdir {
+    /// Path to the FIFO.
+    path: PathBuf,
+    /// Name of the single run in this log directory.
+    run: Run,
+    /// How long each read waits for new data. See [`Self::timeout`].
+    timeout: Duration,
+}
+
+impl FifoLogdir {
+    /// Creates a log directory that reads the given run from the FIFO at `path`.
+    ///
+    /// Does not touch the filesystem. The FIFO is opened when the run is first loaded.
+    pub fn new(path: PathBuf, run: Run) -> Self {
+        FifoLogdir {
+            path,
+            run,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets how long each read waits for new data before reporting that none is available.
+    ///
+    /// A load cycle waits for this long once per cycle, after draining all available records.
+    pub fn timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+}
+
+impl Logdir for FifoLogdir {
+    type File = FifoFile;
+
+    /// Returns the single run with the FIFO as its only event file, or an error if the FIFO does
+    /// not exist.
+    fn discover(&self) -> io::Result<HashMap<Run, Vec<EventFileBuf>>> {
+        std::fs::metadata(&self.path)?;
+        let mut run_map = HashMap::new();
+        run_map.insert(self.run.clone(), vec![EventFileBuf(self.path.clone())]);
+        Ok(run_map)
+    }
+
+    fn open(&self, path: &EventFileBuf) -> io::Result<Self::File> {
+        FifoFile::spawn(path.0.clone(), self.timeout)
+    }
+}
+
+/// A stream of bytes read from a FIFO by a background thread.
+///
+/// Reads return `Ok(0)` if no data arrives within the timeout. If the pipe can't be opened or
+/// read, the next read returns that error, and all reads after that return `Ok(0)`.
+///
+/// Dropping a `FifoFile` stops its background thread once that thread next wakes up: that is,
+/// once it has read more data, or once a writer has opened the pipe.
+pub struct FifoFile {
+    rx: Receiver<io::Result<Vec<u8>>>,
+    /// Most recently received chunk, of which `buf[pos..]` has not yet been read.
+    buf: Vec<u8>,
+    pos: usize,
+    timeout: Duration,
+}
+
+impl FifoFile {
+    fn spawn(path: PathBuf, timeout: Duration) -> io::Result<Self> {
+        let (tx, rx) = channel::bounded(CHANNEL_CAPACITY);
+        thread::Builder::new()
+            .name(format!("FifoReader-{}", path.display()))
+            .spawn(move || read_fifo(path, tx))?;
+        Ok(FifoFile {
+            rx,
+            buf: Vec::new(),
+            pos: 0,
+            timeout,
+        })
+    }
+}
+
+/// Reads chunks from the FIFO at `path` and sends them to `tx`, reopening the FIFO each time a
+/// writer closes it. Returns once the receiver is dropped or reading fails.
+fn read_fifo(path: PathBuf, tx: Sender<io::Result<Vec<u8>>>) {
+    loop {
+        // Blocks until a writer opens the pipe.
+        let mut file = match File::open(&path) {
+            Ok(f) => f,
+            Err(e) => {
+                let _ = tx.send(Err(e));
+                return;
+            }
+        };
+        debug!("Opened FIFO {}", path.display());
+        loop {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let n = match file.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    warn!("Failed to read FIFO {}: {}", path.display(), e);
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+            };
+            chunk.truncate(n);
+            if tx.send(Ok(chunk)).is_err() {
+                return;
+            }
+        }
+        debug!("Writer closed FIFO {}; waiting for another", path.display());
+    }
+}
+
+impl Read for FifoFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pos == self.buf.len() {
+            match self.rx.recv_timeout(self.timeout) {
+                Ok(Ok(chunk)) => {
+                    self.buf = chunk;
+                    self.pos = 0;
+                }
+                Ok(Err(e)) => return Err(e),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {
+                    return Ok(0)
+                }
+            }
+        }
+        let n = buf.len().min(self.buf.len() - self.pos);
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::process::Command;
+
+    use crate::commit::Commit;
+    use crate::logdir::LogdirLoader;
+    use crate::types::{Step, Tag, WallTime};
+    use crate::writer::SummaryWriteExt;
+
+    #[test]
+    fn test_fifo() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("tfevents.fifo");
+        let status = Command::new("mkfifo").arg(&path).status()?;
+        assert!(status.success(), "mkfifo failed: {}", status);
+
+        let run = Run("live".to_string());
+        let tag = Tag("loss".to_string());
+        let mut logdir = FifoLogdir::new(path.clone(), run.clone());
+        logdir.timeout(Duration::from_millis(10));
+        let commit = Commit::new();
+        let mut loader = LogdirLoader::new(&commit, logdir, 1);
+        let count = || {
+            let runs = commit.runs.read().unwrap();
+            let run_data = runs.get(&run).expect("run should be discovered");
+            let run_data = run_data.read().unwrap();
+            run_data
+                .scalars
+                .get(&tag)
+                .map_or(0, |ts| ts.valid_values().count())
+        };
+
+        // No writer yet: the reload should return, with no data.
+        loader.reload();
+        assert_eq!(count(), 0);
+
+        // Opening for write blocks until the reader thread has opened the pipe for reading.
+        let mut writer = OpenOptions::new().write(true).open(&path)?;
+        writer.write_scalar(&tag, Step(0), WallTime::new(1235.0).unwrap(), 0.25)?;
+        writer.write_scalar(&tag, Step(1), WallTime::new(1236.0).unwrap(), 0.5)?;
+        // Half of a record: should be held until the rest arrives.
+        let mut next = Vec::new();
+        next.write_scalar(&tag, Step(2), WallTime::new(1237.0).unwrap(), 0.75)?;
+        writer.write_all(&next[..5])?;
+        writer.flush()?;
+        // The writer's data may take a moment to reach the loader.
+        for _ in 0..100 {
+            loader.reload();
+            if count() == 2 {
+                break;
+            }
+        }
+        assert_eq!(count(), 2);
+
+        // The record stream continues across writers.
+        drop(writer);
+        let mut writer = OpenOptions::new().write(true).open(&path)?;
+        writer.write_all(&next[5..])?;
+        drop(writer);
+        for _ in 0..100 {
+            loader.reload();
+            if count() == 3 {
+                break;
+            }
+        }
+        assert_eq!(count(), 3);
+        Ok(())
+    }
+}
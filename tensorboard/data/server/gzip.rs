@@ -0,0 +1,208 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Resumable decompression for gzip-compressed event files.
+
+use flate2::{Decompress, FlushDecompress, Status};
+use std::io::{self, BufRead, Read};
+
+/// Gzip header flag: the header has a CRC-16.
+const FHCRC: u8 = 1 << 1;
+/// Gzip header flag: the header has an "extra" field.
+const FEXTRA: u8 = 1 << 2;
+/// Gzip header flag: the header has a NUL-terminated file name.
+const FNAME: u8 = 1 << 3;
+/// Gzip header flag: the header has a NUL-terminated comment.
+const FCOMMENT: u8 = 1 << 4;
+
+/// A gzip decoder that treats the end of its input as a pause rather than as corruption.
+///
+/// Standard gzip decoders fail when the input ends in the middle of a stream. This decoder
+/// instead returns `Ok(0)`, and picks up where it left off on the next read if more input has
+/// become available since, just like reading a plain file that is still being written. All data
+/// that the writer has sync-flushed can be decompressed.
+///
+/// Only the first member of a multi-member gzip file is read.
+pub struct GzipReader<R> {
+    inner: R,
+    /// Bytes of the header read so far, or `None` once the header has been fully consumed.
+    header: Option<Vec<u8>>,
+    decompress: Decompress,
+    /// Whether the end of the compressed stream has been reached.
+    done: bool,
+}
+
+impl<R: BufRead> GzipReader<R> {
+    pub fn new(inner: R) -> Self {
+        GzipReader {
+            inner,
+            header: Some(Vec::new()),
+            decompress: Decompress::new(false),
+            done: false,
+        }
+    }
+
+    /// Reads as much of the gzip header as is available. Returns `true` once the whole header has
+    /// been consumed.
+    fn read_header(&mut self) -> io::Result<bool> {
+        let header = match self.header.as_mut() {
+            None => return Ok(true),
+            Some(h) => h,
+        };
+        loop {
+            match header_len(header)? {
+                Some(n) if n == header.len() => break,
+                _ => (),
+            }
+            // Read one byte at a time: headers are short, and this way we never consume any of the
+            // compressed data that follows.
+            let mut byte = [0u8];
+            if self.inner.read(&mut byte)? == 0 {
+                return Ok(false);
+            }
+            header.push(byte[0]);
+        }
+        self.header = None;
+        Ok(true)
+    }
+}
+
+/// Computes the total length of a gzip header given a prefix of it, or `None` if the prefix is too
+/// short to tell.
+fn header_len(prefix: &[u8]) -> io::Result<Option<usize>> {
+    const FIXED_LEN: usize = 10;
+    if prefix.len() < FIXED_LEN {
+        return Ok(None);
+    }
+    if prefix[0..3] != [0x1f, 0x8b, 8] {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid gzip header",
+        ));
+    }
+    let flags = prefix[3];
+    let mut len = FIXED_LEN;
+    if flags & FEXTRA != 0 {
+        if prefix.len() < len + 2 {
+            return Ok(None);
+        }
+        len += 2 + usize::from(u16::from_le_bytes([prefix[len], prefix[len + 1]]));
+    }
+    for &flag in &[FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            match prefix
+                .get(len..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+            {
+                Some(nul) => len += nul + 1,
+                None => return Ok(None),
+            }
+        }
+    }
+    if flags & FHCRC != 0 {
+        len += 2;
+    }
+    Ok(Some(len))
+}
+
+impl<R: BufRead> Read for GzipReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.done || !self.read_header()? {
+            return Ok(0);
+        }
+        loop {
+            let input = self.inner.fill_buf()?;
+            // Even with no more input, the decompressor may have output left over from last time.
+            let eof = input.is_empty();
+            let before_in = self.decompress.total_in();
+            let before_out = self.decompress.total_out();
+            let status = self
+                .decompress
+                .decompress(input, buf, FlushDecompress::None)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let consumed = (self.decompress.total_in() - before_in) as usize;
+            let produced = (self.decompress.total_out() - before_out) as usize;
+            self.inner.consume(consumed);
+            if status == Status::StreamEnd {
+                self.done = true;
+            }
+            if produced > 0 || self.done || eof || consumed == 0 {
+                return Ok(produced);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::{Compression, GzBuilder};
+    use std::io::{BufReader, Write};
+    use std::sync::{Arc, Mutex};
+
+    /// A reader over a shared buffer that may grow between reads.
+    struct Growing(Arc<Mutex<Vec<u8>>>, usize);
+
+    impl Read for Growing {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let data = self.0.lock().unwrap();
+            let n = buf.len().min(data.len() - self.1);
+            buf[..n].copy_from_slice(&data[self.1..self.1 + n]);
+            self.1 += n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_tail_growing_stream() {
+        let shared = Arc::new(Mutex::new(Vec::new()));
+        let mut reader = GzipReader::new(BufReader::new(Growing(Arc::clone(&shared), 0)));
+        let mut out = Vec::new();
+
+        // Nothing written yet: not even a full header.
+        assert_eq!(reader.read_to_end(&mut out).unwrap(), 0);
+
+        let mut encoder = GzBuilder::new()
+            .filename("events.out.tfevents")
+            .comment("hello")
+            .write(Vec::new(), Compression::default());
+        encoder.write_all(b"first chunk;").unwrap();
+        encoder.flush().unwrap();
+        shared.lock().unwrap().extend(encoder.get_mut().drain(..));
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"first chunk;");
+
+        encoder.write_all(b"second chunk").unwrap();
+        let rest = encoder.finish().unwrap();
+        shared.lock().unwrap().extend(rest);
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"first chunk;second chunk");
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let mut reader = GzipReader::new(&b"not a gzip file"[..]);
+        let err = reader.read(&mut [0u8; 16]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"ok").unwrap();
+        let data = encoder.finish().unwrap();
+        let mut out = String::new();
+        GzipReader::new(&data[..]).read_to_string(&mut out).unwrap();
+        assert_eq!(out, "ok");
+    }
+}
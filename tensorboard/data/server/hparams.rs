@@ -0,0 +1,494 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Typed views of hparams plugin summaries, and aggregation of them across runs.
+//!
+//! The hparams plugin stores all of its data in the summary metadata of three reserved tags:
+//! [`EXPERIMENT_TAG`] for the experiment config, and [`SESSION_START_INFO_TAG`] and
+//! [`SESSION_END_INFO_TAG`] for the start and end of each session (i.e., each run). See
+//! `tensorboard/plugins/hparams/plugin_data.proto` for the wire format.
+
+use prost::Message;
+use prost_types::value::Kind;
+use std::collections::BTreeMap;
+
+use crate::commit::Commit;
+use crate::data_compat::plugin_names;
+use crate::proto::tensorboard as pb;
+use crate::types::{Run, Tag};
+
+use pb::hparams::h_params_plugin_data::Data;
+
+/// Tag under which the experiment config is written.
+pub const EXPERIMENT_TAG: &str = "_hparams_/experiment";
+/// Tag under which the start of a session is written.
+pub const SESSION_START_INFO_TAG: &str = "_hparams_/session_start_info";
+/// Tag under which the end of a session is written.
+pub const SESSION_END_INFO_TAG: &str = "_hparams_/session_end_info";
+
+/// Version of the `HParamsPluginData` format that we read and write.
+const PLUGIN_DATA_VERSION: i32 = 0;
+
+/// The value of a hyperparameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HParamValue {
+    String(String),
+    Number(f64),
+    Bool(bool),
+}
+
+/// The set of values that a hyperparameter can take.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Domain {
+    /// Any value of the hyperparameter's type.
+    Unspecified,
+    /// One of a finite set of values, which should all be of the same type.
+    Discrete(Vec<HParamValue>),
+    /// Any number in the closed interval `[min, max]`.
+    Interval { min: f64, max: f64 },
+}
+
+/// Declaration of a hyperparameter in an experiment config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HParamConfig {
+    pub name: String,
+    pub domain: Domain,
+}
+
+/// Experiment config: the hyperparameters and metrics that sessions are compared by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentConfig {
+    pub hparams: Vec<HParamConfig>,
+    /// Tags of scalar time series to use as metrics.
+    pub metrics: Vec<Tag>,
+    /// Creation time of the experiment, in seconds since epoch.
+    pub time_created_secs: f64,
+}
+
+/// Information recorded when a session starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionStart {
+    /// Hyperparameter values for this session, keyed by name.
+    pub hparams: BTreeMap<String, HParamValue>,
+    /// Name of the session group; if empty, the session is in a group by itself.
+    pub group_name: String,
+    /// Start time of the session, in seconds since epoch.
+    pub start_time_secs: f64,
+}
+
+/// Final status of a session.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SessionStatus {
+    Unknown,
+    Success,
+    Failure,
+    Running,
+}
+
+/// Information recorded when a session ends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionEnd {
+    pub status: SessionStatus,
+    /// End time of the session, in seconds since epoch.
+    pub end_time_secs: f64,
+}
+
+impl HParamValue {
+    fn to_proto(&self) -> prost_types::Value {
+        let kind = match self {
+            HParamValue::String(s) => Kind::StringValue(s.clone()),
+            HParamValue::Number(x) => Kind::NumberValue(*x),
+            HParamValue::Bool(b) => Kind::BoolValue(*b),
+        };
+        prost_types::Value { kind: Some(kind) }
+    }
+
+    fn from_proto(value: &prost_types::Value) -> Option<Self> {
+        match value.kind.as_ref()? {
+            Kind::StringValue(s) => Some(HParamValue::String(s.clone())),
+            Kind::NumberValue(x) => Some(HParamValue::Number(*x)),
+            Kind::BoolValue(b) => Some(HParamValue::Bool(*b)),
+            _ => None,
+        }
+    }
+
+    fn data_type(&self) -> pb::hparams::DataType {
+        match self {
+            HParamValue::String(_) => pb::hparams::DataType::String,
+            HParamValue::Number(_) => pb::hparams::DataType::Float64,
+            HParamValue::Bool(_) => pb::hparams::DataType::Bool,
+        }
+    }
+}
+
+impl HParamConfig {
+    fn to_proto(&self) -> pb::hparams::HParamInfo {
+        use pb::hparams::h_param_info::Domain as PbDomain;
+        let (data_type, domain) = match &self.domain {
+            Domain::Unspecified => (pb::hparams::DataType::Unset, None),
+            Domain::Discrete(values) => {
+                let data_type = values
+                    .first()
+                    .map_or(pb::hparams::DataType::Unset, HParamValue::data_type);
+                let list = prost_types::ListValue {
+                    values: values.iter().map(HParamValue::to_proto).collect(),
+                };
+                (data_type, Some(PbDomain::DomainDiscrete(list)))
+            }
+            Domain::Interval { min, max } => {
+                let interval = pb::hparams::Interval {
+                    min_value: *min,
+                    max_value: *max,
+                };
+                (
+                    pb::hparams::DataType::Float64,
+                    Some(PbDomain::DomainInterval(interval)),
+                )
+            }
+        };
+        pb::hparams::HParamInfo {
+            name: self.name.clone(),
+            r#type: data_type.into(),
+            domain,
+            ..Default::default()
+        }
+    }
+
+    fn from_proto(info: &pb::hparams::HParamInfo) -> Self {
+        use pb::hparams::h_param_info::Domain as PbDomain;
+        let domain = match &info.domain {
+            None => Domain::Unspecified,
+            Some(PbDomain::DomainDiscrete(list)) => Domain::Discrete(
+                list.values
+                    .iter()
+                    .filter_map(HParamValue::from_proto)
+                    .collect(),
+            ),
+            Some(PbDomain::DomainInterval(interval)) => Domain::Interval {
+                min: interval.min_value,
+                max: interval.max_value,
+            },
+        };
+        HParamConfig {
+            name: info.name.clone(),
+            domain,
+        }
+    }
+}
+
+impl ExperimentConfig {
+    /// Converts this config to the hparams plugin's `Experiment` proto.
+    pub fn to_proto(&self) -> pb::hparams::Experiment {
+        pb::hparams::Experiment {
+            time_created_secs: self.time_created_secs,
+            hparam_infos: self.hparams.iter().map(HParamConfig::to_proto).collect(),
+            metric_infos: self
+                .metrics
+                .iter()
+                .map(|tag| pb::hparams::MetricInfo {
+                    name: Some(pb::hparams::MetricName {
+                        tag: tag.0.clone(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Converts an `Experiment` proto to a config. Metrics without names are skipped.
+    pub fn from_proto(experiment: &pb::hparams::Experiment) -> Self {
+        ExperimentConfig {
+            hparams: experiment
+                .hparam_infos
+                .iter()
+                .map(HParamConfig::from_proto)
+                .collect(),
+            metrics: experiment
+                .metric_infos
+                .iter()
+                .filter_map(|mi| Some(Tag(mi.name.as_ref()?.tag.clone())))
+                .collect(),
+            time_created_secs: experiment.time_created_secs,
+        }
+    }
+}
+
+impl SessionStart {
+    /// Converts this value to the hparams plugin's `SessionStartInfo` proto.
+    pub fn to_proto(&self) -> pb::hparams::SessionStartInfo {
+        pb::hparams::SessionStartInfo {
+            hparams: self
+                .hparams
+                .iter()
+                .map(|(k, v)| (k.clone(), v.to_proto()))
+                .collect(),
+            group_name: self.group_name.clone(),
+            start_time_secs: self.start_time_secs,
+            ..Default::default()
+        }
+    }
+
+    /// Converts a `SessionStartInfo` proto to a typed value. Hyperparameters whose values are not
+    /// strings, numbers, or bools are skipped.
+    pub fn from_proto(info: &pb::hparams::SessionStartInfo) -> Self {
+        SessionStart {
+            hparams: info
+                .hparams
+                .iter()
+                .filter_map(|(k, v)| Some((k.clone(), HParamValue::from_proto(v)?)))
+                .collect(),
+            group_name: info.group_name.clone(),
+            start_time_secs: info.start_time_secs,
+        }
+    }
+}
+
+impl SessionEnd {
+    /// Converts this value to the hparams plugin's `SessionEndInfo` proto.
+    pub fn to_proto(&self) -> pb::hparams::SessionEndInfo {
+        let status = match self.status {
+            SessionStatus::Unknown => pb::hparams::Status::Unknown,
+            SessionStatus::Success => pb::hparams::Status::Success,
+            SessionStatus::Failure => pb::hparams::Status::Failure,
+            SessionStatus::Running => pb::hparams::Status::Running,
+        };
+        pb::hparams::SessionEndInfo {
+            status: status.into(),
+            end_time_secs: self.end_time_secs,
+        }
+    }
+
+    /// Converts a `SessionEndInfo` proto to a typed value. Unrecognized statuses are read as
+    /// [`SessionStatus::Unknown`].
+    pub fn from_proto(info: &pb::hparams::SessionEndInfo) -> Self {
+        let status = match pb::hparams::Status::from_i32(info.status) {
+            Some(pb::hparams::Status::Success) => SessionStatus::Success,
+            Some(pb::hparams::Status::Failure) => SessionStatus::Failure,
+            Some(pb::hparams::Status::Running) => SessionStatus::Running,
+            _ => SessionStatus::Unknown,
+        };
+        SessionEnd {
+            status,
+            end_time_secs: info.end_time_secs,
+        }
+    }
+}
+
+/// Creates summary metadata for an hparams plugin summary with the given contents.
+pub(crate) fn summary_metadata(data: Data) -> pb::SummaryMetadata {
+    let plugin_data = pb::hparams::HParamsPluginData {
+        version: PLUGIN_DATA_VERSION,
+        data: Some(data),
+    };
+    let mut content = Vec::new();
+    plugin_data
+        .encode(&mut content)
+        .expect("failed to encode HParamsPluginData");
+    pb::SummaryMetadata {
+        plugin_data: Some(pb::summary_metadata::PluginData {
+            plugin_name: plugin_names::HPARAMS.to_string(),
+            content,
+            ..Default::default()
+        }),
+        data_class: pb::DataClass::Tensor.into(),
+        ..Default::default()
+    }
+}
+
+/// Hparams data for a single session, as read from a run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    pub start: Option<SessionStart>,
+    pub end: Option<SessionEnd>,
+}
+
+/// Hparams data aggregated across all runs in a commit.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HParamsIndex {
+    /// The experiment config, if any run has one. If several runs do, the one from the
+    /// lexicographically first run wins.
+    pub experiment: Option<ExperimentConfig>,
+    /// Sessions keyed by run, for every run that has a session start or end summary.
+    pub sessions: BTreeMap<Run, Session>,
+}
+
+impl HParamsIndex {
+    /// Collects hparams data from all runs in a commit. Everything is read from the summary
+    /// metadata of the hparams plugin's tensor time series, which the commit keeps without points.
+    ///
+    /// # Panics
+    ///
+    /// If any of the commit locks is poisoned.
+    pub fn from_commit(commit: &Commit) -> Self {
+        let mut index = HParamsIndex::default();
+        let runs = commit.runs.read().expect("failed to read commit runs");
+        let mut run_names: Vec<&Run> = runs.keys().collect();
+        run_names.sort();
+        for run in run_names {
+            let run_data = runs[run].read().expect("failed to read run data");
+            let mut session = Session::default();
+            for (tag, metadata) in &run_data.tensor_metadata {
+                let plugin_data = match metadata.plugin_data.as_ref() {
+                    Some(pd) if pd.plugin_name == plugin_names::HPARAMS => pd,
+                    _ => continue,
+                };
+                let data = match pb::hparams::HParamsPluginData::decode(&plugin_data.content[..]) {
+                    Ok(hpd) => hpd.data,
+                    Err(_) => continue,
+                };
+                match (tag.0.as_str(), data) {
+                    (EXPERIMENT_TAG, Some(Data::Experiment(e))) if index.experiment.is_none() => {
+                        index.experiment = Some(ExperimentConfig::from_proto(&e));
+                    }
+                    (SESSION_START_INFO_TAG, Some(Data::SessionStartInfo(info))) => {
+                        session.start = Some(SessionStart::from_proto(&info));
+                    }
+                    (SESSION_END_INFO_TAG, Some(Data::SessionEndInfo(info))) => {
+                        session.end = Some(SessionEnd::from_proto(&info));
+                    }
+                    _ => (),
+                }
+            }
+            if session != Session::default() {
+                index.sessions.insert(run.clone(), session);
+            }
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    use crate::disk_logdir::DiskLogdir;
+    use crate::logdir::LogdirLoader;
+    use crate::types::WallTime;
+    use crate::writer::{EventFileWriter, SummaryWriteExt};
+
+    #[test]
+    fn test_hparams_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let wt = WallTime::new(1234.0).unwrap();
+        let config = ExperimentConfig {
+            hparams: vec![
+                HParamConfig {
+                    name: "optimizer".to_string(),
+                    domain: Domain::Discrete(vec![
+                        HParamValue::String("adam".to_string()),
+                        HParamValue::String("sgd".to_string()),
+                    ]),
+                },
+                HParamConfig {
+                    name: "learning_rate".to_string(),
+                    domain: Domain::Interval {
+                        min: 1e-4,
+                        max: 1e-1,
+                    },
+                },
+                HParamConfig {
+                    name: "use_bias".to_string(),
+                    domain: Domain::Unspecified,
+                },
+            ],
+            metrics: vec![Tag("accuracy".to_string())],
+            time_created_secs: 1234.0,
+        };
+        let mut sessions = BTreeMap::new();
+        for (i, (optimizer, status)) in [
+            ("adam", SessionStatus::Success),
+            ("sgd", SessionStatus::Failure),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let start = SessionStart {
+                hparams: vec![
+                    (
+                        "optimizer".to_string(),
+                        HParamValue::String(optimizer.to_string()),
+                    ),
+                    (
+                        "learning_rate".to_string(),
+                        HParamValue::Number(0.01 * (i + 1) as f64),
+                    ),
+                    ("use_bias".to_string(), HParamValue::Bool(i == 0)),
+                ]
+                .into_iter()
+                .collect(),
+                group_name: format!("group{}", i),
+                start_time_secs: 1235.0,
+            };
+            let end = SessionEnd {
+                status: *status,
+                end_time_secs: 1236.0,
+            };
+            let mut writer = EventFileWriter::new(logdir.path().join(optimizer))?;
+            writer.write_hparams_config(wt, &config)?;
+            writer.write_hparams_session_start(wt, &start)?;
+            writer.write_hparams_session_end(wt, &end)?;
+            // Tensors of other plugins don't contribute to the index.
+            writer.write_event(&pb::Event {
+                step: 1,
+                wall_time: 1236.0,
+                what: Some(pb::event::What::Summary(pb::Summary {
+                    value: vec![pb::summary::Value {
+                        tag: "weights".to_string(),
+                        metadata: Some(pb::SummaryMetadata {
+                            plugin_data: Some(pb::summary_metadata::PluginData {
+                                plugin_name: plugin_names::HISTOGRAMS.to_string(),
+                                ..Default::default()
+                            }),
+                            data_class: pb::DataClass::Tensor.into(),
+                            ..Default::default()
+                        }),
+                        value: Some(pb::summary::value::Value::Tensor(pb::TensorProto {
+                            dtype: pb::DataType::DtDouble.into(),
+                            double_val: vec![0.0, 1.0, 2.0],
+                            ..Default::default()
+                        })),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })),
+                ..Default::default()
+            })?;
+            writer.flush()?;
+            sessions.insert(
+                Run(optimizer.to_string()),
+                Session {
+                    start: Some(start),
+                    end: Some(end),
+                },
+            );
+        }
+
+        let commit = Commit::new();
+        let mut loader =
+            LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+        loader.reload();
+
+        let index = HParamsIndex::from_commit(&commit);
+        let expected = HParamsIndex {
+            experiment: Some(config),
+            sessions,
+        };
+        assert_eq!(index, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,466 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Log directories served as browsable HTTP(S) directory listings.
+
+use log::{debug, warn};
+use reqwest::{blocking::Client as HttpClient, StatusCode, Url};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use crate::logdir::{is_event_file_name, EventFileBuf, Logdir};
+use crate::types::Run;
+
+/// Number of bytes to request at a time when reading an event file.
+const READ_CHUNK_SIZE: u64 = 1 << 20;
+
+/// A log directory served over HTTP(S) as a tree of directory listings.
+///
+/// A directory listing is any HTML page whose links (`href` attributes) point to the entries of
+/// that directory, as produced by most static file servers (Apache `mod_autoindex`, nginx
+/// `autoindex`, Python's `http.server`, etc.). Links ending in `/` are treated as subdirectories;
+/// links that leave the log directory, or that carry a query string (e.g., sort controls), are
+/// ignored.
+///
+/// Event files are read with HTTP range requests, so the server should support `Range` headers
+/// for incremental loading to be efficient. See [`HttpFile`] for details.
+pub struct HttpLogdir {
+    http: HttpClient,
+    /// Base URL of the log directory. Always ends with a slash.
+    root: Url,
+}
+
+impl HttpLogdir {
+    /// Creates an `HttpLogdir` rooted at the given URL. A trailing slash is added if not present.
+    ///
+    /// May fail if constructing the underlying HTTP client fails.
+    pub fn new(mut root: Url) -> reqwest::Result<Self> {
+        if !root.path().ends_with('/') {
+            let path = format!("{}/", root.path());
+            root.set_path(&path);
+        }
+        let http = HttpClient::builder()
+            .user_agent(format!("tensorboard-data-server/{}", crate::VERSION))
+            .build()?;
+        Ok(HttpLogdir { http, root })
+    }
+
+    /// Fetches a directory listing and returns the absolute URLs of all its children.
+    fn list(&self, dir: &Url) -> io::Result<Vec<Url>> {
+        debug!("Listing HTTP directory {}", dir);
+        let body = self
+            .http
+            .get(dir.clone())
+            .send()
+            .and_then(|res| res.error_for_status())
+            .and_then(|res| res.text())
+            .map_err(reqwest_to_io)?;
+        let mut children = Vec::new();
+        for href in extract_hrefs(&body) {
+            let url = match dir.join(href) {
+                Ok(url) => url,
+                Err(e) => {
+                    debug!("Skipping unparseable link {:?} in {}: {}", href, dir, e);
+                    continue;
+                }
+            };
+            // Only descend: skip parent links, self links, external links, and sort controls.
+            if url.query().is_some() || url.as_str().len() <= dir.as_str().len() {
+                continue;
+            }
+            if !url.as_str().starts_with(dir.as_str()) {
+                continue;
+            }
+            children.push(url);
+        }
+        Ok(children)
+    }
+}
+
+impl Logdir for HttpLogdir {
+    type File = HttpFile;
+
+    fn discover(&self) -> io::Result<HashMap<Run, Vec<EventFileBuf>>> {
+        let mut run_map: HashMap<Run, Vec<EventFileBuf>> = HashMap::new();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(self.root.clone());
+        while let Some(dir) = queue.pop_front() {
+            if !visited.insert(dir.clone()) {
+                continue;
+            }
+            let children = match self.list(&dir) {
+                Ok(children) => children,
+                Err(e) if dir == self.root => return Err(e),
+                Err(e) => {
+                    warn!("While listing HTTP directory {}: {}", dir, e);
+                    continue;
+                }
+            };
+            for child in children {
+                if child.path().ends_with('/') {
+                    queue.push_back(child);
+                    continue;
+                }
+                let relpath = percent_decode(&child.as_str()[self.root.as_str().len()..]);
+                let (run_dir, filename) = match relpath.rfind('/') {
+                    Some(i) => (&relpath[..i], &relpath[i + 1..]),
+                    // Render the root run as ".", not "".
+                    None => (".", relpath.as_str()),
+                };
+                if !is_event_file_name(filename) {
+                    continue;
+                }
+                let run = Run(run_dir.to_string());
+                run_map
+                    .entry(run)
+                    .or_default()
+                    .push(EventFileBuf(PathBuf::from(&relpath)));
+            }
+        }
+        for files in run_map.values_mut() {
+            files.sort();
+        }
+        Ok(run_map)
+    }
+
+    fn open(&self, path: &EventFileBuf) -> io::Result<Self::File> {
+        let mut url = self.root.clone();
+        {
+            let mut segments = url.path_segments_mut().map_err(|()| {
+                io::Error::new(io::ErrorKind::InvalidInput, "URL cannot be a base")
+            })?;
+            segments.pop_if_empty();
+            for component in path.0.iter() {
+                segments.push(&component.to_string_lossy());
+            }
+        }
+        Ok(HttpFile {
+            http: self.http.clone(),
+            url,
+            offset: 0,
+            buf: Vec::new(),
+            pos: 0,
+            ranges_ignored: false,
+        })
+    }
+}
+
+/// A reader for a file served over HTTP, fetched in chunks with range requests.
+///
+/// Reaching the end of the file is not permanent: once a read returns `Ok(0)`, the next read
+/// issues a new range request starting at the same offset, so data appended to the file in the
+/// meantime will be picked up, as with a local file.
+///
+/// If the server ignores the `Range` header and answers with the whole file, the part of the file
+/// past the current offset is kept in memory and served from there, so the file is downloaded
+/// once per read to end of file rather than once per chunk.
+pub struct HttpFile {
+    http: HttpClient,
+    url: Url,
+    /// Offset in the remote file of the first byte past the end of `buf`.
+    offset: u64,
+    /// Most recently fetched chunk.
+    buf: Vec<u8>,
+    /// Number of bytes of `buf` already returned to callers.
+    pos: usize,
+    /// Whether the server has answered a range request with the whole file.
+    ranges_ignored: bool,
+}
+
+impl HttpFile {
+    /// Fetches the next chunk of the file into `buf`, possibly leaving it empty at end of file.
+    fn fill_buf(&mut self) -> io::Result<()> {
+        let range = format!(
+            "bytes={}-{}",
+            self.offset,
+            self.offset + READ_CHUNK_SIZE - 1
+        );
+        let res = self
+            .http
+            .get(self.url.clone())
+            .header("Range", range)
+            .send()
+            .map_err(reqwest_to_io)?;
+        let status = res.status();
+        if status == StatusCode::RANGE_NOT_SATISFIABLE {
+            self.buf.clear();
+            self.pos = 0;
+            return Ok(());
+        }
+        let body = res
+            .error_for_status()
+            .and_then(|res| res.bytes())
+            .map_err(reqwest_to_io)?;
+        self.buf.clear();
+        self.pos = 0;
+        match status {
+            StatusCode::PARTIAL_CONTENT => self.buf.extend_from_slice(&body),
+            StatusCode::OK => {
+                // Server ignored the range and sent the whole file: keep everything past our
+                // offset rather than fetching the file again for each chunk.
+                if !self.ranges_ignored {
+                    self.ranges_ignored = true;
+                    warn!(
+                        "Server ignored range request for {}; reading whole file",
+                        self.url
+                    );
+                }
+                let start = (self.offset as usize).min(body.len());
+                self.buf.extend_from_slice(&body[start..]);
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unexpected status {} for range request", status),
+                ))
+            }
+        }
+        self.offset += self.buf.len() as u64;
+        Ok(())
+    }
+}
+
+impl Read for HttpFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() {
+            self.fill_buf()?;
+        }
+        let n = buf.len().min(self.buf.len() - self.pos);
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[allow(clippy::io_other_error)] // `io::Error::other` needs Rust 1.74
+fn reqwest_to_io(e: reqwest::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+/// Extracts the values of all double- or single-quoted `href` attributes in an HTML document.
+fn extract_hrefs(html: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut rest = html;
+    while let Some(i) = rest.find("href=") {
+        rest = &rest[i + "href=".len()..];
+        let quote = match rest.chars().next() {
+            Some(q @ '"') | Some(q @ '\'') => q,
+            _ => continue,
+        };
+        rest = &rest[1..];
+        match rest.find(quote) {
+            Some(end) => {
+                result.push(&rest[..end]);
+                rest = &rest[end + 1..];
+            }
+            None => break,
+        }
+    }
+    result
+}
+
+/// Decodes `%XX` escapes in a URL path. Invalid escapes are left as is.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(b) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::commit::Commit;
+    use crate::logdir::LogdirLoader;
+    use crate::types::{Step, Tag, WallTime};
+    use crate::writer::SummaryWriteExt;
+
+    /// Starts a minimal HTTP/1.1 file server for `root` on a local port, serving directory
+    /// listings and, if `honor_range` is set, single-range `Range` headers. Each request for a
+    /// file increments `file_requests`. Returns the base URL.
+    fn serve(root: &Path, honor_range: bool, file_requests: Arc<AtomicUsize>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().unwrap();
+        let root = root.to_path_buf();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.expect("accept");
+                let root = root.clone();
+                let file_requests = file_requests.clone();
+                thread::spawn(move || handle(stream, &root, honor_range, &file_requests));
+            }
+        });
+        Url::parse(&format!("http://{}/", addr)).unwrap()
+    }
+
+    fn handle(mut stream: TcpStream, root: &Path, honor_range: bool, file_requests: &AtomicUsize) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        loop {
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                return;
+            }
+            let path = request_line.split(' ').nth(1).unwrap_or("/").to_string();
+            let mut range = None;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if !honor_range {
+                    continue;
+                }
+                let lower = line.to_ascii_lowercase();
+                if let Some(spec) = lower.strip_prefix("range: bytes=") {
+                    let mut parts = spec.splitn(2, '-');
+                    let start: u64 = parts.next().unwrap().parse().unwrap();
+                    let end: u64 = parts.next().unwrap().parse().unwrap();
+                    range = Some((start, end));
+                }
+            }
+            let fs_path = root.join(percent_decode(path.trim_start_matches('/')));
+            let (status, body) = if fs_path.is_dir() {
+                let mut html = String::from("<html><body><a href=\"../\">..</a>\n");
+                for entry in fs::read_dir(&fs_path).unwrap() {
+                    let entry = entry.unwrap();
+                    let mut name = entry.file_name().to_string_lossy().into_owned();
+                    if entry.file_type().unwrap().is_dir() {
+                        name.push('/');
+                    }
+                    html.push_str(&format!("<a href=\"{}\">{}</a>\n", name, name));
+                }
+                html.push_str("</body></html>");
+                ("200 OK", html.into_bytes())
+            } else {
+                file_requests.fetch_add(1, Ordering::SeqCst);
+                let data = fs::read(&fs_path).unwrap();
+                match range {
+                    Some((start, _)) if start >= data.len() as u64 => {
+                        ("416 Range Not Satisfiable", Vec::new())
+                    }
+                    Some((start, end)) => {
+                        let end = (end as usize + 1).min(data.len());
+                        ("206 Partial Content", data[start as usize..end].to_vec())
+                    }
+                    None => ("200 OK", data),
+                }
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n",
+                status,
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+            stream.flush().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_http_logdir() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let train_dir = logdir.path().join("train");
+        fs::create_dir(&train_dir)?;
+        fs::write(logdir.path().join("README"), "not an event file")?;
+        let train_path = train_dir.join("tfevents.123");
+        let mut f = File::create(&train_path)?;
+        let tag = Tag("accuracy".to_string());
+        for i in 0..3 {
+            let wt = WallTime::new(1234.0 + i as f64).unwrap();
+            f.write_scalar(&tag, Step(i), wt, 0.25 * i as f32)?;
+        }
+        f.flush()?;
+
+        let root = serve(logdir.path(), true, Arc::default());
+        let commit = Commit::new();
+        let mut loader = LogdirLoader::new(&commit, HttpLogdir::new(root)?, 1);
+        loader.reload();
+
+        let steps = |commit: &Commit| -> Vec<i64> {
+            let runs = commit.runs.read().unwrap();
+            assert_eq!(
+                runs.keys().collect::<Vec<_>>(),
+                vec![&Run("train".to_string())]
+            );
+            let run_data = runs[&Run("train".to_string())].read().unwrap();
+            let ts = &run_data.scalars[&tag];
+            ts.valid_values().map(|(step, _, _)| step.0).collect()
+        };
+        assert_eq!(steps(&commit), vec![0, 1, 2]);
+
+        // Appended data should be picked up on the next reload.
+        let mut f = fs::OpenOptions::new().append(true).open(&train_path)?;
+        let wt = WallTime::new(1237.0).unwrap();
+        f.write_scalar(&tag, Step(3), wt, 0.75)?;
+        f.flush()?;
+        loader.reload();
+        assert_eq!(steps(&commit), vec![0, 1, 2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_http_file_ranges_ignored() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let data: Vec<u8> = (0..(READ_CHUNK_SIZE * 5 / 2)).map(|i| i as u8).collect();
+        fs::write(logdir.path().join("tfevents.123"), &data)?;
+        let path = EventFileBuf(PathBuf::from("tfevents.123"));
+
+        for &honor_range in &[true, false] {
+            let file_requests = Arc::new(AtomicUsize::new(0));
+            let root = serve(logdir.path(), honor_range, file_requests.clone());
+            let mut file = HttpLogdir::new(root)?.open(&path)?;
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)?;
+            assert_eq!(contents, data);
+            // Three chunks plus an empty read at end of file, or one whole-file download plus
+            // one at end of file.
+            let expected = if honor_range { 4 } else { 2 };
+            assert_eq!(file_requests.load(Ordering::SeqCst), expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_hrefs() {
+        let html = r#"<a href="train/">train/</a> <A HREF="x"> <a href='b%20c'>b</a> href=bare"#;
+        assert_eq!(extract_hrefs(html), vec!["train/", "b%20c"]);
+        assert_eq!(percent_decode("b%20c%2"), "b c%2");
+    }
+}
@@ -0,0 +1,467 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Ingestion service for runs whose events are pushed over gRPC rather than read from files.
+//!
+//! A pushed run is loaded by a [`RunLoader`] like any other run, but its events come from
+//! `WriteEvents` streams instead of event files. [`RunSources`] records which runs are pushed and
+//! which are file-backed, so that no run is ever both.
+
+use log::info;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::Mutex as AsyncMutex;
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::commit::{Commit, RunData};
+use crate::logdir::RunOptions;
+use crate::proto::tensorboard as pb;
+use crate::run::RunLoader;
+use crate::types::Run;
+use data::tensor_board_ingest_server::TensorBoardIngest;
+use pb::data;
+
+/// Loader for a pushed run, which has no files to read.
+type PushedLoader = RunLoader<io::Empty>;
+
+/// Where a run's events come from.
+#[derive(Debug)]
+enum Source {
+    /// Event files in a log directory, loaded by a [`LogdirLoader`](crate::logdir::LogdirLoader).
+    Files,
+    /// `WriteEvents` streams. The lock is held by the stream currently writing to the run.
+    Pushed(Arc<AsyncMutex<PushedLoader>>),
+}
+
+/// Registry of which runs are file-backed and which are pushed. Share one registry between a
+/// [`LogdirLoader`](crate::logdir::LogdirLoader) and an [`IngestHandler`] for the same commit.
+///
+/// A run belongs to whichever source claims it first. File-backed claims are released once the
+/// run has no more event files; pushed claims last for the life of the registry, so that later
+/// streams keep appending to the same run.
+#[derive(Debug, Default)]
+pub struct RunSources {
+    runs: Mutex<HashMap<Run, Source>>,
+    /// Options for new pushed runs, shared by the logdir loader. See
+    /// [`LogdirLoader::run_sources`](crate::logdir::LogdirLoader::run_sources).
+    run_options: Mutex<Option<RunOptions>>,
+}
+
+impl RunSources {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claims `run` for loading from event files. Returns `false` if it's a pushed run, which
+    /// must not also be read from files.
+    pub fn claim_files(&self, run: &Run) -> bool {
+        let mut runs = self.runs.lock().expect("failed to lock run sources");
+        let source = runs.entry(run.clone()).or_insert(Source::Files);
+        matches!(source, Source::Files)
+    }
+
+    /// Releases a claim taken by [`Self::claim_files`]. Does nothing if `run` is pushed.
+    pub fn release_files(&self, run: &Run) {
+        let mut runs = self.runs.lock().expect("failed to lock run sources");
+        if let Entry::Occupied(entry) = runs.entry(run.clone()) {
+            if matches!(entry.get(), Source::Files) {
+                entry.remove();
+            }
+        }
+    }
+
+    /// Checks whether `run` is a pushed run.
+    pub fn is_pushed(&self, run: &Run) -> bool {
+        let runs = self.runs.lock().expect("failed to lock run sources");
+        matches!(runs.get(run), Some(Source::Pushed(_)))
+    }
+
+    /// Sets the options for loading pushed runs that start after this call.
+    pub(crate) fn set_run_options(&self, options: RunOptions) {
+        *self.run_options.lock().expect("failed to lock run options") = Some(options);
+    }
+
+    /// Claims `run` as a pushed run, if it isn't already, and returns its loader. Returns `None`
+    /// if the run is file-backed.
+    pub(crate) fn claim_pushed(&self, run: &Run) -> Option<Arc<AsyncMutex<PushedLoader>>> {
+        let mut runs = self.runs.lock().expect("failed to lock run sources");
+        let source = runs.entry(run.clone()).or_insert_with(|| {
+            Source::Pushed(Arc::new(AsyncMutex::new(RunLoader::new(run.clone()))))
+        });
+        match source {
+            Source::Files => None,
+            Source::Pushed(loader) => Some(Arc::clone(loader)),
+        }
+    }
+}
+
+/// Ingestion gRPC service implementation.
+///
+/// Each stream must authenticate with an `authorization: Bearer <token>` header naming one of
+/// the handler's tokens. A stream holds its run's write lock from its first request to its end;
+/// other streams for the same run fail with `ABORTED` in the meantime.
+///
+/// Requests are staged and committed one at a time, and the next request isn't read until the
+/// previous one is committed, so a client that pushes faster than the server can load is slowed
+/// down by gRPC flow control rather than buffered without bound.
+#[derive(Debug)]
+pub struct IngestHandler {
+    commit: &'static Commit,
+    sources: Arc<RunSources>,
+    tokens: Vec<String>,
+}
+
+impl IngestHandler {
+    /// Creates a handler that writes pushed runs to `commit`. Streams must present one of
+    /// `tokens`; with no tokens, every stream is rejected.
+    pub fn new(commit: &'static Commit, sources: Arc<RunSources>, tokens: Vec<String>) -> Self {
+        IngestHandler {
+            commit,
+            sources,
+            tokens,
+        }
+    }
+
+    /// Checks whether an incoming stream carries one of this handler's bearer tokens.
+    fn authenticate(&self, metadata: &MetadataMap) -> bool {
+        let given = metadata
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        matches!(given, Some(given) if self.tokens.iter().any(|t| tokens_eq(t, given)))
+    }
+
+    /// Creates a loader for a new pushed run, with the logdir loader's options for handling
+    /// events if it shares them, and wired to the commit like the logdir loader's runs.
+    fn new_loader(&self, run: &Run) -> PushedLoader {
+        let mut loader = RunLoader::new(run.clone());
+        let options = (self.sources.run_options.lock()).expect("failed to lock run options");
+        match &*options {
+            Some(options) => options.apply(&mut loader, run, self.commit),
+            None => {
+                loader.notifier(Some(self.commit.notifier()));
+                loader.counters(Some(self.commit.counters()));
+                loader.load_shedding(Some(self.commit.load_shedding()));
+            }
+        }
+        loader
+    }
+
+    /// Stages and commits `events` to `run`, adding the run to the commit if needed. Returns the
+    /// number of events written.
+    ///
+    /// The runs map is only write-locked to add the run, so that reads of other runs aren't held
+    /// up while the events are staged and committed.
+    fn write(&self, loader: &mut PushedLoader, run: &Run, events: Vec<pb::Event>) -> usize {
+        loop {
+            let runs = self
+                .commit
+                .runs
+                .read()
+                .expect("failed to read-lock runs map");
+            if let Some(run_data) = runs.get(run) {
+                return loader.ingest(events, run_data);
+            }
+            drop(runs);
+            let mut runs = self
+                .commit
+                .runs
+                .write()
+                .expect("failed to write-lock runs map");
+            runs.entry(run.clone()).or_insert_with(|| {
+                let mut run_data = RunData::default();
+                self.commit.counters().recount(&mut run_data);
+                RwLock::new(run_data)
+            });
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl TensorBoardIngest for IngestHandler {
+    async fn write_events(
+        &self,
+        req: Request<Streaming<data::WriteEventsRequest>>,
+    ) -> Result<Response<data::WriteEventsResponse>, Status> {
+        if !self.authenticate(req.metadata()) {
+            return Err(Status::unauthenticated("missing or invalid bearer token"));
+        }
+        let mut stream = req.into_inner();
+        let mut msg = match stream.message().await? {
+            Some(msg) => msg,
+            None => return Ok(Response::new(data::WriteEventsResponse::default())),
+        };
+        if msg.run_name.is_empty() {
+            return Err(Status::invalid_argument("first request must set run_name"));
+        }
+        let run = Run(std::mem::take(&mut msg.run_name));
+        if self.commit.read_only().get() {
+            return Err(Status::failed_precondition("server is read-only"));
+        }
+        let loader = self.sources.claim_pushed(&run).ok_or_else(|| {
+            Status::failed_precondition(format!("run {:?} is loaded from event files", run.0))
+        })?;
+        let mut loader = loader.try_lock_owned().map_err(|_| {
+            Status::aborted(format!(
+                "run {:?} is being written by another stream",
+                run.0
+            ))
+        })?;
+        let present = self
+            .commit
+            .runs
+            .read()
+            .expect("failed to read-lock runs map")
+            .contains_key(&run);
+        if !present {
+            // New, or removed from the commit since the last stream: start from scratch.
+            *loader = self.new_loader(&run);
+        }
+
+        let mut written = 0;
+        loop {
+            if !msg.run_name.is_empty() && msg.run_name != run.0 {
+                return Err(Status::invalid_argument(format!(
+                    "stream for run {:?} got a request for run {:?}",
+                    run.0, msg.run_name
+                )));
+            }
+            written += self.write(&mut loader, &run, msg.events);
+            msg = match stream.message().await? {
+                Some(msg) => msg,
+                None => break,
+            };
+        }
+        info!("Ingested {} events for pushed run {:?}", written, run.0);
+        Ok(Response::new(data::WriteEventsResponse {
+            events_written: written as i64,
+        }))
+    }
+}
+
+/// Compares a configured token with a client's in time that depends only on their lengths, so
+/// that response times don't reveal how much of a guess was right.
+fn tokens_eq(expected: &str, given: &str) -> bool {
+    let (a, b) = (expected.as_bytes(), given.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Channel, Server};
+    use tonic::Code;
+
+    use crate::disk_logdir::DiskLogdir;
+    use crate::logdir::LogdirLoader;
+    use crate::run::SampleSizes;
+    use crate::run_filter::TagFilter;
+    use crate::server::DataProviderHandler;
+    use crate::types::Tag;
+    use data::tensor_board_data_provider_server::TensorBoardDataProvider;
+    use data::tensor_board_ingest_client::TensorBoardIngestClient;
+    use data::tensor_board_ingest_server::TensorBoardIngestServer;
+
+    const TOKEN: &str = "s3cret";
+
+    fn scalar_event(step: i64, tag: &str, value: f32) -> pb::Event {
+        pb::Event {
+            step,
+            wall_time: 1234.0 + step as f64,
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: vec![pb::summary::Value {
+                    tag: tag.to_string(),
+                    value: Some(pb::summary::value::Value::SimpleValue(value)),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn write_request(run_name: &str, events: Vec<pb::Event>) -> data::WriteEventsRequest {
+        data::WriteEventsRequest {
+            run_name: run_name.to_string(),
+            events,
+        }
+    }
+
+    /// Serves an ingest handler for `commit` on a local port, and returns a client for it.
+    async fn serve(
+        commit: &'static Commit,
+        sources: Arc<RunSources>,
+    ) -> TensorBoardIngestClient<Channel> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler = IngestHandler::new(commit, sources, vec![TOKEN.to_string()]);
+        tokio::spawn(
+            Server::builder()
+                .add_service(TensorBoardIngestServer::new(handler))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        TensorBoardIngestClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap()
+    }
+
+    fn authed<T>(msg: T, token: &str) -> Request<T> {
+        let mut req = Request::new(msg);
+        let value = format!("Bearer {}", token).parse().unwrap();
+        req.metadata_mut().insert("authorization", value);
+        req
+    }
+
+    #[tokio::test]
+    async fn test_push_and_read_scalars() {
+        let commit: &'static Commit = Box::leak(Box::new(Commit::new()));
+        let mut client = serve(commit, Arc::new(RunSources::new())).await;
+
+        let requests = vec![
+            write_request("pushed", vec![scalar_event(0, "loss", 1.0)]),
+            write_request(
+                "",
+                vec![scalar_event(1, "loss", 0.5), scalar_event(2, "loss", 0.25)],
+            ),
+        ];
+        let res = client
+            .write_events(authed(tokio_stream::iter(requests), TOKEN))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.events_written, 3);
+
+        let handler = DataProviderHandler {
+            commit,
+            blob_source: None,
+        };
+        let req = Request::new(data::ReadScalarsRequest {
+            experiment_id: "123".to_string(),
+            plugin_filter: Some(data::PluginFilter {
+                plugin_name: "scalars".to_string(),
+            }),
+            downsample: Some(data::Downsample { num_points: 1000 }),
+            ..Default::default()
+        });
+        let res = handler.read_scalars(req).await.unwrap().into_inner();
+        assert_eq!(res.runs.len(), 1);
+        assert_eq!(res.runs[0].run_name, "pushed");
+        assert_eq!(res.runs[0].tags.len(), 1);
+        assert_eq!(res.runs[0].tags[0].tag_name, "loss");
+        let data = res.runs[0].tags[0].data.as_ref().unwrap();
+        assert_eq!(data.step, vec![0, 1, 2]);
+        assert_eq!(data.wall_time, vec![1234.0, 1235.0, 1236.0]);
+        assert_eq!(data.value, vec![1.0, 0.5, 0.25]);
+
+        // A later stream appends to the same run.
+        let requests = vec![write_request(
+            "pushed",
+            vec![scalar_event(3, "loss", 0.125)],
+        )];
+        client
+            .write_events(authed(tokio_stream::iter(requests), TOKEN))
+            .await
+            .unwrap();
+        let runs = commit.runs.read().unwrap();
+        let run_data = runs[&Run("pushed".to_string())].read().unwrap();
+        assert_eq!(
+            run_data
+                .scalars
+                .values()
+                .next()
+                .unwrap()
+                .valid_values()
+                .count(),
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn test_logdir_loader_options() {
+        let commit: &'static Commit = Box::leak(Box::new(Commit::new()));
+        let logdir = tempfile::tempdir().unwrap();
+        let mut loader = LogdirLoader::new(commit, DiskLogdir::new(logdir.path().into()), 1);
+        let mut tag_filter = TagFilter::new();
+        tag_filter.exclude("debug/**");
+        loader.tag_filter(tag_filter);
+        loader.sample_sizes(SampleSizes {
+            scalars: 2,
+            ..SampleSizes::default()
+        });
+        let sources = Arc::new(RunSources::new());
+        loader.run_sources(Some(Arc::clone(&sources)));
+        let mut client = serve(commit, sources).await;
+
+        let mut events: Vec<pb::Event> = (0..5).map(|i| scalar_event(i, "loss", 1.0)).collect();
+        events.push(scalar_event(5, "debug/grad_norm", 1.0));
+        let requests = vec![write_request("pushed", events)];
+        client
+            .write_events(authed(tokio_stream::iter(requests), TOKEN))
+            .await
+            .unwrap();
+        let runs = commit.runs.read().unwrap();
+        let run_data = runs[&Run("pushed".to_string())].read().unwrap();
+        let tags: Vec<&Tag> = run_data.scalars.keys().collect();
+        assert_eq!(tags, vec![&Tag("loss".to_string())]);
+        let loss = &run_data.scalars[&Tag("loss".to_string())];
+        assert_eq!(loss.valid_values().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_streams() {
+        let commit: &'static Commit = Box::leak(Box::new(Commit::new()));
+        let sources = Arc::new(RunSources::new());
+        assert!(sources.claim_files(&Run("on_disk".to_string())));
+        let mut client = serve(commit, Arc::clone(&sources)).await;
+        let push = |run: &str, token: &str| {
+            let requests = vec![write_request(run, vec![scalar_event(0, "loss", 1.0)])];
+            authed(tokio_stream::iter(requests), token)
+        };
+
+        let err = client
+            .write_events(push("pushed", "wrong"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::Unauthenticated);
+        let err = client.write_events(push("", TOKEN)).await.unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = client
+            .write_events(push("on_disk", TOKEN))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+        assert!(commit.runs.read().unwrap().is_empty());
+
+        // While another stream holds the run's write lock, new streams are turned away.
+        let lock = sources.claim_pushed(&Run("busy".to_string())).unwrap();
+        let guard = lock.lock().await;
+        let err = client.write_events(push("busy", TOKEN)).await.unwrap_err();
+        assert_eq!(err.code(), Code::Aborted);
+        drop(guard);
+        client.write_events(push("busy", TOKEN)).await.unwrap();
+
+        // Pushed runs can't be claimed for files.
+        assert!(sources.is_pushed(&Run("busy".to_string())));
+        assert!(!sources.claim_files(&Run("busy".to_string())));
+        sources.release_files(&Run("busy".to_string()));
+        assert!(sources.is_pushed(&Run("busy".to_string())));
+    }
+}
@@ -0,0 +1,238 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Resumable reading for streams of length-delimited records.
+//!
+//! Each record is a base-128 varint length followed by that many bytes of data, as written by
+//! protobuf's `writeDelimitedTo` and prost's `encode_length_delimited`. Unlike TFRecords, there
+//! are no checksums, so a corrupt length can't be detected except by the data failing to parse.
+
+use std::fmt::{self, Debug};
+use std::io::{self, Read};
+
+use crate::tf_record::{read_remaining, ReadRecordError};
+
+/// Maximum length of a varint encoding a `u64`.
+const MAX_VARINT_LENGTH: usize = 10;
+
+/// A reader for a stream of length-delimited records. Like a
+/// [`TfRecordReader`][crate::tf_record::TfRecordReader], this reader can read a single record
+/// over one or more underlying reads, to support growing, partially flushed files.
+pub struct LengthDelimitedReader<R> {
+    /// Bytes of the current record's varint length read so far. Complete once the last byte has
+    /// its high bit clear.
+    header: Vec<u8>,
+    /// Data of the current record. Once the header is complete, this has capacity equal to the
+    /// data length; before then, it has no capacity.
+    data: Vec<u8>,
+    /// Underlying reader.
+    reader: R,
+    /// Maximum data length of a record, in bytes. See [`Self::max_record_len`].
+    max_record_len: Option<u64>,
+    /// Offset of the end of the last complete record read, in bytes. See [`Self::offset`].
+    offset: u64,
+}
+
+impl<R: Debug> Debug for LengthDelimitedReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LengthDelimitedReader")
+            .field("header", &format_args!("{}", self.header.len()))
+            .field(
+                "data",
+                &format_args!("{}/{}", self.data.len(), self.data.capacity()),
+            )
+            .field("reader", &self.reader)
+            .finish()
+    }
+}
+
+impl<R: Read> LengthDelimitedReader<R> {
+    /// Creates a reader for a stream of length-delimited records, starting at the beginning of a
+    /// record (usually, the start of the file).
+    pub fn new(reader: R) -> Self {
+        LengthDelimitedReader {
+            header: Vec::with_capacity(MAX_VARINT_LENGTH),
+            data: Vec::new(),
+            reader,
+            max_record_len: None,
+            offset: 0,
+        }
+    }
+
+    /// Sets the maximum data length of a record, in bytes. Reading a longer record fails with
+    /// [`ReadRecordError::ExceedsLimit`] before any of its data is read or allocated. `None` (the
+    /// default) means no limit.
+    pub fn max_record_len(&mut self, bytes: Option<u64>) {
+        self.max_record_len = bytes;
+    }
+
+    /// Gets the number of bytes in all records read so far. This is the offset of the start of
+    /// the next record, not counting any part of it that has been read while truncated.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Skips to the given offset in the stream, which should be the [`Self::offset`] of an
+    /// earlier reader. Should be called before reading any records.
+    ///
+    /// Fails with [`io::ErrorKind::UnexpectedEof`] if the stream is shorter than `offset`.
+    pub fn resume_at(&mut self, offset: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.reader).take(offset), &mut io::sink())?;
+        if skipped < offset {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("stream ended after {} of {} bytes", skipped, offset),
+            ));
+        }
+        self.offset = offset;
+        Ok(())
+    }
+
+    /// Consumes this reader, returning the underlying reader `R`.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Attempts to read a record's data, pausing gracefully in the face of truncations, just like
+    /// [`TfRecordReader::read_record`][crate::tf_record::TfRecordReader::read_record].
+    ///
+    /// A length whose varint encoding is longer than 10 bytes fails with an
+    /// [`io::ErrorKind::InvalidData`] error, after which the stream can't be read further.
+    pub fn read_record(&mut self) -> Result<Vec<u8>, ReadRecordError> {
+        if self.data.capacity() == 0 {
+            let length = loop {
+                if let Some(length) = decode_varint(&self.header)? {
+                    break length;
+                }
+                // Read one byte at a time, since we don't know where the varint ends.
+                let want = self.header.len() + 1;
+                (&mut self.reader).take(1).read_to_end(&mut self.header)?;
+                if self.header.len() < want {
+                    return Err(ReadRecordError::Truncated);
+                }
+            };
+            if let Some(limit) = self.max_record_len {
+                if length > limit {
+                    return Err(ReadRecordError::ExceedsLimit { length, limit });
+                }
+            }
+            let data_length = length as usize;
+            if data_length as u64 != length {
+                return Err(ReadRecordError::TooLarge(length));
+            }
+            if data_length == 0 {
+                return Ok(self.finish_record());
+            }
+            self.data.reserve_exact(data_length);
+        }
+
+        if self.data.len() < self.data.capacity() {
+            read_remaining(&mut self.reader, &mut self.data)?;
+        }
+        Ok(self.finish_record())
+    }
+
+    /// Takes the data of the record just read, leaving this reader ready for the next record.
+    fn finish_record(&mut self) -> Vec<u8> {
+        let data = std::mem::take(&mut self.data);
+        self.offset += (self.header.len() + data.len()) as u64;
+        self.header.clear();
+        data
+    }
+}
+
+/// Decodes a varint from `buf`, returning `None` if it's incomplete.
+fn decode_varint(buf: &[u8]) -> Result<Option<u64>, ReadRecordError> {
+    let mut value: u64 = 0;
+    for (i, &byte) in buf.iter().enumerate() {
+        if i == MAX_VARINT_LENGTH {
+            break;
+        }
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    if buf.len() >= MAX_VARINT_LENGTH {
+        return Err(ReadRecordError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "record length varint is too long",
+        )));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scripted_reader::ScriptedReader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_success_with_truncations() {
+        let mut reader = LengthDelimitedReader::new(ScriptedReader::new(vec![
+            // Record 1: length 3, then part of the data.
+            b"\x03ab".to_vec(),
+            // Record 1: rest of the data; record 2: first byte of a two-byte length (200).
+            b"c\xc8".to_vec(),
+            // Record 2: rest of the length and all of the data; record 3: empty.
+            [&b"\x01"[..], &[b'x'; 200][..], &b"\x00"[..]].concat(),
+        ]));
+
+        assert!(matches!(
+            reader.read_record(),
+            Err(ReadRecordError::Truncated)
+        ));
+        assert_eq!(reader.offset(), 0);
+        assert_eq!(reader.read_record().unwrap(), b"abc".to_vec());
+        assert_eq!(reader.offset(), 4);
+        assert!(matches!(
+            reader.read_record(),
+            Err(ReadRecordError::Truncated)
+        ));
+        assert_eq!(reader.read_record().unwrap(), vec![b'x'; 200]);
+        assert_eq!(reader.offset(), 4 + 2 + 200);
+        assert_eq!(reader.read_record().unwrap(), Vec::<u8>::new());
+        assert_eq!(reader.offset(), 4 + 2 + 200 + 1);
+        assert!(matches!(
+            reader.read_record(),
+            Err(ReadRecordError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn test_resume_and_limits() {
+        let file = b"\x03abc\x05defgh\x02ij".to_vec();
+        let mut reader = LengthDelimitedReader::new(Cursor::new(file.clone()));
+        reader.resume_at(4).unwrap();
+        reader.max_record_len(Some(4));
+        match reader.read_record() {
+            Err(ReadRecordError::ExceedsLimit {
+                length: 5,
+                limit: 4,
+            }) => (),
+            other => panic!("{:?}", other),
+        };
+
+        let mut reader = LengthDelimitedReader::new(Cursor::new(file));
+        assert!(reader.resume_at(100).is_err());
+
+        let mut reader = LengthDelimitedReader::new(Cursor::new(vec![0xff; 11]));
+        match reader.read_record() {
+            Err(ReadRecordError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+            other => panic!("{:?}", other),
+        };
+    }
+}
@@ -21,28 +21,43 @@ limitations under the License.
 /// because of <https://github.com/bazelbuild/rules_rust/issues/573>.
 pub(crate) const VERSION: &str = "0.3.0-alpha.0";
 
+pub mod async_writer;
+pub mod bench_load;
 pub mod blob_key;
 pub mod cli;
+pub mod closure_logdir;
 pub mod commit;
 pub mod data_compat;
 pub mod disk_logdir;
+pub mod doctor;
 pub mod downsample;
+pub mod embed;
 pub mod event_file;
+pub mod export;
+pub mod fifo_logdir;
 pub mod gcs;
+pub mod gzip;
+pub mod hparams;
+#[cfg(feature = "http")]
+pub mod http_logdir;
+pub mod ingest;
+pub mod length_delimited;
+pub mod loader_state;
 pub mod logdir;
+pub mod logging;
 pub mod masked_crc;
+pub mod metadata_override;
 pub mod reservoir;
 pub mod run;
+pub mod run_filter;
 pub mod server;
 pub mod tf_record;
 pub mod types;
+pub mod writer;
 
 #[cfg(test)]
 mod scripted_reader;
 
-#[cfg(test)]
-mod writer;
-
 /// Protocol buffer bindings.
 #[allow(clippy::all)]
 pub mod proto {
@@ -53,5 +68,9 @@ pub mod proto {
         pub mod data {
             include!("tensorboard.data.pb.rs");
         }
+        /// Bindings for `package tensorboard.hparams`, containing hparams plugin protos.
+        pub mod hparams {
+            include!("tensorboard.hparams.pb.rs");
+        }
     }
 }
@@ -0,0 +1,198 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Wire format for saved run loader state. See [`RunLoader::save_state`].
+//!
+//! These are protobuf messages, declared by hand rather than generated from a `.proto` file,
+//! since they're only ever read and written by this crate. Field numbers must never be reused.
+//! Any change that an older server couldn't read correctly must bump [`VERSION`].
+//!
+//! [`RunLoader::save_state`]: crate::run::RunLoader::save_state
+
+use crate::proto::tensorboard as pb;
+
+/// Version of the format written by this server. States with any other version are rejected.
+pub const VERSION: u32 = 1;
+
+/// Everything needed to resume loading a run in a new process.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RunLoaderState {
+    /// Always [`VERSION`] for states written by this server.
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    /// Event files that have been opened, in any order.
+    #[prost(message, repeated, tag = "2")]
+    pub files: Vec<FileState>,
+    /// Earliest wall time seen in the run, if any.
+    #[prost(double, optional, tag = "3")]
+    pub start_time: Option<f64>,
+    /// Wall time of the most recently staged event, if any.
+    #[prost(double, optional, tag = "4")]
+    pub last_wall_time: Option<f64>,
+    #[prost(uint64, tag = "5")]
+    pub zero_wall_time_events: u64,
+    #[prost(message, repeated, tag = "6")]
+    pub time_series: Vec<TimeSeriesState>,
+    #[prost(uint64, tag = "7")]
+    pub negative_step_events_dropped: u64,
+    /// Distinct file versions seen in the run, in the order first seen.
+    #[prost(string, repeated, tag = "8")]
+    pub file_versions: Vec<String>,
+    #[prost(uint64, tag = "9")]
+    pub duplicate_values_dropped: u64,
+    #[prost(uint64, tag = "10")]
+    pub metadata_conflicts: u64,
+    /// Number of values dropped for exceeding the run's tag limit.
+    #[prost(uint64, tag = "11")]
+    pub tag_overflow: u64,
+    /// The first few distinct tags dropped for exceeding the run's tag limit.
+    #[prost(string, repeated, tag = "12")]
+    pub rejected_tags: Vec<String>,
+    /// Number of values dropped by the run's tag filter.
+    #[prost(uint64, tag = "13")]
+    pub filtered_values: u64,
+    /// Number of events read with no contents.
+    #[prost(uint64, tag = "14")]
+    pub empty_events: u64,
+    /// Number of events dropped for having a step above the run's limit.
+    #[prost(uint64, tag = "15")]
+    pub large_step_events_dropped: u64,
+    /// Number of points dropped for being older than the run's retention horizon.
+    #[prost(uint64, tag = "16")]
+    pub expired_points: u64,
+    /// Path of the newest event file forgotten for exceeding the run's file limit. Files named
+    /// up to this are not reopened.
+    #[prost(string, optional, tag = "17")]
+    pub expired_through: Option<String>,
+}
+
+/// Read position in a single event file.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FileState {
+    /// Path to the event file, as given by the log directory.
+    #[prost(string, tag = "1")]
+    pub path: String,
+    /// Offset of the next record to read.
+    #[prost(uint64, tag = "2")]
+    pub offset: u64,
+    /// Whether the file can no longer be read. If set, `offset` is meaningless.
+    #[prost(bool, tag = "3")]
+    pub dead: bool,
+    /// Why the file can no longer be read, if `dead` is set. May be unset even then, in states
+    /// saved before reasons were recorded.
+    #[prost(oneof = "file_state::DeadReason", tags = "4, 5, 6, 7, 8, 9")]
+    pub dead_reason: Option<file_state::DeadReason>,
+}
+
+/// Nested types in [`FileState`].
+pub mod file_state {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum DeadReason {
+        /// The file could not be opened. Always `true` when set.
+        #[prost(bool, tag = "4")]
+        OpenFailed(bool),
+        /// The record at this offset could not be read.
+        #[prost(uint64, tag = "5")]
+        ReadError(u64),
+        /// The file is no longer part of the run. Always `true` when set.
+        #[prost(bool, tag = "6")]
+        RemovedFromDisk(bool),
+        /// The record at this offset failed its checksum.
+        #[prost(uint64, tag = "7")]
+        BadChecksum(u64),
+        /// Reading the record at this offset failed after all retries.
+        #[prost(uint64, tag = "8")]
+        RetriesExhausted(u64),
+        /// The run had more event files than its limit. Always `true` when set.
+        #[prost(bool, tag = "9")]
+        Expired(bool),
+    }
+}
+
+/// Reservoir state and contents for a single time series.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TimeSeriesState {
+    #[prost(string, tag = "1")]
+    pub tag: String,
+    #[prost(message, optional, tag = "2")]
+    pub metadata: Option<pb::SummaryMetadata>,
+    /// Estimated number of records offered to the reservoir.
+    #[prost(uint64, tag = "3")]
+    pub seen: u64,
+    /// Position of the reservoir's random number generator, as a 16-byte little-endian integer.
+    #[prost(bytes, tag = "4")]
+    pub rng_position: Vec<u8>,
+    /// Points that have been committed and are still in the reservoir, in step order. Values are
+    /// in committed form (e.g., `scalar`).
+    #[prost(message, repeated, tag = "5")]
+    pub committed: Vec<Point>,
+    /// Points that have been staged but not committed, in step order. Values are in staged form
+    /// (e.g., `summary`).
+    #[prost(message, repeated, tag = "6")]
+    pub staged: Vec<Point>,
+}
+
+/// A single point in a time series, staged or committed.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Point {
+    #[prost(int64, tag = "1")]
+    pub step: i64,
+    #[prost(double, tag = "2")]
+    pub wall_time: f64,
+    #[prost(oneof = "point::Value", tags = "3, 4, 5, 6, 7, 8, 9, 10")]
+    pub value: Option<point::Value>,
+}
+
+/// Nested types in [`Point`].
+pub mod point {
+    use super::*;
+
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Value {
+        /// Staged: a serialized `GraphDef`.
+        #[prost(bytes, tag = "3")]
+        GraphDef(Vec<u8>),
+        /// Staged: a serialized `RunMetadata`.
+        #[prost(bytes, tag = "4")]
+        TaggedRunMetadata(Vec<u8>),
+        /// Staged: a summary value, of which only the `value` field is set.
+        #[prost(message, tag = "5")]
+        Summary(pb::summary::Value),
+        /// Committed: a scalar.
+        #[prost(float, tag = "6")]
+        Scalar(f32),
+        /// Committed: a tensor.
+        #[prost(message, tag = "7")]
+        Tensor(pb::TensorProto),
+        /// Committed: a blob sequence.
+        #[prost(message, tag = "8")]
+        BlobSequence(BlobSequence),
+        /// Committed: a point that could not be converted to its time series' data class. Always
+        /// `true` when set.
+        #[prost(bool, tag = "9")]
+        DataLoss(bool),
+        /// Committed: a scalar that single precision can't represent exactly. Others are saved
+        /// as `Scalar`.
+        #[prost(double, tag = "10")]
+        ScalarDouble(f64),
+    }
+}
+
+/// A committed blob sequence value.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BlobSequence {
+    #[prost(bytes, repeated, tag = "1")]
+    pub blobs: Vec<Vec<u8>>,
+}
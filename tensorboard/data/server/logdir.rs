@@ -15,15 +15,32 @@ limitations under the License.
 
 //! Loader for many runs under a directory.
 
-use log::warn;
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use log::{error, info, warn, Level};
+use rayon::prelude::{IntoParallelIterator, ParallelBridge, ParallelIterator};
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::io::{self, Read};
+use std::ops::Deref;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::{self, Thread};
+use std::time::{Duration, SystemTime};
 
-use crate::commit::Commit;
-use crate::run::RunLoader;
-use crate::types::Run;
+use crate::commit::{Commit, ReadOnlySwitch, RunData, ScalarPrecision};
+use crate::event_file::Framing;
+use crate::ingest::RunSources;
+use crate::logging::{log_kv, warn_limited};
+use crate::metadata_override::MetadataOverrides;
+use crate::reservoir::DuplicateStepPolicy;
+use crate::run::{
+    DuplicateTagPolicy, EventFileOrder, ExpiredFilePolicy, LoadError, NegativeStepPolicy,
+    RunLoader, RunMetadataTags, SampleSizes, TagBudget, ZeroWallTime,
+    DEFAULT_MAX_PLUGIN_CONTENT_LEN, DEFAULT_MAX_TAGS, DEFAULT_READ_RETRIES,
+};
+use crate::run_filter::{RunFilter, TagFilter};
+use crate::types::{Run, Tag};
 
 /// A TensorBoard log directory, with event files organized into runs.
 pub trait Logdir {
@@ -33,7 +50,7 @@ pub trait Logdir {
     /// Finds all event files under the log directory.
     ///
     /// Event files within each run should be emitted in chronological order. Canonically, a file
-    /// is an event file if its basename contains [`EVENT_FILE_BASENAME_INFIX`] as a substring.
+    /// is an event file if [`is_event_file_name`] accepts its basename.
     ///
     /// A `Run` whose corresponding `Vec<EventFileBuf>` is empty is interpreted as if the run were
     /// absent.
@@ -43,6 +60,81 @@ pub trait Logdir {
     ///
     /// The `path` should be one of the values returned by a previous call to [`Self::discover`].
     fn open(&self, path: &EventFileBuf) -> io::Result<Self::File>;
+
+    /// Describes the current state of a single run's event files, for [`Self::watch_run`].
+    ///
+    /// The default implementation lists the run's files via [`Self::discover`], so it sees files
+    /// added or removed but not data appended to existing files. Implementations that can cheaply
+    /// find file sizes or modification times should include them.
+    fn run_version(&self, run: &Run) -> io::Result<RunVersion> {
+        let mut files = self.discover()?.remove(run).unwrap_or_default();
+        files.sort();
+        Ok(RunVersion(
+            files.into_iter().map(|f| (f, None, None)).collect(),
+        ))
+    }
+
+    /// Watches a single run for changes to its event files, calling `on_change` from a background
+    /// thread after each change until the returned watcher is dropped. This lets a server reload
+    /// one run on demand rather than polling the whole log directory.
+    ///
+    /// Changes are found by comparing [`Self::run_version`] every `poll_interval`, so a change
+    /// may take that long to be noticed, and several changes within one interval are reported
+    /// once. A run that can't be read counts as changed when it becomes readable again, and vice
+    /// versa.
+    fn watch_run(
+        self: Arc<Self>,
+        run: Run,
+        poll_interval: Duration,
+        mut on_change: impl FnMut() + Send + 'static,
+    ) -> RunWatcher
+    where
+        Self: Sized + Send + Sync + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let mut last = self.run_version(&run).ok();
+        let thread = thread::Builder::new()
+            .name(format!("RunWatcher({})", run.0))
+            .spawn(move || loop {
+                thread::park_timeout(poll_interval);
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                let version = self.run_version(&run).ok();
+                if version != last {
+                    last = version;
+                    on_change();
+                }
+            })
+            .expect("failed to spawn run watcher thread");
+        RunWatcher {
+            stop,
+            thread: thread.thread().clone(),
+        }
+    }
+}
+
+/// The state of a run's event files at some point in time, as seen by [`Logdir::run_version`].
+///
+/// Holds the path of each event file in sorted order, with its size and last modification time
+/// if known. Two versions differ if the files changed in any way that the log directory can see.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunVersion(pub Vec<(EventFileBuf, Option<u64>, Option<SystemTime>)>);
+
+/// Handle to a watch started by [`Logdir::watch_run`]. The watch stops when this is dropped,
+/// though a change noticed just before then may still be reported.
+#[derive(Debug)]
+pub struct RunWatcher {
+    stop: Arc<AtomicBool>,
+    thread: Thread,
+}
+
+impl Drop for RunWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread.unpark();
+    }
 }
 
 /// An opaque reference to an event file within the context of a specific log directory.
@@ -55,9 +147,28 @@ pub trait Logdir {
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub struct EventFileBuf(pub PathBuf);
 
+/// A test on run names. See [`LogdirLoader::checksum_runs`].
+type RunPredicate = dyn Fn(&Run) -> bool + Send + Sync;
+
 /// A file is treated as an event file if its basename contains this substring.
 pub const EVENT_FILE_BASENAME_INFIX: &str = "tfevents";
 
+/// Files whose names end with one of these suffixes are sentinels that TensorFlow drops next to
+/// event files, like the profiler's `events.out.tfevents.*.profile-empty`. They hold no data.
+pub const SENTINEL_FILE_SUFFIXES: &[&str] = &[".profile-empty"];
+
+/// Tests whether a file with the given basename is an event file: that is, whether it contains
+/// [`EVENT_FILE_BASENAME_INFIX`] and isn't a known sentinel.
+pub fn is_event_file_name(basename: &str) -> bool {
+    basename.contains(EVENT_FILE_BASENAME_INFIX)
+        && !SENTINEL_FILE_SUFFIXES
+            .iter()
+            .any(|suffix| basename.ends_with(suffix))
+}
+
+/// Event files whose names end with this suffix are gzip-compressed.
+pub const GZIP_EVENT_FILE_SUFFIX: &str = ".gz";
+
 /// A loader for a [`Logdir`], connecting a filesystem to a [`Commit`] via [`RunLoader`]s.
 ///
 /// A `LogdirLoader` is a stateful object. Its [`reload`][Self::reload] method polls the underlying
@@ -68,17 +179,320 @@ pub struct LogdirLoader<'a, L: Logdir> {
     /// Thread pool used for loading runs in parallel.
     thread_pool: rayon::ThreadPool,
     /// Shared reference to a commit, updated by [`Self::reload`].
-    commit: &'a Commit,
+    commit: CommitRef<'a>,
     /// Log directory providing the source of truth for event file data.
     logdir: L,
+    /// Which discovered runs to load (see [`Self::run_filter`]).
+    run_filter: RunFilter,
     /// Stateful run loaders for all known runs.
     runs: HashMap<Run, RunLoader<<L as Logdir>::File>>,
     /// Whether new run loaders should unconditionally verify CRCs (see [`RunLoader::checksum`]).
     checksum: bool,
+    /// Which new run loaders should verify CRCs, overriding `checksum` if set (see
+    /// [`Self::checksum_runs`]).
+    checksum_runs: Option<Box<RunPredicate>>,
+    /// Whether new run loaders should skip records with bad CRCs (see
+    /// [`RunLoader::skip_bad_checksums`]).
+    skip_bad_checksums: bool,
+    /// Maximum record length for new run loaders (see [`RunLoader::max_record_len`]).
+    max_record_len: Option<u64>,
+    /// Read retries for new run loaders (see [`RunLoader::read_retries`]).
+    read_retries: u32,
+    /// How new run loaders should handle zero wall times (see [`RunLoader::zero_wall_time`]).
+    zero_wall_time: ZeroWallTime,
+    /// How new run loaders should handle negative steps (see [`RunLoader::negative_steps`]).
+    negative_steps: NegativeStepPolicy,
+    /// Largest step that new run loaders accept (see [`RunLoader::max_step`]).
+    max_step: Option<i64>,
+    /// Step regressions that new run loaders take as out-of-order delivery (see
+    /// [`RunLoader::out_of_order_tolerance`]).
+    out_of_order_tolerance: u64,
+    /// How new run loaders should handle repeated tags within an event (see
+    /// [`RunLoader::duplicate_tags`]).
+    duplicate_tags: DuplicateTagPolicy,
+    /// How new run loaders should resolve points at the same step (see
+    /// [`RunLoader::duplicate_steps`]).
+    duplicate_steps: DuplicateStepPolicy,
+    /// Precision in which new run loaders commit scalars (see [`RunLoader::scalar_precision`]).
+    scalar_precision: ScalarPrecision,
+    /// Where new run loaders keep run metadata tags (see [`RunLoader::run_metadata_tags`]).
+    run_metadata_tags: RunMetadataTags,
+    /// Whether new run loaders fail on the first load error (see [`RunLoader::strict`]).
+    strict: bool,
+    /// Reservoir capacities for new run loaders (see [`RunLoader::sample_sizes`]).
+    sample_sizes: SampleSizes,
+    /// Tag limit for new run loaders (see [`RunLoader::max_tags`]).
+    max_tags: usize,
+    /// Plugin content limit for new run loaders (see [`RunLoader::max_plugin_content_len`]).
+    max_plugin_content_len: Option<usize>,
+    /// Whether new run loaders take the scalar fast path (see [`RunLoader::scalar_fast_path`]).
+    scalar_fast_path: bool,
+    /// Wall time offsets for new run loaders, by run (see [`RunLoader::wall_time_offset`]).
+    wall_time_offsets: HashMap<Run, f64>,
+    /// Limit on time series across all runs (see [`Self::max_total_tags`]).
+    tag_budget: Option<Arc<TagBudget>>,
+    /// Whether we've warned that `tag_budget` is used up.
+    warned_tag_budget: bool,
+    /// Whether new run loaders record provenance (see [`RunLoader::provenance`]).
+    provenance: bool,
+    /// Whether new run loaders commit blob sequences by reference (see
+    /// [`RunLoader::blob_references`]).
+    blob_references: bool,
+    /// How new run loaders' event files are framed (see [`RunLoader::framing`]).
+    framing: Framing,
+
+    /// Whether new run loaders read only their first event file (see
+    /// [`RunLoader::preview_first_file`]).
+    preview_first_file: bool,
+    /// Order in which new run loaders read event files (see [`RunLoader::file_order`]).
+    file_order: EventFileOrder,
+    /// Maximum number of event files that new run loaders read (see [`RunLoader::max_files`]).
+    max_files: Option<usize>,
+    /// What new run loaders do with files over that limit (see [`RunLoader::expired_files`]).
+    expired_files: ExpiredFilePolicy,
+    /// Corrections to time series metadata in new run loaders (see
+    /// [`RunLoader::metadata_overrides`]).
+    metadata_overrides: MetadataOverrides,
+    /// Which tags run loaders stage (see [`Self::tag_filter`]).
+    tag_filter: TagFilter,
+    /// How to poll runs that have stopped producing data, or `None` to poll every run on every
+    /// load cycle. See [`Self::poll_backoff`].
+    poll_backoff: Option<PollBackoff>,
+    /// Polling schedule for each run in `self.runs` that has been loaded at least once.
+    poll_states: HashMap<Run, PollState>,
+    /// Order in which to load runs on the first load cycle (see [`Self::cold_start_order`]).
+    cold_start_order: ColdStartOrder,
+    /// Whether a load cycle has loaded any runs yet.
+    warm: bool,
+    /// Read-only switch shared with other loaders, if any (see [`Self::global_read_only`]).
+    global_read_only: Option<ReadOnlySwitch>,
+    /// Whether the last load cycle was skipped for read-only mode.
+    was_read_only: bool,
+    /// Registry of pushed runs to leave alone, if any (see [`Self::run_sources`]).
+    run_sources: Option<Arc<RunSources>>,
+    /// Age limits for points in each run (see [`Self::retention`]).
+    retention: RetentionPolicy,
+}
+
+/// Policy for polling idle runs less often. See [`LogdirLoader::poll_backoff`].
+///
+/// A run is idle once `idle_reloads` consecutive polls have read no new events. From then on,
+/// each further empty poll doubles the number of load cycles until the run is next polled, up to
+/// `max_interval` cycles. As soon as a poll reads new events, the run is polled on every cycle
+/// again.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PollBackoff {
+    /// Number of consecutive empty polls after which a run is considered idle.
+    pub idle_reloads: u32,
+    /// Maximum number of load cycles between polls of an idle run.
+    pub max_interval: u32,
+}
+
+/// How long to keep points in each run, by wall time. See [`LogdirLoader::retention`] and
+/// [`RunLoader::retention`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Horizon for runs that match none of `rules`, if any.
+    pub default: Option<Duration>,
+    /// Horizons for runs that pass each filter. The first matching rule wins.
+    pub rules: Vec<(RunFilter, Duration)>,
+}
+
+impl RetentionPolicy {
+    /// Gets the retention horizon for the given run, or `None` to keep all its points.
+    pub fn horizon(&self, run: &Run) -> Option<Duration> {
+        self.rules
+            .iter()
+            .find(|(filter, _)| filter.matches(run))
+            .map(|(_, horizon)| *horizon)
+            .or(self.default)
+    }
+}
+
+/// Options for how run loaders handle events, whatever their source. A [`LogdirLoader`] gives
+/// them to each new run loader, and shares them with its [`RunSources`] so that pushed runs are
+/// loaded the same way as runs read from files. Options about reading files aren't included.
+#[derive(Debug, Clone)]
+pub(crate) struct RunOptions {
+    zero_wall_time: ZeroWallTime,
+    negative_steps: NegativeStepPolicy,
+    max_step: Option<i64>,
+    out_of_order_tolerance: u64,
+    duplicate_tags: DuplicateTagPolicy,
+    duplicate_steps: DuplicateStepPolicy,
+    scalar_precision: ScalarPrecision,
+    run_metadata_tags: RunMetadataTags,
+    strict: bool,
+    sample_sizes: SampleSizes,
+    max_tags: usize,
+    max_plugin_content_len: Option<usize>,
+    scalar_fast_path: bool,
+    wall_time_offsets: HashMap<Run, f64>,
+    retention: RetentionPolicy,
+    tag_budget: Option<Arc<TagBudget>>,
+    metadata_overrides: MetadataOverrides,
+    tag_filter: TagFilter,
+}
+
+impl RunOptions {
+    /// Applies these options to a new loader for `run`, and wires it to `commit`.
+    pub(crate) fn apply<R: Read>(&self, loader: &mut RunLoader<R>, run: &Run, commit: &Commit) {
+        loader.zero_wall_time(self.zero_wall_time);
+        loader.negative_steps(self.negative_steps);
+        loader.max_step(self.max_step);
+        loader.out_of_order_tolerance(self.out_of_order_tolerance);
+        loader.duplicate_tags(self.duplicate_tags);
+        loader.duplicate_steps(self.duplicate_steps);
+        loader.scalar_precision(self.scalar_precision);
+        loader.run_metadata_tags(self.run_metadata_tags);
+        loader.strict(self.strict);
+        loader.sample_sizes(self.sample_sizes);
+        loader.max_tags(self.max_tags);
+        loader.max_plugin_content_len(self.max_plugin_content_len);
+        loader.scalar_fast_path(self.scalar_fast_path);
+        loader.retention(self.retention.horizon(run));
+        if let Some(&offset) = self.wall_time_offsets.get(run) {
+            loader.wall_time_offset(offset);
+        }
+        loader.tag_budget(self.tag_budget.clone());
+        loader.notifier(Some(commit.notifier()));
+        loader.counters(Some(commit.counters()));
+        loader.load_shedding(Some(commit.load_shedding()));
+        loader.metadata_overrides(self.metadata_overrides.clone());
+        loader.tag_filter(self.tag_filter.clone());
+    }
+}
+
+/// Polling schedule for a single run under a [`PollBackoff`].
+#[derive(Debug, Default)]
+struct PollState {
+    /// Number of consecutive polls that have read no events.
+    empty_polls: u32,
+    /// Number of load cycles from one poll to the next. `0` and `1` both mean every cycle.
+    interval: u32,
+    /// Number of load cycles to skip before the next poll.
+    skip: u32,
+}
+
+impl PollState {
+    /// Updates the schedule after a poll that read `events` events.
+    fn record_poll(&mut self, events: usize, backoff: &PollBackoff) {
+        if events > 0 {
+            *self = PollState::default();
+            return;
+        }
+        self.empty_polls = self.empty_polls.saturating_add(1);
+        if self.empty_polls >= backoff.idle_reloads {
+            self.interval = self
+                .interval
+                .max(1)
+                .saturating_mul(2)
+                .min(backoff.max_interval);
+            self.skip = self.interval.saturating_sub(1);
+        }
+    }
+}
+
+/// Order in which to load runs on a [`LogdirLoader`]'s first load cycle. See
+/// [`LogdirLoader::cold_start_order`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColdStartOrder {
+    /// By run name. This is the default.
+    Alphabetical,
+    /// By total size of the run's event files, smallest first, so that many small runs become
+    /// visible before a few large ones finish loading.
+    SmallestFirst,
+    /// By the latest modification time of the run's event files, newest first.
+    MostRecentFirst,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for ColdStartOrder {
+    fn default() -> Self {
+        ColdStartOrder::Alphabetical
+    }
+}
+
+impl FromStr for ColdStartOrder {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alphabetical" => Ok(ColdStartOrder::Alphabetical),
+            "smallest-first" => Ok(ColdStartOrder::SmallestFirst),
+            "most-recent-first" => Ok(ColdStartOrder::MostRecentFirst),
+            _ => Err(format!(
+                "unknown cold start order {:?}; expected \"alphabetical\", \"smallest-first\", \
+                 or \"most-recent-first\"",
+                s
+            )),
+        }
+    }
+}
+
+impl ColdStartOrder {
+    /// Sorts `items` into this order by their runs, as given by `run_of`, reading file sizes and
+    /// modification times from [`Logdir::run_version`]. Runs whose sizes or times can't be found
+    /// go last, and ties go by name.
+    fn sort<T>(self, logdir: &impl Logdir, items: &mut [T], run_of: impl Fn(&T) -> &Run) {
+        match self {
+            ColdStartOrder::Alphabetical => items.sort_by(|a, b| run_of(a).cmp(run_of(b))),
+            ColdStartOrder::SmallestFirst => items.sort_by_cached_key(|item| {
+                let run = run_of(item);
+                let size: Option<u64> = logdir
+                    .run_version(run)
+                    .ok()
+                    .and_then(|version| version.0.iter().map(|&(_, size, _)| size).sum());
+                (size.is_none(), size, run.clone())
+            }),
+            ColdStartOrder::MostRecentFirst => items.sort_by_cached_key(|item| {
+                let run = run_of(item);
+                let mtime = logdir
+                    .run_version(run)
+                    .ok()
+                    .and_then(|version| version.0.iter().filter_map(|&(_, _, t)| t).max());
+                (mtime.is_none(), Reverse(mtime), run.clone())
+            }),
+        }
+    }
+}
+
+/// A commit that a [`LogdirLoader`] either borrows or owns a share of.
+enum CommitRef<'a> {
+    Borrowed(&'a Commit),
+    Shared(Arc<Commit>),
+}
+
+impl Deref for CommitRef<'_> {
+    type Target = Commit;
+    fn deref(&self) -> &Commit {
+        match self {
+            CommitRef::Borrowed(commit) => commit,
+            CommitRef::Shared(commit) => commit,
+        }
+    }
 }
 
 type Discoveries = HashMap<Run, Vec<EventFileBuf>>;
 
+/// A run to load in [`LogdirLoader::load_runs`], with its loader, files, and data.
+type WorkItem<'a, L> = (
+    &'a Run,
+    &'a mut RunLoader<<L as Logdir>::File>,
+    Vec<EventFileBuf>,
+    &'a RwLock<RunData>,
+);
+
+/// Reloads a single run for [`LogdirLoader::load_runs`]. Returns the run, whether it's paused,
+/// and the result of the reload.
+fn load_run<'a, L: Logdir>(
+    logdir: &L,
+    (run, loader, filenames, run_data): WorkItem<'a, L>,
+) -> (&'a Run, bool, Result<usize, LoadError>) {
+    let events = loader.try_reload(logdir, filenames, run_data);
+    (run, loader.is_paused(), events)
+}
+
 impl<'a, L: Logdir> LogdirLoader<'a, L>
 where
     L: Sync,
@@ -87,14 +501,30 @@ where
     /// Creates a new, empty logdir loader. Does not load any data.
     ///
     /// This constructor is heavyweight: it builds a new thread-pool. The thread pool will be
-    /// reused for all calls to [`Self::reload`]. If `reload_threads` is `0`, the number of threads
-    /// will be determined automatically, per [`rayon`] semantics.
+    /// reused for all calls to [`Self::reload`], and shared by all runs, so at most
+    /// `reload_threads` runs are loaded at once. If `reload_threads` is `0`, the number of threads
+    /// will be determined automatically, per [`rayon`] semantics. The threads exit when the loader
+    /// is dropped.
     ///
     /// # Panics
     ///
     /// If [`rayon::ThreadPoolBuilder::build`] returns an error; should only happen if there is a
     /// failure to create threads at the OS level.
     pub fn new(commit: &'a Commit, logdir: L, reload_threads: usize) -> Self {
+        Self::with_commit(CommitRef::Borrowed(commit), logdir, reload_threads)
+    }
+
+    /// Creates a new, empty logdir loader that keeps a share of `commit` rather than borrowing
+    /// it, so that the loader can be moved to another thread. Otherwise the same as [`Self::new`].
+    pub fn shared(
+        commit: Arc<Commit>,
+        logdir: L,
+        reload_threads: usize,
+    ) -> LogdirLoader<'static, L> {
+        LogdirLoader::with_commit(CommitRef::Shared(commit), logdir, reload_threads)
+    }
+
+    fn with_commit(commit: CommitRef<'a>, logdir: L, reload_threads: usize) -> Self {
         let thread_pool = rayon::ThreadPoolBuilder::new()
             .num_threads(reload_threads)
             .thread_name(|i| format!("Reloader-{:03}", i))
@@ -104,93 +534,576 @@ where
             thread_pool,
             commit,
             logdir,
+            run_filter: RunFilter::default(),
             runs: HashMap::new(),
             checksum: true,
+            checksum_runs: None,
+            skip_bad_checksums: false,
+            max_record_len: None,
+            read_retries: DEFAULT_READ_RETRIES,
+            zero_wall_time: ZeroWallTime::default(),
+            negative_steps: NegativeStepPolicy::default(),
+            max_step: None,
+            out_of_order_tolerance: 0,
+            duplicate_tags: DuplicateTagPolicy::default(),
+            duplicate_steps: DuplicateStepPolicy::default(),
+            scalar_precision: ScalarPrecision::default(),
+            run_metadata_tags: RunMetadataTags::default(),
+            strict: false,
+            sample_sizes: SampleSizes::default(),
+            max_tags: DEFAULT_MAX_TAGS,
+            max_plugin_content_len: Some(DEFAULT_MAX_PLUGIN_CONTENT_LEN),
+            scalar_fast_path: true,
+            wall_time_offsets: HashMap::new(),
+            tag_budget: None,
+            warned_tag_budget: false,
+            provenance: false,
+            blob_references: false,
+            framing: Framing::default(),
+            preview_first_file: false,
+            file_order: EventFileOrder::default(),
+            max_files: None,
+            expired_files: ExpiredFilePolicy::default(),
+            metadata_overrides: MetadataOverrides::default(),
+            tag_filter: TagFilter::default(),
+            poll_backoff: None,
+            poll_states: HashMap::new(),
+            cold_start_order: ColdStartOrder::default(),
+            warm: false,
+            global_read_only: None,
+            was_read_only: false,
+            run_sources: None,
+            retention: RetentionPolicy::default(),
         }
     }
 
-    /// Sets whether to compute checksums for records before parsing them as protos.
+    /// Sets which runs to load, by name. Runs that don't pass the filter are ignored as if they
+    /// had no event files: their files are never opened, and they don't appear in the commit.
+    ///
+    /// Takes effect on the next load cycle, which loads any newly included runs from the start
+    /// and removes any newly excluded runs from the commit.
+    pub fn run_filter(&mut self, filter: RunFilter) {
+        self.run_filter = filter;
+    }
+
+    /// Sets whether to compute checksums for records before parsing them as protos. Replaces any
+    /// predicate set with [`Self::checksum_runs`].
     pub fn checksum(&mut self, yes: bool) {
         self.checksum = yes;
+        self.checksum_runs = None;
+    }
+
+    /// Sets which runs to compute checksums for, as with [`Self::checksum`] but decided per run,
+    /// so that runs from trusted sources can skip the cost while others are verified. The
+    /// predicate is called once for each run, when the run is discovered.
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn checksum_runs(&mut self, predicate: impl Fn(&Run) -> bool + Send + Sync + 'static) {
+        self.checksum_runs = Some(Box::new(predicate));
+    }
+
+    /// Sets whether to skip records with bad checksums rather than abandoning their event files.
+    /// See [`RunLoader::skip_bad_checksums`].
+    pub fn skip_bad_checksums(&mut self, yes: bool) {
+        self.skip_bad_checksums = yes;
+    }
+
+    /// Sets the maximum data length of a record, in bytes. See [`RunLoader::max_record_len`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn max_record_len(&mut self, bytes: Option<u64>) {
+        self.max_record_len = bytes;
+    }
+
+    /// Sets how many times to retry transient read errors. See [`RunLoader::read_retries`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn read_retries(&mut self, retries: u32) {
+        self.read_retries = retries;
+    }
+
+    /// Sets how to handle events whose wall time is at the epoch. See [`ZeroWallTime`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn zero_wall_time(&mut self, policy: ZeroWallTime) {
+        self.zero_wall_time = policy;
+    }
+
+    /// Sets how to handle events whose step is negative. See [`NegativeStepPolicy`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn negative_steps(&mut self, policy: NegativeStepPolicy) {
+        self.negative_steps = policy;
+    }
+
+    /// Sets the largest step to accept. See [`RunLoader::max_step`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn max_step(&mut self, max_step: Option<i64>) {
+        self.max_step = max_step;
+    }
+
+    /// Sets how far steps may go backward and still be taken as out-of-order delivery. See
+    /// [`RunLoader::out_of_order_tolerance`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn out_of_order_tolerance(&mut self, steps: u64) {
+        self.out_of_order_tolerance = steps;
+    }
+
+    /// Sets how to handle repeated tags within an event. See [`DuplicateTagPolicy`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn duplicate_tags(&mut self, policy: DuplicateTagPolicy) {
+        self.duplicate_tags = policy;
+    }
+
+    /// Sets how to resolve points at the same step. See [`DuplicateStepPolicy`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn duplicate_steps(&mut self, policy: DuplicateStepPolicy) {
+        self.duplicate_steps = policy;
+    }
+
+    /// Sets the precision in which to commit scalar values. See [`ScalarPrecision`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn scalar_precision(&mut self, precision: ScalarPrecision) {
+        self.scalar_precision = precision;
+    }
+
+    /// Sets where to keep run metadata tags. See [`RunMetadataTags`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn run_metadata_tags(&mut self, policy: RunMetadataTags) {
+        self.run_metadata_tags = policy;
+    }
+
+    /// Sets whether to fail on the first load error. See [`RunLoader::strict`] and
+    /// [`Self::try_reload`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn strict(&mut self, yes: bool) {
+        self.strict = yes;
+    }
+
+    /// Sets how many points to keep in each time series. See [`RunLoader::sample_sizes`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn sample_sizes(&mut self, sizes: SampleSizes) {
+        self.sample_sizes = sizes;
+    }
+
+    /// Sets the maximum number of distinct tags per run. See [`RunLoader::max_tags`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn max_tags(&mut self, max_tags: usize) {
+        self.max_tags = max_tags;
+    }
+
+    /// Sets the longest plugin content to keep, in bytes. See
+    /// [`RunLoader::max_plugin_content_len`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn max_plugin_content_len(&mut self, len: Option<usize>) {
+        self.max_plugin_content_len = len;
+    }
+
+    /// Sets whether to stage scalar-only summaries on a fast path. See
+    /// [`RunLoader::scalar_fast_path`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn scalar_fast_path(&mut self, enabled: bool) {
+        self.scalar_fast_path = enabled;
+    }
+
+    /// Sets the number of seconds to add to wall times in each given run. Runs not in the map
+    /// are not shifted. See [`RunLoader::wall_time_offset`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn wall_time_offsets(&mut self, offsets: HashMap<Run, f64>) {
+        self.wall_time_offsets = offsets;
+    }
+
+    /// Sets a limit on the total number of time series across all runs, or removes it if `None`.
+    ///
+    /// Once the limit is reached, no run starts any new time series: data for new tags is dropped
+    /// and counted per run (see [`RunLoader::tag_overflow`]), and a warning is logged. Existing
+    /// time series keep updating. This bounds memory use when a log directory with many runs has
+    /// a runaway number of tags, which per-run limits (see [`Self::max_tags`]) can't do alone.
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn max_total_tags(&mut self, limit: Option<usize>) {
+        self.tag_budget = limit.map(|n| Arc::new(TagBudget::new(n)));
+        self.warned_tag_budget = false;
+    }
+
+    /// Sets whether to record which event file each point came from. See
+    /// [`RunLoader::provenance`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn provenance(&mut self, enabled: bool) {
+        self.provenance = enabled;
+    }
+
+    /// Sets whether to commit blob sequences as references into their event files rather than
+    /// holding their blobs in memory. See [`RunLoader::blob_references`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn blob_references(&mut self, enabled: bool) {
+        self.blob_references = enabled;
+    }
+
+    /// Sets how records are delimited in event files. See [`RunLoader::framing`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn framing(&mut self, framing: Framing) {
+        self.framing = framing;
+    }
+
+    /// Sets whether to read only the first event file of each run, for a quick but partial
+    /// preview. See [`RunLoader::preview_first_file`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn preview_first_file(&mut self, yes: bool) {
+        self.preview_first_file = yes;
+    }
+
+    /// Sets the order in which to read each run's event files. See [`EventFileOrder`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn file_order(&mut self, order: EventFileOrder) {
+        self.file_order = order;
+    }
+
+    /// Sets the maximum number of event files to read in each run, or removes the limit if
+    /// `None`, and what to do with the oldest files over it. See [`RunLoader::max_files`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn max_files(&mut self, limit: Option<usize>, policy: ExpiredFilePolicy) {
+        self.max_files = limit;
+        self.expired_files = policy;
+    }
+
+    /// Sets corrections to the metadata of time series in every run, by tag. See
+    /// [`RunLoader::metadata_overrides`].
+    ///
+    /// Applies only to runs discovered after this call.
+    pub fn metadata_overrides(&mut self, overrides: MetadataOverrides) {
+        self.metadata_overrides = overrides;
+    }
+
+    /// Sets which tags to load in every run. See [`RunLoader::tag_filter`].
+    ///
+    /// Unlike most options, this applies to existing runs as well as new ones. Existing time
+    /// series are left alone even if their tags no longer pass.
+    pub fn tag_filter(&mut self, filter: TagFilter) {
+        for loader in self.runs.values_mut() {
+            loader.tag_filter(filter.clone());
+        }
+        self.tag_filter = filter;
+    }
+
+    /// Sets how to poll runs that have stopped producing data. See [`PollBackoff`].
+    ///
+    /// With `None` (the default), every run is polled on every load cycle. A run whose data has
+    /// been evicted to fit the memory budget is always polled on the next cycle, so that its data
+    /// is restored promptly.
+    pub fn poll_backoff(&mut self, backoff: Option<PollBackoff>) {
+        self.poll_backoff = backoff;
+        if backoff.is_none() {
+            self.poll_states.clear();
+        }
+    }
+
+    /// Sets the order in which to load runs on the first load cycle. See [`ColdStartOrder`].
+    ///
+    /// Runs are handed to reload threads in this order, so with many runs, those early in the
+    /// order become visible first. Later cycles load runs in no particular order. Has no effect
+    /// once a load cycle has loaded any runs.
+    pub fn cold_start_order(&mut self, order: ColdStartOrder) {
+        self.cold_start_order = order;
+    }
+
+    /// Sets a read-only switch to obey in addition to the commit's own [`Commit::read_only`]
+    /// switch. Give the same switch to several loaders to freeze them all at once.
+    pub fn global_read_only(&mut self, switch: Option<ReadOnlySwitch>) {
+        self.global_read_only = switch;
+    }
+
+    /// Sets how long to keep points in each run, by wall time. See [`RunLoader::retention`].
+    /// Applies only to runs discovered after this call.
+    pub fn retention(&mut self, policy: RetentionPolicy) {
+        self.retention = policy;
+    }
+
+    /// Sets a registry of runs that are pushed over gRPC instead of read from files. Discovered
+    /// runs with the same names as pushed runs are skipped, with a warning. Applies only to runs
+    /// discovered after this call.
+    ///
+    /// Pushed runs are loaded with this loader's options for handling events, like its sample
+    /// sizes, tag filter, and retention policy, as of this call and then as of the latest load
+    /// cycle. So call this after setting those options.
+    pub fn run_sources(&mut self, sources: Option<Arc<RunSources>>) {
+        self.run_sources = sources;
+        self.share_run_options();
+    }
+
+    /// Checks whether this loader is read-only: that is, whether either the commit's switch or
+    /// the [global switch](Self::global_read_only) is on.
+    pub fn is_read_only(&self) -> bool {
+        self.commit.read_only().get() || matches!(&self.global_read_only, Some(s) if s.get())
+    }
+
+    /// Gets the number of load cycles between polls of the given run: `1` for a run that is polled
+    /// on every cycle, or more for an idle run under [`Self::poll_backoff`]. Returns `None` if the
+    /// run is not known to this loader.
+    pub fn poll_interval(&self, run: &Run) -> Option<u32> {
+        if !self.runs.contains_key(run) {
+            return None;
+        }
+        let interval = self.poll_states.get(run).map_or(1, |st| st.interval);
+        Some(interval.max(1))
+    }
+
+    /// Gets how long it has been since a reload last read a record from any of the given run's
+    /// event files, for alerting on runs whose writers have stalled. See
+    /// [`RunLoader::last_new_data`]. Returns `None` if the run is not known to this loader or no
+    /// record has been read from it yet.
+    pub fn time_since_new_data(&self, run: &Run) -> Option<Duration> {
+        let last = self.runs.get(run)?.last_new_data()?;
+        Some(SystemTime::now().duration_since(last).unwrap_or_default())
+    }
+
+    /// Removes all data for `tag` from the given run and keeps it from being loaded again. See
+    /// [`RunLoader::drop_tag`]. Returns whether the run had any data for the tag, or `false` if
+    /// the run is not known to this loader.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `commit.runs` lock or the run's data is poisoned.
+    pub fn drop_tag(&mut self, run: &Run, tag: &Tag) -> bool {
+        let loader = match self.runs.get_mut(run) {
+            Some(loader) => loader,
+            None => return false,
+        };
+        let runs = self
+            .commit
+            .runs
+            .read()
+            .expect("failed to read-lock runs map");
+        match runs.get(run) {
+            Some(run_data) => loader.drop_tag(tag, run_data),
+            None => false,
+        }
     }
 
     /// Performs a complete load cycle: finds all event files and reads data from all runs,
-    /// updating the shared commit.
+    /// updating the shared commit, then evicts data as needed to fit the commit's memory budget.
+    /// Returns the total number of events read.
     ///
     /// If any of the commit locks is poisoned, or if a run is removed from the commit by another
-    /// client while this reload is in progress (should not happen if the commit is only being
-    /// updated by a single `LogdirLoader`).
-    pub fn reload(&mut self) {
+    /// client other than through [`Commit::remove_run`] while this reload is in progress (should
+    /// not happen if the commit is only being updated by a single `LogdirLoader`).
+    ///
+    /// While [read-only](Self::is_read_only), does nothing and returns `0`.
+    ///
+    /// In [strict mode](Self::strict), logs any error that aborted a run's load; use
+    /// [`Self::try_reload`] to handle it instead.
+    pub fn reload(&mut self) -> usize {
+        self.try_reload().unwrap_or_else(|e| {
+            error!("Aborted load cycle: {}", e);
+            0
+        })
+    }
+
+    /// Like [`Self::reload`], but returns the error that aborted a run's load in
+    /// [strict mode](Self::strict). Never fails otherwise.
+    ///
+    /// Runs load in parallel, so other runs still finish loading in the failed cycle. If more than
+    /// one run fails, returns the error for the first by name. Memory budget enforcement is
+    /// skipped when a load fails.
+    pub fn try_reload(&mut self) -> Result<usize, LoadError> {
+        let read_only = self.is_read_only();
+        if read_only != self.was_read_only {
+            self.was_read_only = read_only;
+            if read_only {
+                info!("Log directory is read-only; skipping load cycles");
+            } else {
+                info!("Log directory is no longer read-only; resuming load cycles");
+            }
+        }
+        if read_only {
+            return Ok(0);
+        }
+        self.share_run_options();
+        // Read before discovery, so that runs removed since can't be added back.
+        let generation = self.commit.generation();
         let discoveries = self.discover();
-        self.synchronize_runs(&discoveries);
-        self.load_runs(discoveries);
+        self.synchronize_runs(&discoveries, generation);
+        let events = self.load_runs(discoveries)?;
+        if let Some(budget) = &self.tag_budget {
+            if budget.rejected() > 0 && !self.warned_tag_budget {
+                self.warned_tag_budget = true;
+                warn!(
+                    "Log directory has reached its limit of {} time series across all runs; \
+                     data for new tags is being dropped. Existing time series still update.",
+                    budget.limit()
+                );
+            }
+        }
+        for run in self.commit.enforce_memory_budget() {
+            log_kv!(Level::Info, run = run.0; "Evicted blob sequences to fit memory budget");
+        }
+        Ok(events)
     }
 
-    /// Finds all event files under the log directory and groups them by run.
+    /// Finds all event files under the log directory and groups them by run, omitting runs that
+    /// don't pass the run filter.
     fn discover(&self) -> Discoveries {
-        self.logdir.discover().unwrap_or_else(|e| {
+        let mut discoveries = self.logdir.discover().unwrap_or_else(|e| {
             warn!("While loading log directory: {}", e);
             Default::default()
-        })
+        });
+        if !self.run_filter.is_empty() {
+            let filter = &self.run_filter;
+            discoveries.retain(|run, _| filter.matches(run));
+        }
+        discoveries
     }
 
     /// Updates `self.runs` by adding new runs and removing runs all of whose event files have been
     /// deleted, and updates `commit.runs` to have the same keyset as `self.runs`.
     ///
+    /// Runs that another client removed from the commit are dropped too, and runs removed after
+    /// the given [generation](Commit::generation) aren't added, even if discovered.
+    ///
     /// # Panics
     ///
     /// Panics if the `commit.runs` lock is poisoned.
-    fn synchronize_runs(&mut self, discoveries: &Discoveries) {
+    fn synchronize_runs(&mut self, discoveries: &Discoveries, generation: u64) {
+        let commit = &self.commit;
+        let mut runs_store = commit.runs.write().expect("failed to write-lock runs map");
         // Remove runs with no event files. (This could be cleaner and more efficient with
         // `HashMap::drain_filter`, but that's not yet stabilized.)
         let mut removed: Vec<Run> = Vec::new();
+        let sources = &self.run_sources;
         self.runs.retain(|run, _| {
-            if discoveries.get(run).map_or(false, |fs| !fs.is_empty()) {
+            if !runs_store.contains_key(run) {
+                // Removed by another client; start over if it's added back.
+                if let Some(sources) = sources {
+                    sources.release_files(run);
+                }
+                false
+            } else if discoveries.get(run).map_or(false, |fs| !fs.is_empty()) {
                 true
             } else {
                 removed.push(run.clone());
                 false
             }
         });
+        let runs = &self.runs;
+        self.poll_states.retain(|run, _| runs.contains_key(run));
         // Determine which runs need to be added (we'll add them later).
-        let added: Vec<&Run> = discoveries
+        let mut added: Vec<&Run> = discoveries
             .keys()
-            .filter(|k| !self.runs.contains_key(*k))
+            .filter(|k| !self.runs.contains_key(*k) && !commit.removed_since(k, generation))
             .collect();
+        if let Some(sources) = &self.run_sources {
+            added.retain(|run| {
+                let claimed = sources.claim_files(run);
+                if !claimed {
+                    warn_limited!(key = run; "Ignoring event files for pushed run {:?}", run.0);
+                }
+                claimed
+            });
+        }
 
         // Synchronize to the commit.
-        if !removed.is_empty() || !added.is_empty() {
-            let mut runs_store = self
-                .commit
-                .runs
-                .write()
-                .expect("failed to write-lock runs map");
-            for run in &removed {
-                runs_store.remove(run);
+        let counters = commit.counters();
+        for run in removed {
+            if let Some(sources) = &self.run_sources {
+                sources.release_files(&run);
             }
-            for run in added {
-                runs_store.insert(run.clone(), Default::default());
+            if let Some(run_data) = runs_store.remove(&run) {
+                counters.uncount(&run_data.read().expect("failed to read-lock run data"));
             }
+            commit.bury(run);
+        }
+        for run in &added {
+            let mut run_data = RunData::default();
+            counters.recount(&mut run_data);
+            runs_store.insert((*run).clone(), RwLock::new(run_data));
         }
+        drop(runs_store);
 
         // Add new runs.
-        for run_name in discoveries.keys() {
-            let checksum = self.checksum;
-            self.runs.entry(run_name.clone()).or_insert_with(|| {
-                let mut loader = RunLoader::new(run_name.clone());
-                loader.checksum(checksum);
-                loader
+        if added.is_empty() {
+            return;
+        }
+        let options = self.run_options();
+        for run_name in added {
+            let mut loader = RunLoader::new(run_name.clone());
+            loader.checksum(match &self.checksum_runs {
+                Some(predicate) => predicate(run_name),
+                None => self.checksum,
             });
+            loader.skip_bad_checksums(self.skip_bad_checksums);
+            loader.max_record_len(self.max_record_len);
+            loader.read_retries(self.read_retries);
+            loader.provenance(self.provenance);
+            loader.blob_references(self.blob_references);
+            loader.framing(self.framing);
+            loader.preview_first_file(self.preview_first_file);
+            loader.file_order(self.file_order);
+            loader.max_files(self.max_files);
+            loader.expired_files(self.expired_files);
+            options.apply(&mut loader, run_name, &self.commit);
+            self.runs.insert(run_name.clone(), loader);
+        }
+    }
+
+    /// Snapshots the options that this loader gives new run loaders for handling events.
+    fn run_options(&self) -> RunOptions {
+        RunOptions {
+            zero_wall_time: self.zero_wall_time,
+            negative_steps: self.negative_steps,
+            max_step: self.max_step,
+            out_of_order_tolerance: self.out_of_order_tolerance,
+            duplicate_tags: self.duplicate_tags,
+            duplicate_steps: self.duplicate_steps,
+            scalar_precision: self.scalar_precision,
+            run_metadata_tags: self.run_metadata_tags,
+            strict: self.strict,
+            sample_sizes: self.sample_sizes,
+            max_tags: self.max_tags,
+            max_plugin_content_len: self.max_plugin_content_len,
+            scalar_fast_path: self.scalar_fast_path,
+            wall_time_offsets: self.wall_time_offsets.clone(),
+            retention: self.retention.clone(),
+            tag_budget: self.tag_budget.clone(),
+            metadata_overrides: self.metadata_overrides.clone(),
+            tag_filter: self.tag_filter.clone(),
+        }
+    }
+
+    /// Shares the current [`RunOptions`] with the registry of pushed runs, if any.
+    fn share_run_options(&self) {
+        if let Some(sources) = &self.run_sources {
+            sources.set_run_options(self.run_options());
         }
     }
 
     /// Tells all run loaders to reload data with the given filenames, and blocks until completion.
+    /// Returns the total number of events read, or the first error by run name in strict mode.
+    ///
+    /// Runs that are idle under [`Self::poll_backoff`] are skipped until their next scheduled poll.
     ///
     /// # Panics
     ///
     /// Panics if a run in `self.runs` has no entry in `discoveries`, which should only happen if
     /// `synchronize_runs(&discoveries)` was not called. Panics if any run loader panics.
-    fn load_runs(&mut self, mut discoveries: Discoveries) {
+    fn load_runs(&mut self, mut discoveries: Discoveries) -> Result<usize, LoadError> {
         let commit_runs = self
             .commit
             .runs
@@ -209,16 +1122,57 @@ where
                     run
                 )
             });
-            work_items.push((loader, filenames, run_data));
+            if self.poll_backoff.is_some() {
+                let poll_state = self.poll_states.entry(run.clone()).or_default();
+                if poll_state.skip > 0
+                    && !run_data
+                        .read()
+                        .expect("acquiring tags lock")
+                        .needs_restore()
+                {
+                    poll_state.skip -= 1;
+                    continue;
+                }
+            }
+            work_items.push((run, loader, filenames, run_data));
         }
         let logdir = &self.logdir;
-        self.thread_pool.install(|| {
-            work_items
-                .into_par_iter()
-                .for_each(|(loader, filenames, run_data)| {
-                    loader.reload(logdir, filenames, run_data);
-                });
-        });
+        let load = |item| load_run(logdir, item);
+        let polled: Vec<(&Run, bool, Result<usize, LoadError>)> = if self.warm {
+            self.thread_pool
+                .install(|| work_items.into_par_iter().map(load).collect())
+        } else {
+            self.warm = !work_items.is_empty();
+            self.cold_start_order
+                .sort(logdir, &mut work_items, |item| item.0);
+            // Unlike `into_par_iter`, which splits the items among threads up front, this hands
+            // each free thread the next item in order.
+            self.thread_pool
+                .install(|| work_items.into_iter().par_bridge().map(load).collect())
+        };
+        if let Some(backoff) = &self.poll_backoff {
+            for (run, paused, events) in &polled {
+                // A paused run reads nothing, but that says nothing about whether it's idle.
+                if *paused {
+                    continue;
+                }
+                if let (Some(poll_state), Ok(events)) = (self.poll_states.get_mut(*run), events) {
+                    poll_state.record_poll(*events, backoff);
+                }
+            }
+        }
+        let mut total = 0;
+        let mut errors = Vec::new();
+        for (run, _, events) in polled {
+            match events {
+                Ok(events) => total += events,
+                Err(e) => errors.push((run, e)),
+            }
+        }
+        match errors.into_iter().min_by(|(r1, _), (r2, _)| r1.cmp(r2)) {
+            Some((_, e)) => Err(e),
+            None => Ok(total),
+        }
     }
 }
 
@@ -227,9 +1181,12 @@ mod tests {
     use super::*;
     use std::collections::HashSet;
     use std::fs::{self, File};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
 
     use crate::disk_logdir::DiskLogdir;
-    use crate::types::{Step, Tag, WallTime};
+    use crate::types::{Step, WallTime};
     use crate::writer::SummaryWriteExt;
 
     #[test]
@@ -278,7 +1235,7 @@ mod tests {
         let mut loader = LogdirLoader::new(&commit, logdir, 1);
 
         // Check that we persist the right run states in the loader.
-        loader.reload();
+        assert_eq!(loader.reload(), 6);
         let expected_runs = vec![&root_run, &train_run, &test_run]
             .into_iter()
             .collect::<HashSet<_>>();
@@ -302,13 +1259,103 @@ mod tests {
                         .collect();
                     (run, values)
                 })
-                .collect::<HashMap<&Run, Vec<f32>>>(),
+                .collect::<HashMap<&Run, Vec<f64>>>(),
             expected_data
         );
 
         Ok(())
     }
 
+    #[test]
+    fn test_length_delimited_framing() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::proto::tensorboard as pb;
+        use prost::Message;
+
+        let logdir = tempfile::tempdir()?;
+        let train_dir = logdir.path().join("train");
+        fs::create_dir(&train_dir)?;
+        let mut events = Vec::new();
+        let graph = pb::Event {
+            step: 0,
+            wall_time: 1234.0,
+            what: Some(pb::event::What::GraphDef(b"<sample model graph>".to_vec())),
+            ..Default::default()
+        };
+        graph.encode_length_delimited(&mut events)?;
+        for step in 1..3 {
+            let value = pb::summary::Value {
+                tag: "loss".to_string(),
+                value: Some(pb::summary::value::Value::SimpleValue(0.5 * step as f32)),
+                ..Default::default()
+            };
+            let event = pb::Event {
+                step,
+                wall_time: 1234.0 + step as f64,
+                what: Some(pb::event::What::Summary(pb::Summary {
+                    value: vec![value],
+                    ..Default::default()
+                })),
+                ..Default::default()
+            };
+            event.encode_length_delimited(&mut events)?;
+        }
+        fs::write(train_dir.join("tfevents.123"), events)?;
+
+        let run = Run("train".to_string());
+        let tag = Tag("loss".to_string());
+        let load = |framing| {
+            let commit = Commit::new();
+            let mut loader =
+                LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+            loader.framing(framing);
+            loader.blob_references(true);
+            loader.reload();
+            drop(loader);
+            commit
+        };
+
+        // As TFRecords, the file is corrupt.
+        let commit = load(Framing::TfRecord);
+        assert_eq!(commit.read_scalars(&run, &tag), None);
+
+        let commit = load(Framing::LengthDelimited);
+        let loss = commit.read_scalars(&run, &tag).expect("loss scalars");
+        let values: Vec<(i64, f64)> = loss.iter().map(|(step, _, v)| (step.0, *v)).collect();
+        assert_eq!(values, vec![(1, 0.5), (2, 1.0)]);
+
+        // Blob references read the file back with the same framing.
+        let runs = commit.runs.read().unwrap();
+        let run_data = runs[&run].read().unwrap();
+        let ts = run_data.blob_references.values().next().expect("run graph");
+        let (_, _, reference) = ts.valid_values().next().unwrap();
+        assert_eq!(reference.framing, Framing::LengthDelimited);
+        let blobs = reference.read_blob(&DiskLogdir::new(logdir.path().into()), &ts.metadata)?;
+        assert_eq!(blobs.0, vec![b"<sample model graph>".to_vec()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_skips_sentinel_files() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let train_dir = logdir.path().join("train");
+        fs::create_dir(&train_dir)?;
+        let event_file = train_dir.join("events.out.tfevents.123.host");
+        File::create(&event_file)?;
+        File::create(train_dir.join("events.out.tfevents.123.host.profile-empty"))?;
+
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let run = Run("train".to_string());
+        let discovered = logdir.discover()?;
+        assert_eq!(discovered[&run], vec![EventFileBuf(event_file.clone())]);
+        let files: Vec<EventFileBuf> = (logdir.run_version(&run)?.0.into_iter())
+            .map(|(file, _, _)| file)
+            .collect();
+        assert_eq!(files, vec![EventFileBuf(event_file)]);
+        assert!(is_event_file_name("events.out.tfevents.123.host.gz"));
+        assert!(!is_event_file_name("README"));
+        Ok(())
+    }
+
     #[test]
     fn test_add_remove() -> Result<(), Box<dyn std::error::Error>> {
         let logdir = tempfile::tempdir()?;
@@ -333,13 +1380,9 @@ mod tests {
         let mut loader = LogdirLoader::new(&commit, logdir, 1);
 
         let get_run_names = || {
-            let runs_store = commit.runs.read().unwrap();
-            let mut result = runs_store
-                .keys()
-                .map(|Run(name)| name.clone())
-                .collect::<Vec<String>>();
-            result.sort();
-            result
+            (commit.run_names().into_iter())
+                .map(|Run(name)| name)
+                .collect::<Vec<String>>()
         };
         let get_test_scalar = || {
             let runs_store = commit.runs.read().unwrap();
@@ -369,7 +1412,287 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_run_filter() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let tag = Tag("loss".to_string());
+        for run in &["teamA/run1", "teamB/run2"] {
+            let dir = logdir.path().join(run);
+            fs::create_dir_all(&dir)?;
+            File::create(dir.join(EVENT_FILE_BASENAME_INFIX))?.write_scalar(
+                &tag,
+                Step(0),
+                WallTime::new(1234.0).unwrap(),
+                0.5,
+            )?;
+        }
+        let team_a = Run(format!("teamA{}run1", std::path::MAIN_SEPARATOR));
+        let team_b = Run(format!("teamB{}run2", std::path::MAIN_SEPARATOR));
+
+        let commit = Commit::new();
+        let mut loader =
+            LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+        let mut filter = RunFilter::new();
+        filter.include("teamA/**");
+        loader.run_filter(filter);
+        let run_names = || commit.run_names();
+
+        assert_eq!(loader.reload(), 1);
+        assert_eq!(run_names(), vec![team_a.clone()]);
+        assert_eq!(loader.runs.keys().collect::<Vec<_>>(), vec![&team_a]);
+
+        // Changing the filter picks up newly included runs and drops newly excluded ones.
+        let mut filter = RunFilter::new();
+        filter.exclude("teamA/*");
+        loader.run_filter(filter);
+        assert_eq!(loader.reload(), 1);
+        assert_eq!(run_names(), vec![team_b.clone()]);
+        assert_eq!(loader.runs.keys().collect::<Vec<_>>(), vec![&team_b]);
+
+        loader.run_filter(RunFilter::new());
+        loader.reload();
+        assert_eq!(run_names(), vec![team_a, team_b]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_checksum_runs() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let tag = Tag("loss".to_string());
+        for run in &["local", "remote"] {
+            let dir = logdir.path().join(run);
+            fs::create_dir(&dir)?;
+            // A valid event whose data checksum is wrong: it parses fine, so only a loader that
+            // checksums every record notices.
+            let mut data = Vec::new();
+            data.write_scalar(&tag, Step(0), WallTime::new(1234.0).unwrap(), 0.5)?;
+            *data.last_mut().unwrap() ^= 0xff;
+            fs::write(dir.join(EVENT_FILE_BASENAME_INFIX), data)?;
+        }
+
+        let commit = Commit::new();
+        let mut loader =
+            LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+        loader.checksum_runs(|run| run.0 != "local");
+        loader.reload();
+        let num_points = |run: &str| {
+            let points = commit.read_scalars(&Run(run.to_string()), &tag);
+            points.map(|points| points.len())
+        };
+        assert_eq!(num_points("local"), Some(1));
+        assert_eq!(num_points("remote"), None);
+        let remote = &loader.runs[&Run("remote".to_string())];
+        assert_eq!(remote.dead_files().count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_sources() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let tag = Tag("loss".to_string());
+        for run in &["train", "pushed"] {
+            let dir = logdir.path().join(run);
+            fs::create_dir(&dir)?;
+            File::create(dir.join(EVENT_FILE_BASENAME_INFIX))?.write_scalar(
+                &tag,
+                Step(0),
+                WallTime::new(1234.0).unwrap(),
+                0.5,
+            )?;
+        }
+        let (train, pushed) = (Run("train".to_string()), Run("pushed".to_string()));
+
+        let commit = Commit::new();
+        let sources = Arc::new(RunSources::new());
+        assert!(sources.claim_pushed(&pushed).is_some());
+        let mut loader =
+            LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+        loader.run_sources(Some(Arc::clone(&sources)));
+
+        // The pushed run's event files are ignored, and the file-backed run can't be pushed.
+        assert_eq!(loader.reload(), 1);
+        assert_eq!(loader.runs.keys().collect::<Vec<_>>(), vec![&train]);
+        assert!(sources.claim_pushed(&train).is_none());
+
+        // Once the file-backed run has no more files, its name is free again.
+        fs::remove_dir_all(logdir.path().join("train"))?;
+        loader.reload();
+        assert!(loader.runs.is_empty());
+        assert!(sources.claim_pushed(&train).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscribe() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::commit::CommitEvent;
+        use tokio::sync::broadcast::error::TryRecvError;
+
+        let logdir = tempfile::tempdir()?;
+        let train_dir = logdir.path().join("train");
+        fs::create_dir(&train_dir)?;
+        let mut file = File::create(train_dir.join(EVENT_FILE_BASENAME_INFIX))?;
+        let loss = Tag("loss".to_string());
+        let accuracy = Tag("accuracy".to_string());
+        let wall_time = WallTime::new(1234.0).unwrap();
+        file.write_scalar(&loss, Step(0), wall_time, 0.5)?;
+        file.write_scalar(&accuracy, Step(0), wall_time, 0.25)?;
+        file.sync_all()?;
+
+        let commit = Commit::new();
+        let mut events = commit.subscribe();
+        let mut loader =
+            LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+        let train = Run("train".to_string());
+
+        loader.reload();
+        assert_eq!(
+            events.try_recv(),
+            Ok(CommitEvent {
+                run: train.clone(),
+                changed_tags: vec![accuracy, loss.clone()],
+            })
+        );
+        assert_eq!(events.try_recv(), Err(TryRecvError::Empty));
+
+        file.write_scalar(&loss, Step(1), wall_time, 0.375)?;
+        file.sync_all()?;
+        loader.reload();
+        assert_eq!(
+            events.try_recv(),
+            Ok(CommitEvent {
+                run: train,
+                changed_tags: vec![loss],
+            })
+        );
+
+        // No new data, no notification.
+        loader.reload();
+        assert_eq!(events.try_recv(), Err(TryRecvError::Empty));
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_total_tags() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let tags = [Tag("x".to_string()), Tag("y".to_string())];
+        // Appends points at `step` for both tags to the event file of each of two runs.
+        let write_step = |step: i64| -> std::io::Result<()> {
+            for run in &["a", "b"] {
+                let dir = logdir.path().join(run);
+                fs::create_dir_all(&dir)?;
+                let mut file = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(dir.join(EVENT_FILE_BASENAME_INFIX))?;
+                for tag in &tags {
+                    file.write_scalar(tag, Step(step), WallTime::new(1234.0).unwrap(), 0.5)?;
+                }
+                file.sync_all()?;
+            }
+            Ok(())
+        };
+
+        let commit = Commit::new();
+        let mut loader =
+            LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+        loader.max_total_tags(Some(3));
+        // Gets the number of points in each time series, across all runs.
+        let point_counts = || {
+            let runs = commit.runs.read().unwrap();
+            let mut counts: Vec<usize> = runs
+                .values()
+                .flat_map(|run_data| {
+                    let run_data = run_data.read().unwrap();
+                    let counts: Vec<usize> = run_data
+                        .scalars
+                        .values()
+                        .map(|ts| ts.valid_values().count())
+                        .collect();
+                    counts
+                })
+                .collect();
+            counts.sort_unstable();
+            counts
+        };
+
+        write_step(0)?;
+        loader.reload();
+        assert_eq!(point_counts(), vec![1, 1, 1]);
+        let budget = loader.tag_budget.as_ref().unwrap();
+        assert_eq!(budget.used(), 3);
+        assert_eq!(budget.rejected(), 1);
+        let overflow: usize = loader.runs.values().map(|r| r.tag_overflow()).sum();
+        assert_eq!(overflow, 1);
+
+        // Existing time series keep updating, but no new ones are created.
+        write_step(1)?;
+        loader.reload();
+        assert_eq!(point_counts(), vec![2, 2, 2]);
+
+        // Removing a run frees its share of the budget.
+        fs::remove_dir_all(logdir.path().join("a"))?;
+        loader.reload();
+        assert_eq!(
+            loader.tag_budget.as_ref().unwrap().used(),
+            point_counts().len()
+        );
+        Ok(())
+    }
+
     #[cfg(unix)]
+    #[test]
+    fn test_poll_backoff() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let event_path = logdir.path().join("tfevents.123");
+        let tag = Tag("accuracy".to_string());
+        let mut file = File::create(&event_path)?;
+        file.write_scalar(&tag, Step(0), WallTime::new(1234.0).unwrap(), 0.5)?;
+        file.sync_all()?;
+
+        let commit = Commit::new();
+        let mut loader = LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().into()), 1);
+        loader.poll_backoff(Some(PollBackoff {
+            idle_reloads: 2,
+            max_interval: 8,
+        }));
+        let run = Run(".".to_string());
+        assert_eq!(loader.poll_interval(&run), None);
+
+        assert_eq!(loader.reload(), 1);
+        assert_eq!(loader.poll_interval(&run), Some(1));
+
+        // With no new data, the run is polled twice more at full speed, then less and less often.
+        let mut intervals = Vec::new();
+        for _ in 0..20 {
+            assert_eq!(loader.reload(), 0);
+            intervals.push(loader.poll_interval(&run).unwrap());
+        }
+        assert_eq!(
+            intervals,
+            vec![1, 2, 2, 4, 4, 4, 4, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8]
+        );
+
+        // New data is picked up at the next scheduled poll, which restores the fast cadence.
+        file.write_scalar(&tag, Step(1), WallTime::new(1235.0).unwrap(), 0.75)?;
+        file.sync_all()?;
+        let mut cycles = 0;
+        while loader.reload() == 0 {
+            cycles += 1;
+            assert!(cycles < 8, "new data not loaded after {} cycles", cycles);
+        }
+        assert_eq!(loader.poll_interval(&run), Some(1));
+        assert_eq!(loader.reload(), 0);
+        assert_eq!(loader.poll_interval(&run), Some(1));
+
+        // Without a backoff, every run is polled on every cycle.
+        loader.poll_backoff(None);
+        for _ in 0..5 {
+            loader.reload();
+            assert_eq!(loader.poll_interval(&run), Some(1));
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_symlink() -> Result<(), Box<dyn std::error::Error>> {
         let logdir = tempfile::tempdir()?;
@@ -408,4 +1731,454 @@ mod tests {
         loader.reload(); // should not hang
         Ok(())
     }
+
+    /// A logdir with one single-file run per name, whose files record how many of them are being
+    /// read at once.
+    struct ConcurrencyLogdir {
+        runs: usize,
+        /// Number of reads in progress, and the most seen at once.
+        counters: Arc<(AtomicUsize, AtomicUsize)>,
+    }
+
+    struct ConcurrencyFile {
+        /// Number of concurrent reads that each read waits (briefly) for before returning.
+        wait_for: usize,
+        counters: Arc<(AtomicUsize, AtomicUsize)>,
+    }
+
+    impl Logdir for ConcurrencyLogdir {
+        type File = ConcurrencyFile;
+        fn discover(&self) -> io::Result<HashMap<Run, Vec<EventFileBuf>>> {
+            Ok((0..self.runs)
+                .map(|i| {
+                    let run = Run(format!("run{}", i));
+                    (
+                        run,
+                        vec![EventFileBuf(PathBuf::from(format!("tfevents.{}", i)))],
+                    )
+                })
+                .collect())
+        }
+        fn open(&self, _path: &EventFileBuf) -> io::Result<Self::File> {
+            Ok(ConcurrencyFile {
+                wait_for: self.runs,
+                counters: Arc::clone(&self.counters),
+            })
+        }
+    }
+
+    impl Read for ConcurrencyFile {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            let (active, max_active) = &*self.counters;
+            active.fetch_add(1, Ordering::SeqCst);
+            // Give the other runs a chance to start reading, too, if there are threads for them.
+            let deadline = Instant::now() + Duration::from_millis(50);
+            while active.load(Ordering::SeqCst) < self.wait_for && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            max_active.fetch_max(active.load(Ordering::SeqCst), Ordering::SeqCst);
+            active.fetch_sub(1, Ordering::SeqCst);
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn test_reload_threads() {
+        let max_concurrent_reads = |threads: usize| {
+            let counters = Arc::new((AtomicUsize::new(0), AtomicUsize::new(0)));
+            let logdir = ConcurrencyLogdir {
+                runs: 4,
+                counters: Arc::clone(&counters),
+            };
+            let commit = Commit::new();
+            let mut loader = LogdirLoader::new(&commit, logdir, threads);
+            loader.reload();
+            assert_eq!(commit.runs.read().unwrap().len(), 4);
+            counters.1.load(Ordering::SeqCst)
+        };
+        assert_eq!(max_concurrent_reads(1), 1);
+        let max = max_concurrent_reads(4);
+        assert!(max > 1 && max <= 4, "max concurrent reads: {}", max);
+    }
+
+    /// A logdir with one empty event file per run, with made-up sizes and modification times, that
+    /// records the order in which runs are opened.
+    struct MetadataLogdir {
+        /// Name, event file size, and event file modification time in seconds since epoch of each
+        /// run.
+        runs: Vec<(&'static str, Option<u64>, Option<u64>)>,
+        opened: Mutex<Vec<String>>,
+    }
+
+    impl Logdir for MetadataLogdir {
+        type File = io::Empty;
+        fn discover(&self) -> io::Result<HashMap<Run, Vec<EventFileBuf>>> {
+            Ok(self
+                .runs
+                .iter()
+                .map(|&(name, _, _)| {
+                    let file = EventFileBuf(PathBuf::from(name).join("tfevents.123"));
+                    (Run(name.to_string()), vec![file])
+                })
+                .collect())
+        }
+        fn open(&self, path: &EventFileBuf) -> io::Result<Self::File> {
+            let run = path.0.parent().unwrap().to_string_lossy().into_owned();
+            self.opened.lock().unwrap().push(run);
+            Ok(io::empty())
+        }
+        fn run_version(&self, run: &Run) -> io::Result<RunVersion> {
+            let &(name, size, mtime) = self.runs.iter().find(|r| r.0 == run.0).unwrap();
+            let file = EventFileBuf(PathBuf::from(name).join("tfevents.123"));
+            let mtime = mtime.map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+            Ok(RunVersion(vec![(file, size, mtime)]))
+        }
+    }
+
+    #[test]
+    fn test_cold_start_order() {
+        // Returns the runs in the order that the first load cycle opened them.
+        let load_order = |order: ColdStartOrder| {
+            let logdir = MetadataLogdir {
+                runs: vec![
+                    ("b", Some(100), Some(3)),
+                    ("d", Some(200), None),
+                    ("a", Some(300), Some(1)),
+                    ("c", None, Some(2)),
+                ],
+                opened: Mutex::new(Vec::new()),
+            };
+            let commit = Commit::new();
+            let mut loader = LogdirLoader::new(&commit, logdir, 1);
+            loader.cold_start_order(order);
+            loader.reload();
+            let opened = loader.logdir.opened.lock().unwrap().clone();
+            opened
+        };
+        assert_eq!(
+            load_order(ColdStartOrder::Alphabetical),
+            vec!["a", "b", "c", "d"]
+        );
+        // Runs with unknown sizes or times go last.
+        assert_eq!(
+            load_order(ColdStartOrder::SmallestFirst),
+            vec!["b", "d", "a", "c"]
+        );
+        assert_eq!(
+            load_order(ColdStartOrder::MostRecentFirst),
+            vec!["b", "c", "a", "d"]
+        );
+
+        assert_eq!("smallest-first".parse(), Ok(ColdStartOrder::SmallestFirst));
+        assert!("largest-first".parse::<ColdStartOrder>().is_err());
+    }
+
+    #[test]
+    fn test_watch_run() -> Result<(), Box<dyn std::error::Error>> {
+        use std::io::Write;
+        use std::sync::mpsc;
+
+        let logdir = tempfile::tempdir()?;
+        let train_dir = logdir.path().join("train");
+        let eval_dir = logdir.path().join("eval");
+        fs::create_dir(&train_dir)?;
+        fs::create_dir(&eval_dir)?;
+        let mut train_file = File::create(train_dir.join("tfevents.123"))?;
+        let mut eval_file = File::create(eval_dir.join("tfevents.123"))?;
+
+        let disk_logdir = Arc::new(DiskLogdir::new(logdir.path().to_path_buf()));
+        let (tx, rx) = mpsc::channel();
+        let watcher = disk_logdir.watch_run(
+            Run("train".to_string()),
+            Duration::from_millis(10),
+            move || tx.send(()).unwrap_or(()),
+        );
+        let quiet = Duration::from_millis(100);
+        assert_eq!(rx.recv_timeout(quiet).ok(), None);
+
+        // Writes to other runs don't count.
+        eval_file.write_all(b"eval data")?;
+        eval_file.sync_all()?;
+        assert_eq!(rx.recv_timeout(quiet).ok(), None);
+
+        train_file.write_all(b"train data")?;
+        train_file.sync_all()?;
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)).ok(), Some(()));
+
+        // New files count, too.
+        File::create(train_dir.join("tfevents.456"))?;
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)).ok(), Some(()));
+
+        // Once the watcher is dropped, its thread stops and drops the sender.
+        drop(watcher);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            match rx.recv_timeout(quiet) {
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+                _ => continue,
+            }
+        }
+        panic!("watcher thread still running");
+    }
+
+    #[test]
+    fn test_strict() -> Result<(), Box<dyn std::error::Error>> {
+        use std::io::Write;
+
+        let logdir = tempfile::tempdir()?;
+        let tag = Tag("loss".to_string());
+        for run in &["train", "test"] {
+            let run_dir = logdir.path().join(run);
+            fs::create_dir(&run_dir)?;
+            let mut file = File::create(run_dir.join("tfevents.123"))?;
+            file.write_scalar(&tag, Step(0), WallTime::new(1234.5).unwrap(), 0.5)?;
+            if *run == "test" {
+                file.write_all(&[0xff; 16])?;
+            }
+        }
+        let corrupt_name = logdir.path().join("test").join("tfevents.123");
+
+        let commit = Commit::new();
+        let mut loader = LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().into()), 1);
+        loader.strict(true);
+        match loader.try_reload() {
+            Err(LoadError::Read { path, .. }) => assert_eq!(path, corrupt_name),
+            other => panic!("{:?}", other),
+        }
+        // The other run still loads.
+        let train = Run("train".to_string());
+        assert_eq!(commit.read_scalars(&train, &tag).map(|v| v.len()), Some(1));
+
+        // Without strict mode, both runs load.
+        let commit = Commit::new();
+        let mut loader = LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().into()), 1);
+        assert_eq!(loader.try_reload()?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_counts() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::commit::Counts;
+
+        let logdir = tempfile::tempdir()?;
+        let train_dir = logdir.path().join("train");
+        fs::create_dir(&train_dir)?;
+        let mut file = File::create(train_dir.join("tfevents.123"))?;
+        let wall_time = WallTime::new(1234.5).unwrap();
+        for i in 0..3 {
+            file.write_scalar(&Tag("loss".to_string()), Step(i), wall_time, 0.5)?;
+        }
+        file.write_graph(Step(0), wall_time, b"<graph>".to_vec())?;
+        file.write_tagged_run_metadata(
+            &Tag("step0000".to_string()),
+            Step(0),
+            wall_time,
+            b"<run metadata>".to_vec(),
+        )?;
+        file.sync_all()?;
+
+        let commit = Commit::new();
+        assert_eq!(commit.counts(), Counts::default());
+        let mut loader = LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().into()), 1);
+        loader.reload();
+        let memory = commit.runs.read().unwrap()[&Run("train".to_string())]
+            .read()
+            .unwrap()
+            .memory_usage();
+        assert!(memory > 0);
+        let expected = Counts {
+            runs: 1,
+            scalar_series: 1,
+            tensor_series: 0,
+            blob_sequence_series: 2,
+            points: 5,
+            memory,
+        };
+        assert_eq!(commit.counts(), expected);
+
+        // Reloading with no new data changes nothing; new points are added.
+        loader.reload();
+        assert_eq!(commit.counts(), expected);
+        file.write_scalar(&Tag("loss".to_string()), Step(3), wall_time, 0.5)?;
+        file.sync_all()?;
+        loader.reload();
+        assert_eq!(commit.counts().points, 6);
+
+        // Removing the run removes everything it contributed.
+        assert!(commit.remove_run(&Run("train".to_string())));
+        assert_eq!(commit.counts(), Counts::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_shedding() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::commit::Watermarks;
+
+        let logdir = tempfile::tempdir()?;
+        let train_dir = logdir.path().join("train");
+        fs::create_dir(&train_dir)?;
+        let mut file = File::create(train_dir.join("tfevents.123"))?;
+        let wall_time = WallTime::new(1234.5).unwrap();
+        let (loss, trace) = (Tag("loss".to_string()), Tag("trace".to_string()));
+        let mut write_step = |step: i64| -> std::io::Result<()> {
+            file.write_scalar(&loss, Step(step), wall_time, 0.5)?;
+            file.write_tagged_run_metadata(&trace, Step(step), wall_time, vec![0; 1000])?;
+            file.sync_all()
+        };
+
+        let commit = Commit::new();
+        let shedding = commit.load_shedding();
+        let mut loader = LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().into()), 1);
+        let run = Run("train".to_string());
+        // Whether each committed point has its blobs, and how many scalar points there are.
+        let points = || {
+            let runs = commit.runs.read().unwrap();
+            let run_data = runs[&run].read().unwrap();
+            let blobs: Vec<bool> = run_data.blob_sequences[&trace]
+                .basin
+                .as_slice()
+                .iter()
+                .map(|(_, (_, v))| v.is_ok())
+                .collect();
+            (blobs, run_data.scalars[&loss].basin.as_slice().len())
+        };
+
+        // Nothing is counted before the first commit, so it stores its blobs.
+        shedding.set_watermarks(Some(Watermarks { high: 1, low: 1 }));
+        write_step(0)?;
+        loader.reload();
+        assert_eq!(points(), (vec![true], 1));
+        assert!(!shedding.is_active());
+
+        // Now over the high watermark: new blobs are shed, but scalars keep coming.
+        write_step(1)?;
+        loader.reload();
+        write_step(2)?;
+        loader.reload();
+        assert_eq!(points(), (vec![true, false, false], 3));
+        assert!(shedding.is_active());
+        assert_eq!(shedding.shed_points(), 2);
+        assert_eq!(loader.runs[&run].shed_blob_points(), 2);
+
+        // Under the low watermark again: blobs are stored, and shed points stay shed.
+        shedding.set_watermarks(Some(Watermarks {
+            high: usize::MAX,
+            low: usize::MAX - 1,
+        }));
+        write_step(3)?;
+        loader.reload();
+        assert_eq!(points(), (vec![true, false, false, true], 4));
+        assert!(!shedding.is_active());
+        assert_eq!(shedding.shed_points(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let train_dir = logdir.path().join("train");
+        fs::create_dir(&train_dir)?;
+        let mut file = File::create(train_dir.join("tfevents.123"))?;
+        let tag = Tag("loss".to_string());
+        file.write_scalar(&tag, Step(0), WallTime::new(1234.5).unwrap(), 0.5)?;
+
+        let commit = Commit::new();
+        let mut loader = LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().into()), 1);
+        let global = ReadOnlySwitch::new();
+        loader.global_read_only(Some(global.clone()));
+        let train = Run("train".to_string());
+        let count = || commit.read_scalars(&train, &tag).map_or(0, |v| v.len());
+        assert_eq!(loader.reload(), 1);
+        assert_eq!(count(), 1);
+
+        // Frozen by the commit's own switch: new data and new runs are ignored.
+        commit.read_only().set(true);
+        assert!(loader.is_read_only());
+        file.write_scalar(&tag, Step(1), WallTime::new(1235.5).unwrap(), 0.6)?;
+        fs::create_dir(logdir.path().join("test"))?;
+        File::create(logdir.path().join("test").join("tfevents.123"))?.write_scalar(
+            &tag,
+            Step(0),
+            WallTime::new(1234.5).unwrap(),
+            0.7,
+        )?;
+        assert_eq!(loader.reload(), 0);
+        assert_eq!(count(), 1);
+        assert_eq!(commit.run_names(), vec![train.clone()]);
+
+        // Unfrozen: loading resumes where it left off.
+        commit.read_only().set(false);
+        assert!(!loader.is_read_only());
+        assert_eq!(loader.reload(), 2);
+        assert_eq!(count(), 2);
+        assert_eq!(commit.run_names().len(), 2);
+
+        // Same for the global switch.
+        global.set(true);
+        assert!(loader.is_read_only());
+        file.write_scalar(&tag, Step(2), WallTime::new(1236.5).unwrap(), 0.8)?;
+        assert_eq!(loader.reload(), 0);
+        assert_eq!(count(), 2);
+        global.set(false);
+        assert_eq!(loader.reload(), 1);
+        assert_eq!(count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_run_races_reload() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::atomic::AtomicBool;
+
+        let logdir = tempfile::tempdir()?;
+        let runs: Vec<Run> = (0..20).map(|i| Run(format!("run_{:02}", i))).collect();
+        for run in &runs {
+            let run_dir = logdir.path().join(&run.0);
+            fs::create_dir(&run_dir)?;
+            File::create(run_dir.join("tfevents.123"))?.write_scalar(
+                &Tag("loss".to_string()),
+                Step(0),
+                WallTime::new(1234.5).unwrap(),
+                0.5,
+            )?;
+        }
+
+        let commit = Arc::new(Commit::new());
+        let mut loader =
+            LogdirLoader::shared(commit.clone(), DiskLogdir::new(logdir.path().into()), 1);
+        loader.reload();
+        let cycles = Arc::new(AtomicUsize::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let reloader = {
+            let (cycles, stop) = (cycles.clone(), stop.clone());
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    loader.reload();
+                    cycles.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        };
+
+        // Delete each run on disk and from the commit while the loader keeps reloading. Once
+        // removed, a run must never reappear, even if the loader discovered it just before.
+        let assert_gone = |removed: &[Run]| {
+            let runs_store = commit.runs.read().unwrap();
+            for run in removed {
+                assert!(!runs_store.contains_key(run), "{:?} came back", run);
+            }
+        };
+        for (i, run) in runs.iter().enumerate() {
+            fs::remove_dir_all(logdir.path().join(&run.0))?;
+            commit.remove_run(run);
+            let start = cycles.load(Ordering::SeqCst);
+            let deadline = Instant::now() + Duration::from_secs(30);
+            while cycles.load(Ordering::SeqCst) < start + 2 {
+                assert_gone(&runs[..=i]);
+                assert!(Instant::now() < deadline, "reloader stalled or panicked");
+            }
+        }
+        stop.store(true, Ordering::SeqCst);
+        reloader.join().expect("reloader panicked");
+        assert_gone(&runs);
+        Ok(())
+    }
 }
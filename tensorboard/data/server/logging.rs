@@ -0,0 +1,494 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Log output formats, with support for structured key-value fields, and rate limiting for
+//! repetitive warnings.
+//!
+//! Fields are kept separate from the message, as separate keys in JSON output, only with the
+//! `structured-logging` feature, since that uses the `log` crate's unstable key-value API.
+//! Otherwise, they're appended to the message as `key=value` in both formats.
+
+#[cfg(feature = "structured-logging")]
+use log::kv::{self, Key, Value, Visitor};
+use log::{LevelFilter, Record};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Logs a message with structured key-value fields attached.
+///
+/// Usage: `log_kv!(Level::Debug, run = run_name, events = n; "Loaded events")`. Each value must
+/// implement `log::kv::ToValue` and `Debug`; use `format_args!("{:?}", x)` for other types. In
+/// text output, fields are appended to the message as `key=value`; in JSON output, each becomes a
+/// separate field of the log object.
+#[cfg(feature = "structured-logging")]
+macro_rules! log_kv {
+    ($level:expr, $($key:ident = $value:expr),+ ; $($arg:tt)+) => {{
+        let level: log::Level = $level;
+        if level <= log::STATIC_MAX_LEVEL && level <= log::max_level() {
+            let kvs: &[(&str, &dyn log::kv::ToValue)] = &[$((stringify!($key), &$value)),+];
+            log::logger().log(
+                &log::Record::builder()
+                    .args(format_args!($($arg)+))
+                    .level(level)
+                    .target(module_path!())
+                    .module_path_static(Some(module_path!()))
+                    .file_static(Some(file!()))
+                    .line(Some(line!()))
+                    .key_values(&kvs)
+                    .build(),
+            );
+        }
+    }};
+}
+
+/// Logs a message with key-value fields appended to it, as `key=value` with the value's `Debug`
+/// format. See the version of this macro for the `structured-logging` feature.
+#[cfg(not(feature = "structured-logging"))]
+macro_rules! log_kv {
+    ($level:expr, $($key:ident = $value:expr),+ ; $($arg:tt)+) => {{
+        let level: log::Level = $level;
+        log::log!(
+            level,
+            "{}{}",
+            format_args!($($arg)+),
+            $crate::logging::Fields(&[$((stringify!($key), &$value as &dyn std::fmt::Debug)),+])
+        );
+    }};
+}
+pub(crate) use log_kv;
+
+/// Key-value fields formatted as ` key=value` each, for [`log_kv!`] without structured logging.
+#[cfg(not(feature = "structured-logging"))]
+pub(crate) struct Fields<'a>(pub &'a [(&'a str, &'a dyn std::fmt::Debug)]);
+
+#[cfg(not(feature = "structured-logging"))]
+impl std::fmt::Display for Fields<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (key, value) in self.0 {
+            write!(f, " {}={:?}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Logs a warning like `log::warn!`, but rate-limited per call site, for warnings that may fire
+/// for every record of a corrupt file.
+///
+/// Usage: `warn_limited!("Dropping event at step {}", step)`, or `warn_limited!(key = path;
+/// "Skipping record in {}", path.display())` to limit each distinct key separately. See
+/// [`RateLimiter`] for which occurrences are logged. Each logged message after the first notes
+/// how many similar messages were suppressed since the last one.
+macro_rules! warn_limited {
+    (key = $key:expr; $($arg:tt)+) => {{
+        static LIMITER: $crate::logging::RateLimiter = $crate::logging::RateLimiter::new();
+        if let Some(suppressed) = LIMITER.check_key(&$key) {
+            log::warn!("{}{}", format_args!($($arg)+), $crate::logging::Suppressed(suppressed));
+        }
+    }};
+    ($($arg:tt)+) => {{
+        static LIMITER: $crate::logging::RateLimiter = $crate::logging::RateLimiter::new();
+        if let Some(suppressed) = LIMITER.check() {
+            log::warn!("{}{}", format_args!($($arg)+), $crate::logging::Suppressed(suppressed));
+        }
+    }};
+}
+pub(crate) use warn_limited;
+
+/// Number of independent counters in a [`RateLimiter`]. Keys are hashed into this many slots.
+const RATE_LIMITER_SLOTS: usize = 64;
+
+/// Decides which occurrences of a repetitive event to log: the 1st, 2nd, 4th, 8th, and so on, so
+/// that `n` occurrences produce only about `log2(n)` log lines.
+///
+/// Occurrences may be counted under a key, like a file name, so that a flood of one kind doesn't
+/// hide the first occurrence of another. Keys are hashed into a fixed number of counters, so
+/// memory use is constant, but distinct keys occasionally share a schedule. Checking costs one
+/// atomic increment, plus hashing the key if any.
+#[derive(Debug)]
+pub struct RateLimiter {
+    counts: [AtomicU64; RATE_LIMITER_SLOTS],
+}
+
+impl RateLimiter {
+    /// Creates a rate limiter with no occurrences counted. This is a `const fn` so that each call
+    /// site can have a `static` limiter.
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU64 = AtomicU64::new(0);
+        RateLimiter {
+            counts: [ZERO; RATE_LIMITER_SLOTS],
+        }
+    }
+
+    /// Counts an occurrence with no key. Returns `Some(n)` if this occurrence should be logged,
+    /// where `n` is the number of occurrences suppressed since the last one logged.
+    pub fn check(&self) -> Option<u64> {
+        Self::check_slot(&self.counts[0])
+    }
+
+    /// Counts an occurrence under `key`, like [`Self::check`].
+    pub fn check_key<K: Hash + ?Sized>(&self, key: &K) -> Option<u64> {
+        Self::check_slot(&self.counts[Self::slot_of(key)])
+    }
+
+    /// Index of the counter that occurrences under `key` are counted in.
+    fn slot_of<K: Hash + ?Sized>(key: &K) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % RATE_LIMITER_SLOTS as u64) as usize
+    }
+
+    fn check_slot(count: &AtomicU64) -> Option<u64> {
+        let n = count.fetch_add(1, Ordering::Relaxed) + 1;
+        if n.is_power_of_two() {
+            // The previous logged occurrence was number `n / 2`.
+            Some((n / 2).saturating_sub(1))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formats a count of suppressed messages as a suffix for a rate-limited log message: empty if
+/// the count is zero.
+pub struct Suppressed(pub u64);
+
+impl std::fmt::Display for Suppressed {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.0 {
+            0 => Ok(()),
+            1 => write!(f, " (suppressed 1 similar message)"),
+            n => write!(f, " (suppressed {} similar messages)", n),
+        }
+    }
+}
+
+/// Output format for log records.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines, like `[2021-01-01T00:00:00Z INFO  target] message key=value`.
+    Text,
+    /// One JSON object per line, with `timestamp`, `level`, `target`, and `message` keys plus,
+    /// with the `structured-logging` feature, one key per structured field.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "unknown log format {:?}; expected \"text\" or \"json\"",
+                s
+            )),
+        }
+    }
+}
+
+/// Installs a logging handler whose behavior is determined by the `RUST_LOG` environment variable
+/// (per <https://docs.rs/env_logger> semantics), or by including all logs at `default_log_level`
+/// or above if `RUST_LOG` is not given. Records are written to stderr in the given format.
+pub fn init(default_log_level: LevelFilter, format: LogFormat) {
+    use env_logger::{Builder, Env};
+    let mut builder =
+        Builder::from_env(Env::default().default_filter_or(default_log_level.to_string()));
+    match format {
+        LogFormat::Text => builder.format(|buf, record| {
+            let level = buf.default_styled_level(record.level());
+            write!(
+                buf,
+                "[{} {:<5} {}] ",
+                buf.timestamp(),
+                level,
+                record.target()
+            )?;
+            write_text(buf, record)
+        }),
+        LogFormat::Json => builder.format(|buf, record| {
+            let timestamp = buf.timestamp_millis().to_string();
+            write_json(buf, record, &timestamp)
+        }),
+    };
+    builder.init();
+}
+
+/// Writes the message and fields of `record` as text, followed by a newline.
+fn write_text<W: Write>(w: &mut W, record: &Record) -> io::Result<()> {
+    write!(w, "{}", record.args())?;
+    #[cfg(feature = "structured-logging")]
+    {
+        struct TextFields<'a, W>(&'a mut W);
+        impl<'kvs, W: Write> Visitor<'kvs> for TextFields<'_, W> {
+            fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+                write!(self.0, " {}={:?}", key, value).map_err(|_| kv::Error::msg("write failed"))
+            }
+        }
+        record
+            .key_values()
+            .visit(&mut TextFields(w))
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    }
+    writeln!(w)
+}
+
+/// Writes `record` as a single line of JSON, followed by a newline.
+///
+/// Structured fields (with the `structured-logging` feature) are emitted as top-level keys, with
+/// numeric and boolean values kept as JSON numbers and booleans. Fields named like one of the
+/// standard keys are dropped.
+fn write_json<W: Write>(w: &mut W, record: &Record, timestamp: &str) -> io::Result<()> {
+    use serde_json::{Map, Value as Json};
+
+    let mut obj = Map::new();
+    obj.insert("timestamp".to_string(), Json::from(timestamp));
+    obj.insert("level".to_string(), Json::from(record.level().to_string()));
+    obj.insert("target".to_string(), Json::from(record.target()));
+    obj.insert("message".to_string(), Json::from(record.args().to_string()));
+    #[cfg(feature = "structured-logging")]
+    {
+        struct JsonFields<'a>(&'a mut Map<String, Json>);
+        impl<'kvs> Visitor<'kvs> for JsonFields<'_> {
+            fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+                let json = if let Some(b) = value.to_bool() {
+                    Json::from(b)
+                } else if let Some(n) = value.to_u64() {
+                    Json::from(n)
+                } else if let Some(n) = value.to_i64() {
+                    Json::from(n)
+                } else if let Some(x) = value.to_f64() {
+                    Json::from(x)
+                } else {
+                    Json::from(value.to_string())
+                };
+                self.0.entry(key.as_str()).or_insert(json);
+                Ok(())
+            }
+        }
+        record
+            .key_values()
+            .visit(&mut JsonFields(&mut obj))
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    }
+    serde_json::to_writer(&mut *w, &obj)?;
+    writeln!(w)
+}
+
+/// Captures log messages in tests.
+#[cfg(test)]
+pub(crate) mod capture {
+    use std::cell::RefCell;
+    use std::sync::Once;
+
+    /// What to capture on one thread.
+    struct Capture {
+        /// Least severe level to capture.
+        level: log::Level,
+        /// Whether to capture whole records as JSON lines, or just their messages.
+        json: bool,
+        lines: Vec<String>,
+    }
+
+    thread_local! {
+        /// Records logged on this thread, if capturing.
+        #[allow(clippy::missing_const_for_thread_local)] // `const` needs Rust 1.59
+        static CAPTURE: RefCell<Option<Capture>> = RefCell::new(None);
+    }
+
+    struct CaptureLogger;
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record) {
+            CAPTURE.with(|c| {
+                let mut c = c.borrow_mut();
+                let capture = match c.as_mut() {
+                    Some(capture) if record.level() <= capture.level => capture,
+                    _ => return,
+                };
+                let line = if capture.json {
+                    let mut buf = Vec::new();
+                    super::write_json(&mut buf, record, "").expect("failed to format log record");
+                    String::from_utf8(buf).expect("JSON log line not UTF-8")
+                } else {
+                    record.args().to_string()
+                };
+                capture.lines.push(line);
+            });
+        }
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger;
+
+    /// Calls `f` and returns what it logged on this thread at `level` or above. Installs a global
+    /// logger for the test binary on first call.
+    fn capture(level: log::Level, json: bool, f: impl FnOnce()) -> Vec<String> {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&LOGGER).expect("another logger is already installed");
+        });
+        if log::max_level() < level {
+            log::set_max_level(level.to_level_filter());
+        }
+        CAPTURE.with(|c| {
+            *c.borrow_mut() = Some(Capture {
+                level,
+                json,
+                lines: Vec::new(),
+            })
+        });
+        f();
+        CAPTURE.with(|c| c.borrow_mut().take().map(|c| c.lines).unwrap_or_default())
+    }
+
+    /// Calls `f` and returns the messages of warnings and errors that it logged on this thread.
+    pub fn capture_warnings(f: impl FnOnce()) -> Vec<String> {
+        capture(log::Level::Warn, false, f)
+    }
+
+    /// Calls `f` and returns everything that it logged on this thread at `level` or above, as
+    /// lines of the JSON log format with empty timestamps.
+    pub fn capture_json(level: log::Level, f: impl FnOnce()) -> Vec<String> {
+        capture(level, true, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    #[test]
+    fn test_parse_log_format() {
+        assert_eq!("text".parse::<LogFormat>(), Ok(LogFormat::Text));
+        assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+        "yaml"
+            .parse::<LogFormat>()
+            .expect_err("should reject unknown formats");
+    }
+
+    /// Formats a record with fields using `write` and returns the output as a string.
+    #[cfg(feature = "structured-logging")]
+    fn format_with_fields(write: impl Fn(&mut Vec<u8>, &Record) -> io::Result<()>) -> String {
+        let kvs: &[(&str, &dyn kv::ToValue)] = &[
+            ("run", &"train/\"quoted\""),
+            ("events", &100u64),
+            ("level", &"shadowed"),
+        ];
+        let mut buf = Vec::new();
+        write(
+            &mut buf,
+            &Record::builder()
+                .args(format_args!("Loaded {} events", 100))
+                .level(Level::Debug)
+                .target("rustboard_core::run")
+                .key_values(&kvs)
+                .build(),
+        )
+        .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "structured-logging")]
+    fn test_text_format() {
+        let line = format_with_fields(write_text);
+        assert_eq!(
+            line,
+            "Loaded 100 events run=\"train/\\\"quoted\\\"\" events=100 level=\"shadowed\"\n"
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_schedule() {
+        let limiter = RateLimiter::new();
+        let logged: Vec<(u64, u64)> = (1..=20)
+            .filter_map(|i| limiter.check().map(|suppressed| (i, suppressed)))
+            .collect();
+        assert_eq!(logged, vec![(1, 0), (2, 0), (4, 1), (8, 3), (16, 7)]);
+
+        // Keys in different slots have their own schedules.
+        let a = "a".to_string();
+        let b = (0..)
+            .map(|i| format!("b{}", i))
+            .find(|b| RateLimiter::slot_of(b) != RateLimiter::slot_of(&a))
+            .unwrap();
+        let limiter = RateLimiter::new();
+        assert_eq!(limiter.check_key(&a), Some(0));
+        assert_eq!(limiter.check_key(&a), Some(0));
+        assert_eq!(limiter.check_key(&a), None);
+        assert_eq!(limiter.check_key(&b), Some(0));
+        assert_eq!(limiter.check_key(&a), Some(1));
+    }
+
+    #[test]
+    fn test_suppressed_suffix() {
+        assert_eq!(Suppressed(0).to_string(), "");
+        assert_eq!(Suppressed(1).to_string(), " (suppressed 1 similar message)");
+        assert_eq!(
+            Suppressed(7).to_string(),
+            " (suppressed 7 similar messages)"
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "structured-logging"))]
+    fn test_fields_in_message() {
+        let lines = capture::capture_json(Level::Warn, || {
+            log_kv!(Level::Warn, run = "train/\"quoted\"", events = 100u64; "Loaded {} events", 100);
+        });
+        assert_eq!(lines.len(), 1);
+        let parsed: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(
+            parsed["message"],
+            "Loaded 100 events run=\"train/\\\"quoted\\\"\" events=100"
+        );
+        assert_eq!(parsed["run"], serde_json::Value::Null);
+    }
+
+    #[test]
+    #[cfg(feature = "structured-logging")]
+    fn test_json_format() {
+        let line = format_with_fields(|w, r| write_json(w, r, "2021-01-01T00:00:00.000Z"));
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.lines().count(), 1);
+        let parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!({
+                "timestamp": "2021-01-01T00:00:00.000Z",
+                "level": "DEBUG",
+                "target": "rustboard_core::run",
+                "message": "Loaded 100 events",
+                "run": "train/\"quoted\"",
+                "events": 100,
+            })
+        );
+    }
+}
@@ -0,0 +1,241 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Operator-provided corrections to the summary metadata of time series, by tag.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::proto::tensorboard as pb;
+use crate::run_filter::TagFilter;
+use crate::types::Tag;
+
+/// Corrections to the summary metadata of time series whose tags match glob patterns. See
+/// [`RunLoader::metadata_overrides`].
+///
+/// These are for data whose writer stamped the wrong plugin name or data class, when rewriting
+/// the event files isn't practical. Each time series is matched once, when it's first seen, and
+/// the first override whose pattern matches its tag wins. Patterns have the same syntax as for a
+/// [`TagFilter`].
+///
+/// [`RunLoader::metadata_overrides`]: crate::run::RunLoader::metadata_overrides
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataOverrides(Vec<(TagFilter, MetadataOverride)>);
+
+/// A correction to the metadata of the time series whose tags match a pattern.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetadataOverride {
+    /// Glob pattern that tags must match.
+    pub tag: String,
+    /// Plugin name to set, if any. Any plugin content is kept.
+    pub plugin_name: Option<String>,
+    /// Data class to set, if any.
+    pub data_class: Option<OverrideDataClass>,
+}
+
+/// A data class that an override may set, as written in an overrides file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverrideDataClass {
+    Scalar,
+    Tensor,
+    BlobSequence,
+}
+
+impl From<OverrideDataClass> for pb::DataClass {
+    fn from(data_class: OverrideDataClass) -> Self {
+        match data_class {
+            OverrideDataClass::Scalar => pb::DataClass::Scalar,
+            OverrideDataClass::Tensor => pb::DataClass::Tensor,
+            OverrideDataClass::BlobSequence => pb::DataClass::BlobSequence,
+        }
+    }
+}
+
+/// Contents of an overrides file: a list of `[[override]]` tables in TOML, or an object with an
+/// `"override"` array in JSON.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OverridesFile {
+    #[serde(rename = "override", default)]
+    overrides: Vec<MetadataOverride>,
+}
+
+/// Error reading an overrides file.
+#[derive(Debug, thiserror::Error)]
+pub enum OverridesFileError {
+    /// The file could not be read.
+    #[error("failed to read metadata overrides {}: {}", .0.display(), .1)]
+    Read(PathBuf, #[source] io::Error),
+    /// The file is not valid TOML, or has unknown keys or invalid values.
+    #[error("invalid metadata overrides {}: {}", .0.display(), .1)]
+    ParseToml(PathBuf, #[source] toml::de::Error),
+    /// The file is not valid JSON, or has unknown keys or invalid values.
+    #[error("invalid metadata overrides {}: {}", .0.display(), .1)]
+    ParseJson(PathBuf, #[source] serde_json::Error),
+}
+
+impl MetadataOverrides {
+    /// Creates an empty set of overrides, which changes nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads overrides from a file: JSON if its name ends in `.json`, or TOML otherwise.
+    pub fn read(path: &Path) -> Result<Self, OverridesFileError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| OverridesFileError::Read(path.into(), e))?;
+        let file: OverridesFile = if matches!(path.extension(), Some(ext) if ext == "json") {
+            serde_json::from_str(&contents)
+                .map_err(|e| OverridesFileError::ParseJson(path.into(), e))?
+        } else {
+            toml::from_str(&contents).map_err(|e| OverridesFileError::ParseToml(path.into(), e))?
+        };
+        let mut result = Self::new();
+        for o in file.overrides {
+            result.push(o);
+        }
+        Ok(result)
+    }
+
+    /// Adds an override, to be tried after all existing ones.
+    pub fn push(&mut self, o: MetadataOverride) {
+        let mut filter = TagFilter::new();
+        filter.include(&o.tag);
+        self.0.push((filter, o));
+    }
+
+    /// Number of overrides in this set.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Checks whether this set has no overrides.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Applies the first override that matches `tag` to `md`. Returns whether `md` changed.
+    pub fn apply(&self, tag: &Tag, md: &mut pb::SummaryMetadata) -> bool {
+        let o = match self.0.iter().find(|(filter, _)| filter.matches(tag)) {
+            Some((_, o)) => o,
+            None => return false,
+        };
+        let mut changed = false;
+        if let Some(plugin_name) = &o.plugin_name {
+            let plugin_data = md.plugin_data.get_or_insert_with(Default::default);
+            if plugin_data.plugin_name != *plugin_name {
+                plugin_data.plugin_name = plugin_name.clone();
+                changed = true;
+            }
+        }
+        if let Some(data_class) = o.data_class {
+            let data_class = i32::from(pb::DataClass::from(data_class));
+            if md.data_class != data_class {
+                md.data_class = data_class;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(plugin_name: &str, data_class: pb::DataClass) -> pb::SummaryMetadata {
+        pb::SummaryMetadata {
+            plugin_data: Some(pb::summary_metadata::PluginData {
+                plugin_name: plugin_name.to_string(),
+                content: b"config".to_vec(),
+            }),
+            data_class: data_class.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut overrides = MetadataOverrides::new();
+        overrides.push(MetadataOverride {
+            tag: "loss/*".to_string(),
+            plugin_name: Some("scalars".to_string()),
+            data_class: Some(OverrideDataClass::Scalar),
+        });
+        overrides.push(MetadataOverride {
+            tag: "**".to_string(),
+            plugin_name: None,
+            data_class: Some(OverrideDataClass::Tensor),
+        });
+
+        let mut md = metadata("my_scalars", pb::DataClass::Unknown);
+        assert!(overrides.apply(&Tag("loss/train".to_string()), &mut md));
+        assert_eq!(md, metadata("scalars", pb::DataClass::Scalar));
+        // Already correct, so nothing changes.
+        assert!(!overrides.apply(&Tag("loss/train".to_string()), &mut md));
+
+        // Only the first matching override applies.
+        let mut md = metadata("my_scalars", pb::DataClass::Unknown);
+        assert!(overrides.apply(&Tag("accuracy".to_string()), &mut md));
+        assert_eq!(md, metadata("my_scalars", pb::DataClass::Tensor));
+
+        let mut md = pb::SummaryMetadata::default();
+        assert!(!MetadataOverrides::new().apply(&Tag("loss".to_string()), &mut md));
+    }
+
+    #[test]
+    fn test_read() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let expected = MetadataOverride {
+            tag: "eval/*".to_string(),
+            plugin_name: Some("scalars".to_string()),
+            data_class: Some(OverrideDataClass::BlobSequence),
+        };
+
+        let toml_path = dir.path().join("overrides.toml");
+        std::fs::write(
+            &toml_path,
+            "[[override]]\ntag = \"eval/*\"\nplugin_name = \"scalars\"\n\
+             data_class = \"blob-sequence\"\n",
+        )?;
+        let overrides = MetadataOverrides::read(&toml_path)?;
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(overrides.0[0].1, expected);
+
+        let json_path = dir.path().join("overrides.json");
+        std::fs::write(
+            &json_path,
+            r#"{"override": [{"tag": "eval/*", "plugin_name": "scalars",
+                "data_class": "blob-sequence"}]}"#,
+        )?;
+        assert_eq!(MetadataOverrides::read(&json_path)?, overrides);
+
+        std::fs::write(
+            &toml_path,
+            "[[override]]\ntag = \"x\"\ndata_class = \"histogram\"\n",
+        )?;
+        let err = MetadataOverrides::read(&toml_path).unwrap_err();
+        assert!(
+            matches!(err, OverridesFileError::ParseToml(..)),
+            "{:?}",
+            err
+        );
+        let err = MetadataOverrides::read(&dir.path().join("missing.json")).unwrap_err();
+        assert!(matches!(err, OverridesFileError::Read(..)), "{:?}", err);
+        Ok(())
+    }
+}
@@ -21,7 +21,7 @@ use rand::{
 };
 use rand_chacha::ChaCha20Rng;
 
-use crate::types::Step;
+use crate::types::{Step, WallTime};
 
 /// A [reservoir sampling] data structure, with support for preemption and deferred "commits" of
 /// records to a separate destination for better concurrency.
@@ -32,9 +32,14 @@ use crate::types::Step;
 ///
 /// # Preemption
 ///
-/// All records stored in this reservoir have a *step* and a *payload*. The step, a non-negative
-/// integer, is expected to be monotonically (strictly) increasing over time. Whenever a new record
-/// arrives with step `s`, it preempts any records with steps not smaller than `s`.
+/// All records stored in this reservoir have a *step* and a *payload*. The step, an integer, is
+/// expected to be monotonically (strictly) increasing over time. Whenever a new record arrives with
+/// step `s`, it preempts any records with steps not smaller than `s`. Steps are only ever compared,
+/// never offset, so any `i64` is a valid step: in particular, a record at step `i64::MIN` preempts
+/// everything before it. (Writers that use negative steps as sentinels can be handled before
+/// records reach the reservoir; see [`NegativeStepPolicy`].)
+///
+/// [`NegativeStepPolicy`]: crate::run::NegativeStepPolicy
 ///
 /// This is motivated by preemptions of nondeterministic training jobs. If a job is checkpointed at
 /// step 80 and preempted at step 100, any metrics written between steps 81 and 100 will be
@@ -46,6 +51,14 @@ use crate::types::Step;
 /// preempted may find that this reservoir does not behave as they expect. For instance, a job that
 /// emits many records all at step 0 will find that only one record is retained in the reservoir.
 ///
+/// Writers whose steps merely arrive slightly out of order, like several workers sharing one
+/// event file, can use [`Self::offer_tolerating`] to insert such records by step instead of
+/// letting them preempt their neighbors.
+///
+/// A reservoir with a [`DuplicateStepPolicy`] (see [`Self::duplicate_steps`]) instead keeps a
+/// record that arrives at the same step as another alongside it, and preempts only later steps.
+/// The two are resolved when committed with [`Self::commit_map_resolving`].
+///
 /// # Deferred commits
 ///
 /// This reservoir is designed to maximize throughput of reading records from disk while still
@@ -70,18 +83,20 @@ use crate::types::Step;
 pub struct StageReservoir<T, C = ChaCha20Rng> {
     /// Steps of items currently in the reservoir whose values have already been committed.
     ///
-    /// Stored in step-sorted order, and all steps in `committed_steps` precede all steps in
-    /// `staged_items`.
+    /// Stored in step-sorted order. All steps in `committed_steps` precede all steps in
+    /// `staged_items`, unless records have been inserted out of order (see
+    /// [`Self::offer_tolerating`]); the two never share a step.
     committed_steps: Vec<Step>,
     /// Items currently in the reservoir but not yet committed.
     ///
-    /// Stored in step-sorted order, and all steps in `staged_items` succeed all steps in
-    /// `committed_steps`.
+    /// Stored in step-sorted order. See `committed_steps` for how the two interleave.
     staged_items: Vec<(Step, T)>,
     /// Total capacity of this reservoir.
     ///
     /// The combined physical capacities of `committed_steps` and `staged_items` may exceed this,
-    /// but their combined lengths will not. Behavior is undefined if `capacity == 0`.
+    /// but their combined lengths will not, except under a [`DuplicateStepPolicy`] between the
+    /// arrival of a record at a repeated step and the next commit. Behavior is undefined if
+    /// `capacity == 0`.
     capacity: usize,
     /// Reservoir control, to determine whether and whither a given new record should be included.
     ctl: C,
@@ -98,6 +113,37 @@ pub struct StageReservoir<T, C = ChaCha20Rng> {
     /// Exception: when `capacity == 0`, `seen` is always `0` as well. A reservoir with no capacity
     /// is inert and has no need to track `seen`.
     seen: usize,
+    /// How to resolve records that share a step, or `None` to have each such record preempt or
+    /// replace the one before it.
+    duplicate_steps: Option<DuplicateStepPolicy>,
+    /// Whether any two records in the reservoir may share a step, so that the next commit needs
+    /// to resolve them.
+    pending_duplicates: bool,
+}
+
+/// How to resolve records at the same step, as can come from writers that log a value more than
+/// once per step or from out-of-order delivery. See [`StageReservoir::duplicate_steps`].
+///
+/// Records are compared by wall time. Records with equal wall times resolve in the order that
+/// they were offered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DuplicateStepPolicy {
+    /// Keep only the record with the latest wall time, or the last offered of those. This is the
+    /// default.
+    PreferNewerWallTime,
+    /// Keep only the record with the earliest wall time, or the first offered of those.
+    PreferOlder,
+    /// Keep every record, in wall time order. Steps in the basin are then non-decreasing rather
+    /// than strictly increasing, so a binary search by step may land on any of the records at
+    /// that step.
+    KeepBoth,
+}
+
+#[allow(clippy::derivable_impls)] // deriving needs `#[default]` (Rust 1.62)
+impl Default for DuplicateStepPolicy {
+    fn default() -> Self {
+        DuplicateStepPolicy::PreferNewerWallTime
+    }
 }
 
 /// A buffer of records that have been committed and not yet evicted from the reservoir.
@@ -117,6 +163,28 @@ impl<T> Basin<T> {
     pub fn as_slice(&self) -> &[(Step, T)] {
         &self.0[..]
     }
+
+    /// Gets the number of points that the basin can hold without reallocating. This may be much
+    /// more than its length after points are preempted.
+    pub fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    /// Releases any memory that the basin holds beyond what its points need. Returns the number
+    /// of bytes reclaimed.
+    pub fn compact(&mut self) -> usize {
+        let before = self.0.capacity();
+        self.0.shrink_to_fit();
+        (before - self.0.capacity()) * std::mem::size_of::<(Step, T)>()
+    }
+}
+
+/// Creates a basin with the given records, which should be in step-sorted order. Used to restore
+/// a basin saved from an earlier reservoir.
+impl<T> From<Vec<(Step, T)>> for Basin<T> {
+    fn from(records: Vec<(Step, T)>) -> Self {
+        Basin(records)
+    }
 }
 
 impl<T> Default for Basin<T> {
@@ -139,6 +207,20 @@ pub trait ReservoirControl {
     /// A useful implementation is to simply roll a random integer in `0..n`. Test code may wish to
     /// provide an alternate, deterministic implementation.
     fn destination(&mut self, n: usize) -> usize;
+
+    /// Like [`Self::destination`], but also given the step of the new record and an iterator over
+    /// the steps of the records currently in the reservoir, in order. This is what the reservoir
+    /// actually calls. The default implementation ignores the steps (and never advances the
+    /// iterator) and defers to `destination`.
+    fn destination_for_step(
+        &mut self,
+        n: usize,
+        step: Step,
+        steps: &mut dyn Iterator<Item = Step>,
+    ) -> usize {
+        let _ = (step, steps);
+        self.destination(n)
+    }
 }
 
 impl<R: Rng> ReservoirControl for R {
@@ -147,6 +229,69 @@ impl<R: Rng> ReservoirControl for R {
     }
 }
 
+/// A `ReservoirControl` that spreads the records that it keeps evenly over the range of steps
+/// seen, rather than over the records themselves.
+///
+/// A uniform reservoir keeps each record with equal probability, so when a stream is written
+/// densely for a while and then sparsely, most of the kept records come from the dense stretch,
+/// even if it spans only a small part of the step range. This control instead weights retention
+/// inversely to local step density: when the reservoir is full, it evicts the record whose
+/// neighbors are closest together in step, which is the record that covers the least of the step
+/// range. The first record is never evicted, and as with any reservoir, the latest record is
+/// always kept.
+///
+/// This control is deterministic. Each record offered to a full reservoir costs time linear in the
+/// reservoir's capacity.
+#[derive(Debug, Clone)]
+pub struct StepDensityControl {
+    capacity: usize,
+}
+
+impl StepDensityControl {
+    /// Creates a control for a reservoir with the given capacity. The capacity must match that
+    /// of the reservoir.
+    pub fn new(capacity: usize) -> Self {
+        StepDensityControl { capacity }
+    }
+}
+
+impl ReservoirControl for StepDensityControl {
+    /// Always returns `0`. The reservoir only calls [`Self::destination_for_step`].
+    fn destination(&mut self, _n: usize) -> usize {
+        0
+    }
+
+    fn destination_for_step(
+        &mut self,
+        _n: usize,
+        step: Step,
+        steps: &mut dyn Iterator<Item = Step>,
+    ) -> usize {
+        // Find the interior record whose neighbors are closest together. The last record in the
+        // reservoir counts as interior, since the new record will follow it. Gaps are computed in
+        // `i128` so that extreme steps can't overflow.
+        let mut best: Option<(i128, usize)> = None;
+        let mut len = 0;
+        let mut window: [Option<Step>; 2] = [None, None];
+        for (i, next) in steps.chain(std::iter::once(step)).enumerate() {
+            if let [Some(prev), Some(_)] = window {
+                let gap = i128::from(next.0) - i128::from(prev.0);
+                if best.iter().all(|&(min, _)| gap < min) {
+                    best = Some((gap, i - 1));
+                }
+            }
+            window = [window[1], Some(next)];
+            len = i;
+        }
+        match best {
+            Some((_, index)) if len >= self.capacity => index,
+            // With room to spare, add without evicting: `0` is in bounds, and a reservoir that
+            // isn't full doesn't evict on an in-bounds destination.
+            _ => 0,
+        }
+    }
+}
+
 impl<T> StageReservoir<T, ChaCha20Rng> {
     /// Creates a new reservoir with the specified capacity, using a fixed-seed random number
     /// generator for reservoir control.
@@ -155,7 +300,74 @@ impl<T> StageReservoir<T, ChaCha20Rng> {
     ///
     /// This function does not allocate. Reservoir capacity is allocated as records are offered.
     pub fn new(capacity: usize) -> Self {
-        Self::with_control(capacity, ChaCha20Rng::seed_from_u64(0))
+        let mut ctl = ChaCha20Rng::seed_from_u64(0);
+        // Seeking to the start of the stream changes nothing but makes the generator fill its
+        // buffer, without which `get_word_pos` underflows (in `rand_chacha` 0.2) until the first
+        // random number is drawn.
+        ctl.set_word_pos(0);
+        Self::with_control(capacity, ctl)
+    }
+
+    /// Gets the position of this reservoir's random number generator in its stream. See
+    /// [`Self::resume`].
+    pub fn rng_position(&self) -> u128 {
+        self.ctl.get_word_pos()
+    }
+
+    /// Recreates a reservoir created with [`Self::new`] from a saved copy of its state: its
+    /// [`committed_steps`], [`staged_items`], [`seen`] count, and [`rng_position`]. The new
+    /// reservoir makes the same sampling decisions for future records as the original would have.
+    ///
+    /// Returns `None` if the state is not one that a reservoir with the given capacity could have
+    /// been in: e.g., if it has more items than the capacity or its steps are out of order.
+    /// Committed and staged steps may interleave, as after out-of-order records. They may repeat
+    /// only if `duplicate_steps` gives the policy under which the state was saved (see
+    /// [`Self::duplicate_steps`]).
+    ///
+    /// [`committed_steps`]: Self::committed_steps
+    /// [`staged_items`]: Self::staged_items
+    /// [`seen`]: Self::seen
+    /// [`rng_position`]: Self::rng_position
+    pub fn resume(
+        capacity: usize,
+        committed_steps: Vec<Step>,
+        staged_items: Vec<(Step, T)>,
+        seen: usize,
+        rng_position: u128,
+        duplicate_steps: Option<DuplicateStepPolicy>,
+    ) -> Option<Self> {
+        let len = committed_steps.len() + staged_items.len();
+        let steps = MergedSteps::new(&committed_steps, &staged_items);
+        let pending_duplicates = steps
+            .clone()
+            .zip(steps.clone().skip(1))
+            .any(|(prev, next)| prev == next);
+        let sorted = committed_steps.windows(2).all(|w| w[0] <= w[1])
+            && staged_items.windows(2).all(|w| w[0].0 <= w[1].0)
+            && steps
+                .clone()
+                .zip(steps.skip(1))
+                .all(|(prev, next)| prev <= next)
+            && (duplicate_steps.is_some() || !pending_duplicates);
+        let valid = if capacity == 0 {
+            len == 0 && seen == 0
+        } else {
+            len <= capacity && len <= seen && sorted
+        };
+        if !valid {
+            return None;
+        }
+        let mut ctl = ChaCha20Rng::seed_from_u64(0);
+        ctl.set_word_pos(rng_position);
+        Some(Self {
+            committed_steps,
+            staged_items,
+            capacity,
+            ctl,
+            seen,
+            duplicate_steps,
+            pending_duplicates,
+        })
     }
 }
 
@@ -170,9 +382,19 @@ impl<T, C: ReservoirControl> StageReservoir<T, C> {
             capacity,
             ctl,
             seen: 0,
+            duplicate_steps: None,
+            pending_duplicates: false,
         }
     }
 
+    /// Sets how to resolve records that share a step. With a policy, a record offered at the same
+    /// step as one already in the reservoir is kept alongside it (subject to sampling) instead of
+    /// preempting or replacing it, and the two are resolved by the next call to
+    /// [`Self::commit_map_resolving`].
+    pub fn duplicate_steps(&mut self, policy: DuplicateStepPolicy) {
+        self.duplicate_steps = Some(policy);
+    }
+
     /// Offers a record to the reservoir.
     ///
     /// The reservoir will always include the latest record. Other than the latest record, the
@@ -184,12 +406,17 @@ impl<T, C: ReservoirControl> StageReservoir<T, C> {
         }
         self.preempt(step);
         self.seen += 1;
+        if self.duplicate_steps.is_some() && self.last_step() == Some(step) {
+            self.push_duplicate(step, v);
+            return;
+        }
 
         // If we can hold every record that we've seen, we can add this record unconditionally.
         // Otherwise, we need to roll a destination---even if there's available space, to avoid
         // bias right after a preemption.
         if self.seen > self.capacity {
-            let dst = self.ctl.destination(self.seen);
+            let mut steps = MergedSteps::new(&self.committed_steps, &self.staged_items);
+            let dst = self.ctl.destination_for_step(self.seen, step, &mut steps);
             if dst >= self.capacity {
                 // Didn't make the cut? Keep-last only.
                 self.pop();
@@ -203,68 +430,218 @@ impl<T, C: ReservoirControl> StageReservoir<T, C> {
         self.staged_items.push((step, v));
     }
 
+    /// Offers a record to the reservoir, treating it as delivered out of order if its step
+    /// precedes the latest step in the reservoir by less than `tolerance`. Such a record is
+    /// inserted in step order without preempting anything, and replaces any record already in the
+    /// reservoir at its step. Records further behind preempt as with [`Self::offer`], which this
+    /// is equivalent to when `tolerance` is zero.
+    ///
+    /// An out-of-order record is sampled like any other, except that it is never kept at the
+    /// expense of the latest record.
+    pub fn offer_tolerating(&mut self, step: Step, v: T, tolerance: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+        match self.last_step() {
+            Some(last)
+                if step < last && i128::from(last.0) - i128::from(step.0) < tolerance.into() =>
+            {
+                self.insert(step, v)
+            }
+            _ => self.offer(step, v),
+        }
+    }
+
+    /// Inserts an out-of-order record, which precedes the latest record in the reservoir. See
+    /// [`Self::offer_tolerating`].
+    fn insert(&mut self, step: Step, v: T) {
+        let staged_pos = |items: &[(Step, T)]| items.binary_search_by_key(&step, |(s, _)| *s);
+        match (
+            staged_pos(&self.staged_items),
+            self.committed_steps.binary_search(&step),
+        ) {
+            (Err(_), Err(_)) => (),
+            _ if self.duplicate_steps.is_some() => {
+                self.seen += 1;
+                self.push_duplicate(step, v);
+                return;
+            }
+            (Ok(i), _) => {
+                self.staged_items[i].1 = v;
+                return;
+            }
+            (Err(i), Ok(j)) => {
+                // Forget the committed value, so that the next commit replaces it.
+                self.committed_steps.remove(j);
+                self.staged_items.insert(i, (step, v));
+                return;
+            }
+        }
+        self.seen += 1;
+        if self.seen > self.capacity {
+            let mut steps = MergedSteps::new(&self.committed_steps, &self.staged_items);
+            let dst = self.ctl.destination_for_step(self.seen, step, &mut steps);
+            if dst >= self.capacity {
+                return;
+            }
+            if self.len() >= self.capacity {
+                if dst == self.len() - 1 {
+                    // Keep the latest record instead.
+                    return;
+                }
+                self.remove(dst);
+            }
+        }
+        let i = staged_pos(&self.staged_items).unwrap_or_else(|i| i);
+        self.staged_items.insert(i, (step, v));
+    }
+
+    /// Stages a record at the same step as one already in the reservoir, under a
+    /// [`DuplicateStepPolicy`]. The record isn't sampled, and evicts nothing: it competes only
+    /// with the records at its step, when the next commit resolves them.
+    fn push_duplicate(&mut self, step: Step, v: T) {
+        self.pending_duplicates = true;
+        // After any staged records at the same step, which were offered earlier.
+        let i = self
+            .staged_items
+            .binary_search_by(|(s, _)| {
+                if *s <= step {
+                    std::cmp::Ordering::Less
+                } else {
+                    std::cmp::Ordering::Greater
+                }
+            })
+            .unwrap_err();
+        self.staged_items.insert(i, (step, v));
+    }
+
+    /// Gets the latest step in the reservoir, committed or staged, if any.
+    fn last_step(&self) -> Option<Step> {
+        match (self.committed_steps.last(), self.staged_items.last()) {
+            (Some(c), Some((s, _))) => Some(*c.max(s)),
+            (c, s) => c.copied().or_else(|| s.map(|(s, _)| *s)),
+        }
+    }
+
     /// Returns the number of items in the reservoir, including both committed and staged items.
     fn len(&self) -> usize {
         self.committed_steps.len() + self.staged_items.len()
     }
 
+    /// Whether some staged item precedes or shares a step with some committed step, which happens
+    /// only after out-of-order records or under a [`DuplicateStepPolicy`].
+    fn interleaved(&self) -> bool {
+        matches!(
+            (self.committed_steps.last(), self.staged_items.first()),
+            (Some(c), Some((s, _))) if s <= c
+        )
+    }
+
     /// Pops the last item in this reservoir, which will be a staged item if there is one or a
-    /// committed step otherwise.
+    /// committed step otherwise (or whichever has the later step, if they interleave).
     ///
     /// Has no effect if the reservoir is empty.
     fn pop(&mut self) {
-        if self.staged_items.pop().is_none() {
+        let staged_last = match (self.committed_steps.last(), self.staged_items.last()) {
+            (Some(c), Some((s, _))) => s > c,
+            (_, s) => s.is_some(),
+        };
+        if staged_last {
+            self.staged_items.pop();
+        } else {
             self.committed_steps.pop();
         }
     }
 
     /// Removes an item at the given index in the sequence of items in the reservoir, including
-    /// both committed and staged items.
+    /// both committed and staged items, in step order.
     ///
     /// # Panics
     ///
     /// Panics if `index >= self.len()`.
     fn remove(&mut self, index: usize) {
-        if index < self.committed_steps.len() {
-            self.committed_steps.remove(index);
+        if !self.interleaved() {
+            if index < self.committed_steps.len() {
+                self.committed_steps.remove(index);
+            } else {
+                self.staged_items.remove(index - self.committed_steps.len());
+            }
+            return;
+        }
+        let (mut i, mut j) = (0, 0);
+        for _ in 0..index {
+            if self.committed_next(i, j) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        if self.committed_next(i, j) {
+            self.committed_steps.remove(i);
         } else {
-            self.staged_items.remove(index - self.committed_steps.len());
+            self.staged_items.remove(j);
+        }
+    }
+
+    /// Helper for `remove`: whether, after the first `i` committed steps and `j` staged items,
+    /// the next item in step order is committed.
+    fn committed_next(&self, i: usize, j: usize) -> bool {
+        match (self.committed_steps.get(i), self.staged_items.get(j)) {
+            (Some(c), Some((s, _))) => c < s,
+            (c, _) => c.is_some(),
         }
     }
 
+    /// Accesses the steps of items in the reservoir that have already been committed.
+    pub fn committed_steps(&self) -> &[Step] {
+        &self.committed_steps[..]
+    }
+
+    /// Gets the estimated number of non-preempted records offered so far. See [`Self::resume`].
+    pub fn seen(&self) -> usize {
+        self.seen
+    }
+
     /// Accesses a view of the currently staged items. This includes all items that have been added
     /// to the reservoir since the last commit and have not been evicted.
     pub fn staged_items(&self) -> &[(Step, T)] {
         &self.staged_items[..]
     }
 
-    /// Preempts any records whose step does not precede the given step.
+    /// Preempts any records whose step does not precede the given step, or, under a
+    /// [`DuplicateStepPolicy`], any records whose step follows it.
     fn preempt(&mut self, step: Step) {
         let old_len = self.len();
+        let keep_same_step = self.duplicate_steps.is_some();
+        let preempted = |s: Step| s > step || (s == step && !keep_same_step);
         let staged_preempted = self
             .staged_items
             .iter()
             .rev()
-            .take_while(|(s, _)| *s >= step)
+            .take_while(|(s, _)| preempted(*s))
             .count();
         if staged_preempted > 0 {
             self.staged_items
                 .truncate(self.staged_items.len() - staged_preempted);
         }
-        if self.staged_items.is_empty() {
-            // Committed steps may have been preempted as well. Note: we can hit this case even if
-            // `staged_preempted == 0`, since `staged_items` may have been empty to begin with.
-            let committed_preempted = self
-                .committed_steps
-                .iter()
-                .rev()
-                .take_while(|s| **s >= step)
-                .count();
-            if committed_preempted > 0 {
-                self.committed_steps
-                    .truncate(self.committed_steps.len() - committed_preempted);
-            }
+        // Committed steps may have been preempted as well. Unless the two interleave, this can
+        // only happen once no staged items are left. Note: that can be the case even if
+        // `staged_preempted == 0`, since `staged_items` may have been empty to begin with.
+        let committed_preempted = self
+            .committed_steps
+            .iter()
+            .rev()
+            .take_while(|s| preempted(**s))
+            .count();
+        if committed_preempted > 0 {
+            self.committed_steps
+                .truncate(self.committed_steps.len() - committed_preempted);
         }
+        self.shrink_seen(old_len);
+    }
+
+    /// Updates `seen` after records were removed from a reservoir that had `old_len` records.
+    fn shrink_seen(&mut self, old_len: usize) {
         let new_len = self.len();
         if new_len == old_len {
             return; // No need to adjust `seen`.
@@ -299,7 +676,7 @@ impl<T, C: ReservoirControl> StageReservoir<T, C> {
     /// The basin should initially be empty and should be modified only by calls to
     /// `commit`/`commit_map` on this reservoir.
     pub fn commit(&mut self, basin: &mut Basin<T>) {
-        self.commit_map(basin, |t| t)
+        self.commit_map(basin, |t| t);
     }
 
     /// Commits pending changes from this reservoir into a basin, applying a mapping function
@@ -308,7 +685,42 @@ impl<T, C: ReservoirControl> StageReservoir<T, C> {
     /// This can be used to perform relatively expensive conversions or enrichments only for
     /// records that are actually committed. The basin should initially be empty and should be
     /// modified only by calls to `commit`/`commit_map` on this reservoir.
-    pub fn commit_map<S, F: FnMut(T) -> S>(&mut self, basin: &mut Basin<S>, mut f: F) {
+    ///
+    /// Returns whether the new records were all appended after the old ones in the basin. This
+    /// is always so unless records were inserted out of order (see [`Self::offer_tolerating`]),
+    /// in which case they're merged among the old ones by step.
+    pub fn commit_map<S, F: FnMut(T) -> S>(&mut self, basin: &mut Basin<S>, mut f: F) -> bool {
+        self.sync_basin(basin);
+        if !self.interleaved() {
+            self.committed_steps
+                .extend(self.staged_items.iter().map(|(step, _)| *step));
+            basin
+                .0
+                .extend(self.staged_items.drain(..).map(|(step, t)| (step, f(t))));
+            return true;
+        }
+        let old = std::mem::take(&mut basin.0);
+        let mut merged = Vec::with_capacity(old.len() + self.staged_items.len());
+        let mut new = self
+            .staged_items
+            .drain(..)
+            .map(|(step, t)| (step, f(t)))
+            .peekable();
+        for item in old {
+            while matches!(new.peek(), Some((s, _)) if *s < item.0) {
+                merged.push(new.next().unwrap());
+            }
+            merged.push(item);
+        }
+        merged.extend(new);
+        self.committed_steps = merged.iter().map(|(s, _)| *s).collect();
+        basin.0 = merged;
+        false
+    }
+
+    /// Drops records from the basin that have been evicted or preempted from this reservoir since
+    /// the last commit, so that the basin has exactly the committed steps.
+    fn sync_basin<S>(&self, basin: &mut Basin<S>) {
         let mut keep_steps = self.committed_steps.iter().peekable();
         basin.0.retain(|(s, _)| match keep_steps.peek() {
             Some(t) if *s == **t => {
@@ -317,11 +729,129 @@ impl<T, C: ReservoirControl> StageReservoir<T, C> {
             }
             _ => false,
         });
-        self.committed_steps
-            .extend(self.staged_items.iter().map(|(step, _)| *step));
-        basin
-            .0
-            .extend(self.staged_items.drain(..).map(|(step, t)| (step, f(t))));
+    }
+
+    /// Removes committed records for which `expired` returns `true` from both this reservoir and
+    /// its basin, as for age-based retention. Staged records are unaffected. Returns the number
+    /// of records removed.
+    ///
+    /// Like a preemption, this lowers the estimate of records seen in proportion, so that later
+    /// records are sampled as if the removed ones had never been offered.
+    pub fn evict_committed<S, F: FnMut(&S) -> bool>(
+        &mut self,
+        basin: &mut Basin<S>,
+        mut expired: F,
+    ) -> usize {
+        self.sync_basin(basin);
+        let old_len = self.len();
+        let old_committed = basin.0.len();
+        basin.0.retain(|(_, v)| !expired(v));
+        let evicted = old_committed - basin.0.len();
+        if evicted > 0 {
+            self.committed_steps = basin.0.iter().map(|(s, _)| *s).collect();
+            self.shrink_seen(old_len);
+        }
+        evicted
+    }
+
+    /// Like [`Self::commit_map`], but then resolves records in the basin that share a step per
+    /// this reservoir's [`DuplicateStepPolicy`], comparing them by the wall times that
+    /// `wall_time` reads from committed values. Records at the same step are merged with older
+    /// records first, so ties go by the order in which records were offered.
+    ///
+    /// Returns `false` if any records were resolved, as well as whenever `commit_map` would.
+    pub fn commit_map_resolving<S, F, W>(
+        &mut self,
+        basin: &mut Basin<S>,
+        f: F,
+        wall_time: W,
+    ) -> bool
+    where
+        F: FnMut(T) -> S,
+        W: Fn(&S) -> WallTime,
+    {
+        let appended = self.commit_map(basin, f);
+        let policy = match self.duplicate_steps {
+            Some(policy) if self.pending_duplicates => policy,
+            _ => return appended,
+        };
+        self.pending_duplicates = false;
+        let records = &mut basin.0;
+        // For `dedup_by`, `later` is removed if the closure returns `true`; swapping first keeps
+        // its value instead.
+        let mut resolve = |prefer_later: fn(WallTime, WallTime) -> bool| {
+            records.dedup_by(|later, earlier| {
+                if later.0 != earlier.0 {
+                    return false;
+                }
+                if prefer_later(wall_time(&later.1), wall_time(&earlier.1)) {
+                    std::mem::swap(later, earlier);
+                }
+                true
+            })
+        };
+        match policy {
+            DuplicateStepPolicy::PreferNewerWallTime => resolve(|later, earlier| later >= earlier),
+            DuplicateStepPolicy::PreferOlder => resolve(|later, earlier| later < earlier),
+            DuplicateStepPolicy::KeepBoth => {
+                // Stable, so ties keep their order; and cheap, since records are nearly sorted.
+                records.sort_by(|(s1, v1), (s2, v2)| {
+                    s1.cmp(s2).then_with(|| wall_time(v1).cmp(&wall_time(v2)))
+                });
+            }
+        }
+        // Duplicates were added without evicting anything, so there may be too many records
+        // left. Evict the extras as `offer` would, except that the latest record always stays.
+        while records.len() > self.capacity {
+            let n = records.len() - 1;
+            let last = records[n].0;
+            let mut steps = records[..n].iter().map(|(s, _)| *s);
+            let dst = self.ctl.destination_for_step(n, last, &mut steps);
+            records.remove(dst.min(n - 1));
+        }
+        self.committed_steps = records.iter().map(|(s, _)| *s).collect();
+        false
+    }
+}
+
+/// Iterator over the steps of the items in a reservoir, committed and staged, in step order.
+struct MergedSteps<'a, T> {
+    committed: std::iter::Peekable<std::slice::Iter<'a, Step>>,
+    staged: std::iter::Peekable<std::slice::Iter<'a, (Step, T)>>,
+}
+
+impl<'a, T> MergedSteps<'a, T> {
+    fn new(committed: &'a [Step], staged: &'a [(Step, T)]) -> Self {
+        MergedSteps {
+            committed: committed.iter().peekable(),
+            staged: staged.iter().peekable(),
+        }
+    }
+}
+
+// Not derived, which would require `T: Clone`.
+impl<'a, T> Clone for MergedSteps<'a, T> {
+    fn clone(&self) -> Self {
+        MergedSteps {
+            committed: self.committed.clone(),
+            staged: self.staged.clone(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for MergedSteps<'a, T> {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        let committed_next = match (self.committed.peek(), self.staged.peek()) {
+            (Some(c), Some((s, _))) => *c < s,
+            (c, _) => c.is_some(),
+        };
+        if committed_next {
+            self.committed.next().copied()
+        } else {
+            self.staged.next().map(|(s, _)| *s)
+        }
     }
 }
 
@@ -542,6 +1072,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_resume() {
+        let mut original = StageReservoir::new(10);
+        let mut h1 = Basin::new();
+        for i in 0..50 {
+            original.offer(Step(i), i);
+            if i == 30 {
+                original.commit(&mut h1);
+            }
+        }
+        let mut resumed = StageReservoir::resume(
+            10,
+            original.committed_steps().to_vec(),
+            original.staged_items().to_vec(),
+            original.seen(),
+            original.rng_position(),
+            None,
+        )
+        .expect("valid state");
+        let mut h2 = Basin::from(h1.as_slice().to_vec());
+        for i in (50..200).chain(120..150) {
+            original.offer(Step(i), i);
+            resumed.offer(Step(i), i);
+        }
+        original.commit(&mut h1);
+        resumed.commit(&mut h2);
+        assert_eq!(h1.as_slice(), h2.as_slice());
+
+        let resume = |committed: Vec<i64>, staged: Vec<i64>, seen| {
+            let committed = committed.into_iter().map(Step).collect();
+            let staged = staged.into_iter().map(|i| (Step(i), ())).collect();
+            StageReservoir::resume(3, committed, staged, seen, 0, None).is_some()
+        };
+        assert!(resume(vec![1, 2], vec![3], 10));
+        assert!(!resume(vec![1, 2], vec![3, 4], 10)); // over capacity
+        assert!(resume(vec![1, 3], vec![2], 10)); // interleaved, as after out-of-order records
+        assert!(!resume(vec![3, 1], vec![4], 10)); // out of order
+        assert!(!resume(vec![1], vec![1], 10)); // duplicate step
+        assert!(!resume(vec![1, 2], vec![3], 2)); // seen fewer than kept
+    }
+
     #[test]
     fn test_empty() {
         let mut rsv = StageReservoir::new(0);
@@ -601,4 +1172,208 @@ mod tests {
         assert_eq!(steps(&head), (0..8).map(Step).collect::<Vec<_>>());
         assert_eq!(rsv.seen, 8);
     }
+
+    #[test]
+    fn test_extreme_steps() {
+        let mut rsv = StageReservoir::with_control(10, ScriptedControl::new());
+        let mut head = Basin::new();
+        rsv.offer(Step(i64::MIN), "min");
+        rsv.offer(Step(-1), "neg");
+        rsv.offer(Step(i64::MAX), "max");
+        rsv.commit(&mut head);
+        assert_eq!(steps(&head), vec![Step(i64::MIN), Step(-1), Step(i64::MAX)]);
+
+        // Nothing precedes `i64::MIN`, so it preempts every record, committed or staged.
+        rsv.offer(Step(0), "zero");
+        rsv.offer(Step(i64::MIN), "min again");
+        assert_eq!(rsv.seen, 1);
+        rsv.commit(&mut head);
+        assert_eq!(head.as_slice(), &[(Step(i64::MIN), "min again")]);
+
+        // A repeat of `i64::MIN` preempts itself.
+        rsv.offer(Step(i64::MIN), "min once more");
+        rsv.commit(&mut head);
+        assert_eq!(head.as_slice(), &[(Step(i64::MIN), "min once more")]);
+    }
+
+    #[test]
+    fn test_out_of_order_tolerance() {
+        let mut rsv = StageReservoir::with_control(10, ScriptedControl::new());
+        let mut head = Basin::new();
+        for &step in &[10, 8, 11] {
+            rsv.offer_tolerating(Step(step), step, 3);
+        }
+        rsv.commit(&mut head);
+        assert_eq!(steps(&head), vec![Step(8), Step(10), Step(11)]);
+
+        // Step 9 lands among committed steps, and is merged in at the next commit.
+        rsv.offer_tolerating(Step(9), 9, 3);
+        rsv.offer_tolerating(Step(12), 12, 3);
+        assert!(!rsv.commit_map(&mut head, |x| x));
+        assert_eq!(
+            steps(&head),
+            vec![Step(8), Step(9), Step(10), Step(11), Step(12)]
+        );
+        assert_eq!(rsv.seen, 5);
+
+        // A repeated step replaces the committed record without preempting later ones.
+        rsv.offer_tolerating(Step(10), 100, 3);
+        rsv.commit(&mut head);
+        assert_eq!(
+            head.as_slice(),
+            &[
+                (Step(8), 8),
+                (Step(9), 9),
+                (Step(10), 100),
+                (Step(11), 11),
+                (Step(12), 12)
+            ]
+        );
+
+        // A larger regression is a preemption.
+        rsv.offer_tolerating(Step(0), 0, 3);
+        rsv.commit(&mut head);
+        assert_eq!(head.as_slice(), &[(Step(0), 0)]);
+        assert_eq!(rsv.seen, 1);
+    }
+
+    #[test]
+    fn test_out_of_order_sampling() {
+        let mut rsv = StageReservoir::with_control(3, ScriptedControl::new());
+        let mut head = Basin::new();
+        for step in 0..3 {
+            rsv.offer_tolerating(Step(step * 10), (), 100);
+        }
+        rsv.commit(&mut head);
+        // Full: the out-of-order record evicts index 0, then another would evict the latest
+        // record and so is dropped instead.
+        rsv.ctl.extend(vec![0, 2]);
+        rsv.offer_tolerating(Step(5), (), 100);
+        rsv.offer_tolerating(Step(15), (), 100);
+        rsv.commit(&mut head);
+        assert_eq!(steps(&head), vec![Step(5), Step(10), Step(20)]);
+        assert_eq!(rsv.seen, 5);
+    }
+
+    #[test]
+    fn test_duplicate_steps_over_capacity() {
+        let wall_time = |&(t, _): &(f64, char)| WallTime::new(t).unwrap();
+        let mut rsv = StageReservoir::with_control(3, ScriptedControl::new());
+        rsv.duplicate_steps(DuplicateStepPolicy::KeepBoth);
+        let mut head = Basin::new();
+        for &(step, t, c) in &[(1, 1.0, 'a'), (2, 2.0, 'b'), (3, 4.0, 'c')] {
+            rsv.offer(Step(step), (t, c));
+        }
+        // Full, but a record at a repeated step is never sampled, so evicts nothing yet.
+        rsv.offer(Step(3), (3.0, 'd'));
+        assert_eq!(rsv.len(), 4);
+        // Once resolved, the extra record is evicted as if by `offer`, sparing the latest.
+        rsv.ctl.extend(vec![0]);
+        assert!(!rsv.commit_map_resolving(&mut head, |x| x, wall_time));
+        let values: Vec<(Step, char)> =
+            head.as_slice().iter().map(|(s, (_, c))| (*s, *c)).collect();
+        assert_eq!(values, vec![(Step(2), 'b'), (Step(3), 'd'), (Step(3), 'c')]);
+        assert_eq!(rsv.committed_steps(), &[Step(2), Step(3), Step(3)]);
+
+        // Without duplicates, it's the same as `commit_map`.
+        rsv.ctl.extend(vec![0]);
+        rsv.offer(Step(4), (5.0, 'e'));
+        let values: Vec<char> = {
+            rsv.commit_map_resolving(&mut head, |x| x, wall_time);
+            head.as_slice().iter().map(|(_, (_, c))| *c).collect()
+        };
+        assert_eq!(values, vec!['d', 'c', 'e']);
+    }
+
+    #[test]
+    fn test_evict_committed() {
+        let mut rsv = StageReservoir::new(10);
+        let mut head = Basin::new();
+        for i in 0..5 {
+            rsv.offer(Step(i), i);
+        }
+        rsv.commit(&mut head);
+        rsv.offer(Step(5), 5);
+        assert_eq!(rsv.evict_committed(&mut head, |&v| v < 3), 3);
+        assert_eq!(steps(&head), vec![Step(3), Step(4)]);
+        assert_eq!(rsv.committed_steps(), &[Step(3), Step(4)]);
+        // Staged records stay, and `seen` shrinks along with the reservoir.
+        assert_eq!(rsv.staged_items(), &[(Step(5), 5)]);
+        assert_eq!(rsv.seen(), 3);
+        rsv.commit(&mut head);
+        assert_eq!(steps(&head), vec![Step(3), Step(4), Step(5)]);
+
+        // Records preempted since the last commit are gone from the basin, too, even though
+        // they aren't expired.
+        rsv.offer(Step(4), 6);
+        assert_eq!(rsv.evict_committed(&mut head, |&v| v < 4), 1);
+        assert_eq!(steps(&head), vec![]);
+        rsv.commit(&mut head);
+        assert_eq!(steps(&head), vec![Step(4)]);
+        assert_eq!(rsv.evict_committed(&mut head, |_| false), 0);
+    }
+
+    #[test]
+    fn test_step_density_control() {
+        // Dense in `[0, 100)`, then sparse up to step 10000.
+        let stream: Vec<i64> = (0..100).chain((100..=10000).step_by(100)).collect();
+        let capacity = 40;
+        let retained = |steps: &[Step], range: std::ops::Range<i64>| {
+            steps.iter().filter(|s| range.contains(&s.0)).count()
+        };
+
+        let mut uniform = StageReservoir::new(capacity);
+        let mut uniform_head = Basin::new();
+        let mut density = StageReservoir::with_control(capacity, StepDensityControl::new(capacity));
+        let mut density_head = Basin::new();
+        for (i, &step) in stream.iter().enumerate() {
+            uniform.offer(Step(step), ());
+            density.offer(Step(step), ());
+            if i % 17 == 0 {
+                density.commit(&mut density_head);
+            }
+        }
+        uniform.commit(&mut uniform_head);
+        density.commit(&mut density_head);
+        let uniform_steps = steps(&uniform_head);
+        let density_steps = steps(&density_head);
+
+        assert_eq!(density_steps.len(), capacity);
+        assert!(density_steps.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(density_steps.first(), Some(&Step(0)));
+        assert_eq!(density_steps.last(), Some(&Step(10000)));
+
+        // The dense stretch is 1% of the step range but half of the records. A uniform sample
+        // keeps about half of its points there; the density-aware one keeps only a few.
+        let uniform_dense = retained(&uniform_steps, 0..100);
+        let density_dense = retained(&density_steps, 0..100);
+        assert!(uniform_dense >= 10, "uniform: {:?}", uniform_steps);
+        assert!(density_dense <= 3, "density: {:?}", density_steps);
+
+        // Each quarter of the step range gets its share of the points.
+        for quarter in 0..4 {
+            let range = (quarter * 2500)..((quarter + 1) * 2500);
+            let n = retained(&density_steps, range.clone());
+            assert!(
+                (6..=14).contains(&n),
+                "{} points in {:?}: {:?}",
+                n,
+                range,
+                density_steps
+            );
+        }
+    }
+
+    #[test]
+    fn test_step_density_control_extreme_steps() {
+        let mut rsv = StageReservoir::with_control(3, StepDensityControl::new(3));
+        let mut head = Basin::new();
+        for &step in &[i64::MIN, -1, 0, 1, i64::MAX] {
+            rsv.offer(Step(step), ());
+        }
+        rsv.commit(&mut head);
+        // Step 0 goes first (smallest gap); then step 1 covers `i64::MAX + 1` steps, and step -1
+        // covers one more than that.
+        assert_eq!(steps(&head), vec![Step(i64::MIN), Step(-1), Step(i64::MAX)]);
+    }
 }
@@ -15,18 +15,29 @@ limitations under the License.
 
 //! Loader for a single run, with one or more event files.
 
-use log::{debug, warn};
-use std::collections::{BTreeMap, HashMap, HashSet};
+use log::{error, info, warn, Level};
+use prost::Message;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::io::Read;
-use std::sync::RwLock;
-use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
 
-use crate::commit;
-use crate::data_compat::{EventValue, GraphDefValue, SummaryValue, TaggedRunMetadataValue};
-use crate::event_file::EventFileReader;
-use crate::logdir::{EventFileBuf, Logdir};
+use crate::commit::{self, public_tag, reserved_key, ScalarPrecision, RESERVED_TAG_PREFIX};
+use crate::data_compat::{
+    plugin_names, EventValue, GraphDefValue, SummaryValue, TaggedRunMetadataValue,
+};
+use crate::event_file::{EventFileReader, Framing, ReadEventError};
+use crate::loader_state::{self, file_state, point, RunLoaderState};
+use crate::logdir::{EventFileBuf, Logdir, GZIP_EVENT_FILE_SUFFIX};
+use crate::logging::{log_kv, warn_limited};
+use crate::metadata_override::MetadataOverrides;
 use crate::proto::tensorboard as pb;
-use crate::reservoir::StageReservoir;
+use crate::reservoir::{Basin, DuplicateStepPolicy, StageReservoir};
+use crate::run_filter::TagFilter;
 use crate::types::{Run, Step, Tag, WallTime};
 
 /// A loader to accumulate reservoir-sampled events in a single TensorBoard run.
@@ -41,14 +52,67 @@ pub struct RunLoader<R> {
     /// The event files in this run.
     ///
     /// Event files are sorted and read lexicographically by name, which is designed to coincide
-    /// with actual start time. See [`EventFile::Dead`] for conditions under which an event file
-    /// may be dead. Once an event file is added to this map, it may become dead, but it will not
+    /// with actual start time. See [`DeadReason`] for conditions under which an event file may
+    /// be dead. Once an event file is added to this map, it may become dead, but it will not
     /// be removed entirely. This way, we know not to just re-open it again at the next load cycle.
+    /// The exception is files expired under [`ExpiredFilePolicy::Forget`], which are tracked by
+    /// `expired_through` instead.
     files: BTreeMap<EventFileBuf, EventFile<R>>,
 
+    /// Maximum number of event files to read. See [`Self::max_files`].
+    max_files: Option<usize>,
+
+    /// What to do with files over the limit. See [`Self::expired_files`].
+    expired_files: ExpiredFilePolicy,
+
+    /// The newest event file forgotten under [`ExpiredFilePolicy::Forget`]. Files named up to
+    /// this are never opened, unless they're still in `files`.
+    expired_through: Option<EventFileBuf>,
+
+    /// Whether we've warned that this run has more event files than its limit.
+    warned_file_limit: bool,
+
     /// Whether to compute CRCs for records before parsing as protos.
     checksum: bool,
 
+    /// Whether to skip records with bad data CRCs rather than abandoning their event files. See
+    /// [`Self::skip_bad_checksums`].
+    skip_bad_checksums: bool,
+
+    /// Maximum data length of a record, in bytes. See [`Self::max_record_len`].
+    max_record_len: Option<u64>,
+
+    /// How many times to retry a transient read error. See [`Self::read_retries`].
+    read_retries: u32,
+
+    /// Wall-clock limit on each load. See [`Self::reload_deadline`].
+    reload_deadline: Option<Duration>,
+
+    /// Whether loading is paused. See [`Self::pause`].
+    paused: bool,
+
+    /// Whether to read only the first event file. See [`Self::preview_first_file`].
+    preview_first_file: bool,
+
+    /// Order in which to read event files. See [`Self::file_order`].
+    file_order: EventFileOrder,
+
+    /// Number of readable event files that the last reload skipped. See
+    /// [`Self::skipped_files`].
+    skipped_files: usize,
+
+    /// When each event file was last checked and last yielded a record. See
+    /// [`Self::file_activity`].
+    file_activity: HashMap<EventFileBuf, FileActivity>,
+
+    /// Offsets at which to start reading event files that have not yet been opened, from a
+    /// restored state. See [`Self::restore_state`].
+    resume_offsets: HashMap<EventFileBuf, u64>,
+
+    /// Whether we've looked up the modification times of this run's event files for a fallback
+    /// start time. See [`commit::RunData::start_time_synthesized`].
+    checked_file_times: bool,
+
     /// The data staged by this `RunLoader`. This is encapsulated in a sub-struct so that these
     /// fields can be reborrowed within `reload_files` in a context that already has an exclusive
     /// reference into `self.files`, and hence can't call methods on the whole of `&mut self`.
@@ -59,12 +123,75 @@ pub struct RunLoader<R> {
 enum EventFile<R> {
     /// An event file that may still have more valid data.
     Active(EventFileReader<R>),
-    /// An event file that can no longer be read.
-    ///
-    /// This can be because of a non-recoverable read error (e.g., a bad length checksum), due to
-    /// the last-read record being very old (note: not yet implemented), or due to the file being
-    /// deleted.
-    Dead,
+    /// An event file that can no longer be read, for the given reason.
+    Dead(DeadReason),
+}
+
+/// Why a [`RunLoader`] stopped reading an event file. See [`RunLoader::dead_files`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DeadReason {
+    /// The file could not be opened.
+    OpenFailed(std::io::ErrorKind),
+    /// The record at this offset could not be read, because of a non-recoverable error not covered
+    /// below, like an invalid protocol buffer, or the file was shorter than a restored read offset.
+    ReadError { offset: u64 },
+    /// The record at this offset failed its length checksum, or its data checksum when such
+    /// records aren't [skipped](RunLoader::skip_bad_checksums).
+    BadChecksum { offset: u64 },
+    /// Reading the record at this offset failed with an I/O error that might have been transient,
+    /// but it persisted through all [read retries](RunLoader::read_retries).
+    RetriesExhausted { offset: u64 },
+    /// The file is no longer part of the run, usually because it was deleted.
+    RemovedFromDisk,
+    /// The run had more event files than its [limit](RunLoader::max_files), and this was among
+    /// the oldest that weren't still being written.
+    Expired,
+    /// The file was dead in a [restored state](RunLoader::restore_state) that didn't record why.
+    Unrecorded,
+}
+
+/// When a [`RunLoader`] last checked an event file for new records, and when it last read one.
+/// See [`RunLoader::file_activity`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct FileActivity {
+    /// Wall-clock time at which a reload last read at least one record from the file.
+    pub last_read: Option<SystemTime>,
+    /// Wall-clock time at which a reload last tried to read from the file.
+    pub last_checked: Option<SystemTime>,
+    /// Number of reloads that have tried to read from the file.
+    pub checks: u64,
+}
+
+impl DeadReason {
+    /// Gets the reason for giving up on a file after `e` occurred reading the record at `offset`,
+    /// assuming that `e` wasn't retried.
+    pub(crate) fn from_read_error(e: &ReadEventError, offset: u64) -> Self {
+        use crate::tf_record::ReadRecordError::BadLengthCrc;
+        match e {
+            ReadEventError::InvalidRecord(_) | ReadEventError::ReadRecordError(BadLengthCrc(_)) => {
+                DeadReason::BadChecksum { offset }
+            }
+            _ => DeadReason::ReadError { offset },
+        }
+    }
+}
+
+impl std::fmt::Display for DeadReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeadReason::OpenFailed(kind) => write!(f, "open failed with {:?}", kind),
+            DeadReason::ReadError { offset } => write!(f, "read error at offset {}", offset),
+            DeadReason::BadChecksum { offset } => {
+                write!(f, "checksum mismatch at offset {}", offset)
+            }
+            DeadReason::RetriesExhausted { offset } => {
+                write!(f, "read retries exhausted at offset {}", offset)
+            }
+            DeadReason::RemovedFromDisk => f.write_str("removed from disk"),
+            DeadReason::Expired => f.write_str("expired over the run's file limit"),
+            DeadReason::Unrecorded => f.write_str("reason not recorded"),
+        }
+    }
 }
 
 /// Holds data staged by a `RunLoader` that will be committed to the `Commit`.
@@ -77,8 +204,632 @@ struct RunLoaderData {
     /// `file_version` event in the first event file.
     start_time: Option<WallTime>,
 
+    /// Earliest modification time of this run's event files, committed as the start time while
+    /// `start_time` is `None`. See [`commit::RunData::start_time_synthesized`].
+    fallback_start_time: Option<WallTime>,
+
+    /// Distinct `file_version` strings seen in any event file in this run, in the order first
+    /// seen.
+    file_versions: Vec<String>,
+
     /// Reservoir-sampled data and metadata for each time series.
     time_series: HashMap<Tag, StageTimeSeries>,
+
+    /// Optional hook to rename or drop tags before they are staged. See
+    /// [`RunLoader::tag_rewriter`].
+    tag_rewriter: Option<TagRewriter>,
+
+    /// Optional hook to rewrite or drop events before they are read. See
+    /// [`RunLoader::event_interceptor`].
+    event_interceptor: Option<EventInterceptor>,
+
+    /// Which new tags to stage. See [`RunLoader::tag_filter`].
+    tag_filter: TagFilter,
+
+    /// Tags known not to pass `tag_filter`, so that it's checked only once per tag.
+    filtered_tags: HashSet<Tag>,
+
+    /// Number of values dropped by `tag_filter` or `dropped_tags`, or for having reserved tags.
+    filtered_values: usize,
+
+    /// Tags never to stage again. See [`RunLoader::drop_tag`].
+    dropped_tags: HashSet<Tag>,
+
+    /// Whether we've warned about values with [reserved tags](RESERVED_TAG_PREFIX).
+    warned_reserved_tag: bool,
+
+    /// Whether anything has been staged since the last commit. If not, committing would be a
+    /// no-op, so we can skip taking the write lock.
+    dirty: bool,
+
+    /// How to handle events with a wall time at the epoch. See [`RunLoader::zero_wall_time`].
+    zero_wall_time: ZeroWallTime,
+
+    /// The wall time of the most recently staged event, used by [`ZeroWallTime::Inherit`].
+    last_wall_time: Option<WallTime>,
+
+    /// Number of events read with a wall time at the epoch, however they were handled.
+    zero_wall_time_events: usize,
+
+    /// How to handle events with negative steps. See [`RunLoader::negative_steps`].
+    negative_steps: NegativeStepPolicy,
+
+    /// Number of events dropped by [`NegativeStepPolicy::Drop`].
+    negative_step_events_dropped: usize,
+
+    /// Largest step to accept, if any. See [`RunLoader::max_step`].
+    max_step: Option<i64>,
+
+    /// Number of events dropped for having a step above `max_step`.
+    large_step_events_dropped: usize,
+
+    /// Number of events read with no contents. See [`RunLoader::empty_events`].
+    empty_events: usize,
+
+    /// Step regressions smaller than this are out-of-order delivery, not preemptions. See
+    /// [`RunLoader::out_of_order_tolerance`].
+    out_of_order_tolerance: u64,
+
+    /// How to handle repeated tags within one event. See [`RunLoader::duplicate_tags`].
+    duplicate_tags: DuplicateTagPolicy,
+
+    /// How to resolve points at the same step. See [`RunLoader::duplicate_steps`].
+    duplicate_steps: DuplicateStepPolicy,
+
+    /// Precision of committed scalar values. See [`RunLoader::scalar_precision`].
+    scalar_precision: ScalarPrecision,
+
+    /// Where to keep run metadata tags. See [`RunLoader::run_metadata_tags`].
+    run_metadata_tags: RunMetadataTags,
+
+    /// Whether summaries of only `simple_value`s take the fast path. Cleared once the run has
+    /// anything else. See [`RunLoader::scalar_fast_path`].
+    scalar_fast_path: bool,
+
+    /// Whether to fail on the first load error. See [`RunLoader::strict`].
+    strict: bool,
+    /// Why the event just read was dropped, in strict mode.
+    dropped_event: Option<String>,
+
+    /// Number of summary values dropped by [`DuplicateTagPolicy`].
+    duplicate_values_dropped: usize,
+
+    /// Number of time series whose initial metadata conflicted with their first value. See
+    /// [`RunLoader::metadata_conflicts`].
+    metadata_conflicts: usize,
+
+    /// Corrections to the metadata of new time series. See [`RunLoader::metadata_overrides`].
+    metadata_overrides: MetadataOverrides,
+
+    /// Tags whose metadata was changed by `metadata_overrides`. See
+    /// [`RunLoader::overridden_tags`].
+    overridden_tags: BTreeSet<Tag>,
+
+    /// Maximum number of time series in this run. See [`RunLoader::max_tags`].
+    max_tags: usize,
+
+    /// Longest plugin content to keep, in bytes. See [`RunLoader::max_plugin_content_len`].
+    max_plugin_content_len: Option<usize>,
+
+    /// Original length of each tag's dropped plugin content. See
+    /// [`RunLoader::truncated_plugin_content`].
+    truncated_plugin_content: BTreeMap<Tag, usize>,
+
+    /// Values rejected for exceeding `max_tags` or `tag_budget`.
+    tag_overflow: TagOverflow,
+
+    /// Limit on time series shared with other runs. See [`RunLoader::tag_budget`].
+    tag_budget: Option<Arc<TagBudget>>,
+
+    /// Reservoir capacities for new time series. See [`RunLoader::sample_sizes`].
+    sample_sizes: SampleSizes,
+
+    /// Events at or before this wall time are not staged. See [`RunLoader::resume_after`].
+    resume_after: Option<WallTime>,
+
+    /// Seconds added to each event's wall time. See [`RunLoader::wall_time_offset`].
+    wall_time_offset: f64,
+
+    /// Whether to record which event file each point came from. See [`RunLoader::provenance`].
+    provenance: bool,
+
+    /// Whether to commit blob sequences as references into their event files. See
+    /// [`RunLoader::blob_references`].
+    blob_references: bool,
+
+    /// How records are delimited in this run's event files. Kept here rather than with the
+    /// other reading options since blob references record it. See [`RunLoader::framing`].
+    framing: Framing,
+
+    /// Length of the current event record, in bytes, for blob reference mode.
+    record_len: u64,
+
+    /// Whether the [event interceptor](RunLoader::event_interceptor) changed the current event,
+    /// in blob reference mode. Values of a changed event can't be located in its record.
+    record_rewritten: bool,
+
+    /// In provenance or blob reference mode, the event files read so far, indexed by
+    /// [`commit::FileId`].
+    event_files: Vec<PathBuf>,
+
+    /// In provenance or blob reference mode, the event file currently being read and the offset of
+    /// the current event record.
+    source: Option<commit::Provenance>,
+
+    /// Channel on which to announce commits of new points. See [`RunLoader::notifier`].
+    notifier: Option<broadcast::Sender<commit::CommitEvent>>,
+
+    /// Totals to update on each commit. See [`RunLoader::counters`].
+    counters: Option<Arc<commit::Counters>>,
+
+    /// Switch to check before each commit. See [`RunLoader::load_shedding`].
+    load_shedding: Option<Arc<commit::LoadShedding>>,
+
+    /// Number of blob sequence points committed without their blobs. See
+    /// [`RunLoader::shed_blob_points`].
+    shed_blob_points: usize,
+
+    /// How long to keep points, by wall time. See [`RunLoader::retention`].
+    retention: Option<Duration>,
+
+    /// Points older than this are dropped, under `retention`. Set at the start of each load.
+    retention_cutoff: Option<WallTime>,
+
+    /// No staged or committed point is older than this, if set; sweeps for `retention` are
+    /// skipped while it's at or after the cutoff. May be too early, but never too late.
+    oldest_wall_time: Option<WallTime>,
+
+    /// Number of points dropped under `retention`. See [`RunLoader::expired_points`].
+    expired_points: usize,
+}
+
+/// Default maximum number of distinct tags per run. See [`RunLoader::max_tags`].
+pub const DEFAULT_MAX_TAGS: usize = 200_000;
+
+/// Default limit on plugin content length, in bytes. See [`RunLoader::max_plugin_content_len`].
+pub const DEFAULT_MAX_PLUGIN_CONTENT_LEN: usize = 1 << 20;
+
+/// Default number of times to retry a transient read error: none, so that a failing disk isn't
+/// read over and over. See [`RunLoader::read_retries`].
+pub const DEFAULT_READ_RETRIES: u32 = 0;
+
+/// Time to wait before the first retry of a read error. Each later retry waits twice as long as
+/// the one before, up to [`MAX_READ_RETRY_DOUBLINGS`] times.
+const READ_RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Limit on the doubling of [`READ_RETRY_BACKOFF`], so that no retry waits longer than 6.4s.
+const MAX_READ_RETRY_DOUBLINGS: u32 = 7;
+
+/// Whether an I/O error from reading an event file might go away if the read is retried. Network
+/// filesystems can fail reads with errors like `EIO` that succeed on retry, so any error not known
+/// to be permanent counts.
+fn is_retryable(e: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    !matches!(
+        e.kind(),
+        ErrorKind::NotFound
+            | ErrorKind::PermissionDenied
+            | ErrorKind::InvalidInput
+            | ErrorKind::InvalidData
+    )
+}
+
+/// Gets the earliest modification time of a run's event files, if the log directory reports any.
+fn earliest_modified(logdir: &impl Logdir, run: &Run) -> Option<WallTime> {
+    let version = logdir.run_version(run).ok()?;
+    let earliest = version.0.iter().filter_map(|&(_, _, mtime)| mtime).min()?;
+    let secs = earliest.duration_since(UNIX_EPOCH).ok()?.as_secs_f64();
+    WallTime::new(secs)
+}
+
+/// Gets the wall time before which points expire under a retention horizon, as of now.
+fn retention_cutoff(horizon: Duration) -> Option<WallTime> {
+    let now = std::time::SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs_f64();
+    WallTime::new(now - horizon.as_secs_f64())
+}
+
+/// Maximum number of rejected tags to remember per run. See [`RunLoader::rejected_tags`].
+const REJECTED_TAGS_SAMPLE_SIZE: usize = 20;
+
+/// Checks whether a time series from saved state is a run graph saved under its conventional tag,
+/// as by loaders from before synthesized time series had [reserved keys](reserved_key).
+fn is_legacy_run_graph(tag: &Tag, metadata: &pb::SummaryMetadata) -> bool {
+    tag.0 == GraphDefValue::TAG_NAME
+        && metadata.data_class == i32::from(pb::DataClass::BlobSequence)
+        && matches!(&metadata.plugin_data, Some(pd) if pd.plugin_name == plugin_names::GRAPHS)
+}
+
+/// Checks whether a summary value is a `simple_value`, which the scalar fast path handles. See
+/// [`RunLoader::scalar_fast_path`].
+fn is_simple_value(value: &pb::summary::Value) -> bool {
+    matches!(value.value, Some(pb::summary::value::Value::SimpleValue(_)))
+}
+
+/// A limit on the total number of time series across many runs, shared by their loaders. See
+/// [`RunLoader::tag_budget`].
+///
+/// Each time series counts against the budget from when its loader creates it until the loader
+/// forgets it (when the run is re-read from scratch, its tag is dropped, or the loader is
+/// dropped).
+#[derive(Debug)]
+pub struct TagBudget {
+    limit: usize,
+    used: AtomicUsize,
+    rejected: AtomicUsize,
+}
+
+impl TagBudget {
+    /// Creates a budget allowing up to `limit` time series.
+    pub fn new(limit: usize) -> Self {
+        TagBudget {
+            limit,
+            used: AtomicUsize::new(0),
+            rejected: AtomicUsize::new(0),
+        }
+    }
+
+    /// Gets the maximum number of time series.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Gets the number of time series currently counted against this budget.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Gets the number of values dropped so far because this budget was used up.
+    pub fn rejected(&self) -> usize {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Counts a new time series against the budget, if there's room.
+    fn try_reserve(&self) -> bool {
+        let limit = self.limit;
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                if n < limit {
+                    Some(n + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+
+    /// Counts `n` existing time series against the budget, even if that exceeds the limit.
+    fn add(&self, n: usize) {
+        self.used.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Stops counting `n` time series against the budget.
+    fn release(&self, n: usize) {
+        self.used.fetch_sub(n, Ordering::Relaxed);
+    }
+}
+
+/// Record of values rejected because their run already had [`RunLoader::max_tags`] tags, or
+/// because its [`TagBudget`] was used up.
+#[derive(Debug, Default)]
+struct TagOverflow {
+    /// Number of values rejected.
+    values: usize,
+    /// The first few distinct tags rejected, in the order first seen.
+    sample: Vec<Tag>,
+    /// Whether we've already logged a warning about this run.
+    warned: bool,
+}
+
+/// Events whose wall times are closer than this many seconds to the epoch are considered to have
+/// zero wall times. See [`ZeroWallTime`].
+pub const ZERO_WALL_TIME_EPSILON: f64 = 1.0;
+
+/// How to handle events whose wall time is zero, or within [`ZERO_WALL_TIME_EPSILON`] of it.
+///
+/// Some event writers are known to emit `wall_time = 0.0` for the first event in a file. Since
+/// the run's start time is the earliest wall time seen, one such event moves the start of the
+/// whole run back to 1970, which breaks relative-time views.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ZeroWallTime {
+    /// Accept the event as is. This is the default.
+    Keep,
+    /// Drop the event.
+    Drop,
+    /// Use the wall time of the previous event in the run instead. If there is no previous event
+    /// with a valid wall time, drop the event.
+    Inherit,
+}
+
+#[allow(clippy::derivable_impls)] // deriving needs `#[default]` (Rust 1.62)
+impl Default for ZeroWallTime {
+    fn default() -> Self {
+        ZeroWallTime::Keep
+    }
+}
+
+/// Maximum number of points to keep in each time series, by data class.
+///
+/// Points beyond these limits are reservoir-sampled; see [`StageReservoir`]. Time series of other
+/// data classes keep no points. Tensor points in particular aren't committed, so only the metadata
+/// of tensor time series is kept; see [`commit::RunData::tensor_metadata`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SampleSizes {
+    pub scalars: usize,
+    pub blob_sequences: usize,
+}
+
+impl Default for SampleSizes {
+    fn default() -> Self {
+        SampleSizes {
+            scalars: 1000,
+            blob_sequences: 10,
+        }
+    }
+}
+
+impl SampleSizes {
+    /// Sample sizes that keep every point of every scalar and blob sequence time series,
+    /// so that loads are exact rather than sampled. Points are still preempted by later points at
+    /// the same or earlier steps (see [`StageReservoir`]).
+    ///
+    /// Memory use then grows with the size of the log directory rather than with the number of
+    /// time series, so this is meant for tests and small log directories.
+    pub fn unlimited() -> Self {
+        SampleSizes {
+            scalars: usize::MAX,
+            blob_sequences: usize::MAX,
+        }
+    }
+
+    /// Reservoir capacity for time series of the given data class.
+    fn capacity(&self, data_class: pb::DataClass) -> usize {
+        match data_class {
+            pb::DataClass::Scalar => self.scalars,
+            pb::DataClass::BlobSequence => self.blob_sequences,
+            _ => 0,
+        }
+    }
+}
+
+/// How to handle events whose step is negative.
+///
+/// Some writers emit events at step `-1` as a sentinel for "no step". Since a record preempts all
+/// records at steps not smaller than its own (see [`StageReservoir`]), a stray event at step `-1`
+/// discards everything that came before it in its time series.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NegativeStepPolicy {
+    /// Accept the event at its negative step. This is the default.
+    Keep,
+    /// Drop the event. Dropped events are counted in [`RunLoader::negative_step_events_dropped`].
+    Drop,
+    /// Treat the event as if it were at step `0`.
+    ClampToZero,
+}
+
+#[allow(clippy::derivable_impls)] // deriving needs `#[default]` (Rust 1.62)
+impl Default for NegativeStepPolicy {
+    fn default() -> Self {
+        NegativeStepPolicy::Keep
+    }
+}
+
+/// How to handle an event whose summary has more than one value with the same tag.
+///
+/// Some writers accumulate values this way. Since the values share a step, each one preempts the
+/// one before it in the reservoir, but once the reservoir is full each one also evicts a random
+/// older point, which skews sampling for the rest of the time series.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DuplicateTagPolicy {
+    /// Keep only the last value for each tag. This is the default.
+    KeepLast,
+    /// Keep only the first value for each tag.
+    KeepFirst,
+    /// Offer every value to the reservoir, in order.
+    KeepAll,
+}
+
+#[allow(clippy::derivable_impls)] // deriving needs `#[default]` (Rust 1.62)
+impl Default for DuplicateTagPolicy {
+    fn default() -> Self {
+        DuplicateTagPolicy::KeepLast
+    }
+}
+
+impl DuplicateTagPolicy {
+    /// Like [`Self::apply`], but also returns the index in the original `values` of each value
+    /// kept, for [`RunLoader::blob_references`].
+    fn apply_tracking(self, values: &mut Vec<pb::summary::Value>) -> (usize, Vec<u32>) {
+        if values.len() < 2 || self == DuplicateTagPolicy::KeepAll {
+            return (0, (0..values.len() as u32).collect());
+        }
+        let keep = self.keep_mask(values);
+        let positions = (0..keep.len() as u32)
+            .filter(|&i| keep[i as usize])
+            .collect();
+        (Self::retain(values, keep), positions)
+    }
+
+    /// Removes values from `values` whose tags repeat, per this policy, and returns the number
+    /// removed. Tags are compared before any tag rewriting.
+    fn apply(self, values: &mut Vec<pb::summary::Value>) -> usize {
+        // Most events have a single value, and need no allocation. Loggers that batch every
+        // scalar for a step can write hundreds of values per event, so the rest are checked in
+        // linear time.
+        if values.len() < 2 || self == DuplicateTagPolicy::KeepAll {
+            return 0;
+        }
+        let keep = self.keep_mask(values);
+        Self::retain(values, keep)
+    }
+
+    /// Marks which of `values` to keep: the first or the last with each tag, per this policy.
+    fn keep_mask(self, values: &[pb::summary::Value]) -> Vec<bool> {
+        let n = values.len();
+        let mut keep = vec![false; n];
+        let mut seen = HashSet::with_capacity(n);
+        for j in 0..n {
+            let i = match self {
+                DuplicateTagPolicy::KeepFirst => j,
+                _ => n - 1 - j,
+            };
+            keep[i] = seen.insert(values[i].tag.as_str());
+        }
+        keep
+    }
+
+    /// Keeps the values marked in `keep`, and returns the number removed.
+    fn retain(values: &mut Vec<pb::summary::Value>, keep: Vec<bool>) -> usize {
+        let n = values.len();
+        let mut keep = keep.into_iter();
+        values.retain(|_| keep.next().unwrap());
+        n - values.len()
+    }
+}
+
+/// Where to keep `tagged_run_metadata` events, which are tagged like summary values.
+///
+/// Writers name run metadata by step, like `step0000`, so a summary tag of the same name is
+/// unlikely but possible. Since a tag names a single time series in a run, whichever kind of
+/// value comes first takes the time series, and later values of the other kind are lost.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RunMetadataTags {
+    /// Use the tag as written, sharing a namespace with summary tags. This is the default, and
+    /// is what the graphs plugin expects.
+    Shared,
+    /// Prepend [`TaggedRunMetadataValue::TAG_PREFIX`] to the tag, after any tag rewriting, so
+    /// that run metadata never collides with a summary tag.
+    Prefixed,
+}
+
+#[allow(clippy::derivable_impls)] // deriving needs `#[default]` (Rust 1.62)
+impl Default for RunMetadataTags {
+    fn default() -> Self {
+        RunMetadataTags::Shared
+    }
+}
+
+/// Order in which a [`RunLoader`] reads a run's event files on each reload.
+///
+/// Points are sampled in the order they're read, so files should be read oldest first.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventFileOrder {
+    /// By path. This is the default, and suits the usual timestamped event file names.
+    Name,
+    /// For log-rotation names like `tfevents.log`, `tfevents.log.1`, and `tfevents.log.2`, where
+    /// the highest-numbered file is the oldest and the unnumbered file is being written. Files
+    /// with the same name up to a numeric extension (and any [`GZIP_EVENT_FILE_SUFFIX`]) form a
+    /// set, read from the highest number down and then the unnumbered file. Sets are read in
+    /// order by name.
+    ///
+    /// Timestamped names like `tfevents.123` look like rotated files here, so only use this
+    /// order for runs written with rotation-style names.
+    ///
+    /// [`GZIP_EVENT_FILE_SUFFIX`]: crate::logdir::GZIP_EVENT_FILE_SUFFIX
+    Rotation,
+}
+
+#[allow(clippy::derivable_impls)] // deriving needs `#[default]` (Rust 1.62)
+impl Default for EventFileOrder {
+    fn default() -> Self {
+        EventFileOrder::Name
+    }
+}
+
+impl FromStr for EventFileOrder {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(EventFileOrder::Name),
+            "rotation" => Ok(EventFileOrder::Rotation),
+            _ => Err(format!(
+                "unknown event file order {:?}; expected \"name\" or \"rotation\"",
+                s
+            )),
+        }
+    }
+}
+
+/// What a [`RunLoader`] does with event files over its [limit](RunLoader::max_files).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExpiredFilePolicy {
+    /// Keep each expired file in the loader's file set, as dead with [`DeadReason::Expired`].
+    /// This is the default. Expired files are never read again, but each still costs a little
+    /// memory and a step of every reload.
+    MarkDead,
+    /// Remove expired files from the loader's file set, remembering only the newest expired
+    /// name. Files named up to that one are then never opened, even if they're new.
+    Forget,
+}
+
+#[allow(clippy::derivable_impls)] // deriving needs `#[default]` (Rust 1.62)
+impl Default for ExpiredFilePolicy {
+    fn default() -> Self {
+        ExpiredFilePolicy::MarkDead
+    }
+}
+
+impl FromStr for ExpiredFilePolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mark-dead" => Ok(ExpiredFilePolicy::MarkDead),
+            "forget" => Ok(ExpiredFilePolicy::Forget),
+            _ => Err(format!(
+                "unknown expired file policy {:?}; expected \"mark-dead\" or \"forget\"",
+                s
+            )),
+        }
+    }
+}
+
+/// Sort key for an event file under [`EventFileOrder::Rotation`]: the directory and name of its
+/// rotation set, then whether it's the current (unnumbered) file, then its number, descending.
+fn rotation_key(path: &Path) -> (Option<&Path>, String, bool, std::cmp::Reverse<u64>) {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let name = name.strip_suffix(GZIP_EVENT_FILE_SUFFIX).unwrap_or(&name);
+    let rotated = name.rfind('.').and_then(|i| {
+        let digits = &name[i + 1..];
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some((&name[..i], digits.parse::<u64>().ok()?))
+    });
+    match rotated {
+        Some((set, n)) => (path.parent(), set.to_string(), false, std::cmp::Reverse(n)),
+        None => (path.parent(), name.to_string(), true, std::cmp::Reverse(0)),
+    }
+}
+
+/// A user-supplied function to rewrite tags at load time. Returning `None` drops the value.
+struct TagRewriter(Box<TagRewriteFn>);
+type TagRewriteFn = dyn FnMut(&Tag) -> Option<Tag> + Send;
+
+impl std::fmt::Debug for TagRewriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TagRewriter")
+            .field(&format_args!("_"))
+            .finish()
+    }
+}
+
+/// A user-supplied function to rewrite events as they are read. Returning `None` drops the event.
+struct EventInterceptor(Box<EventInterceptFn>);
+type EventInterceptFn = dyn FnMut(pb::Event) -> Option<pb::Event> + Send;
+
+impl std::fmt::Debug for EventInterceptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("EventInterceptor")
+            .field(&format_args!("_"))
+            .finish()
+    }
 }
 
 #[derive(Debug)]
@@ -86,6 +837,11 @@ struct StageTimeSeries {
     data_class: pb::DataClass,
     metadata: Box<pb::SummaryMetadata>,
     rsv: StageReservoir<StageValue>,
+    /// Whether `metadata` was inferred from a first value that had none, so that metadata on a
+    /// later value may replace it. See [`Self::upgrade_metadata`].
+    metadata_inferred: bool,
+    /// Whether `metadata` has changed since the last commit.
+    metadata_changed: bool,
 }
 
 /// A value staged in the reservoir.
@@ -96,66 +852,620 @@ struct StageTimeSeries {
 struct StageValue {
     wall_time: WallTime,
     payload: EventValue,
+    /// Where this value came from, in provenance or blob reference mode.
+    source: Option<commit::Provenance>,
+    /// Where this value is within its event record, in blob reference mode.
+    position: Option<RecordPosition>,
+}
+
+/// Where a staged value is within its event record. See [`RunLoader::blob_references`].
+#[derive(Debug, Copy, Clone)]
+struct RecordPosition {
+    /// Length of the event record, in bytes.
+    length: u64,
+    /// Index of the value among the event's summary values, as written.
+    value: u32,
+}
+
+/// Error that aborts a load in [strict mode](RunLoader::strict).
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+    /// An event file could not be opened.
+    #[error("failed to open event file {}: {source}", .path.display())]
+    Open {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// An event file could not be read from the offset saved for it.
+    #[error("failed to resume event file {} at offset {offset}: {source}", .path.display())]
+    Resume {
+        path: PathBuf,
+        offset: u64,
+        source: std::io::Error,
+    },
+    /// The record at `offset` could not be read, or is corrupt.
+    #[error("failed to read event file {} at offset {offset}: {source}", .path.display())]
+    Read {
+        path: PathBuf,
+        offset: u64,
+        source: ReadEventError,
+    },
+    /// The event at `offset` was read, but had to be dropped.
+    #[error("dropped event in {} at offset {offset}: {reason}", .path.display())]
+    DroppedEvent {
+        path: PathBuf,
+        offset: u64,
+        reason: String,
+    },
+}
+
+/// Error restoring a [`RunLoader`] from saved state. See [`RunLoader::restore_state`].
+#[derive(Debug, thiserror::Error)]
+pub enum RestoreStateError {
+    /// The state could not be decoded at all.
+    #[error("failed to decode loader state: {0}")]
+    Decode(#[from] prost::DecodeError),
+    /// The state was written by a server with an incompatible format.
+    #[error(
+        "unsupported loader state version {0} (expected {})",
+        loader_state::VERSION
+    )]
+    UnsupportedVersion(u32),
+    /// The state decoded, but its contents are inconsistent.
+    #[error("invalid loader state: {0}")]
+    Invalid(String),
 }
 
 impl StageTimeSeries {
-    fn new(metadata: Box<pb::SummaryMetadata>) -> Self {
+    fn new(
+        metadata: Box<pb::SummaryMetadata>,
+        sample_sizes: SampleSizes,
+        duplicate_steps: DuplicateStepPolicy,
+    ) -> Self {
         let data_class =
             pb::DataClass::from_i32(metadata.data_class).unwrap_or(pb::DataClass::Unknown);
-        let capacity = match data_class {
-            pb::DataClass::Scalar => 1000,
-            pb::DataClass::Tensor => 100,
-            pb::DataClass::BlobSequence => 10,
-            _ => 0,
-        };
+        let mut rsv = StageReservoir::new(sample_sizes.capacity(data_class));
+        rsv.duplicate_steps(duplicate_steps);
         Self {
             data_class,
             metadata,
-            rsv: StageReservoir::new(capacity),
+            rsv,
+            metadata_inferred: false,
+            metadata_changed: false,
+        }
+    }
+
+    /// Replaces metadata inferred from the first value of this time series with metadata that
+    /// arrived on a later value, so that its display name, description, and plugin content are
+    /// kept. This happens at most once per time series, and only if the new metadata has the same
+    /// data class: the data class decides how points were staged, so it can't change.
+    fn upgrade_metadata(
+        &mut self,
+        tag: &Tag,
+        value: &SummaryValue,
+        md: pb::SummaryMetadata,
+        overrides: &MetadataOverrides,
+    ) {
+        self.metadata_inferred = false;
+        let mut md = value.initial_metadata(Some(md));
+        overrides.apply(tag, &mut md);
+        if md.data_class == self.metadata.data_class && md != self.metadata {
+            self.metadata = md;
+            self.metadata_changed = true;
         }
     }
 
-    /// Writes all staged data for this time series into the commit.
-    fn commit(&mut self, tag: &Tag, run: &mut commit::RunData) {
+    /// Writes all staged data for this time series into the commit, along with the provenance of
+    /// each new point if `provenance` is set. Scalars are rounded to `scalar_precision`. If
+    /// `blob_files` is set, blob sequence points are committed as references into those event
+    /// files, indexed by [`commit::FileId`] and read with the given framing. Otherwise,
+    /// if `shed_blobs` is set, blob sequence points are committed as data loss; returns how many.
+    fn commit(
+        &mut self,
+        tag: &Tag,
+        run: &mut commit::RunData,
+        provenance: bool,
+        scalar_precision: ScalarPrecision,
+        blob_files: Option<(&[PathBuf], Framing)>,
+        shed_blobs: bool,
+    ) -> usize {
         use pb::DataClass;
         match self.data_class {
-            DataClass::Scalar => self.commit_to(tag, &mut run.scalars, |ev, _| ev.into_scalar()),
+            DataClass::Scalar => {
+                let old_len = run
+                    .scalars
+                    .get(tag)
+                    .map_or(0, |ts| ts.basin.as_slice().len());
+                let added = self.commit_to(tag, &mut run.scalars, provenance, |sv, _| {
+                    Ok(sv.payload.into_scalar()?.with_precision(scalar_precision))
+                });
+                if let Some(ts) = run.scalars.get_mut(tag) {
+                    match added {
+                        Some(added) => ts.note_committed(old_len, added),
+                        None => ts.refresh_value_range(),
+                    }
+                }
+            }
             DataClass::Tensor => {
-                warn!(
-                    "Tensor time series not yet supported (tag: {:?}, plugin: {:?})",
-                    tag.0,
-                    self.metadata
-                        .plugin_data
-                        .as_ref()
-                        .map(|p| p.plugin_name.as_str())
-                        .unwrap_or("")
-                );
+                // Tensor points aren't committed, only the time series' metadata.
+                let metadata = (run.tensor_metadata.entry(tag.clone()))
+                    .or_insert_with(|| self.metadata.clone());
+                if std::mem::take(&mut self.metadata_changed) {
+                    *metadata = self.metadata.clone();
+                }
             }
-            DataClass::BlobSequence => {
-                self.commit_to(tag, &mut run.blob_sequences, EventValue::into_blob_sequence)
+            DataClass::BlobSequence if shed_blobs && blob_files.is_none() => {
+                let mut shed = 0;
+                self.commit_to(tag, &mut run.blob_sequences, provenance, |_, _| {
+                    shed += 1;
+                    Err(commit::DataLoss)
+                });
+                return shed;
             }
+            DataClass::BlobSequence => match blob_files {
+                Some((files, framing)) => {
+                    self.commit_to(tag, &mut run.blob_references, provenance, |sv, metadata| {
+                        // Values restored from saved state aren't located, so can't be referenced.
+                        let (source, position) = match (sv.source, sv.position) {
+                            (Some(source), Some(position)) => (source, position),
+                            _ => return Err(commit::DataLoss),
+                        };
+                        let file = files.get(source.file as usize).ok_or(commit::DataLoss)?;
+                        let blobs = sv.payload.into_blob_sequence(metadata)?;
+                        Ok(commit::BlobReference {
+                            file: EventFileBuf(file.clone()),
+                            offset: source.offset,
+                            length: position.length,
+                            value: position.value,
+                            count: blobs.0.len(),
+                            framing,
+                        })
+                    });
+                }
+                None => {
+                    self.commit_to(tag, &mut run.blob_sequences, provenance, |sv, metadata| {
+                        sv.payload.into_blob_sequence(metadata)
+                    });
+                }
+            },
             _ => (),
         };
+        0
+    }
+
+    /// Removes committed points older than `cutoff` from this time series, in both the reservoir
+    /// and `run`. Returns how many, along with the wall time of the oldest point left, staged or
+    /// committed.
+    fn expire(
+        &mut self,
+        tag: &Tag,
+        run: &mut commit::RunData,
+        cutoff: WallTime,
+    ) -> (usize, Option<WallTime>) {
+        use pb::DataClass;
+        let (expired, oldest) = match self.data_class {
+            DataClass::Scalar => {
+                let result = self.expire_from(tag, &mut run.scalars, cutoff);
+                if let (Some(ts), true) = (run.scalars.get_mut(tag), result.0 > 0) {
+                    ts.refresh_value_range();
+                }
+                result
+            }
+            DataClass::BlobSequence => self.expire_from(tag, &mut run.blob_sequences, cutoff),
+            _ => (0, None),
+        };
+        let staged = self.rsv.staged_items().iter().map(|(_, sv)| sv.wall_time);
+        (expired, oldest.into_iter().chain(staged).min())
+    }
+
+    /// Helper for `expire`: expires points of a statically known data class.
+    fn expire_from<V>(
+        &mut self,
+        tag: &Tag,
+        store: &mut commit::TagStore<V>,
+        cutoff: WallTime,
+    ) -> (usize, Option<WallTime>) {
+        let ts = match store.get_mut(tag) {
+            None => return (0, None),
+            Some(ts) => ts,
+        };
+        let expired = self
+            .rsv
+            .evict_committed(&mut ts.basin, |(wall_time, _)| *wall_time < cutoff);
+        if expired > 0 {
+            let points = ts.basin.as_slice();
+            if let Some(provenance) = &mut ts.provenance {
+                provenance
+                    .retain(|(step, _)| points.binary_search_by_key(step, |(s, _)| *s).is_ok());
+            }
+            compact_if_sparse(ts);
+        }
+        let oldest = ts.basin.as_slice().iter().map(|(_, (wt, _))| *wt).min();
+        (expired, oldest)
     }
 
     /// Helper for `commit`: writes staged data for this time series into storage for a statically
-    /// known data class.
-    fn commit_to<V, F: FnMut(EventValue, &pb::SummaryMetadata) -> Result<V, commit::DataLoss>>(
+    /// known data class. Returns the number of points added if they're all at the end of the
+    /// basin, or `None` if some were inserted among the old points (see
+    /// [`RunLoader::out_of_order_tolerance`]).
+    fn commit_to<V, F: FnMut(StageValue, &pb::SummaryMetadata) -> Result<V, commit::DataLoss>>(
         &mut self,
         tag: &Tag,
         store: &mut commit::TagStore<V>,
+        provenance: bool,
         mut enrich: F,
-    ) {
+    ) -> Option<usize> {
         let commit_ts = store
             .entry(tag.clone())
             .or_insert_with(|| commit::TimeSeries::new(self.metadata.clone()));
+        if std::mem::take(&mut self.metadata_changed) {
+            commit_ts.metadata = self.metadata.clone();
+        }
+        commit_ts.offered = self.rsv.seen();
         let metadata = self.metadata.as_ref();
-        self.rsv
-            .commit_map(&mut commit_ts.basin, |StageValue { wall_time, payload }| {
-                (wall_time, enrich(payload, metadata))
-            });
-    }
-}
+        if !provenance {
+            let mut added = 0;
+            let appended = self.rsv.commit_map_resolving(
+                &mut commit_ts.basin,
+                |sv| {
+                    added += 1;
+                    (sv.wall_time, enrich(sv, metadata))
+                },
+                |(wall_time, _)| *wall_time,
+            );
+            compact_if_sparse(commit_ts);
+            return Some(added).filter(|_| appended);
+        }
+
+        let new_steps: Vec<Step> = self.rsv.staged_items().iter().map(|(s, _)| *s).collect();
+        let mut sources = Vec::new();
+        let appended = self.rsv.commit_map_resolving(
+            &mut commit_ts.basin,
+            |sv| {
+                sources.push(sv.source);
+                (sv.wall_time, enrich(sv, metadata))
+            },
+            |(wall_time, _)| *wall_time,
+        );
+        let added_count = sources.len();
+        let points = commit_ts.basin.as_slice();
+        let provenance = commit_ts.provenance.get_or_insert_with(Vec::new);
+        let added = new_steps
+            .iter()
+            .zip(sources)
+            .filter_map(|(step, source)| Some((*step, source?)));
+        if !appended {
+            // New points are among the old ones, some of which they may have replaced.
+            provenance.retain(|(step, _)| {
+                new_steps.binary_search(step).is_err()
+                    && points.binary_search_by_key(step, |(s, _)| *s).is_ok()
+            });
+            provenance.extend(added);
+            provenance.sort_by_key(|(step, _)| *step);
+            compact_if_sparse(commit_ts);
+            return None;
+        }
+        // New points are appended to the basin, after whichever old points were kept.
+        let kept = &points[..points.len() - added_count];
+        let mut kept_steps = kept.iter().map(|(step, _)| *step).peekable();
+        provenance.retain(|(step, _)| {
+            while matches!(kept_steps.peek(), Some(s) if s < step) {
+                kept_steps.next();
+            }
+            kept_steps.peek() == Some(step)
+        });
+        provenance.extend(added);
+        compact_if_sparse(commit_ts);
+        Some(added_count)
+    }
+}
+
+impl StageTimeSeries {
+    /// Saves the state of this time series for [`RunLoader::save_state`], including the points
+    /// that it has committed to `run_data`.
+    fn state(&self, tag: &Tag, run_data: &commit::RunData) -> loader_state::TimeSeriesState {
+        use pb::DataClass;
+        let steps = self.rsv.committed_steps();
+        let committed = match self.data_class {
+            DataClass::Scalar => committed_points(run_data.scalars.get(tag), steps, |v| {
+                match v.to_f32_exact() {
+                    Some(x) => point::Value::Scalar(x),
+                    None => point::Value::ScalarDouble(v.0),
+                }
+            }),
+            DataClass::BlobSequence => {
+                committed_points(run_data.blob_sequences.get(tag), steps, |v| {
+                    point::Value::BlobSequence(loader_state::BlobSequence { blobs: v.0.clone() })
+                })
+            }
+            _ => Vec::new(),
+        };
+        let staged = self
+            .rsv
+            .staged_items()
+            .iter()
+            .map(|(step, sv)| loader_state::Point {
+                step: step.0,
+                wall_time: sv.wall_time.into(),
+                value: Some(staged_value(&sv.payload)),
+            })
+            .collect();
+        loader_state::TimeSeriesState {
+            tag: tag.0.clone(),
+            metadata: Some((*self.metadata).clone()),
+            seen: self.rsv.seen() as u64,
+            rng_position: self.rsv.rng_position().to_le_bytes().to_vec(),
+            committed,
+            staged,
+        }
+    }
+
+    /// Restores a time series saved by [`Self::state`]. Also returns its committed points, to be
+    /// written to the commit.
+    fn restore(
+        state: loader_state::TimeSeriesState,
+        sample_sizes: SampleSizes,
+        duplicate_steps: DuplicateStepPolicy,
+    ) -> Result<(Self, CommittedPoints), RestoreStateError> {
+        use std::convert::TryInto;
+        let tag = state.tag;
+        let metadata = Box::new(state.metadata.unwrap_or_default());
+        let mut ts = StageTimeSeries::new(metadata, sample_sizes, duplicate_steps);
+        let committed_steps = state.committed.iter().map(|p| Step(p.step)).collect();
+        let committed = CommittedPoints::restore(ts.data_class, state.committed)?;
+        let staged = state
+            .staged
+            .into_iter()
+            .map(|p| {
+                let step = Step(p.step);
+                let wall_time = restore_wall_time(p.wall_time)?;
+                let payload = p.value.and_then(staged_payload).ok_or_else(|| {
+                    invalid(format!("bad staged value at step {} of {:?}", step.0, tag))
+                })?;
+                // Provenance isn't saved, so restored points have none.
+                let (source, position) = (None, None);
+                Ok((
+                    step,
+                    StageValue {
+                        wall_time,
+                        payload,
+                        source,
+                        position,
+                    },
+                ))
+            })
+            .collect::<Result<Vec<_>, RestoreStateError>>()?;
+        let rng_position: [u8; 16] = state.rng_position[..]
+            .try_into()
+            .map_err(|_| invalid(format!("bad RNG position for {:?}", tag)))?;
+        ts.rsv = StageReservoir::resume(
+            sample_sizes.capacity(ts.data_class),
+            committed_steps,
+            staged,
+            state.seen as usize,
+            u128::from_le_bytes(rng_position),
+            Some(duplicate_steps),
+        )
+        .ok_or_else(|| invalid(format!("inconsistent reservoir for {:?}", tag)))?;
+        Ok((ts, committed))
+    }
+}
+
+/// Compacts a committed time series if its basin has more than twice the room that it needs,
+/// which happens only once many of its points have been preempted or evicted: ordinary growth
+/// never over-allocates that much.
+fn compact_if_sparse<V>(ts: &mut commit::TimeSeries<V>) {
+    if ts.basin.capacity() > 2 * ts.basin.as_slice().len() {
+        ts.compact();
+    }
+}
+
+/// Gets the points of a committed time series whose steps are in `steps`, which must be sorted.
+///
+/// The basin may have extra points that have since been evicted from the reservoir; these are
+/// skipped, just as the next commit would drop them.
+fn committed_points<V>(
+    ts: Option<&commit::TimeSeries<V>>,
+    steps: &[Step],
+    mut f: impl FnMut(&V) -> point::Value,
+) -> Vec<loader_state::Point> {
+    let basin = match ts {
+        None => return Vec::new(),
+        Some(ts) => ts.basin.as_slice(),
+    };
+    let mut steps = steps.iter().peekable();
+    basin
+        .iter()
+        .filter(|(step, _)| match steps.peek() {
+            Some(s) if *s == step => {
+                steps.next();
+                true
+            }
+            _ => false,
+        })
+        .map(|(step, (wall_time, v))| loader_state::Point {
+            step: step.0,
+            wall_time: (*wall_time).into(),
+            value: Some(match v {
+                Ok(v) => f(v),
+                Err(commit::DataLoss) => point::Value::DataLoss(true),
+            }),
+        })
+        .collect()
+}
+
+/// Converts a staged event value to its saved form.
+fn staged_value(payload: &EventValue) -> point::Value {
+    match payload {
+        EventValue::GraphDef(GraphDefValue(bytes)) => point::Value::GraphDef(bytes.clone()),
+        EventValue::TaggedRunMetadata(TaggedRunMetadataValue(bytes)) => {
+            point::Value::TaggedRunMetadata(bytes.clone())
+        }
+        EventValue::Summary(SummaryValue(value)) => point::Value::Summary(pb::summary::Value {
+            value: Some((**value).clone()),
+            ..Default::default()
+        }),
+        EventValue::SimpleValue(f) => point::Value::Summary(pb::summary::Value {
+            value: Some(pb::summary::value::Value::SimpleValue(*f)),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Converts a saved staged value back to an event value, or returns `None` if it's not a staged
+/// value.
+fn staged_payload(value: point::Value) -> Option<EventValue> {
+    match value {
+        point::Value::GraphDef(bytes) => Some(EventValue::GraphDef(GraphDefValue(bytes))),
+        point::Value::TaggedRunMetadata(bytes) => {
+            Some(EventValue::TaggedRunMetadata(TaggedRunMetadataValue(bytes)))
+        }
+        point::Value::Summary(pb::summary::Value {
+            value: Some(value), ..
+        }) => Some(EventValue::Summary(SummaryValue(Box::new(value)))),
+        _ => None,
+    }
+}
+
+fn restore_wall_time(t: f64) -> Result<WallTime, RestoreStateError> {
+    WallTime::new(t).ok_or_else(|| invalid(format!("invalid wall time {}", t)))
+}
+
+fn invalid(message: String) -> RestoreStateError {
+    RestoreStateError::Invalid(message)
+}
+
+/// Converts a [`DeadReason`] to its saved form. The error kind of [`DeadReason::OpenFailed`]
+/// isn't saved, and [`DeadReason::Unrecorded`] is saved as no reason at all.
+fn save_dead_reason(reason: DeadReason) -> Option<file_state::DeadReason> {
+    Some(match reason {
+        DeadReason::OpenFailed(_) => file_state::DeadReason::OpenFailed(true),
+        DeadReason::ReadError { offset } => file_state::DeadReason::ReadError(offset),
+        DeadReason::BadChecksum { offset } => file_state::DeadReason::BadChecksum(offset),
+        DeadReason::RetriesExhausted { offset } => file_state::DeadReason::RetriesExhausted(offset),
+        DeadReason::RemovedFromDisk => file_state::DeadReason::RemovedFromDisk(true),
+        DeadReason::Expired => file_state::DeadReason::Expired(true),
+        DeadReason::Unrecorded => return None,
+    })
+}
+
+/// Converts a saved dead reason back to a [`DeadReason`].
+fn restore_dead_reason(reason: Option<file_state::DeadReason>) -> DeadReason {
+    match reason {
+        Some(file_state::DeadReason::OpenFailed(_)) => {
+            DeadReason::OpenFailed(std::io::ErrorKind::Other)
+        }
+        Some(file_state::DeadReason::ReadError(offset)) => DeadReason::ReadError { offset },
+        Some(file_state::DeadReason::BadChecksum(offset)) => DeadReason::BadChecksum { offset },
+        Some(file_state::DeadReason::RetriesExhausted(offset)) => {
+            DeadReason::RetriesExhausted { offset }
+        }
+        Some(file_state::DeadReason::RemovedFromDisk(_)) => DeadReason::RemovedFromDisk,
+        Some(file_state::DeadReason::Expired(_)) => DeadReason::Expired,
+        None => DeadReason::Unrecorded,
+    }
+}
+
+/// A committed point as stored in a [`commit::TimeSeries`] basin.
+type CommittedPoint<V> = (Step, (WallTime, Result<V, commit::DataLoss>));
+
+/// Committed points of a restored time series, to be written to the commit.
+enum CommittedPoints {
+    Scalars(Vec<CommittedPoint<commit::ScalarValue>>),
+    /// The time series is a tensor time series, of which only the metadata is committed.
+    TensorMetadata,
+    BlobSequences(Vec<CommittedPoint<commit::BlobSequenceValue>>),
+    /// The time series has a data class that is never committed.
+    None,
+}
+
+impl CommittedPoints {
+    /// Converts saved committed points to the given data class.
+    fn restore(
+        data_class: pb::DataClass,
+        points: Vec<loader_state::Point>,
+    ) -> Result<Self, RestoreStateError> {
+        use pb::DataClass;
+        Ok(match data_class {
+            DataClass::Scalar => CommittedPoints::Scalars(restore_points(points, |v| match v {
+                point::Value::Scalar(x) => Some(commit::ScalarValue(f64::from(x))),
+                point::Value::ScalarDouble(x) => Some(commit::ScalarValue(x)),
+                _ => None,
+            })?),
+            DataClass::Tensor => CommittedPoints::TensorMetadata,
+            DataClass::BlobSequence => {
+                CommittedPoints::BlobSequences(restore_points(points, |v| match v {
+                    point::Value::BlobSequence(b) => Some(commit::BlobSequenceValue(b.blobs)),
+                    _ => None,
+                })?)
+            }
+            _ => CommittedPoints::None,
+        })
+    }
+
+    /// Writes these points to the given run as the entire contents of the time series `tag`,
+    /// out of `offered` points offered to its reservoir. Does nothing if there are no points,
+    /// since then the time series may never have been committed.
+    fn commit(
+        self,
+        tag: Tag,
+        metadata: Box<pb::SummaryMetadata>,
+        offered: usize,
+        run: &mut commit::RunData,
+    ) {
+        fn insert<V>(
+            store: &mut commit::TagStore<V>,
+            tag: Tag,
+            metadata: Box<pb::SummaryMetadata>,
+            offered: usize,
+            points: Vec<CommittedPoint<V>>,
+        ) {
+            if points.is_empty() {
+                return;
+            }
+            let mut ts = commit::TimeSeries::new(metadata);
+            ts.basin = Basin::from(points);
+            ts.offered = offered;
+            store.insert(tag, ts);
+        }
+        match self {
+            CommittedPoints::Scalars(points) => {
+                insert(&mut run.scalars, tag.clone(), metadata, offered, points);
+                if let Some(ts) = run.scalars.get_mut(&tag) {
+                    ts.refresh_value_range();
+                }
+            }
+            CommittedPoints::TensorMetadata => {
+                run.tensor_metadata.insert(tag, metadata);
+            }
+            CommittedPoints::BlobSequences(points) => {
+                insert(&mut run.blob_sequences, tag, metadata, offered, points)
+            }
+            CommittedPoints::None => (),
+        }
+    }
+}
+
+/// Converts saved committed points with a conversion function for valid values.
+fn restore_points<V>(
+    points: Vec<loader_state::Point>,
+    f: impl Fn(point::Value) -> Option<V>,
+) -> Result<Vec<CommittedPoint<V>>, RestoreStateError> {
+    points
+        .into_iter()
+        .map(|p| {
+            let step = Step(p.step);
+            let wall_time = restore_wall_time(p.wall_time)?;
+            let bad_value = || invalid(format!("bad committed value at step {}", step.0));
+            let value = match p.value {
+                Some(point::Value::DataLoss(_)) => Err(commit::DataLoss),
+                Some(v) => Ok(f(v).ok_or_else(bad_value)?),
+                None => return Err(bad_value()),
+            };
+            Ok((step, (wall_time, value)))
+        })
+        .collect()
+}
 
 /// Minimum time to wait between committing while a run is still loading.
 const COMMIT_INTERVAL: Duration = Duration::from_secs(5);
@@ -165,247 +1475,1922 @@ impl<R: Read> RunLoader<R> {
         Self {
             run,
             files: BTreeMap::new(),
+            max_files: None,
+            expired_files: ExpiredFilePolicy::default(),
+            expired_through: None,
+            warned_file_limit: false,
             checksum: true,
-            data: RunLoaderData::default(),
+            skip_bad_checksums: false,
+            max_record_len: None,
+            read_retries: DEFAULT_READ_RETRIES,
+            reload_deadline: None,
+            paused: false,
+            preview_first_file: false,
+            file_order: EventFileOrder::default(),
+            skipped_files: 0,
+            file_activity: HashMap::new(),
+            resume_offsets: HashMap::new(),
+            checked_file_times: false,
+            data: RunLoaderData {
+                max_tags: DEFAULT_MAX_TAGS,
+                max_plugin_content_len: Some(DEFAULT_MAX_PLUGIN_CONTENT_LEN),
+                scalar_fast_path: true,
+                ..Default::default()
+            },
         }
     }
 
+    /// Sets the maximum number of event files to read in this run, or removes the limit if `None`
+    /// (the default). Some misbehaving writers start a new event file every few seconds, and
+    /// tracking tens of thousands of them slows every reload.
+    ///
+    /// On each reload, if the run has more event files than this, the oldest by name are expired
+    /// until it doesn't, skipping files that yielded records when last checked, since those are
+    /// probably still being written. Expired files are never opened again, and what's done with
+    /// them is up to the [policy](Self::expired_files). Their committed points are kept. A
+    /// warning is logged the first time the limit is exceeded.
+    pub fn max_files(&mut self, limit: Option<usize>) {
+        self.max_files = limit;
+    }
+
+    /// Sets what to do with event files expired over the [limit](Self::max_files). Defaults to
+    /// [`ExpiredFilePolicy::MarkDead`].
+    pub fn expired_files(&mut self, policy: ExpiredFilePolicy) {
+        self.expired_files = policy;
+    }
+
     /// Sets whether to compute checksums for records before parsing them as protos.
     pub fn checksum(&mut self, yes: bool) {
         self.checksum = yes;
     }
 
-    /// Loads new data given the current set of event files.
+    /// Sets whether to skip records whose data fails its checksum and keep reading the event
+    /// file, instead of abandoning the rest of the file (the default).
     ///
-    /// The provided filenames should correspond to the entire set of event files currently part of
-    /// this run.
+    /// Records are skipped individually, since the record framing is still intact: only the
+    /// record's payload is suspect. Each skipped record is logged as a warning.
+    pub fn skip_bad_checksums(&mut self, yes: bool) {
+        self.skip_bad_checksums = yes;
+    }
+
+    /// Sets the maximum data length of a record, in bytes, for event files opened after this
+    /// call. An event file is abandoned at the first record longer than this. `None` (the
+    /// default) means no limit.
+    pub fn max_record_len(&mut self, bytes: Option<u64>) {
+        self.max_record_len = bytes;
+    }
+
+    /// Sets how many times to retry reading an event record after an I/O error that may be
+    /// transient, waiting longer before each retry, before abandoning the rest of the event file.
+    /// Defaults to [`DEFAULT_READ_RETRIES`]. Errors that retrying can't fix, like bad checksums
+    /// or invalid protos, abandon the file at once. A retry that would wait past the
+    /// [deadline](Self::reload_deadline) instead ends the load early, and the next load retries.
+    pub fn read_retries(&mut self, retries: u32) {
+        self.read_retries = retries;
+    }
+
+    /// Sets a limit on how long each [reload](Self::reload) may spend reading events. Once it
+    /// passes, the reload commits what it has read and returns early, and the next reload picks
+    /// up where this one stopped. The limit is checked every 100 events, so a reload may run
+    /// somewhat past it. `None` (the default) means no limit.
+    pub fn reload_deadline(&mut self, deadline: Option<Duration>) {
+        self.reload_deadline = deadline;
+    }
+
+    /// Sets a function to rewrite the tag of each value before it is staged.
     ///
-    /// The given commit must have an entry for this run (the entry may be empty).
+    /// The function is called for every summary value and tagged run metadata event. If it returns
+    /// `Some(tag)`, the value is staged (and later committed) under the new tag; if it returns
+    /// `None`, the value is dropped. This can be used to namespace, rename, or filter time series.
+    /// Run-level graphs are always stored under the [reserved key](commit::reserved_key) for
+    /// [`GraphDefValue::TAG_NAME`] and are not passed to the rewriter.
     ///
-    /// # Panics
+    /// The rewriter should be a pure function of its input: it is applied anew to every value, and
+    /// values that map to the same tag are merged into the same time series.
+    pub fn tag_rewriter(&mut self, f: impl FnMut(&Tag) -> Option<Tag> + Send + 'static) {
+        self.data.tag_rewriter = Some(TagRewriter(Box::new(f)));
+    }
+
+    /// Sets a function to preprocess each event right after it is decoded, before any other
+    /// processing. If it returns `Some(event)`, that event is read in place of the original; if it
+    /// returns `None`, the event is dropped. This can be used to scrub tag names or to add computed
+    /// values to summaries.
     ///
-    /// If we need to access `run_data` but the lock is poisoned.
-    pub fn reload(
+    /// The interceptor sees events as written, before [tag rewriting](Self::tag_rewriter),
+    /// filtering, and step and wall time checks, and is also applied when [sniffing](Self::sniff)
+    /// and [ingesting](Self::ingest). Like the tag rewriter, it's applied anew to every event read,
+    /// including when the run is re-read from the start.
+    ///
+    /// In [blob reference mode](Self::blob_references), blob sequence values can only be
+    /// referenced in events that the interceptor returns unchanged, since references are read
+    /// back from the event file without it. Values of changed events are committed as
+    /// [`DataLoss`] points, which keep their steps and wall times. Each event is copied to tell
+    /// whether it changed.
+    ///
+    /// [`DataLoss`]: crate::commit::DataLoss
+    pub fn event_interceptor(
         &mut self,
-        logdir: &impl Logdir<File = R>,
-        filenames: Vec<EventFileBuf>,
-        run_data: &RwLock<commit::RunData>,
+        f: impl FnMut(pb::Event) -> Option<pb::Event> + Send + 'static,
     ) {
-        let run_name = self.run.0.clone();
-        debug!("Starting load for run {:?}", run_name);
-        let start = Instant::now();
-        self.update_file_set(logdir, filenames);
-        let mut n = 0;
-        let mut last_commit_time = Instant::now();
-        self.reload_files(|run_loader_data, event| {
-            run_loader_data.read_event(event);
-            n += 1;
-            // Reduce overhead of checking elapsed time by only doing it every 100 events.
-            if n % 100 == 0 && last_commit_time.elapsed() >= COMMIT_INTERVAL {
-                debug!(
-                    "Loaded {} events for run {:?} after {:?}",
-                    n,
-                    run_name,
-                    start.elapsed()
-                );
-                run_loader_data.commit_all(run_data);
-                last_commit_time = Instant::now();
-            }
-        });
-        self.data.commit_all(run_data);
-        debug!(
-            "Finished load for run {:?} ({:?})",
-            run_name,
-            start.elapsed()
-        );
+        self.data.event_interceptor = Some(EventInterceptor(Box::new(f)));
     }
 
-    /// Updates the active key set of `self.files` to match the given filenames.
+    /// Sets which tags to load. Values whose tag doesn't pass the filter are counted (see
+    /// [`Self::filtered_values`]) but never staged or committed, so they cost no memory. The
+    /// filter sees tags after any [rewriting](Self::tag_rewriter), and doesn't apply to run-level
+    /// graphs.
     ///
-    /// After this function returns, `self.files` may still have keys not in `filenames`, but they
-    /// will all map to [`EventFile::Dead`].
-    fn update_file_set(&mut self, logdir: &impl Logdir<File = R>, filenames: Vec<EventFileBuf>) {
-        // Remove any discarded files.
-        let new_file_set: HashSet<&EventFileBuf> = filenames.iter().collect();
-        for (k, v) in self.files.iter_mut() {
-            if !new_file_set.contains(k) {
-                *v = EventFile::Dead;
-            }
-        }
+    /// The filter is only checked for tags that don't have a time series yet. Changing it leaves
+    /// existing time series alone, even those whose tags no longer pass: they stay in the commit
+    /// and keep accepting new data. Tags that newly pass are loaded from the next value read; to
+    /// get their earlier values too, reload the run from the start.
+    pub fn tag_filter(&mut self, filter: TagFilter) {
+        self.data.tag_filter = filter;
+        self.data.filtered_tags.clear();
+    }
 
-        // Open readers for any new files.
-        for filename in filenames {
-            use std::collections::btree_map::Entry;
-            match self.files.entry(filename) {
-                Entry::Occupied(_) => {}
-                Entry::Vacant(v) => {
-                    let event_file = match logdir.open(v.key()) {
-                        Ok(file) => {
-                            let mut reader = EventFileReader::new(file);
-                            reader.checksum(self.checksum);
-                            EventFile::Active(reader)
-                        }
-                        // TODO(@wchargin): Improve error handling?
-                        Err(e) => {
-                            warn!("Failed to open event file {:?}: {:?}", v.key(), e);
-                            EventFile::Dead
-                        }
-                    };
-                    v.insert(event_file);
-                }
-            };
+    /// Number of values read so far that were dropped by the [tag filter](Self::tag_filter), for
+    /// [dropped tags](Self::drop_tag), or for tags with the [reserved prefix](RESERVED_TAG_PREFIX).
+    pub fn filtered_values(&self) -> usize {
+        self.data.filtered_values
+    }
+
+    /// Removes all data for `tag` from this run, both staged and in `run_data`, and ignores any
+    /// values for it read from now on, so that it doesn't come back on the next reload. Use this
+    /// to get rid of a known-bad time series without reloading the run. Returns whether the run
+    /// had any data for the tag.
+    ///
+    /// The tag stays dropped for the life of this loader, even if the run is re-read from the
+    /// start. Values ignored for it count toward [`Self::filtered_values`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `run_data` is poisoned.
+    pub fn drop_tag(&mut self, tag: &Tag, run_data: &RwLock<commit::RunData>) -> bool {
+        self.data.dropped_tags.insert(tag.clone());
+        let staged_before = self.data.time_series.len();
+        self.data
+            .time_series
+            .retain(|key, _| public_tag(key) != tag.0);
+        let staged = staged_before - self.data.time_series.len();
+        if let Some(budget) = &self.data.tag_budget {
+            budget.release(staged);
+        }
+        let mut run_data = run_data.write().expect("acquiring tags lock");
+        let committed = run_data.drop_tag(tag);
+        if let Some(counters) = &self.data.counters {
+            counters.recount(&mut run_data);
         }
+        staged > 0 || committed
     }
 
-    /// Reads data from all active event files, and calls a handler for each event.
-    fn reload_files<F: FnMut(&mut RunLoaderData, pb::Event)>(&mut self, mut handle_event: F) {
-        for (filename, ef) in self.files.iter_mut() {
-            let reader = match ef {
-                EventFile::Dead => continue,
-                EventFile::Active(reader) => reader,
-            };
+    /// Sets how to handle events whose wall time is at the epoch. See [`ZeroWallTime`].
+    pub fn zero_wall_time(&mut self, policy: ZeroWallTime) {
+        self.data.zero_wall_time = policy;
+    }
 
-            loop {
-                use crate::event_file::ReadEventError::ReadRecordError;
-                use crate::tf_record::ReadRecordError::Truncated;
-                let event = match reader.read_event() {
-                    Ok(event) => event,
-                    Err(ReadRecordError(Truncated)) => break,
-                    Err(e) => {
-                        // TODO(@wchargin): Improve error handling?
-                        warn!("Read error in {}: {:?}", filename.0.display(), e);
-                        *ef = EventFile::Dead;
-                        break;
-                    }
-                };
-                handle_event(&mut self.data, event);
-            }
-        }
+    /// Number of events read so far whose wall time was at the epoch, regardless of the
+    /// [`ZeroWallTime`] policy applied to them.
+    pub fn zero_wall_time_events(&self) -> usize {
+        self.data.zero_wall_time_events
     }
-}
 
-impl RunLoaderData {
-    /// Commits all staged data into the given run of the commit.
-    fn commit_all(&mut self, run_data: &RwLock<commit::RunData>) {
-        let mut run = run_data.write().expect("acquiring tags lock");
-        run.start_time = self.start_time;
-        for (tag, ts) in &mut self.time_series {
-            ts.commit(tag, &mut *run);
-        }
+    /// Shifts the wall time of every event read after this call by `seconds`, so that runs
+    /// written on machines with different clocks can be lined up. The offset may be negative.
+    ///
+    /// The run's start time, [`Self::resume_after`], and the [`ZeroWallTime`] policy all see
+    /// shifted wall times. Events whose shifted wall time is out of range are dropped like events
+    /// with invalid wall times.
+    pub fn wall_time_offset(&mut self, seconds: f64) {
+        self.data.wall_time_offset = seconds;
     }
 
-    /// Reads a single event and stages it for future committing.
-    fn read_event(&mut self, e: pb::Event) {
-        let step = Step(e.step);
-        let wall_time = match WallTime::new(e.wall_time) {
-            None => {
-                // TODO(@wchargin): Improve error handling.
-                warn!(
-                    "Dropping event at step {} with invalid wall time {}",
-                    e.step, e.wall_time
-                );
-                return;
-            }
-            Some(wt) => wt,
-        };
-        if self.start_time.map_or(true, |start| wall_time < start) {
-            self.start_time = Some(wall_time);
-        }
-        match e.what {
-            Some(pb::event::What::GraphDef(graph_bytes)) => {
-                let sv = StageValue {
-                    wall_time,
-                    payload: EventValue::GraphDef(GraphDefValue(graph_bytes)),
-                };
-                use std::collections::hash_map::Entry;
-                let ts = match self
-                    .time_series
-                    .entry(Tag(GraphDefValue::TAG_NAME.to_string()))
-                {
-                    Entry::Occupied(o) => o.into_mut(),
-                    Entry::Vacant(v) => {
-                        v.insert(StageTimeSeries::new(GraphDefValue::initial_metadata()))
-                    }
-                };
-                ts.rsv.offer(step, sv);
-            }
-            Some(pb::event::What::TaggedRunMetadata(trm_proto)) => {
-                let sv = StageValue {
-                    wall_time,
-                    payload: EventValue::GraphDef(GraphDefValue(trm_proto.run_metadata)),
-                };
-                use std::collections::hash_map::Entry;
-                let ts = match self.time_series.entry(Tag(trm_proto.tag)) {
-                    Entry::Occupied(o) => o.into_mut(),
-                    Entry::Vacant(v) => {
-                        let metadata = TaggedRunMetadataValue::initial_metadata();
-                        v.insert(StageTimeSeries::new(metadata))
-                    }
-                };
-                ts.rsv.offer(step, sv);
-            }
-            Some(pb::event::What::Summary(sum)) => {
-                for mut summary_pb_value in sum.value {
-                    let summary_value = match summary_pb_value.value {
-                        None => continue,
-                        Some(v) => SummaryValue(Box::new(v)),
-                    };
+    /// Sets how to handle events whose step is negative. See [`NegativeStepPolicy`].
+    pub fn negative_steps(&mut self, policy: NegativeStepPolicy) {
+        self.data.negative_steps = policy;
+    }
 
-                    use std::collections::hash_map::Entry;
-                    let ts = match self.time_series.entry(Tag(summary_pb_value.tag)) {
-                        Entry::Occupied(o) => o.into_mut(),
-                        Entry::Vacant(v) => {
-                            let metadata =
-                                summary_value.initial_metadata(summary_pb_value.metadata.take());
-                            v.insert(StageTimeSeries::new(metadata))
-                        }
-                    };
-                    let sv = StageValue {
-                        wall_time,
-                        payload: EventValue::Summary(summary_value),
-                    };
-                    ts.rsv.offer(step, sv);
-                }
-            }
-            _ => {}
-        }
+    /// Sets the largest step to accept. Events at larger steps are dropped, and counted in
+    /// [`Self::large_step_events_dropped`]. Defaults to `None`, for no limit.
+    ///
+    /// A step counter that underflowed and wrapped around, or was written from garbage, can
+    /// produce steps so large that they make a time series unplottable.
+    pub fn max_step(&mut self, max_step: Option<i64>) {
+        self.data.max_step = max_step;
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use std::fs::File;
-    use std::io::BufWriter;
+    /// Sets how far a time series' step may go backward and still be taken as out-of-order
+    /// delivery rather than a restart. Defaults to `0`, so that any regression preempts.
+    ///
+    /// Some writers, like several workers sharing one event file, write steps slightly out of
+    /// order (e.g., 10, 8, 11, 9). Normally each dip would preempt the points after it, as if the
+    /// job had restarted from a checkpoint. A point less than `steps` steps behind the latest
+    /// point in its time series is instead inserted in step order, while larger regressions still
+    /// preempt. A point at the same step as an existing one is resolved against it per
+    /// [`Self::duplicate_steps`].
+    pub fn out_of_order_tolerance(&mut self, steps: u64) {
+        self.data.out_of_order_tolerance = steps;
+    }
 
-    use crate::commit::Commit;
-    use crate::data_compat::plugin_names;
-    use crate::disk_logdir::DiskLogdir;
-    use crate::types::Run;
-    use crate::writer::SummaryWriteExt;
+    /// Sets how to handle repeated tags within one event. See [`DuplicateTagPolicy`].
+    pub fn duplicate_tags(&mut self, policy: DuplicateTagPolicy) {
+        self.data.duplicate_tags = policy;
+    }
 
-    #[test]
-    fn test() -> Result<(), Box<dyn std::error::Error>> {
-        let logdir = tempfile::tempdir()?;
-        let f1_name = logdir.path().join("tfevents.123");
-        let f2_name = logdir.path().join("tfevents.456");
-        let mut f1 = BufWriter::new(File::create(&f1_name)?);
-        let mut f2 = BufWriter::new(File::create(&f2_name)?);
+    /// Sets how to resolve points in a time series that share a step, as from
+    /// [`DuplicateTagPolicy::KeepAll`], [out-of-order delivery](Self::out_of_order_tolerance), or
+    /// a writer that restarts at the step where it left off. See [`DuplicateStepPolicy`].
+    ///
+    /// Points at the same step are resolved as they're committed, so a reader never sees two
+    /// points at a step except under [`DuplicateStepPolicy::KeepBoth`]. Applies only to time
+    /// series created after this call.
+    pub fn duplicate_steps(&mut self, policy: DuplicateStepPolicy) {
+        self.data.duplicate_steps = policy;
+    }
 
-        // Write file versions.
-        for (f, wall_time) in &mut [(&mut f1, 1234.0), (&mut f2, 2345.0)] {
-            let file_version = pb::Event {
-                wall_time: *wall_time,
-                what: Some(pb::event::What::FileVersion("brain.Event:2".to_string())),
-                ..Default::default()
-            };
-            f.write_event(&file_version)?;
-        }
+    /// Sets the precision in which to commit scalar values. See [`ScalarPrecision`].
+    ///
+    /// Values are rounded as they're committed, so points committed before this call keep their
+    /// precision, as do points restored from [saved state](Self::restore_state).
+    pub fn scalar_precision(&mut self, precision: ScalarPrecision) {
+        self.data.scalar_precision = precision;
+    }
 
-        // Write some data points across both files.
-        let run = Run("train".to_string());
-        let tag = Tag("accuracy".to_string());
-        f1.write_graph(
+    /// Sets where to keep run metadata tags. See [`RunMetadataTags`].
+    pub fn run_metadata_tags(&mut self, policy: RunMetadataTags) {
+        self.data.run_metadata_tags = policy;
+    }
+
+    /// Sets whether to fail on the first load error, for validating logs rather than serving them.
+    /// Defaults to `false`.
+    ///
+    /// In strict mode, an event file that can't be opened or read, a corrupt record (even with
+    /// [`Self::skip_bad_checksums`]), or an event dropped for an invalid wall time or missing
+    /// contents makes [`Self::try_reload`] stop and return a [`LoadError`] naming the file and
+    /// offset, after committing what it read before the error. The file is abandoned as usual. Events dropped by policy,
+    /// like [`NegativeStepPolicy::Drop`], are not errors.
+    pub fn strict(&mut self, yes: bool) {
+        self.data.strict = yes;
+    }
+
+    /// Number of summary values read so far that were dropped for repeating a tag within their
+    /// event, under [`DuplicateTagPolicy`].
+    pub fn duplicate_values_dropped(&self) -> usize {
+        self.data.duplicate_values_dropped
+    }
+
+    /// Number of time series whose initial summary metadata named a plugin that conflicts with
+    /// the type of their first value, like a `simple_value` claiming the histograms plugin. Such
+    /// time series get metadata inferred from the value instead. See
+    /// [`SummaryValue::conflicts_with`].
+    pub fn metadata_conflicts(&self) -> usize {
+        self.data.metadata_conflicts
+    }
+
+    /// Sets corrections to the summary metadata of time series, by tag, for writers that stamp
+    /// the wrong plugin name or data class. Empty by default.
+    ///
+    /// An override applies to a time series when it's first seen, after any metadata is inferred
+    /// from its first value and before its data class picks a reservoir, so existing time series
+    /// are left alone. Tags whose metadata changed are recorded in [`Self::overridden_tags`].
+    pub fn metadata_overrides(&mut self, overrides: MetadataOverrides) {
+        self.data.metadata_overrides = overrides;
+    }
+
+    /// Tags of time series whose metadata was changed by [`Self::metadata_overrides`].
+    pub fn overridden_tags(&self) -> &BTreeSet<Tag> {
+        &self.data.overridden_tags
+    }
+
+    /// Sets the maximum number of distinct tags (time series) in this run. Defaults to
+    /// [`DEFAULT_MAX_TAGS`]; use `usize::MAX` for no limit.
+    ///
+    /// This guards against bugs like writing a new tag at every step, which could otherwise
+    /// create millions of time series. Once the run has this many tags, values for new tags are
+    /// dropped and counted (see [`Self::tag_overflow`]), while existing tags still accept data.
+    pub fn max_tags(&mut self, max_tags: usize) {
+        self.data.max_tags = max_tags;
+    }
+
+    /// Sets the longest plugin content to keep in summary metadata, in bytes. Defaults to
+    /// [`DEFAULT_MAX_PLUGIN_CONTENT_LEN`]; `None` means no limit.
+    ///
+    /// Metadata is kept with every time series, so a writer that puts a large payload in plugin
+    /// content can use a lot of memory. Longer content is replaced with empty content, and the
+    /// tag is recorded in [`Self::truncated_plugin_content`].
+    pub fn max_plugin_content_len(&mut self, len: Option<usize>) {
+        self.data.max_plugin_content_len = len;
+    }
+
+    /// Sets whether to stage summaries that hold only `simple_value`s on a fast path, which
+    /// skips the checks for other kinds of data and stages values without boxing them. Enabled by
+    /// default. The run leaves the fast path for good once it has any graph, run metadata, or
+    /// other kind of summary value. Results are the same either way; this exists to measure the
+    /// difference.
+    pub fn scalar_fast_path(&mut self, enabled: bool) {
+        self.data.scalar_fast_path = enabled;
+    }
+
+    /// Tags whose plugin content was dropped for exceeding [`Self::max_plugin_content_len`], with
+    /// the original length of the content in bytes.
+    pub fn truncated_plugin_content(&self) -> &BTreeMap<Tag, usize> {
+        &self.data.truncated_plugin_content
+    }
+
+    /// Shares a limit on the number of time series with the loaders of other runs, or removes it
+    /// if `None`. This run's existing time series are moved to the new budget, even if that
+    /// exceeds its limit. Once the budget is used up, values for new tags are dropped and counted
+    /// (see [`Self::tag_overflow`]), while existing tags still accept data.
+    pub fn tag_budget(&mut self, budget: Option<Arc<TagBudget>>) {
+        let n = self.data.time_series.len();
+        if let Some(old) = &self.data.tag_budget {
+            old.release(n);
+        }
+        if let Some(new) = &budget {
+            new.add(n);
+        }
+        self.data.tag_budget = budget;
+    }
+
+    /// Number of values read so far that were dropped because their tag would have exceeded
+    /// [`Self::max_tags`] or the [tag budget](Self::tag_budget).
+    pub fn tag_overflow(&self) -> usize {
+        self.data.tag_overflow.values
+    }
+
+    /// The first few distinct tags that were dropped for exceeding [`Self::max_tags`] or the
+    /// [tag budget](Self::tag_budget), in the order first seen.
+    pub fn rejected_tags(&self) -> &[Tag] {
+        &self.data.tag_overflow.sample
+    }
+
+    /// Sets whether to record which event file each committed point came from, and the offset of
+    /// its event record in that file, so that readers can find them with [`Commit::provenance`].
+    /// This is meant for debugging where unexpected points came from, such as how points from
+    /// different files preempted each other. It costs memory for every point, so it's off by
+    /// default. Applies only to points read after this call.
+    ///
+    /// [`Commit::provenance`]: crate::commit::Commit::provenance
+    pub fn provenance(&mut self, enabled: bool) {
+        self.data.provenance = enabled;
+    }
+
+    /// Sets whether to commit each blob sequence point as a [`BlobReference`] to its event record,
+    /// in [`RunData::blob_references`], rather than holding its blobs in memory. This is for
+    /// servers that serve blobs from a separate endpoint, which can read them back with
+    /// [`BlobReference::read_blob`]. Runs with many images or graphs then take much less memory,
+    /// at the cost of reading the event file for each blob served. Scalars are unaffected. The
+    /// gRPC data server reads them back through its
+    /// [`blob_source`](crate::server::DataProviderHandler::blob_source).
+    ///
+    /// Set this before the first reload, so that each time series is kept in one place. A loader
+    /// in this mode doesn't [save its state](Self::save_state). Events changed by an [event
+    /// interceptor](Self::event_interceptor) can't be referenced.
+    ///
+    /// [`BlobReference`]: crate::commit::BlobReference
+    /// [`BlobReference::read_blob`]: crate::commit::BlobReference::read_blob
+    /// [`RunData::blob_references`]: crate::commit::RunData::blob_references
+    pub fn blob_references(&mut self, enabled: bool) {
+        self.data.blob_references = enabled;
+    }
+
+    /// Sets how records are delimited in this run's event files, for event files opened after
+    /// this call. Defaults to [`Framing::TfRecord`]. With [`Framing::LengthDelimited`], for
+    /// writers that frame each event with just a varint length, the [checksum](Self::checksum)
+    /// options have no effect. Set this before the first reload, so that every file of the run is
+    /// read the same way.
+    pub fn framing(&mut self, framing: Framing) {
+        self.data.framing = framing;
+    }
+
+    /// Sets a channel on which to announce each commit of new points to this run, or stops
+    /// announcing if `None`. Pass the [notifier] of the commit that this loader writes to, so that
+    /// its [subscribers] learn which tags changed.
+    ///
+    /// [notifier]: crate::commit::Commit::notifier
+    /// [subscribers]: crate::commit::Commit::subscribe
+    pub fn notifier(&mut self, notifier: Option<broadcast::Sender<commit::CommitEvent>>) {
+        self.data.notifier = notifier;
+    }
+
+    /// Sets the running totals to update whenever this loader changes its run's data, or stops
+    /// updating them if `None`. Pass the [counters] of the commit that this loader writes to, so
+    /// that its [counts] stay current.
+    ///
+    /// [counters]: crate::commit::Commit::counters
+    /// [counts]: crate::commit::Commit::counts
+    pub fn counters(&mut self, counters: Option<Arc<commit::Counters>>) {
+        self.data.counters = counters;
+    }
+
+    /// Sets the switch to check before each commit, which tells this loader to commit blob
+    /// sequence points without their blobs while the commit is using too much memory, or stops
+    /// checking if `None`. Pass the [load shedding] switch of the commit that this loader writes
+    /// to. Has no effect unless the loader also has [counters](Self::counters), which measure the
+    /// commit's memory use.
+    ///
+    /// [load shedding]: crate::commit::Commit::load_shedding
+    pub fn load_shedding(&mut self, shedding: Option<Arc<commit::LoadShedding>>) {
+        self.data.load_shedding = shedding;
+    }
+
+    /// Number of blob sequence points committed as [data loss](commit::DataLoss) markers, without
+    /// their blobs, because the commit was using too much memory. See [`Self::load_shedding`].
+    pub fn shed_blob_points(&self) -> usize {
+        self.data.shed_blob_points
+    }
+
+    /// Sets how many points to keep in each time series. Applies only to time series first seen
+    /// (or restored) after this call.
+    pub fn sample_sizes(&mut self, sizes: SampleSizes) {
+        self.data.sample_sizes = sizes;
+    }
+
+    /// Number of events read so far that were dropped for having a negative step, under
+    /// [`NegativeStepPolicy::Drop`].
+    pub fn negative_step_events_dropped(&self) -> usize {
+        self.data.negative_step_events_dropped
+    }
+
+    /// Number of events read so far that were dropped for having a step above
+    /// [`Self::max_step`].
+    pub fn large_step_events_dropped(&self) -> usize {
+        self.data.large_step_events_dropped
+    }
+
+    /// Number of events read so far with none of the contents that an event may have (a summary,
+    /// graph, file version, etc.). Writers never emit these on purpose, so a run that has many
+    /// likely has a broken writer or corrupt event files.
+    pub fn empty_events(&self) -> usize {
+        self.data.empty_events
+    }
+
+    /// Sets how long to keep points, by wall time. At the start of each load, points whose wall
+    /// time is more than `horizon` before the current time expire: committed ones are swept out
+    /// of the commit once the load is done, and new ones are dropped as they're read. Defaults to
+    /// `None`, to keep points of any age.
+    ///
+    /// Expired points are removed from the reservoir as well, which then samples later points as
+    /// if the expired ones had never been offered.
+    pub fn retention(&mut self, horizon: Option<Duration>) {
+        self.data.retention = horizon;
+    }
+
+    /// Number of points dropped so far for being older than the [retention](Self::retention)
+    /// horizon, whether as they were read or in a later sweep.
+    pub fn expired_points(&self) -> usize {
+        self.data.expired_points
+    }
+
+    /// Skips events whose wall time is at or before `wall_time`, so that a client that has
+    /// already seen a run's data up to some time can re-attach without staging it all again.
+    ///
+    /// Skipped events still count toward the run's start time and file versions, but none of
+    /// their values are staged. This works alongside read offsets (see [`Self::restore_state`]):
+    /// offsets avoid re-reading bytes, while this avoids re-staging events in whatever is read.
+    pub fn resume_after(&mut self, wall_time: WallTime) {
+        self.data.resume_after = Some(wall_time);
+    }
+
+    /// Pauses loading for this run.
+    ///
+    /// While paused, [`Self::reload`] returns immediately without opening, reading, or closing
+    /// any files, and without touching the commit. Open files keep their read offsets, so after
+    /// [`Self::resume`], the next reload continues where the last one left off. This can be used
+    /// to focus I/O on runs that are actively being viewed.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes loading for this run after a call to [`Self::pause`]. Data is not read until the
+    /// next call to [`Self::reload`].
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Whether loading is paused. See [`Self::pause`].
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Sets the order in which to read the run's event files on each reload. See
+    /// [`EventFileOrder`].
+    pub fn file_order(&mut self, order: EventFileOrder) {
+        self.file_order = order;
+    }
+
+    /// Sets whether to read only the run's first event file (in the order that files are read,
+    /// which is usually the oldest), for a quick preview of a run with many large files. Other
+    /// event files are skipped, and counted by [`Self::skipped_files`], so the committed data is
+    /// partial. The first file is still read as it grows, but if it's deleted or can't be read,
+    /// no data is read at all.
+    ///
+    /// Skipped files are left unread rather than abandoned: after turning preview mode off, the
+    /// next reload reads them from the start.
+    pub fn preview_first_file(&mut self, yes: bool) {
+        self.preview_first_file = yes;
+    }
+
+    /// Lists the event files that this loader has stopped reading, with the reason for each, in
+    /// the order that files are read.
+    pub fn dead_files(&self) -> impl Iterator<Item = (&EventFileBuf, DeadReason)> {
+        self.files.iter().filter_map(|(filename, ef)| match ef {
+            EventFile::Dead(reason) => Some((filename, *reason)),
+            EventFile::Active(_) => None,
+        })
+    }
+
+    /// Lists when each active event file was last checked for new records and when it last
+    /// yielded one, in the order that files are read. A file that is checked on every reload but
+    /// hasn't been read for a long time may belong to a wedged writer.
+    ///
+    /// Files skipped by [`Self::preview_first_file`] or a [deadline](Self::reload_deadline), and
+    /// runs skipped by a [poll backoff](crate::logdir::LogdirLoader::poll_backoff), aren't
+    /// checked.
+    pub fn file_activity(&self) -> impl Iterator<Item = (&EventFileBuf, FileActivity)> {
+        let activity = &self.file_activity;
+        self.files
+            .iter()
+            .filter_map(move |(filename, ef)| match ef {
+                EventFile::Active(_) => Some((
+                    filename,
+                    activity.get(filename).copied().unwrap_or_default(),
+                )),
+                EventFile::Dead(_) => None,
+            })
+    }
+
+    /// Gets the latest time at which a reload read a record from any of this run's event files,
+    /// including files that have since died, or `None` if no record has been read yet.
+    pub fn last_new_data(&self) -> Option<SystemTime> {
+        self.file_activity
+            .values()
+            .filter_map(|a| a.last_read)
+            .max()
+    }
+
+    /// Number of readable event files that the last reload skipped under
+    /// [`Self::preview_first_file`]. If this is nonzero, the run's committed data is partial.
+    pub fn skipped_files(&self) -> usize {
+        self.skipped_files
+    }
+
+    /// Saves this loader's read positions and staged data, along with the data that it has
+    /// committed to `run_data`, so that a loader in another process can continue loading the run
+    /// exactly where this one left off. See [`Self::restore_state`].
+    ///
+    /// Loader settings, like [`Self::checksum`], [`Self::tag_rewriter`], and
+    /// [`Self::event_interceptor`], are not saved. If the run's data has been evicted from the
+    /// commit, the loader keeps [blob references](Self::blob_references), or an event file path
+    /// is not valid Unicode, the saved state is empty, and a restored loader will read the run
+    /// from the start.
+    pub fn save_state(&self, run_data: &commit::RunData) -> Vec<u8> {
+        let state = self.state(run_data).unwrap_or_else(|| RunLoaderState {
+            version: loader_state::VERSION,
+            ..Default::default()
+        });
+        let mut buf = Vec::with_capacity(state.encoded_len());
+        state
+            .encode(&mut buf)
+            // vectors are resizable, so should always be able to encode
+            .expect("failed to encode loader state");
+        buf
+    }
+
+    /// Helper for [`Self::save_state`]. Returns `None` if the state can't be saved.
+    fn state(&self, run_data: &commit::RunData) -> Option<RunLoaderState> {
+        if run_data.evicted.is_some() || self.data.blob_references {
+            return None;
+        }
+        let mut files = Vec::new();
+        for (filename, ef) in &self.files {
+            let (offset, dead_reason) = match ef {
+                EventFile::Active(reader) => (reader.offset(), None),
+                EventFile::Dead(reason) => (0, Some(*reason)),
+            };
+            let path = filename.0.to_str()?.to_string();
+            files.push(loader_state::FileState {
+                path,
+                offset,
+                dead: dead_reason.is_some(),
+                dead_reason: dead_reason.and_then(save_dead_reason),
+            });
+        }
+        for (filename, &offset) in &self.resume_offsets {
+            let path = filename.0.to_str()?.to_string();
+            files.push(loader_state::FileState {
+                path,
+                offset,
+                dead: false,
+                dead_reason: None,
+            });
+        }
+        let time_series = self
+            .data
+            .time_series
+            .iter()
+            .map(|(key, ts)| ts.state(key, run_data))
+            .collect();
+        Some(RunLoaderState {
+            version: loader_state::VERSION,
+            files,
+            start_time: self.data.start_time.map(f64::from),
+            last_wall_time: self.data.last_wall_time.map(f64::from),
+            zero_wall_time_events: self.data.zero_wall_time_events as u64,
+            time_series,
+            negative_step_events_dropped: self.data.negative_step_events_dropped as u64,
+            large_step_events_dropped: self.data.large_step_events_dropped as u64,
+            expired_points: self.data.expired_points as u64,
+            expired_through: match &self.expired_through {
+                Some(filename) => Some(filename.0.to_str()?.to_string()),
+                None => None,
+            },
+            file_versions: self.data.file_versions.clone(),
+            duplicate_values_dropped: self.data.duplicate_values_dropped as u64,
+            metadata_conflicts: self.data.metadata_conflicts as u64,
+            tag_overflow: self.data.tag_overflow.values as u64,
+            filtered_values: self.data.filtered_values as u64,
+            empty_events: self.data.empty_events as u64,
+            rejected_tags: self
+                .data
+                .tag_overflow
+                .sample
+                .iter()
+                .map(|tag| tag.0.clone())
+                .collect(),
+        })
+    }
+
+    /// Restores state saved by [`Self::save_state`], so that the next [`Self::reload`] continues
+    /// reading each event file where the saved loader left off, and samples new points exactly
+    /// as the saved loader would have. Committed points from the saved state are written to
+    /// `run_data` immediately.
+    ///
+    /// This should be called on a new loader, before its first reload, with an empty entry in
+    /// the commit. Event files that were not in the saved state will be read from the start. On
+    /// error, neither this loader nor `run_data` is changed.
+    ///
+    /// # Panics
+    ///
+    /// If we need to access `run_data` but the lock is poisoned.
+    pub fn restore_state(
+        &mut self,
+        state: &[u8],
+        run_data: &RwLock<commit::RunData>,
+    ) -> Result<(), RestoreStateError> {
+        let state = RunLoaderState::decode(state)?;
+        if state.version != loader_state::VERSION {
+            return Err(RestoreStateError::UnsupportedVersion(state.version));
+        }
+        let start_time = state.start_time.map(restore_wall_time).transpose()?;
+        let expired_through = state.expired_through.map(|path| EventFileBuf(path.into()));
+        let last_wall_time = state.last_wall_time.map(restore_wall_time).transpose()?;
+        let mut time_series = HashMap::new();
+        let mut committed = Vec::new();
+        for ts_state in state.time_series {
+            let tag = Tag(ts_state.tag.clone());
+            let (ts, points) = StageTimeSeries::restore(
+                ts_state,
+                self.data.sample_sizes,
+                self.data.duplicate_steps,
+            )?;
+            let key = if is_legacy_run_graph(&tag, &ts.metadata) {
+                reserved_key(&tag.0)
+            } else {
+                tag
+            };
+            committed.push((key.clone(), ts.metadata.clone(), ts.rsv.seen(), points));
+            time_series.insert(key, ts);
+        }
+
+        let mut run = run_data.write().expect("acquiring tags lock");
+        run.start_time = start_time;
+        run.file_versions = state.file_versions.clone();
+        for (tag, metadata, offered, points) in committed {
+            points.commit(tag, metadata, offered, &mut run);
+        }
+        if let Some(counters) = &self.data.counters {
+            counters.recount(&mut run);
+        }
+        drop(run);
+        for file in state.files {
+            let filename = EventFileBuf(file.path.into());
+            if file.dead {
+                let reason = restore_dead_reason(file.dead_reason);
+                self.files.insert(filename, EventFile::Dead(reason));
+            } else {
+                self.resume_offsets.insert(filename, file.offset);
+            }
+        }
+        self.expired_through = expired_through;
+        self.data.start_time = start_time;
+        self.data.file_versions = state.file_versions;
+        self.data.last_wall_time = last_wall_time;
+        self.data.zero_wall_time_events = state.zero_wall_time_events as usize;
+        self.data.negative_step_events_dropped = state.negative_step_events_dropped as usize;
+        self.data.large_step_events_dropped = state.large_step_events_dropped as usize;
+        self.data.expired_points = state.expired_points as usize;
+        // No restored point can be older than the run's start.
+        self.data.oldest_wall_time = start_time;
+        self.data.duplicate_values_dropped = state.duplicate_values_dropped as usize;
+        self.data.empty_events = state.empty_events as usize;
+        self.data.metadata_conflicts = state.metadata_conflicts as usize;
+        self.data.tag_overflow.values = state.tag_overflow as usize;
+        self.data.filtered_values = state.filtered_values as usize;
+        self.data.tag_overflow.sample = state.rejected_tags.into_iter().map(Tag).collect();
+        if let Some(budget) = &self.data.tag_budget {
+            budget.release(self.data.time_series.len());
+            budget.add(time_series.len());
+        }
+        self.data.time_series = time_series;
+        self.data.dirty = true;
+        Ok(())
+    }
+
+    /// Loads new data given the current set of event files.
+    ///
+    /// The provided filenames should correspond to the entire set of event files currently part of
+    /// this run.
+    ///
+    /// The given commit must have an entry for this run (the entry may be empty).
+    ///
+    /// Returns the number of events read. Does nothing (and returns `0`) if this loader is paused.
+    /// In [strict mode](Self::strict), logs the first load error and returns `0`; use
+    /// [`Self::try_reload`] to handle it instead.
+    ///
+    /// # Panics
+    ///
+    /// If we need to access `run_data` but the lock is poisoned.
+    pub fn reload(
+        &mut self,
+        logdir: &impl Logdir<File = R>,
+        filenames: Vec<EventFileBuf>,
+        run_data: &RwLock<commit::RunData>,
+    ) -> usize {
+        self.try_reload(logdir, filenames, run_data)
+            .unwrap_or_else(|e| {
+                error!("Aborted load for run {:?}: {}", self.run.0, e);
+                0
+            })
+    }
+
+    /// Like [`Self::reload`], but returns the error that aborted the load in
+    /// [strict mode](Self::strict). Never fails otherwise.
+    ///
+    /// # Panics
+    ///
+    /// If we need to access `run_data` but the lock is poisoned.
+    pub fn try_reload(
+        &mut self,
+        logdir: &impl Logdir<File = R>,
+        filenames: Vec<EventFileBuf>,
+        run_data: &RwLock<commit::RunData>,
+    ) -> Result<usize, LoadError> {
+        let run_name = self.run.0.clone();
+        let run = self.run.clone();
+        if self.paused {
+            log_kv!(Level::Debug, run = run_name; "Skipping load for paused run");
+            return Ok(0);
+        }
+        let needs_restore = run_data
+            .read()
+            .expect("acquiring tags lock")
+            .needs_restore();
+        if needs_restore {
+            log_kv!(Level::Debug, run = run_name; "Restoring evicted data for run");
+            self.restart();
+        }
+        log_kv!(Level::Debug, run = run_name; "Starting load for run");
+        let start = Instant::now();
+        self.data.retention_cutoff = self.data.retention.and_then(retention_cutoff);
+        self.update_file_set(logdir, filenames)?;
+        let mut n = 0;
+        let mut last_commit_time = Instant::now();
+        let deadline = self.reload_deadline.map(|limit| start + limit);
+        let result = self.reload_files(deadline, |run_loader_data, event| {
+            run_loader_data.read_event(event);
+            n += 1;
+            // Reduce overhead of checking elapsed time by only doing it every 100 events.
+            if n % 100 != 0 {
+                return true;
+            }
+            if matches!(deadline, Some(deadline) if Instant::now() >= deadline) {
+                log_kv!(
+                    Level::Debug,
+                    run = run_name,
+                    events = n,
+                    elapsed = format_args!("{:?}", start.elapsed());
+                    "Reached deadline for run; stopping load early"
+                );
+                return false;
+            }
+            if last_commit_time.elapsed() >= COMMIT_INTERVAL {
+                log_kv!(
+                    Level::Debug,
+                    run = run_name,
+                    events = n,
+                    elapsed = format_args!("{:?}", start.elapsed());
+                    "Loaded events for run"
+                );
+                run_loader_data.commit_all(&run, run_data);
+                last_commit_time = Instant::now();
+            }
+            true
+        });
+        if let Err(e) = result {
+            // Commit what was read before the error, rather than leaving it staged for whichever
+            // load comes next.
+            if self.data.dirty {
+                self.data.commit_all(&run, run_data);
+            }
+            return Err(e);
+        }
+        if self.data.start_time.is_none() && !self.checked_file_times && !self.files.is_empty() {
+            self.checked_file_times = true;
+            self.data.fallback_start_time = earliest_modified(logdir, &run)
+                .and_then(|wall_time| self.data.offset_wall_time(wall_time));
+            if self.data.fallback_start_time.is_some() {
+                self.data.dirty = true;
+            }
+        }
+        if self.data.dirty {
+            self.data.commit_all(&run, run_data);
+        }
+        self.data.sweep_expired(run_data);
+        if needs_restore {
+            run_data.write().expect("acquiring tags lock").evicted = None;
+        }
+        let overflow = &mut self.data.tag_overflow;
+        if overflow.values > 0 && !overflow.warned {
+            overflow.warned = true;
+            warn!(
+                "Run {:?} has reached its limit of {} tags; dropping data for new tags, like {:?}",
+                run_name, self.data.max_tags, overflow.sample
+            );
+        }
+        log_kv!(
+            Level::Debug,
+            run = run_name,
+            events = n,
+            zero_wall_time_events = self.data.zero_wall_time_events,
+            negative_step_events_dropped = self.data.negative_step_events_dropped,
+            large_step_events_dropped = self.data.large_step_events_dropped,
+            expired_points = self.data.expired_points,
+            empty_events = self.data.empty_events,
+            duplicate_values_dropped = self.data.duplicate_values_dropped,
+            metadata_conflicts = self.data.metadata_conflicts,
+            overridden_tags = self.data.overridden_tags.len(),
+            filtered_values = self.data.filtered_values,
+            skipped_files = self.skipped_files,
+            dead_files = self.dead_files().count(),
+            elapsed = format_args!("{:?}", start.elapsed());
+            "Finished load for run"
+        );
+        Ok(n)
+    }
+
+    /// Stages events that were pushed to this run rather than read from event files, then
+    /// commits them to `run_data`. Returns the number of events staged.
+    ///
+    /// Pushed events go through the same policies, reservoirs, and commit path as events read
+    /// from files. A loader that is fed this way should never also be [reloaded](Self::reload),
+    /// and evicted data can't be restored, since there's nothing to re-read it from.
+    ///
+    /// # Panics
+    ///
+    /// If we need to access `run_data` but the lock is poisoned.
+    pub fn ingest(
+        &mut self,
+        events: impl IntoIterator<Item = pb::Event>,
+        run_data: &RwLock<commit::RunData>,
+    ) -> usize {
+        self.data.retention_cutoff = self.data.retention.and_then(retention_cutoff);
+        let mut n = 0;
+        for event in events {
+            self.data.read_event(event);
+            n += 1;
+        }
+        if self.data.dirty {
+            self.data.commit_all(&self.run, run_data);
+        }
+        self.data.sweep_expired(run_data);
+        n
+    }
+
+    /// Reads at most `max_bytes_per_file` bytes from the start of each of the given event files,
+    /// and reports the time series found there as `(tag, data class, plugin name)`, sorted by tag.
+    /// Nothing is staged or committed, and this loader's read positions are unaffected, so this is
+    /// a cheap way to catalog a run before committing to a full load.
+    ///
+    /// Tags are rewritten and filtered as for a full load. Each series is described by the
+    /// metadata of its first value. Files that can't be opened are skipped, and a record that is
+    /// cut off by the byte limit or can't be read ends its file.
+    pub fn sniff(
+        &mut self,
+        logdir: &impl Logdir<File = R>,
+        filenames: &[EventFileBuf],
+        max_bytes_per_file: u64,
+    ) -> Vec<(Tag, pb::DataClass, String)> {
+        let mut found = BTreeMap::new();
+        for filename in filenames {
+            let file = match logdir.open(filename) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("Failed to open event file {:?}: {:?}", filename, e);
+                    continue;
+                }
+            };
+            let file = file.take(max_bytes_per_file);
+            let mut reader = EventFileReader::with_framing(file, self.data.framing);
+            reader.checksum(self.checksum);
+            reader.max_record_len(self.max_record_len);
+            while let Ok(event) = reader.read_event() {
+                self.data.sniff_event(event, &mut found);
+            }
+        }
+        found
+            .into_iter()
+            .map(|(tag, md)| {
+                let data_class =
+                    pb::DataClass::from_i32(md.data_class).unwrap_or(pb::DataClass::Unknown);
+                let plugin_name = md.plugin_data.map(|p| p.plugin_name).unwrap_or_default();
+                (tag, data_class, plugin_name)
+            })
+            .collect()
+    }
+
+    /// Forgets all staged data and read positions, so that this run is re-read from the start of
+    /// each event file. Committing afterward replaces the committed points of every time series.
+    fn restart(&mut self) {
+        self.files.clear();
+        self.resume_offsets.clear();
+        self.checked_file_times = false;
+        self.data.start_time = None;
+        self.data.fallback_start_time = None;
+        self.data.file_versions.clear();
+        self.data.event_files.clear();
+        if let Some(budget) = &self.data.tag_budget {
+            budget.release(self.data.time_series.len());
+        }
+        self.data.time_series.clear();
+        self.data.tag_overflow.values = 0;
+        self.data.tag_overflow.sample.clear();
+        self.data.truncated_plugin_content.clear();
+        self.data.overridden_tags.clear();
+        self.data.filtered_values = 0;
+        self.data.last_wall_time = None;
+        self.data.oldest_wall_time = None;
+        self.data.dirty = true;
+    }
+
+    /// Updates the active key set of `self.files` to match the given filenames.
+    ///
+    /// After this function returns, `self.files` may still have keys not in `filenames`, but they
+    /// will all map to [`EventFile::Dead`].
+    ///
+    /// In strict mode, fails on the first file that can't be opened or resumed, after opening the
+    /// files before it.
+    fn update_file_set(
+        &mut self,
+        logdir: &impl Logdir<File = R>,
+        mut filenames: Vec<EventFileBuf>,
+    ) -> Result<(), LoadError> {
+        self.expire_files(&filenames);
+        if let Some(through) = &self.expired_through {
+            let files = &self.files;
+            filenames.retain(|f| f > through || files.contains_key(f));
+        }
+
+        // Remove any discarded files.
+        let new_file_set: HashSet<&EventFileBuf> = filenames.iter().collect();
+        for (k, v) in self.files.iter_mut() {
+            if !new_file_set.contains(k) && matches!(v, EventFile::Active(_)) {
+                *v = EventFile::Dead(DeadReason::RemovedFromDisk);
+            }
+        }
+
+        // Open readers for any new files.
+        for filename in filenames {
+            use std::collections::btree_map::Entry;
+            match self.files.entry(filename) {
+                Entry::Occupied(_) => {}
+                Entry::Vacant(v) => {
+                    let resume_offset = self.resume_offsets.remove(v.key());
+                    let event_file = match logdir.open(v.key()) {
+                        Ok(file) => {
+                            let mut reader = EventFileReader::with_framing(file, self.data.framing);
+                            reader.checksum(self.checksum);
+                            reader.max_record_len(self.max_record_len);
+                            match resume_offset.map(|offset| (offset, reader.resume_at(offset))) {
+                                Some((offset, Err(e))) => {
+                                    warn!("Failed to resume event file {:?}: {}", v.key(), e);
+                                    let path = v.key().0.clone();
+                                    v.insert(EventFile::Dead(DeadReason::ReadError { offset }));
+                                    if self.data.strict {
+                                        return Err(LoadError::Resume {
+                                            path,
+                                            offset,
+                                            source: e,
+                                        });
+                                    }
+                                    continue;
+                                }
+                                _ => EventFile::Active(reader),
+                            }
+                        }
+                        // TODO(@wchargin): Improve error handling?
+                        Err(e) => {
+                            warn!("Failed to open event file {:?}: {:?}", v.key(), e);
+                            let path = v.key().0.clone();
+                            v.insert(EventFile::Dead(DeadReason::OpenFailed(e.kind())));
+                            if self.data.strict {
+                                return Err(LoadError::Open { path, source: e });
+                            }
+                            continue;
+                        }
+                    };
+                    v.insert(event_file);
+                }
+            };
+        }
+        Ok(())
+    }
+
+    /// Expires the oldest of `filenames` that aren't still being written, by name, until no more
+    /// than [`Self::max_files`] are left unexpired. New files are expired before they're opened.
+    /// Forgotten files, at or before [`Self::expired_through`], don't count toward the limit.
+    fn expire_files(&mut self, filenames: &[EventFileBuf]) {
+        let limit = match self.max_files {
+            Some(limit) => limit,
+            None => return,
+        };
+        let files = &self.files;
+        let through = self.expired_through.as_ref();
+        let mut live: Vec<&EventFileBuf> = filenames
+            .iter()
+            .filter(|f| match files.get(*f) {
+                Some(file) => !matches!(file, EventFile::Dead(DeadReason::Expired)),
+                None => Some(*f) > through,
+            })
+            .collect();
+        if live.len() <= limit {
+            return;
+        }
+        if !self.warned_file_limit {
+            self.warned_file_limit = true;
+            warn!(
+                "Run {:?} has {} event files, more than its limit of {}; expiring the oldest",
+                self.run.0,
+                live.len(),
+                limit
+            );
+        }
+        live.sort_unstable();
+        let activity = &self.file_activity;
+        // A file that yielded records when last checked is probably still being written.
+        let expired: Vec<EventFileBuf> = live
+            .iter()
+            .filter(|f| {
+                !matches!(
+                    activity.get(**f),
+                    Some(FileActivity {
+                        last_read: Some(read),
+                        last_checked: Some(checked),
+                        ..
+                    }) if read >= checked
+                )
+            })
+            .take(live.len() - limit)
+            .map(|f| (*f).clone())
+            .collect();
+        for filename in expired {
+            self.resume_offsets.remove(&filename);
+            self.file_activity.remove(&filename);
+            match self.expired_files {
+                ExpiredFilePolicy::MarkDead => {
+                    self.files
+                        .insert(filename, EventFile::Dead(DeadReason::Expired));
+                }
+                ExpiredFilePolicy::Forget => {
+                    self.files.remove(&filename);
+                    if self.expired_through.as_ref() < Some(&filename) {
+                        self.expired_through = Some(filename);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads data from all active event files (or just the first file, in preview mode), and
+    /// calls a handler for each event. Stops early, leaving each file's reader where it was, once
+    /// the handler returns `false`, or once waiting to retry a read would pass the deadline.
+    ///
+    /// In strict mode, stops at the first read error or dropped event.
+    fn reload_files<F: FnMut(&mut RunLoaderData, pb::Event) -> bool>(
+        &mut self,
+        deadline: Option<Instant>,
+        mut handle_event: F,
+    ) -> Result<(), LoadError> {
+        let mut skipped_files = 0;
+        let mut stopped = false;
+        let mut files: Vec<_> = self.files.iter_mut().collect();
+        if self.file_order == EventFileOrder::Rotation {
+            files.sort_by_cached_key(|(filename, _)| rotation_key(&filename.0));
+        }
+        for (i, (filename, ef)) in files.into_iter().enumerate() {
+            let reader = match ef {
+                EventFile::Dead(_) => continue,
+                EventFile::Active(reader) => reader,
+            };
+            if self.preview_first_file && i > 0 {
+                skipped_files += 1;
+                continue;
+            }
+            if stopped {
+                continue;
+            }
+            self.data.start_file(&filename.0);
+            let activity = self.file_activity.entry(filename.clone()).or_default();
+            activity.last_checked = Some(SystemTime::now());
+            activity.checks += 1;
+            let mut read_any = false;
+
+            let mut retries = 0;
+            loop {
+                let record_offset = reader.offset();
+                if let Some(source) = &mut self.data.source {
+                    source.offset = record_offset;
+                }
+                use crate::event_file::ReadEventError::{InvalidRecord, ReadRecordError};
+                use crate::tf_record::ReadRecordError::{Io, Truncated};
+                let event = match reader.read_event() {
+                    Ok(event) => {
+                        retries = 0;
+                        event
+                    }
+                    Err(ReadRecordError(Truncated)) => break,
+                    Err(ReadRecordError(Io(e)))
+                        if retries < self.read_retries && is_retryable(&e) =>
+                    {
+                        // The reader keeps whatever it read of the record, so it can pick up
+                        // where it left off.
+                        let backoff =
+                            READ_RETRY_BACKOFF * (1 << retries.min(MAX_READ_RETRY_DOUBLINGS));
+                        if matches!(deadline, Some(deadline) if Instant::now() + backoff > deadline)
+                        {
+                            warn_limited!(
+                                key = filename;
+                                "Read error in {} (retrying on next load): {}",
+                                filename.0.display(),
+                                e
+                            );
+                            stopped = true;
+                            break;
+                        }
+                        retries += 1;
+                        warn_limited!(
+                            key = filename;
+                            "Read error in {} (retrying in {:?}): {}",
+                            filename.0.display(),
+                            backoff,
+                            e
+                        );
+                        std::thread::sleep(backoff);
+                        continue;
+                    }
+                    Err(InvalidRecord(e)) if self.skip_bad_checksums && !self.data.strict => {
+                        warn_limited!(key = filename; "Skipping record in {}: {}", filename.0.display(), e);
+                        continue;
+                    }
+                    Err(e) => {
+                        // TODO(@wchargin): Improve error handling?
+                        warn!("Read error in {}: {:?}", filename.0.display(), e);
+                        // A record that failed its data checksum has been consumed, so report
+                        // where it started.
+                        let offset = record_offset;
+                        let reason = match &e {
+                            ReadRecordError(Io(e)) if self.read_retries > 0 && is_retryable(e) => {
+                                DeadReason::RetriesExhausted { offset }
+                            }
+                            e => DeadReason::from_read_error(e, offset),
+                        };
+                        *ef = EventFile::Dead(reason);
+                        if self.data.strict {
+                            return Err(LoadError::Read {
+                                path: filename.0.clone(),
+                                offset,
+                                source: e,
+                            });
+                        }
+                        break;
+                    }
+                };
+                self.data.record_len = reader.offset() - record_offset;
+                if !read_any {
+                    read_any = true;
+                    activity.last_read = Some(SystemTime::now());
+                }
+                let keep_going = handle_event(&mut self.data, event);
+                if let Some(reason) = self.data.dropped_event.take() {
+                    return Err(LoadError::DroppedEvent {
+                        path: filename.0.clone(),
+                        offset: record_offset,
+                        reason,
+                    });
+                }
+                if !keep_going {
+                    stopped = true;
+                    break;
+                }
+            }
+        }
+        self.skipped_files = skipped_files;
+        Ok(())
+    }
+}
+
+impl<R> Drop for RunLoader<R> {
+    fn drop(&mut self) {
+        if let Some(budget) = &self.data.tag_budget {
+            budget.release(self.data.time_series.len());
+        }
+    }
+}
+
+impl RunLoaderData {
+    /// Commits all staged data into the given run of the commit, then announces the changed tags
+    /// on the notifier, if any.
+    fn commit_all(&mut self, run: &Run, run_data: &RwLock<commit::RunData>) {
+        let notifier = match &self.notifier {
+            Some(tx) if tx.receiver_count() > 0 => Some(tx),
+            _ => None,
+        };
+        let mut changed_tags = Vec::new();
+        let shed_blobs = match (&self.load_shedding, &self.counters) {
+            (Some(shedding), Some(counters)) => shedding.check(counters.snapshot().memory),
+            _ => false,
+        };
+        let mut shed = 0;
+        {
+            let mut run_data = run_data.write().expect("acquiring tags lock");
+            run_data.start_time = self.start_time.or(self.fallback_start_time);
+            run_data.start_time_synthesized =
+                self.start_time.is_none() && self.fallback_start_time.is_some();
+            run_data.file_versions.clone_from(&self.file_versions);
+            if self.provenance {
+                run_data.event_files.clone_from(&self.event_files);
+            }
+            let blob_files =
+                Some((self.event_files.as_slice(), self.framing)).filter(|_| self.blob_references);
+            for (key, ts) in &mut self.time_series {
+                if notifier.is_some() && !ts.rsv.staged_items().is_empty() {
+                    changed_tags.push(Tag(public_tag(key).to_string()));
+                }
+                shed += ts.commit(
+                    key,
+                    &mut *run_data,
+                    self.provenance,
+                    self.scalar_precision,
+                    blob_files,
+                    shed_blobs,
+                );
+            }
+            if let Some(counters) = &self.counters {
+                counters.recount(&mut run_data);
+            }
+        }
+        if shed > 0 {
+            self.shed_blob_points += shed;
+            if let Some(shedding) = &self.load_shedding {
+                shedding.record_shed(shed);
+            }
+        }
+        self.dirty = false;
+        if let (Some(tx), false) = (notifier, changed_tags.is_empty()) {
+            changed_tags.sort();
+            // Fails only if all subscribers have since gone away, which is fine.
+            let _ = tx.send(commit::CommitEvent {
+                run: run.clone(),
+                changed_tags,
+            });
+        }
+    }
+
+    /// Removes committed points older than the retention cutoff from `run_data`, if there may be
+    /// any.
+    ///
+    /// # Panics
+    ///
+    /// If the `run_data` lock is poisoned.
+    fn sweep_expired(&mut self, run_data: &RwLock<commit::RunData>) {
+        let cutoff = match (self.retention_cutoff, self.oldest_wall_time) {
+            (Some(cutoff), Some(oldest)) if oldest < cutoff => cutoff,
+            _ => return,
+        };
+        let mut run_data = run_data.write().expect("acquiring tags lock");
+        let mut expired = 0;
+        let mut oldest = None;
+        for (key, ts) in &mut self.time_series {
+            let (n, ts_oldest) = ts.expire(key, &mut run_data, cutoff);
+            expired += n;
+            oldest = oldest.into_iter().chain(ts_oldest).min();
+        }
+        self.oldest_wall_time = oldest;
+        if expired > 0 {
+            self.expired_points += expired;
+            if let Some(counters) = &self.counters {
+                counters.recount(&mut run_data);
+            }
+        }
+    }
+
+    /// Notes that the following events come from the event file at `path`, for provenance and
+    /// blob reference modes.
+    fn start_file(&mut self, path: &Path) {
+        if !self.provenance && !self.blob_references {
+            return;
+        }
+        let id = match self.event_files.iter().position(|f| f == path) {
+            Some(i) => i,
+            None => {
+                self.event_files.push(path.to_path_buf());
+                self.event_files.len() - 1
+            }
+        };
+        self.source = Some(commit::Provenance {
+            file: id as commit::FileId,
+            offset: 0,
+        });
+    }
+
+    /// Locates the value at `index` in the current event record, in blob reference mode.
+    fn record_position(&self, index: u32) -> Option<RecordPosition> {
+        Some(RecordPosition {
+            length: self.record_len,
+            value: index,
+        })
+        .filter(|_| self.blob_references && !self.record_rewritten)
+    }
+
+    /// Applies the run's [wall time offset](RunLoader::wall_time_offset), or returns `None` if
+    /// the result is not a valid wall time.
+    fn offset_wall_time(&self, wall_time: WallTime) -> Option<WallTime> {
+        if self.wall_time_offset == 0.0 {
+            return Some(wall_time);
+        }
+        WallTime::new(f64::from(wall_time) + self.wall_time_offset)
+    }
+
+    /// Reads a single event and stages it for future committing.
+    fn read_event(&mut self, e: pb::Event) {
+        let e = match self.intercept(e) {
+            None => return,
+            Some(e) => e,
+        };
+        if matches!(self.max_step, Some(max) if e.step > max) {
+            self.large_step_events_dropped += 1;
+            return;
+        }
+        let step = match (e.step < 0, self.negative_steps) {
+            (false, _) | (true, NegativeStepPolicy::Keep) => Step(e.step),
+            (true, NegativeStepPolicy::ClampToZero) => Step(0),
+            (true, NegativeStepPolicy::Drop) => {
+                self.negative_step_events_dropped += 1;
+                return;
+            }
+        };
+        let wall_time = match WallTime::new(e.wall_time) {
+            None => {
+                // TODO(@wchargin): Improve error handling.
+                warn_limited!(
+                    "Dropping event at step {} with invalid wall time {}",
+                    e.step,
+                    e.wall_time
+                );
+                if self.strict {
+                    self.dropped_event = Some(format!("invalid wall time {}", e.wall_time));
+                }
+                return;
+            }
+            Some(wt) => wt,
+        };
+        let wall_time = if f64::from(wall_time).abs() < ZERO_WALL_TIME_EPSILON {
+            self.zero_wall_time_events += 1;
+            match (self.zero_wall_time, self.last_wall_time) {
+                (ZeroWallTime::Keep, _) => self.offset_wall_time(wall_time),
+                // Already offset.
+                (ZeroWallTime::Inherit, Some(last)) => Some(last),
+                (ZeroWallTime::Inherit, None) | (ZeroWallTime::Drop, _) => return,
+            }
+        } else {
+            self.offset_wall_time(wall_time)
+        };
+        let wall_time = match wall_time {
+            None => {
+                warn_limited!(
+                    "Dropping event at step {} with wall time {} out of range after offset {}",
+                    e.step,
+                    e.wall_time,
+                    self.wall_time_offset
+                );
+                if self.strict {
+                    self.dropped_event = Some(format!(
+                        "wall time {} out of range after offset {}",
+                        e.wall_time, self.wall_time_offset
+                    ));
+                }
+                return;
+            }
+            Some(wt) => wt,
+        };
+        self.last_wall_time = Some(wall_time);
+        self.dirty = true;
+        if self.start_time.map_or(true, |start| wall_time < start) {
+            self.start_time = Some(wall_time);
+        }
+        if matches!(self.resume_after, Some(after) if wall_time <= after)
+            && !matches!(e.what, Some(pb::event::What::FileVersion(_)))
+        {
+            return;
+        }
+        if matches!(self.retention_cutoff, Some(cutoff) if wall_time < cutoff) {
+            self.expired_points += match &e.what {
+                Some(pb::event::What::Summary(sum)) => sum.value.len(),
+                Some(pb::event::What::GraphDef(_))
+                | Some(pb::event::What::TaggedRunMetadata(_)) => 1,
+                _ => 0,
+            };
+            if !matches!(e.what, Some(pb::event::What::FileVersion(_))) {
+                return;
+            }
+        } else if !matches!(self.oldest_wall_time, Some(oldest) if oldest <= wall_time) {
+            self.oldest_wall_time = Some(wall_time);
+        }
+        let what = match e.what {
+            Some(pb::event::What::Summary(sum))
+                if self.scalar_fast_path && sum.value.iter().all(is_simple_value) =>
+            {
+                self.read_simple_values(step, wall_time, sum);
+                return;
+            }
+            what => what,
+        };
+        match what {
+            Some(pb::event::What::FileVersion(version))
+                if !self.file_versions.contains(&version) =>
+            {
+                self.file_versions.push(version);
+            }
+            Some(pb::event::What::GraphDef(graph_bytes)) => {
+                self.scalar_fast_path = false;
+                let sv = StageValue {
+                    wall_time,
+                    payload: EventValue::GraphDef(GraphDefValue(graph_bytes)),
+                    source: self.source,
+                    position: self.record_position(0),
+                };
+                if self.is_dropped(&Tag(GraphDefValue::TAG_NAME.to_string())) {
+                    self.filtered_values += 1;
+                    return;
+                }
+                let tag = reserved_key(GraphDefValue::TAG_NAME);
+                if !self.admit_tag(&tag) {
+                    return;
+                }
+                use std::collections::hash_map::Entry;
+                let ts = match self.time_series.entry(tag) {
+                    Entry::Occupied(o) => o.into_mut(),
+                    Entry::Vacant(v) => {
+                        let metadata = GraphDefValue::initial_metadata();
+                        v.insert(StageTimeSeries::new(
+                            metadata,
+                            self.sample_sizes,
+                            self.duplicate_steps,
+                        ))
+                    }
+                };
+                ts.rsv
+                    .offer_tolerating(step, sv, self.out_of_order_tolerance);
+            }
+            Some(pb::event::What::TaggedRunMetadata(trm_proto)) => {
+                self.scalar_fast_path = false;
+                let tag = match self.rewrite_tag(Tag(trm_proto.tag)) {
+                    None => return,
+                    Some(tag) => tag,
+                };
+                let tag = match self.run_metadata_tags {
+                    RunMetadataTags::Shared => tag,
+                    RunMetadataTags::Prefixed => {
+                        Tag(format!("{}{}", TaggedRunMetadataValue::TAG_PREFIX, tag.0))
+                    }
+                };
+                if !self.passes_tag_filter(&tag) || !self.admit_tag(&tag) {
+                    return;
+                }
+                let sv = StageValue {
+                    wall_time,
+                    payload: EventValue::GraphDef(GraphDefValue(trm_proto.run_metadata)),
+                    source: self.source,
+                    position: self.record_position(0),
+                };
+                use std::collections::hash_map::Entry;
+                let ts = match self.time_series.entry(tag) {
+                    Entry::Occupied(o) => o.into_mut(),
+                    Entry::Vacant(v) => {
+                        let metadata = TaggedRunMetadataValue::initial_metadata();
+                        v.insert(StageTimeSeries::new(
+                            metadata,
+                            self.sample_sizes,
+                            self.duplicate_steps,
+                        ))
+                    }
+                };
+                ts.rsv
+                    .offer_tolerating(step, sv, self.out_of_order_tolerance);
+            }
+            Some(pb::event::What::Summary(mut sum)) => {
+                self.scalar_fast_path = false;
+                // Blob references locate values as written, before duplicates are dropped.
+                let (dropped, positions) = if self.blob_references && !self.record_rewritten {
+                    let (dropped, positions) = self.duplicate_tags.apply_tracking(&mut sum.value);
+                    (dropped, Some(positions))
+                } else {
+                    (self.duplicate_tags.apply(&mut sum.value), None)
+                };
+                self.duplicate_values_dropped += dropped;
+                if self.time_series.is_empty() {
+                    // Loggers usually write the same tags at every step, so the first summary
+                    // is a fair guess at how many time series the run will have.
+                    self.time_series.reserve(sum.value.len());
+                }
+                let (source, tolerance) = (self.source, self.out_of_order_tolerance);
+                let length = self.record_len;
+                for (i, mut summary_pb_value) in sum.value.into_iter().enumerate() {
+                    let summary_value = match summary_pb_value.value {
+                        None => continue,
+                        Some(v) => SummaryValue(Box::new(v)),
+                    };
+                    let tag = match self.rewrite_tag(Tag(summary_pb_value.tag)) {
+                        None => continue,
+                        Some(tag) => tag,
+                    };
+                    if !self.passes_tag_filter(&tag) || !self.admit_tag(&tag) {
+                        continue;
+                    }
+                    if let Some(md) = &mut summary_pb_value.metadata {
+                        self.cap_plugin_content(&tag, md);
+                    }
+                    let ts = match self.summary_time_series(
+                        tag,
+                        summary_pb_value.metadata,
+                        &summary_value,
+                    ) {
+                        None => continue,
+                        Some(ts) => ts,
+                    };
+                    let sv = StageValue {
+                        wall_time,
+                        payload: EventValue::Summary(summary_value),
+                        source,
+                        position: positions.as_ref().map(|p| RecordPosition {
+                            length,
+                            value: p[i],
+                        }),
+                    };
+                    ts.rsv.offer_tolerating(step, sv, tolerance);
+                }
+            }
+            None => {
+                // Writers never emit these on purpose, so this may be corruption.
+                self.empty_events += 1;
+                warn_limited!("Ignoring event at step {} with no contents", e.step);
+                if self.strict {
+                    self.dropped_event = Some("event has no contents".to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Stages a summary whose values are all `simple_value`s. This has the same effect as the
+    /// general path in [`Self::read_event`], but skips its checks for other kinds of data and
+    /// stages values without boxing them, except for the first value of each time series.
+    fn read_simple_values(&mut self, step: Step, wall_time: WallTime, mut sum: pb::Summary) {
+        use pb::summary::value::Value;
+        self.duplicate_values_dropped += self.duplicate_tags.apply(&mut sum.value);
+        if self.time_series.is_empty() {
+            self.time_series.reserve(sum.value.len());
+        }
+        for mut summary_pb_value in sum.value {
+            let f = match summary_pb_value.value {
+                Some(Value::SimpleValue(f)) => f,
+                _ => continue,
+            };
+            let tag = match self.rewrite_tag(Tag(summary_pb_value.tag)) {
+                None => continue,
+                Some(tag) => tag,
+            };
+            if !self.passes_tag_filter(&tag) || !self.admit_tag(&tag) {
+                continue;
+            }
+            if let Some(md) = &mut summary_pb_value.metadata {
+                self.cap_plugin_content(&tag, md);
+            }
+            let sv = StageValue {
+                wall_time,
+                payload: EventValue::SimpleValue(f),
+                source: self.source,
+                position: None,
+            };
+            match self.time_series.get_mut(&tag) {
+                Some(ts) if !ts.metadata_inferred || summary_pb_value.metadata.is_none() => {
+                    ts.rsv
+                        .offer_tolerating(step, sv, self.out_of_order_tolerance);
+                    continue;
+                }
+                _ => {}
+            }
+            // A new time series, or new metadata for one: needs the boxed value.
+            let summary_value = SummaryValue(Box::new(Value::SimpleValue(f)));
+            let tolerance = self.out_of_order_tolerance;
+            if let Some(ts) =
+                self.summary_time_series(tag, summary_pb_value.metadata, &summary_value)
+            {
+                ts.rsv.offer_tolerating(step, sv, tolerance);
+            }
+        }
+    }
+
+    /// Finds or creates the time series for a summary value with the given tag, upgrading its
+    /// inferred metadata if `md` is given. Returns `None` if the value should be dropped.
+    fn summary_time_series(
+        &mut self,
+        tag: Tag,
+        md: Option<pb::SummaryMetadata>,
+        summary_value: &SummaryValue,
+    ) -> Option<&mut StageTimeSeries> {
+        use std::collections::hash_map::Entry;
+        match self.time_series.entry(tag) {
+            Entry::Occupied(mut o) => {
+                if o.get().metadata_inferred {
+                    if let Some(md) = md {
+                        let tag = o.key().clone();
+                        o.get_mut().upgrade_metadata(
+                            &tag,
+                            summary_value,
+                            md,
+                            &self.metadata_overrides,
+                        );
+                    }
+                }
+                Some(o.into_mut())
+            }
+            Entry::Vacant(v) => {
+                let inferred = md.is_none();
+                if matches!(&md, Some(md) if summary_value.conflicts_with(md)) {
+                    self.metadata_conflicts += 1;
+                    warn!(
+                        "Summary metadata for tag {:?} names a plugin that doesn't match its \
+                         value type; inferring metadata from the value",
+                        v.key().0
+                    );
+                }
+                let mut metadata = summary_value.initial_metadata(md);
+                if self.metadata_overrides.apply(v.key(), &mut metadata)
+                    && self.overridden_tags.insert(v.key().clone())
+                {
+                    info!(
+                        "Overriding metadata for tag {:?}: plugin {:?}, data class {:?}",
+                        v.key().0,
+                        metadata
+                            .plugin_data
+                            .as_ref()
+                            .map_or("", |pd| &pd.plugin_name),
+                        pb::DataClass::from_i32(metadata.data_class)
+                            .unwrap_or(pb::DataClass::Unknown),
+                    );
+                }
+                let ts = v.insert(StageTimeSeries::new(
+                    metadata,
+                    self.sample_sizes,
+                    self.duplicate_steps,
+                ));
+                ts.metadata_inferred = inferred;
+                Some(ts)
+            }
+        }
+    }
+
+    /// Empties plugin content longer than [`RunLoader::max_plugin_content_len`], recording the
+    /// tag and the original length.
+    fn cap_plugin_content(&mut self, tag: &Tag, md: &mut pb::SummaryMetadata) {
+        let limit = match self.max_plugin_content_len {
+            None => return,
+            Some(limit) => limit,
+        };
+        let plugin_data = match &mut md.plugin_data {
+            Some(pd) if pd.content.len() > limit => pd,
+            _ => return,
+        };
+        let len = plugin_data.content.len();
+        plugin_data.content = Vec::new();
+        if self
+            .truncated_plugin_content
+            .insert(tag.clone(), len)
+            .is_none()
+        {
+            warn!(
+                "Dropping {} bytes of plugin content for tag {:?}, over the limit of {} bytes",
+                len, tag.0, limit
+            );
+        }
+    }
+
+    /// Checks whether a value for `tag` may be staged under [`RunLoader::tag_filter`], counting it
+    /// as filtered if not.
+    fn passes_tag_filter(&mut self, tag: &Tag) -> bool {
+        if self.is_dropped(tag) {
+            self.filtered_values += 1;
+            return false;
+        }
+        if self.tag_filter.is_empty() || self.time_series.contains_key(tag) {
+            return true;
+        }
+        if !self.filtered_tags.contains(tag) {
+            if self.tag_filter.matches(tag) {
+                return true;
+            }
+            self.filtered_tags.insert(tag.clone());
+        }
+        self.filtered_values += 1;
+        false
+    }
+
+    /// Checks whether `tag` has been [dropped](RunLoader::drop_tag).
+    fn is_dropped(&self, tag: &Tag) -> bool {
+        !self.dropped_tags.is_empty() && self.dropped_tags.contains(tag)
+    }
+
+    /// Checks whether a value for `tag` may be staged under [`RunLoader::max_tags`] and
+    /// [`RunLoader::tag_budget`], recording it as overflow if not.
+    fn admit_tag(&mut self, tag: &Tag) -> bool {
+        let under_max = self.time_series.len() < self.max_tags;
+        match &self.tag_budget {
+            // Common case: no need to look up the tag.
+            None if under_max => return true,
+            _ if self.time_series.contains_key(tag) => return true,
+            Some(budget) if under_max => {
+                if budget.try_reserve() {
+                    return true;
+                }
+                budget.rejected.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => (),
+        }
+        let overflow = &mut self.tag_overflow;
+        overflow.values += 1;
+        let tag = Tag(public_tag(tag).to_string());
+        if overflow.sample.len() < REJECTED_TAGS_SAMPLE_SIZE && !overflow.sample.contains(&tag) {
+            overflow.sample.push(tag);
+        }
+        false
+    }
+
+    /// Records the metadata of any time series in `e` that isn't already in `found`, for
+    /// [`RunLoader::sniff`]. Applies the tag rewriter and filters, but stages nothing.
+    fn sniff_event(&mut self, e: pb::Event, found: &mut BTreeMap<Tag, Box<pb::SummaryMetadata>>) {
+        let e = match self.intercept(e) {
+            None => return,
+            Some(e) => e,
+        };
+        match e.what {
+            Some(pb::event::What::GraphDef(_)) => {
+                let tag = Tag(GraphDefValue::TAG_NAME.to_string());
+                if !self.is_dropped(&tag) {
+                    found
+                        .entry(tag)
+                        .or_insert_with(GraphDefValue::initial_metadata);
+                }
+            }
+            Some(pb::event::What::TaggedRunMetadata(trm_proto)) => {
+                let tag = match self.apply_tag_rewriter(Tag(trm_proto.tag)) {
+                    Some(tag) if !tag.0.starts_with(RESERVED_TAG_PREFIX) => tag,
+                    _ => return,
+                };
+                let tag = match self.run_metadata_tags {
+                    RunMetadataTags::Shared => tag,
+                    RunMetadataTags::Prefixed => {
+                        Tag(format!("{}{}", TaggedRunMetadataValue::TAG_PREFIX, tag.0))
+                    }
+                };
+                if self.sniffs_tag(&tag) {
+                    found
+                        .entry(tag)
+                        .or_insert_with(TaggedRunMetadataValue::initial_metadata);
+                }
+            }
+            Some(pb::event::What::Summary(sum)) => {
+                for summary_pb_value in sum.value {
+                    let value = match summary_pb_value.value {
+                        None => continue,
+                        Some(v) => SummaryValue(Box::new(v)),
+                    };
+                    let tag = match self.apply_tag_rewriter(Tag(summary_pb_value.tag)) {
+                        Some(tag) if !tag.0.starts_with(RESERVED_TAG_PREFIX) => tag,
+                        _ => continue,
+                    };
+                    if self.sniffs_tag(&tag) && !found.contains_key(&tag) {
+                        let mut md = value.initial_metadata(summary_pb_value.metadata);
+                        self.metadata_overrides.apply(&tag, &mut md);
+                        found.insert(tag, md);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks whether a full load would keep values for `tag`, without counting it as filtered.
+    fn sniffs_tag(&self, tag: &Tag) -> bool {
+        !self.is_dropped(tag) && (self.tag_filter.is_empty() || self.tag_filter.matches(tag))
+    }
+
+    /// Passes an event through the [interceptor](RunLoader::event_interceptor), if any.
+    fn intercept(&mut self, e: pb::Event) -> Option<pb::Event> {
+        let f = match &mut self.event_interceptor {
+            None => return Some(e),
+            Some(EventInterceptor(f)) => f,
+        };
+        if !self.blob_references {
+            return f(e);
+        }
+        // Blob references locate values in the record as written, so note whether it changed.
+        let original = e.clone();
+        let e = f(e)?;
+        self.record_rewritten = e != original;
+        Some(e)
+    }
+
+    /// Applies the tag rewriter, if any, to the given tag, then drops it if it has the
+    /// [reserved prefix](RESERVED_TAG_PREFIX), counting it as filtered.
+    fn rewrite_tag(&mut self, tag: Tag) -> Option<Tag> {
+        let tag = self.apply_tag_rewriter(tag)?;
+        if !tag.0.starts_with(RESERVED_TAG_PREFIX) {
+            return Some(tag);
+        }
+        self.filtered_values += 1;
+        if !self.warned_reserved_tag {
+            self.warned_reserved_tag = true;
+            warn!(
+                "Dropping values with reserved tags, like {:?}; tags may not start with {:?}",
+                tag.0, RESERVED_TAG_PREFIX
+            );
+        }
+        None
+    }
+
+    /// Applies the tag rewriter, if any, to the given tag.
+    fn apply_tag_rewriter(&mut self, tag: Tag) -> Option<Tag> {
+        match &mut self.tag_rewriter {
+            None => Some(tag),
+            Some(TagRewriter(f)) => f(&tag),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+    use std::io::{BufWriter, Write};
+
+    use crate::commit::Commit;
+    use crate::disk_logdir::DiskLogdir;
+    use crate::types::Run;
+    use crate::writer::SummaryWriteExt;
+
+    #[test]
+    fn test() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let f2_name = logdir.path().join("tfevents.456");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let mut f2 = BufWriter::new(File::create(&f2_name)?);
+
+        // Write file versions.
+        for (f, wall_time) in &mut [(&mut f1, 1234.0), (&mut f2, 2345.0)] {
+            let file_version = pb::Event {
+                wall_time: *wall_time,
+                what: Some(pb::event::What::FileVersion("brain.Event:2".to_string())),
+                ..Default::default()
+            };
+            f.write_event(&file_version)?;
+        }
+
+        // Write some data points across both files.
+        let run = Run("train".to_string());
+        let tag = Tag("accuracy".to_string());
+        f1.write_graph(
             Step(0),
             WallTime::new(1235.0).unwrap(),
             b"<sample model graph>".to_vec(),
@@ -413,118 +3398,3109 @@ mod test {
         f1.write_tagged_run_metadata(
             &Tag("step0000".to_string()),
             Step(0),
-            WallTime::new(1235.0).unwrap(),
+            WallTime::new(1235.0).unwrap(),
+            b"<sample run metadata>".to_vec(),
+        )?;
+        f1.write_scalar(&tag, Step(0), WallTime::new(1235.0).unwrap(), 0.25)?;
+        f1.write_scalar(&tag, Step(1), WallTime::new(1236.0).unwrap(), 0.50)?;
+        f1.write_scalar(&tag, Step(2), WallTime::new(1237.0).unwrap(), 0.75)?;
+        f1.write_scalar(&tag, Step(3), WallTime::new(1238.0).unwrap(), 1.00)?;
+        // preempt!
+        f2.write_scalar(&tag, Step(2), WallTime::new(2346.0).unwrap(), 0.70)?;
+        f2.write_scalar(&tag, Step(3), WallTime::new(2347.0).unwrap(), 0.85)?;
+        f2.write_scalar(&tag, Step(4), WallTime::new(2348.0).unwrap(), 0.90)?;
+        // flush, so that the data's there when we read it
+        f1.into_inner()?.sync_all()?;
+        f2.into_inner()?.sync_all()?;
+
+        let mut loader = RunLoader::new(run.clone());
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let commit = Commit::new();
+        commit
+            .runs
+            .write()
+            .expect("write-locking runs map")
+            .insert(run.clone(), Default::default());
+        loader.reload(
+            &logdir,
+            vec![EventFileBuf(f1_name), EventFileBuf(f2_name)],
+            &commit.runs.read().unwrap()[&run],
+        );
+
+        // Start time should be that of the file version event, even though that didn't correspond
+        // to any time series.
+        assert_eq!(loader.data.start_time, Some(WallTime::new(1234.0).unwrap()));
+
+        let runs = commit.runs.read().expect("read-locking runs map");
+        let run_data: &commit::RunData = &*runs
+            .get(&run)
+            .expect("looking up data for run")
+            .read()
+            .expect("read-locking run data map");
+
+        assert_eq!(run_data.file_versions, vec!["brain.Event:2".to_string()]);
+        assert_eq!(run_data.scalars.keys().collect::<Vec<_>>(), vec![&tag]);
+        let scalar_ts = run_data.scalars.get(&tag).unwrap();
+        assert_eq!(
+            *scalar_ts.metadata,
+            pb::SummaryMetadata {
+                plugin_data: Some(pb::summary_metadata::PluginData {
+                    plugin_name: plugin_names::SCALARS.to_string(),
+                    ..Default::default()
+                }),
+                data_class: pb::DataClass::Scalar.into(),
+                ..Default::default()
+            }
+        );
+        // Points should be as expected (no downsampling at these sizes).
+        // Written as `simple_value`s, so widened from single precision.
+        let scalar = |x: f32| commit::ScalarValue(f64::from(x));
+        assert_eq!(
+            scalar_ts.valid_values().collect::<Vec<_>>(),
+            vec![
+                (Step(0), WallTime::new(1235.0).unwrap(), &scalar(0.25)),
+                (Step(1), WallTime::new(1236.0).unwrap(), &scalar(0.50)),
+                (Step(2), WallTime::new(2346.0).unwrap(), &scalar(0.70)),
+                (Step(3), WallTime::new(2347.0).unwrap(), &scalar(0.85)),
+                (Step(4), WallTime::new(2348.0).unwrap(), &scalar(0.90)),
+            ]
+        );
+
+        assert_eq!(run_data.blob_sequences.len(), 2);
+
+        assert_eq!(run_data.run_graph(), Some(&b"<sample model graph>"[..]));
+        let run_graph_tag = reserved_key(GraphDefValue::TAG_NAME);
+        let graph_ts = run_data.blob_sequences.get(&run_graph_tag).unwrap();
+        assert_eq!(
+            *graph_ts.metadata,
+            pb::SummaryMetadata {
+                plugin_data: Some(pb::summary_metadata::PluginData {
+                    plugin_name: plugin_names::GRAPHS.to_string(),
+                    ..Default::default()
+                }),
+                data_class: pb::DataClass::BlobSequence.into(),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            graph_ts.valid_values().collect::<Vec<_>>(),
+            vec![(
+                Step(0),
+                WallTime::new(1235.0).unwrap(),
+                &commit::BlobSequenceValue(vec![b"<sample model graph>".to_vec()])
+            )]
+        );
+
+        let run_metadata_tag = Tag("step0000".to_string());
+        let run_metadata_ts = run_data.blob_sequences.get(&run_metadata_tag).unwrap();
+        assert_eq!(
+            *run_metadata_ts.metadata,
+            pb::SummaryMetadata {
+                plugin_data: Some(pb::summary_metadata::PluginData {
+                    plugin_name: plugin_names::GRAPH_TAGGED_RUN_METADATA.to_string(),
+                    ..Default::default()
+                }),
+                data_class: pb::DataClass::BlobSequence.into(),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            run_metadata_ts.valid_values().collect::<Vec<_>>(),
+            vec![(
+                Step(0),
+                WallTime::new(1235.0).unwrap(),
+                &commit::BlobSequenceValue(vec![b"<sample run metadata>".to_vec()])
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tag_rewriter() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let wall_time = WallTime::new(1235.0).unwrap();
+        f1.write_scalar(&Tag("loss/total".to_string()), Step(0), wall_time, 0.25)?;
+        f1.write_scalar(&Tag("debug/noise".to_string()), Step(0), wall_time, 0.5)?;
+        f1.write_tagged_run_metadata(
+            &Tag("step0000".to_string()),
+            Step(0),
+            wall_time,
             b"<sample run metadata>".to_vec(),
         )?;
+        f1.write_graph(Step(0), wall_time, b"<sample model graph>".to_vec())?;
+        f1.into_inner()?.sync_all()?;
+
+        let run = Run("train".to_string());
+        let mut loader = RunLoader::new(run.clone());
+        loader.tag_rewriter(|Tag(tag)| {
+            if tag.starts_with("debug/") {
+                None
+            } else {
+                Some(Tag(format!("train/{}", tag)))
+            }
+        });
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let commit = Commit::new();
+        commit
+            .runs
+            .write()
+            .expect("write-locking runs map")
+            .insert(run.clone(), Default::default());
+        loader.reload(
+            &logdir,
+            vec![EventFileBuf(f1_name)],
+            &commit.runs.read().unwrap()[&run],
+        );
+
+        let runs = commit.runs.read().expect("read-locking runs map");
+        let run_data = runs.get(&run).unwrap().read().unwrap();
+        assert_eq!(
+            run_data.scalars.keys().collect::<Vec<_>>(),
+            vec![&Tag("train/loss/total".to_string())]
+        );
+        let mut blob_tags = run_data.blob_sequences.keys().collect::<Vec<_>>();
+        blob_tags.sort();
+        assert_eq!(
+            blob_tags,
+            vec![
+                &reserved_key(GraphDefValue::TAG_NAME),
+                &Tag("train/step0000".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_interceptor() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let wall_time = WallTime::new(1235.0).unwrap();
+        f1.write_graph(Step(0), wall_time, b"<sample model graph>".to_vec())?;
+        f1.write_scalar(&Tag("loss".to_string()), Step(0), wall_time, 0.25)?;
+        f1.write_graph(Step(1), wall_time, b"<another model graph>".to_vec())?;
+        f1.write_scalar(&Tag("loss".to_string()), Step(1), wall_time, 0.125)?;
+        f1.into_inner()?.sync_all()?;
+
+        let run = Run("train".to_string());
+        let mut loader = RunLoader::new(run.clone());
+        loader.event_interceptor(|e| match e.what {
+            Some(pb::event::What::GraphDef(_)) => None,
+            _ => Some(e),
+        });
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let commit = Commit::new();
+        commit
+            .runs
+            .write()
+            .expect("write-locking runs map")
+            .insert(run.clone(), Default::default());
+        loader.reload(
+            &logdir,
+            vec![EventFileBuf(f1_name)],
+            &commit.runs.read().unwrap()[&run],
+        );
+
+        let runs = commit.runs.read().expect("read-locking runs map");
+        let run_data = runs.get(&run).unwrap().read().unwrap();
+        assert!(run_data.blob_sequences.is_empty());
+        assert_eq!(
+            run_data.scalars[&Tag("loss".to_string())]
+                .basin
+                .as_slice()
+                .len(),
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reserved_tag_collision() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let graph_tag = Tag(GraphDefValue::TAG_NAME.to_string());
+        let wall_time = WallTime::new(1235.0).unwrap();
+        let image = |step: i64| pb::Event {
+            step,
+            wall_time: 1235.0,
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: vec![pb::summary::Value {
+                    tag: graph_tag.0.clone(),
+                    value: Some(pb::summary::value::Value::Image(pb::summary::Image {
+                        encoded_image_string: b"<image>".to_vec(),
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        // The user's blob sequence arrives first, so it would have claimed the tag's staged state.
+        f1.write_event(&image(0))?;
+        f1.write_graph(Step(0), wall_time, b"<sample model graph>".to_vec())?;
+        f1.write_event(&image(1))?;
+        // A user tag spelled like a staging key is dropped.
+        f1.write_scalar(&reserved_key("loss"), Step(0), wall_time, 0.75)?;
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+        let check = |run_data: &commit::RunData| {
+            assert!(run_data.scalars.is_empty());
+            let graphs = &run_data.blob_sequences[&reserved_key(GraphDefValue::TAG_NAME)];
+            assert_eq!(graphs.plugin_name(), Some(plugin_names::GRAPHS));
+            assert_eq!(graphs.valid_values().count(), 1);
+            assert_eq!(run_data.run_graph(), Some(&b"<sample model graph>"[..]));
+            let images = &run_data.blob_sequences[&graph_tag];
+            assert_eq!(images.plugin_name(), Some(plugin_names::IMAGES));
+            let steps: Vec<Step> = images.valid_values().map(|(s, _, _)| s).collect();
+            assert_eq!(steps, vec![Step(0), Step(1)]);
+            let tags: Vec<&str> = run_data.tags().map(public_tag).collect();
+            assert_eq!(tags, vec![GraphDefValue::TAG_NAME; 2]);
+        };
+        check(&run_data.read().unwrap());
+        assert_eq!(loader.filtered_values(), 1);
+
+        // Saved state keeps the two series apart, too.
+        let state = loader.save_state(&run_data.read().unwrap());
+        let mut restored = RunLoader::<File>::new(Run("train".to_string()));
+        let restored_data = RwLock::new(commit::RunData::default());
+        restored.restore_state(&state, &restored_data)?;
+        check(&restored_data.read().unwrap());
+        assert_eq!(restored.data.time_series.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scalar_precision() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("x".to_string());
+        // None of these are exact in single precision.
+        let values = [0.1, 1.0 / 3.0, 16777217.0, 1e6 + 1e-3];
+        for (i, &x) in values.iter().enumerate() {
+            f1.write_event(&pb::Event {
+                step: i as i64,
+                wall_time: 1235.0,
+                what: Some(pb::event::What::Summary(pb::Summary {
+                    value: vec![pb::summary::Value {
+                        tag: tag.0.clone(),
+                        metadata: Some(pb::SummaryMetadata {
+                            plugin_data: Some(pb::summary_metadata::PluginData {
+                                plugin_name: plugin_names::SCALARS.to_string(),
+                                ..Default::default()
+                            }),
+                            data_class: pb::DataClass::Scalar.into(),
+                            ..Default::default()
+                        }),
+                        value: Some(pb::summary::value::Value::Tensor(pb::TensorProto {
+                            dtype: pb::DataType::DtDouble.into(),
+                            tensor_shape: Some(pb::TensorShapeProto::default()),
+                            double_val: vec![x],
+                            ..Default::default()
+                        })),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })),
+                ..Default::default()
+            })?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        // Loads the run, returning its values and the bytes they take in saved state.
+        let load = |precision: ScalarPrecision| {
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.scalar_precision(precision);
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+            let run_data = run_data.read().unwrap();
+            let values: Vec<f64> = (run_data.scalars[&tag].valid_values())
+                .map(|(_, _, v)| v.0)
+                .collect();
+            let state = RunLoaderState::decode(&loader.save_state(&run_data)[..]).unwrap();
+            let value_bytes: usize = (state.time_series[0].committed.iter())
+                .map(|p| match p.value {
+                    Some(point::Value::Scalar(x)) => std::mem::size_of_val(&x),
+                    Some(point::Value::ScalarDouble(x)) => std::mem::size_of_val(&x),
+                    ref other => panic!("unexpected committed value: {:?}", other),
+                })
+                .sum();
+            (values, value_bytes)
+        };
+
+        let (doubles, double_bytes) = load(ScalarPrecision::F64);
+        assert_eq!(doubles, values);
+        let (singles, single_bytes) = load(ScalarPrecision::F32);
+        for (&single, &x) in singles.iter().zip(&values) {
+            assert_eq!(single, f64::from(x as f32));
+            assert!((single - x).abs() <= x.abs() * f64::from(f32::EPSILON));
+        }
+        assert_ne!(singles, values);
+        assert_eq!(single_bytes * 2, double_bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retention() -> Result<(), Box<dyn std::error::Error>> {
+        const DAY: f64 = 86400.0;
+        let now = std::time::SystemTime::now()
+            .duration_since(UNIX_EPOCH)?
+            .as_secs_f64();
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("uptime".to_string());
+        for (step, age) in [(0, 3.0 * DAY), (1, 2.0 * DAY), (2, 0.1 * DAY)].iter() {
+            let wall_time = WallTime::new(now - age).unwrap();
+            f1.write_scalar(&tag, Step(*step), wall_time, *step as f32)?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let filenames = vec![EventFileBuf(f1_name)];
+        let steps = |run_data: &RwLock<commit::RunData>| -> Vec<Step> {
+            let run_data = run_data.read().unwrap();
+            let ts = &run_data.scalars[&tag];
+            ts.valid_values().map(|(step, _, _)| step).collect()
+        };
+
+        // All points are within a week.
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.retention(Some(Duration::from_secs(7 * 86400)));
+        loader.reload(&logdir, filenames.clone(), &run_data);
+        assert_eq!(steps(&run_data), vec![Step(0), Step(1), Step(2)]);
+        assert_eq!(loader.expired_points(), 0);
+
+        // Once the horizon passes them, a sweep removes the old points with no new data read.
+        loader.retention(Some(Duration::from_secs(86400)));
+        assert_eq!(loader.reload(&logdir, filenames.clone(), &run_data), 0);
+        assert_eq!(steps(&run_data), vec![Step(2)]);
+        assert_eq!(loader.expired_points(), 2);
+        let range = run_data.read().unwrap().scalars[&tag].value_range();
+        assert_eq!(range, Some((2.0, 2.0)));
+
+        // A new loader drops the old points as it reads them.
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.retention(Some(Duration::from_secs(86400)));
+        loader.reload(&logdir, filenames, &run_data);
+        assert_eq!(steps(&run_data), vec![Step(2)]);
+        assert_eq!(loader.expired_points(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_image_and_audio() -> Result<(), Box<dyn std::error::Error>> {
+        use pb::summary::value::Value;
+
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let (image_tag, audio_tag) = (Tag("input".to_string()), Tag("speech".to_string()));
+        f1.write_event(&pb::Event {
+            step: 5,
+            wall_time: 1235.0,
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: vec![
+                    pb::summary::Value {
+                        tag: image_tag.0.clone(),
+                        value: Some(Value::Image(pb::summary::Image {
+                            height: 480,
+                            width: 640,
+                            colorspace: 3,
+                            encoded_image_string: b"\x89PNGabc".to_vec(),
+                        })),
+                        ..Default::default()
+                    },
+                    pb::summary::Value {
+                        tag: audio_tag.0.clone(),
+                        value: Some(Value::Audio(pb::summary::Audio {
+                            sample_rate: 44100.0,
+                            num_channels: 1,
+                            length_frames: 4,
+                            encoded_audio_string: b"RIFFabcd".to_vec(),
+                            content_type: "audio/wav".to_string(),
+                        })),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            })),
+            ..Default::default()
+        })?;
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+        let run_data = run_data.read().unwrap();
+        assert!(run_data.tensor_metadata.is_empty());
+
+        let images = &run_data.blob_sequences[&image_tag];
+        assert_eq!(
+            images.metadata.data_class,
+            i32::from(pb::DataClass::BlobSequence)
+        );
+        assert_eq!(images.plugin_name(), Some(plugin_names::IMAGES));
+        let content = &images.metadata.plugin_data.as_ref().unwrap().content;
+        assert!(pb::ImagePluginData::decode(&content[..])?.converted_to_tensor);
+        let values: Vec<_> = images.valid_values().collect();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].0, Step(5));
+        assert_eq!(
+            values[0].2 .0,
+            vec![b"640".to_vec(), b"480".to_vec(), b"\x89PNGabc".to_vec()]
+        );
+
+        let audio = &run_data.blob_sequences[&audio_tag];
+        assert_eq!(
+            audio.metadata.data_class,
+            i32::from(pb::DataClass::BlobSequence)
+        );
+        assert_eq!(audio.plugin_name(), Some(plugin_names::AUDIO));
+        let content = &audio.metadata.plugin_data.as_ref().unwrap().content;
+        let plugin_data = pb::AudioPluginData::decode(&content[..])?;
+        assert!(plugin_data.converted_to_tensor);
+        assert_eq!(
+            plugin_data.encoding,
+            i32::from(pb::audio_plugin_data::Encoding::Wav)
+        );
+        let values: Vec<_> = audio.valid_values().collect();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].2 .0, vec![b"RIFFabcd".to_vec()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tag_filter() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let wall_time = WallTime::new(1235.0).unwrap();
+        let tags = [
+            "eval/loss",
+            "train/loss",
+            "eval/accuracy",
+            "evaluation",
+            "lr",
+        ];
+        for step in 0..3 {
+            for tag in &tags {
+                f1.write_scalar(&Tag(tag.to_string()), Step(step), wall_time, 0.5)?;
+            }
+        }
+        f1.into_inner()?.sync_all()?;
+
+        let run = Run("train".to_string());
+        let mut loader = RunLoader::new(run.clone());
+        let mut filter = TagFilter::new();
+        filter.include("eval/**");
+        loader.tag_filter(filter);
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let commit = Commit::new();
+        commit
+            .runs
+            .write()
+            .expect("write-locking runs map")
+            .insert(run.clone(), Default::default());
+        let files = vec![EventFileBuf(f1_name.clone())];
+        loader.reload(&logdir, files.clone(), &commit.runs.read().unwrap()[&run]);
+
+        let scalar_tags = || {
+            let runs = commit.runs.read().expect("read-locking runs map");
+            let run_data = runs.get(&run).unwrap().read().unwrap();
+            let mut tags: Vec<String> = run_data.scalars.keys().map(|t| t.0.clone()).collect();
+            tags.sort();
+            tags
+        };
+        assert_eq!(scalar_tags(), vec!["eval/accuracy", "eval/loss"]);
+        // Three steps of the other three tags.
+        assert_eq!(loader.filtered_values(), 9);
+
+        // Existing time series are left alone when they stop passing the filter.
+        let mut filter = TagFilter::new();
+        filter.exclude("eval/loss");
+        loader.tag_filter(filter);
+        let mut f1 = BufWriter::new(std::fs::OpenOptions::new().append(true).open(&f1_name)?);
+        for tag in &tags {
+            f1.write_scalar(&Tag(tag.to_string()), Step(3), wall_time, 0.5)?;
+        }
+        f1.into_inner()?.sync_all()?;
+        loader.reload(&logdir, files, &commit.runs.read().unwrap()[&run]);
+        assert_eq!(
+            scalar_tags(),
+            vec![
+                "eval/accuracy",
+                "eval/loss",
+                "evaluation",
+                "lr",
+                "train/loss"
+            ]
+        );
+        assert_eq!(loader.filtered_values(), 9);
+        let runs = commit.runs.read().expect("read-locking runs map");
+        let run_data = runs.get(&run).unwrap().read().unwrap();
+        let eval_loss = &run_data.scalars[&Tag("eval/loss".to_string())];
+        assert_eq!(eval_loss.valid_values().count(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_tag() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let wall_time = WallTime::new(1235.0).unwrap();
+        let loss = Tag("loss".to_string());
+        let debug = Tag("debug".to_string());
+        for step in 0..3 {
+            f1.write_scalar(&loss, Step(step), wall_time, 0.5)?;
+            f1.write_scalar(&debug, Step(step), wall_time, f32::NAN)?;
+        }
+        f1.into_inner()?.sync_all()?;
+
+        let run = Run("train".to_string());
+        let mut loader = RunLoader::new(run.clone());
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let commit = Commit::new();
+        loader.counters(Some(commit.counters()));
+        commit
+            .runs
+            .write()
+            .expect("write-locking runs map")
+            .insert(run.clone(), Default::default());
+        let files = vec![EventFileBuf(f1_name.clone())];
+        loader.reload(&logdir, files.clone(), &commit.runs.read().unwrap()[&run]);
+
+        let scalar_tags = || {
+            let runs = commit.runs.read().expect("read-locking runs map");
+            let run_data = runs.get(&run).unwrap().read().unwrap();
+            let mut tags: Vec<String> = run_data.scalars.keys().map(|t| t.0.clone()).collect();
+            tags.sort();
+            tags
+        };
+        assert_eq!(scalar_tags(), vec!["debug", "loss"]);
+        assert_eq!(commit.counts().scalar_series, 2);
+
+        assert!(loader.drop_tag(&debug, &commit.runs.read().unwrap()[&run]));
+        assert_eq!(scalar_tags(), vec!["loss"]);
+        assert_eq!(commit.counts().scalar_series, 1);
+        assert_eq!(commit.counts().points, 3);
+        assert!(!loader.drop_tag(&debug, &commit.runs.read().unwrap()[&run]));
+
+        // New values for the dropped tag are ignored.
+        let mut f1 = BufWriter::new(std::fs::OpenOptions::new().append(true).open(&f1_name)?);
+        f1.write_scalar(&loss, Step(3), wall_time, 0.5)?;
+        f1.write_scalar(&debug, Step(3), wall_time, f32::NAN)?;
+        f1.into_inner()?.sync_all()?;
+        loader.reload(&logdir, files, &commit.runs.read().unwrap()[&run]);
+        assert_eq!(scalar_tags(), vec!["loss"]);
+        assert_eq!(loader.filtered_values(), 1);
+        let runs = commit.runs.read().expect("read-locking runs map");
+        let run_data = runs.get(&run).unwrap().read().unwrap();
+        assert_eq!(run_data.scalars[&loss].valid_values().count(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_preview_first_file() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let f2_name = logdir.path().join("tfevents.456");
+        let tag = Tag("loss".to_string());
+        let mut f1 = File::create(&f1_name)?;
+        f1.write_scalar(&tag, Step(0), WallTime::new(1234.0).unwrap(), 0.25)?;
+        f1.write_scalar(&tag, Step(1), WallTime::new(1235.0).unwrap(), 0.5)?;
+        f1.sync_all()?;
+        let mut f2 = File::create(&f2_name)?;
+        f2.write_scalar(&tag, Step(2), WallTime::new(1236.0).unwrap(), 0.75)?;
+        f2.sync_all()?;
+
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let filenames = vec![EventFileBuf(f1_name), EventFileBuf(f2_name)];
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        let steps = |run_data: &RwLock<commit::RunData>| -> Vec<Step> {
+            let run_data = run_data.read().unwrap();
+            run_data.scalars[&tag]
+                .valid_values()
+                .map(|(step, _, _)| step)
+                .collect()
+        };
+
+        loader.preview_first_file(true);
+        assert_eq!(loader.reload(&logdir, filenames.clone(), &run_data), 2);
+        assert_eq!(steps(&run_data), vec![Step(0), Step(1)]);
+        assert_eq!(loader.skipped_files(), 1);
+
+        // Turning preview mode off reads the skipped file from the start.
+        loader.preview_first_file(false);
+        assert_eq!(loader.reload(&logdir, filenames, &run_data), 1);
+        assert_eq!(steps(&run_data), vec![Step(0), Step(1), Step(2)]);
+        assert_eq!(loader.skipped_files(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_activity() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let tag = Tag("loss".to_string());
+        let mut f1 = File::create(&f1_name)?;
+        f1.write_scalar(&tag, Step(0), WallTime::new(1234.0).unwrap(), 0.25)?;
+        f1.sync_all()?;
+
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let filenames = vec![EventFileBuf(f1_name.clone())];
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        let activity = |loader: &RunLoader<_>| -> FileActivity {
+            let all: Vec<_> = loader.file_activity().collect();
+            assert_eq!(all.len(), 1);
+            all[0].1
+        };
+        assert_eq!(loader.last_new_data(), None);
+
+        loader.reload(&logdir, filenames.clone(), &run_data);
+        let first = activity(&loader);
+        assert_eq!(first.checks, 1);
+        assert!(first.last_read.is_some());
+        assert!(first.last_checked <= first.last_read);
+        assert_eq!(loader.last_new_data(), first.last_read);
+
+        // A reload with no new data checks the file again, but doesn't move its last read.
+        loader.reload(&logdir, filenames.clone(), &run_data);
+        let second = activity(&loader);
+        assert_eq!(second.checks, 2);
+        assert!(second.last_checked >= first.last_checked);
+        assert_eq!(second.last_read, first.last_read);
+
+        f1.write_scalar(&tag, Step(1), WallTime::new(1235.0).unwrap(), 0.5)?;
+        f1.sync_all()?;
+        loader.reload(&logdir, filenames, &run_data);
+        let third = activity(&loader);
+        assert_eq!(third.checks, 3);
+        assert!(third.last_read >= second.last_checked);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotation_file_order() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let tag = Tag("loss".to_string());
+        // Oldest data is in the highest-numbered file, and newest in the unnumbered one.
+        let names = ["tfevents.log.2", "tfevents.log.1", "tfevents.log"];
+        let mut filenames = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            let path = logdir.path().join(name);
+            let mut file = File::create(&path)?;
+            for step in (2 * i as i64)..(2 * i as i64 + 2) {
+                let wall_time = WallTime::new(1234.0 + step as f64).unwrap();
+                file.write_scalar(&tag, Step(step), wall_time, 0.5)?;
+            }
+            file.sync_all()?;
+            filenames.push(EventFileBuf(path));
+        }
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let load = |order: EventFileOrder| {
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.file_order(order);
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, filenames.clone(), &run_data);
+            let run_data = run_data.read().unwrap();
+            run_data.scalars[&tag]
+                .valid_values()
+                .map(|(Step(step), _, _)| step)
+                .collect::<Vec<_>>()
+        };
+
+        // By name, the current file is read first, and each older file's lower steps then
+        // preempt the points read before it.
+        assert_eq!(load(EventFileOrder::Name), vec![0, 1]);
+        assert_eq!(load(EventFileOrder::Rotation), vec![0, 1, 2, 3, 4, 5]);
+
+        let mut paths: Vec<PathBuf> = [
+            "b/tfevents.log",
+            "a/tfevents.log.10.gz",
+            "a/tfevents.log",
+            "a/tfevents.log.2",
+            "a/other.tfevents",
+            "a/tfevents.log.x",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        paths.sort_by(|a, b| rotation_key(a).cmp(&rotation_key(b)));
+        assert_eq!(
+            paths,
+            [
+                "a/other.tfevents",
+                "a/tfevents.log.10.gz",
+                "a/tfevents.log.2",
+                "a/tfevents.log",
+                "a/tfevents.log.x",
+                "b/tfevents.log",
+            ]
+            .iter()
+            .map(PathBuf::from)
+            .collect::<Vec<_>>()
+        );
+        assert_eq!("rotation".parse(), Ok(EventFileOrder::Rotation));
+        assert!("mtime".parse::<EventFileOrder>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_deadline() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let f2_name = logdir.path().join("tfevents.456");
+        let tag = Tag("loss".to_string());
+        let mut f1 = File::create(&f1_name)?;
+        for i in 0..250 {
+            let wall_time = WallTime::new(1234.0 + i as f64).unwrap();
+            f1.write_scalar(&tag, Step(i), wall_time, 0.25)?;
+        }
+        f1.sync_all()?;
+        let mut f2 = File::create(&f2_name)?;
+        f2.write_scalar(&tag, Step(250), WallTime::new(1500.0).unwrap(), 0.5)?;
+        f2.sync_all()?;
+
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let filenames = vec![EventFileBuf(f1_name), EventFileBuf(f2_name)];
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        let last_step = |run_data: &RwLock<commit::RunData>| -> Option<Step> {
+            let run_data = run_data.read().unwrap();
+            run_data.scalars[&tag]
+                .valid_values()
+                .map(|(step, _, _)| step)
+                .last()
+        };
+
+        // A deadline that has always passed stops each load at the first check, after 100
+        // events, and commits what was read so far.
+        loader.reload_deadline(Some(Duration::from_secs(0)));
+        assert_eq!(loader.reload(&logdir, filenames.clone(), &run_data), 100);
+        assert_eq!(last_step(&run_data), Some(Step(99)));
+        assert_eq!(loader.reload(&logdir, filenames.clone(), &run_data), 100);
+        assert_eq!(last_step(&run_data), Some(Step(199)));
+
+        // Without a deadline, the next load resumes mid-file and reads through to the end.
+        loader.reload_deadline(None);
+        assert_eq!(loader.reload(&logdir, filenames, &run_data), 51);
+        assert_eq!(last_step(&run_data), Some(Step(250)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sniff() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let f2_name = logdir.path().join("tfevents.456");
+        let wall_time = WallTime::new(1234.0).unwrap();
+        let (loss, images) = (Tag("loss".to_string()), Tag("images".to_string()));
+        let mut f1 = File::create(&f1_name)?;
+        f1.write_scalar(&loss, Step(0), wall_time, 0.25)?;
+        f1.write_graph(Step(0), wall_time, b"<graph>".to_vec())?;
+        let prefix_len = f1.metadata()?.len();
+        // Past the byte limit, so never seen.
+        f1.write_scalar(&Tag("late".to_string()), Step(1), wall_time, 0.5)?;
+        f1.sync_all()?;
+        let mut f2 = File::create(&f2_name)?;
+        f2.write_event(&pb::Event {
+            step: 0,
+            wall_time: 1234.0,
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: vec![pb::summary::Value {
+                    tag: images.0.clone(),
+                    metadata: Some(pb::SummaryMetadata {
+                        plugin_data: Some(pb::summary_metadata::PluginData {
+                            plugin_name: "images".to_string(),
+                            ..Default::default()
+                        }),
+                        data_class: pb::DataClass::BlobSequence.into(),
+                        ..Default::default()
+                    }),
+                    value: Some(pb::summary::value::Value::Tensor(pb::TensorProto {
+                        dtype: pb::DataType::DtString.into(),
+                        string_val: vec![b"1".to_vec(), b"1".to_vec(), b"<png>".to_vec()],
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })),
+            ..Default::default()
+        })?;
+        f2.sync_all()?;
+
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let filenames = vec![EventFileBuf(f1_name), EventFileBuf(f2_name)];
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let series = loader.sniff(&logdir, &filenames, prefix_len);
+        assert_eq!(
+            series,
+            vec![
+                (
+                    Tag(GraphDefValue::TAG_NAME.to_string()),
+                    pb::DataClass::BlobSequence,
+                    "graphs".to_string()
+                ),
+                (images, pb::DataClass::BlobSequence, "images".to_string()),
+                (loss, pb::DataClass::Scalar, "scalars".to_string()),
+            ]
+        );
+
+        // Nothing was staged, so a full load still reads everything.
+        let run_data = RwLock::new(commit::RunData::default());
+        assert_eq!(loader.reload(&logdir, filenames, &run_data), 4);
+        assert_eq!(run_data.read().unwrap().scalars.len(), 2);
+        Ok(())
+    }
+
+    /// An in-memory log directory that counts how many times its files are opened.
+    struct CountingLogdir {
+        files: HashMap<EventFileBuf, Vec<u8>>,
+        opens: std::cell::Cell<usize>,
+    }
+
+    impl Logdir for CountingLogdir {
+        type File = std::io::Cursor<Vec<u8>>;
+
+        fn discover(&self) -> std::io::Result<HashMap<Run, Vec<EventFileBuf>>> {
+            unimplemented!()
+        }
+
+        fn open(&self, path: &EventFileBuf) -> std::io::Result<Self::File> {
+            self.opens.set(self.opens.get() + 1);
+            Ok(std::io::Cursor::new(self.files[path].clone()))
+        }
+    }
+
+    #[test]
+    fn test_max_files() -> Result<(), Box<dyn std::error::Error>> {
+        let tag = Tag("loss".to_string());
+        let mut files = HashMap::new();
+        for i in 0..1001 {
+            let mut data = Vec::new();
+            data.write_scalar(&tag, Step(i), WallTime::new(1234.0).unwrap(), 0.25)?;
+            files.insert(
+                EventFileBuf(PathBuf::from(format!("tfevents.{:04}", i))),
+                data,
+            );
+        }
+        let logdir = CountingLogdir {
+            files,
+            opens: std::cell::Cell::new(0),
+        };
+        let filenames = |n: usize| -> Vec<EventFileBuf> {
+            (0..n)
+                .map(|i| EventFileBuf(PathBuf::from(format!("tfevents.{:04}", i))))
+                .collect()
+        };
+        let steps = |run_data: &RwLock<commit::RunData>| -> Vec<i64> {
+            let run_data = run_data.read().unwrap();
+            let mut steps: Vec<i64> = run_data.scalars[&tag]
+                .valid_values()
+                .map(|(step, _, _)| step.0)
+                .collect();
+            steps.sort_unstable();
+            steps
+        };
+
+        // Marking files dead keeps them around as tombstones.
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.max_files(Some(100));
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, filenames(1000), &run_data);
+        assert_eq!(logdir.opens.get(), 100);
+        assert_eq!(steps(&run_data), (900..1000).collect::<Vec<_>>());
+        let expired = loader
+            .dead_files()
+            .filter(|(_, reason)| *reason == DeadReason::Expired)
+            .count();
+        assert_eq!(expired, 900);
+        loader.reload(&logdir, filenames(1000), &run_data);
+        assert_eq!(logdir.opens.get(), 100);
+
+        // A new file expires the oldest one that has gone quiet.
+        loader.reload(&logdir, filenames(1001), &run_data);
+        assert_eq!(logdir.opens.get(), 101);
+        assert_eq!(loader.dead_files().count(), 901);
+        assert_eq!(steps(&run_data), (900..1001).collect::<Vec<_>>());
+
+        // Forgetting files keeps only a watermark, which survives saving and restoring.
+        logdir.opens.set(0);
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.max_files(Some(100));
+        loader.expired_files(ExpiredFilePolicy::Forget);
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, filenames(1000), &run_data);
+        assert_eq!(logdir.opens.get(), 100);
+        assert_eq!(loader.files.len(), 100);
+        assert_eq!(loader.dead_files().count(), 0);
+        let watermark = Some(EventFileBuf(PathBuf::from("tfevents.0899")));
+        assert_eq!(loader.expired_through, watermark);
+        loader.reload(&logdir, filenames(1000), &run_data);
+        assert_eq!(logdir.opens.get(), 100);
+        assert_eq!(loader.files.len(), 100);
+
+        let state = loader.save_state(&run_data.read().unwrap());
+        let mut restored = RunLoader::new(Run("train".to_string()));
+        restored.max_files(Some(100));
+        restored.expired_files(ExpiredFilePolicy::Forget);
+        restored.restore_state(&state, &RwLock::new(commit::RunData::default()))?;
+        assert_eq!(restored.expired_through, watermark);
+        restored.reload(
+            &logdir,
+            filenames(1000),
+            &RwLock::new(commit::RunData::default()),
+        );
+        assert_eq!(logdir.opens.get(), 200);
+        assert_eq!(restored.files.len(), 100);
+        Ok(())
+    }
+
+    /// A log directory with a single in-memory event file, whose reads fail with the given errors
+    /// once they reach a given offset, one error per read, before going on as normal.
+    struct FlakyLogdir {
+        data: Vec<u8>,
+        fail_at: u64,
+        errors: Vec<std::io::ErrorKind>,
+    }
+
+    struct FlakyFile {
+        data: std::io::Cursor<Vec<u8>>,
+        fail_at: u64,
+        errors: std::collections::VecDeque<std::io::ErrorKind>,
+    }
+
+    impl Logdir for FlakyLogdir {
+        type File = FlakyFile;
+
+        fn discover(&self) -> std::io::Result<HashMap<Run, Vec<EventFileBuf>>> {
+            unimplemented!()
+        }
+
+        fn open(&self, _path: &EventFileBuf) -> std::io::Result<FlakyFile> {
+            Ok(FlakyFile {
+                data: std::io::Cursor::new(self.data.clone()),
+                fail_at: self.fail_at,
+                errors: self.errors.iter().copied().collect(),
+            })
+        }
+    }
+
+    impl Read for FlakyFile {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let pos = self.data.position();
+            if pos < self.fail_at {
+                let n = buf.len().min((self.fail_at - pos) as usize);
+                return self.data.read(&mut buf[..n]);
+            }
+            match self.errors.pop_front() {
+                Some(kind) => Err(std::io::Error::new(kind, "flaky read")),
+                None => self.data.read(buf),
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_retries() -> Result<(), Box<dyn std::error::Error>> {
+        let tag = Tag("loss".to_string());
+        let mut data = Vec::new();
+        data.write_scalar(&tag, Step(0), WallTime::new(1234.0).unwrap(), 0.25)?;
+        let first_len = data.len() as u64;
+        data.write_scalar(&tag, Step(1), WallTime::new(1235.0).unwrap(), 0.5)?;
+        let filename = EventFileBuf(PathBuf::from("tfevents.123"));
+
+        // Fails partway through the second record. Returns the loaded steps and dead files.
+        let load = |errors: Vec<std::io::ErrorKind>, retries: u32| {
+            let logdir = FlakyLogdir {
+                data: data.clone(),
+                fail_at: first_len + 5,
+                errors,
+            };
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.read_retries(retries);
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, vec![filename.clone()], &run_data);
+            let steps: Vec<i64> = run_data.read().unwrap().scalars[&tag]
+                .valid_values()
+                .map(|(step, _, _)| step.0)
+                .collect();
+            let dead: Vec<DeadReason> = loader.dead_files().map(|(_, reason)| reason).collect();
+            (steps, dead)
+        };
+        use std::io::ErrorKind::{InvalidData, Other, TimedOut};
+
+        // A transient error is retried, and reading picks up mid-record.
+        assert_eq!(load(vec![Other], 3), (vec![0, 1], vec![]));
+        assert_eq!(load(vec![Other, TimedOut], 2), (vec![0, 1], vec![]));
+        // Errors that outlast the retries, or can't be fixed by retrying, kill the file.
+        let exhausted = DeadReason::RetriesExhausted { offset: first_len };
+        assert_eq!(load(vec![Other, Other], 1), (vec![0], vec![exhausted]));
+        let dead = DeadReason::ReadError { offset: first_len };
+        assert_eq!(load(vec![InvalidData], 3), (vec![0], vec![dead]));
+        // By default, errors aren't retried at all.
+        assert_eq!(
+            load(vec![Other], DEFAULT_READ_RETRIES),
+            (vec![0], vec![dead])
+        );
+
+        // A retry that would wait past the deadline ends the load, and the next load retries.
+        let logdir = FlakyLogdir {
+            data: data.clone(),
+            fail_at: first_len + 5,
+            errors: vec![Other],
+        };
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.read_retries(3);
+        loader.reload_deadline(Some(Duration::from_millis(10)));
+        let run_data = RwLock::new(commit::RunData::default());
+        let start = Instant::now();
+        assert_eq!(loader.reload(&logdir, vec![filename.clone()], &run_data), 1);
+        assert!(start.elapsed() < READ_RETRY_BACKOFF);
+        assert_eq!(loader.dead_files().count(), 0);
+        assert_eq!(loader.reload(&logdir, vec![filename], &run_data), 1);
+        assert_eq!(
+            run_data.read().unwrap().scalars[&tag]
+                .valid_values()
+                .count(),
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dead_reasons() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let good_name = logdir.path().join("tfevents.1");
+        let corrupt_name = logdir.path().join("tfevents.2");
+        let missing_name = logdir.path().join("tfevents.3");
+        let bad_data_name = logdir.path().join("tfevents.4");
+        let tag = Tag("loss".to_string());
+        let mut good = File::create(&good_name)?;
+        good.write_scalar(&tag, Step(0), WallTime::new(1234.0).unwrap(), 0.25)?;
+        good.sync_all()?;
+        // One good record, then a bad length checksum.
+        let mut corrupt = File::create(&corrupt_name)?;
+        corrupt.write_scalar(&tag, Step(1), WallTime::new(1235.0).unwrap(), 0.5)?;
+        let corrupt_offset = corrupt.metadata()?.len();
+        corrupt.write_all(&[0xff; 16])?;
+        corrupt.sync_all()?;
+        // A record whose data checksum doesn't match.
+        let mut bad_data = Vec::new();
+        bad_data.write_scalar(&tag, Step(2), WallTime::new(1236.0).unwrap(), 0.75)?;
+        *bad_data.last_mut().unwrap() ^= 1;
+        std::fs::write(&bad_data_name, &bad_data)?;
+
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        let dead = |loader: &RunLoader<_>| -> Vec<(PathBuf, DeadReason)> {
+            loader
+                .dead_files()
+                .map(|(filename, reason)| (filename.0.clone(), reason))
+                .collect()
+        };
+
+        let filenames = vec![
+            EventFileBuf(good_name.clone()),
+            EventFileBuf(corrupt_name.clone()),
+            EventFileBuf(missing_name.clone()),
+            EventFileBuf(bad_data_name.clone()),
+        ];
+        loader.reload(&logdir, filenames, &run_data);
+        let bad_length = DeadReason::BadChecksum {
+            offset: corrupt_offset,
+        };
+        let open_failed = DeadReason::OpenFailed(std::io::ErrorKind::NotFound);
+        let bad_data = DeadReason::BadChecksum { offset: 0 };
+        assert_eq!(
+            dead(&loader),
+            vec![
+                (corrupt_name.clone(), bad_length),
+                (missing_name.clone(), open_failed),
+                (bad_data_name.clone(), bad_data),
+            ]
+        );
+
+        // Dropping files from the run kills the live one and keeps earlier reasons.
+        loader.reload(&logdir, vec![], &run_data);
+        let removed = DeadReason::RemovedFromDisk;
+        assert_eq!(
+            dead(&loader),
+            vec![
+                (good_name.clone(), removed),
+                (corrupt_name.clone(), bad_length),
+                (missing_name.clone(), open_failed),
+                (bad_data_name.clone(), bad_data),
+            ]
+        );
+
+        // Reasons survive saving and restoring, except for the kind of open error.
+        let state = loader.save_state(&run_data.read().unwrap());
+        let mut restored = RunLoader::new(Run("train".to_string()));
+        restored.restore_state(&state, &RwLock::new(commit::RunData::default()))?;
+        let open_failed = DeadReason::OpenFailed(std::io::ErrorKind::Other);
+        assert_eq!(
+            dead(&restored),
+            vec![
+                (good_name, removed),
+                (corrupt_name, bad_length),
+                (missing_name, open_failed),
+                (bad_data_name, bad_data),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_strict() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let good_name = logdir.path().join("tfevents.1");
+        let corrupt_name = logdir.path().join("tfevents.2");
+        let tag = Tag("loss".to_string());
+        let mut good = File::create(&good_name)?;
+        good.write_scalar(&tag, Step(0), WallTime::new(1234.0).unwrap(), 0.25)?;
+        good.sync_all()?;
+        let mut corrupt = File::create(&corrupt_name)?;
+        corrupt.write_scalar(&tag, Step(1), WallTime::new(1235.0).unwrap(), 0.5)?;
+        let corrupt_offset = corrupt.metadata()?.len();
+        corrupt.write_all(&[0xff; 16])?;
+        corrupt.sync_all()?;
+        let filenames = vec![
+            EventFileBuf(good_name.clone()),
+            EventFileBuf(corrupt_name.clone()),
+        ];
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        // Normally, the bad tail is skipped.
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        assert_eq!(loader.try_reload(&logdir, filenames.clone(), &run_data)?, 2);
+        assert_eq!(
+            run_data.read().unwrap().scalars[&tag]
+                .basin
+                .as_slice()
+                .len(),
+            2
+        );
+
+        // In strict mode, it aborts the load, but what was read before it is committed.
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.strict(true);
+        let run_data = RwLock::new(commit::RunData::default());
+        match loader.try_reload(&logdir, filenames.clone(), &run_data) {
+            Err(LoadError::Read { path, offset, .. }) => {
+                assert_eq!((path, offset), (corrupt_name, corrupt_offset));
+            }
+            other => panic!("{:?}", other),
+        }
+        let steps = |run_data: &RwLock<commit::RunData>| -> Vec<i64> {
+            run_data.read().unwrap().scalars[&tag]
+                .valid_values()
+                .map(|(step, _, _)| step.0)
+                .collect()
+        };
+        assert_eq!(steps(&run_data), vec![0, 1]);
+
+        // The bad file stays abandoned, so the next load succeeds with nothing new.
+        assert_eq!(loader.try_reload(&logdir, filenames, &run_data)?, 0);
+        assert_eq!(steps(&run_data), vec![0, 1]);
+        assert_eq!(loader.dead_files().count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_strict_dropped_event() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        f1.write_scalar(&tag, Step(0), WallTime::new(1234.0).unwrap(), 0.25)?;
+        f1.flush()?;
+        let empty_offset = f1.get_ref().metadata()?.len();
+        f1.write_event(&pb::Event {
+            step: 1,
+            wall_time: 1235.0,
+            ..Default::default()
+        })?;
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.strict(true);
+        let run_data = RwLock::new(commit::RunData::default());
+        match loader.try_reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data) {
+            Err(LoadError::DroppedEvent { path, offset, .. }) => {
+                assert_eq!((path, offset), (f1_name, empty_offset));
+            }
+            other => panic!("{:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pause_resume() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let f2_name = logdir.path().join("tfevents.456");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        for i in 0..3 {
+            let wall_time = WallTime::new(1235.0 + i as f64).unwrap();
+            f1.write_scalar(&tag, Step(i), wall_time, 0.25)?;
+        }
+        f1.flush()?;
+
+        let run = Run("train".to_string());
+        let mut loader = RunLoader::new(run.clone());
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let commit = Commit::new();
+        commit
+            .runs
+            .write()
+            .expect("write-locking runs map")
+            .insert(run.clone(), Default::default());
+        let steps = |commit: &Commit| -> Vec<Step> {
+            let runs = commit.runs.read().expect("read-locking runs map");
+            let run_data = runs.get(&run).unwrap().read().unwrap();
+            run_data.scalars[&tag]
+                .valid_values()
+                .map(|(step, _, _)| step)
+                .collect()
+        };
+        loader.reload(
+            &logdir,
+            vec![EventFileBuf(f1_name.clone())],
+            &commit.runs.read().unwrap()[&run],
+        );
+        assert_eq!(steps(&commit), vec![Step(0), Step(1), Step(2)]);
+
+        // Write more data to the existing file and to a new file while paused.
+        for i in 3..5 {
+            let wall_time = WallTime::new(1235.0 + i as f64).unwrap();
+            f1.write_scalar(&tag, Step(i), wall_time, 0.25)?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let mut f2 = BufWriter::new(File::create(&f2_name)?);
+        f2.write_scalar(&tag, Step(5), WallTime::new(1240.0).unwrap(), 0.25)?;
+        f2.into_inner()?.sync_all()?;
+
+        loader.pause();
+        assert!(loader.is_paused());
+        let filenames = vec![EventFileBuf(f1_name), EventFileBuf(f2_name)];
+        loader.reload(
+            &logdir,
+            filenames.clone(),
+            &commit.runs.read().unwrap()[&run],
+        );
+        assert_eq!(steps(&commit), vec![Step(0), Step(1), Step(2)]);
+
+        loader.resume();
+        assert!(!loader.is_paused());
+        loader.reload(&logdir, filenames, &commit.runs.read().unwrap()[&run]);
+        assert_eq!(steps(&commit), (0..6).map(Step).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        for i in 0..3 {
+            let wall_time = WallTime::new(1235.0 + i as f64).unwrap();
+            f1.write_scalar(&tag, Step(i), wall_time, 0.25 * i as f32)?;
+        }
+        f1.flush()?;
+
+        let run = Run("train".to_string());
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let filenames = vec![EventFileBuf(f1_name)];
+        // Loads the current contents of the log directory into a fresh commit.
+        let load = |loader: &mut RunLoader<_>| -> u64 {
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, filenames.clone(), &run_data);
+            let fingerprint = run_data.read().unwrap().fingerprint();
+            fingerprint
+        };
+
+        let fp1 = load(&mut RunLoader::new(run.clone()));
+        let fp2 = load(&mut RunLoader::new(run.clone()));
+        assert_eq!(fp1, fp2);
+        assert_ne!(fp1, commit::RunData::default().fingerprint());
+
+        f1.write_scalar(&tag, Step(3), WallTime::new(1238.0).unwrap(), 0.75)?;
+        f1.flush()?;
+        let fp3 = load(&mut RunLoader::new(run.clone()));
+        assert_ne!(fp1, fp3);
+        assert_eq!(fp3, load(&mut RunLoader::new(run)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary_descriptions() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let metadata = pb::SummaryMetadata {
+            display_name: "Loss".to_string(),
+            summary_description: "Training loss, *per batch*.".to_string(),
+            ..Default::default()
+        };
+        for (step, tag, value) in &[
+            (0, "loss", pb::summary::value::Value::SimpleValue(0.5)),
+            (
+                0,
+                "loss_tensor",
+                pb::summary::value::Value::Tensor(pb::TensorProto {
+                    dtype: pb::DataType::DtFloat.into(),
+                    float_val: vec![0.5],
+                    ..Default::default()
+                }),
+            ),
+        ] {
+            let mut metadata = metadata.clone();
+            if *tag == "loss_tensor" {
+                metadata.plugin_data = Some(pb::summary_metadata::PluginData {
+                    plugin_name: plugin_names::SCALARS.to_string(),
+                    ..Default::default()
+                });
+            }
+            f1.write_event(&pb::Event {
+                step: *step,
+                wall_time: 1235.0,
+                what: Some(pb::event::What::Summary(pb::Summary {
+                    value: vec![pb::summary::Value {
+                        tag: tag.to_string(),
+                        metadata: Some(metadata),
+                        value: Some(value.clone()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })),
+                ..Default::default()
+            })?;
+        }
+        f1.flush()?;
+
+        let run = Run("train".to_string());
+        let mut loader = RunLoader::new(run);
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+
+        let run_data = run_data.read().unwrap();
+        for tag in &["loss", "loss_tensor"] {
+            let ts = &run_data.scalars[*tag];
+            assert_eq!(ts.display_name(), "Loss");
+            assert_eq!(ts.summary_description(), "Training loss, *per batch*.");
+            assert_eq!(
+                ts.metadata.data_class,
+                i32::from(pb::DataClass::Scalar),
+                "tag {:?}",
+                tag
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_after_eviction() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        f1.write_scalar(&tag, Step(0), WallTime::new(1235.0).unwrap(), 0.25)?;
+        f1.write_graph(Step(0), WallTime::new(1235.0).unwrap(), vec![1u8; 100])?;
+        f1.into_inner()?.sync_all()?;
+
+        let run = Run("train".to_string());
+        let mut loader = RunLoader::new(run.clone());
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let mut commit = Commit::new();
+        commit.memory_budget(Some(0));
+        commit
+            .runs
+            .write()
+            .expect("write-locking runs map")
+            .insert(run.clone(), Default::default());
+        let filenames = vec![EventFileBuf(f1_name)];
+        let graph_tag = reserved_key(GraphDefValue::TAG_NAME);
+        let counts = |commit: &Commit| -> (usize, usize, bool) {
+            let runs = commit.runs.read().expect("read-locking runs map");
+            let run_data = runs.get(&run).unwrap().read().unwrap();
+            (
+                run_data.scalars[&tag].valid_values().count(),
+                run_data.blob_sequences[&graph_tag].valid_values().count(),
+                run_data.evicted.is_some(),
+            )
+        };
+
+        loader.reload(
+            &logdir,
+            filenames.clone(),
+            &commit.runs.read().unwrap()[&run],
+        );
+        assert_eq!(counts(&commit), (1, 1, false));
+        assert_eq!(commit.enforce_memory_budget(), vec![run.clone()]);
+        assert_eq!(counts(&commit), (1, 0, true));
+
+        // Not accessed since eviction, so reloading doesn't restore anything.
+        loader.reload(
+            &logdir,
+            filenames.clone(),
+            &commit.runs.read().unwrap()[&run],
+        );
+        assert_eq!(counts(&commit), (1, 0, true));
+
+        {
+            let runs = commit.runs.read().unwrap();
+            commit.touch(&runs[&run].read().unwrap());
+        }
+        loader.reload(&logdir, filenames, &commit.runs.read().unwrap()[&run]);
+        assert_eq!(counts(&commit), (1, 1, false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_commit_without_new_data() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::{mpsc, Arc};
+
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        f1.write_scalar(&tag, Step(0), WallTime::new(1235.0).unwrap(), 0.25)?;
+        f1.flush()?;
+
+        let run = Run("train".to_string());
+        let mut loader = RunLoader::new(run);
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let filenames = vec![EventFileBuf(f1_name)];
+        let run_data = Arc::new(RwLock::new(commit::RunData::default()));
+        loader.reload(&logdir, filenames.clone(), &run_data);
+        assert_eq!(
+            run_data.read().unwrap().scalars[&tag]
+                .valid_values()
+                .count(),
+            1
+        );
+
+        // Reload on another thread while holding a read lock here. If the reload tried to take
+        // the write lock, it would block until the timeout.
+        let guard = run_data.read().unwrap();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn({
+            let run_data = Arc::clone(&run_data);
+            move || {
+                loader.reload(&logdir, filenames.clone(), &run_data);
+                tx.send((loader, logdir, filenames)).unwrap();
+            }
+        });
+        let (mut loader, logdir, filenames) = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("reload with no new data should not take the write lock");
+        drop(guard);
+
+        // With new data, the reload commits as usual.
+        f1.write_scalar(&tag, Step(1), WallTime::new(1236.0).unwrap(), 0.5)?;
+        f1.flush()?;
+        loader.reload(&logdir, filenames, &run_data);
+        assert_eq!(
+            run_data.read().unwrap().scalars[&tag]
+                .valid_values()
+                .count(),
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_restore_state() -> Result<(), Box<dyn std::error::Error>> {
+        use std::fs::OpenOptions;
+
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let f2_name = logdir.path().join("tfevents.456");
+        let scalars = Tag("loss".to_string());
+        let graphs = reserved_key(GraphDefValue::TAG_NAME);
+        // Appends points for steps in `range` to the given file, plus the first `partial` bytes of
+        // the point at `range.end`. Returns the rest of that point.
+        let append = |path: &Path, range: std::ops::Range<i64>, partial: usize| {
+            let mut buf = Vec::new();
+            for step in range.clone() {
+                let wall_time = WallTime::new(1000.0 + step as f64).unwrap();
+                buf.write_scalar(&scalars, Step(step), wall_time, step as f32)?;
+                if step % 7 == 0 {
+                    buf.write_graph(Step(step), wall_time, step.to_le_bytes().to_vec())?;
+                }
+            }
+            let mut next = Vec::new();
+            let wall_time = WallTime::new(1000.0 + range.end as f64).unwrap();
+            next.write_scalar(&scalars, Step(range.end), wall_time, 0.0)?;
+            buf.extend(&next[..partial]);
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(&buf)?;
+            file.sync_all()?;
+            Ok::<_, std::io::Error>(next[partial..].to_vec())
+        };
+        let finish = |path: &Path, rest: Vec<u8>| -> std::io::Result<()> {
+            let mut file = OpenOptions::new().append(true).open(path)?;
+            file.write_all(&rest)
+        };
+        let disk_logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let run = Run("train".to_string());
+
+        // Load enough points that the reservoirs have to sample, then read more without committing,
+        // so that some points are staged when the state is saved.
+        let rest = append(&f1_name, 0..1500, 10)?;
+        let mut loader = RunLoader::new(run.clone());
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&disk_logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+        finish(&f1_name, rest)?;
+        let rest = append(&f1_name, 1501..1800, 20)?;
+        loader.update_file_set(&disk_logdir, vec![EventFileBuf(f1_name.clone())])?;
+        loader
+            .reload_files(None, |data, event| {
+                data.read_event(event);
+                true
+            })
+            .unwrap();
+        assert!(!loader.data.time_series[&scalars]
+            .rsv
+            .staged_items()
+            .is_empty());
+        let state = loader.save_state(&run_data.read().unwrap());
+
+        // Both the original loader and one restored from its state read the same new data.
+        finish(&f1_name, rest)?;
+        let rest = append(&f1_name, 1801..2500, 0)?;
+        assert!(!rest.is_empty());
+        append(&f2_name, 2500..3000, 0)?;
+        let filenames = vec![EventFileBuf(f1_name.clone()), EventFileBuf(f2_name.clone())];
+        loader.reload(&disk_logdir, filenames.clone(), &run_data);
+
+        let mut restored = RunLoader::new(run);
+        let restored_data = RwLock::new(commit::RunData::default());
+        restored.restore_state(&state, &restored_data)?;
+        restored.reload(&disk_logdir, filenames, &restored_data);
+
+        let (expected, actual) = (run_data.read().unwrap(), restored_data.read().unwrap());
+        assert_eq!(actual.start_time, expected.start_time);
+        assert_eq!(actual.fingerprint(), expected.fingerprint());
+        assert_eq!(expected.scalars[&scalars].valid_values().count(), 1000);
+        assert_eq!(expected.blob_sequences[&graphs].valid_values().count(), 10);
+        let steps = |ts: &commit::TimeSeries<commit::ScalarValue>| {
+            ts.valid_values().map(|(s, _, _)| s).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            steps(&actual.scalars[&scalars]),
+            steps(&expected.scalars[&scalars])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_state_errors() {
+        let run_data = RwLock::new(commit::RunData::default());
+        let mut loader = RunLoader::<File>::new(Run("train".to_string()));
+        let err = loader.restore_state(b"\xff\xff", &run_data).unwrap_err();
+        assert!(matches!(err, RestoreStateError::Decode(_)), "{:?}", err);
+
+        let mut state = RunLoaderState {
+            version: loader_state::VERSION + 1,
+            ..Default::default()
+        };
+        let encode = |state: &RunLoaderState| {
+            let mut buf = Vec::new();
+            state.encode(&mut buf).unwrap();
+            buf
+        };
+        let err = loader
+            .restore_state(&encode(&state), &run_data)
+            .unwrap_err();
+        assert!(
+            matches!(err, RestoreStateError::UnsupportedVersion(v) if v == loader_state::VERSION + 1),
+            "{:?}",
+            err
+        );
+
+        // More staged points than the reservoir can hold.
+        state.version = loader_state::VERSION;
+        let point = |step| loader_state::Point {
+            step,
+            wall_time: 1234.0,
+            value: Some(point::Value::GraphDef(vec![])),
+        };
+        state.time_series.push(loader_state::TimeSeriesState {
+            tag: GraphDefValue::TAG_NAME.to_string(),
+            metadata: Some(*GraphDefValue::initial_metadata()),
+            seen: 100,
+            rng_position: vec![0; 16],
+            committed: vec![],
+            staged: (0..20).map(point).collect(),
+        });
+        let err = loader
+            .restore_state(&encode(&state), &run_data)
+            .unwrap_err();
+        assert!(matches!(err, RestoreStateError::Invalid(_)), "{:?}", err);
+        assert!(loader.data.time_series.is_empty());
+
+        state.time_series[0].staged.truncate(5);
+        loader.restore_state(&encode(&state), &run_data).unwrap();
+        assert_eq!(loader.data.time_series.len(), 1);
+    }
+
+    #[test]
+    fn test_file_versions() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let names = ["tfevents.1", "tfevents.2", "tfevents.3"];
+        let versions = ["brain.Event:2", "brain.Event:1", "brain.Event:2"];
+        for (name, version) in names.iter().zip(&versions) {
+            let mut file = File::create(logdir.path().join(name))?;
+            file.write_event(&pb::Event {
+                wall_time: 1234.0,
+                what: Some(pb::event::What::FileVersion(version.to_string())),
+                ..Default::default()
+            })?;
+            file.sync_all()?;
+        }
+        let filenames: Vec<EventFileBuf> = names
+            .iter()
+            .map(|name| EventFileBuf(logdir.path().join(name)))
+            .collect();
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, filenames, &run_data);
+        let expected = vec!["brain.Event:2".to_string(), "brain.Event:1".to_string()];
+        assert_eq!(run_data.read().unwrap().file_versions, expected);
+
+        // File versions survive a save and restore.
+        let state = loader.save_state(&run_data.read().unwrap());
+        let mut restored = RunLoader::<File>::new(Run("train".to_string()));
+        let restored_data = RwLock::new(commit::RunData::default());
+        restored.restore_state(&state, &restored_data)?;
+        assert_eq!(restored_data.read().unwrap().file_versions, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlimited_sample_sizes() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        for step in 0..5000 {
+            let wall_time = WallTime::new(1234.0 + step as f64).unwrap();
+            f1.write_scalar(&tag, Step(step), wall_time, step as f32)?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.sample_sizes(SampleSizes::unlimited());
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+        let points: Vec<(i64, f64)> = run_data.read().unwrap().scalars[&tag]
+            .valid_values()
+            .map(|(step, _, value)| (step.0, value.0))
+            .collect();
+        let expected: Vec<(i64, f64)> = (0..5000).map(|i| (i, i as f64)).collect();
+        assert_eq!(points, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_provenance() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let f2_name = logdir.path().join("tfevents.456");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let mut f2 = BufWriter::new(File::create(&f2_name)?);
+        // Same points as in the main test: the second file preempts steps 2 and 3.
+        let tag = Tag("accuracy".to_string());
         f1.write_scalar(&tag, Step(0), WallTime::new(1235.0).unwrap(), 0.25)?;
         f1.write_scalar(&tag, Step(1), WallTime::new(1236.0).unwrap(), 0.50)?;
         f1.write_scalar(&tag, Step(2), WallTime::new(1237.0).unwrap(), 0.75)?;
         f1.write_scalar(&tag, Step(3), WallTime::new(1238.0).unwrap(), 1.00)?;
-        // preempt!
         f2.write_scalar(&tag, Step(2), WallTime::new(2346.0).unwrap(), 0.70)?;
         f2.write_scalar(&tag, Step(3), WallTime::new(2347.0).unwrap(), 0.85)?;
         f2.write_scalar(&tag, Step(4), WallTime::new(2348.0).unwrap(), 0.90)?;
-        // flush, so that the data's there when we read it
         f1.into_inner()?.sync_all()?;
-        f2.into_inner()?.sync_all()?;
+        f2.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let filenames = vec![EventFileBuf(f1_name.clone()), EventFileBuf(f2_name.clone())];
+
+        let run = Run("train".to_string());
+        let commit = Commit::new();
+        commit
+            .runs
+            .write()
+            .unwrap()
+            .insert(run.clone(), Default::default());
+        let mut loader = RunLoader::new(run.clone());
+        loader.provenance(true);
+        loader.reload(
+            &logdir,
+            filenames.clone(),
+            &commit.runs.read().unwrap()[&run],
+        );
+
+        let (files, offsets): (Vec<PathBuf>, Vec<u64>) = (0..=4)
+            .map(|i| commit.provenance(&run, &tag, Step(i)).unwrap())
+            .unzip();
+        assert_eq!(
+            files,
+            vec![
+                f1_name.clone(),
+                f1_name,
+                f2_name.clone(),
+                f2_name.clone(),
+                f2_name
+            ]
+        );
+        // Points are at the start of successive records. Records in the second file all have the
+        // same length, since their fields do.
+        let record_len = offsets[3];
+        assert!(offsets[1] > 0 && record_len > 0, "{:?}", offsets);
+        assert_eq!(offsets, vec![0, offsets[1], 0, record_len, 2 * record_len]);
+        assert_eq!(commit.provenance(&run, &tag, Step(5)), None);
+        assert_eq!(commit.provenance(&run, &Tag("xent".into()), Step(0)), None);
+
+        // Provenance is off by default.
+        let run_data = RwLock::new(commit::RunData::default());
+        RunLoader::new(run).reload(&logdir, filenames, &run_data);
+        let run_data = run_data.into_inner().unwrap();
+        assert_eq!(run_data.event_files, Vec::<PathBuf>::new());
+        assert_eq!(run_data.scalars[&tag].provenance, None);
+        assert_eq!(run_data.scalars[&tag].valid_values().count(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blob_references() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let wall_time = WallTime::new(1234.0).unwrap();
+        f1.write_graph(Step(0), wall_time, b"<sample model graph>".to_vec())?;
+        // The image is second as written, but first once the repeated tag is dropped.
+        use pb::summary::value::Value;
+        let value = |tag: &str, value| pb::summary::Value {
+            tag: tag.to_string(),
+            value: Some(value),
+            ..Default::default()
+        };
+        let image = pb::summary::Image {
+            width: 28,
+            height: 14,
+            encoded_image_string: b"\x89PNGabc".to_vec(),
+            ..Default::default()
+        };
+        f1.write_event(&pb::Event {
+            step: 1,
+            wall_time: 1235.0,
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: vec![
+                    value("loss", Value::SimpleValue(1.0)),
+                    value("input", Value::Image(image)),
+                    value("loss", Value::SimpleValue(2.0)),
+                ],
+                ..Default::default()
+            })),
+            ..Default::default()
+        })?;
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.blob_references(true);
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+        let run_data = run_data.into_inner().unwrap();
+
+        assert!(run_data.blob_sequences.is_empty());
+        let loss = &run_data.scalars[&Tag("loss".to_string())];
+        let losses: Vec<f64> = loss.valid_values().map(|(_, _, v)| v.0).collect();
+        assert_eq!(losses, vec![2.0]);
+        let read = |tag: &str| {
+            let ts = &run_data.blob_references[&Tag(tag.to_string())];
+            let (_, _, reference) = ts.valid_values().next().unwrap();
+            assert_eq!(reference.file, EventFileBuf(f1_name.clone()));
+            (
+                reference.clone(),
+                reference.read_blob(&logdir, &ts.metadata),
+            )
+        };
+        let (graph, blobs) = read(&reserved_key(GraphDefValue::TAG_NAME).0);
+        assert_eq!((graph.offset, graph.value, graph.count), (0, 0, 1));
+        assert_eq!(blobs?.0, vec![b"<sample model graph>".to_vec()]);
+        let (input, blobs) = read("input");
+        assert_eq!(
+            (input.offset, input.value, input.count),
+            (graph.length, 1, 3)
+        );
+        let expected = vec![b"28".to_vec(), b"14".to_vec(), b"\x89PNGabc".to_vec()];
+        assert_eq!(blobs?.0, expected);
+
+        // A reference that no longer matches its record fails rather than reading other data.
+        let stale = commit::BlobReference {
+            length: input.length + 1,
+            ..input
+        };
+        let err = stale
+            .read_blob(
+                &logdir,
+                &run_data.blob_references[&Tag("input".to_string())].metadata,
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // Loaders in this mode don't save state.
+        assert!(loader.state(&run_data).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_blob_references_with_event_interceptor() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        use pb::summary::value::Value;
+        let value = |tag: &str, value| pb::summary::Value {
+            tag: tag.to_string(),
+            value: Some(value),
+            ..Default::default()
+        };
+        let image = |bytes: &[u8]| {
+            Value::Image(pb::summary::Image {
+                width: 1,
+                height: 1,
+                encoded_image_string: bytes.to_vec(),
+                ..Default::default()
+            })
+        };
+        for (step, secret) in [(0, false), (1, true), (2, false)].iter().copied() {
+            let mut values = vec![value("input", image(format!("png{}", step).as_bytes()))];
+            if secret {
+                values.insert(0, value("secret", image(b"private")));
+            }
+            f1.write_event(&pb::Event {
+                step,
+                wall_time: 1234.0,
+                what: Some(pb::event::What::Summary(pb::Summary {
+                    value: values,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            })?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        // Dropping a value shifts the others, so the changed event can't be referenced.
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.blob_references(true);
+        loader.event_interceptor(|mut e| {
+            if let Some(pb::event::What::Summary(sum)) = &mut e.what {
+                sum.value.retain(|v| v.tag != "secret");
+            }
+            Some(e)
+        });
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+        let run_data = run_data.into_inner().unwrap();
+
+        assert_eq!(
+            run_data.blob_references.keys().collect::<Vec<_>>(),
+            vec![&Tag("input".to_string())]
+        );
+        let ts = &run_data.blob_references[&Tag("input".to_string())];
+        assert_eq!(ts.basin.as_slice().len(), 3);
+        let blobs = ts
+            .valid_values()
+            .map(|(step, _, reference)| {
+                let blobs = reference.read_blob(&logdir, &ts.metadata)?;
+                Ok((step, blobs.0[2].clone()))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        assert_eq!(
+            blobs,
+            vec![(Step(0), b"png0".to_vec()), (Step(2), b"png2".to_vec())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let tag = Tag("loss".to_string());
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let mut f1 = File::create(&f1_name)?;
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+
+        // Writes points at `steps` and loads them, committing once.
+        let mut write_and_load = |steps: std::ops::Range<i64>| -> std::io::Result<()> {
+            for i in steps {
+                let wall_time = WallTime::new(1234.0 + i as f64).unwrap();
+                f1.write_scalar(&tag, Step(i), wall_time, i as f32)?;
+            }
+            f1.sync_all()?;
+            loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+            Ok(())
+        };
+        write_and_load(0..10)?;
+        // Two preemptions, each of which leaves a little room in the basin.
+        write_and_load(8..9)?;
+        write_and_load(7..8)?;
+
+        let mut run_data = run_data.into_inner().unwrap();
+        let points = |run_data: &commit::RunData| -> Vec<(Step, WallTime, f64)> {
+            run_data.scalars[&tag]
+                .valid_values()
+                .map(|(step, wall_time, value)| (step, wall_time, value.0))
+                .collect()
+        };
+        let before = points(&run_data);
+        assert_eq!(
+            before.iter().map(|(step, _, _)| step.0).collect::<Vec<_>>(),
+            (0..=7).collect::<Vec<_>>()
+        );
+        assert!(run_data.compact_all() > 0);
+        assert_eq!(points(&run_data), before);
+        // Nothing left to reclaim.
+        assert_eq!(run_data.compact_all(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_range() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let tag = Tag("loss".to_string());
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let mut f1 = File::create(&f1_name)?;
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+
+        let mut write_and_load = |points: &[(i64, f32)]| -> std::io::Result<()> {
+            for &(step, value) in points {
+                let wall_time = WallTime::new(1234.0 + step as f64).unwrap();
+                f1.write_scalar(&tag, Step(step), wall_time, value)?;
+            }
+            f1.sync_all()?;
+            loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+            Ok(())
+        };
+        let range = |run_data: &RwLock<commit::RunData>| {
+            run_data.read().unwrap().scalars[&tag].value_range()
+        };
+
+        write_and_load(&[(0, 0.25), (1, 1.0), (2, 0.5), (3, f32::NAN)])?;
+        assert_eq!(range(&run_data), Some((0.25, 1.0)));
+        write_and_load(&[(4, f32::INFINITY), (5, -2.0)])?;
+        assert_eq!(range(&run_data), Some((-2.0, 1.0)));
+        // Preempting the minimum shrinks the range again.
+        write_and_load(&[(5, 0.75)])?;
+        assert_eq!(range(&run_data), Some((0.25, 1.0)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resume_after() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        for i in 0..=100 {
+            f1.write_scalar(&tag, Step(i), WallTime::new(i as f64).unwrap(), i as f32)?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.resume_after(WallTime::new(50.0).unwrap());
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+        let run_data = run_data.into_inner().unwrap();
+        let steps: Vec<i64> = run_data.scalars[&tag]
+            .valid_values()
+            .map(|(step, _, _)| step.0)
+            .collect();
+        assert_eq!(steps, (51..=100).collect::<Vec<_>>());
+        // Skipped events still count toward the start time.
+        assert_eq!(run_data.start_time, Some(WallTime::new(0.0).unwrap()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_steps() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let tag = Tag("loss".to_string());
+        let append = |points: &[(i64, f64, f32)]| -> std::io::Result<()> {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&f1_name)?;
+            let mut f1 = BufWriter::new(file);
+            for &(step, wall_time, value) in points {
+                let wall_time = WallTime::new(wall_time).unwrap();
+                f1.write_scalar(&tag, Step(step), wall_time, value)?;
+            }
+            f1.into_inner()?.sync_all()
+        };
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let files = vec![EventFileBuf(f1_name.clone())];
+
+        // Loads the two batches of points in turn, returning the (step, wall time, value) of each
+        // committed point after each.
+        type Points = Vec<(i64, f64, f64)>;
+        let load = |policy: DuplicateStepPolicy| -> std::io::Result<(Points, Points)> {
+            std::fs::remove_file(&f1_name).ok();
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.duplicate_steps(policy);
+            let run_data = RwLock::new(commit::RunData::default());
+            let mut reload = |points: &[(i64, f64, f32)]| -> std::io::Result<Points> {
+                append(points)?;
+                loader.reload(&logdir, files.clone(), &run_data);
+                let run_data = run_data.read().unwrap();
+                Ok(run_data.scalars[&tag]
+                    .valid_values()
+                    .map(|(step, wall_time, v)| (step.0, wall_time.into(), v.0))
+                    .collect())
+            };
+            // Two values at step 5, committed together, the first with the later wall time.
+            let first = reload(&[(4, 1000.0, 0.0), (5, 1020.0, 1.0), (5, 1010.0, 2.0)])?;
+            // Another at step 5, merged with those already committed.
+            let second = reload(&[(5, 1030.0, 3.0), (6, 1040.0, 4.0)])?;
+            Ok((first, second))
+        };
+
+        assert_eq!(
+            load(DuplicateStepPolicy::PreferNewerWallTime)?,
+            (
+                vec![(4, 1000.0, 0.0), (5, 1020.0, 1.0)],
+                vec![(4, 1000.0, 0.0), (5, 1030.0, 3.0), (6, 1040.0, 4.0)],
+            )
+        );
+        assert_eq!(
+            load(DuplicateStepPolicy::PreferOlder)?,
+            (
+                vec![(4, 1000.0, 0.0), (5, 1010.0, 2.0)],
+                vec![(4, 1000.0, 0.0), (5, 1010.0, 2.0), (6, 1040.0, 4.0)],
+            )
+        );
+        assert_eq!(
+            load(DuplicateStepPolicy::KeepBoth)?,
+            (
+                vec![(4, 1000.0, 0.0), (5, 1010.0, 2.0), (5, 1020.0, 1.0)],
+                vec![
+                    (4, 1000.0, 0.0),
+                    (5, 1010.0, 2.0),
+                    (5, 1020.0, 1.0),
+                    (5, 1030.0, 3.0),
+                    (6, 1040.0, 4.0),
+                ],
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_out_of_order_tolerance() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let tag = Tag("loss".to_string());
+        let wall_time = WallTime::new(1234.0).unwrap();
+        let append = |steps: &[i64]| -> std::io::Result<()> {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&f1_name)?;
+            let mut f1 = BufWriter::new(file);
+            for &step in steps {
+                f1.write_scalar(&tag, Step(step), wall_time, step as f32)?;
+            }
+            f1.into_inner()?.sync_all()
+        };
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let files = vec![EventFileBuf(f1_name.clone())];
+
+        let run = Run("train".to_string());
+        let commit = Commit::new();
+        commit
+            .runs
+            .write()
+            .unwrap()
+            .insert(run.clone(), Default::default());
+        let mut loader = RunLoader::new(run.clone());
+        loader.out_of_order_tolerance(3);
+        loader.provenance(true);
+        let mut reload = |steps: &[i64]| -> std::io::Result<Vec<i64>> {
+            append(steps)?;
+            loader.reload(&logdir, files.clone(), &commit.runs.read().unwrap()[&run]);
+            let runs = commit.runs.read().unwrap();
+            let run_data = runs[&run].read().unwrap();
+            let ts = &run_data.scalars[&tag];
+            let steps: Vec<i64> = ts.valid_values().map(|(step, _, _)| step.0).collect();
+            let sourced: Vec<i64> = ts.provenance.iter().flatten().map(|(s, _)| s.0).collect();
+            assert_eq!(sourced, steps);
+            Ok(steps)
+        };
+
+        // Step 9 arrives after 8, 10, and 11 were committed, and is merged in among them.
+        assert_eq!(reload(&[10, 8, 11])?, vec![8, 10, 11]);
+        assert_eq!(reload(&[9])?, vec![8, 9, 10, 11]);
+        {
+            let runs = commit.runs.read().unwrap();
+            let run_data = runs[&run].read().unwrap();
+            assert_eq!(run_data.scalars[&tag].value_range(), Some((8.0, 11.0)));
+        }
+
+        // A genuine restart still preempts.
+        assert_eq!(reload(&[0])?, vec![0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_negative_steps() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        for (i, &step) in [-1, 0, 1, -1, 2].iter().enumerate() {
+            let wall_time = WallTime::new(1234.0 + i as f64).unwrap();
+            f1.write_scalar(&tag, Step(step), wall_time, i as f32)?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        // Returns the number of dropped events and the (step, value) of each point.
+        let load = |policy: NegativeStepPolicy| {
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.negative_steps(policy);
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+            let run_data = run_data.into_inner().unwrap();
+            let points: Vec<(i64, f64)> = run_data.scalars[&tag]
+                .valid_values()
+                .map(|(step, _, value)| (step.0, value.0))
+                .collect();
+            (loader.negative_step_events_dropped(), points)
+        };
+
+        // The second step -1 preempts everything before it.
+        assert_eq!(
+            load(NegativeStepPolicy::Keep),
+            (0, vec![(-1, 3.0), (2, 4.0)])
+        );
+        assert_eq!(
+            load(NegativeStepPolicy::Drop),
+            (2, vec![(0, 1.0), (1, 2.0), (2, 4.0)])
+        );
+        // Clamped steps still preempt later points at or after step 0.
+        assert_eq!(
+            load(NegativeStepPolicy::ClampToZero),
+            (0, vec![(0, 3.0), (2, 4.0)])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_step() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        for (i, &step) in [0, -1, 1, i64::MAX, 1000, 1001].iter().enumerate() {
+            let wall_time = WallTime::new(1234.0 + i as f64).unwrap();
+            f1.write_scalar(&tag, Step(step), wall_time, i as f32)?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.negative_steps(NegativeStepPolicy::Drop);
+        loader.max_step(Some(1000));
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+        let run_data = run_data.into_inner().unwrap();
+        let steps: Vec<i64> = run_data.scalars[&tag].steps().map(|s| s.0).collect();
+        assert_eq!(steps, vec![0, 1, 1000]);
+        assert_eq!(loader.negative_step_events_dropped(), 1);
+        assert_eq!(loader.large_step_events_dropped(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_tags() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let value = |tag: &str, x: f32| pb::summary::Value {
+            tag: tag.to_string(),
+            value: Some(pb::summary::value::Value::SimpleValue(x)),
+            ..Default::default()
+        };
+        f1.write_event(&pb::Event {
+            step: 0,
+            wall_time: 1234.0,
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: vec![
+                    value("loss", 1.0),
+                    value("accuracy", 0.5),
+                    value("loss", 2.0),
+                    value("loss", 3.0),
+                ],
+            })),
+            ..Default::default()
+        })?;
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let loss = Tag("loss".to_string());
+        let accuracy = Tag("accuracy".to_string());
+
+        // Returns the number of dropped values and the committed `loss` values.
+        let load = |policy: DuplicateTagPolicy| {
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.duplicate_tags(policy);
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+            let run_data = run_data.into_inner().unwrap();
+            assert_eq!(run_data.scalars[&accuracy].valid_values().count(), 1);
+            let values: Vec<f64> = run_data.scalars[&loss]
+                .valid_values()
+                .map(|(_, _, value)| value.0)
+                .collect();
+            (loader.duplicate_values_dropped(), values)
+        };
+
+        assert_eq!(load(DuplicateTagPolicy::KeepLast), (2, vec![3.0]));
+        assert_eq!(load(DuplicateTagPolicy::KeepFirst), (2, vec![1.0]));
+        // All values are offered, and each preempts the last.
+        assert_eq!(load(DuplicateTagPolicy::KeepAll), (0, vec![3.0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_metadata_tags() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("step0000".to_string());
+        let wall_time = WallTime::new(1234.0).unwrap();
+        f1.write_tagged_run_metadata(&tag, Step(0), wall_time, b"<run metadata>".to_vec())?;
+        f1.write_scalar(&tag, Step(0), wall_time, 0.5)?;
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let load = |policy: RunMetadataTags| {
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.run_metadata_tags(policy);
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+            run_data.into_inner().unwrap()
+        };
+
+        // The run metadata comes first, so it takes the tag, and the scalar is lost.
+        let run_data = load(RunMetadataTags::Shared);
+        assert_eq!(run_data.scalars.len(), 0);
+        let ts = &run_data.blob_sequences[&tag];
+        assert_eq!(ts.basin.as_slice().len(), 1);
+        assert_eq!(ts.valid_values().count(), 0);
+
+        let run_data = load(RunMetadataTags::Prefixed);
+        let scalars: Vec<f64> = run_data.scalars[&tag]
+            .valid_values()
+            .map(|(_, _, value)| value.0)
+            .collect();
+        assert_eq!(scalars, vec![0.5]);
+        let prefixed = Tag(format!("{}step0000", TaggedRunMetadataValue::TAG_PREFIX));
+        assert_eq!(
+            run_data.blob_sequences.keys().collect::<Vec<_>>(),
+            vec![&prefixed]
+        );
+        let blobs: Vec<Vec<Vec<u8>>> = run_data.blob_sequences[&prefixed]
+            .valid_values()
+            .map(|(_, _, value)| value.0.iter().map(|b| b.to_vec()).collect())
+            .collect();
+        assert_eq!(blobs, vec![vec![b"<run metadata>".to_vec()]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_tags_many_values() {
+        // Every tag is written twice.
+        let n = 100;
+        let values: Vec<pb::summary::Value> = (0..2 * n)
+            .map(|i| pb::summary::Value {
+                tag: format!("tag_{}", i % n),
+                value: Some(pb::summary::value::Value::SimpleValue(i as f32)),
+                ..Default::default()
+            })
+            .collect();
+        let mut actual = values.clone();
+        assert_eq!(DuplicateTagPolicy::KeepLast.apply(&mut actual), n);
+        assert_eq!(actual, values[n..]);
+        let mut actual = values.clone();
+        assert_eq!(DuplicateTagPolicy::KeepFirst.apply(&mut actual), n);
+        assert_eq!(actual, values[..n]);
+
+        let mut actual = values.clone();
+        let (dropped, positions) = DuplicateTagPolicy::KeepLast.apply_tracking(&mut actual);
+        assert_eq!(dropped, n);
+        assert_eq!(actual, values[n..]);
+        assert_eq!(positions, (n as u32..2 * n as u32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_metadata_conflicts() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let histograms_metadata = pb::SummaryMetadata {
+            plugin_data: Some(pb::summary_metadata::PluginData {
+                plugin_name: plugin_names::HISTOGRAMS.to_string(),
+                ..Default::default()
+            }),
+            data_class: pb::DataClass::Tensor.into(),
+            ..Default::default()
+        };
+        for step in 0..2 {
+            f1.write_event(&pb::Event {
+                step,
+                wall_time: 1234.0,
+                what: Some(pb::event::What::Summary(pb::Summary {
+                    value: vec![pb::summary::Value {
+                        tag: "loss".to_string(),
+                        metadata: Some(histograms_metadata.clone()),
+                        value: Some(pb::summary::value::Value::SimpleValue(0.5)),
+                        ..Default::default()
+                    }],
+                })),
+                ..Default::default()
+            })?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+        // Counted once per time series, not once per event.
+        assert_eq!(loader.metadata_conflicts(), 1);
+        let run_data = run_data.into_inner().unwrap();
+        assert!(run_data.tensor_metadata.is_empty());
+        let ts = &run_data.scalars[&Tag("loss".to_string())];
+        assert_eq!(ts.plugin_name(), Some(plugin_names::SCALARS));
+        assert_eq!(ts.valid_values().count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_offered_points() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        for step in 0..2000 {
+            let wall_time = WallTime::new(1234.0 + step as f64).unwrap();
+            f1.write_scalar(&tag, Step(step), wall_time, 0.5)?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
 
-        let mut loader = RunLoader::new(run.clone());
+        let sample_sizes = SampleSizes {
+            scalars: 100,
+            ..SampleSizes::default()
+        };
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.sample_sizes(sample_sizes);
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+        {
+            let run_data = run_data.read().unwrap();
+            let ts = &run_data.scalars[&tag];
+            assert_eq!((ts.offered, ts.retained()), (2000, 100));
+        }
+
+        // Restored time series report the same counts.
+        let state = loader.save_state(&run_data.read().unwrap());
+        let mut restored = RunLoader::<File>::new(Run("train".to_string()));
+        restored.sample_sizes(sample_sizes);
+        let restored_data = RwLock::new(commit::RunData::default());
+        restored.restore_state(&state, &restored_data)?;
+        let restored_data = restored_data.into_inner().unwrap();
+        let ts = &restored_data.scalars[&tag];
+        assert_eq!((ts.offered, ts.retained()), (2000, 100));
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_overrides() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::metadata_override::{MetadataOverride, OverrideDataClass};
+
+        // Scalars written as rank-0 tensors under a custom plugin, with no data class.
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        for step in 0..3 {
+            f1.write_event(&pb::Event {
+                step,
+                wall_time: 1234.0,
+                what: Some(pb::event::What::Summary(pb::Summary {
+                    value: vec![pb::summary::Value {
+                        tag: "loss".to_string(),
+                        metadata: Some(pb::SummaryMetadata {
+                            plugin_data: Some(pb::summary_metadata::PluginData {
+                                plugin_name: "my_scalars".to_string(),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }),
+                        value: Some(pb::summary::value::Value::Tensor(pb::TensorProto {
+                            dtype: pb::DataType::DtFloat.into(),
+                            float_val: vec![step as f32 * 0.5],
+                            ..Default::default()
+                        })),
+                        ..Default::default()
+                    }],
+                })),
+                ..Default::default()
+            })?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let tag = Tag("loss".to_string());
+
+        let load = |overrides: MetadataOverrides| {
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.metadata_overrides(overrides);
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+            (loader, run_data.into_inner().unwrap())
+        };
+
+        let (loader, run_data) = load(MetadataOverrides::new());
+        assert!(run_data.scalars.is_empty());
+        assert!(loader.overridden_tags().is_empty());
+
+        let mut overrides = MetadataOverrides::new();
+        overrides.push(MetadataOverride {
+            tag: "lo*".to_string(),
+            plugin_name: Some(plugin_names::SCALARS.to_string()),
+            data_class: Some(OverrideDataClass::Scalar),
+        });
+        let (loader, run_data) = load(overrides);
+        assert_eq!(
+            loader.overridden_tags().iter().collect::<Vec<_>>(),
+            vec![&tag]
+        );
+        let ts = &run_data.scalars[&tag];
+        assert_eq!(ts.plugin_name(), Some(plugin_names::SCALARS));
+        let values: Vec<(i64, f64)> = ts
+            .valid_values()
+            .map(|(step, _, value)| (step.0, value.0))
+            .collect();
+        assert_eq!(values, vec![(0, 0.0), (1, 0.5), (2, 1.0)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_plugin_content_len() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let metadata = |content_len: usize| pb::SummaryMetadata {
+            plugin_data: Some(pb::summary_metadata::PluginData {
+                plugin_name: "custom_scalars".to_string(),
+                content: vec![b'x'; content_len],
+            }),
+            data_class: pb::DataClass::Scalar.into(),
+            ..Default::default()
+        };
+        for step in 0..2 {
+            f1.write_event(&pb::Event {
+                step,
+                wall_time: 1234.0,
+                what: Some(pb::event::What::Summary(pb::Summary {
+                    value: vec![
+                        pb::summary::Value {
+                            tag: "layout".to_string(),
+                            metadata: Some(metadata(2 << 20)),
+                            value: Some(pb::summary::value::Value::SimpleValue(0.5)),
+                            ..Default::default()
+                        },
+                        pb::summary::Value {
+                            tag: "small".to_string(),
+                            metadata: Some(metadata(100)),
+                            value: Some(pb::summary::value::Value::SimpleValue(0.5)),
+                            ..Default::default()
+                        },
+                    ],
+                })),
+                ..Default::default()
+            })?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        // Returns the plugin content length of each committed time series.
+        let load = |limit: Option<usize>| {
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.max_plugin_content_len(limit);
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+            let run_data = run_data.into_inner().unwrap();
+            let mut lens: Vec<(String, usize)> = run_data
+                .scalars
+                .iter()
+                .map(|(tag, ts)| {
+                    let pd = ts.metadata.plugin_data.as_ref().unwrap();
+                    assert_eq!(pd.plugin_name, "custom_scalars");
+                    assert_eq!(ts.valid_values().count(), 2);
+                    (tag.0.clone(), pd.content.len())
+                })
+                .collect();
+            lens.sort();
+            (lens, loader.truncated_plugin_content().clone())
+        };
+
+        let (lens, truncated) = load(Some(DEFAULT_MAX_PLUGIN_CONTENT_LEN));
+        assert_eq!(
+            lens,
+            vec![("layout".to_string(), 0), ("small".to_string(), 100)]
+        );
+        let expected: BTreeMap<Tag, usize> = vec![(Tag("layout".to_string()), 2 << 20)]
+            .into_iter()
+            .collect();
+        assert_eq!(truncated, expected);
+
+        let (lens, truncated) = load(None);
+        assert_eq!(
+            lens,
+            vec![("layout".to_string(), 2 << 20), ("small".to_string(), 100)]
+        );
+        assert!(truncated.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_tags() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tags: Vec<Tag> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|&t| Tag(t.to_string()))
+            .collect();
+        for step in 0..2 {
+            for tag in &tags {
+                f1.write_scalar(tag, Step(step), WallTime::new(1234.0).unwrap(), 0.5)?;
+            }
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.max_tags(3);
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+        let run_data = run_data.into_inner().unwrap();
+
+        let mut committed: Vec<&Tag> = run_data.scalars.keys().collect();
+        committed.sort();
+        assert_eq!(committed, tags[..3].iter().collect::<Vec<_>>());
+        // Existing tags keep accepting data.
+        for tag in &tags[..3] {
+            assert_eq!(run_data.scalars[tag].valid_values().count(), 2);
+        }
+        assert_eq!(loader.rejected_tags(), &tags[3..]);
+        assert_eq!(loader.tag_overflow(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_valid_wall_time() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        f1.write_event(&pb::Event {
+            step: 0,
+            wall_time: f64::INFINITY,
+            what: Some(pb::event::What::FileVersion("brain.Event:2".to_string())),
+            ..Default::default()
+        })?;
+        f1.into_inner()?.sync_all()?;
         let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
         let commit = Commit::new();
+        let run = Run("train".to_string());
         commit
             .runs
             .write()
-            .expect("write-locking runs map")
+            .unwrap()
             .insert(run.clone(), Default::default());
-        loader.reload(
-            &logdir,
-            vec![EventFileBuf(f1_name), EventFileBuf(f2_name)],
-            &commit.runs.read().unwrap()[&run],
+        let mut loader = RunLoader::new(run.clone());
+        let runs = commit.runs.read().unwrap();
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &runs[&run]);
+
+        let run_data = runs[&run].read().unwrap();
+        assert_eq!(run_data.start_time, None);
+        assert!(!run_data.has_start_time());
+        assert!(run_data.scalars.is_empty());
+        // Relative times fall back to absolute ones.
+        let wall_time = WallTime::new(1234.5).unwrap();
+        assert_eq!(run_data.relative_time(wall_time), 1234.5);
+        drop(run_data);
+        drop(runs);
+        assert_eq!(commit.read_scalars_relative(&run, &Tag("x".into())), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_log_fields() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let wall_time = WallTime::new(1235.0).unwrap();
+        for step in 0..3 {
+            f1.write_scalar(&Tag("loss".to_string()), Step(step), wall_time, 0.5)?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        let lines = crate::logging::capture::capture_json(Level::Debug, || {
+            loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+        });
+        let finished = lines
+            .iter()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .find(|record| {
+                let message = record["message"].as_str().unwrap_or_default();
+                message.starts_with("Finished load for run")
+            })
+            .unwrap_or_else(|| panic!("no summary line in {:?}", lines));
+        assert_eq!(finished["level"], "DEBUG");
+        assert_eq!(finished["target"], "rustboard_core::run");
+        if cfg!(feature = "structured-logging") {
+            assert_eq!(finished["message"], "Finished load for run");
+            assert_eq!(finished["run"], "train");
+            assert_eq!(finished["events"], 3);
+            assert_eq!(finished["dead_files"], 0);
+            assert!(finished["elapsed"].is_string());
+        } else {
+            let message = finished["message"].as_str().unwrap();
+            assert!(message.contains(" run=\"train\" events=3 "), "{}", message);
+            assert!(message.contains(" dead_files=0"), "{}", message);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rate_limited_warnings() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        for step in 0..10_000 {
+            f1.write_event(&pb::Event {
+                step,
+                wall_time: f64::INFINITY,
+                ..Default::default()
+            })?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        let warnings = crate::logging::capture::capture_warnings(|| {
+            assert_eq!(
+                loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data),
+                10_000
+            );
+        });
+        // The limiter is shared with other tests, but any 10k occurrences include at most 14
+        // powers of two.
+        assert!(
+            (1..=14).contains(&warnings.len()),
+            "{} warnings: {:?}",
+            warnings.len(),
+            warnings
         );
+        assert!(warnings.iter().all(|w| w.contains("invalid wall time")));
+        Ok(())
+    }
 
-        // Start time should be that of the file version event, even though that didn't correspond
-        // to any time series.
-        assert_eq!(loader.data.start_time, Some(WallTime::new(1234.0).unwrap()));
+    #[test]
+    fn test_empty_events() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        f1.write_scalar(&tag, Step(0), WallTime::new(1234.0).unwrap(), 0.25)?;
+        for step in 1..=3 {
+            f1.write_event(&pb::Event {
+                step,
+                wall_time: 1234.0 + step as f64,
+                what: None,
+                ..Default::default()
+            })?;
+        }
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
 
-        let runs = commit.runs.read().expect("read-locking runs map");
-        let run_data: &commit::RunData = &*runs
-            .get(&run)
-            .expect("looking up data for run")
-            .read()
-            .expect("read-locking run data map");
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f1_name)], &run_data);
+        assert_eq!(loader.empty_events(), 3);
+        let run_data = run_data.into_inner().unwrap();
+        assert_eq!(run_data.scalars[&tag].valid_values().count(), 1);
+        Ok(())
+    }
 
-        assert_eq!(run_data.scalars.keys().collect::<Vec<_>>(), vec![&tag]);
-        let scalar_ts = run_data.scalars.get(&tag).unwrap();
-        assert_eq!(
-            *scalar_ts.metadata,
-            pb::SummaryMetadata {
-                plugin_data: Some(pb::summary_metadata::PluginData {
-                    plugin_name: plugin_names::SCALARS.to_string(),
+    #[test]
+    fn test_metadata_descriptions() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let scalar = |tag: &str, step, description: Option<&str>| pb::Event {
+            step,
+            wall_time: 1234.0 + step as f64,
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: vec![pb::summary::Value {
+                    tag: tag.to_string(),
+                    metadata: description.map(|d| pb::SummaryMetadata {
+                        display_name: format!("{} (display)", tag),
+                        summary_description: d.to_string(),
+                        ..Default::default()
+                    }),
+                    value: Some(pb::summary::value::Value::SimpleValue(0.5)),
                     ..Default::default()
-                }),
-                data_class: pb::DataClass::Scalar.into(),
-                ..Default::default()
-            }
+                }],
+            })),
+            ..Default::default()
+        };
+        // "loss" has metadata from the start; "accuracy" only from its second value on, and
+        // "xent" changes its metadata after the first.
+        f1.write_event(&scalar("loss", 0, Some("Cross-entropy loss.")))?;
+        f1.write_event(&scalar("accuracy", 0, None))?;
+        f1.write_event(&scalar("xent", 0, Some("First.")))?;
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        let run_data = RwLock::new(commit::RunData::default());
+        let files = vec![EventFileBuf(f1_name.clone())];
+        loader.reload(&logdir, files.clone(), &run_data);
+        let description = |tag: &str| {
+            let md = &run_data.read().unwrap().scalars[&Tag(tag.to_string())].metadata;
+            assert_eq!(md.data_class, i32::from(pb::DataClass::Scalar));
+            (md.display_name.clone(), md.summary_description.clone())
+        };
+        assert_eq!(
+            description("loss"),
+            (
+                "loss (display)".to_string(),
+                "Cross-entropy loss.".to_string()
+            )
         );
-        // Points should be as expected (no downsampling at these sizes).
-        let scalar = commit::ScalarValue;
+        assert_eq!(description("accuracy"), (String::new(), String::new()));
+
+        let file = std::fs::OpenOptions::new().append(true).open(&f1_name)?;
+        let mut f1 = BufWriter::new(file);
+        f1.write_event(&scalar("accuracy", 1, Some("Top-1 accuracy.")))?;
+        f1.write_event(&scalar("accuracy", 2, Some("Ignored.")))?;
+        f1.write_event(&scalar("xent", 1, Some("Second.")))?;
+        f1.into_inner()?.sync_all()?;
+        loader.reload(&logdir, files, &run_data);
         assert_eq!(
-            scalar_ts.valid_values().collect::<Vec<_>>(),
-            vec![
-                (Step(0), WallTime::new(1235.0).unwrap(), &scalar(0.25)),
-                (Step(1), WallTime::new(1236.0).unwrap(), &scalar(0.50)),
-                (Step(2), WallTime::new(2346.0).unwrap(), &scalar(0.70)),
-                (Step(3), WallTime::new(2347.0).unwrap(), &scalar(0.85)),
-                (Step(4), WallTime::new(2348.0).unwrap(), &scalar(0.90)),
-            ]
+            description("accuracy"),
+            (
+                "accuracy (display)".to_string(),
+                "Top-1 accuracy.".to_string()
+            )
         );
+        assert_eq!(description("xent").1, "First.");
+        Ok(())
+    }
 
-        assert_eq!(run_data.blob_sequences.len(), 2);
+    #[test]
+    fn test_zero_wall_time() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        f1.write_scalar(&tag, Step(0), WallTime::new(0.0).unwrap(), 0.25)?;
+        f1.write_scalar(&tag, Step(1), WallTime::new(1235.0).unwrap(), 0.5)?;
+        f1.write_scalar(&tag, Step(2), WallTime::new(0.0).unwrap(), 0.75)?;
+        f1.write_scalar(&tag, Step(3), WallTime::new(1237.0).unwrap(), 1.0)?;
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        // Returns the start time and the (step, wall time) of each point.
+        let load = |policy: ZeroWallTime| {
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.zero_wall_time(policy);
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+            assert_eq!(loader.zero_wall_time_events(), 2);
+            let run_data = run_data.into_inner().unwrap();
+            let points: Vec<(i64, f64)> = run_data.scalars[&tag]
+                .valid_values()
+                .map(|(step, wall_time, _)| (step.0, wall_time.into()))
+                .collect();
+            (run_data.start_time.map(f64::from), points)
+        };
 
-        let run_graph_tag = Tag(GraphDefValue::TAG_NAME.to_string());
-        let graph_ts = run_data.blob_sequences.get(&run_graph_tag).unwrap();
         assert_eq!(
-            *graph_ts.metadata,
-            pb::SummaryMetadata {
-                plugin_data: Some(pb::summary_metadata::PluginData {
-                    plugin_name: plugin_names::GRAPHS.to_string(),
-                    ..Default::default()
-                }),
-                data_class: pb::DataClass::BlobSequence.into(),
-                ..Default::default()
-            }
+            load(ZeroWallTime::Keep),
+            (
+                Some(0.0),
+                vec![(0, 0.0), (1, 1235.0), (2, 0.0), (3, 1237.0)]
+            )
         );
         assert_eq!(
-            graph_ts.valid_values().collect::<Vec<_>>(),
-            vec![(
-                Step(0),
-                WallTime::new(1235.0).unwrap(),
-                &commit::BlobSequenceValue(vec![b"<sample model graph>".to_vec()])
-            )]
+            load(ZeroWallTime::Drop),
+            (Some(1235.0), vec![(1, 1235.0), (3, 1237.0)])
+        );
+        assert_eq!(
+            load(ZeroWallTime::Inherit),
+            (Some(1235.0), vec![(1, 1235.0), (2, 1235.0), (3, 1237.0)])
         );
+        Ok(())
+    }
+
+    #[test]
+    fn test_wall_time_offset() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let tag = Tag("loss".to_string());
+        f1.write_scalar(&tag, Step(0), WallTime::new(1234.0).unwrap(), 0.25)?;
+        f1.write_scalar(&tag, Step(1), WallTime::new(1235.0).unwrap(), 0.5)?;
+        f1.write_scalar(&tag, Step(2), WallTime::new(0.0).unwrap(), 0.75)?;
+        f1.into_inner()?.sync_all()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        // Returns the start time and the (step, wall time) of each point.
+        let load = |offset: f64| {
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.zero_wall_time(ZeroWallTime::Inherit);
+            loader.wall_time_offset(offset);
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+            let run_data = run_data.into_inner().unwrap();
+            let points: Vec<(i64, f64)> = run_data.scalars[&tag]
+                .valid_values()
+                .map(|(step, wall_time, _)| (step.0, wall_time.into()))
+                .collect();
+            (run_data.start_time.map(f64::from), points)
+        };
 
-        let run_metadata_tag = Tag("step0000".to_string());
-        let run_metadata_ts = run_data.blob_sequences.get(&run_metadata_tag).unwrap();
         assert_eq!(
-            *run_metadata_ts.metadata,
-            pb::SummaryMetadata {
-                plugin_data: Some(pb::summary_metadata::PluginData {
-                    plugin_name: plugin_names::GRAPH_TAGGED_RUN_METADATA.to_string(),
-                    ..Default::default()
-                }),
-                data_class: pb::DataClass::BlobSequence.into(),
-                ..Default::default()
-            }
+            load(0.0),
+            (Some(1234.0), vec![(0, 1234.0), (1, 1235.0), (2, 1235.0)])
         );
+        // Inherited wall times are shifted only once.
         assert_eq!(
-            run_metadata_ts.valid_values().collect::<Vec<_>>(),
-            vec![(
-                Step(0),
-                WallTime::new(1235.0).unwrap(),
-                &commit::BlobSequenceValue(vec![b"<sample run metadata>".to_vec()])
-            )]
+            load(3600.0),
+            (Some(4834.0), vec![(0, 4834.0), (1, 4835.0), (2, 4835.0)])
+        );
+        assert_eq!(
+            load(-1000.0),
+            (Some(234.0), vec![(0, 234.0), (1, 235.0), (2, 235.0)])
         );
 
+        // A start time taken from file modification times is shifted too.
+        let logdir = tempfile::tempdir()?;
+        let run_dir = logdir.path().join("train");
+        std::fs::create_dir(&run_dir)?;
+        let f2_name = run_dir.join("tfevents.456");
+        let mut f2 = File::create(&f2_name)?;
+        f2.write_scalar(&tag, Step(0), WallTime::new(0.0).unwrap(), 0.25)?;
+        f2.sync_all()?;
+        let mtime = f2.metadata()?.modified()?;
+        let mtime = mtime.duration_since(UNIX_EPOCH)?.as_secs_f64();
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.zero_wall_time(ZeroWallTime::Drop);
+        loader.wall_time_offset(3600.0);
+        let run_data = RwLock::new(commit::RunData::default());
+        loader.reload(&logdir, vec![EventFileBuf(f2_name)], &run_data);
+        let run_data = run_data.into_inner().unwrap();
+        assert!(run_data.start_time_synthesized);
+        assert_eq!(run_data.start_time.map(f64::from), Some(mtime + 3600.0));
+        let later = WallTime::new(mtime + 3610.0).unwrap();
+        assert_eq!(run_data.relative_time(later), 10.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_time_from_file_mtime() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let run_dir = logdir.path().join("train");
+        std::fs::create_dir(&run_dir)?;
+        let f1_name = run_dir.join("tfevents.123");
+        let mut f1 = File::create(&f1_name)?;
+        let tag = Tag("loss".to_string());
+        f1.write_scalar(&tag, Step(0), WallTime::new(0.0).unwrap(), 0.25)?;
+        f1.write_scalar(&tag, Step(1), WallTime::new(0.0).unwrap(), 0.5)?;
+        f1.sync_all()?;
+        let mtime = f1.metadata()?.modified()?;
+        let mtime = mtime.duration_since(UNIX_EPOCH)?.as_secs_f64();
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        // With every event dropped, the start time comes from the file.
+        let mut loader = RunLoader::new(Run("train".to_string()));
+        loader.zero_wall_time(ZeroWallTime::Drop);
+        let run_data = RwLock::new(commit::RunData::default());
+        let filenames = vec![EventFileBuf(f1_name)];
+        loader.reload(&logdir, filenames.clone(), &run_data);
+        {
+            let run_data = run_data.read().unwrap();
+            assert_eq!(run_data.start_time.map(f64::from), Some(mtime));
+            assert!(run_data.start_time_synthesized);
+        }
+
+        // A real wall time replaces it, even though it's later.
+        f1.write_scalar(&tag, Step(2), WallTime::new(mtime + 100.0).unwrap(), 0.75)?;
+        f1.sync_all()?;
+        loader.reload(&logdir, filenames, &run_data);
+        let run_data = run_data.read().unwrap();
+        assert_eq!(run_data.start_time.map(f64::from), Some(mtime + 100.0));
+        assert!(!run_data.start_time_synthesized);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scalar_fast_path() -> Result<(), Box<dyn std::error::Error>> {
+        use pb::summary::value::Value;
+        let logdir = tempfile::tempdir()?;
+        let f1_name = logdir.path().join("tfevents.123");
+        let mut f1 = BufWriter::new(File::create(&f1_name)?);
+        let described = |name: &str| {
+            Some(pb::SummaryMetadata {
+                display_name: name.to_string(),
+                ..Default::default()
+            })
+        };
+        let histogram = Value::Tensor(pb::TensorProto {
+            dtype: pb::DataType::DtFloat.into(),
+            tensor_shape: Some(pb::TensorShapeProto {
+                dim: vec![pb::tensor_shape_proto::Dim {
+                    size: 2,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            float_val: vec![1.0, 2.0],
+            ..Default::default()
+        });
+        let events = vec![
+            vec![
+                ("loss", None, Value::SimpleValue(0.5)),
+                ("acc", described("Accuracy"), Value::SimpleValue(0.25)),
+            ],
+            // A repeated tag, and metadata for a time series whose metadata was inferred.
+            vec![
+                ("loss", None, Value::SimpleValue(0.3)),
+                ("loss", described("Loss"), Value::SimpleValue(0.4)),
+            ],
+            vec![("loss", None, Value::SimpleValue(0.3))],
+            // Leaves the fast path for good.
+            vec![(
+                "hist",
+                Some(pb::SummaryMetadata {
+                    data_class: pb::DataClass::Tensor.into(),
+                    ..Default::default()
+                }),
+                histogram,
+            )],
+            vec![
+                ("loss", None, Value::SimpleValue(0.2)),
+                ("acc", None, Value::SimpleValue(0.5)),
+            ],
+        ];
+        for (step, values) in events.into_iter().enumerate() {
+            let value = values
+                .into_iter()
+                .map(|(tag, metadata, value)| pb::summary::Value {
+                    tag: tag.to_string(),
+                    metadata,
+                    value: Some(value),
+                    ..Default::default()
+                })
+                .collect();
+            f1.write_event(&pb::Event {
+                step: step as i64,
+                wall_time: 1235.0 + step as f64,
+                what: Some(pb::event::What::Summary(pb::Summary {
+                    value,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            })?;
+        }
+        f1.flush()?;
+        let logdir = DiskLogdir::new(logdir.path().to_path_buf());
+
+        // Loads the run, returning the display name and points of each scalar time series, the
+        // tensor tags, and whether the run is still on the fast path.
+        type Scalars = Vec<(Tag, String, Vec<(i64, f64)>)>;
+        let load = |fast_path: bool| -> (Scalars, Vec<Tag>, bool) {
+            let mut loader = RunLoader::new(Run("train".to_string()));
+            loader.scalar_fast_path(fast_path);
+            let run_data = RwLock::new(commit::RunData::default());
+            loader.reload(&logdir, vec![EventFileBuf(f1_name.clone())], &run_data);
+            let run_data = run_data.read().unwrap();
+            let mut scalars: Scalars = run_data
+                .scalars
+                .iter()
+                .map(|(tag, ts)| {
+                    let points = ts.valid_values().map(|(s, _, v)| (s.0, v.0)).collect();
+                    (tag.clone(), ts.display_name().to_string(), points)
+                })
+                .collect();
+            scalars.sort_by(|a, b| a.0.cmp(&b.0));
+            let tensors = run_data.tensor_metadata.keys().cloned().collect();
+            (scalars, tensors, loader.data.scalar_fast_path)
+        };
+
+        let (fast, fast_tensors, still_fast) = load(true);
+        let (slow, slow_tensors, _) = load(false);
+        assert_eq!(fast, slow);
+        assert_eq!(fast_tensors, slow_tensors);
+        assert_eq!(fast_tensors, vec![Tag("hist".to_string())]);
+        assert_eq!(fast[0].0, Tag("acc".to_string()));
+        assert_eq!(fast[0].1, "Accuracy");
+        assert_eq!(fast[1].1, "Loss");
+        assert_eq!(fast[1].2.len(), 4);
+        assert!(!still_fast);
         Ok(())
     }
 }
@@ -0,0 +1,256 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Selecting which runs and tags of a log directory to load, by name.
+
+use std::path;
+
+use crate::types::{Run, Tag};
+
+/// Include and exclude lists of glob patterns on run names. See [`LogdirLoader::run_filter`].
+///
+/// A run passes the filter if it matches at least one include pattern (or there are none) and
+/// no exclude pattern. In patterns, `?` matches any single character other than a path
+/// separator, `*` matches any sequence of such characters, `**` matches any sequence of
+/// characters including separators, and `**/` matches zero or more whole path components. All
+/// other characters match themselves, except that `/` also matches the platform's path
+/// separator. A pattern must match the whole run name.
+///
+/// [`LogdirLoader::run_filter`]: crate::logdir::LogdirLoader::run_filter
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunFilter(Patterns);
+
+impl RunFilter {
+    /// Creates a filter that passes every run.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pattern that runs may match to pass the filter.
+    pub fn include(&mut self, pattern: &str) {
+        self.0.include.push(Glob::new(pattern));
+    }
+
+    /// Adds a pattern that runs must not match to pass the filter.
+    pub fn exclude(&mut self, pattern: &str) {
+        self.0.exclude.push(Glob::new(pattern));
+    }
+
+    /// Checks whether this filter passes every run.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Checks whether the given run passes this filter.
+    pub fn matches(&self, run: &Run) -> bool {
+        self.0.matches(&run.0, path::is_separator)
+    }
+}
+
+/// Include and exclude lists of glob patterns on tag names. See [`RunLoader::tag_filter`].
+///
+/// Patterns have the same syntax as for a [`RunFilter`], except that only `/` is a separator,
+/// on all platforms. For example, `eval/*` matches `eval/loss` but not `eval/per_class/cat`,
+/// while `eval/**` matches both.
+///
+/// [`RunLoader::tag_filter`]: crate::run::RunLoader::tag_filter
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagFilter(Patterns);
+
+impl TagFilter {
+    /// Creates a filter that passes every tag.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pattern that tags may match to pass the filter.
+    pub fn include(&mut self, pattern: &str) {
+        self.0.include.push(Glob::new(pattern));
+    }
+
+    /// Adds a pattern that tags must not match to pass the filter.
+    pub fn exclude(&mut self, pattern: &str) {
+        self.0.exclude.push(Glob::new(pattern));
+    }
+
+    /// Checks whether this filter passes every tag.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Checks whether the given tag passes this filter.
+    pub fn matches(&self, tag: &Tag) -> bool {
+        self.0.matches(&tag.0, |c| c == '/')
+    }
+}
+
+/// Include and exclude lists shared by [`RunFilter`] and [`TagFilter`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Patterns {
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+}
+
+impl Patterns {
+    fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    fn matches(&self, name: &str, is_separator: fn(char) -> bool) -> bool {
+        let name: Vec<char> = name.chars().collect();
+        let matches = |g: &Glob| g.matches(&name, is_separator);
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+/// A parsed glob pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Glob {
+    tokens: Vec<Token>,
+}
+
+/// A unit of a [`Glob`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Token {
+    /// A single character, matched exactly except that `/` matches any separator.
+    Char(char),
+    /// `?`: any one character other than a separator.
+    AnyChar,
+    /// `*`: any number of characters other than separators.
+    Star,
+    /// `**`: any number of characters.
+    DoubleStar,
+    /// `**/`: nothing, or any number of characters ending in a separator.
+    Components,
+}
+
+impl Glob {
+    fn new(pattern: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '?' => Token::AnyChar,
+                '*' if chars.peek() == Some(&'*') => {
+                    while chars.peek() == Some(&'*') {
+                        chars.next();
+                    }
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        Token::Components
+                    } else {
+                        Token::DoubleStar
+                    }
+                }
+                '*' => Token::Star,
+                c => Token::Char(c),
+            };
+            tokens.push(token);
+        }
+        Glob { tokens }
+    }
+
+    /// Checks whether this pattern matches all of `name`, given which characters separate
+    /// components.
+    fn matches(&self, name: &[char], is_separator: fn(char) -> bool) -> bool {
+        // `ok[j]` is whether the tokens after the current one match `name[j..]`. Working backward
+        // from the last token avoids backtracking: time is linear in the length of the name for
+        // each token.
+        let n = name.len();
+        let mut ok = vec![false; n + 1];
+        ok[n] = true;
+        for token in self.tokens.iter().rev() {
+            let mut next = vec![false; n + 1];
+            // For `**/`: whether some prefix of `name[j..]` ending in a separator can be consumed.
+            let mut components = false;
+            for j in (0..=n).rev() {
+                let c = name.get(j).copied();
+                next[j] = match (token, c) {
+                    (Token::Char('/'), Some(c)) => is_separator(c) && ok[j + 1],
+                    (Token::Char(want), Some(c)) => *want == c && ok[j + 1],
+                    (Token::AnyChar, Some(c)) => !is_separator(c) && ok[j + 1],
+                    (Token::Star, Some(c)) if !is_separator(c) => ok[j] || next[j + 1],
+                    (Token::DoubleStar, Some(_)) => ok[j] || next[j + 1],
+                    (Token::Components, Some(c)) => {
+                        components = components || (is_separator(c) && ok[j + 1]);
+                        ok[j] || components
+                    }
+                    (Token::Star, _) | (Token::DoubleStar, _) | (Token::Components, _) => ok[j],
+                    (_, None) => false,
+                };
+            }
+            ok = next;
+        }
+        ok[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str, name: &str) -> bool {
+        let name: Vec<char> = name.chars().collect();
+        Glob::new(pattern).matches(&name, path::is_separator)
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(glob("train", "train"));
+        assert!(!glob("train", "train2"));
+        assert!(glob("tr?in", "train"));
+        assert!(!glob("a?b", "a/b"));
+        assert!(glob("*", "train"));
+        assert!(glob("*", ""));
+        assert!(!glob("*", "a/b"));
+        assert!(glob("*/train", "mnist/train"));
+        assert!(glob("teamA/**", "teamA/run1"));
+        assert!(glob("teamA/**", "teamA/x/y/run1"));
+        assert!(!glob("teamA/**", "teamB/run1"));
+        assert!(!glob("teamA/**", "teamAB/run1"));
+        assert!(glob("**/train", "train"));
+        assert!(glob("**/train", "a/b/train"));
+        assert!(!glob("**/train", "a/btrain"));
+        assert!(!glob("**/train", "atrain"));
+        assert!(glob("a/**/b", "a/b"));
+        assert!(glob("a/**/b", "a/x/y/b"));
+        assert!(glob("**", "anything/at/all"));
+        assert!(glob("*_lr=0.1*", "run_lr=0.1_bs=32"));
+        assert!(!glob("*_lr=0.1*", "run_lr=0.01"));
+    }
+
+    #[test]
+    fn test_filter() {
+        let run = |name: &str| Run(name.to_string());
+        let mut filter = RunFilter::new();
+        assert!(filter.is_empty());
+        assert!(filter.matches(&run("anything")));
+
+        filter.include("teamA/**");
+        filter.include("shared");
+        filter.exclude("**/scratch*");
+        assert!(!filter.is_empty());
+        assert!(filter.matches(&run("teamA/run1")));
+        assert!(filter.matches(&run("shared")));
+        assert!(!filter.matches(&run("teamB/run2")));
+        assert!(!filter.matches(&run("teamA/scratch_1")));
+
+        let mut filter = RunFilter::new();
+        filter.exclude("teamB/*");
+        assert!(filter.matches(&run("teamA/run1")));
+        assert!(!filter.matches(&run("teamB/run2")));
+    }
+}
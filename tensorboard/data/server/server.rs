@@ -18,24 +18,32 @@ use futures_core::Stream;
 use std::borrow::{Borrow, Cow};
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryInto;
 use std::hash::Hash;
 use std::pin::Pin;
-use std::sync::{RwLock, RwLockReadGuard};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
 use tonic::{Request, Response, Status};
 
 use crate::blob_key::BlobKey;
-use crate::commit::{self, BlobSequenceValue, Commit};
-use crate::downsample;
+use crate::commit::{self, BlobSource, Commit, TimeSeries};
 use crate::proto::tensorboard as pb;
 use crate::proto::tensorboard::data;
-use crate::types::{Run, Tag, WallTime};
+use crate::types::{Downsample, Run, Step, Tag, WallTime};
 use data::tensor_board_data_provider_server::TensorBoardDataProvider;
 
 /// Data provider gRPC service implementation.
+///
+/// Responses list runs and tags in a deterministic order, so that the same data always gives the
+/// same response: plugins are sorted by name, runs by start time (ties by name) in `ListRuns` and
+/// by name elsewhere, and tags by name within each run.
 #[derive(Debug)]
 pub struct DataProviderHandler {
     pub commit: &'static Commit,
+    /// Log directory from which to read blobs of runs loaded in [blob reference
+    /// mode](crate::run::RunLoader::blob_references). Without one, `ReadBlob` fails for those
+    /// blobs, though they're still listed.
+    pub blob_source: Option<Arc<dyn BlobSource>>,
 }
 
 impl DataProviderHandler {
@@ -51,10 +59,6 @@ impl DataProviderHandler {
 /// Maximum size (in bytes) of the `data` field of any single [`data::ReadBlobResponse`].
 const BLOB_CHUNK_SIZE: usize = 1024 * 1024 * 8;
 
-fn plugin_name(md: &pb::SummaryMetadata) -> Option<&str> {
-    md.plugin_data.as_ref().map(|pd| pd.plugin_name.as_str())
-}
-
 #[tonic::async_trait]
 impl TensorBoardDataProvider for DataProviderHandler {
     async fn list_plugins(
@@ -63,13 +67,14 @@ impl TensorBoardDataProvider for DataProviderHandler {
     ) -> Result<Response<data::ListPluginsResponse>, Status> {
         let runs = self.read_runs()?;
         // Collect set of plugin names.
-        let mut plugin_names = HashSet::new();
+        let mut plugin_names = BTreeSet::new();
         for (run, data) in runs.iter() {
             let data = data
                 .read()
                 .map_err(|_| Status::internal(format!("failed to read run data for {:?}", run)))?;
             for metadata in (data.scalars.values().map(|ts| ts.metadata.as_ref()))
                 .chain(data.blob_sequences.values().map(|ts| ts.metadata.as_ref()))
+                .chain(data.blob_references.values().map(|ts| ts.metadata.as_ref()))
             {
                 let plugin_name = match &metadata.plugin_data {
                     Some(d) => d.plugin_name.clone(),
@@ -105,7 +110,9 @@ impl TensorBoardDataProvider for DataProviderHandler {
                 results.push((run.clone(), start_time));
             }
         }
-        results.sort_by_key(|&(_, start_time)| start_time);
+        results.sort_by(|(run_a, time_a), (run_b, time_b)| {
+            time_a.cmp(time_b).then_with(|| run_a.cmp(run_b))
+        });
         drop(runs); // release lock a bit earlier
 
         let res = data::ListRunsResponse {
@@ -120,6 +127,43 @@ impl TensorBoardDataProvider for DataProviderHandler {
         Ok(Response::new(res))
     }
 
+    async fn list_tag_groups(
+        &self,
+        req: Request<data::ListTagGroupsRequest>,
+    ) -> Result<Response<data::ListTagGroupsResponse>, Status> {
+        let req = req.into_inner();
+        let run = Run(req.run_name);
+        let runs = self.read_runs()?;
+        let data = runs
+            .get(&run)
+            .ok_or_else(|| Status::not_found(format!("no such run: {:?}", run.0)))?
+            .read()
+            .map_err(|_| Status::internal(format!("failed to read run data for {:?}", run)))?;
+
+        let mut groups: BTreeMap<&str, i64> = BTreeMap::new();
+        let mut tag_names = Vec::new();
+        let tags: BTreeSet<&str> = data.tags().map(commit::public_tag).collect();
+        for tag in tags {
+            let prefix = commit::tag_group(tag);
+            *groups.entry(prefix).or_default() += 1;
+            if matches!(&req.group_filter, Some(gf) if gf.prefix == prefix) {
+                tag_names.push(tag.to_string());
+            }
+        }
+
+        let res = data::ListTagGroupsResponse {
+            groups: groups
+                .into_iter()
+                .map(|(prefix, tag_count)| data::TagGroup {
+                    prefix: prefix.to_string(),
+                    tag_count,
+                })
+                .collect(),
+            tag_names,
+        };
+        Ok(Response::new(res))
+    }
+
     async fn list_scalars(
         &self,
         req: Request<data::ListScalarsRequest>,
@@ -130,7 +174,7 @@ impl TensorBoardDataProvider for DataProviderHandler {
         let runs = self.read_runs()?;
 
         let mut res: data::ListScalarsResponse = Default::default();
-        for (run, data) in runs.iter() {
+        for (run, data) in sorted(&runs) {
             if !run_filter.want(run) {
                 continue;
             }
@@ -138,11 +182,11 @@ impl TensorBoardDataProvider for DataProviderHandler {
                 .read()
                 .map_err(|_| Status::internal(format!("failed to read run data for {:?}", run)))?;
             let mut run_res: data::list_scalars_response::RunEntry = Default::default();
-            for (tag, ts) in &data.scalars {
+            for (tag, ts) in sorted(&data.scalars) {
                 if !tag_filter.want(tag) {
                     continue;
                 }
-                if plugin_name(&ts.metadata) != Some(&want_plugin) {
+                if ts.plugin_name() != Some(&want_plugin) {
                     continue;
                 }
                 let max_step = match ts.valid_values().last() {
@@ -185,7 +229,7 @@ impl TensorBoardDataProvider for DataProviderHandler {
         let runs = self.read_runs()?;
 
         let mut res: data::ReadScalarsResponse = Default::default();
-        for (run, data) in runs.iter() {
+        for (run, data) in sorted(&runs) {
             if !run_filter.want(run) {
                 continue;
             }
@@ -193,16 +237,16 @@ impl TensorBoardDataProvider for DataProviderHandler {
                 .read()
                 .map_err(|_| Status::internal(format!("failed to read run data for {:?}", run)))?;
             let mut run_res: data::read_scalars_response::RunEntry = Default::default();
-            for (tag, ts) in &data.scalars {
+            for (tag, ts) in sorted(&data.scalars) {
                 if !tag_filter.want(tag) {
                     continue;
                 }
-                if plugin_name(&ts.metadata) != Some(&want_plugin) {
+                if ts.plugin_name() != Some(&want_plugin) {
                     continue;
                 }
 
                 let mut points = ts.valid_values().collect::<Vec<_>>();
-                downsample::downsample(&mut points, num_points);
+                num_points.apply(&mut points);
                 let n = points.len();
                 let mut steps = Vec::with_capacity(n);
                 let mut wall_times = Vec::with_capacity(n);
@@ -210,7 +254,8 @@ impl TensorBoardDataProvider for DataProviderHandler {
                 for (step, wall_time, &commit::ScalarValue(value)) in points {
                     steps.push(step.into());
                     wall_times.push(wall_time.into());
-                    values.push(value);
+                    // The API serves single precision only.
+                    values.push(value as f32);
                 }
 
                 run_res.tags.push(data::read_scalars_response::TagEntry {
@@ -255,7 +300,7 @@ impl TensorBoardDataProvider for DataProviderHandler {
         let runs = self.read_runs()?;
 
         let mut res: data::ListBlobSequencesResponse = Default::default();
-        for (run, data) in runs.iter() {
+        for (run, data) in sorted(&runs) {
             if !run_filter.want(run) {
                 continue;
             }
@@ -263,23 +308,24 @@ impl TensorBoardDataProvider for DataProviderHandler {
                 .read()
                 .map_err(|_| Status::internal(format!("failed to read run data for {:?}", run)))?;
             let mut run_res: data::list_blob_sequences_response::RunEntry = Default::default();
-            for (tag, ts) in &data.blob_sequences {
-                if !tag_filter.want(tag) {
+            for (tag, metadata, points) in blob_series(&data) {
+                let tag_name = commit::public_tag(tag);
+                if !tag_filter.want(tag_name) {
                     continue;
                 }
-                if plugin_name(&ts.metadata) != Some(&want_plugin) {
+                if plugin_name(metadata) != Some(&want_plugin) {
                     continue;
                 }
                 let (mut max_step, mut max_wall_time, mut max_length) = (None, None, None);
-                for (step, wall_time, value) in ts.valid_values() {
+                for (step, wall_time, length) in points {
                     if max_step.map_or(true, |s| s < step) {
                         max_step = Some(step);
                     }
                     if max_wall_time.map_or(true, |wt| wt < wall_time) {
                         max_wall_time = Some(wall_time);
                     }
-                    if max_length.map_or(true, |len| len < value.0.len()) {
-                        max_length = Some(value.0.len());
+                    if max_length.map_or(true, |len| len < length) {
+                        max_length = Some(length);
                     }
                 }
                 let (max_step, max_wall_time, max_length) =
@@ -290,12 +336,12 @@ impl TensorBoardDataProvider for DataProviderHandler {
                 run_res
                     .tags
                     .push(data::list_blob_sequences_response::TagEntry {
-                        tag_name: tag.0.clone(),
+                        tag_name: tag_name.to_string(),
                         metadata: Some(data::BlobSequenceMetadata {
                             max_step: max_step.into(),
                             max_wall_time: max_wall_time.into(),
                             max_length: max_length as i64,
-                            summary_metadata: Some(*ts.metadata.clone()),
+                            summary_metadata: Some(metadata.clone()),
                         }),
                     });
             }
@@ -319,33 +365,34 @@ impl TensorBoardDataProvider for DataProviderHandler {
         let runs = self.read_runs()?;
 
         let mut res: data::ReadBlobSequencesResponse = Default::default();
-        for (run, data) in runs.iter() {
+        for (run, data) in sorted(&runs) {
             if !run_filter.want(run) {
                 continue;
             }
             let data = data
                 .read()
                 .map_err(|_| Status::internal(format!("failed to read run data for {:?}", run)))?;
+            self.commit.touch(&data);
             let mut run_res: data::read_blob_sequences_response::RunEntry = Default::default();
-            for (tag, ts) in &data.blob_sequences {
-                if !tag_filter.want(tag) {
+            for (tag, metadata, mut points) in blob_series(&data) {
+                let tag_name = commit::public_tag(tag);
+                if !tag_filter.want(tag_name) {
                     continue;
                 }
-                if plugin_name(&ts.metadata) != Some(&want_plugin) {
+                if plugin_name(metadata) != Some(&want_plugin) {
                     continue;
                 }
 
-                let mut points = ts.valid_values().collect::<Vec<_>>();
-                downsample::downsample(&mut points, num_points);
+                num_points.apply(&mut points);
                 let n = points.len();
                 let mut steps = Vec::with_capacity(n);
                 let mut wall_times = Vec::with_capacity(n);
                 let mut values = Vec::with_capacity(n);
-                for (step, wall_time, &BlobSequenceValue(ref value)) in points {
+                for (step, wall_time, length) in points {
                     steps.push(step.into());
                     wall_times.push(wall_time.into());
                     let eid = req.experiment_id.as_str();
-                    let blob_refs = (0..value.len())
+                    let blob_refs = (0..length)
                         .map(|i| {
                             let bk = BlobKey {
                                 experiment_id: Cow::Borrowed(eid),
@@ -366,7 +413,7 @@ impl TensorBoardDataProvider for DataProviderHandler {
                 run_res
                     .tags
                     .push(data::read_blob_sequences_response::TagEntry {
-                        tag_name: tag.0.clone(),
+                        tag_name: tag_name.to_string(),
                         data: Some(data::BlobSequenceData {
                             step: steps,
                             wall_time: wall_times,
@@ -402,45 +449,54 @@ impl TensorBoardDataProvider for DataProviderHandler {
             .ok_or_else(|| Status::not_found(format!("no such run: {:?}", bk.run)))?
             .read()
             .map_err(|_| Status::internal(format!("failed to read run data for {:?}", bk.run)))?;
-        let ts = run_data
-            .blob_sequences
-            .get(bk.tag.as_ref())
-            .ok_or_else(|| {
-                Status::not_found(format!("run {:?} has no such tag: {:?}", bk.run, bk.tag))
-            })?;
-        let datum = ts
-            .valid_values()
-            .find_map(
-                |(step, _, value)| {
-                    if step == bk.step {
-                        Some(value)
-                    } else {
-                        None
-                    }
-                },
-            )
-            .ok_or_else(|| {
-                Status::not_found(format!(
-                    "run {:?}, tag {:?} has no step {}; may have been evicted",
-                    bk.run, bk.tag, bk.step.0
-                ))
-            })?;
-        let blobs = &datum.0;
-        let blob = blobs.get(bk.index).ok_or_else(|| {
+        self.commit.touch(&run_data);
+        let no_tag =
+            || Status::not_found(format!("run {:?} has no such tag: {:?}", bk.run, bk.tag));
+        let no_step = || {
+            Status::not_found(format!(
+                "run {:?}, tag {:?} has no step {}; may have been evicted",
+                bk.run, bk.tag, bk.step.0
+            ))
+        };
+        let no_index = |length: usize| {
             Status::not_found(format!(
                 "blob sequence at run {:?}, tag {:?}, step {:?} has no index {} (length: {})",
-                bk.run,
-                bk.tag,
-                bk.step,
-                bk.index,
-                blobs.len()
+                bk.run, bk.tag, bk.step, bk.index, length
             ))
-        })?;
-        // Clone blob so that we can send it down to the client after dropping the lock.
-        // TODO(@wchargin): Consider replacing this with an `Arc<[u8]>`.
-        let blob = blob.clone();
-        drop(run_data);
-        drop(runs);
+        };
+        let blob = if let Some(ts) = run_data.blob_sequences.get(bk.tag.as_ref()) {
+            let datum = point_at(ts, bk.step).ok_or_else(no_step)?;
+            // Clone blob so that we can send it down to the client after dropping the lock.
+            // TODO(@wchargin): Consider replacing this with an `Arc<[u8]>`.
+            let blobs = &datum.0;
+            let blob = (blobs.get(bk.index).ok_or_else(|| no_index(blobs.len())))?.clone();
+            drop(run_data);
+            drop(runs);
+            blob
+        } else {
+            let ts = run_data
+                .blob_references
+                .get(bk.tag.as_ref())
+                .ok_or_else(no_tag)?;
+            let reference = point_at(ts, bk.step).ok_or_else(no_step)?.clone();
+            let metadata = ts.metadata.clone();
+            // Read the event file without holding the locks.
+            drop(run_data);
+            drop(runs);
+            let source = self.blob_source.as_ref().ok_or_else(|| {
+                Status::failed_precondition(format!(
+                    "run {:?} holds blob references, but there's no log directory to read them from",
+                    bk.run
+                ))
+            })?;
+            let mut blobs = source
+                .read_blob(&reference, &metadata)
+                .map_err(|e| Status::data_loss(format!("failed to read blob sequence: {}", e)))?;
+            if bk.index >= blobs.0.len() {
+                return Err(no_index(blobs.0.len()));
+            }
+            blobs.0.swap_remove(bk.index)
+        };
 
         let stream = try_stream! {
             for chunk in blob.chunks(BLOB_CHUNK_SIZE) {
@@ -452,6 +508,48 @@ impl TensorBoardDataProvider for DataProviderHandler {
     }
 }
 
+/// Lists the entries of a map in order by key, for responses that don't depend on hash order.
+fn sorted<K: Ord, V>(map: &HashMap<K, V>) -> Vec<(&K, &V)> {
+    let mut entries: Vec<(&K, &V)> = map.iter().collect();
+    entries.sort_unstable_by_key(|&(key, _)| key);
+    entries
+}
+
+/// Finds the valid point of `ts` at the given step, if any.
+fn point_at<V>(ts: &TimeSeries<V>, step: Step) -> Option<&V> {
+    ts.valid_values()
+        .find_map(|(s, _, value)| if s == step { Some(value) } else { None })
+}
+
+/// Lists the blob sequence time series of a run in order by tag, with the step, wall time, and
+/// length of each valid point, whether the run holds the blobs themselves or
+/// [references](commit::RunData::blob_references) to them.
+#[allow(clippy::type_complexity)]
+fn blob_series(
+    data: &commit::RunData,
+) -> Vec<(&Tag, &pb::SummaryMetadata, Vec<(Step, WallTime, usize)>)> {
+    let mut series: Vec<_> = (data.blob_sequences.iter())
+        .map(|(tag, ts)| {
+            let points = ts.valid_values().map(|(s, wt, v)| (s, wt, v.0.len()));
+            (tag, ts.metadata.as_ref(), points.collect())
+        })
+        .chain(data.blob_references.iter().map(|(tag, ts)| {
+            let points = ts.valid_values().map(|(s, wt, r)| (s, wt, r.count));
+            (tag, ts.metadata.as_ref(), points.collect())
+        }))
+        .collect();
+    series.sort_unstable_by_key(|&(tag, _, _)| tag);
+    series
+}
+
+/// Gets the plugin name of a time series' metadata, if any.
+fn plugin_name(metadata: &pb::SummaryMetadata) -> Option<&str> {
+    metadata
+        .plugin_data
+        .as_ref()
+        .map(|pd| pd.plugin_name.as_str())
+}
+
 /// Parses a request plugin filter. Returns the desired plugin name, or an error if that's empty.
 fn parse_plugin_filter(pf: Option<data::PluginFilter>) -> Result<String, Status> {
     let want_plugin = pf.unwrap_or_default().plugin_name;
@@ -478,7 +576,7 @@ fn parse_rtf(rtf: Option<data::RunTagFilter>) -> (Filter<Run>, Filter<Tag>) {
 }
 
 /// Parses `Downsample.num_points` from a request, failing if it's not given or invalid.
-fn parse_downsample(downsample: Option<data::Downsample>) -> Result<usize, Status> {
+fn parse_downsample(downsample: Option<data::Downsample>) -> Result<Downsample, Status> {
     let num_points = downsample
         .ok_or_else(|| Status::invalid_argument("must specify downsample"))?
         .num_points;
@@ -488,13 +586,14 @@ fn parse_downsample(downsample: Option<data::Downsample>) -> Result<usize, Statu
             num_points
         )));
     }
-    num_points.try_into().map_err(|_| {
+    let num_points = num_points.try_into().map_err(|_| {
         Status::out_of_range(format!(
             "num_points ({}) is too large for this system; max: {}",
             num_points,
             usize::MAX
         ))
-    })
+    })?;
+    Ok(Downsample { num_points })
 }
 
 /// A predicate that accepts either all values or just an explicit set of values.
@@ -531,12 +630,15 @@ mod tests {
     use tonic::Code;
 
     use crate::commit::test_data::CommitBuilder;
+    use crate::commit::BlobSequenceValue;
+    use crate::proto::tensorboard as pb;
     use crate::types::{Run, Step, Tag};
 
     fn sample_handler(commit: Commit) -> DataProviderHandler {
         DataProviderHandler {
             // Leak the commit object, since the Tonic server must have only 'static references.
             commit: Box::leak(Box::new(commit)),
+            blob_source: None,
         }
     }
 
@@ -659,6 +761,126 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_list_tag_groups() {
+        let commit = CommitBuilder::new()
+            .scalars("train", "losses/total", |b| b.build())
+            .scalars("train", "losses/regularization", |b| b.build())
+            .scalars("train", "accuracy", |b| b.build())
+            .scalars("train", "eval/per_class/cat", |b| b.build())
+            .scalars("train", "/leading", |b| b.build())
+            .scalars("train", "eval//double", |b| b.build())
+            .blob_sequences("train", "eval/images", |b| b.build())
+            .run("empty", None)
+            .build();
+        let handler = sample_handler(commit);
+        let list = |run: &str, prefix: Option<&str>| {
+            let req = Request::new(data::ListTagGroupsRequest {
+                experiment_id: "123".to_string(),
+                run_name: run.to_string(),
+                group_filter: prefix.map(|p| data::TagGroupFilter {
+                    prefix: p.to_string(),
+                }),
+            });
+            handler.list_tag_groups(req)
+        };
+
+        let res = list("train", None).await.unwrap().into_inner();
+        let groups: Vec<(&str, i64)> = res
+            .groups
+            .iter()
+            .map(|g| (g.prefix.as_str(), g.tag_count))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![("", 1), ("accuracy", 1), ("eval", 3), ("losses", 2)]
+        );
+        assert!(res.tag_names.is_empty());
+
+        let res = list("train", Some("eval")).await.unwrap().into_inner();
+        assert_eq!(
+            res.tag_names,
+            vec!["eval//double", "eval/images", "eval/per_class/cat"]
+        );
+        let res = list("train", Some("")).await.unwrap().into_inner();
+        assert_eq!(res.tag_names, vec!["/leading"]);
+        let res = list("train", Some("nope")).await.unwrap().into_inner();
+        assert!(res.tag_names.is_empty());
+
+        let res = list("empty", None).await.unwrap().into_inner();
+        assert!(res.groups.is_empty());
+        let status = list("nonexistent", None).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_list_order_stable_while_loading() {
+        use crate::disk_logdir::DiskLogdir;
+        use crate::logdir::LogdirLoader;
+        use crate::types::WallTime;
+        use crate::writer::SummaryWriteExt;
+        use std::fs::{self, File};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let logdir = tempfile::tempdir().unwrap();
+        let root = logdir.path().to_path_buf();
+        let handler = sample_handler(Commit::new());
+        let commit = handler.commit;
+        let done = Arc::new(AtomicBool::new(false));
+        let loader_done = Arc::clone(&done);
+        // Adds runs and tags in reverse name order, reloading after each run, while the
+        // handler lists them below.
+        let loader_thread = std::thread::spawn(move || {
+            let mut loader = LogdirLoader::new(commit, DiskLogdir::new(root.clone()), 1);
+            for i in (0..20).rev() {
+                let dir = root.join(format!("run{:02}", i));
+                fs::create_dir(&dir).unwrap();
+                let mut file = File::create(dir.join("tfevents.123")).unwrap();
+                for t in (0..5).rev() {
+                    let tag = Tag(format!("tag{}", t));
+                    // All runs start at the same time, so `ListRuns` falls back to names.
+                    let wall_time = WallTime::new(1234.0).unwrap();
+                    file.write_scalar(&tag, Step(0), wall_time, 0.5).unwrap();
+                }
+                file.sync_all().unwrap();
+                loader.reload();
+            }
+            loader_done.store(true, Ordering::SeqCst);
+        });
+
+        let is_sorted = |names: &[String]| names.windows(2).all(|w| w[0] < w[1]);
+        loop {
+            let finished = done.load(Ordering::SeqCst);
+            let req = Request::new(data::ListRunsRequest {
+                experiment_id: "123".to_string(),
+            });
+            let res = handler.list_runs(req).await.unwrap().into_inner();
+            let names: Vec<String> = res.runs.into_iter().map(|r| r.name).collect();
+            assert!(is_sorted(&names), "{:?}", names);
+
+            let req = Request::new(data::ListScalarsRequest {
+                experiment_id: "123".to_string(),
+                plugin_filter: Some(data::PluginFilter {
+                    plugin_name: "scalars".to_string(),
+                }),
+                run_tag_filter: None,
+            });
+            let res = handler.list_scalars(req).await.unwrap().into_inner();
+            let run_names: Vec<String> = res.runs.iter().map(|r| r.run_name.clone()).collect();
+            assert!(is_sorted(&run_names), "{:?}", run_names);
+            for run in &res.runs {
+                let tags: Vec<String> = run.tags.iter().map(|t| t.tag_name.clone()).collect();
+                assert!(is_sorted(&tags), "{:?}", tags);
+            }
+            if finished {
+                assert_eq!(run_names.len(), 20);
+                break;
+            }
+        }
+        loader_thread.join().unwrap();
+    }
+
     /// Converts a list of `RunEntry`s into a nested map from `Run` to `Tag` to `TagEntry`, for
     /// easy assertions that don't depend on serialization order.
     ///
@@ -930,4 +1152,60 @@ mod tests {
         ];
         assert_eq!(chunks, expected_chunks);
     }
+
+    #[tokio::test]
+    async fn test_blob_references() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::disk_logdir::DiskLogdir;
+        use crate::logdir::LogdirLoader;
+        use crate::types::WallTime;
+        use crate::writer::SummaryWriteExt;
+        use std::fs::File;
+        use std::io::BufWriter;
+
+        let logdir = tempfile::tempdir()?;
+        std::fs::create_dir(logdir.path().join("train"))?;
+        let mut f = BufWriter::new(File::create(logdir.path().join("train/tfevents.123"))?);
+        let graph = b"<sample model graph>".to_vec();
+        f.write_graph(Step(0), WallTime::new(1234.0).unwrap(), graph.clone())?;
+        f.into_inner()?.sync_all()?;
+
+        let commit = Commit::new();
+        let mut loader = LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().into()), 1);
+        loader.blob_references(true);
+        loader.reload();
+        let mut handler = sample_handler(commit);
+
+        let read_req = Request::new(data::ReadBlobSequencesRequest {
+            experiment_id: "123".to_string(),
+            plugin_filter: Some(data::PluginFilter {
+                plugin_name: "graphs".to_string(),
+            }),
+            downsample: Some(data::Downsample { num_points: 1000 }),
+            ..Default::default()
+        });
+        let read_res = handler.read_blob_sequences(read_req).await?.into_inner();
+        assert_eq!(read_res.runs.len(), 1);
+        assert_eq!(read_res.runs[0].tags[0].tag_name, "__run_graph__");
+        let data = (read_res.runs[0].tags[0].data.as_ref()).expect("blob sequence data");
+        assert_eq!(data.step, vec![0]);
+        assert_eq!(data.values[0].blob_refs.len(), 1);
+        let blob_key = data.values[0].blob_refs[0].blob_key.clone();
+
+        // Blob references can't be read back without the log directory.
+        let blob_req = Request::new(data::ReadBlobRequest {
+            blob_key: blob_key.clone(),
+        });
+        let status = handler.read_blob(blob_req).await.map(|_| ()).unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+
+        handler.blob_source = Some(Arc::new(DiskLogdir::new(logdir.path().into())));
+        let blob_req = Request::new(data::ReadBlobRequest { blob_key });
+        let mut blob_res = handler.read_blob(blob_req).await?.into_inner();
+        let mut blob = Vec::new();
+        while let Some(chunk) = blob_res.next().await {
+            blob.extend(chunk?.data);
+        }
+        assert_eq!(blob, graph);
+        Ok(())
+    }
 }
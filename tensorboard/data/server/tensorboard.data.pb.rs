@@ -91,6 +91,45 @@ pub struct Run {
     pub start_time: f64,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListTagGroupsRequest {
+    /// ID of experiment in which to query data.
+    #[prost(string, tag="1")]
+    pub experiment_id: ::prost::alloc::string::String,
+    /// Name of the run whose tags to group.
+    #[prost(string, tag="2")]
+    pub run_name: ::prost::alloc::string::String,
+    /// Optional filter for a single group. If set, `tag_names` in the response
+    /// lists the tags in this group; if omitted, `tag_names` is empty.
+    #[prost(message, optional, tag="3")]
+    pub group_filter: ::core::option::Option<TagGroupFilter>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TagGroupFilter {
+    /// Only match tags in the group with exactly this prefix, which may be empty.
+    #[prost(string, tag="1")]
+    pub prefix: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListTagGroupsResponse {
+    /// Groups of tags in the run, sorted by prefix. A tag's group is the part of
+    /// its name before the first `/`, or its whole name if it has no `/`.
+    #[prost(message, repeated, tag="1")]
+    pub groups: ::prost::alloc::vec::Vec<TagGroup>,
+    /// Tags in the group given by `group_filter`, sorted, across all data classes.
+    #[prost(string, repeated, tag="2")]
+    pub tag_names: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TagGroup {
+    /// First component of the names of the tags in this group. May be empty, for
+    /// tags whose names start with `/`.
+    #[prost(string, tag="1")]
+    pub prefix: ::prost::alloc::string::String,
+    /// Number of tags in this group, across all data classes.
+    #[prost(int64, tag="2")]
+    pub tag_count: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListScalarsRequest {
     /// ID of experiment in which to query data.
     #[prost(string, tag="1")]
@@ -425,4 +464,20 @@ pub struct ReadBlobResponse {
     #[prost(bytes="vec", tag="1")]
     pub data: ::prost::alloc::vec::Vec<u8>,
 }
-# [doc = r" Generated client implementations."] pub mod tensor_board_data_provider_client { # ! [allow (unused_variables , dead_code , missing_docs)] use tonic :: codegen :: * ; pub struct TensorBoardDataProviderClient < T > { inner : tonic :: client :: Grpc < T > , } impl TensorBoardDataProviderClient < tonic :: transport :: Channel > { # [doc = r" Attempt to create a new client by connecting to a given endpoint."] pub async fn connect < D > (dst : D) -> Result < Self , tonic :: transport :: Error > where D : std :: convert :: TryInto < tonic :: transport :: Endpoint > , D :: Error : Into < StdError > , { let conn = tonic :: transport :: Endpoint :: new (dst) ? . connect () . await ? ; Ok (Self :: new (conn)) } } impl < T > TensorBoardDataProviderClient < T > where T : tonic :: client :: GrpcService < tonic :: body :: BoxBody > , T :: ResponseBody : Body + HttpBody + Send + 'static , T :: Error : Into < StdError > , < T :: ResponseBody as HttpBody > :: Error : Into < StdError > + Send , { pub fn new (inner : T) -> Self { let inner = tonic :: client :: Grpc :: new (inner) ; Self { inner } } pub fn with_interceptor (inner : T , interceptor : impl Into < tonic :: Interceptor >) -> Self { let inner = tonic :: client :: Grpc :: with_interceptor (inner , interceptor) ; Self { inner } } # [doc = " List plugins that have data for an experiment."] pub async fn list_plugins (& mut self , request : impl tonic :: IntoRequest < super :: ListPluginsRequest > ,) -> Result < tonic :: Response < super :: ListPluginsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ListPlugins") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " List runs within an experiment."] pub async fn list_runs (& mut self , request : impl tonic :: IntoRequest < super :: ListRunsRequest > ,) -> Result < tonic :: Response < super :: ListRunsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ListRuns") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " List metadata about scalar time series."] pub async fn list_scalars (& mut self , request : impl tonic :: IntoRequest < super :: ListScalarsRequest > ,) -> Result < tonic :: Response < super :: ListScalarsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ListScalars") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " Read data from scalar time series."] pub async fn read_scalars (& mut self , request : impl tonic :: IntoRequest < super :: ReadScalarsRequest > ,) -> Result < tonic :: Response < super :: ReadScalarsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ReadScalars") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " List metadata about tensor time series."] pub async fn list_tensors (& mut self , request : impl tonic :: IntoRequest < super :: ListTensorsRequest > ,) -> Result < tonic :: Response < super :: ListTensorsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ListTensors") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " Read data from tensor time series."] pub async fn read_tensors (& mut self , request : impl tonic :: IntoRequest < super :: ReadTensorsRequest > ,) -> Result < tonic :: Response < super :: ReadTensorsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ReadTensors") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " List metadata about blob sequence time series."] pub async fn list_blob_sequences (& mut self , request : impl tonic :: IntoRequest < super :: ListBlobSequencesRequest > ,) -> Result < tonic :: Response < super :: ListBlobSequencesResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ListBlobSequences") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " Read blob references from blob sequence time series. See `ReadBlob` to read"] # [doc = " the actual blob data."] pub async fn read_blob_sequences (& mut self , request : impl tonic :: IntoRequest < super :: ReadBlobSequencesRequest > ,) -> Result < tonic :: Response < super :: ReadBlobSequencesResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ReadBlobSequences") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " Read data for a specific blob."] pub async fn read_blob (& mut self , request : impl tonic :: IntoRequest < super :: ReadBlobRequest > ,) -> Result < tonic :: Response < tonic :: codec :: Streaming < super :: ReadBlobResponse >> , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ReadBlob") ; self . inner . server_streaming (request . into_request () , path , codec) . await } } impl < T : Clone > Clone for TensorBoardDataProviderClient < T > { fn clone (& self) -> Self { Self { inner : self . inner . clone () , } } } impl < T > std :: fmt :: Debug for TensorBoardDataProviderClient < T > { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { write ! (f , "TensorBoardDataProviderClient {{ ... }}") } } }# [doc = r" Generated server implementations."] pub mod tensor_board_data_provider_server { # ! [allow (unused_variables , dead_code , missing_docs)] use tonic :: codegen :: * ; # [doc = "Generated trait containing gRPC methods that should be implemented for use with TensorBoardDataProviderServer."] # [async_trait] pub trait TensorBoardDataProvider : Send + Sync + 'static { # [doc = " List plugins that have data for an experiment."] async fn list_plugins (& self , request : tonic :: Request < super :: ListPluginsRequest >) -> Result < tonic :: Response < super :: ListPluginsResponse > , tonic :: Status > ; # [doc = " List runs within an experiment."] async fn list_runs (& self , request : tonic :: Request < super :: ListRunsRequest >) -> Result < tonic :: Response < super :: ListRunsResponse > , tonic :: Status > ; # [doc = " List metadata about scalar time series."] async fn list_scalars (& self , request : tonic :: Request < super :: ListScalarsRequest >) -> Result < tonic :: Response < super :: ListScalarsResponse > , tonic :: Status > ; # [doc = " Read data from scalar time series."] async fn read_scalars (& self , request : tonic :: Request < super :: ReadScalarsRequest >) -> Result < tonic :: Response < super :: ReadScalarsResponse > , tonic :: Status > ; # [doc = " List metadata about tensor time series."] async fn list_tensors (& self , request : tonic :: Request < super :: ListTensorsRequest >) -> Result < tonic :: Response < super :: ListTensorsResponse > , tonic :: Status > ; # [doc = " Read data from tensor time series."] async fn read_tensors (& self , request : tonic :: Request < super :: ReadTensorsRequest >) -> Result < tonic :: Response < super :: ReadTensorsResponse > , tonic :: Status > ; # [doc = " List metadata about blob sequence time series."] async fn list_blob_sequences (& self , request : tonic :: Request < super :: ListBlobSequencesRequest >) -> Result < tonic :: Response < super :: ListBlobSequencesResponse > , tonic :: Status > ; # [doc = " Read blob references from blob sequence time series. See `ReadBlob` to read"] # [doc = " the actual blob data."] async fn read_blob_sequences (& self , request : tonic :: Request < super :: ReadBlobSequencesRequest >) -> Result < tonic :: Response < super :: ReadBlobSequencesResponse > , tonic :: Status > ; # [doc = "Server streaming response type for the ReadBlob method."] type ReadBlobStream : Stream < Item = Result < super :: ReadBlobResponse , tonic :: Status >> + Send + Sync + 'static ; # [doc = " Read data for a specific blob."] async fn read_blob (& self , request : tonic :: Request < super :: ReadBlobRequest >) -> Result < tonic :: Response < Self :: ReadBlobStream > , tonic :: Status > ; } # [derive (Debug)] pub struct TensorBoardDataProviderServer < T : TensorBoardDataProvider > { inner : _Inner < T > , } struct _Inner < T > (Arc < T > , Option < tonic :: Interceptor >) ; impl < T : TensorBoardDataProvider > TensorBoardDataProviderServer < T > { pub fn new (inner : T) -> Self { let inner = Arc :: new (inner) ; let inner = _Inner (inner , None) ; Self { inner } } pub fn with_interceptor (inner : T , interceptor : impl Into < tonic :: Interceptor >) -> Self { let inner = Arc :: new (inner) ; let inner = _Inner (inner , Some (interceptor . into ())) ; Self { inner } } } impl < T , B > Service < http :: Request < B >> for TensorBoardDataProviderServer < T > where T : TensorBoardDataProvider , B : HttpBody + Send + Sync + 'static , B :: Error : Into < StdError > + Send + 'static , { type Response = http :: Response < tonic :: body :: BoxBody > ; type Error = Never ; type Future = BoxFuture < Self :: Response , Self :: Error > ; fn poll_ready (& mut self , _cx : & mut Context < '_ >) -> Poll < Result < () , Self :: Error >> { Poll :: Ready (Ok (())) } fn call (& mut self , req : http :: Request < B >) -> Self :: Future { let inner = self . inner . clone () ; match req . uri () . path () { "/tensorboard.data.TensorBoardDataProvider/ListPlugins" => { # [allow (non_camel_case_types)] struct ListPluginsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ListPluginsRequest > for ListPluginsSvc < T > { type Response = super :: ListPluginsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ListPluginsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . list_plugins (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ListPluginsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ListRuns" => { # [allow (non_camel_case_types)] struct ListRunsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ListRunsRequest > for ListRunsSvc < T > { type Response = super :: ListRunsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ListRunsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . list_runs (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ListRunsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ListScalars" => { # [allow (non_camel_case_types)] struct ListScalarsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ListScalarsRequest > for ListScalarsSvc < T > { type Response = super :: ListScalarsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ListScalarsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . list_scalars (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ListScalarsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ReadScalars" => { # [allow (non_camel_case_types)] struct ReadScalarsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ReadScalarsRequest > for ReadScalarsSvc < T > { type Response = super :: ReadScalarsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ReadScalarsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . read_scalars (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ReadScalarsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ListTensors" => { # [allow (non_camel_case_types)] struct ListTensorsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ListTensorsRequest > for ListTensorsSvc < T > { type Response = super :: ListTensorsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ListTensorsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . list_tensors (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ListTensorsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ReadTensors" => { # [allow (non_camel_case_types)] struct ReadTensorsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ReadTensorsRequest > for ReadTensorsSvc < T > { type Response = super :: ReadTensorsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ReadTensorsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . read_tensors (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ReadTensorsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ListBlobSequences" => { # [allow (non_camel_case_types)] struct ListBlobSequencesSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ListBlobSequencesRequest > for ListBlobSequencesSvc < T > { type Response = super :: ListBlobSequencesResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ListBlobSequencesRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . list_blob_sequences (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ListBlobSequencesSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ReadBlobSequences" => { # [allow (non_camel_case_types)] struct ReadBlobSequencesSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ReadBlobSequencesRequest > for ReadBlobSequencesSvc < T > { type Response = super :: ReadBlobSequencesResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ReadBlobSequencesRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . read_blob_sequences (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ReadBlobSequencesSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ReadBlob" => { # [allow (non_camel_case_types)] struct ReadBlobSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: ServerStreamingService < super :: ReadBlobRequest > for ReadBlobSvc < T > { type Response = super :: ReadBlobResponse ; type ResponseStream = T :: ReadBlobStream ; type Future = BoxFuture < tonic :: Response < Self :: ResponseStream > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ReadBlobRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . read_blob (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 ; let inner = inner . 0 ; let method = ReadBlobSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . server_streaming (method , req) . await ; Ok (res) } ; Box :: pin (fut) } _ => Box :: pin (async move { Ok (http :: Response :: builder () . status (200) . header ("grpc-status" , "12") . header ("content-type" , "application/grpc") . body (tonic :: body :: BoxBody :: empty ()) . unwrap ()) }) , } } } impl < T : TensorBoardDataProvider > Clone for TensorBoardDataProviderServer < T > { fn clone (& self) -> Self { let inner = self . inner . clone () ; Self { inner } } } impl < T : TensorBoardDataProvider > Clone for _Inner < T > { fn clone (& self) -> Self { Self (self . 0 . clone () , self . 1 . clone ()) } } impl < T : std :: fmt :: Debug > std :: fmt :: Debug for _Inner < T > { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { write ! (f , "{:?}" , self . 0) } } impl < T : TensorBoardDataProvider > tonic :: transport :: NamedService for TensorBoardDataProviderServer < T > { const NAME : & 'static str = "tensorboard.data.TensorBoardDataProvider" ; } }
\ No newline at end of file
+# [doc = r" Generated client implementations."] pub mod tensor_board_data_provider_client { # ! [allow (unused_variables , dead_code , missing_docs)] use tonic :: codegen :: * ; pub struct TensorBoardDataProviderClient < T > { inner : tonic :: client :: Grpc < T > , } impl TensorBoardDataProviderClient < tonic :: transport :: Channel > { # [doc = r" Attempt to create a new client by connecting to a given endpoint."] pub async fn connect < D > (dst : D) -> Result < Self , tonic :: transport :: Error > where D : std :: convert :: TryInto < tonic :: transport :: Endpoint > , D :: Error : Into < StdError > , { let conn = tonic :: transport :: Endpoint :: new (dst) ? . connect () . await ? ; Ok (Self :: new (conn)) } } impl < T > TensorBoardDataProviderClient < T > where T : tonic :: client :: GrpcService < tonic :: body :: BoxBody > , T :: ResponseBody : Body + HttpBody + Send + 'static , T :: Error : Into < StdError > , < T :: ResponseBody as HttpBody > :: Error : Into < StdError > + Send , { pub fn new (inner : T) -> Self { let inner = tonic :: client :: Grpc :: new (inner) ; Self { inner } } pub fn with_interceptor (inner : T , interceptor : impl Into < tonic :: Interceptor >) -> Self { let inner = tonic :: client :: Grpc :: with_interceptor (inner , interceptor) ; Self { inner } } # [doc = " List plugins that have data for an experiment."] pub async fn list_plugins (& mut self , request : impl tonic :: IntoRequest < super :: ListPluginsRequest > ,) -> Result < tonic :: Response < super :: ListPluginsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ListPlugins") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " List runs within an experiment."] pub async fn list_runs (& mut self , request : impl tonic :: IntoRequest < super :: ListRunsRequest > ,) -> Result < tonic :: Response < super :: ListRunsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ListRuns") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " List groups of tags within a run, by the first component of tag names."] pub async fn list_tag_groups (& mut self , request : impl tonic :: IntoRequest < super :: ListTagGroupsRequest > ,) -> Result < tonic :: Response < super :: ListTagGroupsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ListTagGroups") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " List metadata about scalar time series."] pub async fn list_scalars (& mut self , request : impl tonic :: IntoRequest < super :: ListScalarsRequest > ,) -> Result < tonic :: Response < super :: ListScalarsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ListScalars") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " Read data from scalar time series."] pub async fn read_scalars (& mut self , request : impl tonic :: IntoRequest < super :: ReadScalarsRequest > ,) -> Result < tonic :: Response < super :: ReadScalarsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ReadScalars") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " List metadata about tensor time series."] pub async fn list_tensors (& mut self , request : impl tonic :: IntoRequest < super :: ListTensorsRequest > ,) -> Result < tonic :: Response < super :: ListTensorsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ListTensors") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " Read data from tensor time series."] pub async fn read_tensors (& mut self , request : impl tonic :: IntoRequest < super :: ReadTensorsRequest > ,) -> Result < tonic :: Response < super :: ReadTensorsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ReadTensors") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " List metadata about blob sequence time series."] pub async fn list_blob_sequences (& mut self , request : impl tonic :: IntoRequest < super :: ListBlobSequencesRequest > ,) -> Result < tonic :: Response < super :: ListBlobSequencesResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ListBlobSequences") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " Read blob references from blob sequence time series. See `ReadBlob` to read"] # [doc = " the actual blob data."] pub async fn read_blob_sequences (& mut self , request : impl tonic :: IntoRequest < super :: ReadBlobSequencesRequest > ,) -> Result < tonic :: Response < super :: ReadBlobSequencesResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ReadBlobSequences") ; self . inner . unary (request . into_request () , path , codec) . await } # [doc = " Read data for a specific blob."] pub async fn read_blob (& mut self , request : impl tonic :: IntoRequest < super :: ReadBlobRequest > ,) -> Result < tonic :: Response < tonic :: codec :: Streaming < super :: ReadBlobResponse >> , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardDataProvider/ReadBlob") ; self . inner . server_streaming (request . into_request () , path , codec) . await } } impl < T : Clone > Clone for TensorBoardDataProviderClient < T > { fn clone (& self) -> Self { Self { inner : self . inner . clone () , } } } impl < T > std :: fmt :: Debug for TensorBoardDataProviderClient < T > { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { write ! (f , "TensorBoardDataProviderClient {{ ... }}") } } }# [doc = r" Generated server implementations."] pub mod tensor_board_data_provider_server { # ! [allow (unused_variables , dead_code , missing_docs)] use tonic :: codegen :: * ; # [doc = "Generated trait containing gRPC methods that should be implemented for use with TensorBoardDataProviderServer."] # [async_trait] pub trait TensorBoardDataProvider : Send + Sync + 'static { # [doc = " List plugins that have data for an experiment."] async fn list_plugins (& self , request : tonic :: Request < super :: ListPluginsRequest >) -> Result < tonic :: Response < super :: ListPluginsResponse > , tonic :: Status > ; # [doc = " List runs within an experiment."] async fn list_runs (& self , request : tonic :: Request < super :: ListRunsRequest >) -> Result < tonic :: Response < super :: ListRunsResponse > , tonic :: Status > ; # [doc = " List groups of tags within a run, by the first component of tag names."] async fn list_tag_groups (& self , request : tonic :: Request < super :: ListTagGroupsRequest >) -> Result < tonic :: Response < super :: ListTagGroupsResponse > , tonic :: Status > ; # [doc = " List metadata about scalar time series."] async fn list_scalars (& self , request : tonic :: Request < super :: ListScalarsRequest >) -> Result < tonic :: Response < super :: ListScalarsResponse > , tonic :: Status > ; # [doc = " Read data from scalar time series."] async fn read_scalars (& self , request : tonic :: Request < super :: ReadScalarsRequest >) -> Result < tonic :: Response < super :: ReadScalarsResponse > , tonic :: Status > ; # [doc = " List metadata about tensor time series."] async fn list_tensors (& self , request : tonic :: Request < super :: ListTensorsRequest >) -> Result < tonic :: Response < super :: ListTensorsResponse > , tonic :: Status > ; # [doc = " Read data from tensor time series."] async fn read_tensors (& self , request : tonic :: Request < super :: ReadTensorsRequest >) -> Result < tonic :: Response < super :: ReadTensorsResponse > , tonic :: Status > ; # [doc = " List metadata about blob sequence time series."] async fn list_blob_sequences (& self , request : tonic :: Request < super :: ListBlobSequencesRequest >) -> Result < tonic :: Response < super :: ListBlobSequencesResponse > , tonic :: Status > ; # [doc = " Read blob references from blob sequence time series. See `ReadBlob` to read"] # [doc = " the actual blob data."] async fn read_blob_sequences (& self , request : tonic :: Request < super :: ReadBlobSequencesRequest >) -> Result < tonic :: Response < super :: ReadBlobSequencesResponse > , tonic :: Status > ; # [doc = "Server streaming response type for the ReadBlob method."] type ReadBlobStream : Stream < Item = Result < super :: ReadBlobResponse , tonic :: Status >> + Send + Sync + 'static ; # [doc = " Read data for a specific blob."] async fn read_blob (& self , request : tonic :: Request < super :: ReadBlobRequest >) -> Result < tonic :: Response < Self :: ReadBlobStream > , tonic :: Status > ; } # [derive (Debug)] pub struct TensorBoardDataProviderServer < T : TensorBoardDataProvider > { inner : _Inner < T > , } struct _Inner < T > (Arc < T > , Option < tonic :: Interceptor >) ; impl < T : TensorBoardDataProvider > TensorBoardDataProviderServer < T > { pub fn new (inner : T) -> Self { let inner = Arc :: new (inner) ; let inner = _Inner (inner , None) ; Self { inner } } pub fn with_interceptor (inner : T , interceptor : impl Into < tonic :: Interceptor >) -> Self { let inner = Arc :: new (inner) ; let inner = _Inner (inner , Some (interceptor . into ())) ; Self { inner } } } impl < T , B > Service < http :: Request < B >> for TensorBoardDataProviderServer < T > where T : TensorBoardDataProvider , B : HttpBody + Send + Sync + 'static , B :: Error : Into < StdError > + Send + 'static , { type Response = http :: Response < tonic :: body :: BoxBody > ; type Error = Never ; type Future = BoxFuture < Self :: Response , Self :: Error > ; fn poll_ready (& mut self , _cx : & mut Context < '_ >) -> Poll < Result < () , Self :: Error >> { Poll :: Ready (Ok (())) } fn call (& mut self , req : http :: Request < B >) -> Self :: Future { let inner = self . inner . clone () ; match req . uri () . path () { "/tensorboard.data.TensorBoardDataProvider/ListPlugins" => { # [allow (non_camel_case_types)] struct ListPluginsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ListPluginsRequest > for ListPluginsSvc < T > { type Response = super :: ListPluginsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ListPluginsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . list_plugins (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ListPluginsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ListRuns" => { # [allow (non_camel_case_types)] struct ListRunsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ListRunsRequest > for ListRunsSvc < T > { type Response = super :: ListRunsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ListRunsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . list_runs (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ListRunsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ListTagGroups" => { # [allow (non_camel_case_types)] struct ListTagGroupsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ListTagGroupsRequest > for ListTagGroupsSvc < T > { type Response = super :: ListTagGroupsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ListTagGroupsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . list_tag_groups (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ListTagGroupsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ListScalars" => { # [allow (non_camel_case_types)] struct ListScalarsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ListScalarsRequest > for ListScalarsSvc < T > { type Response = super :: ListScalarsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ListScalarsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . list_scalars (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ListScalarsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ReadScalars" => { # [allow (non_camel_case_types)] struct ReadScalarsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ReadScalarsRequest > for ReadScalarsSvc < T > { type Response = super :: ReadScalarsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ReadScalarsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . read_scalars (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ReadScalarsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ListTensors" => { # [allow (non_camel_case_types)] struct ListTensorsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ListTensorsRequest > for ListTensorsSvc < T > { type Response = super :: ListTensorsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ListTensorsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . list_tensors (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ListTensorsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ReadTensors" => { # [allow (non_camel_case_types)] struct ReadTensorsSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ReadTensorsRequest > for ReadTensorsSvc < T > { type Response = super :: ReadTensorsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ReadTensorsRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . read_tensors (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ReadTensorsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ListBlobSequences" => { # [allow (non_camel_case_types)] struct ListBlobSequencesSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ListBlobSequencesRequest > for ListBlobSequencesSvc < T > { type Response = super :: ListBlobSequencesResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ListBlobSequencesRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . list_blob_sequences (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ListBlobSequencesSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ReadBlobSequences" => { # [allow (non_camel_case_types)] struct ReadBlobSequencesSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: UnaryService < super :: ReadBlobSequencesRequest > for ReadBlobSequencesSvc < T > { type Response = super :: ReadBlobSequencesResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ReadBlobSequencesRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . read_blob_sequences (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 . clone () ; let inner = inner . 0 ; let method = ReadBlobSequencesSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . unary (method , req) . await ; Ok (res) } ; Box :: pin (fut) } "/tensorboard.data.TensorBoardDataProvider/ReadBlob" => { # [allow (non_camel_case_types)] struct ReadBlobSvc < T : TensorBoardDataProvider > (pub Arc < T >) ; impl < T : TensorBoardDataProvider > tonic :: server :: ServerStreamingService < super :: ReadBlobRequest > for ReadBlobSvc < T > { type Response = super :: ReadBlobResponse ; type ResponseStream = T :: ReadBlobStream ; type Future = BoxFuture < tonic :: Response < Self :: ResponseStream > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < super :: ReadBlobRequest >) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . read_blob (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 ; let inner = inner . 0 ; let method = ReadBlobSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . server_streaming (method , req) . await ; Ok (res) } ; Box :: pin (fut) } _ => Box :: pin (async move { Ok (http :: Response :: builder () . status (200) . header ("grpc-status" , "12") . header ("content-type" , "application/grpc") . body (tonic :: body :: BoxBody :: empty ()) . unwrap ()) }) , } } } impl < T : TensorBoardDataProvider > Clone for TensorBoardDataProviderServer < T > { fn clone (& self) -> Self { let inner = self . inner . clone () ; Self { inner } } } impl < T : TensorBoardDataProvider > Clone for _Inner < T > { fn clone (& self) -> Self { Self (self . 0 . clone () , self . 1 . clone ()) } } impl < T : std :: fmt :: Debug > std :: fmt :: Debug for _Inner < T > { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { write ! (f , "{:?}" , self . 0) } } impl < T : TensorBoardDataProvider > tonic :: transport :: NamedService for TensorBoardDataProviderServer < T > { const NAME : & 'static str = "tensorboard.data.TensorBoardDataProvider" ; } }#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WriteEventsRequest {
+    /// Name of the run to write to. Required on the first request of a stream;
+    /// later requests must leave it empty or repeat it.
+    #[prost(string, tag="1")]
+    pub run_name: ::prost::alloc::string::String,
+    /// Events to write, in order.
+    #[prost(message, repeated, tag="2")]
+    pub events: ::prost::alloc::vec::Vec<super::Event>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WriteEventsResponse {
+    /// Number of events written over the whole stream.
+    #[prost(int64, tag="1")]
+    pub events_written: i64,
+}
+# [doc = r" Generated client implementations."] pub mod tensor_board_ingest_client { # ! [allow (unused_variables , dead_code , missing_docs)] use tonic :: codegen :: * ; # [doc = " Accepts events pushed straight to the data server, as an alternative to"] # [doc = " writing event files to the log directory."] pub struct TensorBoardIngestClient < T > { inner : tonic :: client :: Grpc < T > , } impl TensorBoardIngestClient < tonic :: transport :: Channel > { # [doc = r" Attempt to create a new client by connecting to a given endpoint."] pub async fn connect < D > (dst : D) -> Result < Self , tonic :: transport :: Error > where D : std :: convert :: TryInto < tonic :: transport :: Endpoint > , D :: Error : Into < StdError > , { let conn = tonic :: transport :: Endpoint :: new (dst) ? . connect () . await ? ; Ok (Self :: new (conn)) } } impl < T > TensorBoardIngestClient < T > where T : tonic :: client :: GrpcService < tonic :: body :: BoxBody > , T :: ResponseBody : Body + HttpBody + Send + 'static , T :: Error : Into < StdError > , < T :: ResponseBody as HttpBody > :: Error : Into < StdError > + Send , { pub fn new (inner : T) -> Self { let inner = tonic :: client :: Grpc :: new (inner) ; Self { inner } } pub fn with_interceptor (inner : T , interceptor : impl Into < tonic :: Interceptor >) -> Self { let inner = tonic :: client :: Grpc :: with_interceptor (inner , interceptor) ; Self { inner } } # [doc = " Write events to a run that has no event files. Only one stream may write"] # [doc = " to a run at a time."] pub async fn write_events (& mut self , request : impl tonic :: IntoStreamingRequest < Message = super :: WriteEventsRequest >) -> Result < tonic :: Response < super :: WriteEventsResponse > , tonic :: Status > { self . inner . ready () . await . map_err (| e | { tonic :: Status :: new (tonic :: Code :: Unknown , format ! ("Service was not ready: {}" , e . into ())) }) ? ; let codec = tonic :: codec :: ProstCodec :: default () ; let path = http :: uri :: PathAndQuery :: from_static ("/tensorboard.data.TensorBoardIngest/WriteEvents") ; self . inner . client_streaming (request . into_streaming_request () , path , codec) . await } } impl < T : Clone > Clone for TensorBoardIngestClient < T > { fn clone (& self) -> Self { Self { inner : self . inner . clone () , } } } impl < T > std :: fmt :: Debug for TensorBoardIngestClient < T > { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { write ! (f , "TensorBoardIngestClient {{ ... }}") } } }# [doc = r" Generated server implementations."] pub mod tensor_board_ingest_server { # ! [allow (unused_variables , dead_code , missing_docs)] use tonic :: codegen :: * ; # [doc = "Generated trait containing gRPC methods that should be implemented for use with TensorBoardIngestServer."] # [async_trait] pub trait TensorBoardIngest : Send + Sync + 'static { # [doc = " Write events to a run that has no event files. Only one stream may write"] # [doc = " to a run at a time."] async fn write_events (& self , request : tonic :: Request < tonic :: Streaming < super :: WriteEventsRequest >>) -> Result < tonic :: Response < super :: WriteEventsResponse > , tonic :: Status > ; } # [doc = " Accepts events pushed straight to the data server, as an alternative to"] # [doc = " writing event files to the log directory."] # [derive (Debug)] pub struct TensorBoardIngestServer < T : TensorBoardIngest > { inner : _Inner < T > , } struct _Inner < T > (Arc < T > , Option < tonic :: Interceptor >) ; impl < T : TensorBoardIngest > TensorBoardIngestServer < T > { pub fn new (inner : T) -> Self { let inner = Arc :: new (inner) ; let inner = _Inner (inner , None) ; Self { inner } } pub fn with_interceptor (inner : T , interceptor : impl Into < tonic :: Interceptor >) -> Self { let inner = Arc :: new (inner) ; let inner = _Inner (inner , Some (interceptor . into ())) ; Self { inner } } } impl < T , B > Service < http :: Request < B >> for TensorBoardIngestServer < T > where T : TensorBoardIngest , B : HttpBody + Send + Sync + 'static , B :: Error : Into < StdError > + Send + 'static , { type Response = http :: Response < tonic :: body :: BoxBody > ; type Error = Never ; type Future = BoxFuture < Self :: Response , Self :: Error > ; fn poll_ready (& mut self , _cx : & mut Context < '_ >) -> Poll < Result < () , Self :: Error >> { Poll :: Ready (Ok (())) } fn call (& mut self , req : http :: Request < B >) -> Self :: Future { let inner = self . inner . clone () ; match req . uri () . path () { "/tensorboard.data.TensorBoardIngest/WriteEvents" => { # [allow (non_camel_case_types)] struct WriteEventsSvc < T : TensorBoardIngest > (pub Arc < T >) ; impl < T : TensorBoardIngest > tonic :: server :: ClientStreamingService < super :: WriteEventsRequest > for WriteEventsSvc < T > { type Response = super :: WriteEventsResponse ; type Future = BoxFuture < tonic :: Response < Self :: Response > , tonic :: Status > ; fn call (& mut self , request : tonic :: Request < tonic :: Streaming < super :: WriteEventsRequest >>) -> Self :: Future { let inner = self . 0 . clone () ; let fut = async move { (* inner) . write_events (request) . await } ; Box :: pin (fut) } } let inner = self . inner . clone () ; let fut = async move { let interceptor = inner . 1 ; let inner = inner . 0 ; let method = WriteEventsSvc (inner) ; let codec = tonic :: codec :: ProstCodec :: default () ; let mut grpc = if let Some (interceptor) = interceptor { tonic :: server :: Grpc :: with_interceptor (codec , interceptor) } else { tonic :: server :: Grpc :: new (codec) } ; let res = grpc . client_streaming (method , req) . await ; Ok (res) } ; Box :: pin (fut) } _ => Box :: pin (async move { Ok (http :: Response :: builder () . status (200) . header ("grpc-status" , "12") . header ("content-type" , "application/grpc") . body (tonic :: body :: BoxBody :: empty ()) . unwrap ()) }) , } } } impl < T : TensorBoardIngest > Clone for TensorBoardIngestServer < T > { fn clone (& self) -> Self { let inner = self . inner . clone () ; Self { inner } } } impl < T : TensorBoardIngest > Clone for _Inner < T > { fn clone (& self) -> Self { Self (self . 0 . clone () , self . 1 . clone ()) } } impl < T : std :: fmt :: Debug > std :: fmt :: Debug for _Inner < T > { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { write ! (f , "{:?}" , self . 0) } } impl < T : TensorBoardIngest > tonic :: transport :: NamedService for TensorBoardIngestServer < T > { const NAME : & 'static str = "tensorboard.data.TensorBoardIngest" ; } }
\ No newline at end of file
@@ -0,0 +1,484 @@
+/* Copyright 2020 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+/// Represents a single experiment.
+/// An experiment consists of multiple "sessions". Typically, in each session
+/// a model is trained for a given set of hyperparameter values. In each session
+/// a training program may generate one or more series of real numbers--each
+/// containing the evaluation of some metric on the model at different training
+/// steps.
+///
+/// Note that Sessions can consist of multiple Tensorboard "runs", since in
+/// a distributed Tensorflow deployment, training can be accomplished using
+/// several cooporating processes, each one emitting Summary data to a different
+/// log directory or run. For example, in a single session one process could
+/// periodically compute the loss on the validation set, and another could
+/// compute the loss on the training set.
+/// NEXT_TAG: 7
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Experiment {
+    /// -- Experiments are scoped by a global name.
+    /// Currently, Tensorboard supports displaying data for a single experiment.
+    #[prost(string, tag="6")]
+    pub name: ::prost::alloc::string::String,
+    /// A description. May contain markdown.
+    #[prost(string, tag="1")]
+    pub description: ::prost::alloc::string::String,
+    /// An id for the owning user or group.
+    #[prost(string, tag="2")]
+    pub user: ::prost::alloc::string::String,
+    /// The time the experiment was created. In seconds since the UNIX epoch.
+    #[prost(double, tag="3")]
+    pub time_created_secs: f64,
+    /// Information about each hyperparameter used in the experiment.
+    #[prost(message, repeated, tag="4")]
+    pub hparam_infos: ::prost::alloc::vec::Vec<HParamInfo>,
+    /// Information about each metric used in the experiment.
+    #[prost(message, repeated, tag="5")]
+    pub metric_infos: ::prost::alloc::vec::Vec<MetricInfo>,
+}
+/// NEXT_TAG: 7
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HParamInfo {
+    /// An id for the hyperparameter.
+    #[prost(string, tag="1")]
+    pub name: ::prost::alloc::string::String,
+    /// A string used to display the hyperparameter in the UI. If empty, the UI
+    /// will display the 'name' field.
+    #[prost(string, tag="2")]
+    pub display_name: ::prost::alloc::string::String,
+    /// A description. May contain markdown.
+    #[prost(string, tag="3")]
+    pub description: ::prost::alloc::string::String,
+    /// The data type of this hyperparameter.
+    #[prost(enumeration="DataType", tag="4")]
+    pub r#type: i32,
+    /// Specifies the set of values this hyperparameter can hold. The UI assumes
+    /// every instance of this hyperparameter will hold a value from this set. It
+    /// is used by the UI to allow filtering so that only session groups (see
+    /// below) whose associated hyperparameter value "passes" the filter are
+    /// displayed. If this is not populated, the domain is assumed to be the
+    /// entire domain of the type of the hyperparameter.
+    #[prost(oneof="h_param_info::Domain", tags="5, 6")]
+    pub domain: ::core::option::Option<h_param_info::Domain>,
+}
+/// Nested message and enum types in `HParamInfo`.
+pub mod h_param_info {
+    /// Specifies the set of values this hyperparameter can hold. The UI assumes
+    /// every instance of this hyperparameter will hold a value from this set. It
+    /// is used by the UI to allow filtering so that only session groups (see
+    /// below) whose associated hyperparameter value "passes" the filter are
+    /// displayed. If this is not populated, the domain is assumed to be the
+    /// entire domain of the type of the hyperparameter.
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Domain {
+        /// A discrete set of the values this hyperparameter can hold.
+        #[prost(message, tag="5")]
+        DomainDiscrete(::prost_types::ListValue),
+        /// Numeric data type only. The (real) interval from which values of this
+        /// hyperparameter are taken.
+        #[prost(message, tag="6")]
+        DomainInterval(super::Interval),
+    }
+}
+/// Represents the closed interval [min_value, max_value] of the real line.
+/// NEXT_TAG: 3
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Interval {
+    #[prost(double, tag="1")]
+    pub min_value: f64,
+    #[prost(double, tag="2")]
+    pub max_value: f64,
+}
+/// NEXT_TAG: 3
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MetricName {
+    /// An identifier for a metric. A metric is a real-valued function of the
+    /// model. The UI can plot metrics for a session evaluated on the model at
+    /// different training steps.
+    ///
+    /// We identify a metric by a (group, tag) pair of strings. The UI treats
+    /// both of these as opaque strings. The only requirement is that the pair
+    /// uniquely identifies a metric in the experiment.
+    ///
+    /// We use a pair so the UI could allow the user to group metrics for a
+    /// single session by either group or tag to be displayed in the same chart.
+    /// For instance, one can set the metric group to correspond to the dataset
+    /// on which the model was evaluated, and the UI can then display different
+    /// metrics describing the same underlying computation and using different
+    /// datasets, on the same chart.
+    ///
+    /// When exporting summaries from Tensorflow, in a typical setup, a
+    /// training session exports evaluations of metrics at different training steps
+    /// as Scalar-plugin summaries--each having a run of the form
+    /// "<session_base_log_dir>/<sub_dir>", and some associated tag. The same
+    /// metric for different sessions would use the same sub_dir and tag, but
+    /// would have a different session_base_log_dir. For example, a session
+    /// computing two metrics: model loss on the validation set and model loss on
+    /// the training set, can export these as scalar summaries with the tag "loss"
+    /// and runs session_base_log_dir/validation and session_base_log_dir/training,
+    /// respectively. In this setup, the 'group' field can be populated with
+    /// the "sub_dir" associated with the metric, and the 'tag' field can be
+    /// populated with the tag: "loss".
+    #[prost(string, tag="1")]
+    pub group: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub tag: ::prost::alloc::string::String,
+}
+/// NEXT_TAG: 6
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MetricInfo {
+    #[prost(message, optional, tag="1")]
+    pub name: ::core::option::Option<MetricName>,
+    /// A string used to display the metric in the UI. If empty, the UI
+    /// will display the 'name' field.
+    #[prost(string, tag="3")]
+    pub display_name: ::prost::alloc::string::String,
+    /// A description. May contain markdown.
+    #[prost(string, tag="4")]
+    pub description: ::prost::alloc::string::String,
+    /// The dataset type (validation, training) on which the metric is computed.
+    #[prost(enumeration="DatasetType", tag="5")]
+    pub dataset_type: i32,
+}
+/// In some experiments, the user trains a model with the same set of
+/// hyperparameters multiple times to get the distribution of metric
+/// evaluations, when the computation (such as the training algorithm, or metric
+/// evaluation) is non-deterministic. To make the UI aware of this, sessions
+/// are partitioned into groups: each group consists of all training sessions
+/// which share the same values for the hyperparameters. In experiments with no
+/// repeated executions, each group consists of exactly one session.
+/// NEXT_TAG: 6
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionGroup {
+    #[prost(string, tag="1")]
+    pub name: ::prost::alloc::string::String,
+    /// Stores the hyperparameters for sessions within this group as a mapping
+    /// from the hyperparameter name to its value.
+    #[prost(map="string, message", tag="2")]
+    pub hparams: ::std::collections::HashMap<::prost::alloc::string::String, ::prost_types::Value>,
+    /// A list of pairs (metric, value)--one for each metric in the experiment.
+    /// The value denotes the evaluation of the corresponding metric on
+    /// the model aggregated across the sessions in this group. The exact method
+    /// of aggregation is specified in the comments of ListSessionGroupsRequest.
+    /// Unfortunately, we can't store these as a map, since proto maps can't have
+    /// message keys.
+    #[prost(message, repeated, tag="3")]
+    pub metric_values: ::prost::alloc::vec::Vec<MetricValue>,
+    /// The sessions belonging to this group.
+    #[prost(message, repeated, tag="4")]
+    pub sessions: ::prost::alloc::vec::Vec<Session>,
+    /// An optional link to a web page monitoring the session group.
+    #[prost(string, tag="5")]
+    pub monitor_url: ::prost::alloc::string::String,
+}
+/// NEXT_TAG: 5
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MetricValue {
+    #[prost(message, optional, tag="1")]
+    pub name: ::core::option::Option<MetricName>,
+    #[prost(double, tag="2")]
+    pub value: f64,
+    /// The training step at which this value is computed.
+    #[prost(int32, tag="3")]
+    pub training_step: i32,
+    /// The wall time in seconds since UNIX epoch at which this value is computed.
+    #[prost(double, tag="4")]
+    pub wall_time_secs: f64,
+}
+/// NEXT_TAG: 8
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Session {
+    /// An id for the session. Unique within an experiment (not just the group).
+    #[prost(string, tag="1")]
+    pub name: ::prost::alloc::string::String,
+    /// In seconds since the UNIX epoch.
+    #[prost(double, tag="2")]
+    pub start_time_secs: f64,
+    /// In seconds since the UNIX epoch.
+    /// May be 0 if unavailable or the session has not finished yet.
+    #[prost(double, tag="3")]
+    pub end_time_secs: f64,
+    /// May be STATUS_UNKNOWN if unavailable.
+    #[prost(enumeration="Status", tag="4")]
+    pub status: i32,
+    /// A URI for a resource that will allow the user to reconstruct the model for
+    /// this session. E.g., in Tensorflow this could point to a directory where the
+    /// checkpoints are stored. Currently, this is treated opaquely by the UI
+    /// and only displayed to the user as it is passed here.
+    #[prost(string, tag="5")]
+    pub model_uri: ::prost::alloc::string::String,
+    /// Stores each metric evaluation on the model at the current training step.
+    /// Unfortunately, we can't store these as a map, since proto maps can't have
+    /// message keys.
+    #[prost(message, repeated, tag="6")]
+    pub metric_values: ::prost::alloc::vec::Vec<MetricValue>,
+    /// An optional link to a web page monitoring the session.
+    #[prost(string, tag="7")]
+    pub monitor_url: ::prost::alloc::string::String,
+}
+/// Parameters for a GetExperiment API call.
+/// Each experiment is scoped by a unique global id.
+/// NEXT_TAG: 2
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetExperimentRequest {
+    /// REQUIRED
+    #[prost(string, tag="1")]
+    pub experiment_name: ::prost::alloc::string::String,
+}
+/// Parameters for a ListSessionGroups API call.
+/// Computes a list of the current session groups allowing for filtering and
+/// sorting by metrics and hyperparameter values. Returns a "slice" of
+/// that list specified by start_index and slice_size.
+/// NEXT_TAG: 8
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListSessionGroupsRequest {
+    #[prost(string, tag="6")]
+    pub experiment_name: ::prost::alloc::string::String,
+    /// Filters the set of sessions (from which the session groups are formed) to
+    /// contain only these sessions whose status is contained in
+    /// 'allowed_statuses'.
+    #[prost(enumeration="Status", repeated, tag="7")]
+    pub allowed_statuses: ::prost::alloc::vec::Vec<i32>,
+    /// A list of ColParams messages--one for each "column" of a session group. A
+    /// session group column contains either a metric evaluated at the current
+    /// reported computation step or a hyperparameter value. In addition to
+    /// 'regular' values, a column may take on a special 'missing-value' which
+    /// denotes that the hyperparameter or metric is not available
+    /// for the session group (for example, if the metric is not used in the
+    /// group).
+    ///
+    /// The ColParams messages in the repeated field below configure filtering and
+    /// sorting of the resulting collection of session groups. See the comments of
+    /// the fields in the ColParam message below for more details.
+    #[prost(message, repeated, tag="1")]
+    pub col_params: ::prost::alloc::vec::Vec<ColParams>,
+    /// Fields controlling how to aggregate metrics across sessions within a
+    /// session group.
+    /// If aggregation_type is AGGREGATION_AVG, each metric value of the
+    /// session group is the average of the values of the metric across the
+    /// sessions.
+    /// Otherwise, the session group metric values are taken directly from a
+    /// "representative" session in the group, selected as a session for which
+    /// primary_metric takes on its minimum, maximum, or median value, as
+    /// specified by the choice of aggregation_type (for median, if the number of
+    /// sessions in the group is even, a session with a lower "middle" value is
+    /// chosen as the representative session).
+    #[prost(enumeration="AggregationType", tag="2")]
+    pub aggregation_type: i32,
+    /// See comment for 'aggregation_type' above.
+    #[prost(message, optional, tag="3")]
+    pub aggregation_metric: ::core::option::Option<MetricName>,
+    /// The next two parameters determine the "slice" of the full list of
+    /// session groups--sorted and filtered by the parameters above--to return.
+    /// The 0-based index of the first session group to return.
+    #[prost(int32, tag="4")]
+    pub start_index: i32,
+    /// The number of session groups to return starting at the session group
+    /// indexed by 'start_index'. The actual number of session groups returned
+    /// is min{slice_size, total_size - start_index}, where
+    /// total_size is the number of session groups in the full list
+    /// sorted and filtered by the parameters above (if start_index > total_size
+    /// no session groups are returned).
+    #[prost(int32, tag="5")]
+    pub slice_size: i32,
+}
+/// Defines parmeters for a ListSessionGroupsRequest for a specific column.
+/// See the comment for "ListSessionGroupsRequest" above for more details.
+/// NEXT_TAG: 9
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ColParams {
+    /// Sorting.
+    /// The final order of session groups in the response is defined by the sub
+    /// collection of ColParams messages (out of the
+    /// ListSessionGroupsRequest.col_params repeated field) whose 'order' field
+    /// (below) is not ORDER_UNSPECIFIED. In each of the messages in this
+    /// sub-collection, the next two fields specify the ordering of the values
+    /// and missing_values in the associated column of the session group. The
+    /// order of the ColParams messages themselves within the sub-collection
+    /// determines the "significance" of the associated column as a sorting key:
+    /// with the first being the primary sorting key, the second being the
+    /// secondary sorting key, etc.
+    /// Note: The 'session group name' is added as a least significant sorting
+    /// key to the keys defined by the user, so the order in the response is always
+    /// deterministic.
+    #[prost(enumeration="SortOrder", tag="3")]
+    pub order: i32,
+    /// This field is ignored if order is ORDER_UNSPECIFIED.
+    /// Otherwise, if true, missing values are ordered before every other value in
+    /// the column; if false they are ordered after every other value in the
+    /// column.
+    #[prost(bool, tag="4")]
+    pub missing_values_first: bool,
+    /// Specifies whether to exclude session groups whose column value is missing
+    /// from the response.
+    #[prost(bool, tag="8")]
+    pub exclude_missing_values: bool,
+    #[prost(oneof="col_params::Name", tags="1, 2")]
+    pub name: ::core::option::Option<col_params::Name>,
+    /// Filtering.
+    /// The 'filter' oneof specifies a subset of the domain of the values a column
+    /// may take. Only session groups with each of their column values belonging
+    /// to this subset are included in the response. If this field is not
+    /// specified, the subset is taken to be the entire column domain.
+    #[prost(oneof="col_params::Filter", tags="5, 6, 7")]
+    pub filter: ::core::option::Option<col_params::Filter>,
+}
+/// Nested message and enum types in `ColParams`.
+pub mod col_params {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Name {
+        #[prost(message, tag="1")]
+        Metric(super::MetricName),
+        #[prost(string, tag="2")]
+        Hparam(::prost::alloc::string::String),
+    }
+    /// Filtering.
+    /// The 'filter' oneof specifies a subset of the domain of the values a column
+    /// may take. Only session groups with each of their column values belonging
+    /// to this subset are included in the response. If this field is not
+    /// specified, the subset is taken to be the entire column domain.
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Filter {
+        /// Only valid for string-valued hyperparameter columns. The subset is
+        /// the set of all strings matching the regular expression stored
+        /// in 'regexp' as a partial match (use '^<regexp>$' to have a full
+        /// match against regexp).
+        #[prost(string, tag="5")]
+        FilterRegexp(::prost::alloc::string::String),
+        /// Only valid for numeric-valued columns. The subset is the given interval.
+        #[prost(message, tag="6")]
+        FilterInterval(super::Interval),
+        /// Valid for all data types. The subset is defined explicitly.
+        #[prost(message, tag="7")]
+        FilterDiscrete(::prost_types::ListValue),
+    }
+}
+/// See ListSessionGroups in http_api.md.
+/// NEXT_TAG: 4
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListSessionGroupsResponse {
+    #[prost(message, repeated, tag="1")]
+    pub session_groups: ::prost::alloc::vec::Vec<SessionGroup>,
+    /// Denotes the total number of session groups in the full filtered list.
+    /// (Recall that this response may only be a slice).
+    /// It is used by the UI to calculate total number of pages and can be
+    /// set here to -1 to mean "unknown".
+    #[prost(int32, tag="3")]
+    pub total_size: i32,
+}
+/// See ListMetricEvalsRequest in http_api.md.
+/// NEXT_TAG: 4
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListMetricEvalsRequest {
+    #[prost(string, tag="3")]
+    pub experiment_name: ::prost::alloc::string::String,
+    #[prost(string, tag="1")]
+    pub session_name: ::prost::alloc::string::String,
+    #[prost(message, optional, tag="2")]
+    pub metric_name: ::core::option::Option<MetricName>,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum DataType {
+    Unset = 0,
+    String = 1,
+    Bool = 2,
+    Float64 = 3,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum DatasetType {
+    DatasetUnknown = 0,
+    DatasetTraining = 1,
+    DatasetValidation = 2,
+}
+/// Represents the status of a Session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Status {
+    Unknown = 0,
+    Success = 1,
+    Failure = 2,
+    Running = 3,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum SortOrder {
+    OrderUnspecified = 0,
+    OrderAsc = 1,
+    OrderDesc = 2,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum AggregationType {
+    AggregationUnset = 0,
+    AggregationAvg = 1,
+    AggregationMedian = 2,
+    AggregationMin = 3,
+    AggregationMax = 4,
+}
+/// HParam summaries created by `tensorboard.plugins.hparams.summary`
+/// module will include `SummaryMetadata` whose `plugin_data` field has
+/// as `content` a serialized HParamsPluginData message.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HParamsPluginData {
+    /// The version of the plugin data schema.
+    #[prost(int32, tag="1")]
+    pub version: i32,
+    #[prost(oneof="h_params_plugin_data::Data", tags="2, 3, 4")]
+    pub data: ::core::option::Option<h_params_plugin_data::Data>,
+}
+/// Nested message and enum types in `HParamsPluginData`.
+pub mod h_params_plugin_data {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Data {
+        #[prost(message, tag="2")]
+        Experiment(super::Experiment),
+        #[prost(message, tag="3")]
+        SessionStartInfo(super::SessionStartInfo),
+        #[prost(message, tag="4")]
+        SessionEndInfo(super::SessionEndInfo),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionStartInfo {
+    /// A map describing the hyperparameter values for the session.
+    /// Maps each hyperparameter name to its value.
+    /// Currently only scalars are supported.
+    #[prost(map="string, message", tag="1")]
+    pub hparams: ::std::collections::HashMap<::prost::alloc::string::String, ::prost_types::Value>,
+    /// A URI for where checkpoints are saved.
+    #[prost(string, tag="2")]
+    pub model_uri: ::prost::alloc::string::String,
+    /// An optional URL to a website monitoring the session.
+    #[prost(string, tag="3")]
+    pub monitor_url: ::prost::alloc::string::String,
+    /// The name of the session group containing this session. If empty, the
+    /// group name is taken to be the session id (so this session is the only
+    /// member of its group).
+    #[prost(string, tag="4")]
+    pub group_name: ::prost::alloc::string::String,
+    /// The time the session started in seconds since epoch.
+    #[prost(double, tag="5")]
+    pub start_time_secs: f64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionEndInfo {
+    #[prost(enumeration="Status", tag="1")]
+    pub status: i32,
+    /// The time the session ended in seconds since epoch.
+    #[prost(double, tag="2")]
+    pub end_time_secs: f64,
+}
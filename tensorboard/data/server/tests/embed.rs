@@ -0,0 +1,158 @@
+/* Copyright 2021 The TensorFlow Authors. All Rights Reserved.
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+==============================================================================*/
+
+//! Integration tests for the embedding API, using only the public interface of the crate.
+
+use std::fs::{self, File, OpenOptions};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use rustboard_core::commit::Commit;
+use rustboard_core::embed::{Loader, LoaderOptions};
+use rustboard_core::run::SampleSizes;
+use rustboard_core::types::{Run, Step, Tag, WallTime};
+use rustboard_core::writer::SummaryWriteExt;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Appends scalar points at `steps` to the `loss` time series of the event file for `run`,
+/// creating it if needed. Each point's value is half its step.
+fn write_loss(logdir: &Path, run: &str, steps: std::ops::Range<i64>) -> std::io::Result<()> {
+    let dir = logdir.join(run);
+    fs::create_dir_all(&dir)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join("events.out.tfevents.123"))?;
+    for step in steps {
+        let wall_time = WallTime::new(1000.0 + step as f64).unwrap();
+        file.write_scalar(&loss(), Step(step), wall_time, step as f32 * 0.5)?;
+    }
+    file.sync_all()
+}
+
+/// Writes the fixture log directory: runs `train` and `eval`, each with a `loss` time series.
+fn write_fixture(logdir: &Path) -> std::io::Result<()> {
+    write_loss(logdir, "train", 0..10)?;
+    write_loss(logdir, "eval", 0..5)?;
+    // Not an event file; should be ignored.
+    File::create(logdir.join("README"))?;
+    Ok(())
+}
+
+fn loss() -> Tag {
+    Tag("loss".to_string())
+}
+
+fn run(name: &str) -> Run {
+    Run(name.to_string())
+}
+
+/// Gets the steps of the `loss` points in the given run, if any.
+fn loss_steps(commit: &Commit, name: &str) -> Option<Vec<i64>> {
+    let points = commit.read_scalars(&run(name), &loss())?;
+    Some(points.into_iter().map(|(step, _, _)| step.0).collect())
+}
+
+#[test]
+fn test_load_once() -> TestResult {
+    let logdir = tempfile::tempdir()?;
+    write_fixture(logdir.path())?;
+
+    let mut loader = Loader::open(logdir.path(), LoaderOptions::default());
+    let commit = loader.commit();
+    assert_eq!(commit.run_names(), Vec::<Run>::new());
+    assert_eq!(loader.load_once(), 15);
+
+    assert_eq!(commit.run_names(), vec![run("eval"), run("train")]);
+    assert_eq!(commit.scalar_tags(&run("train")), Some(vec![loss()]));
+    let points = commit.read_scalars(&run("eval"), &loss()).unwrap();
+    assert_eq!(
+        points,
+        (0..5)
+            .map(|i| (
+                Step(i),
+                WallTime::new(1000.0 + i as f64).unwrap(),
+                i as f64 * 0.5
+            ))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(loss_steps(&commit, "train"), Some((0..10).collect()));
+    assert_eq!(
+        commit.read_scalars(&run("train"), &Tag("xent".into())),
+        None
+    );
+
+    // A second load picks up only new data.
+    write_loss(logdir.path(), "eval", 5..7)?;
+    assert_eq!(loader.load_once(), 2);
+    assert_eq!(loss_steps(&commit, "eval"), Some((0..7).collect()));
+    Ok(())
+}
+
+#[test]
+fn test_sample_sizes() -> TestResult {
+    let logdir = tempfile::tempdir()?;
+    write_fixture(logdir.path())?;
+
+    let options = LoaderOptions {
+        sample_sizes: SampleSizes {
+            scalars: 3,
+            ..Default::default()
+        },
+        reload_threads: 1,
+        ..Default::default()
+    };
+    let mut loader = Loader::open(logdir.path(), options);
+    loader.load_once();
+    let steps = loss_steps(&loader.commit(), "train").unwrap();
+    assert_eq!(steps.len(), 3, "{:?}", steps);
+    // The latest point is always kept.
+    assert_eq!(steps.last(), Some(&9));
+    Ok(())
+}
+
+#[test]
+fn test_spawn_polling() -> TestResult {
+    let logdir = tempfile::tempdir()?;
+    write_fixture(logdir.path())?;
+
+    let loader = Loader::open(logdir.path(), LoaderOptions::default());
+    let handle = loader.spawn_polling(Duration::from_millis(10));
+    let commit = handle.commit();
+
+    // Waits until the `train` run has the given steps, or panics after a while.
+    let wait_for = |want: Vec<i64>| {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        loop {
+            let steps = loss_steps(&commit, "train");
+            if steps.as_ref() == Some(&want) {
+                return;
+            }
+            assert!(Instant::now() < deadline, "timed out; last saw {:?}", steps);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    };
+    wait_for((0..10).collect());
+    write_loss(logdir.path(), "train", 10..12)?;
+    wait_for((0..12).collect());
+    handle.stop();
+
+    // No more loads after stopping.
+    write_loss(logdir.path(), "train", 12..13)?;
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(loss_steps(&commit, "train"), Some((0..12).collect()));
+    Ok(())
+}
@@ -51,6 +51,10 @@ pub struct TfRecordReader<R> {
     data_plus_footer: Vec<u8>,
     /// Underlying reader.
     reader: R,
+    /// Maximum data length of a record, in bytes. See [`Self::max_record_len`].
+    max_record_len: Option<u64>,
+    /// Offset of the end of the last complete record read, in bytes. See [`Self::offset`].
+    offset: u64,
 }
 
 /// A TFRecord with a data buffer and expected checksum. The checksum may or may not match the
@@ -133,6 +137,10 @@ pub enum ReadRecordError {
     /// recovery codepath has been implemented, so reading must abort.
     #[error("record too large to fit in memory ({0} bytes)")]
     TooLarge(u64),
+    /// Record is longer than the limit set with [`TfRecordReader::max_record_len`]. Reading must
+    /// abort, since the record might be a corrupted length that happens to pass its checksum.
+    #[error("record of {length} bytes exceeds maximum record length of {limit} bytes")]
+    ExceedsLimit { length: u64, limit: u64 },
     /// Underlying I/O error. May be retryable if the underlying error is.
     #[error(transparent)]
     Io(#[from] io::Error),
@@ -171,9 +179,42 @@ impl<R: Read> TfRecordReader<R> {
             reader,
             header: Vec::with_capacity(HEADER_LENGTH),
             data_plus_footer: Vec::new(),
+            max_record_len: None,
+            offset: 0,
         }
     }
 
+    /// Sets the maximum data length of a record, in bytes. Reading a longer record fails with
+    /// [`ReadRecordError::ExceedsLimit`] before any of its data is read or allocated. `None` (the
+    /// default) means no limit.
+    pub fn max_record_len(&mut self, bytes: Option<u64>) {
+        self.max_record_len = bytes;
+    }
+
+    /// Gets the number of bytes in all records read so far. This is the offset of the start of
+    /// the next record, not counting any part of it that has been read while truncated.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Skips to the given offset in the stream, by reading and discarding that many bytes, so
+    /// that the next record read is the one at that offset. This can be used to resume reading a
+    /// file at the [`Self::offset`] of an earlier reader. Should be called before reading any
+    /// records.
+    ///
+    /// Fails with [`io::ErrorKind::UnexpectedEof`] if the stream is shorter than `offset`.
+    pub fn resume_at(&mut self, offset: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.reader).take(offset), &mut io::sink())?;
+        if skipped < offset {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("stream ended after {} of {} bytes", skipped, offset),
+            ));
+        }
+        self.offset = offset;
+        Ok(())
+    }
+
     /// Consumes this `TfRecordReader<R>`, returning the underlying reader `R`.
     pub fn into_inner(self) -> R {
         self.reader
@@ -257,6 +298,11 @@ impl<R: Read> TfRecordReader<R> {
             }
 
             let length = LittleEndian::read_u64(length_buf);
+            if let Some(limit) = self.max_record_len {
+                if length > limit {
+                    return Err(ReadRecordError::ExceedsLimit { length, limit });
+                }
+            }
             let data_plus_footer_length_u64 = length + (FOOTER_LENGTH as u64);
             let data_plus_footer_length = data_plus_footer_length_u64 as usize;
             if data_plus_footer_length as u64 != data_plus_footer_length_u64 {
@@ -276,12 +322,13 @@ impl<R: Read> TfRecordReader<R> {
         let data = std::mem::take(&mut self.data_plus_footer);
         let data_crc = MaskedCrc(LittleEndian::read_u32(&data_crc_buf));
         self.header.clear(); // reset; caller may use this again
+        self.offset += (HEADER_LENGTH + data.len() + FOOTER_LENGTH) as u64;
         Ok(TfRecord { data, data_crc })
     }
 }
 
 /// Fills `buf`'s remaining capacity from `reader`, or fails with `Truncated` if the reader is dry.
-fn read_remaining<R: Read>(reader: R, buf: &mut Vec<u8>) -> Result<(), ReadRecordError> {
+pub(crate) fn read_remaining<R: Read>(reader: R, buf: &mut Vec<u8>) -> Result<(), ReadRecordError> {
     let want = buf.capacity() - buf.len();
     reader.take(want as u64).read_to_end(buf)?;
     if buf.len() < buf.capacity() {
@@ -384,6 +431,61 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_offset_and_resume() {
+        let mut file = Vec::new();
+        for data in &[&b"abc"[..], b"defgh", b"ijkl"] {
+            TfRecord::from_data(data.to_vec()).write(&mut file).unwrap();
+        }
+
+        let mut reader = TfRecordReader::new(Cursor::new(file[..40].to_vec()));
+        assert_eq!(reader.offset(), 0);
+        reader.read_record().unwrap();
+        assert_eq!(reader.offset(), 19);
+        reader.read_record().unwrap();
+        assert_eq!(reader.offset(), 40);
+        // A truncated record doesn't count.
+        let mut reader = TfRecordReader::new(Cursor::new(file[..45].to_vec()));
+        reader.read_record().unwrap();
+        reader.read_record().unwrap();
+        assert!(matches!(
+            reader.read_record(),
+            Err(ReadRecordError::Truncated)
+        ));
+        assert_eq!(reader.offset(), 40);
+
+        let mut resumed = TfRecordReader::new(Cursor::new(file.clone()));
+        resumed.resume_at(40).unwrap();
+        assert_eq!(resumed.read_record().unwrap().data, b"ijkl".to_vec());
+        assert_eq!(resumed.offset(), file.len() as u64);
+
+        let mut short = TfRecordReader::new(Cursor::new(file[..30].to_vec()));
+        let err = short.resume_at(40).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_max_record_len() {
+        let mut file = Vec::new();
+        TfRecord::from_data(b"small".to_vec())
+            .write(&mut file)
+            .unwrap();
+        TfRecord::from_data(b"much too large".to_vec())
+            .write(&mut file)
+            .unwrap();
+
+        let mut reader = TfRecordReader::new(Cursor::new(file));
+        reader.max_record_len(Some(5));
+        assert_eq!(reader.read_record().unwrap().data, b"small".to_vec());
+        match reader.read_record() {
+            Err(ReadRecordError::ExceedsLimit {
+                length: 14,
+                limit: 5,
+            }) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
     #[test]
     fn test_data_crc_mismatch() {
         let mut file = Vec::new();
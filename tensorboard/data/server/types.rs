@@ -16,6 +16,8 @@ limitations under the License.
 //! Core simple types.
 
 use std::borrow::Borrow;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// A step associated with a record, strictly increasing over time within a record stream.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
@@ -27,6 +29,75 @@ impl From<Step> for i64 {
     }
 }
 
+/// An inclusive range of steps, possibly unbounded on either side.
+///
+/// A range with both bounds present is valid only if `min <= max`; see [`StepRange::new`].
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct StepRange {
+    /// Least step in the range, or `None` if the range has no lower bound.
+    pub min: Option<Step>,
+    /// Greatest step in the range, or `None` if the range has no upper bound.
+    pub max: Option<Step>,
+}
+
+impl StepRange {
+    /// Creates a step range, or returns `None` if `min > max`.
+    pub fn new(min: Option<Step>, max: Option<Step>) -> Option<Self> {
+        let range = StepRange { min, max };
+        if range.is_valid() {
+            Some(range)
+        } else {
+            None
+        }
+    }
+
+    /// Creates a step range that contains every step. Same as `StepRange::default()`.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Checks whether `min <= max`, when both are present. Ranges built by setting fields
+    /// directly may be invalid; an invalid range contains no steps.
+    pub fn is_valid(&self) -> bool {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => min <= max,
+            _ => true,
+        }
+    }
+
+    /// Checks whether `step` lies within this range.
+    pub fn contains(&self, step: Step) -> bool {
+        self.min.iter().all(|&min| min <= step) && self.max.iter().all(|&max| step <= max)
+    }
+
+    /// Computes the range of steps that lie in both `self` and `other`, or `None` if there are
+    /// no such steps.
+    pub fn intersect(&self, other: &StepRange) -> Option<StepRange> {
+        let min = self.min.max(other.min);
+        let max = match (self.max, other.max) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        StepRange::new(min, max)
+    }
+}
+
+/// A limit on the number of points to return for a time series.
+///
+/// Downsampling always keeps the first and last points of a series and picks the rest uniformly
+/// at random; see [`crate::downsample::downsample`] for details.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Downsample {
+    pub num_points: usize,
+}
+
+impl Downsample {
+    /// Downsamples `xs` in place to at most `self.num_points` elements.
+    pub fn apply<T>(self, xs: &mut Vec<T>) {
+        crate::downsample::downsample(xs, self.num_points);
+    }
+}
+
 /// The wall time of a TensorBoard event.
 ///
 /// Wall times represent floating-point seconds since Unix epoch. They must be finite and non-NaN.
@@ -44,6 +115,132 @@ impl WallTime {
             None
         }
     }
+
+    /// Converts a system time to a wall time, with nanosecond precision to the extent that an
+    /// `f64` can represent it. Times before the epoch give negative wall times.
+    ///
+    /// Returns `None` only if the system time is too far from the epoch to be represented, which
+    /// doesn't happen on any common platform.
+    pub fn from_system_time(time: SystemTime) -> Option<Self> {
+        let secs = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs_f64(),
+            Err(e) => -e.duration().as_secs_f64(),
+        };
+        WallTime::new(secs)
+    }
+
+    /// Converts this wall time to a system time, rounding to the nearest nanosecond.
+    ///
+    /// Returns `None` if this wall time is too far from the epoch for the platform's
+    /// `SystemTime`: e.g., more than about 584 billion years either way on Unix.
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        let d = duration_from_secs(self.0.abs())?;
+        if self.0 >= 0.0 {
+            UNIX_EPOCH.checked_add(d)
+        } else {
+            UNIX_EPOCH.checked_sub(d)
+        }
+    }
+
+    /// Gets the time since the epoch as a [`Duration`], rounding to the nearest nanosecond.
+    ///
+    /// Returns `None` if this wall time is before the epoch, or too large for a `Duration`.
+    pub fn as_duration_since_epoch(self) -> Option<Duration> {
+        if self.0 < 0.0 {
+            return None;
+        }
+        duration_from_secs(self.0)
+    }
+
+    /// Gets the time elapsed from `earlier` to `self`, or `None` if `earlier` is later than
+    /// `self` or the difference is too large for a `Duration`.
+    pub fn checked_sub(self, earlier: WallTime) -> Option<Duration> {
+        let diff = self.0 - earlier.0;
+        if diff < 0.0 {
+            return None;
+        }
+        duration_from_secs(diff)
+    }
+
+    /// Gets the time elapsed from `earlier` to `self`, or zero if `earlier` is later than `self`.
+    /// Differences too large for a `Duration` saturate to the largest `Duration`.
+    pub fn saturating_sub(self, earlier: WallTime) -> Duration {
+        if self.0 <= earlier.0 {
+            return Duration::from_secs(0);
+        }
+        self.checked_sub(earlier)
+            .unwrap_or_else(|| Duration::new(u64::MAX, 999_999_999))
+    }
+
+    /// Adds a duration to this wall time. The result is as precise as an `f64` allows, and
+    /// saturates to the largest finite wall time rather than overflowing.
+    pub fn saturating_add(self, d: Duration) -> WallTime {
+        WallTime::new(self.0 + d.as_secs_f64()).unwrap_or(WallTime(f64::MAX))
+    }
+}
+
+/// Converts non-negative, finite seconds to a `Duration`, rounding to the nearest nanosecond.
+/// Returns `None` if the result doesn't fit.
+fn duration_from_secs(secs: f64) -> Option<Duration> {
+    // `u64::MAX as f64` rounds up to 2^64, so this also excludes values that would truncate to
+    // `u64::MAX` but then carry from the nanoseconds.
+    if !(0.0..u64::MAX as f64).contains(&secs) {
+        return None;
+    }
+    let whole = secs.trunc();
+    let nanos = ((secs - whole) * 1e9).round() as u32;
+    let whole = whole as u64;
+    if nanos >= 1_000_000_000 {
+        return Some(Duration::new(whole.checked_add(1)?, 0));
+    }
+    Some(Duration::new(whole, nanos))
+}
+
+/// Earliest and latest wall times that can be formatted in RFC 3339 (years 0000 through 9999).
+const MIN_RFC3339_SECS: f64 = -62_167_219_200.0;
+const MAX_RFC3339_SECS: f64 = 253_402_300_800.0;
+
+/// Formats as an RFC 3339 timestamp in UTC with millisecond precision, like
+/// `2021-01-23T04:56:07.890Z`, rounding down to the millisecond. Wall times outside years 0000
+/// through 9999 can't be written that way, and are formatted as seconds since the epoch instead.
+impl fmt::Display for WallTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !(MIN_RFC3339_SECS..MAX_RFC3339_SECS).contains(&self.0) {
+            return write!(f, "{}s", self.0);
+        }
+        let millis = (self.0 * 1000.0).floor() as i64;
+        let days = millis.div_euclid(86_400_000);
+        let ms_of_day = millis.rem_euclid(86_400_000);
+        let (year, month, day) = civil_from_days(days);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year,
+            month,
+            day,
+            ms_of_day / 3_600_000,
+            ms_of_day / 60_000 % 60,
+            ms_of_day / 1000 % 60,
+            ms_of_day % 1000,
+        )
+    }
+}
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian `(year, month, day)`.
+///
+/// This is the `civil_from_days` algorithm from Howard Hinnant's "`chrono`-Compatible Low-Level
+/// Date Algorithms": <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097); // [0, 146096]
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365; // [0, 399]
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100); // [0, 365]
+    let mp = (5 * doy + 2) / 153; // [0, 11]
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32; // [1, 31]
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32; // [1, 12]
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
 }
 
 // Wall times are totally ordered and have a total equivalence relation, since we guarantee that
@@ -63,6 +260,50 @@ impl From<WallTime> for f64 {
     }
 }
 
+/// An inclusive range of wall times, possibly unbounded on either side. The wall-time analog of
+/// [`StepRange`].
+///
+/// A range with both bounds present is valid only if `min <= max`; see [`WallTimeRange::new`].
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct WallTimeRange {
+    /// Earliest wall time in the range, or `None` if the range has no lower bound.
+    pub min: Option<WallTime>,
+    /// Latest wall time in the range, or `None` if the range has no upper bound.
+    pub max: Option<WallTime>,
+}
+
+impl WallTimeRange {
+    /// Creates a wall time range, or returns `None` if `min > max`.
+    pub fn new(min: Option<WallTime>, max: Option<WallTime>) -> Option<Self> {
+        let range = WallTimeRange { min, max };
+        if range.is_valid() {
+            Some(range)
+        } else {
+            None
+        }
+    }
+
+    /// Creates a wall time range that contains every wall time. Same as
+    /// `WallTimeRange::default()`.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Checks whether `min <= max`, when both are present. Ranges built by setting fields
+    /// directly may be invalid; an invalid range contains no wall times.
+    pub fn is_valid(&self) -> bool {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => min <= max,
+            _ => true,
+        }
+    }
+
+    /// Checks whether `wall_time` lies within this range.
+    pub fn contains(&self, wall_time: WallTime) -> bool {
+        self.min.iter().all(|&min| min <= wall_time) && self.max.iter().all(|&max| wall_time <= max)
+    }
+}
+
 /// The name of a time series within the context of a run.
 ///
 /// Tag names are valid Unicode text strings. They should be non-empty, though this type does not
@@ -93,6 +334,102 @@ impl Borrow<str> for Run {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_step_range_new() {
+        assert_eq!(
+            StepRange::new(Some(Step(1)), Some(Step(3))),
+            Some(StepRange {
+                min: Some(Step(1)),
+                max: Some(Step(3)),
+            })
+        );
+        assert!(StepRange::new(Some(Step(2)), Some(Step(2))).is_some());
+        assert_eq!(StepRange::new(Some(Step(3)), Some(Step(1))), None);
+        assert_eq!(StepRange::new(None, None), Some(StepRange::all()));
+        assert!(!StepRange {
+            min: Some(Step(3)),
+            max: Some(Step(1)),
+        }
+        .is_valid());
+    }
+
+    #[test]
+    fn test_step_range_contains() {
+        let range = StepRange::new(Some(Step(-2)), Some(Step(5))).unwrap();
+        assert!(!range.contains(Step(-3)));
+        assert!(range.contains(Step(-2)));
+        assert!(range.contains(Step(5)));
+        assert!(!range.contains(Step(6)));
+
+        let above = StepRange::new(Some(Step(0)), None).unwrap();
+        assert!(above.contains(Step(i64::MAX)));
+        assert!(!above.contains(Step(-1)));
+        let below = StepRange::new(None, Some(Step(0))).unwrap();
+        assert!(below.contains(Step(i64::MIN)));
+        assert!(!below.contains(Step(1)));
+        assert!(StepRange::all().contains(Step(i64::MIN)));
+
+        let invalid = StepRange {
+            min: Some(Step(3)),
+            max: Some(Step(1)),
+        };
+        assert!(!(0..5).any(|i| invalid.contains(Step(i))));
+    }
+
+    #[test]
+    fn test_wall_time_range() {
+        let wt = |t: f64| WallTime::new(t).unwrap();
+        let range = WallTimeRange::new(Some(wt(10.0)), Some(wt(20.5))).unwrap();
+        assert!(!range.contains(wt(9.999)));
+        assert!(range.contains(wt(10.0)));
+        assert!(range.contains(wt(20.5)));
+        assert!(!range.contains(wt(20.6)));
+        assert!(WallTimeRange::new(None, Some(wt(0.0)))
+            .unwrap()
+            .contains(wt(-1e9)));
+        assert!(WallTimeRange::all().contains(wt(f64::MAX)));
+        assert_eq!(WallTimeRange::new(Some(wt(2.0)), Some(wt(1.0))), None);
+        let invalid = WallTimeRange {
+            min: Some(wt(2.0)),
+            max: Some(wt(1.0)),
+        };
+        assert!(!invalid.is_valid());
+        assert!(!invalid.contains(wt(1.5)));
+    }
+
+    #[test]
+    fn test_step_range_intersect() {
+        let r = |min: Option<i64>, max: Option<i64>| {
+            StepRange::new(min.map(Step), max.map(Step)).unwrap()
+        };
+        assert_eq!(
+            r(Some(0), Some(10)).intersect(&r(Some(5), Some(20))),
+            Some(r(Some(5), Some(10)))
+        );
+        assert_eq!(
+            r(Some(0), None).intersect(&r(None, Some(7))),
+            Some(r(Some(0), Some(7)))
+        );
+        assert_eq!(
+            r(None, None).intersect(&r(Some(3), None)),
+            Some(r(Some(3), None))
+        );
+        assert_eq!(
+            r(Some(0), Some(4)).intersect(&r(Some(4), Some(8))),
+            Some(r(Some(4), Some(4)))
+        );
+        assert_eq!(r(Some(0), Some(4)).intersect(&r(Some(5), Some(8))), None);
+    }
+
+    #[test]
+    fn test_downsample_apply() {
+        let mut xs: Vec<i32> = (0..100).collect();
+        Downsample { num_points: 10 }.apply(&mut xs);
+        assert_eq!(xs.len(), 10);
+        assert_eq!(xs.first(), Some(&0));
+        assert_eq!(xs.last(), Some(&99));
+    }
+
     #[test]
     fn test_tag_hash_map_str_access() {
         use std::collections::HashMap;
@@ -138,4 +475,138 @@ mod tests {
         ];
         assert_eq!(actual, expected);
     }
+
+    /// Asserts that `actual` is within a nanosecond of `expected`, or within rounding error for
+    /// an `f64` of that size.
+    fn assert_close(actual: f64, expected: f64) {
+        let tolerance = 1e-9_f64.max(expected.abs() * f64::EPSILON);
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} != {} (within {})",
+            actual,
+            expected,
+            tolerance
+        );
+    }
+
+    /// Wall times to test round trips with: edge values, plus fixed-seed random values of many
+    /// magnitudes, with and without sub-microsecond fractions.
+    fn sample_wall_times() -> Vec<f64> {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(0);
+        let mut times = vec![
+            0.0,
+            -0.0,
+            1e-9,
+            1234.0000001234,
+            1_612_345_678.123_456,
+            -1234.567890123,
+            1e15,
+            -1e15,
+        ];
+        for _ in 0..10_000 {
+            let magnitude = rng.gen_range(-9, 16);
+            let t: f64 = rng.gen::<f64>() * 10f64.powi(magnitude);
+            times.push(if rng.gen() { t } else { -t });
+        }
+        times
+    }
+
+    #[test]
+    fn test_wall_time_system_time_round_trip() {
+        for t in sample_wall_times() {
+            let wt = WallTime::new(t).unwrap();
+            let st = wt
+                .to_system_time()
+                .unwrap_or_else(|| panic!("{} out of range", t));
+            let back = WallTime::from_system_time(st).unwrap();
+            assert_close(back.into(), t);
+            if t >= 0.0 {
+                let d = wt.as_duration_since_epoch().unwrap();
+                assert_eq!(UNIX_EPOCH + d, st);
+            } else {
+                assert_eq!(wt.as_duration_since_epoch(), None);
+            }
+        }
+        assert_eq!(
+            WallTime::new(0.0).unwrap().to_system_time(),
+            Some(UNIX_EPOCH)
+        );
+        assert_eq!(
+            WallTime::from_system_time(UNIX_EPOCH + Duration::new(1, 500_000_000)),
+            WallTime::new(1.5)
+        );
+        assert_eq!(
+            WallTime::new(1234.0000001234)
+                .unwrap()
+                .as_duration_since_epoch(),
+            Some(Duration::new(1234, 123))
+        );
+        // Rounds up to the next whole second without overflowing the nanoseconds.
+        assert_eq!(
+            WallTime::new(0.999_999_999_9)
+                .unwrap()
+                .as_duration_since_epoch(),
+            Some(Duration::from_secs(1))
+        );
+        let huge = WallTime::new(1e300).unwrap();
+        assert_eq!(huge.to_system_time(), None);
+        assert_eq!(huge.as_duration_since_epoch(), None);
+    }
+
+    #[test]
+    fn test_wall_time_arithmetic() {
+        let wt = |t| WallTime::new(t).unwrap();
+        assert_eq!(
+            wt(1235.25).checked_sub(wt(1234.0)),
+            Some(Duration::from_millis(1250))
+        );
+        assert_eq!(
+            wt(1234.0).checked_sub(wt(1234.0)),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(wt(1234.0).checked_sub(wt(1235.25)), None);
+        assert_eq!(wt(1e300).checked_sub(wt(0.0)), None);
+
+        assert_eq!(
+            wt(1235.25).saturating_sub(wt(1234.0)),
+            Duration::from_millis(1250)
+        );
+        assert_eq!(
+            wt(1234.0).saturating_sub(wt(1235.25)),
+            Duration::from_secs(0)
+        );
+        assert_eq!(
+            wt(1e300).saturating_sub(wt(-1e300)),
+            Duration::new(u64::MAX, 999_999_999)
+        );
+
+        assert_eq!(
+            wt(1234.0).saturating_add(Duration::from_millis(1250)),
+            wt(1235.25)
+        );
+        assert_eq!(
+            wt(f64::MAX).saturating_add(Duration::from_secs(1 << 60)),
+            wt(f64::MAX)
+        );
+        for t in sample_wall_times() {
+            let later = wt(t).saturating_add(Duration::from_secs(60));
+            assert_close(later.checked_sub(wt(t)).unwrap().as_secs_f64(), 60.0);
+        }
+    }
+
+    #[test]
+    fn test_wall_time_display() {
+        let fmt = |t| WallTime::new(t).unwrap().to_string();
+        assert_eq!(fmt(0.0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(fmt(1_611_375_367.890_5), "2021-01-23T04:16:07.890Z");
+        // Rounds down, even just under a whole second.
+        assert_eq!(fmt(1_611_375_367.999_9), "2021-01-23T04:16:07.999Z");
+        assert_eq!(fmt(951_782_400.0), "2000-02-29T00:00:00.000Z"); // leap day
+        assert_eq!(fmt(-0.5), "1969-12-31T23:59:59.500Z");
+        assert_eq!(fmt(-62_167_219_200.0), "0000-01-01T00:00:00.000Z");
+        assert_eq!(fmt(253_402_300_799.999), "9999-12-31T23:59:59.999Z");
+        assert_eq!(fmt(253_402_300_800.0), "253402300800s");
+        assert_eq!(fmt(-1e20), "-100000000000000000000s");
+    }
 }
@@ -13,12 +13,274 @@ See the License for the specific language governing permissions and
 limitations under the License.
 ==============================================================================*/
 
-//! Test helpers for writing event files.
+//! Helpers for writing event files.
 
-use std::io::Write;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::hparams::{self, ExperimentConfig, SessionEnd, SessionStart};
+use crate::logdir::{EVENT_FILE_BASENAME_INFIX, GZIP_EVENT_FILE_SUFFIX};
 use crate::proto::tensorboard as pb;
 use crate::types::{Step, Tag, WallTime};
+use pb::hparams::h_params_plugin_data::Data as HParamsData;
+
+/// File version string written as the first event of every event file.
+const FILE_VERSION: &str = "brain.Event:2";
+
+/// A writer for the event files of a single run, in a directory on local disk.
+///
+/// Each event file starts with a `file_version` event. Data written with the methods of
+/// [`SummaryWriteExt`] is buffered, and reaches disk when this writer is flushed or dropped.
+///
+/// By default, all events go to a single file. With [`Self::max_file_size`], the writer rolls
+/// over to a new file once the current file has grown past the limit. Files are named
+/// `events.out.tfevents.<timestamp>.<hostname>.<pid>`, where the timestamp strictly increases with
+/// each new file so that lexicographic order matches write order even when files are rotated more
+/// than once per second.
+///
+/// With [`Self::batch_scalars`], consecutive scalars for the same step are coalesced into a single
+/// event; see [`Self::write_scalar`].
+///
+/// Event files may be gzip-compressed; see [`Self::with_compression`].
+pub struct EventFileWriter {
+    /// Directory containing the event files.
+    dir: PathBuf,
+    /// Currently open event file, or `None` if the last file was finished due to rotation and the
+    /// next file has not yet been opened. A new file is opened on the next write.
+    file: Option<Sink>,
+    /// Compression for new event files.
+    compression: Compression,
+    /// Number of bytes written to the current file, including any still buffered. For compressed
+    /// files, this counts bytes before compression.
+    bytes_written: u64,
+    /// Timestamp (seconds since epoch) in the name of the most recently opened file.
+    last_timestamp: u64,
+    /// Maximum size of each event file in bytes, if any. See [`Self::max_file_size`].
+    max_file_size: Option<u64>,
+    /// Whether to coalesce scalars for the same step. See [`Self::batch_scalars`].
+    batch_scalars: bool,
+    /// Scalars for a single step that have been batched but not yet written.
+    pending: Option<PendingScalars>,
+}
+
+/// Compression applied to event files written by an [`EventFileWriter`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    /// Write plain TFRecord files.
+    None,
+    /// Write gzip-compressed TFRecord files, with names ending in [`GZIP_EVENT_FILE_SUFFIX`].
+    Gzip,
+}
+
+/// Output stream for a single event file.
+enum Sink {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Plain(f) => f.write(buf),
+            Sink::Gzip(f) => f.write(buf),
+        }
+    }
+
+    /// Flushes buffered data to disk. For gzip files, this performs a sync flush, so that all data
+    /// written so far can be decompressed even though the stream is not yet finished.
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Plain(f) => f.flush(),
+            Sink::Gzip(f) => f.flush(),
+        }
+    }
+}
+
+/// A batch of scalar values for a single step, to be written as one event.
+struct PendingScalars {
+    step: Step,
+    /// Wall time of the most recently batched value.
+    wall_time: WallTime,
+    values: Vec<pb::summary::Value>,
+}
+
+impl EventFileWriter {
+    /// Creates a writer for a run in the given directory, creating the directory if needed, and
+    /// opens the first event file.
+    pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        Self::with_compression(dir, Compression::None)
+    }
+
+    /// Creates a writer as with [`Self::new`], but with the given compression for all event files.
+    ///
+    /// For gzip-compressed files, flushing the writer performs a sync flush, so all events written
+    /// before the flush can be read even if the process dies before the file is finished. Readers
+    /// tailing a compressed file see new data only after each flush, rather than as soon as each
+    /// buffer fills, so prefer uncompressed files for runs that will be viewed while they are
+    /// being written.
+    pub fn with_compression<P: AsRef<Path>>(dir: P, compression: Compression) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let mut writer = EventFileWriter {
+            dir,
+            file: None,
+            compression,
+            bytes_written: 0,
+            last_timestamp: 0,
+            max_file_size: None,
+            batch_scalars: false,
+            pending: None,
+        };
+        writer.open_next_file()?;
+        Ok(writer)
+    }
+
+    /// Sets the size in bytes past which the writer rolls over to a new file.
+    ///
+    /// The size is checked whenever the writer is flushed, so files may exceed this size by up to
+    /// the amount of data written between flushes. Individual events are never split across
+    /// files.
+    pub fn max_file_size(&mut self, bytes: u64) {
+        self.max_file_size = Some(bytes);
+    }
+
+    /// Sets whether to coalesce consecutive scalars for the same step into a single event.
+    ///
+    /// Each event carries a few dozen bytes of framing and proto overhead, which dominates the
+    /// size of files written by loggers that record many scalars per step. With batching enabled,
+    /// scalars are held in memory until a scalar for a different step is written, any other event
+    /// is written, or the writer is flushed or dropped. Disabling batching writes out any pending
+    /// scalars immediately.
+    pub fn batch_scalars(&mut self, yes: bool) -> io::Result<()> {
+        self.batch_scalars = yes;
+        if !yes {
+            self.write_pending()?;
+        }
+        Ok(())
+    }
+
+    /// Writes a TF 1.x scalar event (`simple_value`), or adds it to the current batch if
+    /// [batching][Self::batch_scalars] is enabled.
+    ///
+    /// A batch is written as a single event whose summary has one value per scalar, in the order
+    /// they were written. All values in an event share its wall time, which is taken to be the wall
+    /// time of the last scalar in the batch.
+    pub fn write_scalar(
+        &mut self,
+        tag: &Tag,
+        step: Step,
+        wt: WallTime,
+        value: f32,
+    ) -> io::Result<()> {
+        if !self.batch_scalars {
+            return SummaryWriteExt::write_scalar(self, tag, step, wt, value);
+        }
+        if matches!(&self.pending, Some(p) if p.step != step) {
+            self.write_pending()?;
+        }
+        let pending = self.pending.get_or_insert_with(|| PendingScalars {
+            step,
+            wall_time: wt,
+            values: Vec::new(),
+        });
+        pending.wall_time = wt;
+        pending.values.push(pb::summary::Value {
+            tag: tag.0.clone(),
+            value: Some(pb::summary::value::Value::SimpleValue(value)),
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Writes out any batched scalars as a single event.
+    fn write_pending(&mut self) -> io::Result<()> {
+        let pending = match self.pending.take() {
+            None => return Ok(()),
+            Some(p) => p,
+        };
+        let event = pb::Event {
+            step: pending.step.0,
+            wall_time: pending.wall_time.into(),
+            what: Some(pb::event::What::Summary(pb::Summary {
+                value: pending.values,
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        self.write_event(&event)
+    }
+
+    /// Opens a new event file and writes its `file_version` event.
+    fn open_next_file(&mut self) -> io::Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let timestamp = now.as_secs().max(self.last_timestamp + 1);
+        let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+        let mut basename = format!(
+            "events.out.{}.{:010}.{}.{}",
+            EVENT_FILE_BASENAME_INFIX,
+            timestamp,
+            hostname,
+            std::process::id()
+        );
+        if self.compression == Compression::Gzip {
+            basename.push_str(GZIP_EVENT_FILE_SUFFIX);
+        }
+        let file = BufWriter::new(File::create(self.dir.join(basename))?);
+        self.file = Some(match self.compression {
+            Compression::None => Sink::Plain(file),
+            Compression::Gzip => Sink::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+        });
+        self.bytes_written = 0;
+        self.last_timestamp = timestamp;
+        let file_version = pb::Event {
+            wall_time: now.as_secs_f64(),
+            what: Some(pb::event::What::FileVersion(FILE_VERSION.to_string())),
+            ..Default::default()
+        };
+        self.write_event(&file_version)
+    }
+}
+
+impl Write for EventFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Keep events in order: any batched scalars precede whatever is being written now.
+        self.write_pending()?;
+        if self.file.is_none() {
+            self.open_next_file()?;
+        }
+        let file = self.file.as_mut().expect("event file opened above");
+        let n = file.write(buf)?;
+        self.bytes_written += n as u64;
+        Ok(n)
+    }
+
+    /// Flushes buffered events to disk. If the current file has grown past the maximum file size,
+    /// it is finished, and subsequent events will go to a new file.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        let file = match self.file.as_mut() {
+            None => return Ok(()),
+            Some(f) => f,
+        };
+        file.flush()?;
+        if matches!(self.max_file_size, Some(max) if self.bytes_written > max) {
+            self.file = None;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for EventFileWriter {
+    fn drop(&mut self) {
+        // Errors can't be reported from here; callers who care should flush explicitly.
+        let _ = self.write_pending();
+    }
+}
 
 /// Extends [`Write`] with methods for writing summary event files.
 pub trait SummaryWriteExt: Write {
@@ -39,31 +301,12 @@ pub trait SummaryWriteExt: Write {
         wt: WallTime,
         value: f32,
     ) -> std::io::Result<()> {
-        let event = pb::Event {
-            step: step.0,
-            wall_time: wt.into(),
-            what: Some(pb::event::What::Summary(pb::Summary {
-                value: vec![pb::summary::Value {
-                    tag: tag.0.clone(),
-                    value: Some(pb::summary::value::Value::SimpleValue(value)),
-                    ..Default::default()
-                }],
-                ..Default::default()
-            })),
-            ..Default::default()
-        };
-        self.write_event(&event)
+        self.write_event(&scalar_event(tag, step, wt, value))
     }
 
     /// Writes a TFRecord containing a TF 1.x `graph_def` event.
     fn write_graph(&mut self, step: Step, wt: WallTime, bytes: Vec<u8>) -> std::io::Result<()> {
-        let event = pb::Event {
-            step: step.0,
-            wall_time: wt.into(),
-            what: Some(pb::event::What::GraphDef(bytes)),
-            ..Default::default()
-        };
-        self.write_event(&event)
+        self.write_event(&graph_event(step, wt, bytes))
     }
 
     /// Writes a TFRecord containing a TF 1.x `tagged_run_metadata` event.
@@ -74,28 +317,117 @@ pub trait SummaryWriteExt: Write {
         wt: WallTime,
         run_metadata: Vec<u8>,
     ) -> std::io::Result<()> {
-        let event = pb::Event {
-            step: step.0,
-            wall_time: wt.into(),
-            what: Some(pb::event::What::TaggedRunMetadata(pb::TaggedRunMetadata {
+        self.write_event(&tagged_run_metadata_event(tag, step, wt, run_metadata))
+    }
+
+    /// Writes a TFRecord containing an hparams plugin experiment config.
+    fn write_hparams_config(
+        &mut self,
+        wt: WallTime,
+        config: &ExperimentConfig,
+    ) -> std::io::Result<()> {
+        let data = HParamsData::Experiment(config.to_proto());
+        self.write_event(&hparams_event(hparams::EXPERIMENT_TAG, wt, data))
+    }
+
+    /// Writes a TFRecord containing an hparams plugin session start summary.
+    fn write_hparams_session_start(
+        &mut self,
+        wt: WallTime,
+        start: &SessionStart,
+    ) -> std::io::Result<()> {
+        let data = HParamsData::SessionStartInfo(start.to_proto());
+        self.write_event(&hparams_event(hparams::SESSION_START_INFO_TAG, wt, data))
+    }
+
+    /// Writes a TFRecord containing an hparams plugin session end summary.
+    fn write_hparams_session_end(&mut self, wt: WallTime, end: &SessionEnd) -> std::io::Result<()> {
+        let data = HParamsData::SessionEndInfo(end.to_proto());
+        self.write_event(&hparams_event(hparams::SESSION_END_INFO_TAG, wt, data))
+    }
+}
+
+impl<W: Write> SummaryWriteExt for W {}
+
+/// Creates a TF 1.x scalar event (`simple_value`).
+pub fn scalar_event(tag: &Tag, step: Step, wt: WallTime, value: f32) -> pb::Event {
+    pb::Event {
+        step: step.0,
+        wall_time: wt.into(),
+        what: Some(pb::event::What::Summary(pb::Summary {
+            value: vec![pb::summary::Value {
                 tag: tag.0.clone(),
-                run_metadata,
+                value: Some(pb::summary::value::Value::SimpleValue(value)),
                 ..Default::default()
-            })),
+            }],
             ..Default::default()
-        };
-        self.write_event(&event)
+        })),
+        ..Default::default()
     }
 }
 
-impl<W: Write> SummaryWriteExt for W {}
+/// Creates a TF 1.x `graph_def` event.
+pub fn graph_event(step: Step, wt: WallTime, bytes: Vec<u8>) -> pb::Event {
+    pb::Event {
+        step: step.0,
+        wall_time: wt.into(),
+        what: Some(pb::event::What::GraphDef(bytes)),
+        ..Default::default()
+    }
+}
+
+/// Creates a TF 1.x `tagged_run_metadata` event.
+pub fn tagged_run_metadata_event(
+    tag: &Tag,
+    step: Step,
+    wt: WallTime,
+    run_metadata: Vec<u8>,
+) -> pb::Event {
+    pb::Event {
+        step: step.0,
+        wall_time: wt.into(),
+        what: Some(pb::event::What::TaggedRunMetadata(pb::TaggedRunMetadata {
+            tag: tag.0.clone(),
+            run_metadata,
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
+
+/// Creates an hparams plugin summary event at step 0. As with summaries written by TensorBoard's
+/// Python hparams API, all data is in the summary metadata, and the value is a placeholder scalar.
+fn hparams_event(tag: &str, wt: WallTime, data: HParamsData) -> pb::Event {
+    pb::Event {
+        step: 0,
+        wall_time: wt.into(),
+        what: Some(pb::event::What::Summary(pb::Summary {
+            value: vec![pb::summary::Value {
+                tag: tag.to_string(),
+                metadata: Some(hparams::summary_metadata(data)),
+                value: Some(pb::summary::value::Value::Tensor(pb::TensorProto {
+                    dtype: pb::DataType::DtFloat.into(),
+                    float_val: vec![0.0],
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::{Cursor, Read};
 
+    use crate::commit::Commit;
+    use crate::disk_logdir::DiskLogdir;
     use crate::event_file::{self, EventFileReader};
+    use crate::logdir::LogdirLoader;
+    use crate::types::Run;
 
     fn read_all_events<R: Read>(reader: R) -> Result<Vec<pb::Event>, event_file::ReadEventError> {
         let mut result = Vec::new();
@@ -207,4 +539,184 @@ mod tests {
         };
         assert_eq!(event, &expected);
     }
+
+    #[test]
+    fn test_event_file_writer_rotation() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let tag = Tag("loss".to_string());
+        let mut writer = EventFileWriter::new(logdir.path())?;
+        writer.max_file_size(200);
+        // Each batch of 10 scalars is well over 200 bytes, so each flush forces a rotation. The
+        // final rotation is deferred until the next write, so no empty fourth file is created.
+        for batch in 0..3 {
+            for i in 0..10 {
+                let step = batch * 10 + i;
+                let wall_time = WallTime::new(1234.0 + step as f64).unwrap();
+                writer.write_scalar(&tag, Step(step), wall_time, step as f32)?;
+            }
+            writer.flush()?;
+        }
+        drop(writer);
+
+        let mut filenames = std::fs::read_dir(logdir.path())?
+            .map(|entry| entry.map(|e| e.file_name()))
+            .collect::<io::Result<Vec<_>>>()?;
+        filenames.sort();
+        assert_eq!(filenames.len(), 3, "{:?}", filenames);
+        for filename in &filenames {
+            let file = File::open(logdir.path().join(filename))?;
+            let events = read_all_events(io::BufReader::new(file))?;
+            assert_eq!(
+                events[0].what,
+                Some(pb::event::What::FileVersion(FILE_VERSION.to_string()))
+            );
+            assert_eq!(events.len(), 11);
+        }
+
+        let commit = Commit::new();
+        let mut loader =
+            LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+        loader.reload();
+        let runs = commit.runs.read().unwrap();
+        let run_data = runs[&Run(".".to_string())].read().unwrap();
+        let steps = run_data.scalars[&tag]
+            .valid_values()
+            .map(|(step, _, _)| step)
+            .collect::<Vec<_>>();
+        assert_eq!(steps, (0..30).map(Step).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_file_writer_batch_scalars() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let write = |dir: &Path, batch: bool| -> io::Result<()> {
+            let mut writer = EventFileWriter::new(dir)?;
+            writer.batch_scalars(batch)?;
+            for step in 0..100 {
+                for i in 0..100 {
+                    let tag = Tag(format!("metric{:02}", i));
+                    let wall_time = WallTime::new(1234.0 + step as f64).unwrap();
+                    writer.write_scalar(&tag, Step(step), wall_time, (step * i) as f32)?;
+                }
+            }
+            writer.flush()
+        };
+        let unbatched_dir = logdir.path().join("unbatched");
+        let batched_dir = logdir.path().join("batched");
+        write(&unbatched_dir, false)?;
+        write(&batched_dir, true)?;
+
+        let dir_size = |dir: &Path| -> io::Result<u64> {
+            let mut total = 0;
+            for entry in std::fs::read_dir(dir)? {
+                total += entry?.metadata()?.len();
+            }
+            Ok(total)
+        };
+        let unbatched_size = dir_size(&unbatched_dir)?;
+        let batched_size = dir_size(&batched_dir)?;
+        assert!(
+            batched_size * 2 < unbatched_size,
+            "batched: {} bytes, unbatched: {} bytes",
+            batched_size,
+            unbatched_size
+        );
+
+        let commit = Commit::new();
+        let mut loader =
+            LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+        loader.reload();
+        let runs = commit.runs.read().unwrap();
+        let unbatched = runs[&Run("unbatched".to_string())].read().unwrap();
+        let batched = runs[&Run("batched".to_string())].read().unwrap();
+        assert_eq!(unbatched.scalars.len(), 100);
+        assert_eq!(batched.scalars.len(), 100);
+        for (tag, ts) in &unbatched.scalars {
+            let expected = ts.valid_values().collect::<Vec<_>>();
+            let actual = batched.scalars[tag].valid_values().collect::<Vec<_>>();
+            assert_eq!(expected.len(), 100);
+            assert_eq!(actual, expected, "tag {:?}", tag);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_file_writer_gzip() -> Result<(), Box<dyn std::error::Error>> {
+        let logdir = tempfile::tempdir()?;
+        let tag = Tag("loss".to_string());
+        let mut writer = EventFileWriter::with_compression(logdir.path(), Compression::Gzip)?;
+        for step in 0..10 {
+            let wall_time = WallTime::new(1234.0 + step as f64).unwrap();
+            writer.write_scalar(&tag, Step(step), wall_time, step as f32)?;
+        }
+        drop(writer);
+
+        let filenames = std::fs::read_dir(logdir.path())?
+            .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(filenames.len(), 1);
+        assert!(filenames[0].ends_with(".gz"), "{:?}", filenames);
+
+        let commit = Commit::new();
+        let mut loader =
+            LogdirLoader::new(&commit, DiskLogdir::new(logdir.path().to_path_buf()), 1);
+        loader.reload();
+        let runs = commit.runs.read().unwrap();
+        let run_data = runs[&Run(".".to_string())].read().unwrap();
+        let values = run_data.scalars[&tag]
+            .valid_values()
+            .map(|(step, _, value)| (step, value.0))
+            .collect::<Vec<_>>();
+        let expected = (0..10).map(|i| (Step(i), i as f64)).collect::<Vec<_>>();
+        assert_eq!(values, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_file_writer_gzip_unfinished() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::logdir::{EventFileBuf, Logdir};
+
+        let logdir = tempfile::tempdir()?;
+        let tag = Tag("loss".to_string());
+        let mut writer =
+            EventFileWriter::with_compression(logdir.path().join("live"), Compression::Gzip)?;
+        for step in 0..10 {
+            let wall_time = WallTime::new(1234.0 + step as f64).unwrap();
+            writer.write_scalar(&tag, Step(step), wall_time, step as f32)?;
+            if step == 4 {
+                writer.flush()?;
+            }
+        }
+
+        // Snapshot the file while the writer still holds unflushed data and the gzip stream has no
+        // trailer, as if the writing process had been killed.
+        let live_file = std::fs::read_dir(logdir.path().join("live"))?
+            .next()
+            .expect("event file")?
+            .path();
+        let snapshot_dir = logdir.path().join("snapshot");
+        std::fs::create_dir(&snapshot_dir)?;
+        let snapshot_name = "events.out.tfevents.123.gz";
+        std::fs::copy(&live_file, snapshot_dir.join(snapshot_name))?;
+        drop(writer);
+
+        let disk_logdir = DiskLogdir::new(snapshot_dir);
+        let file = disk_logdir.open(&EventFileBuf(snapshot_name.into()))?;
+        let mut reader = EventFileReader::new(file);
+        let mut steps = Vec::new();
+        loop {
+            match reader.read_event() {
+                Ok(event) => {
+                    if let Some(pb::event::What::Summary(_)) = event.what {
+                        steps.push(event.step);
+                    }
+                }
+                Err(e) if e.truncated() => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        assert_eq!(steps, vec![0, 1, 2, 3, 4]);
+        Ok(())
+    }
 }